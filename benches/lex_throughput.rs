@@ -0,0 +1,49 @@
+//! Lexer throughput benchmark: `cargo bench --bench lex_throughput`.
+//!
+//! Lexes a synthetic translation unit of a few megabytes repeatedly and
+//! reports the best observed throughput in MB/s.
+
+use std::time::Instant;
+
+use ruscom::lexer::token::Token;
+use ruscom::lexer::Lexer;
+
+const UNIT: &str = r#"
+// Accumulates the values of a widget list.
+/* Block comments are scanned with memchr
+   and can span several lines. */
+template<typename T>
+static long accumulate_values(const T* items, int count, long seed) {
+    long total = seed + 42;
+    for (int i = 0; i < count; ++i) {
+        total += items[i].value * 3 - (total >> 2);
+        if (total != 0 && items[i].name == "widget \"quoted\"\n") { total -= 'x'; }
+    }
+    return total;
+}
+"#;
+
+fn lex_all(src: &str) -> usize {
+    let mut n = 0;
+    for t in Lexer::new(src) {
+        match t {
+            Ok(Token::Eof) => break,
+            Ok(_) => n += 1,
+            Err(e) => panic!("lex error: {}", e),
+        }
+    }
+    n
+}
+
+fn main() {
+    let src = UNIT.repeat(8 * 1024 * 1024 / UNIT.len());
+    let mb = src.len() as f64 / (1024.0 * 1024.0);
+    let tokens = lex_all(&src);
+    let mut best = f64::INFINITY;
+    for _ in 0..5 {
+        let start = Instant::now();
+        std::hint::black_box(lex_all(std::hint::black_box(&src)));
+        best = best.min(start.elapsed().as_secs_f64());
+    }
+    println!("lexed {:.1} MB ({} tokens) in {:.3}s: {:.1} MB/s", mb, tokens, best, mb / best);
+}
@@ -0,0 +1,71 @@
+//! Parser throughput benchmark: `cargo bench --bench parse_throughput`.
+//!
+//! Parses a synthetic translation unit of a few megabytes repeatedly,
+//! from tokens preprocessed once up front, into an arena that is reset
+//! between runs the way a batch compile reuses it between units. Reports
+//! the best observed throughput in MB/s and the arena's footprint.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use ruscom::arena::TuArena;
+use ruscom::lexer::token::SpannedToken;
+use ruscom::parser::Parser;
+use ruscom::preprocessor::Preprocessor;
+use ruscom::session::{Options, Session};
+use ruscom::vfs::MemoryFileSystem;
+
+const UNIT: &str = r#"
+namespace geo {
+struct Point {
+    double x = 0, y = 0;
+    Point(double x, double y) : x(x), y(y) {}
+    double dot(const Point& other) const { return x * other.x + y * other.y; }
+};
+}
+
+template<typename T>
+T clamp(T value, T low, T high) { return value < low ? low : value > high ? high : value; }
+
+static long accumulate_values(const int* items, int count, long seed) {
+    long total = seed + 42;
+    for (int i = 0; i < count; ++i) {
+        total += items[i] * 3 - (total >> 2);
+        if (total != 0 && (items[i] & 1) == 0) { total -= 'x'; } else { total ^= i << 3; }
+    }
+    switch (total % 4) { case 0: return total; default: break; }
+    return clamp<long>(total, -1000, 1000);
+}
+"#;
+
+fn parse_all(session: &mut Session, arena: &TuArena, tokens: Vec<SpannedToken>) -> usize {
+    let tu = Parser::new(session, arena, tokens).parse().expect("not cancelled");
+    assert!(session.diagnostics.is_empty(), "the benchmark source has syntax errors");
+    tu.decls.len()
+}
+
+fn main() {
+    let src = UNIT.repeat(4 * 1024 * 1024 / UNIT.len());
+    let mb = src.len() as f64 / (1024.0 * 1024.0);
+    let mut fs = MemoryFileSystem::new();
+    fs.insert("main.cpp", src);
+    let mut session = Session::new(Options::default()).with_file_system(Arc::new(fs));
+    let main = session.sources.load(session.fs.clone().as_ref(), "main.cpp".as_ref()).unwrap();
+    let tokens = Preprocessor::new(&mut session).run(main).unwrap().tokens;
+    let mut arena = TuArena::new();
+    let decls = parse_all(&mut session, &arena, tokens.clone());
+    let stats = arena.stats();
+    let mut best = f64::INFINITY;
+    for _ in 0..5 {
+        arena.reset();
+        let tokens = tokens.clone();
+        let start = Instant::now();
+        std::hint::black_box(parse_all(&mut session, &arena, std::hint::black_box(tokens)));
+        best = best.min(start.elapsed().as_secs_f64());
+    }
+    println!(
+        "parsed {:.1} MB ({} tokens, {} declarations) in {:.3}s: {:.1} MB/s; arena {:.1} MB used of {:.1} MB",
+        mb, tokens.len(), decls, best, mb / best,
+        stats.allocated_bytes as f64 / (1024.0 * 1024.0), stats.reserved_bytes as f64 / (1024.0 * 1024.0),
+    );
+}
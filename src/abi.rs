@@ -0,0 +1,491 @@
+//! Binary compatibility between two versions of a library, as `ruscom
+//! abidiff` reports it from the headers (or sources) each version was
+//! built from.
+//!
+//! A version's interface is what programs linked against it depend on:
+//! the functions and variables it exports, by mangled name, with the
+//! return or variable type the name does not encode; the layout of each
+//! class; and the order of each class's virtual functions in its vtable.
+//! Removing a symbol, changing a type its symbol does not encode, moving
+//! a member or growing a class, and adding, removing or reordering
+//! virtual functions break programs built against the old version; adding
+//! a symbol or a class does not.
+//!
+//! A vtable lists the primary base's entries (the first polymorphic base)
+//! with overriders in their slots, then the class's other virtual
+//! functions in declaration order, a virtual destructor taking two slots.
+//! Secondary vtables are not compared. Classes are laid out by
+//! `sema::layout`, which knows a class by its unqualified name.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use crate::ast::{ClassDecl, Decl, FunctionDecl, FunctionKind, StorageClass, TranslationUnit, VarDecl};
+use crate::intern::Symbol;
+use crate::mangle;
+use crate::sema::layout::{Layout, Layouts, Placed, Slot};
+use crate::session::Target;
+use crate::symbols::Visibility;
+use crate::types::{Qualifiers, TemplateArgument, Type, TypeId};
+
+/// How deeply typedefs and bases may refer to one another before one is
+/// taken to refer to itself.
+const MAX_DEPTH: usize = 64;
+
+/// A function or variable a library exports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Exported {
+    /// `function` or `variable`, as messages say.
+    what: &'static str,
+    /// As messages show it, like `geo::area(double)`.
+    signature: String,
+    /// A function's return type or a variable's type, which its mangled
+    /// name does not encode.
+    ty: String,
+}
+
+/// A class definition, as programs built against it see it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Record {
+    /// `None` for a class `sema::layout` cannot lay out.
+    layout: Option<Layout>,
+    /// The signatures of its primary vtable's virtual functions, by slot.
+    vtable: Vec<String>,
+}
+
+/// What a library built from a translation unit exposes to programs
+/// linked against it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Interface {
+    /// By mangled name, or by signature for what cannot be mangled.
+    symbols: BTreeMap<String, Exported>,
+    /// By qualified name.
+    records: BTreeMap<String, Record>,
+}
+
+/// One difference between two interfaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// Whether programs built against the old interface may misbehave, or
+    /// fail to load, against the new one.
+    pub breaking: bool,
+    pub message: String,
+}
+
+impl Change {
+    fn breaking(message: String) -> Self { Self { breaking: true, message } }
+
+    fn compatible(message: String) -> Self { Self { breaking: false, message } }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}: {}", if self.breaking { "breaking" } else { "compatible" }, self.message) }
+}
+
+/// A member function, as vtables match overriders to what they override.
+#[derive(Debug, Clone)]
+struct Method {
+    /// Like `area() const`; `destructor` for any destructor.
+    signature: String,
+    /// Declared `virtual`, `override`, `final` or pure.
+    is_virtual: bool,
+}
+
+/// A class definition's bases and member functions.
+#[derive(Debug, Clone)]
+struct Class {
+    /// The scope its bases are looked up in.
+    scope: String,
+    bases: Vec<(Symbol, bool)>,
+    methods: Vec<Method>,
+}
+
+/// What a name found by lookup names.
+enum Named {
+    /// A class or enum, or a class template, by its qualified name.
+    Type(String),
+    /// A typedef, with the scope its type is looked up in.
+    Alias(TypeId, String),
+}
+
+impl Interface {
+    /// The interface of a library built from `unit` for `target`, whose
+    /// symbols have visibility `default` unless an attribute says
+    /// otherwise.
+    pub fn of(unit: &TranslationUnit, target: &Target, default: Visibility) -> Self {
+        let names = Names::of(unit);
+        let mut collector = Collector { names: &names, layouts: Layouts::of_unit(unit, target), interface: Interface::default() };
+        collector.decls(&unit.decls, "", default);
+        collector.interface
+    }
+
+    /// What changed from `self`, the old interface, to `new`: removals
+    /// and changes first, then additions.
+    pub fn diff(&self, new: &Interface) -> Vec<Change> {
+        let mut changes = Vec::new();
+        for (key, old) in &self.symbols {
+            let symbol = if *key == old.signature { String::new() } else { format!(" (`{}`)", key) };
+            match new.symbols.get(key) {
+                None => changes.push(Change::breaking(format!("{} `{}`{} was removed", old.what, old.signature, symbol))),
+                Some(now) if now.ty != old.ty && old.what == "function" => {
+                    changes.push(Change::breaking(format!("function `{}`{} now returns `{}` instead of `{}`", old.signature, symbol, now.ty, old.ty)));
+                }
+                Some(now) if now.ty != old.ty => changes.push(Change::breaking(format!("variable `{}`{} changed type from `{}` to `{}`", old.signature, symbol, old.ty, now.ty))),
+                Some(_) => {}
+            }
+        }
+        for (name, old) in &self.records {
+            match new.records.get(name) {
+                None => changes.push(Change::breaking(format!("class `{}` was removed", name))),
+                Some(now) => {
+                    if let (Some(old), Some(now)) = (&old.layout, &now.layout) { layout_changes(name, old, now, &mut changes); }
+                    vtable_changes(name, &old.vtable, &now.vtable, &mut changes);
+                }
+            }
+        }
+        for (key, now) in &new.symbols {
+            if self.symbols.contains_key(key) { continue; }
+            let symbol = if *key == now.signature { String::new() } else { format!(" (`{}`)", key) };
+            changes.push(Change::compatible(format!("{} `{}`{} was added", now.what, now.signature, symbol)));
+        }
+        for name in new.records.keys().filter(|name| !self.records.contains_key(*name)) {
+            changes.push(Change::compatible(format!("class `{}` was added", name)));
+        }
+        changes
+    }
+}
+
+/// What a class's size, alignment, bases and members moving does to
+/// programs that allocate it or reach into it.
+fn layout_changes(name: &str, old: &Layout, new: &Layout, changes: &mut Vec<Change>) {
+    if old.size != new.size { changes.push(Change::breaking(format!("class `{}` changed size from {} to {} bytes", name, old.size, new.size))); }
+    if old.align != new.align { changes.push(Change::breaking(format!("class `{}` changed alignment from {} to {}", name, old.align, new.align))); }
+    let describe = |slot: &Slot| match slot {
+        Slot::VtablePointer => "its vtable pointer".to_string(),
+        Slot::Base(base) => format!("base `{}`", base),
+        Slot::Field(field, _) => format!("field `{}`", field),
+    };
+    let at = |p: &Placed| match p.bit_range() {
+        Some(range) => format!("offset {} {}", p.offset, range),
+        None => format!("offset {}", p.offset),
+    };
+    let same = |a: &Slot, b: &Slot| match (a, b) {
+        (Slot::Field(a, _), Slot::Field(b, _)) => a == b,
+        _ => a == b,
+    };
+    for placed in &old.placed {
+        match new.placed.iter().find(|p| same(&p.slot, &placed.slot)) {
+            None => changes.push(Change::breaking(format!("class `{}` no longer has {}", name, describe(&placed.slot)))),
+            Some(now) => {
+                if now.offset != placed.offset && placed.bits.is_none() && now.bits.is_none() {
+                    changes.push(Change::breaking(format!("{} of `{}` moved from offset {} to {}", describe(&placed.slot), name, placed.offset, now.offset)));
+                } else if now.offset != placed.offset || now.bits != placed.bits {
+                    changes.push(Change::breaking(format!("{} of `{}` moved from {} to {}", describe(&placed.slot), name, at(placed), at(now))));
+                }
+                if let (Slot::Field(field, old_ty), Slot::Field(_, new_ty)) = (placed.slot, now.slot) {
+                    if old_ty != new_ty { changes.push(Change::breaking(format!("field `{}` of `{}` changed type from `{}` to `{}`", field, name, old_ty, new_ty))); }
+                }
+            }
+        }
+    }
+    for placed in new.placed.iter().filter(|p| !old.placed.iter().any(|o| same(&o.slot, &p.slot))) {
+        changes.push(Change::breaking(format!("class `{}` has a new {} at offset {}", name, describe(&placed.slot).trim_start_matches("its "), placed.offset)));
+    }
+}
+
+/// What reordering a vtable does to calls compiled against the old order:
+/// each slot that now holds a different function calls the wrong one.
+fn vtable_changes(name: &str, old: &[String], new: &[String], changes: &mut Vec<Change>) {
+    for (slot, signature) in old.iter().enumerate() {
+        match new.iter().position(|s| s == signature) {
+            None => changes.push(Change::breaking(format!("virtual function `{}` was removed from the vtable of `{}`", signature, name))),
+            Some(now) if now != slot => changes.push(Change::breaking(format!("virtual function `{}` moved from slot {} to slot {} of the vtable of `{}`", signature, slot, now, name))),
+            Some(_) => {}
+        }
+    }
+    for (slot, signature) in new.iter().enumerate().filter(|(_, s)| !old.contains(s)) {
+        changes.push(Change::breaking(format!("virtual function `{}` was added to the vtable of `{}` at slot {}", signature, name, slot)));
+    }
+}
+
+/// The classes, enums, class templates and typedefs a unit declares, by
+/// qualified name, for resolving the names signatures use.
+#[derive(Default)]
+pub(crate) struct Names {
+    types: HashSet<String>,
+    aliases: HashMap<String, (TypeId, String)>,
+    classes: HashMap<String, Class>,
+}
+
+impl Names {
+    pub(crate) fn of(unit: &TranslationUnit) -> Self {
+        let mut names = Names::default();
+        names.declare(&unit.decls.iter().collect::<Vec<_>>(), "");
+        names
+    }
+
+    fn declare(&mut self, decls: &[&Decl], scope: &str) {
+        for decl in decls {
+            match decl {
+                Decl::Namespace(n) => {
+                    let inner = n.name.map_or_else(|| scope.to_string(), |name| qualify(scope, &name.as_str()));
+                    self.declare(&n.decls.iter().collect::<Vec<_>>(), &inner);
+                }
+                Decl::Export(e) => self.declare(&e.decls.iter().collect::<Vec<_>>(), scope),
+                Decl::Class(c) => self.class(c, scope),
+                Decl::Enum(e) => {
+                    if let Some(name) = e.name { self.types.insert(qualify(scope, &name.as_str())); }
+                }
+                Decl::Typedef(t) => {
+                    self.aliases.insert(qualify(scope, &t.name.as_str()), (t.ty, scope.to_string()));
+                }
+                Decl::Template(t) => {
+                    if let Decl::Class(ClassDecl { name: Some(name), .. }) = &*t.decl { self.types.insert(qualify(scope, &name.as_str())); }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn class(&mut self, c: &ClassDecl, scope: &str) {
+        let Some(name) = c.name else { return };
+        let qualified = qualify(scope, &name.as_str());
+        self.types.insert(qualified.clone());
+        let Some(members) = &c.members else { return };
+        self.declare(&members.iter().map(|m| &m.decl).collect::<Vec<_>>(), &qualified);
+        let mut methods = Vec::new();
+        for member in members {
+            let Decl::Function(f) = &member.decl else { continue };
+            if f.kind == FunctionKind::Constructor || f.specifiers.storage == StorageClass::Static || f.specifiers.is_friend { continue; }
+            let q = &f.qualifiers;
+            let is_virtual = f.specifiers.is_virtual || q.is_override || q.is_final || q.is_pure;
+            let signature = if f.kind == FunctionKind::Destructor { "destructor".to_string() } else { self.signature(last(&f.name.as_str()), f.ty, q.quals, &qualified) };
+            methods.push(Method { signature, is_virtual });
+        }
+        self.classes.insert(qualified.clone(), Class { scope: scope.to_string(), bases: c.bases.iter().map(|b| (b.name, b.is_virtual)).collect(), methods });
+    }
+
+    /// What `name`, used in `scope`, names: the innermost class, enum or
+    /// typedef of that name in `scope` or a scope enclosing it.
+    fn lookup(&self, name: &str, scope: &str) -> Option<Named> {
+        let (name, mut scope) = match name.strip_prefix("::") {
+            Some(global) => (global, ""),
+            None => (name, scope),
+        };
+        loop {
+            let qualified = qualify(scope, name);
+            if self.types.contains(&qualified) { return Some(Named::Type(qualified)); }
+            if let Some((ty, at)) = self.aliases.get(&qualified) { return Some(Named::Alias(*ty, at.clone())); }
+            if scope.is_empty() { return None; }
+            scope = scope.rsplit_once("::").map_or("", |(outer, _)| outer);
+        }
+    }
+
+    /// Whether the class `name`, qualified, is defined.
+    pub(crate) fn is_class(&self, name: &str) -> bool { self.classes.contains_key(name) }
+
+    /// The qualified name of the class or enum `name` names in `scope`, or
+    /// `name` itself if it names neither.
+    pub(crate) fn qualified(&self, name: &str, scope: &str) -> String {
+        match self.lookup(name, scope) {
+            Some(Named::Type(qualified)) => qualified,
+            _ => name.to_string(),
+        }
+    }
+
+    /// Why the class `c`, named `qualified`, is not laid out and copied as
+    /// a C struct would be whatever its data members' types, if it is not:
+    /// it has bases or virtual functions, declares a destructor or copy
+    /// operations, or has data members of different access.
+    pub(crate) fn not_c_struct(&self, c: &ClassDecl, qualified: &str) -> Option<&'static str> {
+        if !c.bases.is_empty() { return Some("it has base classes"); }
+        let this = TypeId::intern(Type::Named(Symbol::intern(qualified)));
+        let mut access = None;
+        for member in c.members.as_deref().unwrap_or_default() {
+            match &member.decl {
+                Decl::Var(v) if v.specifiers.storage != StorageClass::Static => {
+                    if access.is_some_and(|a| a != member.access) { return Some("its data members have different access"); }
+                    access = Some(member.access);
+                }
+                Decl::Function(f) => {
+                    let q = &f.qualifiers;
+                    if f.specifiers.is_virtual || q.is_override || q.is_final || q.is_pure { return Some("it has virtual functions"); }
+                    if q.is_defaulted || f.specifiers.is_friend { continue; }
+                    if f.kind == FunctionKind::Destructor { return Some("it declares a destructor"); }
+                    let copies = |ty: &TypeId| matches!(self.resolve(*ty, qualified).get(), Type::Reference(t) | Type::RvalueReference(t) if t.unqualified() == this);
+                    let Type::Function { params, .. } = f.ty.get() else { continue };
+                    if (f.kind == FunctionKind::Constructor || &*f.name.as_str() == "operator=") && params.len() == 1 && params.iter().all(copies) {
+                        return Some("it declares a copy or move operation");
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// `ty`, used in `scope`, with its typedefs replaced by what they name
+    /// and its classes and enums named by their qualified names.
+    pub(crate) fn resolve(&self, ty: TypeId, scope: &str) -> TypeId { self.resolve_at(ty, scope, 0) }
+
+    fn resolve_at(&self, ty: TypeId, scope: &str, depth: usize) -> TypeId {
+        if depth > MAX_DEPTH { return ty; }
+        let resolve = |inner: TypeId| self.resolve_at(inner, scope, depth + 1);
+        match ty.get() {
+            Type::Named(name) => match self.lookup(&name.as_str(), scope) {
+                Some(Named::Type(qualified)) => TypeId::intern(Type::Named(Symbol::intern(&qualified))),
+                Some(Named::Alias(aliased, at)) => self.resolve_at(aliased, &at, depth + 1),
+                None => ty,
+            },
+            Type::Specialization(name, args) => {
+                let name = match self.lookup(&name.as_str(), scope) {
+                    Some(Named::Type(qualified)) => Symbol::intern(&qualified),
+                    _ => name,
+                };
+                let args = args
+                    .into_iter()
+                    .map(|arg| match arg {
+                        TemplateArgument::Type(ty) => TemplateArgument::Type(resolve(ty)),
+                        value => value,
+                    })
+                    .collect();
+                TypeId::intern(Type::Specialization(name, args))
+            }
+            Type::Pointer(inner) => TypeId::intern(Type::Pointer(resolve(inner))),
+            Type::Reference(inner) => TypeId::intern(Type::Reference(resolve(inner))),
+            Type::RvalueReference(inner) => TypeId::intern(Type::RvalueReference(resolve(inner))),
+            Type::Array(element, bound) => TypeId::intern(Type::Array(resolve(element), bound)),
+            Type::Function { ret, params, variadic } => TypeId::intern(Type::Function { ret: resolve(ret), params: params.into_iter().map(resolve).collect(), variadic }),
+            Type::Qualified(inner, quals) => resolve(inner).qualified(quals),
+            _ => ty,
+        }
+    }
+
+    /// `name(params) quals`, with the parameter types of `ty` resolved in
+    /// `scope`.
+    fn signature(&self, name: &str, ty: TypeId, quals: Qualifiers, scope: &str) -> String {
+        let Type::Function { params, variadic, .. } = self.resolve(ty, scope).get() else { return name.to_string() };
+        let mut params: Vec<String> = params.iter().map(|p| p.to_string()).collect();
+        if variadic { params.push("...".to_string()); }
+        let quals = if quals.is_empty() { String::new() } else { format!(" {}", quals) };
+        format!("{}({}){}", name, params.join(", "), quals)
+    }
+
+    /// The primary vtable of the class `name`, with a destructor's
+    /// complete and deleting entries.
+    pub(crate) fn vtable(&self, name: &str, depth: usize) -> Vec<String> {
+        let Some(class) = self.classes.get(name).filter(|_| depth <= MAX_DEPTH) else { return Vec::new() };
+        let bases: Vec<Vec<String>> = class
+            .bases
+            .iter()
+            .filter(|(_, is_virtual)| !is_virtual)
+            .filter_map(|(base, _)| match self.lookup(&base.as_str(), &class.scope) {
+                Some(Named::Type(qualified)) => Some(self.vtable(&qualified, depth + 1)),
+                _ => None,
+            })
+            .collect();
+        let mut entries = bases.iter().find(|b| !b.is_empty()).cloned().unwrap_or_default();
+        for method in &class.methods {
+            let overrides = bases.iter().flatten().any(|s| *s == method.signature || (method.signature == "destructor" && s.ends_with("destructor")));
+            if !method.is_virtual && !overrides { continue; }
+            if method.signature == "destructor" {
+                if !entries.iter().any(|s| s.ends_with("destructor")) { entries.extend(["complete destructor".to_string(), "deleting destructor".to_string()]); }
+            } else if !entries.contains(&method.signature) {
+                entries.push(method.signature.clone());
+            }
+        }
+        entries
+    }
+}
+
+struct Collector<'n> {
+    names: &'n Names,
+    layouts: Layouts,
+    interface: Interface,
+}
+
+impl Collector<'_> {
+    fn decls(&mut self, decls: &[Decl], scope: &str, visibility: Visibility) {
+        for decl in decls {
+            match decl {
+                // What an unnamed namespace declares has internal linkage.
+                Decl::Namespace(n) => {
+                    if let Some(name) = n.name { self.decls(&n.decls, &qualify(scope, &name.as_str()), Visibility::from_attributes(&n.attrs).unwrap_or(visibility)); }
+                }
+                Decl::Export(e) => self.decls(&e.decls, scope, visibility),
+                Decl::Class(c) => self.class(c, scope, visibility),
+                Decl::Function(f) => {
+                    let name = qualify(scope, &f.name.as_str());
+                    // A member defined outside its class was exported, or
+                    // not, with the class.
+                    if name.rsplit_once("::").is_some_and(|(owner, _)| self.names.classes.contains_key(owner)) { continue; }
+                    self.function(f, &name, None, visibility);
+                }
+                Decl::Var(v) => {
+                    let internal = v.specifiers.storage != StorageClass::Extern && v.ty.qualifiers().is_const;
+                    if !internal { self.variable(v, &qualify(scope, &v.name.as_str()), visibility); }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn class(&mut self, c: &ClassDecl, scope: &str, visibility: Visibility) {
+        let (Some(name), Some(members)) = (c.name, &c.members) else { return };
+        let qualified = qualify(scope, &name.as_str());
+        let visibility = Visibility::from_attributes(&c.attrs).unwrap_or(visibility);
+        let layout = self.layouts.layout(TypeId::intern(Type::Named(Symbol::intern(&qualified)))).ok();
+        self.interface.records.insert(qualified.clone(), Record { layout, vtable: self.names.vtable(&qualified, 0) });
+        for member in members {
+            match &member.decl {
+                Decl::Class(nested) => self.class(nested, &qualified, visibility),
+                // Those defined in the class are inline.
+                Decl::Function(f) if f.body.is_none() && !f.qualifiers.is_pure && !f.specifiers.is_friend => {
+                    let this = (f.specifiers.storage != StorageClass::Static).then_some(f.qualifiers.quals);
+                    self.function(f, &qualify(&qualified, &f.name.as_str()), this, visibility);
+                }
+                Decl::Var(v) if v.specifiers.storage == StorageClass::Static => self.variable(v, &qualify(&qualified, &v.name.as_str()), visibility),
+                _ => {}
+            }
+        }
+    }
+
+    fn function(&mut self, f: &FunctionDecl, name: &str, this: Option<Qualifiers>, visibility: Visibility) {
+        let q = &f.qualifiers;
+        let inline = f.specifiers.is_inline || f.specifiers.is_constexpr || q.is_deleted || q.is_defaulted;
+        let scope = scope_of(name);
+        // A `static` member has external linkage; any other `static`
+        // function does not.
+        if inline || (f.specifiers.storage == StorageClass::Static && !self.names.classes.contains_key(scope)) { return; }
+        if !Visibility::from_attributes(&f.attrs).unwrap_or(visibility).is_exported() { return; }
+        let ty = self.names.resolve(f.ty, scope);
+        let Type::Function { ret, .. } = ty.get() else { return };
+        let signature = format!("{}{}", scope_prefix(scope), self.names.signature(last(name), f.ty, this.unwrap_or_default(), scope));
+        let mangled = if f.specifiers.is_extern_c { Some(mangle::extern_c(name)) } else { mangle::function(name, f.kind, ty, this) };
+        let key = mangled.unwrap_or_else(|| signature.clone());
+        self.interface.symbols.entry(key).or_insert(Exported { what: "function", signature, ty: ret.to_string() });
+    }
+
+    fn variable(&mut self, v: &VarDecl, name: &str, visibility: Visibility) {
+        let s = &v.specifiers;
+        if s.is_constexpr || s.is_inline || (s.storage == StorageClass::Static && !self.names.classes.contains_key(scope_of(name))) { return; }
+        if !Visibility::from_attributes(&v.attrs).unwrap_or(visibility).is_exported() { return; }
+        let mangled = if s.is_extern_c { Some(mangle::extern_c(name)) } else { mangle::variable(name) };
+        let key = mangled.unwrap_or_else(|| name.to_string());
+        let ty = self.names.resolve(v.ty, scope_of(name));
+        self.interface.symbols.entry(key).or_insert(Exported { what: "variable", signature: name.to_string(), ty: ty.to_string() });
+    }
+}
+
+pub(crate) fn qualify(scope: &str, name: &str) -> String {
+    if scope.is_empty() { name.to_string() } else { format!("{}::{}", scope, name) }
+}
+
+/// The scope a qualified name is declared in, empty for the global one.
+pub(crate) fn scope_of(name: &str) -> &str { name.rsplit_once("::").map_or("", |(scope, _)| scope) }
+
+fn scope_prefix(scope: &str) -> String {
+    if scope.is_empty() { String::new() } else { format!("{}::", scope) }
+}
+
+fn last(name: &str) -> &str { name.rsplit_once("::").map_or(name, |(_, last)| last) }
@@ -0,0 +1,369 @@
+//! A description of the API a header declares, as `ruscom interface`
+//! prints it: its functions, classes, enums, typedefs, constants and
+//! variables, for binding generators and other tools that would rather
+//! not parse C++.
+//!
+//! Only what the file itself declares is described, not what it includes,
+//! and only what has external linkage: nothing in an unnamed namespace,
+//! `static` functions or templates. Types are written with typedefs
+//! resolved and classes and enums qualified, like `const geo::Point&`, so
+//! a reader needs no scopes of its own. Classes come with their layout
+//! for the target, each field's offset and size, and their primary vtable;
+//! enums with their underlying type and enumerator values; constants with
+//! their values. Functions carry their Itanium mangled names when they
+//! have one, and their own names when declared `extern "C"`.
+//!
+//! A function or variable declared more than once is described once, as
+//! its definition if the file has one.
+//!
+//! The JSON form is versioned by its `version` member, which changes only
+//! when a member is removed or changes meaning.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use serde_json::{json, Value};
+
+use crate::abi::{qualify, scope_of, Names};
+use crate::ast::{ClassDecl, Decl, EnumDecl, FunctionDecl, FunctionKind, StorageClass, TranslationUnit, VarDecl};
+use crate::diagnostics::Diagnostics;
+use crate::intern::Symbol;
+use crate::lexer::token::Span;
+use crate::mangle;
+use crate::sema::constant::{Constant, Constants, Value as Number};
+use crate::sema::enums;
+use crate::sema::layout::{Layouts, Slot};
+use crate::session::Target;
+use crate::source::{FileId, SourceManager};
+use crate::types::{Qualifiers, Type, TypeId};
+
+/// The version of the JSON description.
+pub const VERSION: u32 = 1;
+
+/// How `ruscom interface` prints a description.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// One line per entity, members indented under their class or enum.
+    #[default]
+    Text,
+    /// One JSON object.
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            other => Err(format!("unknown interface format `{}` (expected text or json)", other)),
+        }
+    }
+}
+
+/// The description of what `unit`, parsed from `file`, declares there, for
+/// `target`.
+pub fn describe(unit: &TranslationUnit, file: FileId, sources: &SourceManager, target: &Target) -> Value {
+    let mut describer = Describer {
+        names: Names::of(unit),
+        layouts: Layouts::of_unit(unit, target),
+        constants: Constants::new(target),
+        file,
+        sources,
+        functions: Vec::new(),
+        records: Vec::new(),
+        enums: Vec::new(),
+        typedefs: Vec::new(),
+        constant_values: Vec::new(),
+        variables: Vec::new(),
+        declared: HashMap::new(),
+    };
+    describer.decls(&unit.decls, "", true);
+    json!({
+        "version": VERSION,
+        "file": sources.file(file).path.display().to_string(),
+        "target": target.triple(),
+        "functions": describer.functions,
+        "records": describer.records,
+        "enums": describer.enums,
+        "typedefs": describer.typedefs,
+        "constants": describer.constant_values,
+        "variables": describer.variables,
+    })
+}
+
+/// `description` as text: one line per entity, with a class's bases,
+/// fields, methods and vtable and an enum's enumerators indented under it.
+pub fn render(description: &Value) -> String {
+    let text = |v: &Value| v.as_str().unwrap_or_default().to_string();
+    let items = |v: &Value, key: &str| v[key].as_array().cloned().unwrap_or_default();
+    let symbol = |v: &Value| v["mangled"].as_str().map_or_else(String::new, |m| format!(" [{}]", m));
+    let signature = |f: &Value| {
+        let mut params: Vec<String> = items(f, "parameters").iter().map(|p| text(&p["type"])).collect();
+        if f["variadic"] == true { params.push("...".to_string()); }
+        format!("{}({})", text(&f["name"]), params.join(", "))
+    };
+    let mut out = String::new();
+    let _ = writeln!(out, "{} ({})", text(&description["file"]), text(&description["target"]));
+    for f in items(description, "functions") {
+        let _ = writeln!(out, "function {} -> {}{}", signature(&f), text(&f["returns"]), symbol(&f));
+    }
+    for r in items(description, "records") {
+        let layout = if r["size"].is_null() { String::new() } else { format!(": size {}, align {}", r["size"], r["align"]) };
+        let _ = writeln!(out, "{} {}{}", text(&r["kind"]), text(&r["name"]), layout);
+        for b in items(&r, "bases") {
+            let _ = writeln!(out, "  base {}{}", text(&b["name"]), if b["offset"].is_null() { String::new() } else { format!(" at {}", b["offset"]) });
+        }
+        for f in items(&r, "fields") {
+            let _ = writeln!(out, "  field {}: {}{}", text(&f["name"]), text(&f["type"]), if f["offset"].is_null() { String::new() } else { format!(" at {}", f["offset"]) });
+        }
+        for m in items(&r, "methods") {
+            let quals = if m["const"] == true { " const" } else { "" };
+            let returns = if m["kind"] == "constructor" || m["kind"] == "destructor" { String::new() } else { format!(" -> {}", text(&m["returns"])) };
+            let _ = writeln!(out, "  {} {}{}{}{}", text(&m["kind"]), signature(&m), quals, returns, symbol(&m));
+        }
+        let vtable: Vec<String> = items(&r, "vtable").iter().map(text).collect();
+        if !vtable.is_empty() { let _ = writeln!(out, "  vtable {}", vtable.join(", ")); }
+    }
+    for e in items(description, "enums") {
+        let _ = writeln!(out, "enum {}{}: {}", if e["scoped"] == true { "class " } else { "" }, text(&e["name"]), text(&e["underlying"]));
+        for n in items(&e, "enumerators") { let _ = writeln!(out, "  {} = {}", text(&n["name"]), n["value"]); }
+    }
+    for t in items(description, "typedefs") { let _ = writeln!(out, "typedef {} = {}", text(&t["name"]), text(&t["type"])); }
+    for c in items(description, "constants") { let _ = writeln!(out, "constant {}: {} = {}", text(&c["name"]), text(&c["type"]), c["value"]); }
+    for v in items(description, "variables") { let _ = writeln!(out, "variable {}: {}{}", text(&v["name"]), text(&v["type"]), symbol(&v)); }
+    out
+}
+
+struct Describer<'u, 's> {
+    names: Names,
+    layouts: Layouts,
+    /// Every constant declared so far, in the file or what it includes.
+    constants: Constants<'u>,
+    file: FileId,
+    sources: &'s SourceManager,
+    functions: Vec<Value>,
+    records: Vec<Value>,
+    enums: Vec<Value>,
+    typedefs: Vec<Value>,
+    constant_values: Vec<Value>,
+    variables: Vec<Value>,
+    /// Where each function and variable described is in its list, by its
+    /// qualified name and, for functions, mangled name or type, and
+    /// whether that is its definition.
+    declared: HashMap<String, (usize, bool)>,
+}
+
+/// Adds `entry` to `entries` unless a declaration with the same `key` is
+/// there; a definition replaces a declaration.
+fn merge(entries: &mut Vec<Value>, declared: &mut HashMap<String, (usize, bool)>, key: String, entry: Value, is_definition: bool) {
+    match declared.get_mut(&key) {
+        Some((at, defined)) => {
+            if is_definition && !*defined {
+                entries[*at] = entry;
+                *defined = true;
+            }
+        }
+        None => {
+            declared.insert(key, (entries.len(), is_definition));
+            entries.push(entry);
+        }
+    }
+}
+
+impl<'u> Describer<'u, '_> {
+    /// Whether `span` is in the file described.
+    fn here(&self, span: Span) -> bool { self.sources.file_at(span.start).is_some_and(|f| f.id == self.file) }
+
+    fn line(&self, span: Span) -> Option<usize> { self.sources.location(span.start).map(|at| at.line) }
+
+    /// Describes `decls`, declared in `scope`; what is not `visible`, in an
+    /// unnamed namespace, only declares constants for what follows.
+    fn decls(&mut self, decls: &'u [Decl<'u>], scope: &str, visible: bool) {
+        for decl in decls { self.decl(decl, scope, visible); }
+    }
+
+    fn decl(&mut self, decl: &'u Decl<'u>, scope: &str, visible: bool) {
+        match decl {
+            Decl::Namespace(n) => match n.name {
+                Some(name) => self.decls(&n.decls, &qualify(scope, &name.as_str()), visible),
+                None => self.decls(&n.decls, scope, false),
+            },
+            Decl::Export(e) => self.decls(&e.decls, scope, visible),
+            Decl::Class(c) => {
+                self.constants.layouts.declare_class(c);
+                if visible { self.class(c, scope); }
+            }
+            Decl::Enum(e) => {
+                let values = enums::values(e, &self.constants.layouts, &mut self.constants.enumerators, &mut Diagnostics::new());
+                self.constants.layouts.declare_enum(e, &values);
+                if visible && self.here(e.span) { self.enumeration(e, &values, scope); }
+            }
+            Decl::Typedef(t) => {
+                self.constants.layouts.declare_typedef(t);
+                if visible && self.here(t.span) {
+                    let name = qualify(scope, &t.name.as_str());
+                    self.typedefs.push(json!({ "name": name, "type": self.names.resolve(t.ty, scope).to_string(), "line": self.line(t.name_span) }));
+                }
+            }
+            Decl::Function(f) => {
+                self.constants.declare_function(f);
+                let name = qualify(scope, &f.name.as_str());
+                // A member defined outside its class is described with it.
+                if visible && !self.names.is_class(scope_of(&name)) && f.specifiers.storage != StorageClass::Static && self.here(f.name_span) {
+                    let inline = f.specifiers.is_inline || f.specifiers.is_constexpr;
+                    let described = self.function(f, &name, None, inline);
+                    let key = match described["mangled"].as_str() {
+                        Some(mangled) => format!("function {} {}", name, mangled),
+                        None => format!("function {} {}", name, self.names.resolve(f.ty, scope)),
+                    };
+                    merge(&mut self.functions, &mut self.declared, key, described, f.body.is_some());
+                }
+            }
+            Decl::Var(v) => {
+                self.constants.declare_variable(v);
+                let internal = v.specifiers.storage == StorageClass::Static || (v.specifiers.storage != StorageClass::Extern && v.ty.is_const() && !v.specifiers.is_constexpr);
+                if visible && self.here(v.span) && (!internal || self.constants.variables.contains_key(&v.name)) { self.variable(v, &qualify(scope, &v.name.as_str())); }
+            }
+            _ => {}
+        }
+    }
+
+    /// A constant if `v` has a constant value, else a variable.
+    fn variable(&mut self, v: &VarDecl, name: &str) {
+        let ty = self.names.resolve(v.ty, scope_of(name));
+        // A static data member declared in its class is defined outside
+        // it, unless it is `inline`.
+        let is_definition = match v.specifiers.storage {
+            StorageClass::Extern => v.init.is_some(),
+            StorageClass::Static if self.names.is_class(scope_of(name)) => v.init.is_some() || v.specifiers.is_inline || v.specifiers.is_constexpr,
+            _ => true,
+        };
+        match self.constants.variables.get(&v.name) {
+            Some(&c) => {
+                let entry = json!({ "name": name, "type": ty.unqualified().to_string(), "value": number(c), "line": self.line(v.name_span) });
+                merge(&mut self.constant_values, &mut self.declared, format!("constant {}", name), entry, is_definition);
+            }
+            None => {
+                let mangled = if v.specifiers.is_extern_c { Some(mangle::extern_c(name)) } else { mangle::variable(name) };
+                let entry = json!({ "name": name, "type": ty.to_string(), "mangled": mangled, "line": self.line(v.name_span) });
+                merge(&mut self.variables, &mut self.declared, format!("variable {}", name), entry, is_definition);
+            }
+        }
+    }
+
+    fn enumeration(&mut self, e: &EnumDecl, values: &[Option<i128>], scope: &str) {
+        let Some(name) = e.name else { return };
+        let enumerators: Vec<Value> = e.enumerators.iter().flatten().zip(values).map(|(n, value)| json!({ "name": n.name.to_string(), "value": value.map(integer) })).collect();
+        let underlying = self.layouts.underlying(name).map(|ty| self.names.resolve(ty, scope).to_string());
+        self.enums.push(json!({ "name": qualify(scope, &name.as_str()), "scoped": e.is_scoped, "underlying": underlying, "enumerators": enumerators, "line": self.line(e.span) }));
+    }
+
+    fn class(&mut self, c: &'u ClassDecl<'u>, scope: &str) {
+        let (Some(name), Some(members)) = (c.name, &c.members) else { return };
+        let qualified = qualify(scope, &name.as_str());
+        for member in members {
+            if matches!(member.decl, Decl::Class(_) | Decl::Enum(_) | Decl::Typedef(_)) { self.decl(&member.decl, &qualified, true); }
+        }
+        if !self.here(c.span) { return; }
+        let layout = self.layouts.layout(TypeId::intern(Type::Named(Symbol::intern(&qualified)))).ok();
+        let placed = |slot: &dyn Fn(&Slot) -> bool| layout.as_ref().and_then(|l| l.placed.iter().find(|p| slot(&p.slot)).copied());
+        let bases: Vec<Value> = c
+            .bases
+            .iter()
+            .map(|b| {
+                let at = placed(&|s| matches!(s, Slot::Base(n) if *n == b.name || &*n.as_str() == b.name.as_str().rsplit("::").next().unwrap_or_default()));
+                json!({ "name": self.names.qualified(&b.name.as_str(), scope), "access": b.access.to_string(), "virtual": b.is_virtual, "offset": at.map(|p| p.offset) })
+            })
+            .collect();
+        let (mut fields, mut methods) = (Vec::new(), Vec::new());
+        for member in members {
+            match &member.decl {
+                Decl::Var(v) if v.specifiers.storage != StorageClass::Static => {
+                    let at = placed(&|s| matches!(s, Slot::Field(n, _) if *n == v.name));
+                    let ty = self.names.resolve(v.ty, &qualified);
+                    fields.push(json!({ "name": v.name.to_string(), "type": ty.to_string(), "access": member.access.to_string(), "offset": at.map(|p| p.offset), "size": at.map(|p| p.size) }));
+                }
+                Decl::Var(v) => {
+                    self.constants.declare_variable(v);
+                    self.variable(v, &qualify(&qualified, &v.name.as_str()));
+                }
+                Decl::Function(f) if !f.specifiers.is_friend => {
+                    let is_static = f.specifiers.storage == StorageClass::Static;
+                    let this = (!is_static).then_some(f.qualifiers.quals);
+                    let inline = f.body.is_some() || f.specifiers.is_inline || f.specifiers.is_constexpr;
+                    let mut method = self.function(f, &qualify(&qualified, &f.name.as_str()), this, inline);
+                    let q = &f.qualifiers;
+                    let kind = match f.kind {
+                        FunctionKind::Normal => "method",
+                        FunctionKind::Constructor => "constructor",
+                        FunctionKind::Destructor => "destructor",
+                        FunctionKind::Conversion => "conversion",
+                    };
+                    if let Some(object) = method.as_object_mut() {
+                        object.insert("name".to_string(), json!(f.name.to_string()));
+                        object.insert("kind".to_string(), json!(kind));
+                        object.insert("access".to_string(), json!(member.access.to_string()));
+                        object.insert("static".to_string(), json!(is_static));
+                        object.insert("const".to_string(), json!(q.quals.is_const));
+                        object.insert("virtual".to_string(), json!(f.specifiers.is_virtual || q.is_override || q.is_final || q.is_pure));
+                        object.insert("pure".to_string(), json!(q.is_pure));
+                        object.insert("deleted".to_string(), json!(q.is_deleted));
+                    }
+                    methods.push(method);
+                }
+                _ => {}
+            }
+        }
+        self.records.push(json!({
+            "name": qualified,
+            "kind": c.key.keyword(),
+            "size": layout.as_ref().map(|l| l.size),
+            "align": layout.as_ref().map(|l| l.align),
+            "polymorphic": layout.as_ref().map(|l| l.is_polymorphic),
+            "bases": bases,
+            "fields": fields,
+            "methods": methods,
+            "vtable": self.names.vtable(&qualified, 0),
+            "line": self.line(c.span),
+        }));
+    }
+
+    /// `f`, named `name`: its mangled name, return type and parameters.
+    fn function(&self, f: &FunctionDecl, name: &str, this: Option<Qualifiers>, inline: bool) -> Value {
+        let ty = self.names.resolve(f.ty, scope_of(name));
+        let Type::Function { ret, params, variadic } = ty.get() else { return Value::Null };
+        let parameters: Vec<Value> = f
+            .params
+            .iter()
+            .zip(&params)
+            .map(|(p, ty)| json!({ "name": p.name.map(|n| n.as_str().to_string()), "type": ty.to_string(), "default": p.default.is_some() }))
+            .collect();
+        json!({
+            "name": name,
+            "mangled": if f.specifiers.is_extern_c { Some(mangle::extern_c(name)) } else { mangle::function(name, f.kind, ty, this) },
+            "returns": ret.to_string(),
+            "parameters": parameters,
+            "variadic": variadic,
+            "inline": inline,
+            "line": self.line(f.name_span),
+        })
+    }
+}
+
+/// `n` as a JSON number, or as a string if no JSON number holds it.
+fn integer(n: i128) -> Value {
+    if let Ok(n) = i64::try_from(n) { return json!(n); }
+    if let Ok(n) = u64::try_from(n) { return json!(n); }
+    json!(n.to_string())
+}
+
+fn number(c: Constant) -> Value {
+    match (c.value, c.ty.get()) {
+        (Number::Int(n), Type::Bool) => json!(n != 0),
+        (Number::Int(n), _) => integer(n),
+        (Number::Float(v), _) => json!(v),
+    }
+}
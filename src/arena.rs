@@ -0,0 +1,149 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+use bumpalo::Bump;
+
+use crate::{intern, types};
+
+/// Bump allocator owning everything allocated for one translation unit.
+///
+/// AST nodes for a TU are carved out of a single arena and released
+/// together when the arena is dropped (or `reset`), so batch runs pay one
+/// deallocation per TU instead of one per node. The symbols and types
+/// interned on its thread while it is the newest arena there go with it.
+pub struct TuArena {
+    bump: Bump,
+    /// Where this arena's scope is in `SCOPES`.
+    scope: usize,
+    /// Scopes are per thread.
+    _thread: PhantomData<*const ()>,
+}
+
+/// Where the symbol and type tables stood when an arena was made.
+struct Scope {
+    symbols: u32,
+    types: u32,
+    live: bool,
+}
+
+thread_local! {
+    /// The scopes of this thread's arenas, oldest first. A dropped arena's
+    /// scope stays until the ones above it are dropped too.
+    static SCOPES: RefCell<Vec<Scope>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Memory usage of an arena at a point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArenaStats {
+    /// Bytes handed out to allocations.
+    pub allocated_bytes: usize,
+    /// Bytes reserved from the system, including unused chunk capacity.
+    pub reserved_bytes: usize,
+}
+
+impl TuArena {
+    pub fn new() -> Self {
+        let scope = Scope { symbols: intern::watermark(), types: types::watermark(), live: true };
+        let scope = SCOPES.with_borrow_mut(|scopes| { scopes.push(scope); scopes.len() - 1 });
+        Self { bump: Bump::new(), scope, _thread: PhantomData }
+    }
+
+    pub fn alloc<T>(&self, value: T) -> &mut T { self.bump.alloc(value) }
+
+    /// Moves `value` into the arena, to be dropped with its owner.
+    pub fn boxed<T>(&self, value: T) -> ArenaBox<'_, T> { ArenaBox { value: bumpalo::boxed::Box::new_in(value, &self.bump), arena: self } }
+
+    pub fn alloc_str(&self, s: &str) -> &str { self.bump.alloc_str(s) }
+
+    pub fn alloc_slice_copy<T: Copy>(&self, items: &[T]) -> &[T] { self.bump.alloc_slice_copy(items) }
+
+    pub fn alloc_slice_fill_iter<T, I>(&self, iter: I) -> &[T]
+    where I: IntoIterator<Item = T>, I::IntoIter: ExactSizeIterator {
+        self.bump.alloc_slice_fill_iter(iter)
+    }
+
+    pub fn stats(&self) -> ArenaStats {
+        ArenaStats {
+            allocated_bytes: self.bump.allocated_bytes() - self.bump.chunk_capacity(),
+            reserved_bytes: self.bump.allocated_bytes(),
+        }
+    }
+
+    /// Frees every allocation at once, keeping the largest chunk for reuse
+    /// by the next translation unit. The symbols and types interned since
+    /// the arena was made are freed too if it is the newest.
+    pub fn reset(&mut self) {
+        self.bump.reset();
+        let newest = SCOPES.with_borrow(|scopes| (scopes.len() == self.scope + 1).then(|| (scopes[self.scope].symbols, scopes[self.scope].types)));
+        if let Some((symbols, types)) = newest {
+            intern::release(symbols);
+            types::release(types);
+        }
+    }
+}
+
+impl Default for TuArena {
+    fn default() -> Self { Self::new() }
+}
+
+impl Drop for TuArena {
+    fn drop(&mut self) {
+        let released = SCOPES.with_borrow_mut(|scopes| {
+            scopes[self.scope].live = false;
+            let mut released = None;
+            while scopes.last().is_some_and(|s| !s.live) {
+                let scope = scopes.pop().unwrap();
+                released = Some((scope.symbols, scope.types));
+            }
+            released
+        });
+        if let Some((symbols, types)) = released {
+            intern::release(symbols);
+            types::release(types);
+        }
+    }
+}
+
+/// An owning pointer into a `TuArena`, used for the AST's child nodes in
+/// place of `Box`. The value is dropped with the pointer, so nodes may
+/// own heap data, but its memory is only reclaimed with the arena's.
+/// Cloning copies the value into the same arena.
+pub struct ArenaBox<'a, T> {
+    value: bumpalo::boxed::Box<'a, T>,
+    arena: &'a TuArena,
+}
+
+impl<'a, T> ArenaBox<'a, T> {
+    /// The arena the value lives in, for allocating nodes next to it.
+    pub fn arena(&self) -> &'a TuArena { self.arena }
+
+    pub fn into_inner(self) -> T { bumpalo::boxed::Box::into_inner(self.value) }
+}
+
+impl<T> Deref for ArenaBox<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T { &self.value }
+}
+
+impl<T> DerefMut for ArenaBox<'_, T> {
+    fn deref_mut(&mut self) -> &mut T { &mut self.value }
+}
+
+impl<T: Clone> Clone for ArenaBox<'_, T> {
+    fn clone(&self) -> Self { self.arena.boxed((**self).clone()) }
+}
+
+impl<T: PartialEq> PartialEq for ArenaBox<'_, T> {
+    fn eq(&self, other: &Self) -> bool { **self == **other }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ArenaBox<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { (**self).fmt(f) }
+}
+
+impl<T: fmt::Display> fmt::Display for ArenaBox<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { (**self).fmt(f) }
+}
@@ -0,0 +1,498 @@
+//! Compact binary encoding for compiler artifacts.
+//!
+//! Every artifact starts with a header: the magic bytes `RSCA`, the format
+//! version and a kind byte. Integers are LEB128 varints, signed ones
+//! zigzag-encoded first. Token streams put every distinct string in a
+//! table once and encode each span start as a delta from the previous
+//! token's end, so typical tokens take three or four bytes. Syntax trees,
+//! laid out in `tree`, are encoded with the same `Writer`/`Reader`.
+//!
+//! Token stream layout after the header:
+//!
+//! ```text
+//! strings: varint count, then per string varint length + UTF-8 bytes
+//! tokens:  varint count, then per token
+//!          tag byte, zigzag start delta, varint length, payload by tag;
+//!          an embed's payload is a varint length and the raw bytes, a
+//!          string or char literal's a literal
+//! literal: encoding byte, varint count, then per element a varint, the
+//!          character or code unit shifted left, with 1 for a code unit
+//! ```
+//!
+//! Module interface layout after the header, with strings written in
+//! place:
+//!
+//! ```text
+//! name:    string
+//! imports: varint count, then per re-exported module a string
+//! decls:   varint count, then per declaration
+//!          kind byte, string name, 0 or 1 then the type
+//! type:    tag byte, then by tag: nothing for a built-in type, a string
+//!          for a name, the inner type for a pointer or reference, ...
+//! ```
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::intern::Symbol;
+use crate::lexer::lexer;
+use crate::lexer::token::{Encoding, Literal, LiteralChar, Span, SpannedToken, Token};
+use crate::module::{ExportKind, ExportedDecl, ModuleInterface};
+use crate::types::{Qualifiers, TemplateArgument, Type, TypeId};
+
+mod tree;
+
+pub use tree::{decode_ast, encode_ast};
+
+pub const MAGIC: &[u8; 4] = b"RSCA";
+/// Bumped whenever the encoding changes; readers reject other versions.
+pub const VERSION: u32 = 6;
+
+/// What an artifact holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactKind {
+    Tokens = 1,
+    ModuleInterface = 2,
+    /// A parsed translation unit.
+    Ast = 3,
+}
+
+impl ArtifactKind {
+    fn from_byte(b: u8) -> Option<Self> {
+        match b {
+            1 => Some(ArtifactKind::Tokens),
+            2 => Some(ArtifactKind::ModuleInterface),
+            3 => Some(ArtifactKind::Ast),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ArtifactKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArtifactKind::Tokens => write!(f, "token stream"),
+            ArtifactKind::ModuleInterface => write!(f, "module interface"),
+            ArtifactKind::Ast => write!(f, "syntax tree"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    BadMagic,
+    UnsupportedVersion(u32),
+    UnknownKind(u8),
+    /// The artifact holds something other than what was asked for.
+    WrongKind(ArtifactKind),
+    Truncated,
+    Invalid(&'static str),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::BadMagic => write!(f, "not a RusCom artifact"),
+            DecodeError::UnsupportedVersion(v) => write!(f, "artifact format version {} is not supported (expected {})", v, VERSION),
+            DecodeError::UnknownKind(k) => write!(f, "unknown artifact kind {}", k),
+            DecodeError::WrongKind(k) => write!(f, "artifact holds a {}", k),
+            DecodeError::Truncated => write!(f, "artifact is truncated"),
+            DecodeError::Invalid(what) => write!(f, "invalid artifact: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Appends varints and strings to a buffer.
+#[derive(Debug, Default)]
+pub struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    /// A writer whose buffer starts with the header for `kind`.
+    pub fn new(kind: ArtifactKind) -> Self {
+        let mut w = Self::default();
+        w.buf.extend_from_slice(MAGIC);
+        w.uint(VERSION as u64);
+        w.byte(kind as u8);
+        w
+    }
+
+    pub fn byte(&mut self, b: u8) { self.buf.push(b); }
+
+    pub fn uint(&mut self, mut v: u64) {
+        while v >= 0x80 {
+            self.buf.push(v as u8 | 0x80);
+            v >>= 7;
+        }
+        self.buf.push(v as u8);
+    }
+
+    pub fn int(&mut self, v: i64) { self.uint(((v << 1) ^ (v >> 63)) as u64); }
+
+    pub fn bytes(&mut self, b: &[u8]) {
+        self.uint(b.len() as u64);
+        self.buf.extend_from_slice(b);
+    }
+
+    pub fn str(&mut self, s: &str) { self.bytes(s.as_bytes()); }
+
+    pub fn literal(&mut self, literal: &Literal) {
+        self.byte(ENCODINGS.iter().position(|&e| e == literal.encoding).unwrap_or(0) as u8);
+        self.uint(literal.chars.len() as u64);
+        for c in &literal.chars {
+            match *c {
+                LiteralChar::Char(c) => self.uint((c as u64) << 1),
+                LiteralChar::Unit(u) => self.uint((u as u64) << 1 | 1),
+            }
+        }
+    }
+
+    /// Writes `ty` and, recursively, the types it is built from.
+    pub fn ty(&mut self, ty: TypeId) {
+        let ty = ty.get();
+        if let Some(i) = BUILTIN_TYPES.iter().position(|t| *t == ty) {
+            self.byte(i as u8);
+            return;
+        }
+        match ty {
+            Type::Named(name) => {
+                self.byte(TYPE_NAMED);
+                self.str(&name.as_str());
+            }
+            Type::Specialization(name, args) => {
+                self.byte(TYPE_SPECIALIZATION);
+                self.str(&name.as_str());
+                self.uint(args.len() as u64);
+                for arg in &args {
+                    match arg {
+                        TemplateArgument::Type(ty) => { self.byte(0); self.ty(*ty); }
+                        TemplateArgument::Value(text) => { self.byte(1); self.str(text); }
+                    }
+                }
+            }
+            Type::Pointer(inner) => { self.byte(TYPE_POINTER); self.ty(inner); }
+            Type::Reference(inner) => { self.byte(TYPE_REFERENCE); self.ty(inner); }
+            Type::RvalueReference(inner) => { self.byte(TYPE_RVALUE_REFERENCE); self.ty(inner); }
+            Type::Array(elem, bound) => {
+                self.byte(TYPE_ARRAY);
+                self.ty(elem);
+                // The bound plus one, so 0 is an unknown bound.
+                self.uint(bound.map_or(0, |n| n + 1));
+            }
+            Type::Function { ret, params, variadic } => {
+                self.byte(TYPE_FUNCTION);
+                self.ty(ret);
+                self.uint(params.len() as u64);
+                for p in params { self.ty(p); }
+                self.byte(variadic as u8);
+            }
+            Type::Qualified(inner, quals) => {
+                self.byte(TYPE_QUALIFIED);
+                self.ty(inner);
+                self.byte(quals.is_const as u8 | (quals.is_volatile as u8) << 1);
+            }
+            _ => unreachable!("built-in types are written by index"),
+        }
+    }
+
+    pub fn finish(self) -> Vec<u8> { self.buf }
+}
+
+/// Reads what a `Writer` wrote.
+#[derive(Debug)]
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Checks the header and returns the artifact's kind with a reader
+    /// positioned after it.
+    pub fn open(bytes: &'a [u8]) -> Result<(ArtifactKind, Self), DecodeError> {
+        if !bytes.starts_with(MAGIC) { return Err(DecodeError::BadMagic); }
+        let mut r = Self { bytes, pos: MAGIC.len() };
+        let version = r.uint()?;
+        if version != VERSION as u64 { return Err(DecodeError::UnsupportedVersion(version as u32)); }
+        let kind = r.byte()?;
+        let kind = ArtifactKind::from_byte(kind).ok_or(DecodeError::UnknownKind(kind))?;
+        Ok((kind, r))
+    }
+
+    pub fn is_empty(&self) -> bool { self.pos == self.bytes.len() }
+
+    pub fn byte(&mut self) -> Result<u8, DecodeError> {
+        let b = *self.bytes.get(self.pos).ok_or(DecodeError::Truncated)?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    pub fn uint(&mut self) -> Result<u64, DecodeError> {
+        let mut v = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            v |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 { return Ok(v); }
+        }
+        Err(DecodeError::Invalid("varint longer than 64 bits"))
+    }
+
+    pub fn int(&mut self) -> Result<i64, DecodeError> {
+        let v = self.uint()?;
+        Ok((v >> 1) as i64 ^ -((v & 1) as i64))
+    }
+
+    /// A length or count, checked against the bytes left so corrupt input
+    /// can't ask for a huge allocation.
+    pub fn len(&mut self) -> Result<usize, DecodeError> {
+        let n = self.uint()?;
+        if n > (self.bytes.len() - self.pos) as u64 { return Err(DecodeError::Truncated); }
+        Ok(n as usize)
+    }
+
+    pub fn bytes(&mut self) -> Result<&'a [u8], DecodeError> {
+        let len = self.len()?;
+        let bytes = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub fn str(&mut self) -> Result<&'a str, DecodeError> {
+        std::str::from_utf8(self.bytes()?).map_err(|_| DecodeError::Invalid("string is not UTF-8"))
+    }
+
+    pub fn literal(&mut self) -> Result<Literal, DecodeError> {
+        let encoding = *ENCODINGS.get(self.byte()? as usize).ok_or(DecodeError::Invalid("unknown literal encoding"))?;
+        let count = self.len()?;
+        let chars = (0..count).map(|_| {
+            let v = self.uint()?;
+            let value = u32::try_from(v >> 1).map_err(|_| DecodeError::Invalid("code unit out of range"))?;
+            if v & 1 == 1 { return Ok(LiteralChar::Unit(value)); }
+            char::from_u32(value).map(LiteralChar::Char).ok_or(DecodeError::Invalid("bad character"))
+        }).collect::<Result<_, _>>()?;
+        Ok(Literal { encoding, chars })
+    }
+
+    /// Reads a type `Writer::ty` wrote, interning it.
+    pub fn ty(&mut self) -> Result<TypeId, DecodeError> {
+        let tag = self.byte()?;
+        if let Some(ty) = BUILTIN_TYPES.get(tag as usize) { return Ok(TypeId::intern(ty.clone())); }
+        let ty = match tag {
+            TYPE_NAMED => Type::Named(Symbol::intern(self.str()?)),
+            TYPE_SPECIALIZATION => {
+                let name = Symbol::intern(self.str()?);
+                let count = self.len()?;
+                let args = (0..count).map(|_| match self.byte()? {
+                    0 => Ok(TemplateArgument::Type(self.ty()?)),
+                    1 => Ok(TemplateArgument::Value(self.str()?.to_string())),
+                    _ => Err(DecodeError::Invalid("unknown template argument tag")),
+                }).collect::<Result<_, _>>()?;
+                Type::Specialization(name, args)
+            }
+            TYPE_POINTER => Type::Pointer(self.ty()?),
+            TYPE_REFERENCE => Type::Reference(self.ty()?),
+            TYPE_RVALUE_REFERENCE => Type::RvalueReference(self.ty()?),
+            TYPE_ARRAY => {
+                let elem = self.ty()?;
+                Type::Array(elem, self.uint()?.checked_sub(1))
+            }
+            TYPE_FUNCTION => {
+                let ret = self.ty()?;
+                let count = self.len()?;
+                let params = (0..count).map(|_| self.ty()).collect::<Result<_, _>>()?;
+                Type::Function { ret, params, variadic: self.byte()? != 0 }
+            }
+            TYPE_QUALIFIED => {
+                let inner = self.ty()?;
+                let bits = self.byte()?;
+                return Ok(inner.qualified(Qualifiers { is_const: bits & 1 != 0, is_volatile: bits & 2 != 0 }));
+            }
+            _ => return Err(DecodeError::Invalid("unknown type tag")),
+        };
+        Ok(TypeId::intern(ty))
+    }
+}
+
+/// The types without parts, written as their index here.
+/// Literal encodings, by the byte that stands for them.
+const ENCODINGS: [Encoding; 5] = [Encoding::Ordinary, Encoding::Wide, Encoding::Utf8, Encoding::Utf16, Encoding::Utf32];
+
+const BUILTIN_TYPES: [Type; 21] = [
+    Type::Void, Type::Bool, Type::Char, Type::SignedChar, Type::UnsignedChar, Type::Short, Type::UnsignedShort,
+    Type::Int, Type::UnsignedInt, Type::Long, Type::UnsignedLong, Type::LongLong, Type::UnsignedLongLong,
+    Type::Float, Type::Double, Type::LongDouble, Type::Auto, Type::WChar, Type::Char8, Type::Char16, Type::Char32,
+];
+
+const TYPE_NAMED: u8 = 32;
+const TYPE_SPECIALIZATION: u8 = 33;
+const TYPE_POINTER: u8 = 34;
+const TYPE_REFERENCE: u8 = 35;
+const TYPE_RVALUE_REFERENCE: u8 = 36;
+const TYPE_ARRAY: u8 = 37;
+const TYPE_FUNCTION: u8 = 38;
+const TYPE_QUALIFIED: u8 = 39;
+
+const TAG_IDENTIFIER: u8 = 0;
+const TAG_NUMBER: u8 = 1;
+const TAG_STRING: u8 = 2;
+const TAG_CHAR: u8 = 3;
+const TAG_OPERATOR: u8 = 4;
+const TAG_PUNCT: u8 = 5;
+const TAG_LINE_COMMENT: u8 = 6;
+const TAG_BLOCK_COMMENT: u8 = 7;
+const TAG_DIRECTIVE: u8 = 8;
+const TAG_HEADER_QUOTED: u8 = 9;
+const TAG_HEADER_ANGLED: u8 = 10;
+const TAG_DIRECTIVE_END: u8 = 11;
+const TAG_EOF: u8 = 12;
+const TAG_EMBED: u8 = 13;
+
+/// Encodes a token stream as an artifact.
+pub fn encode_tokens(tokens: &[SpannedToken]) -> Vec<u8> {
+    let mut strings: Vec<Cow<str>> = Vec::new();
+    let mut index: HashMap<Cow<str>, usize> = HashMap::new();
+    let refs: Vec<Option<usize>> = tokens
+        .iter()
+        .map(|t| {
+            let s = token_text(&t.token)?;
+            Some(*index.entry(s.clone()).or_insert_with(|| {
+                strings.push(s);
+                strings.len() - 1
+            }))
+        })
+        .collect();
+    let mut w = Writer::new(ArtifactKind::Tokens);
+    w.uint(strings.len() as u64);
+    for s in &strings { w.str(s); }
+    w.uint(tokens.len() as u64);
+    let mut prev_end = 0i64;
+    for (t, text) in tokens.iter().zip(refs) {
+        let (tag, extra) = match &t.token {
+            Token::Identifier(_) => (TAG_IDENTIFIER, None),
+            Token::Number(_) => (TAG_NUMBER, None),
+            Token::StringLiteral(_) => (TAG_STRING, None),
+            Token::CharLiteral(_) => (TAG_CHAR, None),
+            Token::Operator(_) => (TAG_OPERATOR, None),
+            Token::Punct(c) => (TAG_PUNCT, Some(*c as u64)),
+            Token::Comment { block: false, .. } => (TAG_LINE_COMMENT, None),
+            Token::Comment { block: true, .. } => (TAG_BLOCK_COMMENT, None),
+            Token::Directive(_) => (TAG_DIRECTIVE, None),
+            Token::HeaderName { angled: false, .. } => (TAG_HEADER_QUOTED, None),
+            Token::HeaderName { angled: true, .. } => (TAG_HEADER_ANGLED, None),
+            Token::DirectiveEnd => (TAG_DIRECTIVE_END, None),
+            Token::Embed(_) => (TAG_EMBED, None),
+            Token::Eof => (TAG_EOF, None),
+        };
+        w.byte(tag);
+        w.int(t.span.start as i64 - prev_end);
+        w.uint((t.span.end - t.span.start) as u64);
+        if let Some(i) = text { w.uint(i as u64); }
+        if let Some(v) = extra { w.uint(v); }
+        match &t.token {
+            Token::Embed(bytes) => w.bytes(bytes),
+            Token::StringLiteral(literal) | Token::CharLiteral(literal) => w.literal(literal),
+            _ => {}
+        }
+        prev_end = t.span.end as i64;
+    }
+    w.finish()
+}
+
+/// The string a token stores in the string table, if any.
+fn token_text(token: &Token) -> Option<Cow<'_, str>> {
+    match token {
+        Token::Identifier(s) => Some(Cow::Owned(s.to_string())),
+        Token::Operator(s) => Some(Cow::Borrowed(s)),
+        Token::Number(s) | Token::Directive(s) => Some(Cow::Borrowed(s)),
+        Token::Comment { text, .. } => Some(Cow::Borrowed(text)),
+        Token::HeaderName { name, .. } => Some(Cow::Borrowed(name)),
+        Token::StringLiteral(_) | Token::CharLiteral(_) | Token::Punct(_) | Token::DirectiveEnd | Token::Embed(_) | Token::Eof => None,
+    }
+}
+
+/// Decodes a token stream artifact.
+pub fn decode_tokens(bytes: &[u8]) -> Result<Vec<SpannedToken>, DecodeError> {
+    let (kind, mut r) = Reader::open(bytes)?;
+    if kind != ArtifactKind::Tokens { return Err(DecodeError::WrongKind(kind)); }
+    let count = r.len()?;
+    let strings = (0..count).map(|_| r.str()).collect::<Result<Vec<_>, _>>()?;
+    let count = r.len()?;
+    let mut tokens = Vec::with_capacity(count);
+    let mut prev_end = 0i64;
+    for _ in 0..count {
+        let tag = r.byte()?;
+        let start = prev_end + r.int()?;
+        let len = r.uint()? as i64;
+        if start < 0 { return Err(DecodeError::Invalid("negative span start")); }
+        let span = Span::new(start as usize, (start + len) as usize);
+        let mut text = || -> Result<String, DecodeError> {
+            let i = r.uint()? as usize;
+            strings.get(i).map(|s| s.to_string()).ok_or(DecodeError::Invalid("string index out of range"))
+        };
+        let token = match tag {
+            TAG_IDENTIFIER => Token::Identifier(Symbol::intern(&text()?)),
+            TAG_NUMBER => Token::Number(text()?),
+            TAG_OPERATOR => Token::Operator(lexer::operator(&text()?).ok_or(DecodeError::Invalid("unknown operator"))?),
+            TAG_LINE_COMMENT => Token::Comment { text: text()?, block: false },
+            TAG_BLOCK_COMMENT => Token::Comment { text: text()?, block: true },
+            TAG_DIRECTIVE => Token::Directive(text()?),
+            TAG_HEADER_QUOTED => Token::HeaderName { name: text()?, angled: false },
+            TAG_HEADER_ANGLED => Token::HeaderName { name: text()?, angled: true },
+            TAG_PUNCT => Token::Punct(char::from_u32(r.uint()? as u32).ok_or(DecodeError::Invalid("bad character"))?),
+            TAG_STRING => Token::StringLiteral(r.literal()?),
+            TAG_CHAR => Token::CharLiteral(r.literal()?),
+            TAG_DIRECTIVE_END => Token::DirectiveEnd,
+            TAG_EMBED => Token::Embed(r.bytes()?.into()),
+            TAG_EOF => Token::Eof,
+            _ => return Err(DecodeError::Invalid("unknown token tag")),
+        };
+        tokens.push(SpannedToken { token, span });
+        prev_end = span.end as i64;
+    }
+    if !r.is_empty() { return Err(DecodeError::Invalid("trailing bytes")); }
+    Ok(tokens)
+}
+
+/// Encodes a compiled module interface as an artifact.
+pub fn encode_module(interface: &ModuleInterface) -> Vec<u8> {
+    let mut w = Writer::new(ArtifactKind::ModuleInterface);
+    w.str(&interface.name.as_str());
+    w.uint(interface.imports.len() as u64);
+    for module in &interface.imports { w.str(&module.as_str()); }
+    w.uint(interface.decls.len() as u64);
+    for d in &interface.decls {
+        w.byte(d.kind as u8);
+        w.str(&d.name.as_str());
+        match d.ty {
+            Some(ty) => { w.byte(1); w.ty(ty); }
+            None => w.byte(0),
+        }
+    }
+    w.finish()
+}
+
+/// Decodes a compiled module interface artifact.
+pub fn decode_module(bytes: &[u8]) -> Result<ModuleInterface, DecodeError> {
+    let (kind, mut r) = Reader::open(bytes)?;
+    if kind != ArtifactKind::ModuleInterface { return Err(DecodeError::WrongKind(kind)); }
+    let name = Symbol::intern(r.str()?);
+    let count = r.len()?;
+    let imports = (0..count).map(|_| r.str().map(Symbol::intern)).collect::<Result<_, _>>()?;
+    let count = r.len()?;
+    let mut decls = Vec::with_capacity(count);
+    for _ in 0..count {
+        let kind = ExportKind::from_byte(r.byte()?).ok_or(DecodeError::Invalid("unknown declaration kind"))?;
+        let name = Symbol::intern(r.str()?);
+        let ty = match r.byte()? {
+            0 => None,
+            1 => Some(r.ty()?),
+            _ => return Err(DecodeError::Invalid("bad type flag")),
+        };
+        decls.push(ExportedDecl { kind, name, ty });
+    }
+    if !r.is_empty() { return Err(DecodeError::Invalid("trailing bytes")); }
+    Ok(ModuleInterface { name, imports, decls })
+}
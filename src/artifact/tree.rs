@@ -0,0 +1,771 @@
+//! Syntax tree artifacts: a parsed translation unit written to disk and
+//! read back into an arena, so a unit need not be parsed again.
+//!
+//! Symbols and types repeat throughout a tree, so each distinct one goes in
+//! a table once and nodes refer to it by index. Nodes are written in the
+//! order of their fields, enums as a tag byte first. A span is its start
+//! as a zigzag delta from the previous span's start, then its length, so
+//! most take two bytes.
+//!
+//! ```text
+//! symbols: varint count, then per symbol a string
+//! types:   varint count, then per type as `Writer::ty` writes it
+//! unit:    varint count, then the declarations, then the unit's span
+//! ```
+
+use std::collections::HashMap;
+
+use crate::arena::{ArenaBox, TuArena};
+use crate::artifact::{ArtifactKind, DecodeError, Reader, Writer};
+use crate::ast::{
+    Access, Attribute, BaseSpecifier, BinaryOp, Binding, BitWidth, Block, CastKind, ClassDecl, ClassKey, ConceptDecl, ConstructCategory, Decl,
+    DecompositionDecl, EnumDecl, Enumerator, ExportDecl, Expr, ExprKind, FunctionDecl, FunctionKind, FunctionQualifiers, ImportDecl,
+    InitStyle, IntLength, IntSuffix, Member, MemberInit, ModuleDecl, NamespaceDecl, ParamDecl, QualifiedName, Requirement, Specifiers, StaticAssertDecl, Stmt, StmtKind,
+    StorageClass, TemplateArg, TemplateDecl, TemplateParam, TemplateParamKind, TranslationUnit, TypedefDecl, UnaryOp, UnsupportedConstruct,
+    UsingDecl, UsingDirective, VarDecl,
+};
+use crate::intern::Symbol;
+use crate::lexer::token::Span;
+use crate::types::{Qualifiers, TypeId};
+
+/// Encodes a parsed translation unit as an artifact.
+pub fn encode_ast(tu: &TranslationUnit) -> Vec<u8> {
+    let mut e = Encoder::default();
+    e.list(&tu.decls, Encoder::decl);
+    e.span(tu.span);
+    let mut w = Writer::new(ArtifactKind::Ast);
+    w.uint(e.symbols.len() as u64);
+    for s in &e.symbols { w.str(&s.as_str()); }
+    w.uint(e.types.len() as u64);
+    for ty in &e.types { w.ty(*ty); }
+    w.buf.extend_from_slice(&e.w.buf);
+    w.finish()
+}
+
+/// Decodes a syntax tree artifact, allocating its nodes in `arena`.
+pub fn decode_ast<'a>(bytes: &[u8], arena: &'a TuArena) -> Result<TranslationUnit<'a>, DecodeError> {
+    let (kind, mut r) = Reader::open(bytes)?;
+    if kind != ArtifactKind::Ast { return Err(DecodeError::WrongKind(kind)); }
+    let count = r.len()?;
+    let symbols = (0..count).map(|_| r.str().map(Symbol::intern)).collect::<Result<_, _>>()?;
+    let count = r.len()?;
+    let types = (0..count).map(|_| r.ty()).collect::<Result<_, _>>()?;
+    let mut d = Decoder { r, arena, symbols, types, prev_start: 0 };
+    let decls = d.list(Decoder::decl)?;
+    let span = d.span()?;
+    if !d.r.is_empty() { return Err(DecodeError::Invalid("trailing bytes")); }
+    Ok(TranslationUnit { decls, span })
+}
+
+const STORAGE_CLASSES: [StorageClass; 3] = [StorageClass::None, StorageClass::Static, StorageClass::Extern];
+const INIT_STYLES: [InitStyle; 3] = [InitStyle::Copy, InitStyle::Direct, InitStyle::List];
+const FUNCTION_KINDS: [FunctionKind; 4] = [FunctionKind::Normal, FunctionKind::Constructor, FunctionKind::Destructor, FunctionKind::Conversion];
+const CLASS_KEYS: [ClassKey; 3] = [ClassKey::Struct, ClassKey::Class, ClassKey::Union];
+const ACCESSES: [Access; 3] = [Access::Public, Access::Protected, Access::Private];
+const CATEGORIES: [ConstructCategory; 12] = [
+    ConstructCategory::StaticAssert, ConstructCategory::LinkageSpecification, ConstructCategory::InlineAssembly, ConstructCategory::UsingEnum,
+    ConstructCategory::ConstexprIf, ConstructCategory::Exceptions, ConstructCategory::Labels, ConstructCategory::Lambda,
+    ConstructCategory::Decltype, ConstructCategory::NewDelete, ConstructCategory::PackExpansion, ConstructCategory::Other,
+];
+const UNARY_OPS: [UnaryOp; 10] = [
+    UnaryOp::Plus, UnaryOp::Minus, UnaryOp::Not, UnaryOp::BitNot, UnaryOp::Deref, UnaryOp::AddressOf,
+    UnaryOp::PreIncrement, UnaryOp::PreDecrement, UnaryOp::PostIncrement, UnaryOp::PostDecrement,
+];
+const BINARY_OPS: [BinaryOp; 33] = [
+    BinaryOp::PtrMem, BinaryOp::PtrMemArrow, BinaryOp::Mul, BinaryOp::Div, BinaryOp::Rem, BinaryOp::Add, BinaryOp::Sub, BinaryOp::Shl,
+    BinaryOp::Shr, BinaryOp::ThreeWay, BinaryOp::Lt, BinaryOp::Gt, BinaryOp::Le, BinaryOp::Ge, BinaryOp::Eq, BinaryOp::Ne,
+    BinaryOp::BitAnd, BinaryOp::BitXor, BinaryOp::BitOr, BinaryOp::LogicalAnd, BinaryOp::LogicalOr, BinaryOp::Assign,
+    BinaryOp::MulAssign, BinaryOp::DivAssign, BinaryOp::RemAssign, BinaryOp::AddAssign, BinaryOp::SubAssign, BinaryOp::ShlAssign,
+    BinaryOp::ShrAssign, BinaryOp::AndAssign, BinaryOp::XorAssign, BinaryOp::OrAssign, BinaryOp::Comma,
+];
+const INT_LENGTHS: [IntLength; 4] = [IntLength::None, IntLength::Long, IntLength::LongLong, IntLength::Size];
+const CAST_KINDS: [CastKind; 5] = [CastKind::CStyle, CastKind::Static, CastKind::Dynamic, CastKind::Const, CastKind::Reinterpret];
+
+/// The index of `value` in `all`, one of the tables above.
+fn index<T: PartialEq>(all: &[T], value: T) -> u8 {
+    all.iter().position(|v| *v == value).expect("every variant is in its table") as u8
+}
+
+/// Writes a tree's nodes, collecting its symbols and types.
+#[derive(Default)]
+struct Encoder {
+    w: Writer,
+    symbols: Vec<Symbol>,
+    symbol_index: HashMap<Symbol, usize>,
+    types: Vec<TypeId>,
+    type_index: HashMap<TypeId, usize>,
+    prev_start: usize,
+}
+
+impl Encoder {
+    fn flag(&mut self, b: bool) { self.w.byte(b as u8); }
+
+    fn span(&mut self, span: Span) {
+        self.w.int(span.start as i64 - self.prev_start as i64);
+        self.w.uint((span.end - span.start) as u64);
+        self.prev_start = span.start;
+    }
+
+    fn sym(&mut self, s: Symbol) {
+        let i = *self.symbol_index.entry(s).or_insert_with(|| {
+            self.symbols.push(s);
+            self.symbols.len() - 1
+        });
+        self.w.uint(i as u64);
+    }
+
+    fn name(&mut self, name: QualifiedName) { self.sym(name.symbol()); }
+
+    fn ty(&mut self, ty: TypeId) {
+        let i = *self.type_index.entry(ty).or_insert_with(|| {
+            self.types.push(ty);
+            self.types.len() - 1
+        });
+        self.w.uint(i as u64);
+    }
+
+    fn list<T>(&mut self, items: &[T], mut f: impl FnMut(&mut Self, &T)) {
+        self.w.uint(items.len() as u64);
+        for item in items { f(self, item); }
+    }
+
+    fn opt<T>(&mut self, value: &Option<T>, f: impl FnOnce(&mut Self, &T)) {
+        match value {
+            Some(v) => { self.w.byte(1); f(self, v); }
+            None => self.w.byte(0),
+        }
+    }
+
+    fn opt_sym(&mut self, s: Option<Symbol>) { self.opt(&s, |e, s| e.sym(*s)); }
+
+    fn specifiers(&mut self, s: &Specifiers) {
+        self.w.byte(index(&STORAGE_CLASSES, s.storage));
+        self.w.byte(s.is_inline as u8 | (s.is_constexpr as u8) << 1 | (s.is_virtual as u8) << 2 | (s.is_explicit as u8) << 3
+            | (s.is_mutable as u8) << 4 | (s.is_friend as u8) << 5 | (s.is_extern_c as u8) << 6);
+    }
+
+    fn qualifiers(&mut self, q: &FunctionQualifiers) {
+        self.w.byte(q.quals.is_const as u8 | (q.quals.is_volatile as u8) << 1 | (q.is_override as u8) << 2 | (q.is_final as u8) << 3
+            | (q.is_pure as u8) << 4 | (q.is_defaulted as u8) << 5 | (q.is_deleted as u8) << 6 | (q.is_noexcept as u8) << 7);
+    }
+
+    fn attr(&mut self, a: &Attribute) {
+        self.name(a.name);
+        self.opt(&a.args, |e, args| e.list(args, Encoder::expr));
+        self.span(a.span);
+    }
+
+    fn unsupported(&mut self, u: &UnsupportedConstruct) {
+        self.w.byte(index(&CATEGORIES, u.category));
+        self.span(u.span);
+    }
+
+    fn decl(&mut self, decl: &Decl) {
+        match decl {
+            Decl::Var(v) => {
+                self.w.byte(0);
+                self.sym(v.name);
+                self.ty(v.ty);
+                self.specifiers(&v.specifiers);
+                self.opt(&v.init, Encoder::expr);
+                self.w.byte(index(&INIT_STYLES, v.init_style));
+                self.opt(&v.bit_width, |e, w| {
+                    e.expr(&w.expr);
+                    e.opt(&w.bits, |e, &n| e.w.uint(n));
+                });
+                self.list(&v.attrs, Encoder::attr);
+                self.span(v.span);
+                self.span(v.name_span);
+            }
+            Decl::Decomposition(d) => {
+                self.w.byte(1);
+                self.ty(d.ty);
+                self.specifiers(&d.specifiers);
+                self.list(&d.bindings, |e, b| {
+                    e.sym(b.name);
+                    e.list(&b.attrs, Encoder::attr);
+                    e.span(b.span);
+                });
+                self.opt(&d.init, Encoder::expr);
+                self.w.byte(index(&INIT_STYLES, d.init_style));
+                self.list(&d.attrs, Encoder::attr);
+                self.span(d.span);
+            }
+            Decl::Function(f) => {
+                self.w.byte(2);
+                self.sym(f.name);
+                self.w.byte(index(&FUNCTION_KINDS, f.kind));
+                self.ty(f.ty);
+                self.list(&f.params, Encoder::param);
+                self.specifiers(&f.specifiers);
+                self.qualifiers(&f.qualifiers);
+                self.list(&f.initializers, |e, init| {
+                    e.sym(init.name);
+                    e.list(&init.args, Encoder::expr);
+                    e.flag(init.braced);
+                    e.span(init.span);
+                });
+                self.opt(&f.body, Encoder::block);
+                self.list(&f.attrs, Encoder::attr);
+                self.opt(&f.requires, Encoder::expr);
+                self.flag(f.is_coroutine);
+                self.span(f.span);
+                self.span(f.name_span);
+            }
+            Decl::Typedef(t) => {
+                self.w.byte(3);
+                self.sym(t.name);
+                self.ty(t.ty);
+                self.flag(t.is_alias);
+                self.list(&t.attrs, Encoder::attr);
+                self.span(t.span);
+                self.span(t.name_span);
+            }
+            Decl::Namespace(n) => {
+                self.w.byte(4);
+                self.opt_sym(n.name);
+                self.flag(n.is_inline);
+                self.list(&n.decls, Encoder::decl);
+                self.list(&n.attrs, Encoder::attr);
+                self.span(n.span);
+            }
+            Decl::Using(u) => {
+                self.w.byte(5);
+                self.name(u.name);
+                self.span(u.span);
+            }
+            Decl::UsingDirective(u) => {
+                self.w.byte(6);
+                self.name(u.namespace);
+                self.span(u.span);
+            }
+            Decl::Class(c) => {
+                self.w.byte(7);
+                self.w.byte(index(&CLASS_KEYS, c.key));
+                self.opt_sym(c.name);
+                self.opt(&c.args, |e, args| e.list(args, Encoder::template_arg));
+                self.flag(c.is_final);
+                self.list(&c.bases, |e, b| {
+                    e.sym(b.name);
+                    e.w.byte(index(&ACCESSES, b.access));
+                    e.flag(b.is_virtual);
+                    e.span(b.span);
+                });
+                self.opt(&c.members, |e, members| e.list(members, |e, m| {
+                    e.w.byte(index(&ACCESSES, m.access));
+                    e.decl(&m.decl);
+                }));
+                self.list(&c.attrs, Encoder::attr);
+                self.span(c.span);
+            }
+            Decl::Enum(en) => {
+                self.w.byte(8);
+                self.opt_sym(en.name);
+                self.flag(en.is_scoped);
+                self.opt(&en.underlying, |e, ty| e.ty(*ty));
+                self.opt(&en.enumerators, |e, enumerators| e.list(enumerators, |e, en| {
+                    e.sym(en.name);
+                    e.opt(&en.value, Encoder::expr);
+                    e.list(&en.attrs, Encoder::attr);
+                    e.span(en.span);
+                }));
+                self.list(&en.attrs, Encoder::attr);
+                self.span(en.span);
+            }
+            Decl::Template(t) => {
+                self.w.byte(9);
+                self.list(&t.params, Encoder::template_param);
+                self.opt(&t.requires, Encoder::expr);
+                self.decl(&t.decl);
+                self.span(t.span);
+            }
+            Decl::Concept(c) => {
+                self.w.byte(10);
+                self.sym(c.name);
+                self.expr(&c.constraint);
+                self.span(c.span);
+                self.span(c.name_span);
+            }
+            Decl::Module(m) => {
+                self.w.byte(11);
+                self.opt_sym(m.name);
+                self.opt_sym(m.partition);
+                self.flag(m.is_export);
+                self.span(m.span);
+            }
+            Decl::Import(i) => {
+                self.w.byte(12);
+                self.sym(i.module);
+                self.flag(i.is_export);
+                self.span(i.span);
+            }
+            Decl::Export(x) => {
+                self.w.byte(13);
+                self.list(&x.decls, Encoder::decl);
+                self.flag(x.braced);
+                self.span(x.span);
+            }
+            Decl::Unsupported(u) => {
+                self.w.byte(14);
+                self.unsupported(u);
+            }
+            Decl::Invalid(span) => {
+                self.w.byte(15);
+                self.span(*span);
+            }
+            Decl::StaticAssert(a) => {
+                self.w.byte(16);
+                self.expr(&a.cond);
+                self.opt(&a.message, |e, m| e.w.str(m));
+                self.span(a.span);
+            }
+        }
+    }
+
+    fn param(&mut self, p: &ParamDecl) {
+        self.opt_sym(p.name);
+        self.ty(p.ty);
+        self.flag(p.is_pack);
+        self.opt(&p.default, Encoder::expr);
+        self.list(&p.attrs, Encoder::attr);
+        self.span(p.span);
+    }
+
+    fn template_param(&mut self, p: &TemplateParam) {
+        match &p.kind {
+            TemplateParamKind::Type => self.w.byte(0),
+            TemplateParamKind::Value(ty) => { self.w.byte(1); self.ty(*ty); }
+            TemplateParamKind::Template(params) => { self.w.byte(2); self.list(params, Encoder::template_param); }
+        }
+        self.opt_sym(p.name);
+        self.flag(p.is_pack);
+        self.opt(&p.constraint, Encoder::expr);
+        self.opt(&p.default, Encoder::template_arg);
+        self.span(p.span);
+    }
+
+    fn template_arg(&mut self, arg: &TemplateArg) {
+        match arg {
+            TemplateArg::Type(ty, span) => { self.w.byte(0); self.ty(*ty); self.span(*span); }
+            TemplateArg::Expr(e) => { self.w.byte(1); self.expr(e); }
+        }
+    }
+
+    fn block(&mut self, b: &Block) {
+        self.list(&b.stmts, Encoder::stmt);
+        self.span(b.span);
+    }
+
+    fn stmt(&mut self, s: &Stmt) {
+        match &s.kind {
+            StmtKind::Compound(b) => { self.w.byte(0); self.block(b); }
+            StmtKind::Null => self.w.byte(1),
+            StmtKind::Expr(e) => { self.w.byte(2); self.expr(e); }
+            StmtKind::Decl(decls) => { self.w.byte(3); self.list(decls, Encoder::decl); }
+            StmtKind::If { cond, then, otherwise } => {
+                self.w.byte(4);
+                self.expr(cond);
+                self.stmt(then);
+                self.opt(otherwise, |e, s| e.stmt(s));
+            }
+            StmtKind::While { cond, body } => { self.w.byte(5); self.expr(cond); self.stmt(body); }
+            StmtKind::DoWhile { body, cond } => { self.w.byte(6); self.stmt(body); self.expr(cond); }
+            StmtKind::For { init, cond, step, body } => {
+                self.w.byte(7);
+                self.opt(init, |e, s| e.stmt(s));
+                self.opt(cond, Encoder::expr);
+                self.opt(step, Encoder::expr);
+                self.stmt(body);
+            }
+            StmtKind::RangeFor { init, decl, range, body } => {
+                self.w.byte(8);
+                self.opt(init, |e, s| e.stmt(s));
+                self.decl(decl);
+                self.expr(range);
+                self.stmt(body);
+            }
+            StmtKind::Switch { cond, body } => { self.w.byte(9); self.expr(cond); self.stmt(body); }
+            StmtKind::Case { value, body } => { self.w.byte(10); self.expr(value); self.stmt(body); }
+            StmtKind::Default(body) => { self.w.byte(11); self.stmt(body); }
+            StmtKind::Break => self.w.byte(12),
+            StmtKind::Continue => self.w.byte(13),
+            StmtKind::Return(value) => { self.w.byte(14); self.opt(value, Encoder::expr); }
+            StmtKind::CoReturn(value) => { self.w.byte(15); self.opt(value, Encoder::expr); }
+            StmtKind::Attributed { attrs, stmt } => { self.w.byte(16); self.list(attrs, Encoder::attr); self.stmt(stmt); }
+            StmtKind::Unsupported(u) => { self.w.byte(17); self.unsupported(u); }
+            StmtKind::Invalid => self.w.byte(18),
+        }
+        self.span(s.span);
+    }
+
+    fn expr(&mut self, e: &Expr) {
+        match &e.kind {
+            ExprKind::IntLiteral(v, suffix) => {
+                self.w.byte(0);
+                self.w.uint(*v);
+                self.w.byte(index(&INT_LENGTHS, suffix.length) | (suffix.is_unsigned as u8) << 2 | (suffix.is_non_decimal as u8) << 3);
+            }
+            ExprKind::FloatLiteral(v) => { self.w.byte(1); self.w.uint(v.to_bits()); }
+            ExprKind::CharLiteral(c) => { self.w.byte(2); self.w.literal(c); }
+            ExprKind::StringLiteral(s) => { self.w.byte(3); self.w.literal(s); }
+            ExprKind::BoolLiteral(b) => { self.w.byte(4); self.flag(*b); }
+            ExprKind::Nullptr => self.w.byte(5),
+            ExprKind::This => self.w.byte(6),
+            ExprKind::Name(name) => { self.w.byte(7); self.name(*name); }
+            ExprKind::TemplateId { name, args } => { self.w.byte(8); self.name(*name); self.list(args, Encoder::template_arg); }
+            ExprKind::Paren(inner) => { self.w.byte(9); self.expr(inner); }
+            ExprKind::Unary { op, operand } => { self.w.byte(10); self.w.byte(index(&UNARY_OPS, *op)); self.expr(operand); }
+            ExprKind::Binary { op, lhs, rhs } => {
+                self.w.byte(11);
+                self.w.byte(index(&BINARY_OPS, *op));
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            ExprKind::Conditional { cond, then, otherwise } => { self.w.byte(12); self.expr(cond); self.expr(then); self.expr(otherwise); }
+            ExprKind::Call { callee, args } => { self.w.byte(13); self.expr(callee); self.list(args, Encoder::expr); }
+            ExprKind::Index { base, index } => { self.w.byte(14); self.expr(base); self.expr(index); }
+            ExprKind::Member { base, member, arrow } => { self.w.byte(15); self.expr(base); self.sym(*member); self.flag(*arrow); }
+            ExprKind::Cast { kind, ty, operand } => {
+                self.w.byte(16);
+                self.w.byte(index(&CAST_KINDS, *kind));
+                self.ty(*ty);
+                self.expr(operand);
+            }
+            ExprKind::Construct { ty, args, braced } => { self.w.byte(17); self.ty(*ty); self.list(args, Encoder::expr); self.flag(*braced); }
+            ExprKind::InitList(items) => { self.w.byte(18); self.list(items, Encoder::expr); }
+            ExprKind::Designated { field, init } => { self.w.byte(19); self.sym(*field); self.expr(init); }
+            ExprKind::ParenList(items) => { self.w.byte(20); self.list(items, Encoder::expr); }
+            ExprKind::UserLiteral { literal, suffix } => { self.w.byte(21); self.expr(literal); self.sym(*suffix); }
+            ExprKind::Requires { params, requirements } => {
+                self.w.byte(22);
+                self.list(params, Encoder::param);
+                self.list(requirements, Encoder::requirement);
+            }
+            ExprKind::CoAwait(operand) => { self.w.byte(23); self.expr(operand); }
+            ExprKind::CoYield(operand) => { self.w.byte(24); self.expr(operand); }
+            ExprKind::Embed(bytes) => { self.w.byte(25); self.w.bytes(bytes); }
+            ExprKind::SizeofExpr(operand) => { self.w.byte(26); self.expr(operand); }
+            ExprKind::SizeofType(ty) => { self.w.byte(27); self.ty(*ty); }
+            ExprKind::Alignof(ty) => { self.w.byte(28); self.ty(*ty); }
+        }
+        self.span(e.span);
+    }
+
+    fn requirement(&mut self, r: &Requirement) {
+        match r {
+            Requirement::Simple(e) => { self.w.byte(0); self.expr(e); }
+            Requirement::Type(ty, span) => { self.w.byte(1); self.ty(*ty); self.span(*span); }
+            Requirement::Compound { expr, is_noexcept, constraint, span } => {
+                self.w.byte(2);
+                self.expr(expr);
+                self.flag(*is_noexcept);
+                self.opt(constraint, Encoder::expr);
+                self.span(*span);
+            }
+            Requirement::Nested(e) => { self.w.byte(3); self.expr(e); }
+        }
+    }
+}
+
+/// Reads what an `Encoder` wrote, looking symbols and types up in the
+/// tables read before it.
+struct Decoder<'b, 'a> {
+    r: Reader<'b>,
+    arena: &'a TuArena,
+    symbols: Vec<Symbol>,
+    types: Vec<TypeId>,
+    prev_start: usize,
+}
+
+impl<'a> Decoder<'_, 'a> {
+    fn flag(&mut self) -> Result<bool, DecodeError> {
+        match self.r.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DecodeError::Invalid("bad flag")),
+        }
+    }
+
+    fn variant<T: Copy>(&mut self, all: &[T], what: &'static str) -> Result<T, DecodeError> {
+        let b = self.r.byte()?;
+        all.get(b as usize).copied().ok_or(DecodeError::Invalid(what))
+    }
+
+    fn span(&mut self) -> Result<Span, DecodeError> {
+        let delta = self.r.int()?;
+        let start = (self.prev_start as i64).checked_add(delta).and_then(|s| usize::try_from(s).ok()).ok_or(DecodeError::Invalid("span out of range"))?;
+        let end = start.checked_add(self.r.uint()? as usize).ok_or(DecodeError::Invalid("span out of range"))?;
+        self.prev_start = start;
+        Ok(Span::new(start, end))
+    }
+
+    fn sym(&mut self) -> Result<Symbol, DecodeError> {
+        let i = self.r.uint()? as usize;
+        self.symbols.get(i).copied().ok_or(DecodeError::Invalid("symbol index out of range"))
+    }
+
+    fn name(&mut self) -> Result<QualifiedName, DecodeError> { self.sym().map(QualifiedName::new) }
+
+    fn ty(&mut self) -> Result<TypeId, DecodeError> {
+        let i = self.r.uint()? as usize;
+        self.types.get(i).copied().ok_or(DecodeError::Invalid("type index out of range"))
+    }
+
+    fn list<T>(&mut self, mut f: impl FnMut(&mut Self) -> Result<T, DecodeError>) -> Result<Vec<T>, DecodeError> {
+        let count = self.r.len()?;
+        (0..count).map(|_| f(self)).collect()
+    }
+
+    fn opt<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, DecodeError>) -> Result<Option<T>, DecodeError> {
+        if self.flag()? { f(self).map(Some) } else { Ok(None) }
+    }
+
+    fn opt_sym(&mut self) -> Result<Option<Symbol>, DecodeError> { self.opt(Decoder::sym) }
+
+    fn boxed<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, DecodeError>) -> Result<ArenaBox<'a, T>, DecodeError> {
+        let value = f(self)?;
+        Ok(self.arena.boxed(value))
+    }
+
+    fn specifiers(&mut self) -> Result<Specifiers, DecodeError> {
+        let storage = self.variant(&STORAGE_CLASSES, "unknown storage class")?;
+        let bits = self.r.byte()?;
+        Ok(Specifiers {
+            storage,
+            is_inline: bits & 1 != 0,
+            is_constexpr: bits & 2 != 0,
+            is_virtual: bits & 4 != 0,
+            is_explicit: bits & 8 != 0,
+            is_mutable: bits & 16 != 0,
+            is_friend: bits & 32 != 0,
+            is_extern_c: bits & 64 != 0,
+        })
+    }
+
+    fn qualifiers(&mut self) -> Result<FunctionQualifiers, DecodeError> {
+        let bits = self.r.byte()?;
+        Ok(FunctionQualifiers {
+            quals: Qualifiers { is_const: bits & 1 != 0, is_volatile: bits & 2 != 0 },
+            is_override: bits & 4 != 0,
+            is_final: bits & 8 != 0,
+            is_pure: bits & 16 != 0,
+            is_defaulted: bits & 32 != 0,
+            is_deleted: bits & 64 != 0,
+            is_noexcept: bits & 128 != 0,
+        })
+    }
+
+    fn attr(&mut self) -> Result<Attribute<'a>, DecodeError> {
+        Ok(Attribute { name: self.name()?, args: self.opt(|d| d.list(Decoder::expr))?, span: self.span()? })
+    }
+
+    fn attrs(&mut self) -> Result<Vec<Attribute<'a>>, DecodeError> { self.list(Decoder::attr) }
+
+    fn unsupported(&mut self) -> Result<UnsupportedConstruct, DecodeError> {
+        Ok(UnsupportedConstruct { category: self.variant(&CATEGORIES, "unknown construct category")?, span: self.span()? })
+    }
+
+    fn decl(&mut self) -> Result<Decl<'a>, DecodeError> {
+        Ok(match self.r.byte()? {
+            0 => Decl::Var(VarDecl {
+                name: self.sym()?,
+                ty: self.ty()?,
+                specifiers: self.specifiers()?,
+                init: self.opt(Decoder::expr)?,
+                init_style: self.variant(&INIT_STYLES, "unknown initialization style")?,
+                bit_width: self.opt(|d| Ok(BitWidth { expr: d.expr()?, bits: d.opt(|d| d.r.uint())? }))?,
+                attrs: self.attrs()?,
+                span: self.span()?,
+                name_span: self.span()?,
+            }),
+            1 => Decl::Decomposition(DecompositionDecl {
+                ty: self.ty()?,
+                specifiers: self.specifiers()?,
+                bindings: self.list(|d| Ok(Binding { name: d.sym()?, attrs: d.attrs()?, span: d.span()? }))?,
+                init: self.opt(Decoder::expr)?,
+                init_style: self.variant(&INIT_STYLES, "unknown initialization style")?,
+                attrs: self.attrs()?,
+                span: self.span()?,
+            }),
+            2 => Decl::Function(FunctionDecl {
+                name: self.sym()?,
+                kind: self.variant(&FUNCTION_KINDS, "unknown function kind")?,
+                ty: self.ty()?,
+                params: self.list(Decoder::param)?,
+                specifiers: self.specifiers()?,
+                qualifiers: self.qualifiers()?,
+                initializers: self.list(|d| {
+                    Ok(MemberInit { name: d.sym()?, args: d.list(Decoder::expr)?, braced: d.flag()?, span: d.span()? })
+                })?,
+                body: self.opt(Decoder::block)?,
+                attrs: self.attrs()?,
+                requires: self.opt(Decoder::expr)?,
+                is_coroutine: self.flag()?,
+                span: self.span()?,
+                name_span: self.span()?,
+            }),
+            3 => Decl::Typedef(TypedefDecl {
+                name: self.sym()?,
+                ty: self.ty()?,
+                is_alias: self.flag()?,
+                attrs: self.attrs()?,
+                span: self.span()?,
+                name_span: self.span()?,
+            }),
+            4 => Decl::Namespace(NamespaceDecl {
+                name: self.opt_sym()?,
+                is_inline: self.flag()?,
+                decls: self.list(Decoder::decl)?,
+                attrs: self.attrs()?,
+                span: self.span()?,
+            }),
+            5 => Decl::Using(UsingDecl { name: self.name()?, span: self.span()? }),
+            6 => Decl::UsingDirective(UsingDirective { namespace: self.name()?, span: self.span()? }),
+            7 => Decl::Class(ClassDecl {
+                key: self.variant(&CLASS_KEYS, "unknown class key")?,
+                name: self.opt_sym()?,
+                args: self.opt(|d| d.list(Decoder::template_arg))?,
+                is_final: self.flag()?,
+                bases: self.list(|d| {
+                    Ok(BaseSpecifier { name: d.sym()?, access: d.variant(&ACCESSES, "unknown access")?, is_virtual: d.flag()?, span: d.span()? })
+                })?,
+                members: self.opt(|d| d.list(|d| Ok(Member { access: d.variant(&ACCESSES, "unknown access")?, decl: d.decl()? })))?,
+                attrs: self.attrs()?,
+                span: self.span()?,
+            }),
+            8 => Decl::Enum(EnumDecl {
+                name: self.opt_sym()?,
+                is_scoped: self.flag()?,
+                underlying: self.opt(Decoder::ty)?,
+                enumerators: self.opt(|d| d.list(|d| {
+                    Ok(Enumerator { name: d.sym()?, value: d.opt(Decoder::expr)?, attrs: d.attrs()?, span: d.span()? })
+                }))?,
+                attrs: self.attrs()?,
+                span: self.span()?,
+            }),
+            9 => Decl::Template(TemplateDecl {
+                params: self.list(Decoder::template_param)?,
+                requires: self.opt(Decoder::expr)?,
+                decl: self.boxed(Decoder::decl)?,
+                span: self.span()?,
+            }),
+            10 => Decl::Concept(ConceptDecl { name: self.sym()?, constraint: self.expr()?, span: self.span()?, name_span: self.span()? }),
+            11 => Decl::Module(ModuleDecl { name: self.opt_sym()?, partition: self.opt_sym()?, is_export: self.flag()?, span: self.span()? }),
+            12 => Decl::Import(ImportDecl { module: self.sym()?, is_export: self.flag()?, span: self.span()? }),
+            13 => Decl::Export(ExportDecl { decls: self.list(Decoder::decl)?, braced: self.flag()?, span: self.span()? }),
+            14 => Decl::Unsupported(self.unsupported()?),
+            15 => Decl::Invalid(self.span()?),
+            16 => Decl::StaticAssert(StaticAssertDecl { cond: self.expr()?, message: self.opt(|d| Ok(d.r.str()?.to_string()))?, span: self.span()? }),
+            _ => return Err(DecodeError::Invalid("unknown declaration tag")),
+        })
+    }
+
+    fn param(&mut self) -> Result<ParamDecl<'a>, DecodeError> {
+        Ok(ParamDecl { name: self.opt_sym()?, ty: self.ty()?, is_pack: self.flag()?, default: self.opt(Decoder::expr)?, attrs: self.attrs()?, span: self.span()? })
+    }
+
+    fn template_param(&mut self) -> Result<TemplateParam<'a>, DecodeError> {
+        let kind = match self.r.byte()? {
+            0 => TemplateParamKind::Type,
+            1 => TemplateParamKind::Value(self.ty()?),
+            2 => TemplateParamKind::Template(self.list(Decoder::template_param)?),
+            _ => return Err(DecodeError::Invalid("unknown template parameter kind")),
+        };
+        Ok(TemplateParam {
+            kind,
+            name: self.opt_sym()?,
+            is_pack: self.flag()?,
+            constraint: self.opt(Decoder::expr)?,
+            default: self.opt(Decoder::template_arg)?,
+            span: self.span()?,
+        })
+    }
+
+    fn template_arg(&mut self) -> Result<TemplateArg<'a>, DecodeError> {
+        match self.r.byte()? {
+            0 => Ok(TemplateArg::Type(self.ty()?, self.span()?)),
+            1 => Ok(TemplateArg::Expr(self.expr()?)),
+            _ => Err(DecodeError::Invalid("unknown template argument tag")),
+        }
+    }
+
+    fn block(&mut self) -> Result<Block<'a>, DecodeError> { Ok(Block { stmts: self.list(Decoder::stmt)?, span: self.span()? }) }
+
+    fn stmt(&mut self) -> Result<Stmt<'a>, DecodeError> {
+        let boxed = |d: &mut Self| d.boxed(Decoder::stmt);
+        let kind = match self.r.byte()? {
+            0 => StmtKind::Compound(self.block()?),
+            1 => StmtKind::Null,
+            2 => StmtKind::Expr(self.expr()?),
+            3 => StmtKind::Decl(self.list(Decoder::decl)?),
+            4 => StmtKind::If { cond: self.expr()?, then: boxed(self)?, otherwise: self.opt(boxed)? },
+            5 => StmtKind::While { cond: self.expr()?, body: boxed(self)? },
+            6 => StmtKind::DoWhile { body: boxed(self)?, cond: self.expr()? },
+            7 => StmtKind::For { init: self.opt(boxed)?, cond: self.opt(Decoder::expr)?, step: self.opt(Decoder::expr)?, body: boxed(self)? },
+            8 => StmtKind::RangeFor { init: self.opt(boxed)?, decl: self.boxed(Decoder::decl)?, range: self.expr()?, body: boxed(self)? },
+            9 => StmtKind::Switch { cond: self.expr()?, body: boxed(self)? },
+            10 => StmtKind::Case { value: self.expr()?, body: boxed(self)? },
+            11 => StmtKind::Default(boxed(self)?),
+            12 => StmtKind::Break,
+            13 => StmtKind::Continue,
+            14 => StmtKind::Return(self.opt(Decoder::expr)?),
+            15 => StmtKind::CoReturn(self.opt(Decoder::expr)?),
+            16 => StmtKind::Attributed { attrs: self.attrs()?, stmt: boxed(self)? },
+            17 => StmtKind::Unsupported(self.unsupported()?),
+            18 => StmtKind::Invalid,
+            _ => return Err(DecodeError::Invalid("unknown statement tag")),
+        };
+        Ok(Stmt { kind, span: self.span()? })
+    }
+
+    fn expr(&mut self) -> Result<Expr<'a>, DecodeError> {
+        let boxed = |d: &mut Self| d.boxed(Decoder::expr);
+        let kind = match self.r.byte()? {
+            0 => {
+                let value = self.r.uint()?;
+                let bits = self.r.byte()?;
+                ExprKind::IntLiteral(value, IntSuffix { length: INT_LENGTHS[bits as usize & 3], is_unsigned: bits & 4 != 0, is_non_decimal: bits & 8 != 0 })
+            }
+            1 => ExprKind::FloatLiteral(f64::from_bits(self.r.uint()?)),
+            2 => ExprKind::CharLiteral(self.r.literal()?),
+            3 => ExprKind::StringLiteral(self.r.literal()?),
+            4 => ExprKind::BoolLiteral(self.flag()?),
+            5 => ExprKind::Nullptr,
+            6 => ExprKind::This,
+            7 => ExprKind::Name(self.name()?),
+            8 => ExprKind::TemplateId { name: self.name()?, args: self.list(Decoder::template_arg)? },
+            9 => ExprKind::Paren(boxed(self)?),
+            10 => ExprKind::Unary { op: self.variant(&UNARY_OPS, "unknown unary operator")?, operand: boxed(self)? },
+            11 => ExprKind::Binary { op: self.variant(&BINARY_OPS, "unknown binary operator")?, lhs: boxed(self)?, rhs: boxed(self)? },
+            12 => ExprKind::Conditional { cond: boxed(self)?, then: boxed(self)?, otherwise: boxed(self)? },
+            13 => ExprKind::Call { callee: boxed(self)?, args: self.list(Decoder::expr)? },
+            14 => ExprKind::Index { base: boxed(self)?, index: boxed(self)? },
+            15 => ExprKind::Member { base: boxed(self)?, member: self.sym()?, arrow: self.flag()? },
+            16 => ExprKind::Cast { kind: self.variant(&CAST_KINDS, "unknown cast kind")?, ty: self.ty()?, operand: boxed(self)? },
+            17 => ExprKind::Construct { ty: self.ty()?, args: self.list(Decoder::expr)?, braced: self.flag()? },
+            18 => ExprKind::InitList(self.list(Decoder::expr)?),
+            19 => ExprKind::Designated { field: self.sym()?, init: boxed(self)? },
+            20 => ExprKind::ParenList(self.list(Decoder::expr)?),
+            21 => ExprKind::UserLiteral { literal: boxed(self)?, suffix: self.sym()? },
+            22 => ExprKind::Requires { params: self.list(Decoder::param)?, requirements: self.list(Decoder::requirement)? },
+            23 => ExprKind::CoAwait(boxed(self)?),
+            24 => ExprKind::CoYield(boxed(self)?),
+            25 => ExprKind::Embed(self.r.bytes()?.into()),
+            26 => ExprKind::SizeofExpr(boxed(self)?),
+            27 => ExprKind::SizeofType(self.ty()?),
+            28 => ExprKind::Alignof(self.ty()?),
+            _ => return Err(DecodeError::Invalid("unknown expression tag")),
+        };
+        Ok(Expr { kind, span: self.span()? })
+    }
+
+    fn requirement(&mut self) -> Result<Requirement<'a>, DecodeError> {
+        match self.r.byte()? {
+            0 => Ok(Requirement::Simple(self.expr()?)),
+            1 => Ok(Requirement::Type(self.ty()?, self.span()?)),
+            2 => Ok(Requirement::Compound { expr: self.expr()?, is_noexcept: self.flag()?, constraint: self.opt(Decoder::expr)?, span: self.span()? }),
+            3 => Ok(Requirement::Nested(self.expr()?)),
+            _ => Err(DecodeError::Invalid("unknown requirement tag")),
+        }
+    }
+}
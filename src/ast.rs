@@ -0,0 +1,1574 @@
+//! The abstract syntax tree built by the parser.
+//!
+//! Nodes own their children. Those a node points to, rather than holding
+//! inline or in a `Vec`, are `ArenaBox`es in the `TuArena` the unit was
+//! parsed into, so a large unit costs a few big allocations rather than
+//! one per node, and its subexpressions sit next to each other in memory.
+//! Types are the interned `TypeId`s the parser builds from declarators,
+//! and names are `Symbol`s. `dump_as` renders the
+//! tree as text, JSON or Graphviz dot for `ruscom ast-dump`; `visit` walks
+//! it.
+
+use std::fmt::{self, Write};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use serde_json::json;
+
+use crate::arena::ArenaBox;
+use crate::intern::Symbol;
+use crate::lexer::token::{Literal, Span};
+use crate::source::SourceManager;
+use crate::types::{Qualifiers, Type, TypeId};
+
+/// Everything declared in one preprocessed source file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TranslationUnit<'a> {
+    pub decls: Vec<Decl<'a>>,
+    /// The whole main file. Declarations from headers lie outside it.
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decl<'a> {
+    Var(VarDecl<'a>),
+    /// `auto [a, b] = e;`
+    Decomposition(DecompositionDecl<'a>),
+    Function(FunctionDecl<'a>),
+    Typedef(TypedefDecl<'a>),
+    Namespace(NamespaceDecl<'a>),
+    /// `using a::b;`
+    Using(UsingDecl),
+    /// `using namespace a::b;`
+    UsingDirective(UsingDirective),
+    Class(ClassDecl<'a>),
+    Enum(EnumDecl<'a>),
+    Template(TemplateDecl<'a>),
+    /// `concept C = constraint;`, always inside a `Template`.
+    Concept(ConceptDecl<'a>),
+    /// `export module m;`, `module;` and the like.
+    Module(ModuleDecl),
+    /// `import m;`
+    Import(ImportDecl),
+    /// `export` and the declarations it applies to.
+    Export(ExportDecl<'a>),
+    /// `static_assert(cond, "message");`
+    StaticAssert(StaticAssertDecl<'a>),
+    /// One the parser skipped in `Options::skip_unsupported` mode.
+    Unsupported(UnsupportedConstruct),
+    /// One with a syntax error, covering the tokens skipped to recover.
+    /// Later phases ignore it, having nothing to check.
+    Invalid(Span),
+}
+
+impl Decl<'_> {
+    pub fn name(&self) -> Symbol {
+        match self {
+            Decl::Var(d) => d.name,
+            Decl::Decomposition(_) => Symbol::intern(""),
+            Decl::Function(d) => d.name,
+            Decl::Typedef(d) => d.name,
+            Decl::Namespace(d) => d.name.unwrap_or_else(|| Symbol::intern("")),
+            Decl::Using(d) => d.name.symbol(),
+            Decl::UsingDirective(d) => d.namespace.symbol(),
+            Decl::Class(d) => d.name.unwrap_or_else(|| Symbol::intern("")),
+            Decl::Enum(d) => d.name.unwrap_or_else(|| Symbol::intern("")),
+            Decl::Template(d) => d.decl.name(),
+            Decl::Concept(d) => d.name,
+            Decl::Module(d) => d.full_name().unwrap_or_else(|| Symbol::intern("")),
+            Decl::Import(d) => d.module,
+            Decl::Export(_) | Decl::StaticAssert(_) | Decl::Unsupported(_) | Decl::Invalid(_) => Symbol::intern(""),
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Decl::Var(d) => d.span,
+            Decl::Decomposition(d) => d.span,
+            Decl::Function(d) => d.span,
+            Decl::Typedef(d) => d.span,
+            Decl::Namespace(d) => d.span,
+            Decl::Using(d) => d.span,
+            Decl::UsingDirective(d) => d.span,
+            Decl::Class(d) => d.span,
+            Decl::Enum(d) => d.span,
+            Decl::Template(d) => d.span,
+            Decl::Concept(d) => d.span,
+            Decl::Module(d) => d.span,
+            Decl::Import(d) => d.span,
+            Decl::Export(d) => d.span,
+            Decl::StaticAssert(d) => d.span,
+            Decl::Unsupported(u) => u.span,
+            Decl::Invalid(span) => *span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StorageClass {
+    #[default]
+    None,
+    Static,
+    Extern,
+}
+
+/// `[[name(args)]]`. GNU `__attribute__((name))` is kept as if written
+/// `[[gnu::name]]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribute<'a> {
+    /// With its namespace, if any, like `gnu::always_inline`.
+    pub name: QualifiedName,
+    /// The argument clause, if written, as in `deprecated("old")`.
+    pub args: Option<Vec<Expr<'a>>>,
+    pub span: Span,
+}
+
+impl<'a> Attribute<'a> {
+    /// The first of `attrs` with one of `names`.
+    pub fn find<'b>(attrs: &'b [Attribute<'a>], names: &[&str]) -> Option<&'b Attribute<'a>> {
+        attrs.iter().find(|a| names.contains(&&*a.name.symbol().as_str()))
+    }
+}
+
+impl fmt::Display for Attribute<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        match &self.args {
+            Some(args) => write!(f, "({})", args.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", ")),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The declaration specifiers that are not part of the type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Specifiers {
+    pub storage: StorageClass,
+    pub is_inline: bool,
+    pub is_constexpr: bool,
+    pub is_virtual: bool,
+    pub is_explicit: bool,
+    pub is_mutable: bool,
+    pub is_friend: bool,
+    /// Declared in an `extern "C"` linkage specification, so a function or
+    /// variable is linked by its unmangled name.
+    pub is_extern_c: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VarDecl<'a> {
+    pub name: Symbol,
+    pub ty: TypeId,
+    pub specifiers: Specifiers,
+    pub init: Option<Expr<'a>>,
+    /// How `init` was written; `Copy` if there is none.
+    pub init_style: InitStyle,
+    /// A bit-field's width. An unnamed bit-field has an empty name.
+    pub bit_width: Option<BitWidth<'a>>,
+    pub attrs: Vec<Attribute<'a>>,
+    /// From the declarator to the end of the initializer.
+    pub span: Span,
+    pub name_span: Span,
+}
+
+/// The width of a bit-field, after the `:`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitWidth<'a> {
+    pub expr: Expr<'a>,
+    /// Its value; `None` if it depends on a template parameter.
+    pub bits: Option<u64>,
+}
+
+/// A structured binding declaration, which introduces a name for each
+/// element of its initializer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecompositionDecl<'a> {
+    /// `auto`, with any cv-qualifiers and `&` or `&&` written.
+    pub ty: TypeId,
+    pub specifiers: Specifiers,
+    pub bindings: Vec<Binding<'a>>,
+    /// `None` in a range-based `for`, whose elements initialize it.
+    pub init: Option<Expr<'a>>,
+    pub init_style: InitStyle,
+    pub attrs: Vec<Attribute<'a>>,
+    /// From the specifiers to the end of the initializer, or to the `]`.
+    pub span: Span,
+}
+
+/// One name in the `[...]` of a `DecompositionDecl`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding<'a> {
+    pub name: Symbol,
+    pub attrs: Vec<Attribute<'a>>,
+    pub span: Span,
+}
+
+/// The syntax of a variable's initializer, which decides among
+/// constructors and which conversions are allowed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InitStyle {
+    /// `T x = e;` or `T x = {a, b};`
+    #[default]
+    Copy,
+    /// `T x(a, b);`, where the initializer is a `ParenList`.
+    Direct,
+    /// `T x{a, b};`, where the initializer is an `InitList`.
+    List,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionDecl<'a> {
+    /// `~S` for a destructor.
+    pub name: Symbol,
+    pub kind: FunctionKind,
+    /// Always a `Type::Function`; constructors and destructors return
+    /// `void`.
+    pub ty: TypeId,
+    pub params: Vec<ParamDecl<'a>>,
+    pub specifiers: Specifiers,
+    pub qualifiers: FunctionQualifiers,
+    /// A constructor's `: a(1), b(x)`.
+    pub initializers: Vec<MemberInit<'a>>,
+    /// `None` for a declaration without a definition.
+    pub body: Option<Block<'a>>,
+    pub attrs: Vec<Attribute<'a>>,
+    /// A trailing `requires` clause.
+    pub requires: Option<Expr<'a>>,
+    /// Whether the body uses `co_await`, `co_yield` or `co_return`.
+    pub is_coroutine: bool,
+    pub span: Span,
+    pub name_span: Span,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FunctionKind {
+    #[default]
+    Normal,
+    Constructor,
+    Destructor,
+    /// `operator T()`, which converts to its return type.
+    Conversion,
+}
+
+/// What may follow a function's parameter list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FunctionQualifiers {
+    /// The cv-qualifiers of a member function's `this`.
+    pub quals: Qualifiers,
+    pub is_override: bool,
+    pub is_final: bool,
+    /// `noexcept`, or `noexcept(e)` with `e` true.
+    pub is_noexcept: bool,
+    /// `= 0`
+    pub is_pure: bool,
+    /// `= default`
+    pub is_defaulted: bool,
+    /// `= delete`
+    pub is_deleted: bool,
+}
+
+/// One entry of a constructor's member initializer list.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberInit<'a> {
+    /// A member or base class.
+    pub name: Symbol,
+    pub args: Vec<Expr<'a>>,
+    /// Written `name{args}` rather than `name(args)`.
+    pub braced: bool,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamDecl<'a> {
+    pub name: Option<Symbol>,
+    pub ty: TypeId,
+    /// A function parameter pack, `Ts... ts`.
+    pub is_pack: bool,
+    pub default: Option<Expr<'a>>,
+    pub attrs: Vec<Attribute<'a>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedefDecl<'a> {
+    pub name: Symbol,
+    pub ty: TypeId,
+    /// Written `using name = type;` rather than with `typedef`.
+    pub is_alias: bool,
+    pub attrs: Vec<Attribute<'a>>,
+    pub span: Span,
+    pub name_span: Span,
+}
+
+/// A possibly qualified name, like `x`, `a::b::x` or `::x`, interned as
+/// it is spelled.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QualifiedName(Symbol);
+
+impl QualifiedName {
+    pub fn new(spelling: Symbol) -> Self { Self(spelling) }
+
+    /// The whole name as one symbol.
+    pub fn symbol(self) -> Symbol { self.0 }
+
+    /// Whether it starts with `::`, naming the global namespace.
+    pub fn is_global(self) -> bool { self.0.as_str().starts_with("::") }
+
+    /// The names between the `::`s, outermost first.
+    pub fn segments(self) -> impl Iterator<Item = String> {
+        let text = self.0.as_str();
+        text.trim_start_matches("::").split("::").map(str::to_string).collect::<Vec<_>>().into_iter()
+    }
+
+    /// The last segment: what is named, without its qualifier.
+    pub fn last(self) -> Symbol { Symbol::intern(&self.segments().last().unwrap_or_default()) }
+
+    /// Everything before the last `::`, if there is a qualifier. The
+    /// qualifier of `::x` is the global namespace, spelled `::`.
+    pub fn qualifier(self) -> Option<QualifiedName> {
+        let text = self.0.as_str();
+        let (qualifier, _) = text.rsplit_once("::")?;
+        Some(QualifiedName(Symbol::intern(if qualifier.is_empty() { "::" } else { qualifier })))
+    }
+}
+
+impl fmt::Display for QualifiedName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(&self.0.as_str()) }
+}
+
+impl fmt::Debug for QualifiedName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "QualifiedName({:?})", self.0.as_str()) }
+}
+
+/// A namespace definition. `namespace a::b { ... }` is two of these, one
+/// inside the other, with the same span.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamespaceDecl<'a> {
+    /// `None` for an unnamed namespace.
+    pub name: Option<Symbol>,
+    /// `inline namespace`, whose members are also members of the
+    /// enclosing namespace.
+    pub is_inline: bool,
+    pub decls: Vec<Decl<'a>>,
+    /// Written after `namespace` or after the name. In `namespace a::b`
+    /// they belong to `b`.
+    pub attrs: Vec<Attribute<'a>>,
+    pub span: Span,
+}
+
+/// A module declaration. One without a name is `module;`, which opens
+/// the global module fragment, or `module :private;`, whose partition is
+/// `private`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleDecl {
+    /// Dotted as written, like `std.core`.
+    pub name: Option<Symbol>,
+    pub partition: Option<Symbol>,
+    /// `export module`: the unit is a module interface.
+    pub is_export: bool,
+    pub span: Span,
+}
+
+impl ModuleDecl {
+    /// The name other units import this one by: `m`, or `m:part` for a
+    /// partition.
+    pub fn full_name(&self) -> Option<Symbol> {
+        let name = self.name?;
+        Some(match self.partition {
+            Some(partition) => Symbol::intern(&format!("{}:{}", name, partition)),
+            None => name,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportDecl {
+    /// As written after `import`: `m`, `:part`, or a header unit's
+    /// `<vector>` or `"config.h"`.
+    pub module: Symbol,
+    /// `export import`, which passes the module on to importers.
+    pub is_export: bool,
+    pub span: Span,
+}
+
+/// `export decl`, or `export { decls }` if `braced`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportDecl<'a> {
+    pub decls: Vec<Decl<'a>>,
+    pub braced: bool,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsingDecl {
+    /// The name brought in, like `::size_t`.
+    pub name: QualifiedName,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsingDirective {
+    pub namespace: QualifiedName,
+    pub span: Span,
+}
+
+/// A declaration or statement the parser does not support yet, skipped
+/// whole, with its brackets balanced, instead of reported as an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedConstruct {
+    pub category: ConstructCategory,
+    pub span: Span,
+}
+
+/// What an unsupported construct most likely is, guessed from the
+/// keywords and punctuation in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ConstructCategory {
+    /// `static_assert(...)`
+    StaticAssert,
+    /// `extern "Java" { ... }`, with a language other than C or C++.
+    LinkageSpecification,
+    /// `asm(...)`
+    InlineAssembly,
+    /// `using enum E;`
+    UsingEnum,
+    /// `if constexpr (...)`
+    ConstexprIf,
+    /// `try`, `catch` and `throw`.
+    Exceptions,
+    /// `goto` and labeled statements.
+    Labels,
+    Lambda,
+    /// `decltype(...)`
+    Decltype,
+    /// `new` and `delete` expressions.
+    NewDelete,
+    /// `...` after a pattern or in a fold expression.
+    PackExpansion,
+    Other,
+}
+
+impl fmt::Display for ConstructCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConstructCategory::StaticAssert => "static_assert",
+            ConstructCategory::LinkageSpecification => "linkage specification",
+            ConstructCategory::InlineAssembly => "inline assembly",
+            ConstructCategory::UsingEnum => "using enum",
+            ConstructCategory::ConstexprIf => "if constexpr",
+            ConstructCategory::Exceptions => "exception handling",
+            ConstructCategory::Labels => "goto and labels",
+            ConstructCategory::Lambda => "lambda",
+            ConstructCategory::Decltype => "decltype",
+            ConstructCategory::NewDelete => "new or delete",
+            ConstructCategory::PackExpansion => "pack expansion",
+            ConstructCategory::Other => "other",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClassKey {
+    Struct,
+    Class,
+    Union,
+}
+
+impl ClassKey {
+    pub fn keyword(self) -> &'static str {
+        match self {
+            ClassKey::Struct => "struct",
+            ClassKey::Class => "class",
+            ClassKey::Union => "union",
+        }
+    }
+
+    /// The access of members and bases not given one explicitly.
+    pub fn default_access(self) -> Access {
+        if self == ClassKey::Class { Access::Private } else { Access::Public }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Public,
+    Protected,
+    Private,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Access::Public => "public",
+            Access::Protected => "protected",
+            Access::Private => "private",
+        })
+    }
+}
+
+/// A class, struct or union: a definition, or a forward declaration like
+/// `struct S;`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassDecl<'a> {
+    pub key: ClassKey,
+    /// `None` for an anonymous class.
+    pub name: Option<Symbol>,
+    /// The arguments of an explicit or partial specialization: `<int>` in
+    /// `template<> struct S<int>`.
+    pub args: Option<Vec<TemplateArg<'a>>>,
+    /// Written `final`, so it cannot be a base class.
+    pub is_final: bool,
+    pub bases: Vec<BaseSpecifier>,
+    /// `None` for a forward declaration.
+    pub members: Option<Vec<Member<'a>>>,
+    pub attrs: Vec<Attribute<'a>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BaseSpecifier {
+    pub name: Symbol,
+    pub access: Access,
+    pub is_virtual: bool,
+    pub span: Span,
+}
+
+/// A member declaration and the access it was declared with. Static data
+/// members are `Var`s with `StorageClass::Static`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member<'a> {
+    pub access: Access,
+    pub decl: Decl<'a>,
+}
+
+/// An enumeration: a definition, or an opaque declaration like
+/// `enum class E : short;`. Enumerator values are assigned by sema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumDecl<'a> {
+    /// `None` for an anonymous enum.
+    pub name: Option<Symbol>,
+    /// `enum class` or `enum struct`.
+    pub is_scoped: bool,
+    /// The type after the `:`, if given.
+    pub underlying: Option<TypeId>,
+    /// `None` for an opaque declaration.
+    pub enumerators: Option<Vec<Enumerator<'a>>>,
+    pub attrs: Vec<Attribute<'a>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Enumerator<'a> {
+    pub name: Symbol,
+    /// The `= value`, if written.
+    pub value: Option<Expr<'a>>,
+    pub attrs: Vec<Attribute<'a>>,
+    pub span: Span,
+}
+
+/// `template<params> decl`. Nothing is instantiated yet; the template is
+/// kept as written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateDecl<'a> {
+    /// Empty for `template<>`.
+    pub params: Vec<TemplateParam<'a>>,
+    /// The `requires` clause after the parameters.
+    pub requires: Option<Expr<'a>>,
+    pub decl: ArenaBox<'a, Decl<'a>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaticAssertDecl<'a> {
+    pub cond: Expr<'a>,
+    /// The string literal after the condition, if there is one.
+    pub message: Option<String>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConceptDecl<'a> {
+    pub name: Symbol,
+    pub constraint: Expr<'a>,
+    pub span: Span,
+    pub name_span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateParam<'a> {
+    pub kind: TemplateParamKind<'a>,
+    pub name: Option<Symbol>,
+    /// `typename... Ts`
+    pub is_pack: bool,
+    /// The concept of a constrained type parameter, with any arguments
+    /// after the first: `C` in `template<C T>`, `D<int>` in
+    /// `template<D<int> T>`.
+    pub constraint: Option<Expr<'a>>,
+    pub default: Option<TemplateArg<'a>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateParamKind<'a> {
+    /// `typename T` or `class T`.
+    Type,
+    /// A non-type parameter like `int N`, with its type.
+    Value(TypeId),
+    /// `template<...> class TT`, with the parameters it takes.
+    Template(Vec<TemplateParam<'a>>),
+}
+
+/// An argument between the `<>` of a template-id, or a parameter's default.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateArg<'a> {
+    /// With the span of the type as written.
+    Type(TypeId, Span),
+    Expr(Expr<'a>),
+}
+
+impl TemplateArg<'_> {
+    pub fn span(&self) -> Span {
+        match self {
+            TemplateArg::Type(_, span) => *span,
+            TemplateArg::Expr(e) => e.span,
+        }
+    }
+}
+
+impl fmt::Display for TemplateArg<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateArg::Type(ty, _) => write!(f, "{}", ty),
+            TemplateArg::Expr(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// A `{ ... }` statement list, such as a function body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block<'a> {
+    pub stmts: Vec<Stmt<'a>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stmt<'a> {
+    pub kind: StmtKind<'a>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtKind<'a> {
+    Compound(Block<'a>),
+    /// A lone `;`.
+    Null,
+    Expr(Expr<'a>),
+    /// A block-scope declaration, which may declare several names.
+    Decl(Vec<Decl<'a>>),
+    If { cond: Expr<'a>, then: ArenaBox<'a, Stmt<'a>>, otherwise: Option<ArenaBox<'a, Stmt<'a>>> },
+    While { cond: Expr<'a>, body: ArenaBox<'a, Stmt<'a>> },
+    DoWhile { body: ArenaBox<'a, Stmt<'a>>, cond: Expr<'a> },
+    /// `init` is an expression or declaration statement.
+    For { init: Option<ArenaBox<'a, Stmt<'a>>>, cond: Option<Expr<'a>>, step: Option<Expr<'a>>, body: ArenaBox<'a, Stmt<'a>> },
+    /// `for (init; decl : range) body`, where `decl` is a `Var` or
+    /// `Decomposition` without an initializer and `range` may be a braced
+    /// list.
+    RangeFor { init: Option<ArenaBox<'a, Stmt<'a>>>, decl: ArenaBox<'a, Decl<'a>>, range: Expr<'a>, body: ArenaBox<'a, Stmt<'a>> },
+    Switch { cond: Expr<'a>, body: ArenaBox<'a, Stmt<'a>> },
+    Case { value: Expr<'a>, body: ArenaBox<'a, Stmt<'a>> },
+    Default(ArenaBox<'a, Stmt<'a>>),
+    Break,
+    Continue,
+    Return(Option<Expr<'a>>),
+    /// `co_return;` or `co_return e;`, which makes the function a
+    /// coroutine.
+    CoReturn(Option<Expr<'a>>),
+    /// A statement with attributes, like `[[fallthrough]];`.
+    Attributed { attrs: Vec<Attribute<'a>>, stmt: ArenaBox<'a, Stmt<'a>> },
+    /// One the parser skipped in `Options::skip_unsupported` mode.
+    Unsupported(UnsupportedConstruct),
+    /// One with a syntax error, like `Decl::Invalid`.
+    Invalid,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr<'a> {
+    pub kind: ExprKind<'a>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind<'a> {
+    IntLiteral(u64, IntSuffix),
+    FloatLiteral(f64),
+    CharLiteral(Literal),
+    StringLiteral(Literal),
+    BoolLiteral(bool),
+    Nullptr,
+    This,
+    Name(QualifiedName),
+    /// A function or variable template's name with arguments: `max<int>`.
+    TemplateId { name: QualifiedName, args: Vec<TemplateArg<'a>> },
+    /// Kept so the expression prints as written.
+    Paren(ArenaBox<'a, Expr<'a>>),
+    Unary { op: UnaryOp, operand: ArenaBox<'a, Expr<'a>> },
+    /// Includes assignments and the comma operator.
+    Binary { op: BinaryOp, lhs: ArenaBox<'a, Expr<'a>>, rhs: ArenaBox<'a, Expr<'a>> },
+    Conditional { cond: ArenaBox<'a, Expr<'a>>, then: ArenaBox<'a, Expr<'a>>, otherwise: ArenaBox<'a, Expr<'a>> },
+    Call { callee: ArenaBox<'a, Expr<'a>>, args: Vec<Expr<'a>> },
+    Index { base: ArenaBox<'a, Expr<'a>>, index: ArenaBox<'a, Expr<'a>> },
+    /// `base.member`, or `base->member` if `arrow`.
+    Member { base: ArenaBox<'a, Expr<'a>>, member: Symbol, arrow: bool },
+    /// `(T)e` and the named casts.
+    Cast { kind: CastKind, ty: TypeId, operand: ArenaBox<'a, Expr<'a>> },
+    /// Functional notation: `T(args)`, or `T{args}` if `braced`.
+    Construct { ty: TypeId, args: Vec<Expr<'a>>, braced: bool },
+    /// A braced initializer list: `{1, 2, 3}`.
+    InitList(Vec<Expr<'a>>),
+    /// A designated initializer in a list: `.field = value` or
+    /// `.field{value}`, which has an `InitList` as its `init`.
+    Designated { field: Symbol, init: ArenaBox<'a, Expr<'a>> },
+    /// The parenthesized initializer of `T x(a, b);`.
+    ParenList(Vec<Expr<'a>>),
+    /// A literal with a user-defined suffix, like `12_km` or `"abc"s`,
+    /// which calls `operator""` followed by the suffix.
+    UserLiteral { literal: ArenaBox<'a, Expr<'a>>, suffix: Symbol },
+    /// `requires (params) { requirements }`, true if the requirements
+    /// are met.
+    Requires { params: Vec<ParamDecl<'a>>, requirements: Vec<Requirement<'a>> },
+    /// `co_await e`, which suspends the coroutine until `e` is ready.
+    CoAwait(ArenaBox<'a, Expr<'a>>),
+    /// `co_yield e`, which suspends the coroutine with a value.
+    CoYield(ArenaBox<'a, Expr<'a>>),
+    /// The bytes of an `#embed`ded file, which stand for a list of their
+    /// values.
+    Embed(Arc<[u8]>),
+    SizeofExpr(ArenaBox<'a, Expr<'a>>),
+    SizeofType(TypeId),
+    Alignof(TypeId),
+}
+
+/// One requirement in a requires-expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Requirement<'a> {
+    /// `e;`: the expression is valid.
+    Simple(Expr<'a>),
+    /// `typename T::type;`: the type is valid.
+    Type(TypeId, Span),
+    /// `{ e } noexcept -> C;`, where `C` constrains the type of `e`.
+    Compound { expr: Expr<'a>, is_noexcept: bool, constraint: Option<Expr<'a>>, span: Span },
+    /// `requires constraint;`
+    Nested(Expr<'a>),
+}
+
+impl fmt::Display for Requirement<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Requirement::Simple(e) => write!(f, "{};", e),
+            Requirement::Type(ty, _) => write!(f, "typename {};", ty),
+            Requirement::Compound { expr, is_noexcept, constraint, .. } => {
+                write!(f, "{{ {} }}", expr)?;
+                if *is_noexcept { f.write_str(" noexcept")?; }
+                if let Some(c) = constraint { write!(f, " -> {}", c)?; }
+                f.write_str(";")
+            }
+            Requirement::Nested(e) => write!(f, "requires {};", e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Plus,
+    Minus,
+    Not,
+    BitNot,
+    Deref,
+    AddressOf,
+    PreIncrement,
+    PreDecrement,
+    PostIncrement,
+    PostDecrement,
+}
+
+impl UnaryOp {
+    pub fn spelling(self) -> &'static str {
+        match self {
+            UnaryOp::Plus => "+",
+            UnaryOp::Minus => "-",
+            UnaryOp::Not => "!",
+            UnaryOp::BitNot => "~",
+            UnaryOp::Deref => "*",
+            UnaryOp::AddressOf => "&",
+            UnaryOp::PreIncrement | UnaryOp::PostIncrement => "++",
+            UnaryOp::PreDecrement | UnaryOp::PostDecrement => "--",
+        }
+    }
+
+    pub fn is_postfix(self) -> bool { matches!(self, UnaryOp::PostIncrement | UnaryOp::PostDecrement) }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    /// `.*`
+    PtrMem,
+    /// `->*`
+    PtrMemArrow,
+    Mul,
+    Div,
+    Rem,
+    Add,
+    Sub,
+    Shl,
+    Shr,
+    /// `<=>`
+    ThreeWay,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Eq,
+    Ne,
+    BitAnd,
+    BitXor,
+    BitOr,
+    LogicalAnd,
+    LogicalOr,
+    Assign,
+    MulAssign,
+    DivAssign,
+    RemAssign,
+    AddAssign,
+    SubAssign,
+    ShlAssign,
+    ShrAssign,
+    AndAssign,
+    XorAssign,
+    OrAssign,
+    Comma,
+}
+
+impl BinaryOp {
+    pub fn spelling(self) -> &'static str {
+        match self {
+            BinaryOp::PtrMem => ".*",
+            BinaryOp::PtrMemArrow => "->*",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Shl => "<<",
+            BinaryOp::Shr => ">>",
+            BinaryOp::ThreeWay => "<=>",
+            BinaryOp::Lt => "<",
+            BinaryOp::Gt => ">",
+            BinaryOp::Le => "<=",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::BitXor => "^",
+            BinaryOp::BitOr => "|",
+            BinaryOp::LogicalAnd => "&&",
+            BinaryOp::LogicalOr => "||",
+            BinaryOp::Assign => "=",
+            BinaryOp::MulAssign => "*=",
+            BinaryOp::DivAssign => "/=",
+            BinaryOp::RemAssign => "%=",
+            BinaryOp::AddAssign => "+=",
+            BinaryOp::SubAssign => "-=",
+            BinaryOp::ShlAssign => "<<=",
+            BinaryOp::ShrAssign => ">>=",
+            BinaryOp::AndAssign => "&=",
+            BinaryOp::XorAssign => "^=",
+            BinaryOp::OrAssign => "|=",
+            BinaryOp::Comma => ",",
+        }
+    }
+
+    pub fn is_assignment(self) -> bool {
+        matches!(self, BinaryOp::Assign | BinaryOp::MulAssign | BinaryOp::DivAssign | BinaryOp::RemAssign
+            | BinaryOp::AddAssign | BinaryOp::SubAssign | BinaryOp::ShlAssign | BinaryOp::ShrAssign
+            | BinaryOp::AndAssign | BinaryOp::XorAssign | BinaryOp::OrAssign)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CastKind {
+    /// `(T)e`
+    CStyle,
+    Static,
+    Dynamic,
+    Const,
+    Reinterpret,
+}
+
+impl CastKind {
+    /// The keyword of a named cast.
+    pub fn keyword(self) -> Option<&'static str> {
+        match self {
+            CastKind::CStyle => None,
+            CastKind::Static => Some("static_cast"),
+            CastKind::Dynamic => Some("dynamic_cast"),
+            CastKind::Const => Some("const_cast"),
+            CastKind::Reinterpret => Some("reinterpret_cast"),
+        }
+    }
+}
+
+/// What an integer literal's spelling says about its type besides its
+/// value: its suffix, and whether it is decimal ([lex.icon]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct IntSuffix {
+    /// `u` or `U`.
+    pub is_unsigned: bool,
+    pub length: IntLength,
+    /// Written in octal, hexadecimal or binary, so it may have an unsigned
+    /// type without the `u`.
+    pub is_non_decimal: bool,
+}
+
+/// The length part of an integer literal's suffix.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum IntLength {
+    #[default]
+    None,
+    /// `l` or `L`.
+    Long,
+    /// `ll` or `LL`.
+    LongLong,
+    /// `z` or `Z`, for `size_t` and its signed counterpart.
+    Size,
+}
+
+impl IntSuffix {
+    /// The suffix of the pp-number `text`, if it is a valid one.
+    pub fn of(text: &str) -> Option<Self> {
+        let lower = text.to_ascii_lowercase();
+        let digits = lower.trim_end_matches(['u', 'l', 'z']);
+        let is_non_decimal = digits.len() > 1 && digits.starts_with('0');
+        let suffix = &text[digits.len()..];
+        // `lL` and `Ll` are not suffixes, nor is more than one `u`.
+        let (is_unsigned, length) = match suffix.replace(['u', 'U'], "").as_str() {
+            _ if suffix.matches(['u', 'U']).count() > 1 => return None,
+            "" => (!suffix.is_empty(), IntLength::None),
+            "l" | "L" => (suffix.len() == 2, IntLength::Long),
+            "ll" | "LL" => (suffix.len() == 3, IntLength::LongLong),
+            "z" | "Z" => (suffix.len() == 2, IntLength::Size),
+            _ => return None,
+        };
+        // The `u` comes first or last, never between the others.
+        if is_unsigned && length == IntLength::LongLong && !suffix.starts_with(['u', 'U']) && !suffix.ends_with(['u', 'U']) { return None; }
+        Some(Self { is_unsigned, length, is_non_decimal })
+    }
+
+    /// The type of a literal with this suffix and `value`: the first of
+    /// the types [lex.icon] lists for it that can represent the value, or
+    /// `unsigned long long` if none can.
+    pub fn ty(self, value: u64) -> Type {
+        let candidates: &[Type] = match (self.is_unsigned, self.length) {
+            (false, IntLength::None) if self.is_non_decimal => &[Type::Int, Type::UnsignedInt, Type::Long, Type::UnsignedLong, Type::LongLong, Type::UnsignedLongLong],
+            (false, IntLength::None) => &[Type::Int, Type::Long, Type::LongLong],
+            (false, IntLength::Long) if self.is_non_decimal => &[Type::Long, Type::UnsignedLong, Type::LongLong, Type::UnsignedLongLong],
+            (false, IntLength::Long) => &[Type::Long, Type::LongLong],
+            (false, IntLength::LongLong) if self.is_non_decimal => &[Type::LongLong, Type::UnsignedLongLong],
+            (false, IntLength::LongLong) => &[Type::LongLong],
+            (false, IntLength::Size) => &[Type::Long],
+            (true, IntLength::None) => &[Type::UnsignedInt, Type::UnsignedLong, Type::UnsignedLongLong],
+            (true, IntLength::Long) => &[Type::UnsignedLong, Type::UnsignedLongLong],
+            (true, IntLength::LongLong) => &[Type::UnsignedLongLong],
+            (true, IntLength::Size) => &[Type::UnsignedLong],
+        };
+        let fits = |ty: &&Type| TypeId::intern((*ty).clone()).integer_range().is_some_and(|(_, max)| value as i128 <= max);
+        candidates.iter().find(fits).cloned().unwrap_or(Type::UnsignedLongLong)
+    }
+}
+
+impl fmt::Display for IntSuffix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_unsigned { f.write_str("u")?; }
+        f.write_str(match self.length {
+            IntLength::None => "",
+            IntLength::Long => "l",
+            IntLength::LongLong => "ll",
+            IntLength::Size => "z",
+        })
+    }
+}
+
+/// Prints the expression as C++ source. Parentheses appear where the
+/// source had them, so reparsing the output gives the same tree.
+impl fmt::Display for Expr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |args: &[Expr]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", ");
+        match &self.kind {
+            ExprKind::IntLiteral(v, suffix) if suffix.is_non_decimal => write!(f, "{:#x}{}", v, suffix),
+            ExprKind::IntLiteral(v, suffix) => write!(f, "{}{}", v, suffix),
+            ExprKind::FloatLiteral(v) => write!(f, "{:?}", v),
+            ExprKind::CharLiteral(c) => f.write_str(&c.spelling('\'')),
+            ExprKind::StringLiteral(s) => f.write_str(&s.spelling('"')),
+            ExprKind::BoolLiteral(b) => write!(f, "{}", b),
+            ExprKind::Nullptr => f.write_str("nullptr"),
+            ExprKind::This => f.write_str("this"),
+            ExprKind::Name(n) => write!(f, "{}", n),
+            ExprKind::TemplateId { name, args } => write!(f, "{}<{}>", name, args.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", ")),
+            ExprKind::Paren(e) => write!(f, "({})", e),
+            ExprKind::Unary { op, operand } if op.is_postfix() => write!(f, "{}{}", operand, op.spelling()),
+            ExprKind::Unary { op, operand } => {
+                // `- -x`, not `--x`.
+                let operand = operand.to_string();
+                let gap = if operand.starts_with(op.spelling().chars().last().unwrap_or(' ')) { " " } else { "" };
+                write!(f, "{}{}{}", op.spelling(), gap, operand)
+            }
+            ExprKind::Binary { op: BinaryOp::Comma, lhs, rhs } => write!(f, "{}, {}", lhs, rhs),
+            ExprKind::Binary { op, lhs, rhs } => write!(f, "{} {} {}", lhs, op.spelling(), rhs),
+            ExprKind::Conditional { cond, then, otherwise } => write!(f, "{} ? {} : {}", cond, then, otherwise),
+            ExprKind::Call { callee, args } => write!(f, "{}({})", callee, list(args)),
+            ExprKind::Index { base, index } => write!(f, "{}[{}]", base, index),
+            ExprKind::Member { base, member, arrow } => write!(f, "{}{}{}", base, if *arrow { "->" } else { "." }, member),
+            ExprKind::Cast { kind, ty, operand } => match kind.keyword() {
+                Some(keyword) => write!(f, "{}<{}>({})", keyword, ty, operand),
+                None => write!(f, "({}){}", ty, operand),
+            },
+            ExprKind::Construct { ty, args, braced: false } => write!(f, "{}({})", ty, list(args)),
+            ExprKind::Construct { ty, args, braced: true } => write!(f, "{}{{{}}}", ty, list(args)),
+            ExprKind::InitList(items) => write!(f, "{{{}}}", list(items)),
+            ExprKind::Designated { field, init } if matches!(init.kind, ExprKind::InitList(_)) => write!(f, ".{}{}", field, init),
+            ExprKind::Designated { field, init } => write!(f, ".{} = {}", field, init),
+            ExprKind::ParenList(args) => write!(f, "({})", list(args)),
+            ExprKind::UserLiteral { literal, suffix } => write!(f, "{}{}", literal, suffix),
+            ExprKind::Requires { params, requirements } => {
+                f.write_str("requires ")?;
+                if !params.is_empty() {
+                    let params: Vec<String> = params.iter().map(|p| p.name.map_or(p.ty.to_string(), |n| format!("{} {}", p.ty, n))).collect();
+                    write!(f, "({}) ", params.join(", "))?;
+                }
+                write!(f, "{{ {} }}", requirements.iter().map(|r| r.to_string()).collect::<Vec<_>>().join(" "))
+            }
+            ExprKind::CoAwait(e) => write!(f, "co_await {}", e),
+            ExprKind::CoYield(e) => write!(f, "co_yield {}", e),
+            ExprKind::Embed(bytes) => write!(f, "{}", bytes.iter().map(|b| b.to_string()).collect::<Vec<_>>().join(", ")),
+            ExprKind::SizeofExpr(e) => write!(f, "sizeof {}", e),
+            ExprKind::SizeofType(ty) => write!(f, "sizeof({})", ty),
+            ExprKind::Alignof(ty) => write!(f, "alignof({})", ty),
+        }
+    }
+}
+
+/// One line of `dump` output and the lines nested under it.
+struct DumpNode {
+    label: String,
+    /// The source the node covers; `None` for placeholders.
+    span: Option<Span>,
+    children: Vec<DumpNode>,
+}
+
+impl DumpNode {
+    fn leaf(label: String, span: Span) -> Self { Self { label, span: Some(span), children: Vec::new() } }
+
+    fn render(&self, out: &mut String, prefix: &str, last: bool, root: bool) {
+        let (branch, next) = match (root, last) {
+            (true, _) => ("", String::new()),
+            (false, true) => ("`-", format!("{}  ", prefix)),
+            (false, false) => ("|-", format!("{}| ", prefix)),
+        };
+        let _ = writeln!(out, "{}{}{}", prefix, branch, self.label);
+        for (i, child) in self.children.iter().enumerate() {
+            child.render(out, &next, i + 1 == self.children.len(), false);
+        }
+    }
+
+    /// The node's kind: the first word of its label.
+    fn kind(&self) -> &str { self.label.split(' ').next().unwrap_or_default() }
+
+    /// The label split into its kind, the details after it, and the
+    /// `line:col` position it ends with, if any.
+    fn parts(&self) -> (&str, &str, Option<&str>) {
+        let (rest, loc) = match self.label.strip_suffix('>').and_then(|l| l.rsplit_once(" <")) {
+            Some((rest, loc)) => (rest, Some(loc)),
+            None => (self.label.as_str(), None),
+        };
+        let (kind, detail) = rest.split_once(' ').unwrap_or((rest, ""));
+        (kind, detail, loc)
+    }
+
+    /// The node as a JSON object in the shape of clang's `-ast-dump=json`:
+    /// `kind` always, and `detail`, `loc`, `range` and `inner` where the
+    /// node has them. `range` ends just past the node's last character.
+    fn to_json(&self, position: &dyn Fn(usize) -> Option<serde_json::Value>) -> serde_json::Value {
+        let (kind, detail, loc) = self.parts();
+        let mut node = serde_json::Map::new();
+        node.insert("kind".into(), kind.into());
+        if !detail.is_empty() { node.insert("detail".into(), detail.into()); }
+        if let Some((line, col)) = loc.and_then(|l| l.split_once(':')) {
+            if let (Ok(line), Ok(col)) = (line.parse::<usize>(), col.parse::<usize>()) { node.insert("loc".into(), json!({ "line": line, "col": col })); }
+        }
+        if let Some(span) = self.span {
+            if let (Some(begin), Some(end)) = (position(span.start), position(span.end)) { node.insert("range".into(), json!({ "begin": begin, "end": end })); }
+        }
+        if !self.children.is_empty() { node.insert("inner".into(), self.children.iter().map(|c| c.to_json(position)).collect()); }
+        node.into()
+    }
+
+    /// Appends the range of each node in the tree to its label.
+    fn add_ranges(&mut self, range: &dyn Fn(Span) -> String) {
+        if let Some(span) = self.span {
+            self.label.push(' ');
+            self.label.push_str(&range(span));
+        }
+        for child in &mut self.children { child.add_ranges(range); }
+    }
+
+    /// Writes the node as `n<id>` and its children after it, each with an
+    /// edge from its parent. Returns the next free id.
+    fn render_dot(&self, out: &mut String, id: usize) -> usize {
+        let label = self.label.replace('\\', "\\\\").replace('"', "\\\"");
+        let _ = writeln!(out, "  n{} [label=\"{}\"];", id, label);
+        let mut next = id + 1;
+        for child in &self.children {
+            let _ = writeln!(out, "  n{} -> n{};", id, next);
+            next = child.render_dot(out, next);
+        }
+        next
+    }
+
+    /// Spans are only compared if `same_file` says they can be.
+    fn verify(&self, same_file: &dyn Fn(Span, Span) -> bool, found: &mut Vec<Malformed>) {
+        let mut previous: Option<(&DumpNode, Span)> = None;
+        for child in &self.children {
+            child.verify(same_file, found);
+            let Some(span) = child.span else { continue };
+            let mut report = |problem: String| found.push(Malformed { node: child.kind().to_string(), span, problem });
+            if span.start > span.end { report("ends before it starts".into()); }
+            if let Some(parent) = self.span.filter(|&p| same_file(span, p) && (span.start < p.start || span.end > p.end)) {
+                report(format!("is not inside its parent `{}` at {}..{}", self.kind(), parent.start, parent.end));
+            }
+            if let Some((prev, prev_span)) = previous.filter(|&(_, p)| same_file(span, p) && (span.start < p.start || span.end < p.end)) {
+                report(format!("comes before its previous sibling `{}` at {}..{}", prev.kind(), prev_span.start, prev_span.end));
+            }
+            previous = Some((child, span));
+        }
+    }
+}
+
+/// How `dump_as` renders the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DumpOptions {
+    pub format: DumpFormat,
+    /// Also print the `[line:col, line:col)` range each node covers in
+    /// the tree and dot formats; JSON always has it.
+    pub spans: bool,
+}
+
+/// The syntax `dump_as` writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DumpFormat {
+    /// An indented tree, one node per line, like clang's `-ast-dump`.
+    #[default]
+    Tree,
+    /// One JSON object per node, children under `inner`.
+    Json,
+    /// A Graphviz digraph with an edge from each node to its children.
+    Dot,
+}
+
+impl FromStr for DumpFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tree" => Ok(DumpFormat::Tree),
+            "json" => Ok(DumpFormat::Json),
+            "dot" => Ok(DumpFormat::Dot),
+            other => Err(format!("unknown AST dump format `{}` (expected tree, json or dot)", other)),
+        }
+    }
+}
+
+/// Renders `tu` as an indented tree, one node per line, with `line:col`
+/// positions looked up in `sources`.
+pub fn dump(tu: &TranslationUnit, sources: &SourceManager) -> String {
+    dump_as(tu, sources, DumpOptions::default())
+}
+
+/// Renders `tu` as `options` say. Every format has the same nodes with the
+/// same labels.
+pub fn dump_as(tu: &TranslationUnit, sources: &SourceManager, options: DumpOptions) -> String {
+    let position = |pos: usize| sources.location(pos).map_or_else(|| "<invalid>".into(), |l| format!("{}:{}", l.line, l.column));
+    let json_position = |pos: usize| sources.location(pos).map(|l| json!({ "line": l.line, "col": l.column }));
+    render(tu, options, &position, &json_position)
+}
+
+/// Renders `tu` without the source files it was parsed from, as for a
+/// tree read back from an artifact: positions are byte offsets.
+pub fn dump_offsets(tu: &TranslationUnit, options: DumpOptions) -> String {
+    render(tu, options, &|pos| pos.to_string(), &|pos| Some(json!({ "offset": pos })))
+}
+
+fn render(tu: &TranslationUnit, options: DumpOptions, position: &dyn Fn(usize) -> String, json_position: &dyn Fn(usize) -> Option<serde_json::Value>) -> String {
+    let at = |span: Span| position(span.start);
+    let decls = tu.decls.iter().map(|d| decl_node(d, &at)).collect();
+    let mut root = DumpNode { label: "TranslationUnit".into(), span: Some(tu.span), children: decls };
+    if options.spans && options.format != DumpFormat::Json {
+        root.add_ranges(&|span| format!("[{}, {})", position(span.start), position(span.end)));
+    }
+    let mut out = String::new();
+    match options.format {
+        DumpFormat::Tree => root.render(&mut out, "", true, true),
+        DumpFormat::Json => {
+            let _ = writeln!(out, "{:#}", root.to_json(json_position));
+        }
+        DumpFormat::Dot => {
+            out.push_str("digraph AST {\n  node [shape=box, fontname=\"monospace\"];\n");
+            root.render_dot(&mut out, 0);
+            out.push_str("}\n");
+        }
+    }
+    out
+}
+
+/// Renders `decl` as `dump` does but without positions, one line per node
+/// indented by its depth, so that two declarations written alike render
+/// alike wherever they are.
+pub fn structure(decl: &Decl) -> Vec<String> {
+    fn lines(node: &DumpNode, depth: usize, out: &mut Vec<String>) {
+        out.push(format!("{}{}", "  ".repeat(depth), node.label.strip_suffix(" <>").unwrap_or(&node.label)));
+        for child in &node.children { lines(child, depth + 1, out); }
+    }
+    let mut out = Vec::new();
+    lines(&decl_node(decl, &|_| String::new()), 0, &mut out);
+    out
+}
+
+/// A node whose span disagrees with its place in the tree, which means the
+/// parser built it wrong.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Malformed {
+    /// The kind of node, as `dump` labels it.
+    pub node: String,
+    pub span: Span,
+    pub problem: String,
+}
+
+/// Checks the tree's spans: each node's must lie within its parent's, and
+/// neither start nor end before its previous sibling's. Siblings may
+/// overlap, as declarators sharing their specifiers and tokens from one
+/// macro expansion do, and spans in different files are not compared.
+/// Sema keeps no references into the tree yet, so there is nothing else
+/// to check.
+pub fn verify(tu: &TranslationUnit, sources: &SourceManager) -> Vec<Malformed> {
+    let at = |_: Span| String::new();
+    let file = |pos: usize| sources.file_at(pos).map(|f| f.start);
+    let same_file = |a: Span, b: Span| file(a.start) == file(b.start);
+    let root = DumpNode { label: "TranslationUnit".into(), span: Some(tu.span), children: tu.decls.iter().map(|d| decl_node(d, &at)).collect() };
+    let mut found = Vec::new();
+    root.verify(&same_file, &mut found);
+    found
+}
+
+fn decl_node(decl: &Decl, at: &dyn Fn(Span) -> String) -> DumpNode {
+    member_node(decl, None, at)
+}
+
+/// `access` is set for class members, which are labeled as such.
+fn member_node(decl: &Decl, access: Option<Access>, at: &dyn Fn(Span) -> String) -> DumpNode {
+    let expr = |e: &Expr| expr_node(e, at);
+    let member_access = access;
+    let access = access.map(|a| format!(" {}", a)).unwrap_or_default();
+    let in_class = !access.is_empty();
+    match decl {
+        Decl::Var(v) => {
+            let what = if in_class && v.specifiers.storage != StorageClass::Static { "FieldDecl" } else { "VarDecl" };
+            DumpNode {
+                label: format!("{}{}{} '{}'{}{}{}{} <{}>", what, if v.name.as_str().is_empty() { "" } else { " " }, v.name, v.ty, specifiers(&v.specifiers), init_style(v.init_style), access, attributes(&v.attrs), at(v.name_span)),
+                span: Some(v.span),
+                children: v.bit_width.iter().map(|w| &w.expr).chain(&v.init).map(expr).collect(),
+            }
+        }
+        Decl::Decomposition(d) => {
+            let mut children: Vec<DumpNode> = d.bindings.iter().map(|b| DumpNode::leaf(format!("BindingDecl {}{} <{}>", b.name, attributes(&b.attrs), at(b.span)), b.span)).collect();
+            children.extend(d.init.iter().map(expr));
+            DumpNode {
+                label: format!("DecompositionDecl '{}'{}{}{} <{}>", d.ty, specifiers(&d.specifiers), init_style(d.init_style), attributes(&d.attrs), at(d.span)),
+                span: Some(d.span),
+                children,
+            }
+        }
+        Decl::Function(f) => {
+            let mut children: Vec<DumpNode> = f.params.iter().map(|p| param_node(p, at)).collect();
+            children.extend(f.requires.iter().map(expr));
+            children.extend(f.initializers.iter().map(|i| DumpNode {
+                label: format!("CXXCtorInitializer {}{} <{}>", i.name, if i.braced { " list" } else { "" }, at(i.span)),
+                span: Some(i.span),
+                children: i.args.iter().map(expr).collect(),
+            }));
+            if let Some(body) = &f.body { children.push(block_node(body, at)); }
+            let what = match f.kind {
+                FunctionKind::Constructor => "CXXConstructorDecl",
+                FunctionKind::Destructor => "CXXDestructorDecl",
+                FunctionKind::Conversion => "CXXConversionDecl",
+                // A friend is not a member, though declared in the class.
+                FunctionKind::Normal if in_class && !f.specifiers.is_friend => "CXXMethodDecl",
+                FunctionKind::Normal => "FunctionDecl",
+            };
+            let q = &f.qualifiers;
+            let mut extra = String::new();
+            if !q.quals.is_empty() { extra.push_str(&format!(" {}", q.quals)); }
+            for (set, word) in [(q.is_noexcept, "noexcept"), (q.is_override, "override"), (q.is_final, "final"), (q.is_pure, "pure"), (q.is_defaulted, "default"), (q.is_deleted, "delete"), (f.is_coroutine, "coroutine")] {
+                if set { extra.push(' '); extra.push_str(word); }
+            }
+            let label = format!("{} {} '{}'{}{}{}{} <{}>", what, f.name, f.ty, extra, specifiers(&f.specifiers), access, attributes(&f.attrs), at(f.name_span));
+            DumpNode { label, span: Some(f.span), children }
+        }
+        Decl::Typedef(t) => DumpNode::leaf(format!("{} {} '{}'{}{} <{}>", if t.is_alias { "TypeAliasDecl" } else { "TypedefDecl" }, t.name, t.ty, access, attributes(&t.attrs), at(t.name_span)), t.span),
+        Decl::Namespace(n) => {
+            let name = n.name.map(|n| format!(" {}", n)).unwrap_or_default();
+            let inline = if n.is_inline { " inline" } else { "" };
+            DumpNode { label: format!("NamespaceDecl{}{}{} <{}>", name, inline, attributes(&n.attrs), at(n.span)), span: Some(n.span), children: n.decls.iter().map(|d| decl_node(d, at)).collect() }
+        }
+        Decl::Using(u) => DumpNode::leaf(format!("UsingDecl {}{} <{}>", u.name, access, at(u.span)), u.span),
+        Decl::UsingDirective(u) => DumpNode::leaf(format!("UsingDirectiveDecl {} <{}>", u.namespace, at(u.span)), u.span),
+        Decl::Module(m) => {
+            let name = match (m.name, m.partition) {
+                (Some(_), _) => format!(" {}", m.full_name().expect("named module")),
+                (None, Some(partition)) => format!(" :{}", partition),
+                (None, None) => String::new(),
+            };
+            DumpNode::leaf(format!("ModuleDecl{}{} <{}>", if m.is_export { " export" } else { "" }, name, at(m.span)), m.span)
+        }
+        Decl::Import(i) => DumpNode::leaf(format!("ImportDecl{} {} <{}>", if i.is_export { " export" } else { "" }, i.module, at(i.span)), i.span),
+        Decl::Unsupported(u) => unsupported_node(u, at),
+        Decl::Invalid(span) => DumpNode::leaf(format!("InvalidDecl <{}>", at(*span)), *span),
+        Decl::Export(e) => DumpNode {
+            label: format!("ExportDecl{} <{}>", if e.braced { " braced" } else { "" }, at(e.span)),
+            span: Some(e.span),
+            children: e.decls.iter().map(|d| decl_node(d, at)).collect(),
+        },
+        Decl::Class(c) => {
+            let name = c.name.map(|n| format!(" {}", n)).unwrap_or_default();
+            let args = c.args.as_ref().map(|args| format!("<{}>", args.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", "))).unwrap_or_default();
+            let name = name + &args;
+            let definition = if c.members.is_some() { " definition" } else { "" };
+            let definition = if c.is_final { format!("{} final", definition) } else { definition.to_string() };
+            let mut children: Vec<DumpNode> = c.bases.iter().map(|b| {
+                let virt = if b.is_virtual { " virtual" } else { "" };
+                DumpNode::leaf(format!("CXXBaseSpecifier{} {} {} <{}>", virt, b.access, b.name, at(b.span)), b.span)
+            }).collect();
+            children.extend(c.members.iter().flatten().map(|m| member_node(&m.decl, Some(m.access), at)));
+            DumpNode { label: format!("CXXRecordDecl {}{}{}{}{} <{}>", c.key.keyword(), name, definition, access, attributes(&c.attrs), at(c.span)), span: Some(c.span), children }
+        }
+        Decl::Enum(e) => {
+            let name = e.name.map(|n| format!(" {}", n)).unwrap_or_default();
+            let scoped = if e.is_scoped { " class" } else { "" };
+            let underlying = e.underlying.map(|t| format!(" '{}'", t)).unwrap_or_default();
+            let children = e.enumerators.iter().flatten().map(|c| DumpNode {
+                label: format!("EnumConstantDecl {}{} <{}>", c.name, attributes(&c.attrs), at(c.span)),
+                span: Some(c.span),
+                children: c.value.iter().map(expr).collect(),
+            }).collect();
+            DumpNode { label: format!("EnumDecl{}{}{}{}{} <{}>", scoped, name, underlying, access, attributes(&e.attrs), at(e.span)), span: Some(e.span), children }
+        }
+        Decl::Concept(c) => DumpNode { label: format!("ConceptDecl {} <{}>", c.name, at(c.name_span)), span: Some(c.span), children: vec![expr(&c.constraint)] },
+        Decl::StaticAssert(a) => {
+            let message = a.message.as_ref().map(|m| format!(" {:?}", m)).unwrap_or_default();
+            DumpNode { label: format!("StaticAssertDecl{} <{}>", message, at(a.span)), span: Some(a.span), children: vec![expr(&a.cond)] }
+        }
+        Decl::Template(t) => {
+            let what = match &*t.decl {
+                Decl::Function(_) => "FunctionTemplateDecl",
+                Decl::Class(c) if c.args.is_some() && t.params.is_empty() => "ClassTemplateSpecializationDecl",
+                Decl::Class(c) if c.args.is_some() => "ClassTemplatePartialSpecializationDecl",
+                Decl::Class(_) => "ClassTemplateDecl",
+                Decl::Var(_) => "VarTemplateDecl",
+                Decl::Typedef(_) => "TypeAliasTemplateDecl",
+                _ => "TemplateDecl",
+            };
+            let mut children: Vec<DumpNode> = t.params.iter().map(|p| template_param_node(p, at)).collect();
+            children.extend(t.requires.iter().map(expr));
+            children.push(member_node(&t.decl, member_access, at));
+            DumpNode { label: format!("{} {} <{}>", what, t.decl.name(), at(t.span)), span: Some(t.span), children }
+        }
+    }
+}
+
+fn param_node(p: &ParamDecl, at: &dyn Fn(Span) -> String) -> DumpNode {
+    let name = p.name.map(|n| format!(" {}", n)).unwrap_or_default();
+    let pack = if p.is_pack { "..." } else { "" };
+    DumpNode { label: format!("ParamDecl{} '{}{}'{} <{}>", name, p.ty, pack, attributes(&p.attrs), at(p.span)), span: Some(p.span), children: p.default.iter().map(|e| expr_node(e, at)).collect() }
+}
+
+/// A type default is shown in the label, an expression one as a child
+/// after the constraint, if any.
+fn template_param_node(p: &TemplateParam, at: &dyn Fn(Span) -> String) -> DumpNode {
+    let name = p.name.map(|n| format!(" {}", n)).unwrap_or_default();
+    let pack = if p.is_pack { " ..." } else { "" };
+    let mut children: Vec<DumpNode> = p.constraint.iter().map(|c| expr_node(c, at)).collect();
+    let default = match &p.default {
+        Some(TemplateArg::Type(ty, _)) => format!(" default '{}'", ty),
+        Some(TemplateArg::Expr(e)) => { children.push(expr_node(e, at)); String::new() }
+        None => String::new(),
+    };
+    let label = match &p.kind {
+        TemplateParamKind::Type => format!("TemplateTypeParmDecl typename{}{}{}", pack, name, default),
+        TemplateParamKind::Value(ty) => format!("NonTypeTemplateParmDecl '{}'{}{}{}", ty, pack, name, default),
+        TemplateParamKind::Template(params) => {
+            children.splice(0..0, params.iter().map(|p| template_param_node(p, at)));
+            format!("TemplateTemplateParmDecl{}{}{}", pack, name, default)
+        }
+    };
+    DumpNode { label: format!("{} <{}>", label, at(p.span)), span: Some(p.span), children }
+}
+
+fn requirement_node(r: &Requirement, at: &dyn Fn(Span) -> String) -> DumpNode {
+    let expr = |e: &Expr| expr_node(e, at);
+    let (label, span, children) = match r {
+        Requirement::Simple(e) => ("SimpleRequirement".to_string(), e.span, vec![expr(e)]),
+        Requirement::Type(ty, span) => (format!("TypeRequirement '{}'", ty), *span, vec![]),
+        Requirement::Compound { expr: e, is_noexcept, constraint, span } => {
+            let noexcept = if *is_noexcept { " noexcept" } else { "" };
+            (format!("CompoundRequirement{}", noexcept), *span, std::iter::once(e).chain(constraint).map(expr).collect())
+        }
+        Requirement::Nested(e) => ("NestedRequirement".to_string(), e.span, vec![expr(e)]),
+    };
+    DumpNode { label: format!("{} <{}>", label, at(span)), span: Some(span), children }
+}
+
+fn block_node(block: &Block, at: &dyn Fn(Span) -> String) -> DumpNode {
+    DumpNode { label: format!("CompoundStmt <{}>", at(block.span)), span: Some(block.span), children: block.stmts.iter().map(|s| stmt_node(s, at)).collect() }
+}
+
+fn stmt_node(stmt: &Stmt, at: &dyn Fn(Span) -> String) -> DumpNode {
+    let node = |label: &str, children: Vec<DumpNode>| DumpNode { label: format!("{} <{}>", label, at(stmt.span)), span: Some(stmt.span), children };
+    let expr = |e: &Expr| expr_node(e, at);
+    let child = |s: &Stmt| stmt_node(s, at);
+    // Absent optional parts are shown, so the children keep their places.
+    let null = || DumpNode { label: "<<<NULL>>>".into(), span: None, children: Vec::new() };
+    match &stmt.kind {
+        StmtKind::Compound(block) => block_node(block, at),
+        StmtKind::Null => node("NullStmt", vec![]),
+        StmtKind::Expr(e) => expr(e),
+        StmtKind::Decl(decls) => node("DeclStmt", decls.iter().map(|d| decl_node(d, at)).collect()),
+        StmtKind::If { cond, then, otherwise } => {
+            let mut children = vec![expr(cond), child(then)];
+            children.extend(otherwise.as_deref().map(child));
+            node(if otherwise.is_some() { "IfStmt has_else" } else { "IfStmt" }, children)
+        }
+        StmtKind::While { cond, body } => node("WhileStmt", vec![expr(cond), child(body)]),
+        StmtKind::DoWhile { body, cond } => node("DoStmt", vec![child(body), expr(cond)]),
+        StmtKind::For { init, cond, step, body } => node("ForStmt", vec![
+            init.as_deref().map_or_else(null, child),
+            cond.as_ref().map_or_else(null, expr),
+            step.as_ref().map_or_else(null, expr),
+            child(body),
+        ]),
+        StmtKind::RangeFor { init, decl, range, body } => {
+            let decl = decl_node(decl, at);
+            node("CXXForRangeStmt", vec![
+                init.as_deref().map_or_else(null, child),
+                DumpNode { label: format!("DeclStmt <{}>", at(decl.span.expect("declarations have spans"))), span: decl.span, children: vec![decl] },
+                expr(range),
+                child(body),
+            ])
+        }
+        StmtKind::Switch { cond, body } => node("SwitchStmt", vec![expr(cond), child(body)]),
+        StmtKind::Case { value, body } => node("CaseStmt", vec![expr(value), child(body)]),
+        StmtKind::Default(body) => node("DefaultStmt", vec![child(body)]),
+        StmtKind::Break => node("BreakStmt", vec![]),
+        StmtKind::Continue => node("ContinueStmt", vec![]),
+        StmtKind::Return(value) => node("ReturnStmt", value.iter().map(expr).collect()),
+        StmtKind::CoReturn(value) => node("CoreturnStmt", value.iter().map(expr).collect()),
+        StmtKind::Attributed { attrs, stmt } => node(&format!("AttributedStmt{}", attributes(attrs)), vec![child(stmt)]),
+        StmtKind::Unsupported(u) => unsupported_node(u, at),
+        StmtKind::Invalid => node("InvalidStmt", vec![]),
+    }
+}
+
+fn unsupported_node(u: &UnsupportedConstruct, at: &dyn Fn(Span) -> String) -> DumpNode {
+    DumpNode::leaf(format!("UnsupportedConstruct {} <{}>", u.category, at(u.span)), u.span)
+}
+
+/// ` [[a, b(1)]]`, or nothing if there are no attributes.
+fn attributes(attrs: &[Attribute]) -> String {
+    if attrs.is_empty() { return String::new(); }
+    format!(" [[{}]]", attrs.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", "))
+}
+
+/// ` callinit` or ` listinit`, as clang marks direct initialization.
+fn init_style(style: InitStyle) -> &'static str {
+    match style {
+        InitStyle::Copy => "",
+        InitStyle::Direct => " callinit",
+        InitStyle::List => " listinit",
+    }
+}
+
+fn specifiers(s: &Specifiers) -> String {
+    let mut text = String::new();
+    match s.storage {
+        StorageClass::None => {}
+        StorageClass::Static => text.push_str(" static"),
+        StorageClass::Extern => text.push_str(" extern"),
+    }
+    if s.is_extern_c { text.push_str(" extern \"C\""); }
+    for (set, word) in [(s.is_inline, "inline"), (s.is_constexpr, "constexpr"), (s.is_virtual, "virtual"), (s.is_explicit, "explicit"), (s.is_mutable, "mutable"), (s.is_friend, "friend")] {
+        if set { text.push(' '); text.push_str(word); }
+    }
+    text
+}
+
+fn expr_node(e: &Expr, at: &dyn Fn(Span) -> String) -> DumpNode {
+    let node = |label: String, children: Vec<&Expr>| DumpNode {
+        label: format!("{} <{}>", label, at(e.span)),
+        span: Some(e.span),
+        children: children.into_iter().map(|c| expr_node(c, at)).collect(),
+    };
+    match &e.kind {
+        ExprKind::IntLiteral(v, _) => node(format!("IntegerLiteral {}", v), vec![]),
+        ExprKind::FloatLiteral(v) => node(format!("FloatingLiteral {}", v), vec![]),
+        ExprKind::CharLiteral(c) => node(format!("CharacterLiteral {}", c.spelling('\'')), vec![]),
+        ExprKind::StringLiteral(s) => node(format!("StringLiteral {}", s.spelling('"')), vec![]),
+        ExprKind::BoolLiteral(b) => node(format!("BoolLiteral {}", b), vec![]),
+        ExprKind::Nullptr => node("NullPtrLiteral".into(), vec![]),
+        ExprKind::This => node("CXXThisExpr".into(), vec![]),
+        ExprKind::Name(n) => node(format!("DeclRefExpr {}", n), vec![]),
+        ExprKind::TemplateId { .. } => node(format!("DeclRefExpr {}", e), vec![]),
+        ExprKind::Paren(inner) => node("ParenExpr".into(), vec![inner]),
+        ExprKind::Unary { op, operand } => {
+            let fix = if op.is_postfix() { "postfix" } else { "prefix" };
+            node(format!("UnaryOperator {} '{}'", fix, op.spelling()), vec![operand])
+        }
+        ExprKind::Binary { op, lhs, rhs } => node(format!("BinaryOperator '{}'", op.spelling()), vec![lhs, rhs]),
+        ExprKind::Conditional { cond, then, otherwise } => node("ConditionalOperator".into(), vec![cond, then, otherwise]),
+        ExprKind::Call { callee, args } => node("CallExpr".into(), std::iter::once(&**callee).chain(args).collect()),
+        ExprKind::Index { base, index } => node("ArraySubscriptExpr".into(), vec![base, index]),
+        ExprKind::Member { base, member, arrow } => node(format!("MemberExpr {}{}", if *arrow { "->" } else { "." }, member), vec![base]),
+        ExprKind::Cast { kind, ty, operand } => {
+            let name = match kind {
+                CastKind::CStyle => "CStyleCastExpr",
+                CastKind::Static => "CXXStaticCastExpr",
+                CastKind::Dynamic => "CXXDynamicCastExpr",
+                CastKind::Const => "CXXConstCastExpr",
+                CastKind::Reinterpret => "CXXReinterpretCastExpr",
+            };
+            node(format!("{} '{}'", name, ty), vec![operand])
+        }
+        ExprKind::Construct { ty, args, braced } => node(format!("CXXFunctionalCastExpr '{}'{}", ty, if *braced { " list" } else { "" }), args.iter().collect()),
+        ExprKind::InitList(items) => node("InitListExpr".into(), items.iter().collect()),
+        ExprKind::Designated { field, init } => node(format!("DesignatedInitExpr .{}", field), vec![init]),
+        ExprKind::ParenList(args) => node("ParenListExpr".into(), args.iter().collect()),
+        ExprKind::UserLiteral { literal, suffix } => node(format!("UserDefinedLiteral {}", suffix), vec![literal]),
+        ExprKind::Requires { params, requirements } => {
+            let mut n = node("RequiresExpr".into(), vec![]);
+            n.children.extend(params.iter().map(|p| param_node(p, at)));
+            n.children.extend(requirements.iter().map(|r| requirement_node(r, at)));
+            n
+        }
+        ExprKind::CoAwait(operand) => node("CoawaitExpr".into(), vec![operand]),
+        ExprKind::CoYield(operand) => node("CoyieldExpr".into(), vec![operand]),
+        ExprKind::Embed(bytes) => node(format!("EmbedExpr {} bytes", bytes.len()), vec![]),
+        ExprKind::SizeofExpr(operand) => node("UnaryExprOrTypeTraitExpr sizeof".into(), vec![operand]),
+        ExprKind::SizeofType(ty) => node(format!("UnaryExprOrTypeTraitExpr sizeof '{}'", ty), vec![]),
+        ExprKind::Alignof(ty) => node(format!("UnaryExprOrTypeTraitExpr alignof '{}'", ty), vec![]),
+    }
+}
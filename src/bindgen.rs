@@ -0,0 +1,420 @@
+//! Rust bindings for a header, as `ruscom bindgen` writes them: `extern`
+//! declarations of its functions and variables and `#[repr(C)]` structs
+//! for its classes, for the subset of what `ruscom interface` describes
+//! that Rust can use through the C ABI.
+//!
+//! Functions are linked by their mangled names with `#[link_name]`, so a
+//! C++ function whose parameters and return type are C types can be
+//! called like a C one; those declared `extern "C"` need none. Scalars map to `core::ffi` types, pointers to raw
+//! pointers and pointers to functions to `Option<unsafe extern "C" fn>`.
+//! A class with no bases, virtual functions, user-declared destructor or
+//! copy operations and whose data members all have one access and FFI-safe
+//! types becomes a struct, with its size and alignment checked at compile
+//! time; any other is bound as an opaque type that can only be used
+//! through pointers. Enums become a type alias of their underlying type
+//! and a constant per enumerator, as their values need not be listed.
+//! Names are qualified with `_` for `::`, like `geo_Point`, and keep their
+//! C++ case, so a module including the bindings will want to allow
+//! `non_camel_case_types`, `non_upper_case_globals` and `non_snake_case`.
+//!
+//! What cannot be bound, like a function taking a reference or a `long
+//! double`, a non-static member function or an inline function the
+//! library need not define, is skipped with a warning saying why.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::abi::{qualify, scope_of, Names};
+use crate::ast::{Access, ClassDecl, ClassKey, Decl, EnumDecl, FunctionDecl, FunctionKind, StorageClass, TranslationUnit, VarDecl};
+use crate::diagnostics::{Diagnostic, Diagnostics, Label};
+use crate::intern::Symbol;
+use crate::lexer::token::Span;
+use crate::mangle;
+use crate::sema::constant::{Constant, Constants, Value as Number};
+use crate::sema::enums;
+use crate::sema::layout::Layouts;
+use crate::session::Target;
+use crate::source::{FileId, SourceManager};
+use crate::types::{Type, TypeId};
+
+/// Rust keywords, which are written as raw identifiers.
+const KEYWORDS: [&str; 38] = [
+    "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop",
+    "match", "mod", "move", "mut", "pub", "ref", "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where", "while", "abstract",
+    "become", "box", "gen",
+];
+
+/// The bindings of what `unit`, parsed from `file`, declares there, for
+/// `target`, reporting what is skipped to `diagnostics`.
+pub fn generate(unit: &TranslationUnit, file: FileId, sources: &SourceManager, target: &Target, diagnostics: &mut Diagnostics) -> String {
+    let mut generator = Generator {
+        names: Names::of(unit),
+        layouts: Layouts::of_unit(unit, target),
+        constants: Constants::new(target),
+        file,
+        sources,
+        diagnostics,
+        types: HashMap::new(),
+        values: HashMap::new(),
+        forward: Vec::new(),
+        defined: HashSet::new(),
+        items: String::new(),
+        externs: String::new(),
+    };
+    generator.decls(&unit.decls, "", true);
+    // A class declared but never defined here is opaque.
+    for name in std::mem::take(&mut generator.forward) {
+        if !generator.defined.contains(&name) { generator.opaque(&name); }
+    }
+    let mut out = format!("// Generated by ruscom bindgen from {} for {}.\n", sources.file(file).path.display(), target.triple());
+    out.push_str(&generator.items);
+    if !generator.externs.is_empty() { let _ = write!(out, "\nunsafe extern \"C\" {{\n{}}}\n", generator.externs); }
+    out
+}
+
+/// How a class or enum of the header is bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bound {
+    Struct,
+    /// Only usable through pointers.
+    Opaque,
+    Enum,
+}
+
+/// Why a type has no Rust equivalent, and where it was used.
+struct Unbound {
+    /// Like "return type is" or "parameter `p` has type".
+    place: String,
+    ty: TypeId,
+    why: String,
+}
+
+struct Generator<'u, 's, 'd> {
+    names: Names,
+    layouts: Layouts,
+    /// Every constant declared so far, in the file or what it includes.
+    constants: Constants<'u>,
+    file: FileId,
+    sources: &'s SourceManager,
+    diagnostics: &'d mut Diagnostics,
+    /// The classes and enums of the header bound so far, by qualified name.
+    types: HashMap<String, Bound>,
+    /// The Rust names of the functions, variables and constants bound so
+    /// far, with where they are declared and the symbol, or for constants
+    /// the qualified name, they bind.
+    values: HashMap<String, (Span, String)>,
+    /// The classes of the header, in the order they are first declared.
+    forward: Vec<String>,
+    /// Those of them it defines.
+    defined: HashSet<String>,
+    items: String,
+    /// The functions and variables of the `extern` block.
+    externs: String,
+}
+
+impl<'u> Generator<'u, '_, '_> {
+    /// Whether `span` is in the file bound.
+    fn here(&self, span: Span) -> bool { self.sources.file_at(span.start).is_some_and(|f| f.id == self.file) }
+
+    fn skip(&mut self, what: &str, name: &str, span: Span, why: impl std::fmt::Display) {
+        self.diagnostics.emit(Diagnostic::warning(format!("skipped {} `{}`: {}", what, name, why)).with_label(Label::primary(span, "")));
+    }
+
+    fn skip_unbound(&mut self, what: &str, name: &str, span: Span, unbound: Unbound) {
+        let why = format!("its {} `{}`, and {}", unbound.place, unbound.ty, unbound.why);
+        self.skip(what, name, span, why);
+    }
+
+    /// Claims the Rust name of the function, variable or constant `name`,
+    /// binding `symbol`, unless an earlier one has it: a redeclaration,
+    /// binding the same symbol, is left out quietly, but an overload is
+    /// reported.
+    fn claim(&mut self, what: &str, name: &str, symbol: &str, span: Span) -> Option<String> {
+        let rust = rust_name(name);
+        if let Some((earlier, bound)) = self.values.get(&rust) {
+            if bound == symbol { return None; }
+            let earlier = *earlier;
+            self.diagnostics.emit(Diagnostic::warning(format!("skipped {} `{}`: its Rust name `{}` is taken, and Rust has no overloading", what, name, rust))
+                .with_label(Label::primary(span, ""))
+                .with_label(Label::secondary(earlier, "bound under that name here")));
+            return None;
+        }
+        self.values.insert(rust.clone(), (span, symbol.to_string()));
+        Some(rust)
+    }
+
+    /// Binds `decls`, declared in `scope`; what is not `visible`, in an
+    /// unnamed namespace, only declares constants for what follows.
+    fn decls(&mut self, decls: &'u [Decl<'u>], scope: &str, visible: bool) {
+        for decl in decls { self.decl(decl, scope, visible); }
+    }
+
+    fn decl(&mut self, decl: &'u Decl<'u>, scope: &str, visible: bool) {
+        match decl {
+            Decl::Namespace(n) => match n.name {
+                Some(name) => self.decls(&n.decls, &qualify(scope, &name.as_str()), visible),
+                None => self.decls(&n.decls, scope, false),
+            },
+            Decl::Export(e) => self.decls(&e.decls, scope, visible),
+            Decl::Class(c) => {
+                self.constants.layouts.declare_class(c);
+                if visible { self.class(c, scope); }
+            }
+            Decl::Enum(e) => {
+                let values = enums::values(e, &self.constants.layouts, &mut self.constants.enumerators, &mut Diagnostics::new());
+                self.constants.layouts.declare_enum(e, &values);
+                if visible && self.here(e.span) { self.enumeration(e, &values, scope); }
+            }
+            Decl::Typedef(t) => {
+                self.constants.layouts.declare_typedef(t);
+                if visible && self.here(t.span) {
+                    let name = qualify(scope, &t.name.as_str());
+                    match self.rust_type(self.names.resolve(t.ty, scope), false) {
+                        Ok(ty) => { let _ = writeln!(self.items, "\npub type {} = {};", rust_name(&name), ty); }
+                        Err(why) => self.skip_unbound("typedef", &name, t.name_span, Unbound { place: "aliased type is".to_string(), ty: t.ty, why }),
+                    }
+                }
+            }
+            Decl::Function(f) => {
+                self.constants.declare_function(f);
+                let name = qualify(scope, &f.name.as_str());
+                // A member defined outside its class is bound with it.
+                if visible && !self.names.is_class(scope_of(&name)) && f.specifiers.storage != StorageClass::Static && self.here(f.name_span) {
+                    self.function(f, &name, f.specifiers.is_inline || f.specifiers.is_constexpr);
+                }
+            }
+            Decl::Var(v) => {
+                self.constants.declare_variable(v);
+                let internal = v.specifiers.storage == StorageClass::Static || (v.specifiers.storage != StorageClass::Extern && v.ty.is_const() && !v.specifiers.is_constexpr);
+                if visible && self.here(v.span) && (!internal || self.constants.variables.contains_key(&v.name)) { self.variable(v, &qualify(scope, &v.name.as_str())); }
+            }
+            _ => {}
+        }
+    }
+
+    /// The Rust type of `ty`, resolved, or why it has none; `behind_pointer`
+    /// if it is what a pointer points to, where opaque types and `void` do.
+    fn rust_type(&self, ty: TypeId, behind_pointer: bool) -> Result<String, String> {
+        let ffi = |name: &str| Ok(format!("::core::ffi::{}", name));
+        match ty.unqualified().get() {
+            Type::Void if behind_pointer => ffi("c_void"),
+            Type::Void => Err("`void` is not a value".to_string()),
+            Type::Bool => Ok("bool".to_string()),
+            Type::Char => ffi("c_char"),
+            Type::SignedChar => ffi("c_schar"),
+            Type::UnsignedChar => ffi("c_uchar"),
+            Type::Short => ffi("c_short"),
+            Type::UnsignedShort => ffi("c_ushort"),
+            Type::Int => ffi("c_int"),
+            Type::UnsignedInt => ffi("c_uint"),
+            Type::Long => ffi("c_long"),
+            Type::UnsignedLong => ffi("c_ulong"),
+            Type::LongLong => ffi("c_longlong"),
+            Type::UnsignedLongLong => ffi("c_ulonglong"),
+            Type::WChar => Ok(if self.layouts.size_of(ty) == Ok(2) { "u16" } else { "i32" }.to_string()),
+            Type::Char8 => Ok("u8".to_string()),
+            Type::Char16 => Ok("u16".to_string()),
+            Type::Char32 => Ok("u32".to_string()),
+            Type::Float => Ok("f32".to_string()),
+            Type::Double => Ok("f64".to_string()),
+            Type::LongDouble => Err("`long double` has no Rust equivalent".to_string()),
+            Type::Pointer(pointee) => match pointee.unqualified().get() {
+                Type::Function { ret, params, variadic } => {
+                    let mut types = params.iter().map(|&p| self.rust_type(decay(p), false)).collect::<Result<Vec<_>, _>>()?;
+                    if variadic { types.push("...".to_string()); }
+                    Ok(format!("::core::option::Option<unsafe extern \"C\" fn({}){}>", types.join(", "), self.returns(ret)?))
+                }
+                _ => Ok(format!("*{} {}", if pointee.is_const() { "const" } else { "mut" }, self.rust_type(pointee, true)?)),
+            },
+            Type::Reference(_) | Type::RvalueReference(_) => Err("references are not FFI-safe".to_string()),
+            Type::Array(element, Some(n)) => Ok(format!("[{}; {}]", self.rust_type(element, false)?, n)),
+            Type::Array(_, None) => Err("arrays without a bound are not FFI-safe".to_string()),
+            Type::Named(name) => match self.types.get(&*name.as_str()) {
+                Some(Bound::Opaque) if !behind_pointer => Err(format!("`{}` is opaque, bound only for use through pointers", name)),
+                Some(_) => Ok(rust_name(&name.as_str())),
+                None => Err(format!("`{}` is not declared in this header", name)),
+            },
+            Type::Specialization(..) => Err("template specializations are not FFI-safe".to_string()),
+            Type::Auto => Err("deduced types are not FFI-safe".to_string()),
+            Type::Function { .. } => Err("functions are not FFI-safe".to_string()),
+            Type::Qualified(..) => unreachable!("unqualified"),
+        }
+    }
+
+    /// What follows a function's parameters for its return type `ret`.
+    fn returns(&self, ret: TypeId) -> Result<String, String> {
+        if ret.unqualified().get() == Type::Void { return Ok(String::new()); }
+        Ok(format!(" -> {}", self.rust_type(ret, false)?))
+    }
+
+    /// Binds the function `f`, named `name`, which is not a member function
+    /// or is a static one, unless it is `inline`.
+    fn function(&mut self, f: &FunctionDecl, name: &str, inline: bool) {
+        if inline { return self.skip("function", name, f.name_span, "it is inline, so the library need not define it"); }
+        if f.name.as_str().starts_with("operator") { return self.skip("function", name, f.name_span, "operators are not FFI-safe"); }
+        let ty = self.names.resolve(f.ty, scope_of(name));
+        let Type::Function { ret, params, variadic } = ty.get() else { return };
+        let mangled = if f.specifiers.is_extern_c { Some(mangle::extern_c(name)) } else { mangle::function(name, f.kind, ty, None) };
+        let Some(mangled) = mangled else { return self.skip("function", name, f.name_span, "it has no mangled name") };
+        let mut parameters = Vec::new();
+        for (i, (p, &ty)) in f.params.iter().zip(&params).enumerate() {
+            let param = p.name.map_or_else(|| format!("arg{}", i), |n| n.as_str().to_string());
+            match self.rust_type(decay(ty), false) {
+                Ok(rust) => parameters.push(format!("{}: {}", identifier(&param), rust)),
+                Err(why) => return self.skip_unbound("function", name, f.name_span, Unbound { place: format!("parameter `{}` has type", param), ty, why }),
+            }
+        }
+        if variadic { parameters.push("...".to_string()); }
+        let returns = match self.returns(ret) {
+            Ok(returns) => returns,
+            Err(why) => return self.skip_unbound("function", name, f.name_span, Unbound { place: "return type is".to_string(), ty: ret, why }),
+        };
+        let Some(rust) = self.claim("function", name, &mangled, f.name_span) else { return };
+        if mangled != rust { let _ = writeln!(self.externs, "    #[link_name = \"{}\"]", mangled); }
+        let _ = writeln!(self.externs, "    pub fn {}({}){};", identifier(&rust), parameters.join(", "), returns);
+    }
+
+    /// A constant if `v` has a constant value, else a variable of the
+    /// `extern` block.
+    fn variable(&mut self, v: &VarDecl, name: &str) {
+        let ty = self.names.resolve(v.ty, scope_of(name));
+        let rust = match self.rust_type(ty, false) {
+            Ok(rust) => rust,
+            Err(why) => return self.skip_unbound("variable", name, v.name_span, Unbound { place: "type is".to_string(), ty, why }),
+        };
+        if let Some(&c) = self.constants.variables.get(&v.name) {
+            let Some(value) = literal(c) else { return self.skip("constant", name, v.name_span, "its value is not finite") };
+            let Some(identifier) = self.claim("constant", name, name, v.name_span).map(|n| identifier(&n)) else { return };
+            let _ = writeln!(self.items, "\npub const {}: {} = {};", identifier, rust, value);
+            return;
+        }
+        let mangled = if v.specifiers.is_extern_c { Some(mangle::extern_c(name)) } else { mangle::variable(name) };
+        let Some(mangled) = mangled else { return self.skip("variable", name, v.name_span, "it has no mangled name") };
+        let Some(rust_name) = self.claim("variable", name, &mangled, v.name_span) else { return };
+        if mangled != rust_name { let _ = writeln!(self.externs, "    #[link_name = \"{}\"]", mangled); }
+        let _ = writeln!(self.externs, "    pub static {}{}: {};", if ty.is_const() { "" } else { "mut " }, identifier(&rust_name), rust);
+    }
+
+    fn enumeration(&mut self, e: &EnumDecl, values: &[Option<i128>], scope: &str) {
+        let Some(name) = e.name else { return };
+        let qualified = qualify(scope, &name.as_str());
+        let Some(underlying) = self.layouts.underlying(name) else { return };
+        let Ok(ty) = self.rust_type(self.names.resolve(underlying, scope), false) else { return };
+        let rust = rust_name(&qualified);
+        let _ = writeln!(self.items, "\npub type {} = {};", rust, ty);
+        self.types.insert(qualified.clone(), Bound::Enum);
+        for (n, value) in e.enumerators.iter().flatten().zip(values) {
+            let enumerator = format!("{}::{}", qualified, n.name);
+            let Some(value) = value else { continue };
+            let Some(constant) = self.claim("enumerator", &enumerator, &enumerator, n.span) else { continue };
+            let _ = writeln!(self.items, "pub const {}: {} = {};", identifier(&constant), rust, value);
+        }
+    }
+
+    fn class(&mut self, c: &'u ClassDecl<'u>, scope: &str) {
+        let Some(name) = c.name else { return };
+        let qualified = qualify(scope, &name.as_str());
+        let here = self.here(c.span);
+        // Known before its members, which may point to it.
+        if here && !self.types.contains_key(&qualified) {
+            self.types.insert(qualified.clone(), Bound::Opaque);
+            self.forward.push(qualified.clone());
+        }
+        let Some(members) = &c.members else { return };
+        for member in members {
+            if matches!(member.decl, Decl::Class(_) | Decl::Enum(_) | Decl::Typedef(_)) { self.decl(&member.decl, &qualified, true); }
+        }
+        if !here { return; }
+        self.defined.insert(qualified.clone());
+        let mut fields = Vec::new();
+        let mut opaque = self.names.not_c_struct(c, &qualified).map(str::to_string);
+        for member in members {
+            let Decl::Var(v) = &member.decl else { continue };
+            if v.specifiers.storage == StorageClass::Static || opaque.is_some() { continue; }
+            if v.bit_width.is_some() {
+                opaque = Some(format!("its field `{}` is a bit-field, which Rust cannot declare", v.name));
+                continue;
+            }
+            let ty = self.names.resolve(v.ty, &qualified);
+            match self.rust_type(ty, false) {
+                Ok(rust) => fields.push(format!("    pub {}: {},", identifier(&v.name.as_str()), rust)),
+                Err(why) => opaque = Some(format!("its field `{}` has type `{}`, and {}", v.name, ty, why)),
+            }
+        }
+        let rust = rust_name(&qualified);
+        if let Some(why) = opaque {
+            self.diagnostics.emit(Diagnostic::warning(format!("class `{}` is bound as an opaque type, usable only through pointers: {}", qualified, why))
+                .with_label(Label::primary(c.span, "")));
+            self.opaque(&qualified);
+        } else {
+            self.types.insert(qualified.clone(), Bound::Struct);
+            // An empty C++ class has a size of 1, a Rust one none.
+            if fields.is_empty() { fields.push("    pub _address: u8,".to_string()); }
+            let (key, derives) = if c.key == ClassKey::Union { ("union", "Clone, Copy") } else { ("struct", "Debug, Clone, Copy") };
+            let _ = write!(self.items, "\n#[repr(C)]\n#[derive({})]\npub {} {} {{\n{}\n}}\n", derives, key, rust, fields.join("\n"));
+            if let Ok(layout) = self.layouts.layout(TypeId::intern(Type::Named(Symbol::intern(&qualified)))) {
+                let _ = writeln!(self.items, "const _: () = assert!(::core::mem::size_of::<{0}>() == {1} && ::core::mem::align_of::<{0}>() == {2});", rust, layout.size, layout.align);
+            }
+        }
+        // Static members are bound like what is not a member.
+        for member in members.iter().filter(|m| m.access == Access::Public) {
+            match &member.decl {
+                Decl::Var(v) if v.specifiers.storage == StorageClass::Static => {
+                    self.constants.declare_variable(v);
+                    self.variable(v, &qualify(&qualified, &v.name.as_str()));
+                }
+                Decl::Function(f) if !f.specifiers.is_friend && !f.qualifiers.is_deleted => {
+                    let function = qualify(&qualified, &f.name.as_str());
+                    match f.kind {
+                        FunctionKind::Constructor => self.skip("constructor", &function, f.name_span, "constructors are not FFI-safe"),
+                        FunctionKind::Destructor => self.skip("destructor", &function, f.name_span, "destructors are not FFI-safe"),
+                        _ if f.specifiers.storage == StorageClass::Static => self.function(f, &function, f.body.is_some() || f.specifiers.is_inline || f.specifiers.is_constexpr),
+                        _ => self.skip("member function", &function, f.name_span, "only static member functions are FFI-safe"),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn opaque(&mut self, qualified: &str) {
+        let _ = write!(
+            self.items,
+            "\n#[repr(C)]\npub struct {} {{\n    _data: [u8; 0],\n    _marker: ::core::marker::PhantomData<(*mut u8, ::core::marker::PhantomPinned)>,\n}}\n",
+            rust_name(qualified)
+        );
+    }
+}
+
+/// The type a parameter of type `ty` has: an array's is a pointer to its
+/// element, a function's a pointer to it.
+fn decay(ty: TypeId) -> TypeId {
+    match ty.get() {
+        Type::Array(element, _) => TypeId::intern(Type::Pointer(element)),
+        Type::Function { .. } => TypeId::intern(Type::Pointer(ty)),
+        _ => ty,
+    }
+}
+
+/// The Rust name of the C++ entity `name`, qualified, like `geo_Point`.
+fn rust_name(name: &str) -> String { name.trim_start_matches("::").replace("::", "_") }
+
+/// `name` as a Rust identifier, raw if it is a keyword.
+fn identifier(name: &str) -> String {
+    match name {
+        "self" | "Self" | "super" | "crate" => format!("{}_", name),
+        _ if KEYWORDS.contains(&name) => format!("r#{}", name),
+        _ => name.to_string(),
+    }
+}
+
+/// `c` as a Rust literal, unless it is an infinity or NaN.
+fn literal(c: Constant) -> Option<String> {
+    match (c.value, c.ty.unqualified().get()) {
+        (Number::Int(n), Type::Bool) => Some((n != 0).to_string()),
+        (Number::Int(n), _) => Some(n.to_string()),
+        (Number::Float(v), _) if !v.is_finite() => None,
+        (Number::Float(v), Type::Float) => Some(format!("{:?}", v as f32)),
+        (Number::Float(v), _) => Some(format!("{:?}", v)),
+    }
+}
@@ -0,0 +1,37 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Cooperative cancellation flag shared between a running compilation and
+/// whoever may want to abort it (an LSP request handler, a file watcher).
+///
+/// Phases poll `check()` at their boundaries and inside long loops; cloning
+/// the token shares the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    flag: Arc<AtomicBool>,
+}
+
+/// Returned by a phase that stopped because its token was cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "compilation cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+impl CancellationToken {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn cancel(&self) { self.flag.store(true, Ordering::Relaxed); }
+
+    pub fn is_cancelled(&self) -> bool { self.flag.load(Ordering::Relaxed) }
+
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() { Err(Cancelled) } else { Ok(()) }
+    }
+}
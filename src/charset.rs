@@ -0,0 +1,163 @@
+//! Execution character sets: the encoding string and character literals
+//! have in the compiled program, as `-fexec-charset` and
+//! `-fwide-exec-charset` choose.
+//!
+//! Source text is UTF-8, and a `Literal` keeps the characters it was
+//! written with. Its code units are computed here wherever they matter,
+//! for the length of a string literal's array (`sizeof`) and the value of
+//! a character literal, so the program sees the code units its runtime
+//! expects rather than the host's bytes. Octal and hex escapes give code
+//! units directly and are not converted.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::lexer::token::Encoding;
+use crate::session::Target;
+
+/// A character set literals can be encoded in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Charset {
+    #[default]
+    Utf8,
+    /// ISO-8859-1: the first 256 code points, one byte each.
+    Latin1,
+    Ascii,
+    /// IBM037, the EBCDIC code page for US English, covering Latin-1.
+    Ebcdic,
+    Utf16,
+    Utf32,
+}
+
+impl Charset {
+    /// The canonical name, as `iconv` spells it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Charset::Utf8 => "UTF-8",
+            Charset::Latin1 => "ISO-8859-1",
+            Charset::Ascii => "ASCII",
+            Charset::Ebcdic => "IBM037",
+            Charset::Utf16 => "UTF-16",
+            Charset::Utf32 => "UTF-32",
+        }
+    }
+
+    /// The size of one code unit in bytes.
+    pub fn unit_size(self) -> usize {
+        match self {
+            Charset::Utf16 => 2,
+            Charset::Utf32 => 4,
+            _ => 1,
+        }
+    }
+
+    /// Whether the code units are bytes, as the narrow execution character
+    /// set's must be.
+    pub fn is_narrow(self) -> bool { self.unit_size() == 1 }
+
+    /// The code units encoding `text`, or the first character this
+    /// character set has none for.
+    pub fn encode(self, text: &str) -> Result<Vec<u32>, char> {
+        match self {
+            Charset::Utf8 => Ok(text.bytes().map(u32::from).collect()),
+            Charset::Utf16 => Ok(text.encode_utf16().map(u32::from).collect()),
+            Charset::Utf32 => Ok(text.chars().map(u32::from).collect()),
+            Charset::Latin1 | Charset::Ascii | Charset::Ebcdic => text.chars().map(|c| self.encode_byte(c).map(u32::from).ok_or(c)).collect(),
+        }
+    }
+
+    /// `encode`, with each code unit written as `unit_size` bytes in the
+    /// target's byte order.
+    pub fn encode_bytes(self, text: &str, big_endian: bool) -> Result<Vec<u8>, char> {
+        let size = self.unit_size();
+        Ok(self.encode(text)?.into_iter().flat_map(|unit| {
+            let bytes = if big_endian { unit.to_be_bytes() } else { unit.to_le_bytes() };
+            let range = if big_endian { 4 - size..4 } else { 0..size };
+            bytes[range].to_vec()
+        }).collect())
+    }
+
+    /// The byte `c` is in a single-byte character set.
+    fn encode_byte(self, c: char) -> Option<u8> {
+        let limit = if self == Charset::Ascii { 0x80 } else { 0x100 };
+        let code = u32::from(c);
+        if code >= limit { return None; }
+        Some(if self == Charset::Ebcdic { EBCDIC[code as usize] } else { code as u8 })
+    }
+}
+
+/// The character sets of a compilation's literals, for each encoding
+/// prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecCharsets {
+    /// `-fexec-charset`, for ordinary literals.
+    pub narrow: Charset,
+    /// `-fwide-exec-charset`, for `L` literals.
+    pub wide: Charset,
+    /// Whether `u8` literals are of `char8_t`, as from C++20, rather than
+    /// `char`.
+    pub char8: bool,
+}
+
+impl ExecCharsets {
+    /// UTF-8, and the target's default for wide literals, with `u8`
+    /// literals of `char` as in the default standard.
+    pub fn of(target: &Target) -> Self { Self { narrow: Charset::Utf8, wide: target.wchar_charset(), char8: false } }
+
+    /// The character set literals with `encoding` are in.
+    pub fn charset(self, encoding: Encoding) -> Charset {
+        match encoding {
+            Encoding::Ordinary => self.narrow,
+            Encoding::Wide => self.wide,
+            Encoding::Utf8 => Charset::Utf8,
+            Encoding::Utf16 => Charset::Utf16,
+            Encoding::Utf32 => Charset::Utf32,
+        }
+    }
+}
+
+impl Default for ExecCharsets {
+    fn default() -> Self { Self::of(&Target::host()) }
+}
+
+impl FromStr for Charset {
+    type Err = String;
+
+    /// Accepts the common `iconv` names and aliases, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name: String = s.chars().filter(|c| !matches!(c, '-' | '_')).collect::<String>().to_ascii_uppercase();
+        match name.as_str() {
+            "UTF8" => Ok(Charset::Utf8),
+            "ISO88591" | "LATIN1" | "L1" => Ok(Charset::Latin1),
+            "ASCII" | "USASCII" | "ANSIX3.41968" => Ok(Charset::Ascii),
+            "IBM037" | "CP037" | "EBCDICCPUS" => Ok(Charset::Ebcdic),
+            "UTF16" => Ok(Charset::Utf16),
+            "UTF32" | "UCS4" => Ok(Charset::Utf32),
+            _ => Err(format!("unknown character set `{}` (expected UTF-8, ISO-8859-1, ASCII, IBM037, UTF-16 or UTF-32)", s)),
+        }
+    }
+}
+
+impl fmt::Display for Charset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(self.name()) }
+}
+
+/// IBM037 for each Latin-1 code point.
+const EBCDIC: [u8; 256] = [
+    0x00, 0x01, 0x02, 0x03, 0x37, 0x2D, 0x2E, 0x2F, 0x16, 0x05, 0x25, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F,
+    0x10, 0x11, 0x12, 0x13, 0x3C, 0x3D, 0x32, 0x26, 0x18, 0x19, 0x3F, 0x27, 0x1C, 0x1D, 0x1E, 0x1F,
+    0x40, 0x5A, 0x7F, 0x7B, 0x5B, 0x6C, 0x50, 0x7D, 0x4D, 0x5D, 0x5C, 0x4E, 0x6B, 0x60, 0x4B, 0x61,
+    0xF0, 0xF1, 0xF2, 0xF3, 0xF4, 0xF5, 0xF6, 0xF7, 0xF8, 0xF9, 0x7A, 0x5E, 0x4C, 0x7E, 0x6E, 0x6F,
+    0x7C, 0xC1, 0xC2, 0xC3, 0xC4, 0xC5, 0xC6, 0xC7, 0xC8, 0xC9, 0xD1, 0xD2, 0xD3, 0xD4, 0xD5, 0xD6,
+    0xD7, 0xD8, 0xD9, 0xE2, 0xE3, 0xE4, 0xE5, 0xE6, 0xE7, 0xE8, 0xE9, 0xBA, 0xE0, 0xBB, 0xB0, 0x6D,
+    0x79, 0x81, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x91, 0x92, 0x93, 0x94, 0x95, 0x96,
+    0x97, 0x98, 0x99, 0xA2, 0xA3, 0xA4, 0xA5, 0xA6, 0xA7, 0xA8, 0xA9, 0xC0, 0x4F, 0xD0, 0xA1, 0x07,
+    0x20, 0x21, 0x22, 0x23, 0x24, 0x15, 0x06, 0x17, 0x28, 0x29, 0x2A, 0x2B, 0x2C, 0x09, 0x0A, 0x1B,
+    0x30, 0x31, 0x1A, 0x33, 0x34, 0x35, 0x36, 0x08, 0x38, 0x39, 0x3A, 0x3B, 0x04, 0x14, 0x3E, 0xFF,
+    0x41, 0xAA, 0x4A, 0xB1, 0x9F, 0xB2, 0x6A, 0xB5, 0xBD, 0xB4, 0x9A, 0x8A, 0x5F, 0xCA, 0xAF, 0xBC,
+    0x90, 0x8F, 0xEA, 0xFA, 0xBE, 0xA0, 0xB6, 0xB3, 0x9D, 0xDA, 0x9B, 0x8B, 0xB7, 0xB8, 0xB9, 0xAB,
+    0x64, 0x65, 0x62, 0x66, 0x63, 0x67, 0x9E, 0x68, 0x74, 0x71, 0x72, 0x73, 0x78, 0x75, 0x76, 0x77,
+    0xAC, 0x69, 0xED, 0xEE, 0xEB, 0xEF, 0xEC, 0xBF, 0x80, 0xFD, 0xFE, 0xFB, 0xFC, 0xAD, 0xAE, 0x59,
+    0x44, 0x45, 0x42, 0x46, 0x43, 0x47, 0x9C, 0x48, 0x54, 0x51, 0x52, 0x53, 0x58, 0x55, 0x56, 0x57,
+    0x8C, 0x49, 0xCD, 0xCE, 0xCB, 0xCF, 0xCC, 0xE1, 0x70, 0xDD, 0xDE, 0xDB, 0xDC, 0x8D, 0x8E, 0xDF,
+];
@@ -0,0 +1,463 @@
+//! A C header for a translation unit, as `ruscom compile --emit c-header`
+//! writes it: prototypes of the `extern "C"` functions the file defines,
+//! declarations of its `extern "C"` variables and the types they use, so
+//! that C projects can call into a library built from it.
+//!
+//! A function or variable has C language linkage if any declaration of it
+//! in the unit has, as one in a header the file includes often does.
+//! Classes with no bases, virtual functions, user-declared destructor or
+//! copy operations, whose data members all have one access and C types,
+//! become C structs; any other is declared as an incomplete struct, usable
+//! only through pointers. Enums whose underlying type is `int` become C
+//! enums, and others a typedef of it and an unnamed enum of their values.
+//! Typedefs are replaced by what they name, except the standard ones like
+//! `size_t` and `uint32_t`, whose headers are included. C has no
+//! namespaces, so names lose theirs, and the enumerators of an `enum class`
+//! are prefixed with its name, like `Color_Red`.
+//!
+//! The enums of the file and its classes that are C structs are declared
+//! whether or not anything uses them, other types only if something does.
+//! What C cannot declare, like a function taking a reference or a template
+//! specialization, or an inline function the library need not define, is
+//! skipped with a warning saying why.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::abi::{qualify, scope_of, Names};
+use crate::ast::{ClassDecl, ClassKey, Decl, EnumDecl, FunctionDecl, StorageClass, TranslationUnit, VarDecl};
+use crate::diagnostics::{Diagnostic, Diagnostics, Label};
+use crate::intern::Symbol;
+use crate::lexer::token::Span;
+use crate::mangle;
+use crate::sema::enums;
+use crate::sema::layout::Layouts;
+use crate::session::Target;
+use crate::source::{FileId, SourceManager};
+use crate::types::{Type, TypeId};
+
+/// The standard headers C code includes for typedef names, and the names.
+const STANDARD_TYPEDEFS: [(&str, &[&str]); 2] = [
+    ("stddef.h", &["size_t", "ptrdiff_t", "max_align_t"]),
+    (
+        "stdint.h",
+        &[
+            "int8_t", "int16_t", "int32_t", "int64_t", "uint8_t", "uint16_t", "uint32_t", "uint64_t", "intptr_t", "uintptr_t", "intmax_t", "uintmax_t",
+        ],
+    ),
+];
+
+/// The C header for what `unit`, parsed from `file`, defines there, for
+/// `target`, reporting what is skipped to `diagnostics`.
+pub fn generate(unit: &TranslationUnit, file: FileId, sources: &SourceManager, target: &Target, diagnostics: &mut Diagnostics) -> String {
+    let mut generator = Generator {
+        names: Names::of(unit),
+        layouts: Layouts::of_unit(unit, target),
+        file,
+        sources,
+        diagnostics,
+        enumerators: HashMap::new(),
+        types: HashMap::new(),
+        positions: 0,
+        linkage: HashSet::new(),
+        definitions: Vec::new(),
+        claimed: HashMap::new(),
+        structs: HashMap::new(),
+        needed: HashSet::new(),
+        includes: BTreeSet::new(),
+        prototypes: String::new(),
+    };
+    generator.collect(&unit.decls, "", true);
+    // The file's own types first, so that they keep their names.
+    let mut types: Vec<String> = generator.types.iter().filter(|(_, t)| t.here).map(|(name, _)| name.clone()).collect();
+    types.sort_by_key(|name| generator.types[name].position);
+    for name in types {
+        let (span, is_class) = (generator.types[&name].span, matches!(generator.types[&name].kind, Kind::Class(_)));
+        if is_class && generator.c_struct(&name).is_err() { continue; }
+        if let Err(why) = generator.named(&name, true) { generator.skip(if is_class { "class" } else { "enum" }, &name, span, why); }
+    }
+    for definition in std::mem::take(&mut generator.definitions) {
+        match definition {
+            Definition::Function(f, name) if f.specifiers.is_extern_c || generator.linkage.contains(&name) => generator.function(f, &name),
+            Definition::Variable(v, name) if v.specifiers.is_extern_c || generator.linkage.contains(&name) => generator.variable(v, &name),
+            _ => {}
+        }
+    }
+    // Define the classes used that are C structs, whose fields may use more.
+    loop {
+        let pending: Vec<String> = generator.needed.iter().filter(|&n| matches!(generator.types[n].kind, Kind::Class(_)) && !generator.structs.contains_key(n)).cloned().collect();
+        if pending.is_empty() { break; }
+        for name in pending { let _ = generator.c_struct(&name); }
+    }
+    generator.render(&target.triple())
+}
+
+/// A function or variable the file defines, and its qualified name.
+enum Definition<'u> {
+    Function(&'u FunctionDecl<'u>, String),
+    Variable(&'u VarDecl<'u>, String),
+}
+
+enum Kind<'u> {
+    Class(&'u ClassDecl<'u>),
+    Enum(&'u EnumDecl<'u>, Vec<Option<i128>>),
+}
+
+/// A class or enum of the unit.
+struct TypeDecl<'u> {
+    /// Its definition, if it has one, else its first declaration.
+    kind: Kind<'u>,
+    span: Span,
+    /// Where its definition comes among the others, or its declaration.
+    position: usize,
+    /// Whether the file defines it.
+    here: bool,
+}
+
+/// Why a type has no C equivalent, and where it was used.
+struct Unrepresentable {
+    /// Like "return type is" or "parameter `p` has type".
+    place: String,
+    ty: TypeId,
+    why: String,
+}
+
+/// Where a type is used, which decides what it may be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Use {
+    Value,
+    /// Pointed to, where `void` and incomplete types may be.
+    Pointee,
+    Return,
+}
+
+struct Generator<'u, 's, 'd> {
+    names: Names,
+    layouts: Layouts,
+    file: FileId,
+    sources: &'s SourceManager,
+    diagnostics: &'d mut Diagnostics,
+    /// The values of the enumerators declared so far.
+    enumerators: HashMap<Symbol, i128>,
+    /// The classes and enums of the unit, by qualified name.
+    types: HashMap<String, TypeDecl<'u>>,
+    positions: usize,
+    /// The functions and variables declared `extern "C"`, by qualified name.
+    linkage: HashSet<String>,
+    definitions: Vec<Definition<'u>>,
+    /// The C names taken so far, by what took them and where it is declared.
+    claimed: HashMap<String, (String, Span)>,
+    /// The fields of the classes that are C structs, or why one is not.
+    structs: HashMap<String, Result<Vec<String>, String>>,
+    /// The classes and enums the header declares.
+    needed: HashSet<String>,
+    includes: BTreeSet<&'static str>,
+    /// The declarations of the functions and variables.
+    prototypes: String,
+}
+
+impl<'u> Generator<'u, '_, '_> {
+    /// Whether `span` is in the file the header is for.
+    fn here(&self, span: Span) -> bool { self.sources.file_at(span.start).is_some_and(|f| f.id == self.file) }
+
+    fn skip(&mut self, what: &str, name: &str, span: Span, why: impl std::fmt::Display) {
+        self.diagnostics.emit(Diagnostic::warning(format!("skipped {} `{}`: {}", what, name, why)).with_label(Label::primary(span, "")));
+    }
+
+    fn skip_unrepresentable(&mut self, what: &str, name: &str, span: Span, u: Unrepresentable) {
+        let why = format!("its {} `{}`, and {}", u.place, u.ty, u.why);
+        self.skip(what, name, span, why);
+    }
+
+    /// Takes the C name `c` for `owner`, declared at `span`, unless
+    /// something else has it; then where that is declared.
+    fn claim(&mut self, c: &str, owner: &str, span: Span) -> Result<(), Span> {
+        match self.claimed.get(c) {
+            Some((earlier, _)) if earlier == owner => Ok(()),
+            Some(&(_, at)) => Err(at),
+            None => {
+                self.claimed.insert(c.to_string(), (owner.to_string(), span));
+                Ok(())
+            }
+        }
+    }
+
+    /// Claims the C name of the function, variable or enumerator `name`,
+    /// warning if it is taken.
+    fn claim_value(&mut self, what: &str, name: &str, c: &str, span: Span) -> bool {
+        let Err(earlier) = self.claim(c, name, span) else { return true };
+        self.diagnostics.emit(Diagnostic::warning(format!("skipped {} `{}`: its C name `{}` is taken, and C has no namespaces or overloading", what, name, c))
+            .with_label(Label::primary(span, ""))
+            .with_label(Label::secondary(earlier, "declared under that name here")));
+        false
+    }
+
+    /// Records the classes, enums and definitions of `decls`, declared in
+    /// `scope`; what is not `visible`, in an unnamed namespace, has
+    /// internal linkage.
+    fn collect(&mut self, decls: &'u [Decl<'u>], scope: &str, visible: bool) {
+        for decl in decls { self.collect_decl(decl, scope, visible); }
+    }
+
+    fn collect_decl(&mut self, decl: &'u Decl<'u>, scope: &str, visible: bool) {
+        match decl {
+            Decl::Namespace(n) => match n.name {
+                Some(name) => self.collect(&n.decls, &qualify(scope, &name.as_str()), visible),
+                None => self.collect(&n.decls, scope, false),
+            },
+            Decl::Export(e) => self.collect(&e.decls, scope, visible),
+            Decl::Class(c) => {
+                let Some(name) = c.name else { return };
+                let qualified = qualify(scope, &name.as_str());
+                self.declare_type(&qualified, Kind::Class(c), c.span, c.members.is_some(), visible);
+                for member in c.members.iter().flatten() {
+                    if matches!(member.decl, Decl::Class(_) | Decl::Enum(_)) { self.collect_decl(&member.decl, &qualified, visible); }
+                }
+            }
+            Decl::Enum(e) => {
+                let values = enums::values(e, &self.layouts, &mut self.enumerators, &mut Diagnostics::new());
+                let Some(name) = e.name else { return };
+                self.declare_type(&qualify(scope, &name.as_str()), Kind::Enum(e, values), e.span, e.enumerators.is_some(), visible);
+            }
+            Decl::Function(f) => {
+                let name = qualify(scope, &f.name.as_str());
+                if f.specifiers.is_extern_c { self.linkage.insert(name.clone()); }
+                if visible && f.body.is_some() && !self.names.is_class(scope_of(&name)) && f.specifiers.storage != StorageClass::Static && self.here(f.name_span) {
+                    self.definitions.push(Definition::Function(f, name));
+                }
+            }
+            Decl::Var(v) => {
+                let name = qualify(scope, &v.name.as_str());
+                if v.specifiers.is_extern_c { self.linkage.insert(name.clone()); }
+                let extern_ = v.specifiers.storage == StorageClass::Extern;
+                let internal = v.specifiers.storage == StorageClass::Static || (!extern_ && (v.ty.is_const() || v.specifiers.is_constexpr));
+                if visible && !internal && (!extern_ || v.init.is_some()) && self.here(v.span) { self.definitions.push(Definition::Variable(v, name)); }
+            }
+            _ => {}
+        }
+    }
+
+    fn declare_type(&mut self, qualified: &str, kind: Kind<'u>, span: Span, is_definition: bool, visible: bool) {
+        let here = visible && self.here(span);
+        let position = self.positions;
+        self.positions += 1;
+        match self.types.get_mut(qualified) {
+            Some(t) if is_definition => *t = TypeDecl { kind, span, position, here },
+            Some(_) => {}
+            None => { self.types.insert(qualified.to_string(), TypeDecl { kind, span, position, here }); }
+        }
+    }
+
+    /// The C name of the class or enum `name`, declared in the header,
+    /// or why it cannot be; `by_value` if it is used as more than what a
+    /// pointer points to, which only a C struct or enum can be.
+    fn named(&mut self, name: &str, by_value: bool) -> Result<String, String> {
+        let Some(t) = self.types.get(name) else { return Err(format!("`{}` is not a class or enum of this unit", name)) };
+        let (c, span, is_class) = (last(name).to_string(), t.span, matches!(t.kind, Kind::Class(_)));
+        if self.claim(&c, name, span).is_err() { return Err(format!("the C name of `{}`, `{}`, is taken", name, c)); }
+        if by_value && is_class {
+            if let Err(why) = self.c_struct(name) { return Err(format!("`{}` is not a C struct: {}", name, why)); }
+        }
+        self.needed.insert(name.to_string());
+        Ok(c)
+    }
+
+    /// The fields of the class `name` as a C struct, or why it is not one.
+    fn c_struct(&mut self, name: &str) -> Result<Vec<String>, String> {
+        if let Some(fields) = self.structs.get(name) { return fields.clone(); }
+        // It cannot contain itself.
+        self.structs.insert(name.to_string(), Err("it is incomplete".to_string()));
+        let Some(&Kind::Class(c)) = self.types.get(name).map(|t| &t.kind) else { return Err("it is not a class".to_string()) };
+        let result = self.fields(c, name);
+        self.structs.insert(name.to_string(), result.clone());
+        result
+    }
+
+    fn fields(&mut self, c: &ClassDecl, name: &str) -> Result<Vec<String>, String> {
+        let Some(members) = &c.members else { return Err("it is incomplete".to_string()) };
+        if let Some(why) = self.names.not_c_struct(c, name) { return Err(why.to_string()); }
+        let mut fields = Vec::new();
+        for member in members {
+            let Decl::Var(v) = &member.decl else { continue };
+            if v.specifiers.storage == StorageClass::Static { continue; }
+            match self.declaration(v.ty, v.name.as_str().to_string(), Use::Value, name) {
+                Ok(field) => match v.bit_width.as_ref().and_then(|w| w.bits) {
+                    Some(bits) => fields.push(format!("    {} : {};", field, bits)),
+                    None => fields.push(format!("    {};", field)),
+                },
+                Err(why) => return Err(format!("its field `{}` has type `{}`, and {}", v.name, v.ty, why)),
+            }
+        }
+        Ok(fields)
+    }
+
+    /// The C declaration of `declarator` with type `ty`, used in `scope`,
+    /// or why there is none; an empty `declarator` makes a type name.
+    fn declaration(&mut self, ty: TypeId, declarator: String, position: Use, scope: &str) -> Result<String, String> {
+        let quals = ty.qualifiers();
+        let cv = [(quals.is_const, "const"), (quals.is_volatile, "volatile")].into_iter().filter(|&(on, _)| on).map(|(_, q)| q).collect::<Vec<_>>().join(" ");
+        let base = match ty.unqualified().get() {
+            Type::Void if position == Use::Value => return Err("`void` is not a value".to_string()),
+            Type::Void => "void".to_string(),
+            Type::Bool => {
+                self.includes.insert("stdbool.h");
+                "bool".to_string()
+            }
+            Type::Char => "char".to_string(),
+            Type::SignedChar => "signed char".to_string(),
+            Type::UnsignedChar => "unsigned char".to_string(),
+            Type::Short => "short".to_string(),
+            Type::UnsignedShort => "unsigned short".to_string(),
+            Type::Int => "int".to_string(),
+            Type::UnsignedInt => "unsigned int".to_string(),
+            Type::Long => "long".to_string(),
+            Type::UnsignedLong => "unsigned long".to_string(),
+            Type::LongLong => "long long".to_string(),
+            Type::UnsignedLongLong => "unsigned long long".to_string(),
+            Type::WChar => {
+                self.includes.insert("stddef.h");
+                "wchar_t".to_string()
+            }
+            // C has these as typedefs of the same names.
+            ty @ (Type::Char8 | Type::Char16 | Type::Char32) => {
+                self.includes.insert("uchar.h");
+                ty.to_string()
+            }
+            Type::Float => "float".to_string(),
+            Type::Double => "double".to_string(),
+            Type::LongDouble => "long double".to_string(),
+            Type::Pointer(pointee) => {
+                let cv = if cv.is_empty() { cv } else { format!("{} ", cv) };
+                let inner = format!("*{}{}", cv, declarator);
+                let inner = if matches!(pointee.unqualified().get(), Type::Array(..) | Type::Function { .. }) { format!("({})", inner) } else { inner };
+                return self.declaration(pointee, inner, Use::Pointee, scope);
+            }
+            Type::Array(element, bound) => return self.declaration(element, format!("{}[{}]", declarator, bound.map_or(String::new(), |n| n.to_string())), Use::Value, scope),
+            Type::Function { ret, params, variadic } => {
+                let mut list = params.iter().map(|&p| self.declaration(p, String::new(), Use::Value, scope)).collect::<Result<Vec<_>, _>>()?;
+                if variadic { list.push("...".to_string()); }
+                if list.is_empty() { list.push("void".to_string()); }
+                return self.declaration(ret, format!("{}({})", declarator, list.join(", ")), Use::Return, scope);
+            }
+            Type::Named(name) => {
+                let text = name.as_str();
+                let spelling = text.trim_start_matches("::");
+                let spelling = spelling.strip_prefix("std::").unwrap_or(spelling);
+                if let Some(&(header, _)) = STANDARD_TYPEDEFS.iter().find(|(_, names)| names.contains(&spelling)) {
+                    self.includes.insert(header);
+                    spelling.to_string()
+                } else {
+                    match self.names.resolve(ty.unqualified(), scope).get() {
+                        Type::Named(name) => self.named(&name.as_str(), position != Use::Pointee)?,
+                        _ => return self.declaration(self.names.resolve(ty, scope), declarator, position, scope),
+                    }
+                }
+            }
+            Type::Reference(_) | Type::RvalueReference(_) => return Err("references have no C equivalent".to_string()),
+            Type::Specialization(..) => return Err("template specializations have no C equivalent".to_string()),
+            Type::Auto => return Err("deduced types have no C equivalent".to_string()),
+            Type::Qualified(..) => unreachable!("unqualified"),
+        };
+        let cv = if cv.is_empty() { cv } else { format!("{} ", cv) };
+        let space = if declarator.is_empty() { "" } else { " " };
+        Ok(format!("{}{}{}{}", cv, base, space, declarator))
+    }
+
+    /// Declares the function `f`, named `name`, unless it is inline.
+    fn function(&mut self, f: &FunctionDecl, name: &str) {
+        if f.specifiers.is_inline || f.specifiers.is_constexpr {
+            return self.skip("function", name, f.name_span, "it is inline, so the library need not define it");
+        }
+        let scope = scope_of(name);
+        let Type::Function { ret, params, variadic } = f.ty.get() else { return };
+        let mut parameters = Vec::new();
+        for (i, (p, &ty)) in f.params.iter().zip(&params).enumerate() {
+            let param = p.name.map_or_else(|| format!("arg{}", i), |n| n.as_str().to_string());
+            match self.declaration(ty, p.name.map_or_else(String::new, |n| n.as_str().to_string()), Use::Value, scope) {
+                Ok(declaration) => parameters.push(declaration),
+                Err(why) => return self.skip_unrepresentable("function", name, f.name_span, Unrepresentable { place: format!("parameter `{}` has type", param), ty, why }),
+            }
+        }
+        if variadic { parameters.push("...".to_string()); }
+        if parameters.is_empty() { parameters.push("void".to_string()); }
+        let c = mangle::extern_c(name);
+        let declarator = format!("{}({})", c, parameters.join(", "));
+        let declaration = match self.declaration(ret, declarator, Use::Return, scope) {
+            Ok(declaration) => declaration,
+            Err(why) => return self.skip_unrepresentable("function", name, f.name_span, Unrepresentable { place: "return type is".to_string(), ty: ret, why }),
+        };
+        if self.claim_value("function", name, &c, f.name_span) { let _ = writeln!(self.prototypes, "{};", declaration); }
+    }
+
+    fn variable(&mut self, v: &VarDecl, name: &str) {
+        let c = mangle::extern_c(name);
+        let declaration = match self.declaration(v.ty, c.clone(), Use::Value, scope_of(name)) {
+            Ok(declaration) => declaration,
+            Err(why) => return self.skip_unrepresentable("variable", name, v.name_span, Unrepresentable { place: "type is".to_string(), ty: v.ty, why }),
+        };
+        if self.claim_value("variable", name, &c, v.name_span) { let _ = writeln!(self.prototypes, "extern {};", declaration); }
+    }
+
+    /// The C definition of the enum `e`, named `name`.
+    fn enumeration(&mut self, e: &EnumDecl, values: &[Option<i128>], name: &str) -> String {
+        let c = last(name);
+        let underlying = e.underlying.or_else(|| e.name.and_then(|n| self.layouts.underlying(n))).unwrap_or(TypeId::intern(Type::Int));
+        let mut enumerators = Vec::new();
+        for (n, value) in e.enumerators.iter().flatten().zip(values) {
+            let enumerator = format!("{}::{}", name, n.name);
+            let Some(value) = *value else { continue };
+            if i32::try_from(value).is_err() {
+                self.skip("enumerator", &enumerator, n.span, "its value does not fit in a C `int`");
+                continue;
+            }
+            let constant = if e.is_scoped { format!("{}_{}", c, n.name) } else { n.name.as_str().to_string() };
+            if self.claim_value("enumerator", &enumerator, &constant, n.span) { enumerators.push(format!("    {} = {}", constant, value)); }
+        }
+        let body = if enumerators.is_empty() { String::new() } else { format!(" {{\n{}\n}}", enumerators.join(",\n")) };
+        if self.names.resolve(underlying, scope_of(name)).unqualified().get() == Type::Int && !body.is_empty() { return format!("typedef enum {0}{1} {0};\n", c, body); }
+        let ty = self.declaration(underlying, c.to_string(), Use::Value, scope_of(name)).unwrap_or_else(|_| format!("int {}", c));
+        if body.is_empty() { format!("typedef {};\n", ty) } else { format!("typedef {};\nenum{};\n", ty, body) }
+    }
+
+    fn render(&mut self, triple: &str) -> String {
+        let mut needed: Vec<String> = self.needed.iter().cloned().collect();
+        needed.sort_by_key(|name| self.types[name].position);
+        let mut forward = String::new();
+        let mut definitions = Vec::new();
+        for name in &needed {
+            let c = last(name);
+            match &self.types[name].kind {
+                &Kind::Class(class) => {
+                    let key = if class.key == ClassKey::Union { "union" } else { "struct" };
+                    let _ = writeln!(forward, "typedef {0} {1} {1};", key, c);
+                    let Some(Ok(fields)) = self.structs.get(name) else { continue };
+                    // An empty C++ class has a size of 1, and C has none.
+                    let fields = if fields.is_empty() { "    char _address;".to_string() } else { fields.join("\n") };
+                    definitions.push(format!("{} {} {{\n{}\n}};\n", key, c, fields));
+                }
+                Kind::Enum(e, values) => {
+                    let (e, values) = (*e, values.clone());
+                    definitions.push(self.enumeration(e, &values, name));
+                }
+            }
+        }
+        let path = &self.sources.file(self.file).path;
+        let stem = path.file_stem().map_or("", |s| s.to_str().unwrap_or_default());
+        let guard: String = stem.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect::<String>() + "_H";
+        let guard = if guard.starts_with(|c: char| c.is_ascii_digit()) { format!("_{}", guard) } else { guard };
+        let mut out = format!("/* Generated by ruscom from {} for {}. */\n#ifndef {2}\n#define {2}\n", path.display(), triple, guard);
+        if !self.includes.is_empty() {
+            out.push('\n');
+            for header in &self.includes { let _ = writeln!(out, "#include <{}>", header); }
+        }
+        out.push_str("\n#ifdef __cplusplus\nextern \"C\" {\n#endif\n");
+        if !forward.is_empty() { let _ = write!(out, "\n{}", forward); }
+        for definition in definitions { let _ = write!(out, "\n{}", definition); }
+        if !self.prototypes.is_empty() { let _ = write!(out, "\n{}", self.prototypes); }
+        out.push_str("\n#ifdef __cplusplus\n}\n#endif\n\n#endif\n");
+        out
+    }
+}
+
+/// The last component of the qualified `name`, its C name.
+fn last(name: &str) -> &str { name.rsplit("::").next().unwrap_or(name) }
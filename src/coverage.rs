@@ -0,0 +1,227 @@
+//! How much of a project the parser handles: what `ruscom analyze` and
+//! `ruscom coverage-report` report.
+//!
+//! Parsed with `Options::skip_unsupported`, a translation unit keeps an
+//! `UnsupportedConstruct` for each declaration or statement the parser
+//! skipped. Coverage counts those and the declarations and statements it
+//! did parse, by kind, in the main file only, so a header included by
+//! every file is not counted once per file. A `Report` adds up the
+//! coverage of every source file and header in a project, each parsed as
+//! a main file of its own.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::ast::{ConstructCategory, Decl, Stmt, StmtKind, TranslationUnit, UnsupportedConstruct};
+use crate::lexer::token::Span;
+use crate::visit::Visitor;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Coverage {
+    /// Declarations and statements parsed.
+    pub parsed: usize,
+    /// How many of them there are of each kind, like `function` or
+    /// `range-based for`.
+    pub kinds: BTreeMap<&'static str, usize>,
+    /// The constructs skipped, in source order.
+    pub unsupported: Vec<UnsupportedConstruct>,
+}
+
+impl Coverage {
+    pub fn of(tu: &TranslationUnit) -> Self {
+        let mut counter = Counter { main: tu.span, coverage: Coverage::default() };
+        counter.visit_translation_unit(tu);
+        counter.coverage
+    }
+
+    /// Declarations and statements, parsed or not.
+    pub fn total(&self) -> usize { self.parsed + self.unsupported.len() }
+
+    /// The percentage of constructs parsed: 100 for a unit with none.
+    pub fn percent(&self) -> f64 {
+        if self.total() == 0 { 100.0 } else { self.parsed as f64 * 100.0 / self.total() as f64 }
+    }
+
+    /// Adds the coverage of another unit.
+    pub fn merge(&mut self, other: Coverage) {
+        self.parsed += other.parsed;
+        for (kind, count) in other.kinds { *self.kinds.entry(kind).or_default() += count; }
+        self.unsupported.extend(other.unsupported);
+    }
+
+    /// How many constructs of each category were skipped, most first.
+    pub fn by_category(&self) -> Vec<(ConstructCategory, usize)> {
+        let mut counts: BTreeMap<ConstructCategory, usize> = BTreeMap::new();
+        for u in &self.unsupported { *counts.entry(u.category).or_default() += 1; }
+        most_first(counts)
+    }
+
+    /// How many constructs of each kind were parsed, most first.
+    pub fn by_kind(&self) -> Vec<(&'static str, usize)> { most_first(self.kinds.clone()) }
+}
+
+fn most_first<K>(counts: BTreeMap<K, usize>) -> Vec<(K, usize)> {
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by_key(|&(_, count)| Reverse(count));
+    counts
+}
+
+/// `parsed 41 of 43 constructs (95.3%)`.
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "parsed {} of {} constructs ({:.1}%)", self.parsed, self.total(), self.percent())
+    }
+}
+
+struct Counter {
+    main: Span,
+    coverage: Coverage,
+}
+
+impl Counter {
+    fn in_main_file(&self, span: Span) -> bool { self.main.start <= span.start && span.end <= self.main.end }
+
+    fn parsed(&mut self, kind: &'static str) {
+        self.coverage.parsed += 1;
+        *self.coverage.kinds.entry(kind).or_default() += 1;
+    }
+}
+
+impl Visitor for Counter {
+    fn visit_decl(&mut self, decl: &Decl) {
+        if !self.in_main_file(decl.span()) { return; }
+        if let Some(kind) = decl_kind(decl) { self.parsed(kind); }
+        self.walk_decl(decl);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if let Some(kind) = stmt_kind(&stmt.kind) { self.parsed(kind); }
+        self.walk_stmt(stmt);
+    }
+
+    fn visit_unsupported(&mut self, u: &UnsupportedConstruct) {
+        if self.in_main_file(u.span) { self.coverage.unsupported.push(*u); }
+    }
+}
+
+fn decl_kind(decl: &Decl) -> Option<&'static str> {
+    Some(match decl {
+        Decl::Var(_) => "variable",
+        Decl::Decomposition(_) => "structured binding",
+        Decl::Function(_) => "function",
+        Decl::Typedef(_) => "typedef",
+        Decl::Namespace(_) => "namespace",
+        Decl::Using(_) => "using declaration",
+        Decl::UsingDirective(_) => "using directive",
+        Decl::Class(_) => "class",
+        Decl::Enum(_) => "enum",
+        Decl::Template(_) => "template",
+        Decl::Concept(_) => "concept",
+        Decl::Module(_) => "module declaration",
+        Decl::Import(_) => "import",
+        Decl::Export(_) => "export",
+        Decl::StaticAssert(_) => "static_assert",
+        Decl::Unsupported(_) | Decl::Invalid(_) => return None,
+    })
+}
+
+fn stmt_kind(kind: &StmtKind) -> Option<&'static str> {
+    Some(match kind {
+        StmtKind::Compound(_) => "block",
+        StmtKind::Null => "null statement",
+        StmtKind::Expr(_) => "expression statement",
+        StmtKind::Decl(_) => "declaration statement",
+        StmtKind::If { .. } => "if",
+        StmtKind::While { .. } => "while",
+        StmtKind::DoWhile { .. } => "do-while",
+        StmtKind::For { .. } => "for",
+        StmtKind::RangeFor { .. } => "range-based for",
+        StmtKind::Switch { .. } => "switch",
+        StmtKind::Case { .. } | StmtKind::Default(_) => "case label",
+        StmtKind::Break => "break",
+        StmtKind::Continue => "continue",
+        StmtKind::Return(_) => "return",
+        StmtKind::CoReturn(_) => "co_return",
+        StmtKind::Attributed { .. } => "attributed statement",
+        StmtKind::Unsupported(_) | StmtKind::Invalid => return None,
+    })
+}
+
+/// The extensions of the files `coverage-report` parses.
+pub const SOURCE_EXTENSIONS: &[&str] = &["c", "cc", "cpp", "cxx", "c++", "h", "hh", "hpp", "hxx", "h++", "ipp", "inl"];
+
+/// The C and C++ source files and headers under `dir`, sorted, skipping
+/// hidden directories like `.git`.
+pub fn sources(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    collect_sources(dir, &mut found)?;
+    found.sort();
+    Ok(found)
+}
+
+fn collect_sources(dir: &Path, found: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.file_name().is_some_and(|n| n.to_string_lossy().starts_with('.')) { continue; }
+        let meta = fs::metadata(&path)?;
+        if meta.is_dir() {
+            collect_sources(&path, found)?;
+        } else if meta.is_file() && path.extension().is_some_and(|e| SOURCE_EXTENSIONS.contains(&e.to_string_lossy().as_ref())) {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The coverage of a whole project.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    pub files: usize,
+    /// The files that parsed without skipping anything.
+    pub complete_files: usize,
+    pub coverage: Coverage,
+    /// How many files use each unsupported category.
+    pub files_using: BTreeMap<ConstructCategory, usize>,
+}
+
+impl Report {
+    pub fn add(&mut self, coverage: Coverage) {
+        self.files += 1;
+        if coverage.unsupported.is_empty() { self.complete_files += 1; }
+        let mut used: Vec<ConstructCategory> = coverage.unsupported.iter().map(|u| u.category).collect();
+        used.sort();
+        used.dedup();
+        for category in used { *self.files_using.entry(category).or_default() += 1; }
+        self.coverage.merge(coverage);
+    }
+
+    /// The unsupported categories with how many constructs and files use
+    /// each, the ones that would parse the most files first: what to
+    /// implement next.
+    pub fn roadmap(&self) -> Vec<(ConstructCategory, usize, usize)> {
+        let mut roadmap: Vec<_> = self.coverage.by_category().into_iter().map(|(c, n)| (c, n, self.files_using[&c])).collect();
+        roadmap.sort_by_key(|&(_, constructs, files)| Reverse((files, constructs)));
+        roadmap
+    }
+}
+
+/// The summary `ruscom coverage-report` prints.
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} files, {} parsed completely", self.files, self.complete_files)?;
+        writeln!(f, "{}", self.coverage)?;
+        writeln!(f, "supported constructs encountered:")?;
+        for (kind, count) in self.coverage.by_kind() { writeln!(f, "  {:>7} {}", count, kind)?; }
+        if !self.coverage.unsupported.is_empty() {
+            writeln!(f, "unsupported constructs, by files affected:")?;
+            for (category, constructs, files) in self.roadmap() {
+                writeln!(f, "  {:>7} {} (in {} file{})", constructs, category, files, if files == 1 { "" } else { "s" })?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,388 @@
+//! `ruscom daemon`: a JSON-RPC 2.0 analysis service over stdio.
+//!
+//! Requests and responses are one JSON object per line. The daemon keeps a
+//! single `Session` for its whole lifetime, so interned symbols and loaded
+//! sources are reused across requests instead of being rebuilt each time.
+//! A request's `source` replaces what was loaded under its `path` before.
+//!
+//! Methods:
+//! - `tokenize {source, path?}` -> `{file, tokens, diagnostics}`
+//! - `parse {source, path?}` -> `{file, ast, diagnostics}`, the `ast`
+//!   shaped like `ast-dump --format json`
+//! - `inlayHints {source, path?, parameterNames?, deducedTypes?}` ->
+//!   `{hints, diagnostics}`, each hint shaped like an LSP `InlayHint`
+//!   with a 0-based `position`; both kinds are on unless turned off
+//! - `rename {source, path?, position, newName}` -> an LSP
+//!   `WorkspaceEdit` whose `changes` are keyed by path, renaming the
+//!   name at the 0-based `position` everywhere the reference index finds
+//!   it; a rename that would change what some name refers to is refused
+//!   with `REQUEST_FAILED`. Also `textDocument/rename`.
+//! - `onTypeFormatting {source, position, ch, options?}` -> LSP
+//!   `TextEdit`s re-indenting the lines around the 0-based `position`
+//!   after `ch` was typed there: the block a `}` closes, the lines either
+//!   side of a newline, or the line of anything else. `options` takes
+//!   LSP's `tabSize` and `insertSpaces`. Also
+//!   `textDocument/onTypeFormatting`.
+//! - `codeAction {source, path?, range?}` -> LSP `CodeAction`s of kind
+//!   `quickfix` for the diagnostics whose primary span meets the 0-based
+//!   `range`, one per diagnostic with fix-its, and one adding the missing
+//!   `#include` for each standard header whose names are used in the
+//!   range without it. Without a range, for the whole file. Also
+//!   `textDocument/codeAction`.
+//! - `stats` -> `{files, symbols}`
+//! - `shutdown` -> `null`, then the daemon stops reading
+//!
+//! Positions and ranges are LSP's: a 0-based line, and a `character`
+//! counted in UTF-16 code units, LSP's default position encoding.
+
+use std::io::{self, BufRead, Write};
+
+use serde_json::{json, Map, Value};
+
+use crate::arena::TuArena;
+use crate::ast::{self, DumpFormat, DumpOptions};
+use crate::diagnostics::json::to_json;
+use crate::diagnostics::Diagnostic;
+use crate::driver::DriverError;
+use crate::format::{self, FormatOptions};
+use crate::parser::Parser;
+use crate::lexer::token::Span;
+use crate::preprocessor::Preprocessor;
+use crate::rewrite::Rewriter;
+use crate::sema::{self, includes};
+use crate::sema::hints::{self, HintKind, HintOptions};
+use crate::sema::references::{ReferenceIndex, RenameError};
+use crate::session::Session;
+use crate::source::{SourceFile, SourceManager};
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// LSP's code for a valid request that cannot be carried out, like a
+/// rename to a name already in use.
+pub const REQUEST_FAILED: i64 = -32803;
+/// A compiler error that is not the client's fault (cancelled, unsupported).
+pub const COMPILER_ERROR: i64 = -32000;
+
+/// An error response: JSON-RPC code and message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self { Self { code, message: message.into() } }
+}
+
+impl From<DriverError> for RpcError {
+    fn from(e: DriverError) -> Self { RpcError::new(COMPILER_ERROR, e.to_string()) }
+}
+
+pub struct Daemon {
+    session: Session,
+    shutdown: bool,
+}
+
+impl Daemon {
+    pub fn new(session: Session) -> Self { Self { session, shutdown: false } }
+
+    pub fn session(&self) -> &Session { &self.session }
+
+    /// Whether a `shutdown` request has been handled.
+    pub fn is_shut_down(&self) -> bool { self.shutdown }
+
+    /// Handles one request line. Returns the response line, or `None` for
+    /// notifications (requests without an `id`).
+    pub fn handle_line(&mut self, line: &str) -> Option<String> {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(v) => v,
+            Err(e) => return Some(error_response(Value::Null, RpcError::new(PARSE_ERROR, e.to_string())).to_string()),
+        };
+        self.handle(&request).map(|v| v.to_string())
+    }
+
+    /// Handles one decoded request.
+    pub fn handle(&mut self, request: &Value) -> Option<Value> {
+        let id = request.get("id").cloned();
+        let method = match request.get("method").and_then(Value::as_str) {
+            Some(m) if request.get("jsonrpc") == Some(&json!("2.0")) => m,
+            _ => return Some(error_response(id.unwrap_or(Value::Null), RpcError::new(INVALID_REQUEST, "not a JSON-RPC 2.0 request"))),
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        let result = self.dispatch(method, &params);
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => error_response(id, e),
+        })
+    }
+
+    fn dispatch(&mut self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        match method {
+            "tokenize" => self.tokenize(params),
+            "parse" => self.parse(params),
+            "inlayHints" => self.inlay_hints(params),
+            "rename" | "textDocument/rename" => self.rename(params),
+            "codeAction" | "textDocument/codeAction" => self.code_actions(params),
+            "onTypeFormatting" | "textDocument/onTypeFormatting" => on_type_formatting(params),
+            "stats" => Ok(json!({
+                "files": self.session.sources.files().len(),
+                "symbols": self.session.interner.len(),
+            })),
+            "shutdown" => { self.shutdown = true; Ok(Value::Null) }
+            other => Err(RpcError::new(METHOD_NOT_FOUND, format!("unknown method `{}`", other))),
+        }
+    }
+
+    fn tokenize(&mut self, params: &Value) -> Result<Value, RpcError> {
+        let source = params.get("source").and_then(Value::as_str)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "missing string param `source`"))?;
+        let path = params.get("path").and_then(Value::as_str).unwrap_or("<input>");
+        let file = self.session.sources.set_file(path, source);
+        // Frees the names the tokens intern.
+        let _arena = TuArena::new();
+        let output = self.session.tokenize_file(file).map_err(DriverError::from)?;
+        let sources = &self.session.sources;
+        let base = sources.file(file).start;
+        let tokens: Vec<Value> = output.tokens.iter().map(|t| json!({
+            "kind": format!("{:?}", t.token.kind()),
+            "text": sources.snippet(t.span).unwrap_or_default(),
+            "start": t.span.start - base,
+            "end": t.span.end - base,
+        })).collect();
+        let diagnostics: Vec<Value> = self.session.diagnostics.iter().map(|d| to_json(d, sources)).collect();
+        self.session.diagnostics.take();
+        Ok(json!({ "file": file.index(), "tokens": tokens, "diagnostics": diagnostics }))
+    }
+
+    fn parse(&mut self, params: &Value) -> Result<Value, RpcError> {
+        let source = params.get("source").and_then(Value::as_str)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "missing string param `source`"))?;
+        let path = params.get("path").and_then(Value::as_str).unwrap_or("<input>");
+        let file = self.session.sources.set_file(path, source);
+        let arena = TuArena::new();
+        let output = Preprocessor::new(&mut self.session).run(file).map_err(DriverError::from)?;
+        let unit = Parser::new(&mut self.session, &arena, output.tokens).parse().map_err(DriverError::from)?;
+        let sources = &self.session.sources;
+        let dump = ast::dump_as(&unit, sources, DumpOptions { format: DumpFormat::Json, spans: false });
+        let ast: Value = serde_json::from_str(&dump).map_err(|e| RpcError::new(COMPILER_ERROR, e.to_string()))?;
+        let diagnostics: Vec<Value> = self.session.diagnostics.iter().map(|d| to_json(d, sources)).collect();
+        self.session.diagnostics.take();
+        Ok(json!({ "file": file.index(), "ast": ast, "diagnostics": diagnostics }))
+    }
+
+    fn inlay_hints(&mut self, params: &Value) -> Result<Value, RpcError> {
+        let source = params.get("source").and_then(Value::as_str)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "missing string param `source`"))?;
+        let path = params.get("path").and_then(Value::as_str).unwrap_or("<input>");
+        let enabled = |name: &str| params.get(name).and_then(Value::as_bool).unwrap_or(true);
+        let options = HintOptions { parameter_names: enabled("parameterNames"), deduced_types: enabled("deducedTypes") };
+        let file = self.session.sources.set_file(path, source);
+        let arena = TuArena::new();
+        let output = Preprocessor::new(&mut self.session).run(file).map_err(DriverError::from)?;
+        let unit = Parser::new(&mut self.session, &arena, output.tokens).parse().map_err(DriverError::from)?;
+        let sources = &self.session.sources;
+        let hints: Vec<Value> = hints::inlay_hints(&unit, &self.session.target, self.session.exec_charsets(), options).iter().filter_map(|h| {
+            // LSP's `InlayHintKind`: 1 for types, 2 for parameters.
+            let kind = if h.kind == HintKind::Type { 1 } else { 2 };
+            Some(json!({
+                "position": lsp_position(sources, h.position)?,
+                "label": h.label,
+                "kind": kind,
+                "paddingRight": h.kind == HintKind::Parameter,
+            }))
+        }).collect();
+        let diagnostics: Vec<Value> = self.session.diagnostics.iter().map(|d| to_json(d, sources)).collect();
+        self.session.diagnostics.take();
+        Ok(json!({ "hints": hints, "diagnostics": diagnostics }))
+    }
+
+    fn rename(&mut self, params: &Value) -> Result<Value, RpcError> {
+        let source = params.get("source").and_then(Value::as_str)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "missing string param `source`"))?;
+        let path = params.get("path").and_then(Value::as_str).unwrap_or("<input>");
+        let new_name = params.get("newName").and_then(Value::as_str)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "missing string param `newName`"))?;
+        let coordinate = |name: &str| params.get("position").and_then(|p| p.get(name)).and_then(Value::as_u64);
+        let (Some(line), Some(character)) = (coordinate("line"), coordinate("character")) else {
+            return Err(RpcError::new(INVALID_PARAMS, "missing param `position` with `line` and `character`"));
+        };
+        let file = self.session.sources.set_file(path, source);
+        let arena = TuArena::new();
+        let output = Preprocessor::new(&mut self.session).run(file).map_err(DriverError::from)?;
+        let tokens = output.tokens.clone();
+        let unit = Parser::new(&mut self.session, &arena, output.tokens).parse().map_err(DriverError::from)?;
+        self.session.diagnostics.take();
+        let sources = &self.session.sources;
+        let index = ReferenceIndex::build(&unit, &tokens, sources);
+        let entity = offset(sources.file(file), line as usize, character as usize).and_then(|pos| index.at(pos))
+            .ok_or_else(|| RpcError::new(REQUEST_FAILED, format!("no symbol to rename at {}:{}:{}", path, line + 1, character + 1)))?;
+        let spans = index.rename(entity, new_name).map_err(|e| {
+            let code = if matches!(e, RenameError::InvalidName(_)) { INVALID_PARAMS } else { REQUEST_FAILED };
+            match e.span().and_then(|s| sources.location(s.start)) {
+                Some(at) => RpcError::new(code, format!("{}: {}", at, e)),
+                None => RpcError::new(code, e.to_string()),
+            }
+        })?;
+        let mut rewriter = Rewriter::new();
+        for span in spans { rewriter.replace(span, new_name); }
+        workspace_edit(&rewriter, sources)
+    }
+
+    fn code_actions(&mut self, params: &Value) -> Result<Value, RpcError> {
+        let source = params.get("source").and_then(Value::as_str)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "missing string param `source`"))?;
+        let path = params.get("path").and_then(Value::as_str).unwrap_or("<input>");
+        let file = self.session.sources.set_file(path, source);
+        let range = match params.get("range") {
+            Some(range) => {
+                let at = |end: &str| -> Option<usize> {
+                    let line = range.get(end)?.get("line")?.as_u64()? as usize;
+                    let character = range.get(end)?.get("character")?.as_u64()? as usize;
+                    let file = self.session.sources.file(file);
+                    // Past the end of a line is the end of the line.
+                    (line < file.line_count()).then(|| offset(file, line, character)
+                        .unwrap_or_else(|| file.position(line + 2, 1).map_or(file.end(), |next| next - 1)))
+                };
+                let (Some(start), Some(end)) = (at("start"), at("end")) else {
+                    return Err(RpcError::new(INVALID_PARAMS, "param `range` needs `start` and `end` positions in the file"));
+                };
+                Span::new(start, end)
+            }
+            None => { let f = self.session.sources.file(file); Span::new(f.start, f.end()) }
+        };
+        let meets = |span: Span| span.start <= range.end && range.start <= span.end;
+        let arena = TuArena::new();
+        let output = Preprocessor::new(&mut self.session).run(file).map_err(DriverError::from)?;
+        let tokens = output.tokens.clone();
+        let unit = Parser::new(&mut self.session, &arena, output.tokens).parse().map_err(DriverError::from)?;
+        sema::check(&unit, &self.session.target, self.session.exec_charsets(), &mut self.session.diagnostics);
+        self.session.diagnostics.sort_by_location(&self.session.sources);
+        let diagnostics = self.session.diagnostics.take();
+        let sources = &self.session.sources;
+        let mut actions = Vec::new();
+        for diag in diagnostics.iter().filter(|d| !d.fixits.is_empty() && d.primary_span().is_some_and(meets)) {
+            let mut rewriter = Rewriter::new();
+            rewriter.fix(diag);
+            let Ok(edit) = workspace_edit(&rewriter, sources) else { continue };
+            actions.push(json!({
+                "title": fixit_title(diag, sources),
+                "kind": "quickfix",
+                "diagnostics": [to_json(diag, sources)],
+                "isPreferred": true,
+                "edit": edit,
+            }));
+        }
+        let index = ReferenceIndex::build(&unit, &tokens, sources);
+        let mut headers: Vec<&str> = includes::missing(&tokens, &index, sources, file).into_iter()
+            .filter(|m| meets(m.span)).map(|m| m.header).collect();
+        headers.sort_unstable();
+        headers.dedup();
+        for header in headers {
+            let (at, text) = includes::include_edit(sources.file(file), header);
+            let mut rewriter = Rewriter::new();
+            rewriter.insert(at, text);
+            actions.push(json!({
+                "title": format!("Add `#include <{}>`", header),
+                "kind": "quickfix",
+                "edit": workspace_edit(&rewriter, sources)?,
+            }));
+        }
+        Ok(Value::Array(actions))
+    }
+
+    /// Serves requests from `input` until end of input or `shutdown`.
+    pub fn serve(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() { continue; }
+            if let Some(response) = self.handle_line(&line) {
+                writeln!(output, "{}", response)?;
+                output.flush()?;
+            }
+            if self.shutdown { break; }
+        }
+        Ok(())
+    }
+}
+
+/// An LSP `WorkspaceEdit` making the edits of `rewriter`, keyed by path.
+fn workspace_edit(rewriter: &Rewriter, sources: &SourceManager) -> Result<Value, RpcError> {
+    let files = rewriter.files(sources).map_err(|e| RpcError::new(REQUEST_FAILED, e.to_string()))?;
+    let mut changes = Map::new();
+    for f in files {
+        let edits: Vec<Value> = f.edits.iter().filter_map(|edit| {
+            Some(json!({
+                "range": { "start": lsp_position(sources, edit.span.start)?, "end": lsp_position(sources, edit.span.end)? },
+                "newText": edit.text,
+            }))
+        }).collect();
+        changes.insert(sources.file(f.file).path.display().to_string(), Value::Array(edits));
+    }
+    Ok(json!({ "changes": changes }))
+}
+
+/// The LSP position of `pos`: its 0-based line, and the UTF-16 code units
+/// before it on that line.
+fn lsp_position(sources: &SourceManager, pos: usize) -> Option<Value> {
+    let at = sources.location(pos)?;
+    let before = sources.file(at.file).line_text(at.line).chars().take(at.column - 1);
+    Some(json!({ "line": at.line - 1, "character": before.map(char::len_utf16).sum::<usize>() }))
+}
+
+/// The position in `file` of LSP's 0-based `line` and `character`, in
+/// UTF-16 code units; `None` past the end of the line. A `character`
+/// inside a surrogate pair is after the pair.
+fn offset(file: &SourceFile, line: usize, character: usize) -> Option<usize> {
+    if line >= file.line_count() { return None; }
+    let text = file.line_text(line + 1);
+    if character > text.encode_utf16().count() { return None; }
+    let mut units = 0;
+    let column = text.chars().take_while(|c| { let before = units; units += c.len_utf16(); before < character }).count();
+    file.position(line + 1, column + 1)
+}
+
+/// What applying the fix-its of `diag` does, for a code action's title.
+fn fixit_title(diag: &Diagnostic, sources: &SourceManager) -> String {
+    match diag.fixits.as_slice() {
+        [fixit] => {
+            let old = sources.snippet(fixit.span).unwrap_or_default();
+            if old.is_empty() { format!("Insert `{}`", fixit.replacement) }
+            else if fixit.replacement.is_empty() { format!("Remove `{}`", old) }
+            else { format!("Replace `{}` with `{}`", old, fixit.replacement) }
+        }
+        _ => format!("Fix: {}", diag.message),
+    }
+}
+
+fn on_type_formatting(params: &Value) -> Result<Value, RpcError> {
+    let source = params.get("source").and_then(Value::as_str)
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, "missing string param `source`"))?;
+    let ch = params.get("ch").and_then(Value::as_str).and_then(|ch| ch.chars().next())
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, "missing string param `ch`"))?;
+    let line = params.get("position").and_then(|p| p.get("line")).and_then(Value::as_u64)
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, "missing param `position` with `line`"))? as usize;
+    let option = |name: &str| params.get("options").and_then(|o| o.get(name));
+    let mut options = FormatOptions::default();
+    if let Some(width) = option("tabSize").and_then(Value::as_u64) { options.indent_width = width as usize; }
+    if let Some(spaces) = option("insertSpaces").and_then(Value::as_bool) { options.use_tabs = !spaces; }
+    let lines: Vec<&str> = source.split('\n').collect();
+    let edits: Vec<Value> = format::on_type(source, line, ch, options).iter().map(|e| {
+        // LSP counts UTF-16 code units, the edit bytes.
+        let end = lines[e.line][..e.old_len].encode_utf16().count();
+        json!({
+            "range": {
+                "start": { "line": e.line, "character": 0 },
+                "end": { "line": e.line, "character": end },
+            },
+            "newText": e.indent,
+        })
+    }).collect();
+    Ok(Value::Array(edits))
+}
+
+fn error_response(id: Value, e: RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": e.code, "message": e.message } })
+}
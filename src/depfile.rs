@@ -0,0 +1,54 @@
+//! Make-syntax dependency files, as written by `-MD`/`-MMD`.
+//!
+//! Each input gets one rule naming its object file as the target and the
+//! input plus every header it actually included and file it `#embed`ded
+//! as prerequisites, so Make and Ninja rebuild it when any of them
+//! change. Built-in headers have no file to depend on and are left out.
+
+use std::path::{Path, PathBuf};
+
+/// Where and what `-MD`/`-MMD` write.
+#[derive(Debug, Clone, Default)]
+pub struct DepfileOptions {
+    /// `-MF`: the file to write. Without it each input's dependency file
+    /// goes next to its object file, with a `.d` extension.
+    pub path: Option<PathBuf>,
+    /// `-MMD`: leave headers from system directories out.
+    pub skip_system_headers: bool,
+}
+
+/// The object file a compilation of `input` produces: `-o` if given, else
+/// the input's file name with an `.o` extension.
+pub fn object_path(input: &Path, output: Option<&Path>) -> PathBuf {
+    match output {
+        Some(out) => out.to_path_buf(),
+        None => PathBuf::from(input.file_name().unwrap_or_default()).with_extension("o"),
+    }
+}
+
+/// Where the dependency file for `object` goes when `-MF` is not given.
+pub fn default_path(object: &Path) -> PathBuf { object.with_extension("d") }
+
+/// One Make rule, wrapped with backslash continuations.
+pub fn rule(target: &Path, deps: &[PathBuf]) -> String {
+    let mut out = format!("{}:", escape(target));
+    for dep in deps {
+        out.push_str(" \\\n  ");
+        out.push_str(&escape(dep));
+    }
+    out.push('\n');
+    out
+}
+
+/// Escapes the characters Make treats specially in file names.
+fn escape(path: &Path) -> String {
+    let mut out = String::new();
+    for c in path.display().to_string().chars() {
+        match c {
+            ' ' | '#' => { out.push('\\'); out.push(c); }
+            '$' => out.push_str("$$"),
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,136 @@
+//! Machine-readable diagnostic output: JSON lines and SARIF 2.1.0.
+
+use serde_json::{json, Value};
+
+use crate::diagnostics::{Diagnostic, Diagnostics, FixIt, Label, Level};
+use crate::source::SourceManager;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+fn severity(level: Level) -> &'static str {
+    match level {
+        Level::Error => "error",
+        Level::Warning => "warning",
+        Level::Note => "note",
+        Level::Help => "help",
+    }
+}
+
+/// A label's file and lines are those `#line` directives give it; its
+/// byte offsets are into the file as read.
+fn span_json(label: &Label, sources: &SourceManager) -> Option<Value> {
+    let start = sources.presumed_location(label.span.start)?;
+    let end = sources.presumed_location(label.span.end)?;
+    let file = sources.file(start.file);
+    Some(json!({
+        "file": start.path.display().to_string(),
+        "byte_start": label.span.start - file.start,
+        "byte_end": label.span.end - file.start,
+        "line_start": start.line,
+        "column_start": start.column,
+        "line_end": end.line,
+        "column_end": end.column,
+        "primary": label.primary,
+        "label": if label.message.is_empty() { Value::Null } else { Value::from(label.message.clone()) },
+    }))
+}
+
+fn fixit_json(fixit: &FixIt, sources: &SourceManager) -> Option<Value> {
+    let start = sources.location(fixit.span.start)?;
+    let end = sources.location(fixit.span.end)?;
+    let file = sources.file(start.file);
+    Some(json!({
+        "file": start.path.display().to_string(),
+        "byte_start": fixit.span.start - file.start,
+        "byte_end": fixit.span.end - file.start,
+        "line_start": start.line,
+        "column_start": start.column,
+        "line_end": end.line,
+        "column_end": end.column,
+        "replacement": fixit.replacement,
+    }))
+}
+
+/// One diagnostic as a JSON object. Byte offsets are relative to the file.
+pub fn to_json(diag: &Diagnostic, sources: &SourceManager) -> Value {
+    json!({
+        "severity": severity(diag.level),
+        "code": diag.code,
+        "message": diag.message,
+        "spans": diag.labels.iter().filter_map(|l| span_json(l, sources)).collect::<Vec<_>>(),
+        "notes": diag.notes,
+        "help": diag.help,
+        "fixits": diag.fixits.iter().filter_map(|f| fixit_json(f, sources)).collect::<Vec<_>>(),
+    })
+}
+
+/// All diagnostics as JSON lines: one compact object per line.
+pub fn render_json_lines(diags: &Diagnostics, sources: &SourceManager) -> String {
+    diags.iter().map(|d| to_json(d, sources).to_string() + "\n").collect()
+}
+
+fn sarif_location(label: &Label, sources: &SourceManager) -> Option<Value> {
+    let start = sources.presumed_location(label.span.start)?;
+    let end = sources.presumed_location(label.span.end)?;
+    let mut location = json!({
+        "physicalLocation": {
+            "artifactLocation": { "uri": start.path.display().to_string().replace('\\', "/") },
+            "region": {
+                "startLine": start.line,
+                "startColumn": start.column,
+                "endLine": end.line,
+                "endColumn": end.column,
+            },
+        },
+    });
+    if !label.message.is_empty() {
+        location["message"] = json!({ "text": label.message });
+    }
+    Some(location)
+}
+
+/// All diagnostics as a SARIF 2.1.0 log with a single run.
+pub fn to_sarif(diags: &Diagnostics, sources: &SourceManager) -> Value {
+    let mut rules: Vec<&str> = diags.iter().filter_map(|d| d.code.as_deref()).collect();
+    rules.sort_unstable();
+    rules.dedup();
+    let results: Vec<Value> = diags.iter().map(|d| {
+        let mut text = d.message.clone();
+        for note in &d.notes { text.push_str(&format!("\nnote: {}", note)); }
+        for help in &d.help { text.push_str(&format!("\nhelp: {}", help)); }
+        let level = match d.level {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Note | Level::Help => "note",
+        };
+        let mut result = json!({
+            "level": level,
+            "message": { "text": text },
+            "locations": d.labels.iter().filter(|l| l.primary).filter_map(|l| sarif_location(l, sources)).collect::<Vec<_>>(),
+        });
+        let related: Vec<Value> = d.labels.iter().filter(|l| !l.primary).filter_map(|l| sarif_location(l, sources))
+            .enumerate()
+            .map(|(i, mut loc)| { loc["id"] = json!(i); loc })
+            .collect();
+        if !related.is_empty() { result["relatedLocations"] = Value::from(related); }
+        if let Some(code) = &d.code {
+            result["ruleId"] = json!(code);
+            result["ruleIndex"] = json!(rules.binary_search(&code.as_str()).unwrap_or(0));
+        }
+        result
+    }).collect();
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "ruscom",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>(),
+                },
+            },
+            "results": results,
+        }],
+    })
+}
@@ -0,0 +1,368 @@
+use std::fmt::{self, Write as _};
+use std::str::FromStr;
+
+use crate::lexer::token::Span;
+use crate::source::SourceManager;
+
+pub mod json;
+pub mod warnings;
+pub use warnings::{Warning, WarningOption, WarningOptions};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Note => "note",
+            Level::Help => "help",
+        };
+        f.write_str(s)
+    }
+}
+
+/// How diagnostics are written out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiagnosticFormat {
+    /// Source snippets with underlines, for people.
+    #[default]
+    Human,
+    /// One JSON object per line.
+    Json,
+    /// A SARIF 2.1.0 log, for code-review bots and CI dashboards.
+    Sarif,
+}
+
+impl FromStr for DiagnosticFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(DiagnosticFormat::Human),
+            "json" => Ok(DiagnosticFormat::Json),
+            "sarif" => Ok(DiagnosticFormat::Sarif),
+            other => Err(format!("unknown diagnostic format `{}` (expected human, json or sarif)", other)),
+        }
+    }
+}
+
+/// A span annotated with a message. The primary label marks where the
+/// problem is; secondary labels point at related code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    pub primary: bool,
+}
+
+impl Label {
+    pub fn primary(span: Span, message: impl Into<String>) -> Self {
+        Self { span, message: message.into(), primary: true }
+    }
+
+    pub fn secondary(span: Span, message: impl Into<String>) -> Self {
+        Self { span, message: message.into(), primary: false }
+    }
+}
+
+/// A suggested edit that fixes the problem a diagnostic reports: replace
+/// `span` with `replacement`, or insert it when `span` is empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixIt {
+    pub span: Span,
+    pub replacement: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub code: Option<String>,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+    /// Edits that together fix the problem.
+    pub fixits: Vec<FixIt>,
+}
+
+impl Diagnostic {
+    pub fn new(level: Level, message: impl Into<String>) -> Self {
+        Self { level, code: None, message: message.into(), labels: Vec::new(), notes: Vec::new(), help: Vec::new(), fixits: Vec::new() }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self { Self::new(Level::Error, message) }
+
+    pub fn warning(message: impl Into<String>) -> Self { Self::new(Level::Warning, message) }
+
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    /// Puts a warning in category `w`, whose `-W` option becomes its code.
+    pub fn with_warning(self, w: Warning) -> Self { self.with_code(w.to_string()) }
+
+    /// The category of a warning put in one.
+    pub fn category(&self) -> Option<Warning> { self.code.as_deref()?.strip_prefix("-W").and_then(Warning::from_name) }
+
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help.push(help.into());
+        self
+    }
+
+    pub fn with_fixit(mut self, span: Span, replacement: impl Into<String>) -> Self {
+        self.fixits.push(FixIt { span, replacement: replacement.into() });
+        self
+    }
+
+    /// Applies `f` to the span of every label and fix-it.
+    pub fn map_spans(mut self, f: impl Fn(Span) -> Span) -> Self {
+        for label in &mut self.labels { label.span = f(label.span); }
+        for fixit in &mut self.fixits { fixit.span = f(fixit.span); }
+        self
+    }
+
+    /// The span of the first primary label, if any.
+    pub fn primary_span(&self) -> Option<Span> {
+        self.labels.iter().find(|l| l.primary).map(|l| l.span)
+    }
+}
+
+/// Collects the diagnostics reported during a compilation.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    diagnostics: Vec<Diagnostic>,
+    /// 0 for none.
+    error_limit: usize,
+    /// Errors kept so far, including any already taken.
+    errors: usize,
+    warnings: WarningOptions,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self { Self::default() }
+
+    /// Collects up to `limit` errors, or any number for 0. Past the limit
+    /// every diagnostic is dropped, and a note says so.
+    pub fn with_error_limit(limit: usize) -> Self { Self { error_limit: limit, ..Self::default() } }
+
+    /// Reports warnings as `warnings` says: those of a category it turns
+    /// off are dropped, and `-Werror` makes the rest errors.
+    pub fn with_warnings(mut self, warnings: WarningOptions) -> Self {
+        self.warnings = warnings;
+        self
+    }
+
+    pub fn warnings(&self) -> &WarningOptions { &self.warnings }
+
+    pub fn warnings_mut(&mut self) -> &mut WarningOptions { &mut self.warnings }
+
+    pub fn emit(&mut self, mut diag: Diagnostic) {
+        if self.limit_reached() { return; }
+        if diag.level == Level::Warning {
+            if self.warnings.is_inhibited() { return; }
+            let category = diag.category();
+            if category.is_some_and(|w| !self.warnings.is_enabled(w)) { return; }
+            if let Some(option) = self.warnings.error_option(category) {
+                diag.level = Level::Error;
+                diag.notes.push(format!("treated as an error because of `{}`", option));
+            }
+        }
+        let is_error = diag.level == Level::Error;
+        self.diagnostics.push(diag);
+        if !is_error { return; }
+        self.errors += 1;
+        if self.limit_reached() {
+            self.diagnostics.push(Diagnostic::new(Level::Note, format!("too many errors emitted, stopping now (--error-limit {})", self.error_limit))
+                .with_help("use `--error-limit 0` to report every error"));
+        }
+    }
+
+    /// Whether the error limit has been reached, after which the
+    /// compilation should stop.
+    pub fn limit_reached(&self) -> bool { self.error_limit != 0 && self.errors >= self.error_limit }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> { self.diagnostics.iter() }
+
+    pub fn len(&self) -> usize { self.diagnostics.len() }
+
+    pub fn is_empty(&self) -> bool { self.diagnostics.is_empty() }
+
+    pub fn error_count(&self) -> usize { self.count(Level::Error) }
+
+    pub fn warning_count(&self) -> usize { self.count(Level::Warning) }
+
+    pub fn has_errors(&self) -> bool { self.error_count() > 0 }
+
+    fn count(&self, level: Level) -> usize { self.diagnostics.iter().filter(|d| d.level == level).count() }
+
+    /// Sorts into source order: by file path, then position in the file,
+    /// with diagnostics that have no location last. The sort is stable, so
+    /// diagnostics at the same place keep the order they were emitted in.
+    /// Output is then the same however the work was split across threads.
+    pub fn sort_by_location(&mut self, sources: &SourceManager) {
+        self.diagnostics.sort_by_cached_key(|d| {
+            let at = d.primary_span().and_then(|s| sources.file_at(s.start).map(|f| (f.path.clone(), s.start - f.start)));
+            (at.is_none(), at)
+        });
+    }
+
+    /// Adds a note for each ignored `-Wno-` option that names no warning
+    /// category if anything else was reported, as it may have been meant
+    /// to silence that.
+    pub fn note_unknown_options(&mut self) {
+        if !self.diagnostics.iter().any(|d| matches!(d.level, Level::Error | Level::Warning)) { return; }
+        for name in self.warnings.unknown().to_vec() {
+            self.diagnostics.push(Diagnostic::new(Level::Note, format!("unrecognized command-line option `-Wno-{}` may have been intended to silence earlier diagnostics", name)));
+        }
+    }
+
+    /// Removes and returns everything collected so far.
+    pub fn take(&mut self) -> Vec<Diagnostic> { std::mem::take(&mut self.diagnostics) }
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const BLUE: &str = "\x1b[1;34m";
+
+fn level_color(level: Level) -> &'static str {
+    match level {
+        Level::Error => "\x1b[1;31m",
+        Level::Warning => "\x1b[1;33m",
+        Level::Note => "\x1b[1;32m",
+        Level::Help => "\x1b[1;36m",
+    }
+}
+
+/// Renders `diags` in the given format.
+pub fn render(format: DiagnosticFormat, color: bool, diags: &Diagnostics, sources: &SourceManager) -> String {
+    match format {
+        DiagnosticFormat::Human => Emitter::new(color).render_all(diags, sources),
+        DiagnosticFormat::Json => json::render_json_lines(diags, sources),
+        DiagnosticFormat::Sarif => format!("{:#}\n", json::to_sarif(diags, sources)),
+    }
+}
+
+/// Renders diagnostics as text in the style of rustc: a header line, the
+/// location, and the offending source lines with underlined labels.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Emitter {
+    pub color: bool,
+}
+
+impl Emitter {
+    pub fn new(color: bool) -> Self { Self { color } }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color { format!("{}{}{}", color, text, RESET) } else { text.to_string() }
+    }
+
+    pub fn render(&self, diag: &Diagnostic, sources: &SourceManager) -> String {
+        let mut out = String::new();
+        let (mut header, mut message) = (diag.level.to_string(), format!(": {}", diag.message));
+        // A warning's option follows its message, as GCC and Clang print it.
+        match &diag.code {
+            Some(option) if option.starts_with("-W") => { let _ = write!(message, " [{}]", option); }
+            Some(code) => { let _ = write!(header, "[{}]", code); }
+            None => {}
+        }
+        let _ = writeln!(out, "{}{}", self.paint(level_color(diag.level), &header), self.paint(BOLD, &message));
+
+        // Resolve every label to a location and order them by position,
+        // with the primary label's file first.
+        let mut labels: Vec<_> = diag.labels.iter()
+            .filter_map(|l| sources.location(l.span.start).map(|loc| (loc, l)))
+            .collect();
+        let primary_loc = diag.primary_span().and_then(|s| sources.location(s.start));
+        let primary_file = primary_loc.as_ref().map(|loc| loc.file);
+        labels.sort_by_key(|(loc, l)| (Some(loc.file) != primary_file, loc.file, loc.line, loc.column, !l.primary));
+        let width = labels.iter().map(|(loc, _)| loc.line.to_string().len()).max().unwrap_or(0);
+        let pad = " ".repeat(width);
+        let gutter = self.paint(BLUE, &format!("{} |", pad));
+
+        let mut current_file = None;
+        let mut last_line = None;
+        for (loc, label) in &labels {
+            if current_file != Some(loc.file) {
+                // The lines shown are those of the file as read, but where
+                // they are is given as `#line` directives say.
+                let (arrow, at) = match (&primary_loc, diag.primary_span()) {
+                    (Some(p), Some(primary)) if current_file.is_none() && p.file == loc.file => ("-->", primary.start),
+                    _ if current_file.is_none() => ("-->", label.span.start),
+                    _ => (":::", label.span.start),
+                };
+                let shown = sources.presumed_location(at).unwrap_or_else(|| loc.clone());
+                let _ = writeln!(out, "{}{} {}", pad, self.paint(BLUE, arrow), shown);
+                let _ = writeln!(out, "{}", gutter);
+                current_file = Some(loc.file);
+                last_line = None;
+            }
+            let file = sources.file(loc.file);
+            let text = file.line_text(loc.line);
+            if last_line != Some(loc.line) {
+                let number = self.paint(BLUE, &format!("{:>width$} |", loc.line, width = width));
+                let _ = writeln!(out, "{} {}", number, text);
+                last_line = Some(loc.line);
+            }
+            // Underline from the label start to its end, clipped to this line.
+            let prefix_bytes: usize = text.chars().take(loc.column - 1).map(char::len_utf8).sum();
+            let line_start_pos = label.span.start - prefix_bytes;
+            let end_in_line = label.span.end.min(line_start_pos + text.len()).max(label.span.start);
+            let underlined = text.get(label.span.start - line_start_pos..end_in_line - line_start_pos).unwrap_or("");
+            let lead: String = text.chars().take(loc.column - 1).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+            let marker = if label.primary { '^' } else { '-' };
+            let marks: String = std::iter::repeat_n(marker, underlined.chars().count().max(1)).collect();
+            let color = if label.primary { level_color(diag.level) } else { BLUE };
+            let mut line = format!("{}{}", lead, marks);
+            if !label.message.is_empty() { let _ = write!(line, " {}", label.message); }
+            let _ = writeln!(out, "{} {}", gutter, self.paint(color, &line));
+        }
+        if !labels.is_empty() && (!diag.notes.is_empty() || !diag.help.is_empty()) {
+            let _ = writeln!(out, "{}", gutter);
+        }
+        for note in &diag.notes {
+            let _ = writeln!(out, "{} {} {}", pad, self.paint(BLUE, "="), self.paint(BOLD, "note:") + " " + note);
+        }
+        for help in &diag.help {
+            let _ = writeln!(out, "{} {} {}", pad, self.paint(BLUE, "="), self.paint(BOLD, "help:") + " " + help);
+        }
+        out
+    }
+
+    /// Renders every diagnostic followed by a summary line of the error and
+    /// warning counts.
+    pub fn render_all(&self, diags: &Diagnostics, sources: &SourceManager) -> String {
+        let mut out = String::new();
+        for d in diags.iter() {
+            out.push_str(&self.render(d, sources));
+            out.push('\n');
+        }
+        let (errors, warnings) = (diags.error_count(), diags.warning_count());
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        if errors > 0 {
+            let msg = format!("aborting due to {} error{}", errors, plural(errors));
+            let _ = writeln!(out, "{}{}", self.paint(level_color(Level::Error), "error"), self.paint(BOLD, &format!(": {}", msg)));
+        } else if warnings > 0 {
+            let msg = format!("{} warning{} emitted", warnings, plural(warnings));
+            let _ = writeln!(out, "{}{}", self.paint(level_color(Level::Warning), "warning"), self.paint(BOLD, &format!(": {}", msg)));
+        }
+        out
+    }
+}
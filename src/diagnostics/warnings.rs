@@ -0,0 +1,203 @@
+//! Warning categories and the `-W` options that turn them on and off.
+//!
+//! Each warning a check reports belongs to a category, named like GCC's
+//! option for it: `-Wshadow` turns shadowing warnings on and
+//! `-Wno-shadow` off. `-Wall` and `-Wextra` turn on groups of them, and
+//! a category named on its own wins over a group whatever the order, so
+//! `-Wall -Wno-unused-variable` and `-Wno-unused-variable -Wall` mean the
+//! same. `-Werror` makes every warning an error, and `-Werror=shadow` just
+//! one category, which it also turns on. Warnings without a category,
+//! like those of `ruscom lint`, are only affected by `-Werror` and by
+//! `-w`, which turns every warning off.
+//!
+//! As with GCC, `-Wno-name` for a name that is not a category is ignored,
+//! so flags shared with other compilers still work; it is only mentioned
+//! if something else is reported, in case it was meant to silence that.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// A category of warnings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Warning {
+    /// Implicit arithmetic conversions that may change a value.
+    Conversion,
+    /// Comparisons between signed and unsigned integers.
+    SignCompare,
+    /// Local variables and parameters that hide another declaration.
+    Shadow,
+    /// Local variables that are never used.
+    UnusedVariable,
+    /// Parameters of a function definition that are never used.
+    UnusedParameter,
+    /// Discarded results of `[[nodiscard]]` functions.
+    UnusedResult,
+    /// Unsequenced modifications of a variable.
+    SequencePoint,
+    /// Non-void functions that can run off their end.
+    ReturnType,
+    /// Statements that can never run.
+    UnreachableCode,
+    /// Loops that never end and do nothing observable.
+    InfiniteLoop,
+    /// Local variables read before they are assigned on every path.
+    Uninitialized,
+    /// Local variables read before they are assigned on some path.
+    MaybeUninitialized,
+    /// Attributes that are ignored.
+    Attributes,
+    /// Aliases whose type does not match their target's.
+    AttributeAlias,
+    /// `#warning` directives.
+    Cpp,
+    /// Macros redefined differently.
+    MacroRedefined,
+}
+
+impl Warning {
+    pub const ALL: [Warning; 16] = [
+        Warning::Conversion, Warning::SignCompare, Warning::Shadow, Warning::UnusedVariable, Warning::UnusedParameter, Warning::UnusedResult,
+        Warning::SequencePoint, Warning::ReturnType, Warning::UnreachableCode, Warning::InfiniteLoop, Warning::Uninitialized,
+        Warning::MaybeUninitialized, Warning::Attributes, Warning::AttributeAlias, Warning::Cpp, Warning::MacroRedefined,
+    ];
+
+    /// The name `-W` takes, like `unused-variable`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Warning::Conversion => "conversion",
+            Warning::SignCompare => "sign-compare",
+            Warning::Shadow => "shadow",
+            Warning::UnusedVariable => "unused-variable",
+            Warning::UnusedParameter => "unused-parameter",
+            Warning::UnusedResult => "unused-result",
+            Warning::SequencePoint => "sequence-point",
+            Warning::ReturnType => "return-type",
+            Warning::UnreachableCode => "unreachable-code",
+            Warning::InfiniteLoop => "infinite-loop",
+            Warning::Uninitialized => "uninitialized",
+            Warning::MaybeUninitialized => "maybe-uninitialized",
+            Warning::Attributes => "attributes",
+            Warning::AttributeAlias => "attribute-alias",
+            Warning::Cpp => "cpp",
+            Warning::MacroRedefined => "macro-redefined",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> { Self::ALL.into_iter().find(|w| w.name() == name) }
+
+    /// Whether it is on without any `-W` option.
+    pub fn is_default(self) -> bool { !self.in_all() && !matches!(self, Warning::Conversion | Warning::Shadow | Warning::UnusedParameter) }
+
+    /// Whether `-Wall` turns it on.
+    pub fn in_all(self) -> bool {
+        matches!(self, Warning::SignCompare | Warning::UnusedVariable | Warning::UnreachableCode | Warning::InfiniteLoop | Warning::Uninitialized | Warning::MaybeUninitialized)
+    }
+
+    /// Whether `-Wextra` turns it on.
+    pub fn in_extra(self) -> bool { matches!(self, Warning::UnusedParameter) }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "-W{}", self.name()) }
+}
+
+/// One `-W` option, without the `-W`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WarningOption {
+    /// `-Wall`
+    All,
+    /// `-Wextra`
+    Extra,
+    /// `-Wname`
+    Enable(Warning),
+    /// `-Wno-name`
+    Disable(Warning),
+    /// `-Werror`, or `-Wno-error` for `false`.
+    Error(bool),
+    /// `-Werror=name`, or `-Wno-error=name` for `false`.
+    ErrorFor(Warning, bool),
+    /// `-Wno-name` for a `name` that is not a category.
+    DisableUnknown(String),
+}
+
+impl FromStr for WarningOption {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let category = |name: &str| Warning::from_name(name).ok_or_else(|| format!("unknown warning option `-W{}`", s));
+        match s {
+            "all" => Ok(WarningOption::All),
+            "extra" => Ok(WarningOption::Extra),
+            "error" => Ok(WarningOption::Error(true)),
+            "no-error" => Ok(WarningOption::Error(false)),
+            _ => {
+                if let Some(name) = s.strip_prefix("error=") { return Ok(WarningOption::ErrorFor(category(name)?, true)); }
+                if let Some(name) = s.strip_prefix("no-error=") { return Ok(WarningOption::ErrorFor(category(name)?, false)); }
+                if let Some(name) = s.strip_prefix("no-") {
+                    return Ok(Warning::from_name(name).map_or_else(|| WarningOption::DisableUnknown(name.to_owned()), WarningOption::Disable));
+                }
+                Ok(WarningOption::Enable(category(s)?))
+            }
+        }
+    }
+}
+
+/// Which warnings are reported, and which of them as errors.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WarningOptions {
+    all: bool,
+    extra: bool,
+    /// Categories named on their own, on or off.
+    toggles: HashMap<Warning, bool>,
+    error: bool,
+    /// Categories named by `-Werror=` or `-Wno-error=`.
+    errors: HashMap<Warning, bool>,
+    /// `-w`
+    inhibit: bool,
+    /// Names of `-Wno-` options that are not categories.
+    unknown: Vec<String>,
+}
+
+impl WarningOptions {
+    pub fn new() -> Self { Self::default() }
+
+    /// Applies `option` over those applied before it.
+    pub fn apply(&mut self, option: WarningOption) {
+        match option {
+            WarningOption::All => self.all = true,
+            WarningOption::Extra => self.extra = true,
+            WarningOption::Enable(w) => { self.toggles.insert(w, true); }
+            WarningOption::Disable(w) => { self.toggles.insert(w, false); }
+            WarningOption::Error(on) => self.error = on,
+            WarningOption::ErrorFor(w, on) => { self.errors.insert(w, on); }
+            WarningOption::DisableUnknown(name) => self.unknown.push(name),
+        }
+    }
+
+    /// Turns every warning off, as `-w` does, whatever the other options.
+    pub fn inhibit(&mut self) { self.inhibit = true; }
+
+    /// Whether `-w` turned every warning off.
+    pub fn is_inhibited(&self) -> bool { self.inhibit }
+
+    /// Whether warnings of category `w` are reported.
+    pub fn is_enabled(&self, w: Warning) -> bool {
+        if self.inhibit { return false; }
+        if let Some(&on) = self.toggles.get(&w) { return on; }
+        self.errors.get(&w) == Some(&true) || w.is_default() || (self.all && w.in_all()) || (self.extra && w.in_extra())
+    }
+
+    /// The option that makes a warning of category `w`, or of none, an
+    /// error, if one does.
+    pub fn error_option(&self, w: Option<Warning>) -> Option<String> {
+        match w.and_then(|w| self.errors.get(&w).map(|&on| (w, on))) {
+            Some((w, true)) => Some(format!("-Werror={}", w.name())),
+            Some((_, false)) => None,
+            None => self.error.then(|| "-Werror".to_string()),
+        }
+    }
+
+    /// The names of the ignored `-Wno-` options that are not categories.
+    pub fn unknown(&self) -> &[String] { &self.unknown }
+}
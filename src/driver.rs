@@ -0,0 +1,360 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::arena::TuArena;
+use crate::ast::{self, TranslationUnit};
+use crate::cancel::Cancelled;
+use crate::diagnostics::{self, Diagnostic, DiagnosticFormat, Diagnostics, Label};
+use crate::artifact;
+use crate::lexer::token::Token;
+use crate::depfile::{self, DepfileOptions};
+use crate::module::{self, ModuleInterface};
+use crate::parser::Parser;
+use crate::preprocessor::stats::MacroStats;
+use crate::preprocessor::{headers, PreprocessOutput, Preprocessor};
+use crate::profile::Profiler;
+use crate::sema;
+use crate::session::{LexOutput, Options, Session};
+use crate::source::{FileId, SourceManager};
+
+/// Error from a driver entry point.
+#[derive(Debug)]
+pub enum DriverError {
+    Io { path: PathBuf, source: std::io::Error },
+    Cancelled,
+    /// The requested stage isn't implemented yet.
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for DriverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DriverError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            DriverError::Cancelled => write!(f, "{}", Cancelled),
+            DriverError::Unsupported(what) => write!(f, "{} is not implemented yet", what),
+        }
+    }
+}
+
+impl std::error::Error for DriverError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DriverError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<Cancelled> for DriverError {
+    fn from(_: Cancelled) -> Self { DriverError::Cancelled }
+}
+
+pub type DriverResult<T> = Result<T, DriverError>;
+
+/// What `ruscom compile --emit` writes instead of compiling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emit {
+    /// A C header declaring the `extern "C"` functions and variables the
+    /// input defines, and the types they use.
+    CHeader,
+}
+
+impl FromStr for Emit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "c-header" => Ok(Emit::CHeader),
+            other => Err(format!("unknown output kind `{}` (expected c-header)", other)),
+        }
+    }
+}
+
+/// What a `preprocess_parallel` worker hands back for one input. Its
+/// tokens name symbols of the worker's thread, so they come back encoded
+/// as a token artifact and are interned again on the main one.
+struct WorkerOutput {
+    file: FileId,
+    tokens: Vec<u8>,
+    files: Vec<FileId>,
+    embedded: Vec<PathBuf>,
+    macro_stats: Option<MacroStats>,
+    sources: SourceManager,
+    diagnostics: Diagnostics,
+    profiler: Profiler,
+}
+
+type WorkerResult = DriverResult<WorkerOutput>;
+
+/// Lexed form of one input file.
+#[derive(Debug)]
+pub struct LexedFile {
+    pub path: PathBuf,
+    pub output: LexOutput,
+}
+
+/// Preprocessed form of one input file.
+#[derive(Debug)]
+pub struct PreprocessedFile {
+    pub path: PathBuf,
+    pub output: PreprocessOutput,
+}
+
+/// Parsed form of one input file, with its tree in the caller's arena.
+#[derive(Debug)]
+pub struct ParsedFile<'a> {
+    pub path: PathBuf,
+    pub unit: TranslationUnit<'a>,
+}
+
+/// Library entry point: runs the compiler pipeline over `options.inputs`.
+///
+/// The `ruscom` binary is a thin CLI over this type; other Rust tools can
+/// embed the compiler the same way:
+///
+/// ```no_run
+/// use ruscom::driver::Driver;
+/// use ruscom::session::Options;
+///
+/// let options = Options { inputs: vec!["main.cpp".into()], ..Options::default() };
+/// let mut driver = Driver::new(options);
+/// for file in driver.lex().unwrap() {
+///     println!("{}: {} tokens", file.path.display(), file.output.tokens.len());
+/// }
+/// ```
+pub struct Driver {
+    session: Session,
+}
+
+impl Driver {
+    pub fn new(options: Options) -> Self { Self { session: Session::new(options) } }
+
+    pub fn from_session(session: Session) -> Self { Self { session } }
+
+    pub fn session(&self) -> &Session { &self.session }
+
+    pub fn session_mut(&mut self) -> &mut Session { &mut self.session }
+
+    pub fn into_session(self) -> Session { self.session }
+
+    /// Loads `path` into the session's source manager.
+    fn load(&mut self, path: &Path) -> DriverResult<FileId> {
+        self.session.cancel.check()?;
+        let name = path.display().to_string();
+        let Session { fs, sources, profiler, .. } = &mut self.session;
+        profiler.time(&format!("read {}", name), "io", || sources.load(fs.as_ref(), path))
+            .map_err(|source| DriverError::Io { path: path.to_path_buf(), source })
+    }
+
+    /// Lexes every input file.
+    pub fn lex(&mut self) -> DriverResult<Vec<LexedFile>> {
+        let inputs = self.session.options.inputs.clone();
+        let mut files = Vec::with_capacity(inputs.len());
+        for path in inputs {
+            let file = self.load(&path)?;
+            let output = self.session.tokenize_file(file)?;
+            files.push(LexedFile { path, output });
+        }
+        Ok(files)
+    }
+
+    /// Preprocesses every input file, expanding `#include`s. With
+    /// `options.jobs` above 1 the inputs are spread over that many threads.
+    ///
+    /// With `options.depfile` set, also writes each input's dependency file.
+    pub fn preprocess(&mut self) -> DriverResult<Vec<PreprocessedFile>> {
+        let inputs = self.session.options.inputs.clone();
+        let files = if self.session.options.jobs > 1 && inputs.len() > 1 {
+            // Each worker's own regions go on its row of the trace.
+            let start = Instant::now();
+            let files = self.preprocess_parallel(inputs)?;
+            self.session.profiler.record("preprocess", "phase", start);
+            files
+        } else {
+            let mut files = Vec::with_capacity(inputs.len());
+            for path in inputs {
+                let file = self.load(&path)?;
+                let output = self.session.time(&format!("preprocess {}", path.display()), "phase", |s| Preprocessor::new(s).run(file))?;
+                files.push(PreprocessedFile { path, output });
+            }
+            files
+        };
+        if let Some(depfile) = &self.session.options.depfile {
+            let start = Instant::now();
+            self.write_depfiles(depfile, &files)?;
+            self.session.profiler.record("write dependency files", "io", start);
+        }
+        Ok(files)
+    }
+
+    /// Writes a Make rule per input listing the files it included and
+    /// embedded. Inputs that share a dependency file (one `-MF`) each get
+    /// a rule in it. Built-in headers are not files, so Make could not
+    /// find them, and are always left out.
+    fn write_depfiles(&self, depfile: &DepfileOptions, files: &[PreprocessedFile]) -> DriverResult<()> {
+        let session = &self.session;
+        let mut outputs: Vec<(PathBuf, String)> = Vec::new();
+        for f in files {
+            let object = depfile::object_path(&f.path, session.options.output.as_deref());
+            let mut deps: Vec<PathBuf> = Vec::new();
+            for &id in &f.output.files {
+                let path = &session.sources.file(id).path;
+                if headers::is_builtin(path) { continue; }
+                if depfile.skip_system_headers && id != f.output.file && session.is_system_header(path) { continue; }
+                if !deps.contains(path) { deps.push(path.clone()); }
+            }
+            for path in &f.output.embedded {
+                if !deps.contains(path) { deps.push(path.clone()); }
+            }
+            let path = depfile.path.clone().unwrap_or_else(|| depfile::default_path(&object));
+            let rule = depfile::rule(&object, &deps);
+            match outputs.iter_mut().find(|(p, _)| *p == path) {
+                Some((_, text)) => text.push_str(&rule),
+                None => outputs.push((path, rule)),
+            }
+        }
+        for (path, text) in outputs {
+            std::fs::write(&path, text).map_err(|source| DriverError::Io { path, source })?;
+        }
+        Ok(())
+    }
+
+    /// Preprocesses each input in its own `Session` on a worker thread, then
+    /// merges the workers' sources, diagnostics and profiles back in input
+    /// order.
+    fn preprocess_parallel(&mut self, inputs: Vec<PathBuf>) -> DriverResult<Vec<PreprocessedFile>> {
+        let next = AtomicUsize::new(0);
+        let results: Mutex<Vec<Option<(usize, WorkerResult)>>> = Mutex::new((0..inputs.len()).map(|_| None).collect());
+        let main = &self.session;
+        std::thread::scope(|scope| {
+            for worker in 0..main.options.jobs.min(inputs.len()) {
+                let (next, results, inputs) = (&next, &results, &inputs);
+                scope.spawn(move || loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(path) = inputs.get(i) else { break };
+                    let mut session = Session::with_target(main.options.clone(), main.target.clone())
+                        .with_file_system(main.fs.clone());
+                    session.cancel = main.cancel.clone();
+                    let start = Instant::now();
+                    let result = session.sources.load(session.fs.as_ref(), path)
+                        .map_err(|source| DriverError::Io { path: path.clone(), source })
+                        .and_then(|file| Ok(Preprocessor::new(&mut session).run(file)?));
+                    session.profiler.record(&format!("preprocess {}", path.display()), "phase", start);
+                    let result = result.map(|PreprocessOutput { file, tokens, files, embedded, macro_stats }| WorkerOutput {
+                        file, tokens: artifact::encode_tokens(&tokens), files, embedded, macro_stats,
+                        sources: session.sources, diagnostics: session.diagnostics, profiler: session.profiler,
+                    });
+                    results.lock().unwrap()[i] = Some((worker, result));
+                });
+            }
+        });
+        let mut files = Vec::with_capacity(inputs.len());
+        for (path, result) in inputs.into_iter().zip(results.into_inner().unwrap()) {
+            let (worker, result) = result.expect("every input is claimed by a worker");
+            let WorkerOutput { file, tokens, files: entered, embedded, macro_stats, sources, mut diagnostics, profiler } = result?;
+            let tokens = artifact::decode_tokens(&tokens).expect("a worker's tokens decode");
+            let mut output = PreprocessOutput { file, tokens, files: entered, embedded, macro_stats };
+            // Thread 1 is the main one.
+            self.session.profiler.absorb(profiler, worker as u32 + 2, &format!("worker {}", worker + 1));
+            let remap = self.session.sources.absorb(sources);
+            output.file = remap.file(output.file);
+            for f in &mut output.files { *f = remap.file(*f); }
+            for t in &mut output.tokens {
+                t.span = remap.span(t.span);
+                if let Token::Identifier(name) = &t.token { self.session.interner.record(&name.as_str()); }
+            }
+            for d in diagnostics.take() { self.session.diagnostics.emit(d.map_spans(|s| remap.span(s))); }
+            files.push(PreprocessedFile { path, output });
+        }
+        Ok(files)
+    }
+
+    /// Preprocesses and parses every input file into `arena`. Syntax
+    /// errors are reported to the session's diagnostics.
+    ///
+    /// With `options.module_cache` set, each module interface that parses
+    /// without errors has its compiled interface written there, where the
+    /// inputs after it can import it from.
+    pub fn parse<'a>(&mut self, arena: &'a TuArena) -> DriverResult<Vec<ParsedFile<'a>>> {
+        let files = self.preprocess()?;
+        let mut parsed = Vec::with_capacity(files.len());
+        for file in files {
+            let errors = self.session.diagnostics.error_count();
+            let name = format!("parse {}", file.path.display());
+            let unit = self.session.time(&name, "phase", |s| Parser::new(s, arena, file.output.tokens).parse())?;
+            let stats = arena.stats();
+            self.session.profiler.count("syntax tree arena", &[("allocated", stats.allocated_bytes as u64), ("reserved", stats.reserved_bytes as u64)]);
+            if self.session.diagnostics.error_count() == errors { self.write_module_interface(&unit)?; }
+            if self.session.options.verify_ast {
+                for m in ast::verify(&unit, &self.session.sources) {
+                    self.session.diagnostics.emit(Diagnostic::error(format!("malformed AST: `{}` {}", m.node, m.problem))
+                        .with_label(Label::primary(m.span, ""))
+                        .with_note("this is a bug in the parser"));
+                }
+            }
+            parsed.push(ParsedFile { path: file.path, unit });
+        }
+        Ok(parsed)
+    }
+
+    fn write_module_interface(&self, unit: &TranslationUnit) -> DriverResult<()> {
+        let (Some(dir), Some(interface)) = (&self.session.options.module_cache, ModuleInterface::of(unit)) else { return Ok(()) };
+        let path = module::cache_path(dir, interface.name);
+        std::fs::create_dir_all(dir)
+            .and_then(|()| std::fs::write(&path, artifact::encode_module(&interface)))
+            .map_err(|source| DriverError::Io { path, source })
+    }
+
+    /// Parses every input file into `arena` and runs the semantic checks
+    /// over it.
+    pub fn check<'a>(&mut self, arena: &'a TuArena) -> DriverResult<Vec<ParsedFile<'a>>> {
+        let parsed = self.parse(arena)?;
+        let charsets = self.session.exec_charsets();
+        for file in &parsed {
+            let Session { target, diagnostics, profiler, .. } = &mut self.session;
+            let start = Instant::now();
+            sema::check_profiled(&file.unit, target, charsets, diagnostics, profiler);
+            profiler.record(&format!("sema {}", file.path.display()), "phase", start);
+        }
+        Ok(parsed)
+    }
+
+    /// Compiles every input file to `options.output`.
+    pub fn compile(&mut self) -> DriverResult<()> {
+        self.check(&TuArena::new())?;
+        Err(DriverError::Unsupported("code generation"))
+    }
+
+    /// Writes the collected diagnostics to stderr in the configured format
+    /// (human-readable output is colored when stderr is a terminal) and
+    /// clears them. Returns whether any of them were errors.
+    ///
+    /// Diagnostics are written in source order, not the order they were
+    /// reported in, so output does not depend on `options.jobs`.
+    pub fn flush_diagnostics(&mut self) -> bool {
+        self.session.diagnostics.note_unknown_options();
+        self.session.diagnostics.sort_by_location(&self.session.sources);
+        use std::io::IsTerminal;
+        let format = self.session.options.diagnostic_format;
+        let has_errors = self.session.diagnostics.has_errors();
+        // SARIF consumers expect a log even when there is nothing to report.
+        if !self.session.diagnostics.is_empty() || format == DiagnosticFormat::Sarif {
+            let color = std::io::stderr().is_terminal();
+            eprint!("{}", diagnostics::render(format, color, &self.session.diagnostics, &self.session.sources));
+        }
+        self.session.diagnostics.take();
+        has_errors
+    }
+
+    /// Flushes end-of-run outputs such as the profile.
+    pub fn finish(&self) -> DriverResult<()> {
+        self.session.finish().map_err(|source| DriverError::Io {
+            path: self.session.options.profile_out.clone().unwrap_or_default(),
+            source,
+        })
+    }
+}
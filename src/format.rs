@@ -0,0 +1,249 @@
+//! Source formatting: re-indenting lines by the nesting of braces,
+//! parentheses and brackets around them, for `ruscom format` and the
+//! daemon's on-type formatting.
+//!
+//! Only the whitespace at the start of a line changes. A line is indented
+//! one level per bracket open at its start, one level less if it starts by
+//! closing one or with an access specifier, one level more if it continues
+//! a statement the line before did not finish, and one more if it follows
+//! a `case` or `default` label in its block. Blank lines lose their
+//! whitespace. Preprocessor directives, lines inside a block comment or
+//! string, and everything after a token the lexer rejects are left as they
+//! are.
+//!
+//! A range of lines is formatted as part of the whole file, with the
+//! nesting at its first line taken from the lines before it, so formatting
+//! a range gives those lines what formatting the whole file would. On-type
+//! formatting is range formatting around the cursor.
+
+use std::ops::{Range, RangeInclusive};
+
+use crate::lexer::token::{SpannedToken, Token};
+use crate::lexer::{Lexer, LexerOptions};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Columns per level.
+    pub indent_width: usize,
+    /// Indent with a tab per level instead of spaces.
+    pub use_tabs: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self { Self { indent_width: 4, use_tabs: false } }
+}
+
+/// A change to one line's indentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineEdit {
+    /// 0-based.
+    pub line: usize,
+    /// The bytes of whitespace the line starts with now.
+    pub old_len: usize,
+    pub indent: String,
+}
+
+/// The source with every line re-indented.
+pub fn format(source: &str, options: FormatOptions) -> String {
+    apply(source, &format_lines(source, 0..usize::MAX, options))
+}
+
+/// `source` with `edits`, in line order, made.
+pub fn apply(source: &str, edits: &[LineEdit]) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut edits = edits.iter().peekable();
+    for (i, line) in source.split_inclusive('\n').enumerate() {
+        match edits.next_if(|e| e.line == i) {
+            Some(edit) => {
+                out.push_str(&edit.indent);
+                out.push_str(&line[edit.old_len..]);
+            }
+            None => out.push_str(line),
+        }
+    }
+    out
+}
+
+/// The edits that re-indent the 0-based `lines` of `source`, for lines
+/// whose indentation is wrong.
+pub fn format_lines(source: &str, lines: Range<usize>, options: FormatOptions) -> Vec<LineEdit> {
+    Layout::of(source).edits(source, lines, None, options)
+}
+
+/// The edits after typing `ch`, with the cursor now on 0-based `line`: a
+/// `}` re-indents the block it closes, a newline the line it ends and the
+/// new line, which is indented even if blank, and anything else the line
+/// it was typed on.
+pub fn on_type(source: &str, line: usize, ch: char, options: FormatOptions) -> Vec<LineEdit> {
+    let layout = Layout::of(source);
+    let (lines, cursor) = match ch {
+        '}' => (layout.blocks.iter().filter(|b| *b.end() == line).map(|b| *b.start()).min().unwrap_or(line)..line + 1, None),
+        '\n' => (line.saturating_sub(1)..line + 1, Some(line)),
+        _ => (line..line + 1, None),
+    };
+    layout.edits(source, lines, cursor, options)
+}
+
+impl FormatOptions {
+    fn indent(&self, level: usize) -> String {
+        if self.use_tabs { "\t".repeat(level) } else { " ".repeat(level * self.indent_width) }
+    }
+}
+
+/// What decides a line's indentation.
+#[derive(Debug, Clone, Copy, Default)]
+struct LineState {
+    /// Brackets open at the start of the line.
+    depth: usize,
+    /// The line starts by closing a bracket or with `public:` and the like.
+    outdent: bool,
+    /// The line continues a statement.
+    continued: bool,
+    /// The line is under a `case` or `default` label.
+    in_case: bool,
+    /// Left as it is.
+    fixed: bool,
+}
+
+impl LineState {
+    fn level(&self) -> usize { (self.depth + self.continued as usize + self.in_case as usize).saturating_sub(self.outdent as usize) }
+}
+
+struct Layout {
+    lines: Vec<LineState>,
+    /// The lines of each pair of braces.
+    blocks: Vec<RangeInclusive<usize>>,
+}
+
+impl Layout {
+    /// The edits for `lines`, where the line `cursor` is on is indented
+    /// even if blank.
+    fn edits(&self, source: &str, lines: Range<usize>, cursor: Option<usize>, options: FormatOptions) -> Vec<LineEdit> {
+        let mut edits = Vec::new();
+        for (i, text) in source.split('\n').enumerate().take(lines.end).skip(lines.start) {
+            let Some(state) = self.lines.get(i).filter(|s| !s.fixed) else { continue };
+            let text = text.strip_suffix('\r').unwrap_or(text);
+            let old_len = text.len() - text.trim_start().len();
+            let blank = text.trim().is_empty() && cursor != Some(i);
+            let indent = if blank { String::new() } else { options.indent(state.level()) };
+            if text[..old_len] != indent { edits.push(LineEdit { line: i, old_len, indent }); }
+        }
+        edits
+    }
+
+    fn of(source: &str) -> Self {
+        let starts: Vec<usize> = std::iter::once(0).chain(source.match_indices('\n').map(|(i, _)| i + 1)).collect();
+        let line_of = |pos: usize| starts.partition_point(|&s| s <= pos) - 1;
+        let mut lines = vec![LineState::default(); starts.len()];
+        let mut lexer = Lexer::with_options(source, LexerOptions { preserve_comments: true });
+        let mut tokens: Vec<SpannedToken> = Vec::new();
+        loop {
+            match lexer.next_token() {
+                Ok(t) if t.token == Token::Eof => break,
+                Ok(t) => tokens.push(t),
+                Err(e) => {
+                    for line in &mut lines[line_of(e.span.start)..] { line.fixed = true; }
+                    break;
+                }
+            }
+        }
+
+        let mut blocks = Vec::new();
+        // Brackets open, innermost last, with whether a label has been seen
+        // in each and the line it opened on.
+        let mut open: Vec<(char, bool, usize)> = Vec::new();
+        // The last token that is not a comment, outside directives.
+        let mut last: Option<&Token> = None;
+        let mut next_line = 0;
+        let mut in_directive = false;
+        for (i, t) in tokens.iter().enumerate() {
+            let line = line_of(t.span.start);
+            // Lines up to this token's start where nothing else did.
+            while next_line <= line {
+                let state = &mut lines[next_line];
+                state.depth = open.len();
+                state.continued = continues(&open, last) && (next_line < line || !opens_brace(&t.token));
+                state.in_case = open.last().is_some_and(|&(_, labeled, _)| labeled);
+                if next_line == line {
+                    let next = tokens.get(i + 1).map(|t| &t.token);
+                    // A closing brace ends its labels' block.
+                    state.in_case &= !closes(&t.token);
+                    state.outdent = closes(&t.token) || is_label(&t.token, next) || (state.in_case && is_case(&t.token, next));
+                    state.fixed |= in_directive || matches!(t.token, Token::Directive(_));
+                }
+                next_line += 1;
+            }
+            // Lines inside a token, like a block comment, keep their text.
+            for line in &mut lines[line + 1..=line_of(t.span.end.max(t.span.start))] { line.fixed = true; }
+            match &t.token {
+                Token::Directive(_) => in_directive = true,
+                Token::DirectiveEnd => in_directive = false,
+                _ if in_directive => {}
+                Token::Comment { .. } => {}
+                token => {
+                    match bracket(token) {
+                        Some(c @ ('{' | '(' | '[')) => open.push((c, false, line)),
+                        Some(c) => {
+                            if let Some((_, _, start)) = open.pop().filter(|_| c == '}') { blocks.push(start..=line); }
+                        }
+                        None => {}
+                    }
+                    if is_case(token, tokens.get(i + 1).map(|t| &t.token)) {
+                        if let Some((_, labeled, _)) = open.last_mut() { *labeled = true; }
+                    }
+                    last = Some(token);
+                }
+            }
+        }
+        // Lines after the last token.
+        for state in &mut lines[next_line..] {
+            state.depth = open.len();
+            state.continued = continues(&open, last);
+            state.in_case = open.last().is_some_and(|&(_, labeled, _)| labeled);
+        }
+        Layout { lines, blocks }
+    }
+}
+
+/// Whether a line after `last` continues its statement: one inside braces
+/// or at file scope, where `last` does not finish it.
+fn continues(open: &[(char, bool, usize)], last: Option<&Token>) -> bool {
+    open.last().is_none_or(|&(c, _, _)| c == '{') && last.is_some_and(|l| !finishes(l))
+}
+
+fn bracket(token: &Token) -> Option<char> {
+    match token {
+        Token::Punct(c @ ('{' | '}' | '(' | ')' | '[' | ']')) => Some(*c),
+        _ => None,
+    }
+}
+
+fn closes(token: &Token) -> bool { matches!(bracket(token), Some('}' | ')' | ']')) }
+
+fn opens_brace(token: &Token) -> bool { bracket(token) == Some('{') }
+
+/// Whether a statement or declaration may end after `token`, so the next
+/// line starts a new one: after `;`, a brace, a label's `:` or a `,`, and
+/// after `>` and `]`, which end template headers and attributes.
+fn finishes(token: &Token) -> bool {
+    match token {
+        Token::Punct(c) => matches!(c, ';' | '{' | '}' | ',' | ']' | '>'),
+        Token::Operator(op) => *op == ":",
+        _ => false,
+    }
+}
+
+/// `public:` and the like.
+fn is_label(token: &Token, next: Option<&Token>) -> bool {
+    matches!(token, Token::Identifier(w) if matches!(&*w.as_str(), "public" | "protected" | "private"))
+        && matches!(next, Some(Token::Operator(op)) if *op == ":")
+}
+
+/// `case` or `default:`, but not `= default`.
+fn is_case(token: &Token, next: Option<&Token>) -> bool {
+    match token {
+        Token::Identifier(w) if w == "case" => true,
+        Token::Identifier(w) if w == "default" => matches!(next, Some(Token::Operator(op)) if *op == ":"),
+        _ => false,
+    }
+}
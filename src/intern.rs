@@ -0,0 +1,138 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+/// A small, copyable handle to an interned string.
+///
+/// Symbols live in a per-thread table, so two symbols of a thread compare
+/// equal exactly when they were interned from the same text, independent of
+/// which session produced them. What is interned while a `TuArena` is the
+/// newest on its thread is freed with it; ids are never reused, so a symbol
+/// kept past its arena panics when read rather than naming other text.
+/// A symbol names nothing on another thread, so it is neither `Send` nor
+/// `Sync`:
+///
+/// ```compile_fail
+/// fn send<T: Send>(_: T) {}
+/// send(ruscom::intern::Symbol::intern("x"));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32, PhantomData<*const ()>);
+
+#[derive(Default)]
+struct SymbolTable {
+    map: HashMap<Rc<str>, Symbol>,
+    strings: BTreeMap<u32, Rc<str>>,
+    next: u32,
+}
+
+thread_local! {
+    static TABLE: RefCell<SymbolTable> = RefCell::default();
+}
+
+impl Symbol {
+    /// Interns `s` in this thread's table.
+    pub fn intern(s: &str) -> Symbol {
+        TABLE.with_borrow_mut(|table| {
+            if let Some(&sym) = table.map.get(s) { return sym; }
+            let sym = Symbol(table.next, PhantomData);
+            table.next += 1;
+            let text: Rc<str> = s.into();
+            table.strings.insert(sym.0, text.clone());
+            table.map.insert(text, sym);
+            sym
+        })
+    }
+
+    /// The text `self` was interned from.
+    ///
+    /// # Panics
+    ///
+    /// If the arena `self` was interned under has been dropped or reset.
+    pub fn as_str(self) -> Rc<str> {
+        let text = TABLE.with_borrow(|table| table.strings.get(&self.0).cloned());
+        text.unwrap_or_else(|| panic!("symbol {} outlived the translation unit it was interned for", self.0))
+    }
+
+    pub fn as_u32(self) -> u32 { self.0 }
+
+    /// Calls `f` with the text of `self` without cloning it.
+    fn with_str<R>(self, f: impl FnOnce(&str) -> R) -> R {
+        TABLE.with_borrow(|table| match table.strings.get(&self.0) {
+            Some(text) => f(text),
+            None => panic!("symbol {} outlived the translation unit it was interned for", self.0),
+        })
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(s: &str) -> Symbol { Symbol::intern(s) }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool { self.with_str(|text| text == other) }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool { *self == **other }
+}
+
+/// The id the next symbol interned on this thread gets.
+pub(crate) fn watermark() -> u32 { TABLE.with_borrow(|table| table.next) }
+
+/// Frees the symbols interned on this thread since `watermark`.
+pub(crate) fn release(watermark: u32) {
+    TABLE.with_borrow_mut(|table| {
+        for text in table.strings.split_off(&watermark).into_values() { table.map.remove(&text); }
+    });
+}
+
+/// Shown as its text, so a token prints as `Identifier("main")`.
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.with_str(|text| fmt::Debug::fmt(text, f))
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.as_str())
+    }
+}
+
+/// The distinct names a session has seen.
+///
+/// It keeps their text rather than symbols, which belong to the translation
+/// unit they were interned for, so a session can report how many distinct
+/// names it used across all of its units.
+#[derive(Default)]
+pub struct Interner {
+    names: HashSet<Box<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self { Self::default() }
+
+    /// Records `s` and returns its symbol.
+    pub fn intern(&mut self, s: &str) -> Symbol {
+        self.record(s);
+        Symbol::intern(s)
+    }
+
+    /// Records `s` without interning it.
+    pub fn record(&mut self, s: &str) {
+        if !self.names.contains(s) { self.names.insert(s.into()); }
+    }
+
+    /// The symbol of `s`, if this session has seen it.
+    pub fn get(&self, s: &str) -> Option<Symbol> { self.names.contains(s).then(|| Symbol::intern(s)) }
+
+    /// Returns the text a symbol was interned from.
+    pub fn resolve(&self, sym: Symbol) -> Rc<str> { sym.as_str() }
+
+    pub fn len(&self) -> usize { self.names.len() }
+
+    pub fn is_empty(&self) -> bool { self.names.is_empty() }
+}
@@ -1,127 +1,521 @@
-use crate::lexer::token::{Token, LexError, LexResult};
+use std::borrow::Cow;
 
+use crate::intern::Symbol;
+use crate::lexer::token::{Encoding, LexError, LexResult, Literal, LiteralChar, Span, SpannedLexError, SpannedToken, Token};
+
+/// Knobs controlling what the lexer emits.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LexerOptions {
+    /// Emit `Token::Comment` instead of silently skipping comments.
+    pub preserve_comments: bool,
+}
+
+const IDENT_START: u8 = 1;
+const IDENT: u8 = 2;
+const DIGIT: u8 = 4;
+const SPACE: u8 = 8;
+const PUNCT: u8 = 16;
+
+/// Character classes for every byte value; non-ASCII bytes have no class.
+static CLASS: [u8; 256] = {
+    let mut table = [0u8; 256];
+    let mut i = 0;
+    while i < 128 {
+        let c = i as u8;
+        let mut class = 0;
+        if c.is_ascii_alphabetic() || c == b'_' { class |= IDENT_START | IDENT; }
+        if c.is_ascii_digit() { class |= DIGIT | IDENT; }
+        if matches!(c, b' ' | b'\t' | b'\n' | b'\r' | 0x0b | 0x0c) { class |= SPACE; }
+        if matches!(c, b'{' | b'}' | b'(' | b')' | b';' | b',' | b'[' | b']' | b'<' | b'>') { class |= PUNCT; }
+        table[i] = class;
+        i += 1;
+    }
+    table
+};
+
+#[inline]
+fn is(c: u8, class: u8) -> bool { CLASS[c as usize] & class != 0 }
+
+const TWO_CHAR_OPS: [&str; 16] = ["==", "!=", "<=", "=>", "->", "++", "--", "+=", "-=", "*=", "/=", "&&", "||", "<<", ">>", "##"];
+
+const ASCII_BYTES: [u8; 128] = {
+    let mut bytes = [0u8; 128];
+    let mut i = 0;
+    while i < 128 { bytes[i] = i as u8; i += 1; }
+    bytes
+};
+
+/// Every ASCII character in order, so a one-character operator is a slice
+/// of it rather than an allocation.
+const ASCII: &str = match std::str::from_utf8(&ASCII_BYTES) { Ok(s) => s, Err(_) => panic!("ASCII is UTF-8") };
+
+/// The operator spelled `text`, as the lexer produces it: one ASCII
+/// character or one of `TWO_CHAR_OPS`.
+pub fn operator(text: &str) -> Option<&'static str> {
+    match text.as_bytes() {
+        &[c] if c.is_ascii() => Some(&ASCII[c as usize..=c as usize]),
+        _ => TWO_CHAR_OPS.iter().copied().find(|&op| op == text),
+    }
+}
+
+/// Removes backslash-newline splices from a slice of source text.
+fn unsplice(text: &str) -> Cow<'_, str> {
+    if text.contains('\\') { Cow::Owned(text.replace("\\\r\n", "").replace("\\\n", "")) } else { Cow::Borrowed(text) }
+}
+
+/// A C++ lexer scanning the source as bytes.
+///
+/// Classification is table-driven and comment and string bodies are searched
+/// with `memchr`. Identifiers are interned and operators are static
+/// strings; other token text is sliced out of the source in one allocation
+/// instead of being built up a character at a time. Only ASCII whitespace
+/// separates tokens.
 pub struct Lexer<'a> {
-    chars: std::str::Chars<'a>,
-    peeked: Option<char>,
+    src: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    /// End offset of the last consumed character, before any splices that
+    /// follow it; used as the end of token spans.
+    last_end: usize,
+    options: LexerOptions,
+    /// No token has been produced yet on the current line, so a `#` here
+    /// starts a preprocessor directive.
+    line_start: bool,
+    /// Inside a directive line; the next newline ends it.
+    in_directive: bool,
+    /// The previous token was `#include`-like, so `<...>`/`"..."` is a header name.
+    expect_header: bool,
+    /// Added to every span, placing this source in the session-wide position space.
+    base: usize,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
-        let mut chars = input.chars();
-        let peeked = chars.next();
-        Self { chars, peeked }
+        Self::with_options(input, LexerOptions::default())
     }
 
-    fn bump(&mut self) -> Option<char> {
-        let cur = self.peeked;
-        self.peeked = self.chars.next();
-        cur
+    pub fn with_options(input: &'a str, options: LexerOptions) -> Self {
+        let mut lexer = Self {
+            src: input, bytes: input.as_bytes(), pos: 0, last_end: 0, options,
+            line_start: true, in_directive: false, expect_header: false, base: 0,
+        };
+        lexer.skip_splices();
+        lexer
     }
 
-    fn peek(&self) -> Option<char> { self.peeked }
+    /// Offsets all spans by `base`, the start position the `SourceManager`
+    /// assigned to this file.
+    pub fn with_base_offset(mut self, base: usize) -> Self {
+        self.base = base;
+        self
+    }
 
-    fn eat_while<F>(&mut self, mut f: F) -> String
-    where F: FnMut(char) -> bool {
-        let mut s = String::new();
-        while let Some(c) = self.peek() {
-            if f(c) {
-                s.push(c);
-                self.bump();
-            } else {
-                break;
+    /// Lexes the input as the rest of a line that already has a token on
+    /// it, so a `#` at its start is not a directive.
+    pub fn mid_line(mut self) -> Self {
+        self.line_start = false;
+        self
+    }
+
+    fn span(&self, start: usize, end: usize) -> Span { Span::new(self.base + start, self.base + end) }
+
+    /// Skips any backslash-newline pairs at the current position (translation
+    /// phase 2), advancing `pos` past them so spans keep pointing at the
+    /// original source bytes.
+    #[inline]
+    fn skip_splices(&mut self) {
+        while self.bytes.get(self.pos) == Some(&b'\\') {
+            match (self.bytes.get(self.pos + 1), self.bytes.get(self.pos + 2)) {
+                (Some(b'\n'), _) => self.pos += 2,
+                (Some(b'\r'), Some(b'\n')) => self.pos += 3,
+                _ => return,
             }
         }
-        s
     }
 
-    fn skip_whitespace_and_comments(&mut self) {
+    #[inline]
+    fn peek(&self) -> Option<u8> { self.bytes.get(self.pos).copied() }
+
+    /// The byte after the next one, looking through splices.
+    fn peek2(&self) -> Option<u8> {
+        let mut i = self.pos + 1;
         loop {
-            let mut progressed = false;
-            while let Some(c) = self.peek() {
-                if c.is_whitespace() {
-                    progressed = true;
-                    self.bump();
-                } else {
-                    break;
-                }
+            match (self.bytes.get(i), self.bytes.get(i + 1), self.bytes.get(i + 2)) {
+                (Some(b'\\'), Some(b'\n'), _) => i += 2,
+                (Some(b'\\'), Some(b'\r'), Some(b'\n')) => i += 3,
+                (c, _, _) => return c.copied(),
             }
+        }
+    }
 
-            if self.peek() == Some('/') {
-                // need to check next char without consuming permanently; clone iterator
-                let mut clone = self.chars.clone();
-                let next = clone.next();
-                if next == Some('/') {
-                    // consume '//'
-                    self.bump(); self.bump();
-                    while let Some(c) = self.peek() {
-                        self.bump();
-                        if c == '\n' { break; }
+    /// Moves past `len` bytes that are known not to contain splices.
+    #[inline]
+    fn advance(&mut self, len: usize) {
+        self.pos += len;
+        self.last_end = self.pos;
+        self.skip_splices();
+    }
+
+    #[inline]
+    fn bump(&mut self) -> Option<u8> {
+        let c = self.peek()?;
+        if c == b'\n' { self.line_start = true; }
+        self.advance(1);
+        Some(c)
+    }
+
+    /// Consumes one full (possibly multi-byte) character.
+    fn bump_char(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        if c.is_ascii() { self.bump(); return Some(c as char); }
+        let ch = self.src[self.pos..].chars().next()?;
+        self.advance(ch.len_utf8());
+        Some(ch)
+    }
+
+    /// Consumes bytes while they have `class` and returns where the run started.
+    #[inline]
+    fn eat_class(&mut self, class: u8) -> usize {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !is(c, class) { break; }
+            self.bump();
+        }
+        start
+    }
+
+    /// The text from `start` up to the last consumed character, splices removed.
+    fn text_from(&self, start: usize) -> Cow<'a, str> { unsplice(&self.src[start..self.last_end]) }
+
+    /// Skips whitespace, stopping at a newline that ends a directive.
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == b'\n' && self.in_directive { break; }
+            if is(c, SPACE) { self.bump(); } else { break; }
+        }
+    }
+
+    /// Lexes the directive name after a line-initial `#`.
+    fn read_directive(&mut self) -> Token {
+        while matches!(self.peek(), Some(b' ' | b'\t')) { self.bump(); }
+        let start = self.eat_class(IDENT);
+        let name = if start == self.pos { String::new() } else { self.text_from(start).into_owned() };
+        self.expect_header = matches!(name.as_str(), "include" | "include_next" | "import" | "embed");
+        Token::Directive(name)
+    }
+
+    /// Lexes a `<...>` or `"..."` header name; the opening delimiter has
+    /// already been consumed.
+    fn read_header_name(&mut self, angled: bool) -> LexResult<Token> {
+        let close = if angled { b'>' } else { b'"' };
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c == close || c == b'\n' { break; }
+            self.bump();
+        }
+        if self.peek() != Some(close) { return Err(LexError::UnterminatedHeaderName); }
+        let name = if start == self.pos { String::new() } else { self.text_from(start).into_owned() };
+        self.bump();
+        Ok(Token::HeaderName { name, angled })
+    }
+
+    /// Returns whether a `//` or `/*` comment starts at the current position.
+    fn at_comment(&self) -> bool {
+        self.peek() == Some(b'/') && matches!(self.peek2(), Some(b'/' | b'*'))
+    }
+
+    /// Consumes a comment starting at the current position and returns its
+    /// text (without the delimiters) and whether it was a block comment.
+    fn read_comment(&mut self) -> (String, bool) {
+        self.bump();
+        let block = self.bump() == Some(b'*');
+        let start = self.pos;
+        let rest = &self.bytes[start..];
+        if !block {
+            // A line comment ends at the first newline that is not spliced.
+            let mut end = start;
+            let mut search = rest;
+            loop {
+                match memchr::memchr(b'\n', search) {
+                    Some(i) => {
+                        let nl = end + i;
+                        let spliced = nl > start && (self.bytes[nl - 1] == b'\\'
+                            || (self.bytes[nl - 1] == b'\r' && nl > start + 1 && self.bytes[nl - 2] == b'\\'));
+                        if !spliced { end = nl; break; }
+                        end = nl + 1;
+                        search = &self.bytes[end..];
                     }
-                    continue;
-                } else if next == Some('*') {
-                    // consume '/*'
-                    self.bump(); self.bump();
+                    None => { end = self.bytes.len(); break; }
+                }
+            }
+            let text = unsplice(&self.src[start..end]).into_owned();
+            self.pos = end;
+            self.last_end = end;
+            return (text, false);
+        }
+        let mut i = 0;
+        let (text_end, end) = loop {
+            match memchr::memchr(b'*', &rest[i..]) {
+                Some(j) => {
+                    let star = start + i + j;
+                    let mut after = star + 1;
                     loop {
-                        match self.bump() {
-                            Some('*') => {
-                                if self.peek() == Some('/') { self.bump(); break; }
-                            }
-                            None => break,
-                            _ => {}
+                        match (self.bytes.get(after), self.bytes.get(after + 1), self.bytes.get(after + 2)) {
+                            (Some(b'\\'), Some(b'\n'), _) => after += 2,
+                            (Some(b'\\'), Some(b'\r'), Some(b'\n')) => after += 3,
+                            _ => break,
                         }
                     }
-                    continue;
+                    if self.bytes.get(after) == Some(&b'/') { break (star, after + 1); }
+                    i += j + 1;
                 }
+                None => break (self.bytes.len(), self.bytes.len()),
             }
+        };
+        if memchr::memchr(b'\n', &self.bytes[start..end]).is_some() { self.line_start = true; }
+        let text = unsplice(&self.src[start..text_end]).into_owned();
+        self.pos = end;
+        self.last_end = end;
+        self.skip_splices();
+        (text, true)
+    }
 
-            if !progressed { break; }
+    /// Reads the escape sequence after a `\` in a literal with `encoding`:
+    /// a simple escape, up to three octal digits, `\x` and any number of
+    /// hex digits, `\u`/`\U` and four or eight hex digits, or the
+    /// delimited `\o{}`, `\x{}` and `\u{}`. Octal and hex escapes give a
+    /// code unit, the others a character. `None` at end of input.
+    fn read_escape(&mut self, encoding: Encoding) -> Option<LexResult<LiteralChar>> {
+        let unit = |value: Option<u32>| value.filter(|&v| encoding.max_unit().is_none_or(|max| v <= max)).map(LiteralChar::Unit);
+        let char = |value: Option<u32>| value.and_then(char::from_u32).map(LiteralChar::Char);
+        let c = match self.bump_char()? {
+            'n' => char(Some('\n' as u32)),
+            't' => char(Some('\t' as u32)),
+            'r' => char(Some('\r' as u32)),
+            'a' => char(Some(0x07)),
+            'b' => char(Some(0x08)),
+            'f' => char(Some(0x0c)),
+            'v' => char(Some(0x0b)),
+            c @ ('\\' | '\'' | '"' | '?') => char(Some(c as u32)),
+            c @ '0'..='7' => {
+                let (rest, len) = self.read_digits(8, 2).unwrap_or((0, 0));
+                unit(Some((c as u32 - '0' as u32) * 8u32.pow(len as u32) + rest))
+            }
+            'o' => unit(self.read_delimited_digits(8)),
+            'u' if self.peek() == Some(b'{') => char(self.read_delimited_digits(16)),
+            'x' if self.peek() == Some(b'{') => unit(self.read_delimited_digits(16)),
+            'x' => unit(self.read_digits(16, usize::MAX).map(|(v, _)| v)),
+            c @ ('u' | 'U') => {
+                let len = if c == 'u' { 4 } else { 8 };
+                char(self.read_digits(16, len).filter(|&(_, n)| n == len).map(|(v, _)| v))
+            }
+            _ => None,
+        };
+        Some(c.ok_or(LexError::InvalidEscape))
+    }
+
+    /// Reads up to `max` digits in `radix`, returning their value and
+    /// count; `None` without any digit or when the value overflows.
+    fn read_digits(&mut self, radix: u32, max: usize) -> Option<(u32, usize)> {
+        let (mut value, mut len) = (0u32, 0);
+        while len < max {
+            let Some(d) = self.peek().and_then(|c| (c as char).to_digit(radix)) else { break };
+            value = value.checked_mul(radix)?.checked_add(d)?;
+            len += 1;
+            self.bump();
         }
+        (len > 0).then_some((value, len))
     }
 
-    fn read_string(&mut self) -> LexResult<Token> {
-        let mut s = String::new();
-        while let Some(c) = self.bump() {
-            match c {
-                '\\' => {
-                    if let Some(next) = self.bump() {
-                        let esc = match next {
-                            'n' => '\n',
-                            't' => '\t',
-                            'r' => '\r',
-                            '\\' => '\\',
-                            '\'' => '\'',
-                            '"' => '"',
-                            _ => return Err(LexError::InvalidEscape),
-                        };
-                        s.push(esc);
-                    } else { return Err(LexError::UnterminatedString); }
+    /// Reads `{digits}` in `radix`, for the delimited escapes.
+    fn read_delimited_digits(&mut self, radix: u32) -> Option<u32> {
+        if self.peek() != Some(b'{') { return None; }
+        self.bump();
+        let (value, _) = self.read_digits(radix, usize::MAX)?;
+        if self.peek() != Some(b'}') { return None; }
+        self.bump();
+        Some(value)
+    }
+
+    fn read_string(&mut self, encoding: Encoding) -> LexResult<Token> {
+        let mut chars = Vec::new();
+        loop {
+            let rest = &self.bytes[self.pos..];
+            match memchr::memchr2(b'"', b'\\', rest) {
+                None => {
+                    self.pos = self.bytes.len();
+                    self.last_end = self.pos;
+                    return Err(LexError::UnterminatedString);
+                }
+                Some(i) => {
+                    chars.extend(self.src[self.pos..self.pos + i].chars().map(LiteralChar::Char));
+                    self.advance(i);
+                    // `advance` may have skipped a splice, so look again.
+                    match self.peek() {
+                        Some(b'"') => {
+                            self.bump();
+                            return Ok(Token::StringLiteral(Literal { encoding, chars }));
+                        }
+                        Some(b'\\') => {
+                            self.bump();
+                            match self.read_escape(encoding) {
+                                Some(esc) => chars.push(esc?),
+                                None => return Err(LexError::UnterminatedString),
+                            }
+                        }
+                        _ => {}
+                    }
                 }
-                '"' => return Ok(Token::StringLiteral(s)),
-                c => s.push(c),
             }
         }
-        Err(LexError::UnterminatedString)
-    }
-
-    fn read_char(&mut self) -> LexResult<Token> {
-        if let Some(c) = self.bump() {
-            if c == '\\' {
-                if let Some(next) = self.bump() {
-                    let esc = match next {
-                        'n' => '\n',
-                        't' => '\t',
-                        'r' => '\r',
-                        '\\' => '\\',
-                        '\'' => '\'',
-                        '"' => '"',
-                        _ => return Err(LexError::InvalidEscape),
-                    };
-                    if self.peek() == Some('\'') { self.bump(); Ok(Token::CharLiteral(esc)) } else { Err(LexError::UnterminatedChar) }
-                } else { Err(LexError::UnterminatedChar) }
-            } else {
-                if self.peek() == Some('\'') { self.bump(); Ok(Token::CharLiteral(c)) } else { Err(LexError::UnterminatedChar) }
+    }
+
+    /// Reads a raw string literal after its `R"`: a delimiter of up to 16
+    /// characters, `(`, the contents as they are, `)`, the delimiter and `"`.
+    fn read_raw_string(&mut self, encoding: Encoding) -> LexResult<Token> {
+        let start = self.pos;
+        let open = self.bytes[start..].iter().take(17).position(|&c| c == b'(').ok_or(LexError::UnterminatedString)?;
+        let delimiter = &self.src[start..start + open];
+        if delimiter.bytes().any(|c| matches!(c, b' ' | b')' | b'\\' | b'\t' | b'\n' | 0x0b | 0x0c)) {
+            return Err(LexError::UnterminatedString);
+        }
+        let body = start + open + 1;
+        let close = format!("){}\"", delimiter);
+        let Some(len) = self.src[body..].find(&close) else {
+            self.pos = self.bytes.len();
+            self.last_end = self.pos;
+            return Err(LexError::UnterminatedString);
+        };
+        let chars = self.src[body..body + len].chars().map(LiteralChar::Char).collect();
+        // Newlines in the contents do not start lines for directives.
+        self.pos = body + len + close.len();
+        self.last_end = self.pos;
+        self.skip_splices();
+        Ok(Token::StringLiteral(Literal { encoding, chars }))
+    }
+
+    /// Reads a character literal after its `'`. It may hold several
+    /// characters, as an ordinary multicharacter literal like `'ab'` does.
+    fn read_char(&mut self, encoding: Encoding) -> LexResult<Token> {
+        let mut chars = Vec::new();
+        loop {
+            let c = match self.bump_char() {
+                Some('\'') if !chars.is_empty() => return Ok(Token::CharLiteral(Literal { encoding, chars })),
+                Some('\\') => match self.read_escape(encoding) {
+                    Some(esc) => esc?,
+                    None => return Err(LexError::UnterminatedChar),
+                },
+                Some('\'' | '\n') | None => return Err(LexError::UnterminatedChar),
+                Some(c) => LiteralChar::Char(c),
+            };
+            chars.push(c);
+        }
+    }
+
+    /// Skips source without forming tokens until a line that starts with
+    /// `#`, or end of input. The preprocessor uses this for groups excluded
+    /// by `#if`, whose text only has to be split into lines: an apostrophe
+    /// in `#if 0` prose must not become an unterminated char literal.
+    /// Comments are still recognized, as they can hide a `#`.
+    pub fn skip_to_directive(&mut self) {
+        self.in_directive = false;
+        self.expect_header = false;
+        loop {
+            if self.at_comment() { self.read_comment(); continue; }
+            match self.peek() {
+                None => return,
+                Some(b'#') if self.line_start => return,
+                Some(c) if is(c, SPACE) => { self.bump(); }
+                Some(_) => { self.line_start = false; self.bump(); }
             }
-        } else { Err(LexError::UnterminatedChar) }
+        }
+    }
+
+    /// Lexes the next token together with the byte span it covers.
+    pub fn next_token(&mut self) -> Result<SpannedToken, SpannedLexError> {
+        loop {
+            self.skip_whitespace();
+            if !self.at_comment() { break; }
+            let start = self.pos;
+            let (text, block) = self.read_comment();
+            if self.options.preserve_comments {
+                let span = self.span(start, self.last_end);
+                return Ok(SpannedToken { token: Token::Comment { text, block }, span });
+            }
+        }
+
+        let start = self.pos;
+        if self.in_directive && matches!(self.peek(), Some(b'\n') | None) {
+            self.in_directive = false;
+            self.expect_header = false;
+            return Ok(SpannedToken { token: Token::DirectiveEnd, span: self.span(start, start) });
+        }
+        let line_start = std::mem::replace(&mut self.line_start, false);
+        let expect_header = std::mem::replace(&mut self.expect_header, false);
+        let Some(c) = self.peek() else {
+            return Ok(SpannedToken { token: Token::Eof, span: self.span(start, start) });
+        };
+        match self.lex_token(c, start, line_start, expect_header) {
+            Ok(token) => Ok(SpannedToken { token, span: self.span(start, self.last_end) }),
+            Err(error) => Err(SpannedLexError { error, span: self.span(start, self.last_end) }),
+        }
+    }
+
+    /// Lexes a token starting with byte `c` at `start`.
+    fn lex_token(&mut self, c: u8, start: usize, line_start: bool, expect_header: bool) -> LexResult<Token> {
+        let token = match c {
+            c if is(c, IDENT_START) => {
+                self.eat_class(IDENT);
+                let text = self.text_from(start);
+                // An encoding prefix, or `R` for a raw string, right before
+                // the quote: `u8"..."`, `L'x'`, `R"(...)"`.
+                let (prefix, raw) = match text.strip_suffix('R') {
+                    Some(prefix) => (prefix, true),
+                    None => (&*text, false),
+                };
+                match (Encoding::from_prefix(prefix), self.peek()) {
+                    (Some(encoding), Some(b'"')) if raw => { self.bump(); self.read_raw_string(encoding)? }
+                    (Some(encoding), Some(b'"')) if !prefix.is_empty() => { self.bump(); self.read_string(encoding)? }
+                    (Some(encoding), Some(b'\'')) if !raw && !prefix.is_empty() => { self.bump(); self.read_char(encoding)? }
+                    _ => Token::Identifier(Symbol::intern(&text)),
+                }
+            }
+            c if is(c, DIGIT) || (c == b'.' && self.peek2().is_some_and(|n| is(n, DIGIT))) => {
+                // A pp-number: takes in suffixes, hex digits and exponent
+                // signs so `0x1Fu`, `201703L`, `1e+5` and `.5f` are single
+                // tokens.
+                let mut prev = c;
+                while let Some(c) = self.peek() {
+                    let sign = matches!(c, b'+' | b'-') && matches!(prev, b'e' | b'E' | b'p' | b'P');
+                    let separator = c == b'\'' && self.peek2().is_some_and(|n| is(n, IDENT));
+                    if !(is(c, IDENT) || c == b'.' || separator || sign) { break; }
+                    prev = c;
+                    self.bump();
+                }
+                Token::Number(self.text_from(start).into_owned())
+            }
+            b'#' if line_start => {
+                self.bump();
+                self.in_directive = true;
+                self.read_directive()
+            }
+            b'<' | b'"' if expect_header => {
+                self.bump();
+                self.read_header_name(c == b'<')?
+            }
+            b'"' => { self.bump(); self.read_string(Encoding::Ordinary)? }
+            b'\'' => { self.bump(); self.read_char(Encoding::Ordinary)? }
+            c if is(c, PUNCT) => { self.bump(); Token::Punct(c as char) }
+            c if c.is_ascii() => {
+                self.bump();
+                let two = self.peek().and_then(|next| TWO_CHAR_OPS.iter().copied().find(|op| op.as_bytes() == [c, next]));
+                if two.is_some() { self.bump(); }
+                Token::Operator(two.unwrap_or(&ASCII[c as usize..=c as usize]))
+            }
+            // A stray character outside ASCII.
+            _ => Token::Punct(self.bump_char().unwrap_or(char::REPLACEMENT_CHARACTER)),
+        };
+        Ok(token)
     }
 }
 
@@ -129,33 +523,7 @@ impl<'a> Iterator for Lexer<'a> {
     type Item = LexResult<Token>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.skip_whitespace_and_comments();
-        let ch = self.bump();
-        match ch {
-            None => Some(Ok(Token::Eof)),
-            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
-                let mut s = String::new(); s.push(c);
-                s.push_str(&self.eat_while(|ch| ch.is_ascii_alphanumeric() || ch == '_'));
-                Some(Ok(Token::Identifier(s)))
-            }
-            Some(c) if c.is_ascii_digit() => {
-                let mut s = String::new(); s.push(c);
-                s.push_str(&self.eat_while(|ch| ch.is_ascii_digit() || ch == '.'));
-                Some(Ok(Token::Number(s)))
-            }
-            Some('"') => Some(self.read_string()),
-            Some('\'') => Some(self.read_char()),
-            Some(c) if "{}();,[]<>".contains(c) => Some(Ok(Token::Punct(c))),
-            Some(c) => {
-                let mut s = String::new(); s.push(c);
-                if let Some(next) = self.peek() {
-                    let two = format!("{}{}", c, next);
-                    let two_ops = ["==","!=","<=","=>","->","++","--","+=","-=","*=","/=","&&","||","<<", ">>"];
-                    if two_ops.contains(&two.as_str()) { self.bump(); s.push(next); }
-                }
-                Some(Ok(Token::Operator(s)))
-            }
-        }
+        Some(self.next_token().map(|t| t.token).map_err(|e| e.error))
     }
 }
 
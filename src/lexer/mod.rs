@@ -1,3 +1,6 @@
 pub mod token;
+#[allow(clippy::module_inception)]
 pub mod lexer;
-pub use lexer::Lexer;
+pub mod stream;
+pub use lexer::{Lexer, LexerOptions};
+pub use stream::{Checkpoint, ExpectError, TokenStream};
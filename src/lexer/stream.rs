@@ -0,0 +1,127 @@
+use std::fmt;
+
+use crate::lexer::lexer::Lexer;
+use crate::lexer::token::{Span, SpannedLexError, SpannedToken, Token, TokenKind};
+
+/// Opaque position in a `TokenStream`, used to backtrack with `rollback`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint(usize);
+
+/// Error returned by `TokenStream::expect` when the next token doesn't match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpectError {
+    pub expected: String,
+    pub found: SpannedToken,
+}
+
+impl fmt::Display for ExpectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected {}, found `{}`", self.expected, self.found.token)
+    }
+}
+
+impl std::error::Error for ExpectError {}
+
+/// Buffered token source with arbitrary lookahead and backtracking.
+///
+/// Tokens are pulled from the lexer on demand and kept, so a parser can
+/// `checkpoint()` before a speculative parse and `rollback()` if it fails.
+/// Comments are set aside in `comments()` rather than returned as tokens.
+/// Lex errors are recorded in `errors()` and lexing resumes after them.
+pub struct TokenStream<'a> {
+    lexer: Lexer<'a>,
+    buf: Vec<SpannedToken>,
+    cursor: usize,
+    done: bool,
+    comments: Vec<SpannedToken>,
+    errors: Vec<SpannedLexError>,
+}
+
+impl<'a> TokenStream<'a> {
+    pub fn new(lexer: Lexer<'a>) -> Self {
+        Self { lexer, buf: Vec::new(), cursor: 0, done: false, comments: Vec::new(), errors: Vec::new() }
+    }
+
+    /// A stream over tokens that were already produced, e.g. by the
+    /// preprocessor. An `Eof` is added if they do not end in one; its span
+    /// is empty, just past the last token.
+    pub fn from_tokens(mut tokens: Vec<SpannedToken>) -> TokenStream<'static> {
+        if tokens.last().is_none_or(|t| t.token != Token::Eof) {
+            let end = tokens.last().map_or(0, |t| t.span.end);
+            tokens.push(SpannedToken { token: Token::Eof, span: Span::new(end, end) });
+        }
+        TokenStream { lexer: Lexer::new(""), buf: tokens, cursor: 0, done: true, comments: Vec::new(), errors: Vec::new() }
+    }
+
+    /// Makes sure at least `n` tokens are buffered past the cursor, or that
+    /// the trailing `Eof` has been reached.
+    fn fill(&mut self, n: usize) {
+        while !self.done && self.buf.len() < self.cursor + n {
+            match self.lexer.next_token() {
+                Ok(t) if t.token.kind() == TokenKind::Comment => self.comments.push(t),
+                Ok(t) => {
+                    if t.token == Token::Eof { self.done = true; }
+                    self.buf.push(t);
+                }
+                Err(e) => self.errors.push(e),
+            }
+        }
+    }
+
+    /// Returns the token `k` positions ahead of the cursor (`peek_nth(0)` is
+    /// the next token). Past the end of input this is the `Eof` token.
+    pub fn peek_nth(&mut self, k: usize) -> &SpannedToken {
+        self.fill(k + 1);
+        let idx = (self.cursor + k).min(self.buf.len() - 1);
+        &self.buf[idx]
+    }
+
+    pub fn peek(&mut self) -> &SpannedToken { self.peek_nth(0) }
+
+    /// Consumes and returns the next token. At end of input keeps returning `Eof`.
+    pub fn bump(&mut self) -> SpannedToken {
+        let tok = self.peek().clone();
+        if tok.token != Token::Eof { self.cursor += 1; }
+        tok
+    }
+
+    pub fn check(&mut self, tok: &Token) -> bool { &self.peek().token == tok }
+
+    pub fn check_kind(&mut self, kind: TokenKind) -> bool { self.peek().token.kind() == kind }
+
+    /// Consumes the next token if it equals `tok`.
+    pub fn eat(&mut self, tok: &Token) -> bool {
+        if self.check(tok) { self.bump(); true } else { false }
+    }
+
+    /// Consumes the next token if it is of the given kind.
+    pub fn eat_kind(&mut self, kind: TokenKind) -> Option<SpannedToken> {
+        if self.check_kind(kind) { Some(self.bump()) } else { None }
+    }
+
+    pub fn expect(&mut self, tok: &Token) -> Result<SpannedToken, ExpectError> {
+        if self.check(tok) { return Ok(self.bump()); }
+        Err(ExpectError { expected: format!("`{}`", tok), found: self.peek().clone() })
+    }
+
+    pub fn expect_kind(&mut self, kind: TokenKind) -> Result<SpannedToken, ExpectError> {
+        if self.check_kind(kind) { return Ok(self.bump()); }
+        Err(ExpectError { expected: kind.to_string(), found: self.peek().clone() })
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint { Checkpoint(self.cursor) }
+
+    pub fn rollback(&mut self, cp: Checkpoint) { self.cursor = cp.0; }
+
+    /// Span of the most recently consumed token, or an empty span at the
+    /// start of input.
+    pub fn prev_span(&self) -> Span {
+        if self.cursor == 0 { Span::default() } else { self.buf[self.cursor - 1].span }
+    }
+
+    pub fn is_eof(&mut self) -> bool { self.check(&Token::Eof) }
+
+    pub fn comments(&self) -> &[SpannedToken] { &self.comments }
+
+    pub fn errors(&self) -> &[SpannedLexError] { &self.errors }
+}
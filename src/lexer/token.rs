@@ -1,21 +1,315 @@
 use std::fmt;
+use std::sync::Arc;
+
+use crate::charset::{Charset, ExecCharsets};
+use crate::diagnostics::{Diagnostic, Label};
+use crate::intern::Symbol;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
-    Identifier(String),
+    Identifier(Symbol),
     Number(String),
-    StringLiteral(String),
-    CharLiteral(char),
-    Operator(String),
+    StringLiteral(Literal),
+    CharLiteral(Literal),
+    Operator(&'static str),
+    /// One of `{}()[];,<>`, or a character outside ASCII.
     Punct(char),
+    /// A `//` or `/* */` comment; only produced when comments are preserved.
+    Comment { text: String, block: bool },
+    /// A line-initial `#name`; the payload is the directive name (`include`,
+    /// `define`, ...) and is empty for the null directive.
+    Directive(String),
+    /// `<name>` or `"name"` after `#include`.
+    HeaderName { name: String, angled: bool },
+    /// The newline (or end of file) that terminates a directive line.
+    DirectiveEnd,
+    /// The contents of a file named by `#embed`, standing for the
+    /// comma-separated list of their byte values: one token however large
+    /// the file is.
+    Embed(Arc<[u8]>),
+    Eof,
+}
+
+/// The variant of a `Token` with its payload stripped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Identifier,
+    Number,
+    StringLiteral,
+    CharLiteral,
+    Operator,
+    Punct,
+    Comment,
+    Directive,
+    HeaderName,
+    DirectiveEnd,
+    Embed,
     Eof,
 }
 
-#[derive(Debug)]
+impl Token {
+    pub fn kind(&self) -> TokenKind {
+        match self {
+            Token::Identifier(_) => TokenKind::Identifier,
+            Token::Number(_) => TokenKind::Number,
+            Token::StringLiteral(_) => TokenKind::StringLiteral,
+            Token::CharLiteral(_) => TokenKind::CharLiteral,
+            Token::Operator(_) => TokenKind::Operator,
+            Token::Punct(_) => TokenKind::Punct,
+            Token::Comment { .. } => TokenKind::Comment,
+            Token::Directive(_) => TokenKind::Directive,
+            Token::HeaderName { .. } => TokenKind::HeaderName,
+            Token::DirectiveEnd => TokenKind::DirectiveEnd,
+            Token::Embed(_) => TokenKind::Embed,
+            Token::Eof => TokenKind::Eof,
+        }
+    }
+
+    /// The token as C++ source text, e.g. a string literal with its quotes
+    /// and escapes restored. Comments and markers spell as nothing.
+    pub fn spelling(&self) -> String {
+        match self {
+            Token::Identifier(s) => s.to_string(),
+            Token::Number(s) => s.clone(),
+            Token::Operator(s) => s.to_string(),
+            Token::StringLiteral(s) => s.spelling('"'),
+            Token::CharLiteral(c) => c.spelling('\''),
+            Token::Punct(c) => c.to_string(),
+            Token::Directive(name) => format!("#{}", name),
+            Token::HeaderName { name, angled: true } => format!("<{}>", name),
+            Token::HeaderName { name, angled: false } => format!("\"{}\"", name),
+            Token::Embed(bytes) => bytes.iter().map(|b| b.to_string()).collect::<Vec<_>>().join(","),
+            Token::Comment { .. } | Token::DirectiveEnd | Token::Eof => String::new(),
+        }
+    }
+}
+
+/// The encoding prefix of a string or character literal, which picks the
+/// character type and the execution character set of its code units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Encoding {
+    #[default]
+    Ordinary,
+    /// `L`: `wchar_t`.
+    Wide,
+    /// `u8`: `char8_t`, UTF-8.
+    Utf8,
+    /// `u`: `char16_t`, UTF-16.
+    Utf16,
+    /// `U`: `char32_t`, UTF-32.
+    Utf32,
+}
+
+impl Encoding {
+    /// The encoding a literal prefix such as `u8` (without any `R`) names.
+    pub fn from_prefix(prefix: &str) -> Option<Self> {
+        Some(match prefix {
+            "" => Encoding::Ordinary,
+            "L" => Encoding::Wide,
+            "u8" => Encoding::Utf8,
+            "u" => Encoding::Utf16,
+            "U" => Encoding::Utf32,
+            _ => return None,
+        })
+    }
+
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Encoding::Ordinary => "",
+            Encoding::Wide => "L",
+            Encoding::Utf8 => "u8",
+            Encoding::Utf16 => "u",
+            Encoding::Utf32 => "U",
+        }
+    }
+
+    /// The largest code unit a numeric escape may give, or `None` where
+    /// that depends on the target's `wchar_t`.
+    pub fn max_unit(self) -> Option<u32> {
+        match self {
+            Encoding::Ordinary | Encoding::Utf8 => Some(0xFF),
+            Encoding::Utf16 => Some(0xFFFF),
+            Encoding::Utf32 => Some(u32::MAX),
+            Encoding::Wide => None,
+        }
+    }
+}
+
+/// One element of a literal's contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiteralChar {
+    /// A character, written as itself or as a named escape like `\n` or
+    /// `\u00e9`; it is encoded in the execution character set.
+    Char(char),
+    /// A code unit given by an octal or hex escape like `\xff`, which is
+    /// taken as it is rather than encoded.
+    Unit(u32),
+}
+
+/// The contents of a string or character literal as written: characters
+/// stay characters until `units` encodes them for the program.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Literal {
+    pub encoding: Encoding,
+    pub chars: Vec<LiteralChar>,
+}
+
+impl Literal {
+    pub fn new(encoding: Encoding, text: &str) -> Self { Self { encoding, chars: text.chars().map(LiteralChar::Char).collect() } }
+
+    pub fn is_empty(&self) -> bool { self.chars.is_empty() }
+
+    /// The contents as text, for a literal that names something (a file,
+    /// a language linkage, a message). A code unit stands for the
+    /// character with its value.
+    pub fn text(&self) -> String {
+        self.chars.iter().map(|c| match *c {
+            LiteralChar::Char(c) => c,
+            LiteralChar::Unit(u) => char::from_u32(u).unwrap_or(char::REPLACEMENT_CHARACTER),
+        }).collect()
+    }
+
+    /// The code units of the contents in `charset`, without a terminator,
+    /// or the first character it cannot encode.
+    pub fn units(&self, charset: Charset) -> Result<Vec<u32>, char> {
+        let mut units = Vec::with_capacity(self.chars.len());
+        let mut buf = [0; 4];
+        for c in &self.chars {
+            match *c {
+                LiteralChar::Char(c) => units.extend(charset.encode(c.encode_utf8(&mut buf))?),
+                LiteralChar::Unit(u) => units.push(u),
+            }
+        }
+        Ok(units)
+    }
+
+    /// The value of a character literal, of the type its prefix gives it,
+    /// with its code units in `charsets`: the one code unit, or for an
+    /// ordinary literal of several, an `int` of them as bytes, the first
+    /// most significant. `char` is signed.
+    pub fn char_value(&self, charsets: ExecCharsets) -> Result<i128, char> {
+        let charset = charsets.charset(self.encoding);
+        let units = self.units(charset)?;
+        Ok(match (self.encoding, units.as_slice()) {
+            (Encoding::Ordinary, [unit]) => *unit as u8 as i8 as i128,
+            (Encoding::Ordinary, units) => units.iter().fold(0u32, |v, &u| v << 8 | (u & 0xFF)) as i32 as i128,
+            (Encoding::Wide, [unit, ..]) if charset.unit_size() == 4 => *unit as i32 as i128,
+            (_, [unit, ..]) => *unit as i128,
+            (_, []) => 0,
+        })
+    }
+
+    /// The literal as source text between `quote`s.
+    pub fn spelling(&self, quote: char) -> String {
+        let mut text = format!("{}{}", self.encoding.prefix(), quote);
+        for c in &self.chars {
+            match *c {
+                LiteralChar::Char(c) => text.push_str(&escape(c, quote)),
+                // Three octal digits, so a digit after it cannot extend it.
+                LiteralChar::Unit(u) if u < 0o1000 => text.push_str(&format!("\\{:03o}", u)),
+                LiteralChar::Unit(u) => text.push_str(&format!("\\x{{{:x}}}", u)),
+            }
+        }
+        text.push(quote);
+        text
+    }
+}
+
+impl From<&str> for Literal {
+    fn from(text: &str) -> Self { Self::new(Encoding::Ordinary, text) }
+}
+
+impl From<String> for Literal {
+    fn from(text: String) -> Self { Self::new(Encoding::Ordinary, &text) }
+}
+
+impl From<char> for Literal {
+    fn from(c: char) -> Self { Self { encoding: Encoding::Ordinary, chars: vec![LiteralChar::Char(c)] } }
+}
+
+/// Escapes `c` for a literal between `quote`s, for the escapes the lexer
+/// reads. The other quote is left alone, as `#` leaves it in `"'a'"`.
+fn escape(c: char, quote: char) -> String {
+    match c {
+        '\n' => "\\n".into(),
+        '\t' => "\\t".into(),
+        '\r' => "\\r".into(),
+        '\\' => "\\\\".into(),
+        c if c == quote => format!("\\{}", c),
+        // Three octal digits, so a digit after it cannot extend it.
+        c if c.is_control() => format!("\\{:03o}", c as u32),
+        c => c.to_string(),
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Identifier(s) => write!(f, "{}", s),
+            Token::Number(s) => write!(f, "{}", s),
+            Token::Operator(s) => write!(f, "{}", s),
+            Token::StringLiteral(s) => f.write_str(&s.spelling('"')),
+            Token::CharLiteral(c) => f.write_str(&c.spelling('\'')),
+            Token::Punct(c) => write!(f, "{}", c),
+            Token::Comment { block: true, .. } => write!(f, "block comment"),
+            Token::Comment { block: false, .. } => write!(f, "line comment"),
+            Token::Directive(name) => write!(f, "#{}", name),
+            Token::HeaderName { name, angled: true } => write!(f, "<{}>", name),
+            Token::HeaderName { name, angled: false } => write!(f, "\"{}\"", name),
+            Token::DirectiveEnd => write!(f, "end of directive"),
+            Token::Embed(bytes) => write!(f, "{} embedded bytes", bytes.len()),
+            Token::Eof => write!(f, "end of file"),
+        }
+    }
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            TokenKind::Identifier => "identifier",
+            TokenKind::Number => "number",
+            TokenKind::StringLiteral => "string literal",
+            TokenKind::CharLiteral => "char literal",
+            TokenKind::Operator => "operator",
+            TokenKind::Punct => "punctuation",
+            TokenKind::Comment => "comment",
+            TokenKind::Directive => "preprocessor directive",
+            TokenKind::HeaderName => "header name",
+            TokenKind::DirectiveEnd => "end of directive",
+            TokenKind::Embed => "embedded file",
+            TokenKind::Eof => "end of file",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Half-open byte range `[start, end)` into the lexed source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self { Self { start, end } }
+
+    pub fn len(&self) -> usize { self.end - self.start }
+
+    pub fn is_empty(&self) -> bool { self.start == self.end }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LexError {
     UnterminatedString,
     UnterminatedChar,
     InvalidEscape,
+    UnterminatedHeaderName,
 }
 
 impl fmt::Display for LexError {
@@ -24,10 +318,54 @@ impl fmt::Display for LexError {
             LexError::UnterminatedString => write!(f, "unterminated string literal"),
             LexError::UnterminatedChar => write!(f, "unterminated char literal"),
             LexError::InvalidEscape => write!(f, "invalid escape sequence"),
+            LexError::UnterminatedHeaderName => write!(f, "missing terminating delimiter for header name"),
         }
     }
 }
 
 impl std::error::Error for LexError {}
 
+impl LexError {
+    /// Stable diagnostic code for this error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexError::UnterminatedString => "E0001",
+            LexError::UnterminatedChar => "E0002",
+            LexError::InvalidEscape => "E0003",
+            LexError::UnterminatedHeaderName => "E0004",
+        }
+    }
+}
+
+/// A lex error together with the source it covers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedLexError {
+    pub error: LexError,
+    pub span: Span,
+}
+
+impl fmt::Display for SpannedLexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { self.error.fmt(f) }
+}
+
+impl std::error::Error for SpannedLexError {}
+
+impl SpannedLexError {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let label = match self.error {
+            LexError::UnterminatedString => "string starts here and never ends",
+            LexError::UnterminatedChar => "expected a closing `'`",
+            LexError::InvalidEscape => "unknown escape",
+            LexError::UnterminatedHeaderName => "header name is missing its closing delimiter",
+        };
+        let diag = Diagnostic::error(self.error.to_string())
+            .with_code(self.error.code())
+            .with_label(Label::primary(self.span, label));
+        match self.error {
+            LexError::InvalidEscape => diag.with_help("escapes are \\n, \\t, \\v, \\b, \\r, \\f, \\a, \\\\, \\?, \\', \\\", octal \\ooo, hex \\xhh and universal \\uXXXX"),
+            _ => diag,
+        }
+    }
+}
+
 pub type LexResult<T> = Result<T, LexError>;
@@ -1 +1,38 @@
+//! RusCom: a C++ compiler prototype.
+//!
+//! The `driver::Driver` type is the entry point for embedding; the modules
+//! below it expose the individual phases.
+
+pub mod abi;
+pub mod api;
+pub mod arena;
+pub mod artifact;
+pub mod ast;
+pub mod bindgen;
+pub mod cancel;
+pub mod cheader;
+pub mod charset;
+pub mod coverage;
+pub mod daemon;
+pub mod depfile;
+pub mod diagnostics;
+pub mod driver;
+pub mod format;
+pub mod intern;
 pub mod lexer;
+pub mod lint;
+pub mod mangle;
+pub mod module;
+pub mod parser;
+pub mod preprocessor;
+pub mod profile;
+pub mod query;
+pub mod rewrite;
+pub mod sema;
+pub mod session;
+pub mod source;
+pub mod symbols;
+pub mod sysroot;
+pub mod types;
+pub mod vfs;
+pub mod visit;
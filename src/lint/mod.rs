@@ -0,0 +1,89 @@
+//! Lints: warnings about how code is written rather than what it means,
+//! for `ruscom lint`. Compilation does not run them.
+//!
+//! Lints look at the tokens of one file as written, before preprocessing
+//! and with comments kept, so they see what its author typed and nothing
+//! a header or macro brought in. The naming lint also looks at what the
+//! file declares, through its reference index, so that its fix-its rename
+//! every use of a name. The pairing lint looks at what every file checked
+//! declares and defines, through the project index, to match the functions
+//! source files define with those their headers declare; the ODR lint
+//! compares the classes and inline functions they define.
+
+use std::path::{Path, PathBuf};
+
+use crate::arena::TuArena;
+use crate::cancel::Cancelled;
+use crate::diagnostics::Diagnostics;
+use crate::lexer::token::{SpannedToken, Token};
+use crate::lexer::{Lexer, LexerOptions};
+use crate::parser::Parser;
+use crate::preprocessor::Preprocessor;
+use crate::sema::references::ReferenceIndex;
+use crate::session::Session;
+use crate::source::{FileId, SourceFile};
+use crate::vfs::FileSystem;
+
+pub mod naming;
+pub mod odr;
+pub mod pairing;
+pub mod spelling;
+
+use naming::NamingConfig;
+use pairing::ProjectIndex;
+use spelling::Dictionary;
+
+/// The file a project keeps its settings in, found in the directory of the
+/// file checked or one above it.
+pub const CONFIG_FILE: &str = "ruscom.toml";
+
+#[derive(Debug, Clone, Default)]
+pub struct LintOptions {
+    /// The built-in wordlist and the project's words.
+    pub dictionary: Dictionary,
+    /// The project's naming conventions; none by default.
+    pub naming: NamingConfig,
+}
+
+/// Runs every lint over `file`, reporting to `diagnostics`. Without the
+/// file's reference index, the naming lint checks only macros.
+pub fn check(file: &SourceFile, index: Option<&ReferenceIndex>, options: &LintOptions, diagnostics: &mut Diagnostics) {
+    let tokens = tokens(file);
+    spelling::check(file, &tokens, &options.dictionary, diagnostics);
+    naming::check(file, &tokens, index, &options.naming, diagnostics);
+}
+
+/// The tokens of `file`, comments included, up to the first one the lexer
+/// rejects; the compiler reports that one.
+pub fn tokens(file: &SourceFile) -> Vec<SpannedToken> {
+    let mut lexer = Lexer::with_options(&file.contents, LexerOptions { preserve_comments: true }).with_base_offset(file.start);
+    let mut tokens = Vec::new();
+    while let Ok(t) = lexer.next_token() {
+        if t.token == Token::Eof { break; }
+        tokens.push(t);
+    }
+    tokens
+}
+
+/// The reference index of `file`, preprocessed and parsed in `session`
+/// into `arena`, adding what it declares and defines to `project` if
+/// given. Both hold symbols, so they must not outlive `arena`. What that
+/// reports is dropped: compiling the file reports it.
+pub fn index(session: &mut Session, arena: &TuArena, file: FileId, project: Option<&mut ProjectIndex>) -> Result<ReferenceIndex, Cancelled> {
+    let kept = session.diagnostics.take();
+    let index = Preprocessor::new(session).run(file).and_then(|output| {
+        let tokens = output.tokens.clone();
+        let unit = Parser::new(session, arena, output.tokens).parse()?;
+        if let Some(project) = project { project.add(&unit, file, &output.files, &session.sources); }
+        Ok(ReferenceIndex::build(&unit, &tokens, &session.sources))
+    });
+    session.diagnostics.take();
+    for diag in kept { session.diagnostics.emit(diag); }
+    index
+}
+
+/// The settings file of the project `file` belongs to: the nearest
+/// `ruscom.toml` in its directory or one above it.
+pub fn project_config(fs: &dyn FileSystem, file: &Path) -> Option<PathBuf> {
+    file.parent()?.ancestors().map(|dir| dir.join(CONFIG_FILE)).find(|path| fs.is_file(path))
+}
@@ -0,0 +1,357 @@
+//! Naming conventions: the case style, prefix and suffix each kind of name
+//! should have, set in the `[naming]` section of a project's
+//! `ruscom.toml`:
+//!
+//! ```toml
+//! [naming]
+//! type = "PascalCase"
+//! function = "lower_snake"
+//! macro = "SCREAMING_SNAKE"
+//! member = "lower_snake"
+//! member_suffix = "_"
+//! ```
+//!
+//! A kind's key sets its style, one of `PascalCase`, `camelCase`,
+//! `lower_snake` and `SCREAMING_SNAKE`, and `<kind>_prefix` and
+//! `<kind>_suffix` what its names start and end with. Kinds without a key
+//! are not checked, except that data members follow `variable` unless
+//! `member` is set.
+//!
+//! Declarations are found through the file's reference index, and each
+//! name that breaks its rule gets a fix-it renaming every use of it, as the
+//! rename refactoring would; if that rename would change what some name
+//! refers to, the reason is noted instead. Macros are found by their
+//! `#define`, and renamed wherever the file names them.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::diagnostics::{Diagnostic, Diagnostics, Label};
+use crate::lexer::token::{Span, SpannedToken, Token};
+use crate::sema::references::{EntityKind, ReferenceIndex};
+use crate::source::SourceFile;
+
+/// How the words of a name are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// `ParseHeader`
+    PascalCase,
+    /// `parseHeader`
+    CamelCase,
+    /// `parse_header`
+    LowerSnake,
+    /// `PARSE_HEADER`
+    UpperSnake,
+}
+
+/// What a rule applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NameKind {
+    /// Classes, enums, typedefs and concepts.
+    Type,
+    Function,
+    Variable,
+    /// Data members, static or not.
+    Member,
+    Parameter,
+    Enumerator,
+    Namespace,
+    TemplateParameter,
+    Macro,
+}
+
+/// The style, prefix and suffix of one kind of name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rule {
+    pub style: Option<Style>,
+    pub prefix: String,
+    pub suffix: String,
+}
+
+/// The rules of a project.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NamingConfig {
+    rules: HashMap<NameKind, Rule>,
+}
+
+/// Why a `[naming]` section was rejected, with its line, 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// Not `key = "value"`.
+    Syntax { line: usize },
+    UnknownKey { line: usize, key: String },
+    UnknownStyle { line: usize, style: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Syntax { line } => write!(f, "line {}: expected `key = \"value\"`", line),
+            ConfigError::UnknownKey { line, key } => write!(f, "line {}: unknown naming key `{}`", line, key),
+            ConfigError::UnknownStyle { line, style } => {
+                write!(f, "line {}: unknown naming style `{}`; expected PascalCase, camelCase, lower_snake or SCREAMING_SNAKE", line, style)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Style {
+    /// The style a config names, by its name or `snake_case` or
+    /// `UPPER_SNAKE`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "PascalCase" => Some(Style::PascalCase),
+            "camelCase" => Some(Style::CamelCase),
+            "lower_snake" | "snake_case" => Some(Style::LowerSnake),
+            "SCREAMING_SNAKE" | "UPPER_SNAKE" => Some(Style::UpperSnake),
+            _ => None,
+        }
+    }
+
+    /// Whether `name` is written in this style. Acronyms are allowed, so
+    /// `HTTPServer` is PascalCase.
+    pub fn matches(self, name: &str) -> bool {
+        match self {
+            Style::PascalCase => !name.contains('_') && !name.starts_with(|c: char| c.is_lowercase()),
+            Style::CamelCase => !name.contains('_') && !name.starts_with(|c: char| c.is_uppercase()),
+            Style::LowerSnake => !name.contains(char::is_uppercase),
+            Style::UpperSnake => !name.contains(char::is_lowercase),
+        }
+    }
+
+    /// The words of `name` written in this style.
+    pub fn apply(self, name: &str) -> String {
+        let words = words(name);
+        let capitalized = |w: &str| {
+            let mut chars = w.chars();
+            chars.next().map(|c| c.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect::<String>()).unwrap_or_default()
+        };
+        match self {
+            Style::PascalCase => words.iter().map(|w| capitalized(w)).collect(),
+            Style::CamelCase => words.iter().enumerate().map(|(i, w)| if i == 0 { w.to_lowercase() } else { capitalized(w) }).collect(),
+            Style::LowerSnake => words.iter().map(|w| w.to_lowercase()).collect::<Vec<_>>().join("_"),
+            Style::UpperSnake => words.iter().map(|w| w.to_uppercase()).collect::<Vec<_>>().join("_"),
+        }
+    }
+}
+
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Style::PascalCase => "PascalCase",
+            Style::CamelCase => "camelCase",
+            Style::LowerSnake => "lower_snake",
+            Style::UpperSnake => "SCREAMING_SNAKE",
+        })
+    }
+}
+
+/// The words of a name: split at underscores and where the case changes,
+/// keeping an acronym before a capitalized word apart from it, with digits
+/// part of the word before them.
+fn words(name: &str) -> Vec<&str> {
+    let chars: Vec<(usize, char)> = name.char_indices().collect();
+    let mut words = Vec::new();
+    let mut start: Option<usize> = None;
+    for (i, &(at, c)) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if let Some(s) = start.take() { words.push(&name[s..at]); }
+            continue;
+        }
+        let prev = i.checked_sub(1).map(|j| chars[j].1).filter(|p| p.is_alphanumeric());
+        let next = chars.get(i + 1).map(|&(_, n)| n);
+        let boundary = c.is_uppercase()
+            && prev.is_some_and(|p| p.is_lowercase() || p.is_ascii_digit() || (p.is_uppercase() && next.is_some_and(char::is_lowercase)));
+        if boundary {
+            if let Some(s) = start.take() { words.push(&name[s..at]); }
+        }
+        start.get_or_insert(at);
+    }
+    if let Some(s) = start { words.push(&name[s..]); }
+    words
+}
+
+impl NameKind {
+    pub const ALL: [NameKind; 9] = [
+        NameKind::Type, NameKind::Function, NameKind::Variable, NameKind::Member, NameKind::Parameter,
+        NameKind::Enumerator, NameKind::Namespace, NameKind::TemplateParameter, NameKind::Macro,
+    ];
+
+    /// The kind's key in `[naming]`.
+    pub fn key(self) -> &'static str {
+        match self {
+            NameKind::Type => "type",
+            NameKind::Function => "function",
+            NameKind::Variable => "variable",
+            NameKind::Member => "member",
+            NameKind::Parameter => "parameter",
+            NameKind::Enumerator => "enumerator",
+            NameKind::Namespace => "namespace",
+            NameKind::TemplateParameter => "template_parameter",
+            NameKind::Macro => "macro",
+        }
+    }
+
+    /// The kind of name `kind` declares; a variable declared in a class is
+    /// a member.
+    pub fn of(kind: EntityKind, is_member: bool) -> Self {
+        match kind {
+            EntityKind::Class | EntityKind::Enum | EntityKind::Typedef | EntityKind::Concept => NameKind::Type,
+            EntityKind::Function => NameKind::Function,
+            EntityKind::Variable if is_member => NameKind::Member,
+            EntityKind::Variable => NameKind::Variable,
+            EntityKind::Parameter => NameKind::Parameter,
+            EntityKind::Enumerator => NameKind::Enumerator,
+            EntityKind::Namespace => NameKind::Namespace,
+            EntityKind::TemplateParam => NameKind::TemplateParameter,
+        }
+    }
+}
+
+impl fmt::Display for NameKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(&self.key().replace('_', " ")) }
+}
+
+impl Rule {
+    /// What `name` should be: its prefix, the rest of it in the rule's
+    /// style if it is not already, and its suffix.
+    pub fn expected(&self, name: &str) -> String {
+        let core = name.strip_prefix(self.prefix.as_str()).unwrap_or(name);
+        let core = core.strip_suffix(self.suffix.as_str()).unwrap_or(core);
+        if core.is_empty() { return name.to_owned(); }
+        let core = match self.style {
+            Some(style) if !style.matches(core) => style.apply(core),
+            _ => core.to_owned(),
+        };
+        format!("{}{}{}", self.prefix, core, self.suffix)
+    }
+}
+
+impl NamingConfig {
+    /// Reads the `[naming]` section of a `ruscom.toml`, ignoring the rest.
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let mut config = NamingConfig::default();
+        let mut in_naming = false;
+        for (i, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() { continue; }
+            if let Some(section) = line.strip_prefix('[') {
+                in_naming = section.trim_end_matches(']').trim() == "naming";
+                continue;
+            }
+            if !in_naming { continue; }
+            let line_no = i + 1;
+            let Some((key, value)) = line.split_once('=') else { return Err(ConfigError::Syntax { line: line_no }) };
+            let (key, value) = (key.trim(), value.trim());
+            let Some(value) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) else { return Err(ConfigError::Syntax { line: line_no }) };
+            let (kind, field) = match key.strip_suffix("_prefix").map(|k| (k, "prefix")).or_else(|| key.strip_suffix("_suffix").map(|k| (k, "suffix"))) {
+                Some((kind, field)) => (kind, field),
+                None => (key, "style"),
+            };
+            let Some(kind) = NameKind::ALL.into_iter().find(|k| k.key() == kind) else { return Err(ConfigError::UnknownKey { line: line_no, key: key.to_owned() }) };
+            let rule = config.rules.entry(kind).or_default();
+            match field {
+                "prefix" => rule.prefix = value.to_owned(),
+                "suffix" => rule.suffix = value.to_owned(),
+                _ => rule.style = Some(Style::parse(value).ok_or_else(|| ConfigError::UnknownStyle { line: line_no, style: value.to_owned() })?),
+            }
+        }
+        Ok(config)
+    }
+
+    /// Whether no kind of name is checked.
+    pub fn is_empty(&self) -> bool { self.rules.is_empty() }
+
+    pub fn set(&mut self, kind: NameKind, rule: Rule) { self.rules.insert(kind, rule); }
+
+    /// The rule names of `kind` follow, if any.
+    pub fn rule(&self, kind: NameKind) -> Option<&Rule> {
+        match kind {
+            NameKind::Member => self.rules.get(&kind).or_else(|| self.rules.get(&NameKind::Variable)),
+            _ => self.rules.get(&kind),
+        }
+    }
+}
+
+/// `line` without the comment a `#` outside a string starts.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Reports the names declared in `file` that break `config`'s rules: the
+/// macros its `tokens` define, and with its reference index, everything
+/// else.
+pub fn check(file: &SourceFile, tokens: &[SpannedToken], index: Option<&ReferenceIndex>, config: &NamingConfig, diagnostics: &mut Diagnostics) {
+    let mut found = Vec::new();
+    if let Some(rule) = config.rule(NameKind::Macro) { found.extend(macros(tokens, rule)); }
+    if let Some(index) = index { found.extend(declarations(file, index, config)); }
+    found.sort_by_key(|d| d.primary_span().map(|s| s.start));
+    for diag in found { diagnostics.emit(diag); }
+}
+
+fn declarations(file: &SourceFile, index: &ReferenceIndex, config: &NamingConfig) -> Vec<Diagnostic> {
+    let mut seen = HashSet::new();
+    let mut out = Vec::new();
+    for r in index.references().iter().filter(|r| r.is_declaration) {
+        let entity = index.entity(r.entity);
+        // Names first declared in a header are that header's to fix.
+        if !seen.insert(r.entity) || entity.span.start < file.start || entity.span.end > file.end() { continue; }
+        let kind = NameKind::of(entity.kind, index.is_member(r.entity));
+        let name = entity.name.as_str();
+        if kind == NameKind::Function && &*name == "main" { continue; }
+        let Some(rule) = config.rule(kind) else { continue };
+        let expected = rule.expected(&name);
+        if *expected == *name { continue; }
+        let mut diag = misnamed(kind, &name, &expected, entity.span);
+        match index.rename(r.entity, &expected) {
+            Ok(spans) => for span in spans { diag = diag.with_fixit(span, expected.clone()) },
+            Err(e) => {
+                if let Some(span) = e.span() { diag = diag.with_label(Label::secondary(span, "")); }
+                diag = diag.with_note(format!("not renamed automatically: {}", e));
+            }
+        }
+        out.push(diag);
+    }
+    out
+}
+
+/// The macros `tokens` define that break `rule`, renamed at every
+/// identifier naming them.
+fn macros(tokens: &[SpannedToken], rule: &Rule) -> Vec<Diagnostic> {
+    let mut seen = HashSet::new();
+    let mut out = Vec::new();
+    for pair in tokens.windows(2) {
+        let (Token::Directive(directive), Token::Identifier(name)) = (&pair[0].token, &pair[1].token) else { continue };
+        if directive != "define" || !seen.insert(name) { continue; }
+        let name = &*name.as_str();
+        let expected = rule.expected(name);
+        if expected == name { continue; }
+        let uses = |n: &str| -> Vec<Span> { tokens.iter().filter(|t| matches!(&t.token, Token::Identifier(i) if i == n)).map(|t| t.span).collect() };
+        let mut diag = misnamed(NameKind::Macro, name, &expected, pair[1].span);
+        match uses(&expected).first() {
+            Some(span) => {
+                diag = diag.with_label(Label::secondary(*span, "")).with_note(format!("not renamed automatically: `{}` is already used in this file", expected));
+            }
+            None => for span in uses(name) { diag = diag.with_fixit(span, expected.clone()) },
+        }
+        out.push(diag);
+    }
+    out
+}
+
+fn misnamed(kind: NameKind, name: &str, expected: &str, span: Span) -> Diagnostic {
+    Diagnostic::warning(format!("{} `{}` should be named `{}`", kind, name, expected)).with_label(Label::primary(span, ""))
+}
@@ -0,0 +1,152 @@
+//! One-definition rule checks across the project: a class, or an inline
+//! function, defined in more than one translation unit must be defined
+//! the same way in each. A header compiled under different macros, or two
+//! headers that happen to define the same name, breaks that silently: the
+//! linker keeps one definition and every other unit runs against a layout
+//! or body it was not compiled for.
+//!
+//! Each definition is compared by its structure, the syntax tree rendered
+//! without positions, so the same header included by every unit matches
+//! itself and only a difference in what was parsed is reported, with the
+//! lines of the two structures that differ.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use crate::ast::{self, Decl, StorageClass, TranslationUnit};
+use crate::diagnostics::{Diagnostic, Diagnostics, Label};
+use crate::lexer::token::Span;
+use crate::lint::pairing::{file_name, qualify};
+use crate::source::{FileId, SourceManager};
+
+/// How many differing lines a report shows.
+const MAX_DIFF_LINES: usize = 12;
+
+/// A definition the rule applies to, as one unit parsed it.
+#[derive(Debug, Clone)]
+pub(crate) struct Definition {
+    /// Qualified, like `geo::Point`; a function's includes its type, since
+    /// overloads are different entities.
+    key: String,
+    /// `class` or `inline function`, as messages say.
+    what: &'static str,
+    name: String,
+    hash: u64,
+    structure: Vec<String>,
+    span: Span,
+}
+
+/// The classes and inline functions with external linkage `unit` defines
+/// at namespace scope, each once.
+pub(crate) fn definitions(unit: &TranslationUnit) -> Vec<Definition> {
+    let mut found: Vec<Definition> = Vec::new();
+    collect(&unit.decls, "", &mut found);
+    let mut seen = HashSet::new();
+    found.retain(|d| seen.insert(d.key.clone()));
+    found
+}
+
+fn collect(decls: &[Decl], scope: &str, out: &mut Vec<Definition>) {
+    for decl in decls {
+        match decl {
+            // What an unnamed namespace defines is a different entity in
+            // each unit.
+            Decl::Namespace(n) => {
+                if let Some(name) = n.name { collect(&n.decls, &qualify(scope, name), out); }
+            }
+            Decl::Export(e) => collect(&e.decls, scope, out),
+            // An explicit specialization is not the template it specializes.
+            Decl::Template(t) if t.params.is_empty() => {}
+            Decl::Template(t) => {
+                if let Some((key, what, name, span)) = entity(&t.decl, scope, true) { out.push(definition(decl, key, what, name, span)); }
+            }
+            _ => {
+                if let Some((key, what, name, span)) = entity(decl, scope, false) { out.push(definition(decl, key, what, name, span)); }
+            }
+        }
+    }
+}
+
+/// The key, kind, name and span of `decl` if the rule applies to it; a
+/// template's functions need not be declared `inline`.
+fn entity(decl: &Decl, scope: &str, template: bool) -> Option<(String, &'static str, String, Span)> {
+    match decl {
+        Decl::Class(c) => {
+            let name = qualify(scope, c.name?);
+            c.members.as_ref()?;
+            Some((name.clone(), "class", name, c.span))
+        }
+        Decl::Function(f) => {
+            let inline = template || f.specifiers.is_inline || f.specifiers.is_constexpr;
+            if !inline || f.body.is_none() || f.specifiers.storage == StorageClass::Static { return None; }
+            let name = qualify(scope, f.name);
+            Some((format!("{} {}", name, f.ty), "inline function", name, f.name_span))
+        }
+        _ => None,
+    }
+}
+
+fn definition(decl: &Decl, key: String, what: &'static str, name: String, span: Span) -> Definition {
+    let structure = ast::structure(decl);
+    let mut hasher = DefaultHasher::new();
+    structure.hash(&mut hasher);
+    Definition { key, what, name, hash: hasher.finish(), structure, span }
+}
+
+/// Reports each entity `units`, the source files checked and what each
+/// defines, define differently: every definition unlike the first one's,
+/// once per way it differs.
+pub(crate) fn check(units: &[(FileId, &[Definition])], sources: &SourceManager, diagnostics: &mut Diagnostics) {
+    let mut first: HashMap<&str, (FileId, &Definition)> = HashMap::new();
+    let mut reported = HashSet::new();
+    for &(file, definitions) in units {
+        for d in definitions {
+            let Some(&(first_file, original)) = first.get(d.key.as_str()) else {
+                first.insert(&d.key, (file, d));
+                continue;
+            };
+            if d.hash == original.hash || !reported.insert((d.key.as_str(), d.hash)) { continue; }
+            let (this, that) = (file_name(&sources.file(file).path), file_name(&sources.file(first_file).path));
+            let mut diag = Diagnostic::warning(format!("{} `{}` is defined differently in `{}` and `{}`", d.what, d.name, that, this))
+                .with_label(Label::primary(d.span, format!("as `{}` sees it", this)));
+            if original.span != d.span { diag = diag.with_label(Label::secondary(original.span, format!("as `{}` sees it", that))); }
+            diagnostics.emit(
+                diag.with_note(format!("the definitions differ (- in `{}`, + in `{}`):\n{}", that, this, diff(&original.structure, &d.structure)))
+                    .with_note("only one definition is kept at link time; the units compiled against the other use the wrong one"),
+            );
+        }
+    }
+}
+
+/// The lines only `old` or only `new` has, as `- line` and `+ line` in
+/// order, found through their longest common subsequence.
+fn diff(old: &[String], new: &[String]) -> String {
+    let (n, m) = (old.len(), new.len());
+    let mut common = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            common[i][j] = if old[i] == new[j] { common[i + 1][j + 1] + 1 } else { common[i + 1][j].max(common[i][j + 1]) };
+        }
+    }
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(format!("    - {}", old[i]));
+            i += 1;
+        } else {
+            lines.push(format!("    + {}", new[j]));
+            j += 1;
+        }
+    }
+    if lines.len() > MAX_DIFF_LINES {
+        let more = lines.len() - MAX_DIFF_LINES;
+        lines.truncate(MAX_DIFF_LINES);
+        lines.push(format!("    ... and {} more", more));
+    }
+    lines.join("\n")
+}
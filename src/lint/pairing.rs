@@ -0,0 +1,199 @@
+//! Header/source pairing: each function a source file defines with
+//! external linkage should be declared, with the same type, in its header
+//! (the file beside it with the same stem and a header extension, like
+//! `shapes.h` for `shapes.cpp`), and each function a header declares
+//! should be defined somewhere. Either mismatch is a link error waiting for
+//! a caller: a call through the header's declaration finds no definition,
+//! or finds one only for a different overload.
+//!
+//! The checks run over the project index, which holds what every linted
+//! file declares and defines, so a header's function may be defined by
+//! any of them and a source file's by a header other than its own. Only
+//! functions at namespace scope are checked: the class definition already
+//! declares its members, templates and inline functions are defined where
+//! they are declared, and `static` functions and those in unnamed
+//! namespaces have internal linkage.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::ast::{Decl, FunctionDecl, FunctionKind, StorageClass, TranslationUnit};
+use crate::diagnostics::{Diagnostic, Diagnostics, Label};
+use crate::lint::odr::{self, Definition};
+use crate::intern::Symbol;
+use crate::lexer::token::Span;
+use crate::source::{FileId, SourceManager};
+use crate::types::TypeId;
+use crate::vfs::FileSystem;
+
+const SOURCE_EXTENSIONS: &[&str] = &["c", "cc", "cpp", "cxx"];
+
+const HEADER_EXTENSIONS: &[&str] = &["h", "hh", "hpp", "hxx"];
+
+/// A function with external linkage at namespace scope, as one declaration
+/// declares or defines it.
+#[derive(Debug, Clone)]
+struct Function {
+    /// Qualified, like `geo::area`.
+    name: String,
+    ty: TypeId,
+    defined: bool,
+    file: FileId,
+    span: Span,
+}
+
+/// What one source file's unit declares and defines.
+#[derive(Debug, Clone)]
+struct Unit {
+    file: FileId,
+    /// Every file that contributed to the unit, itself first.
+    files: Vec<FileId>,
+    functions: Vec<Function>,
+    /// The classes and inline functions it defines, for the ODR check.
+    definitions: Vec<Definition>,
+}
+
+/// The functions every linted source file declares and defines, and the
+/// header each is paired with.
+#[derive(Debug, Clone, Default)]
+pub struct ProjectIndex {
+    units: Vec<Unit>,
+    pairs: Vec<(FileId, PathBuf)>,
+}
+
+/// The header paired with `source`: the file in its directory with the
+/// same stem and a header extension. A header has none.
+pub fn paired_header(fs: &dyn FileSystem, source: &Path) -> Option<PathBuf> {
+    let extension = source.extension()?.to_str()?;
+    if !SOURCE_EXTENSIONS.contains(&extension) { return None; }
+    HEADER_EXTENSIONS.iter().map(|ext| source.with_extension(ext)).find(|path| fs.is_file(path))
+}
+
+impl ProjectIndex {
+    pub fn new() -> Self { Self::default() }
+
+    /// Adds what `unit`, parsed from `file`, declares and defines, where
+    /// `files` are those that contributed to it, `file` first.
+    pub fn add(&mut self, unit: &TranslationUnit, file: FileId, files: &[FileId], sources: &SourceManager) {
+        let mut collector = Collector { sources, classes: HashSet::new(), functions: Vec::new() };
+        collector.decls(&unit.decls, "");
+        self.units.push(Unit { file, files: files.to_vec(), functions: collector.functions, definitions: odr::definitions(unit) });
+    }
+
+    /// Pairs the source file `file`, added before the check, with `header`.
+    pub fn pair(&mut self, file: FileId, header: PathBuf) { self.pairs.push((file, header)); }
+
+    /// Reports each paired source file that does not include its header,
+    /// each function a source file defines that no header declares, each
+    /// function a paired header declares that no file defines, and each
+    /// class or inline function two units define differently.
+    pub fn check(&self, sources: &SourceManager, diagnostics: &mut Diagnostics) {
+        let mut headers = HashSet::new();
+        for (file, header) in &self.pairs {
+            let Some(unit) = self.units.iter().find(|u| u.file == *file) else { continue };
+            let Some(id) = sources.lookup_path(header).filter(|id| unit.files.contains(id)) else {
+                let start = sources.file(*file).start;
+                diagnostics.emit(
+                    Diagnostic::warning(format!("`{}` does not include its header `{}`", file_name(&sources.file(*file).path), file_name(header)))
+                        .with_label(Label::primary(Span::new(start, start), ""))
+                        .with_note("its definitions cannot be checked against the header's declarations")
+                        .with_fixit(Span::new(start, start), format!("#include \"{}\"\n", file_name(header))),
+                );
+                continue;
+            };
+            headers.insert(id);
+            self.undeclared(unit, header, sources, diagnostics);
+        }
+        self.undefined(&headers, diagnostics);
+        let units: Vec<(FileId, &[Definition])> = self.units.iter().map(|u| (u.file, u.definitions.as_slice())).collect();
+        odr::check(&units, sources, diagnostics);
+    }
+
+    /// Reports the functions `unit` defines in its own file that no other
+    /// file of it declares with the same type.
+    fn undeclared(&self, unit: &Unit, header: &Path, sources: &SourceManager, diagnostics: &mut Diagnostics) {
+        let declarations: Vec<&Function> = unit.functions.iter().filter(|f| !f.defined && f.file != unit.file).collect();
+        for f in unit.functions.iter().filter(|f| f.defined && f.file == unit.file) {
+            if declarations.iter().any(|d| d.name == f.name && d.ty == f.ty) { continue; }
+            let header = file_name(header);
+            let mut diag = Diagnostic::warning(format!("function `{}` is not declared in `{}`", f.name, header)).with_label(Label::primary(f.span, ""));
+            let overloads: Vec<&&Function> = declarations.iter().filter(|d| d.name == f.name).collect();
+            if overloads.is_empty() {
+                diag = diag.with_help(format!("declare it in `{}`, or make it `static` if only `{}` calls it", header, file_name(&sources.file(unit.file).path)));
+            } else {
+                for d in overloads { diag = diag.with_label(Label::secondary(d.span, format!("declared here as `{}`", d.ty))); }
+                diag = diag.with_note(format!("this definition, of type `{}`, is a different overload: calls through the declaration will not link", f.ty));
+            }
+            diagnostics.emit(diag);
+        }
+    }
+
+    /// Reports the functions declared in `headers` that no unit defines
+    /// with the same type, each once.
+    fn undefined(&self, headers: &HashSet<FileId>, diagnostics: &mut Diagnostics) {
+        let definitions: Vec<&Function> = self.units.iter().flat_map(|u| &u.functions).filter(|f| f.defined).collect();
+        let mut reported = HashSet::new();
+        for d in self.units.iter().flat_map(|u| &u.functions) {
+            if d.defined || !headers.contains(&d.file) || !reported.insert(d.span.start) { continue; }
+            if definitions.iter().any(|f| f.name == d.name && f.ty == d.ty) { continue; }
+            let mut diag = Diagnostic::warning(format!("function `{}` is declared but never defined", d.name))
+                .with_label(Label::primary(d.span, ""))
+                .with_note("calls to it will fail to link");
+            for f in definitions.iter().filter(|f| f.name == d.name) {
+                diag = diag.with_label(Label::secondary(f.span, format!("defined here as `{}`", f.ty)));
+            }
+            diagnostics.emit(diag);
+        }
+    }
+}
+
+/// The last component of `path`, as messages name a file.
+pub(super) fn file_name(path: &Path) -> String { path.file_name().map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned()) }
+
+struct Collector<'s> {
+    sources: &'s SourceManager,
+    /// The qualified name of each class seen so far, whose members defined
+    /// outside it are not checked.
+    classes: HashSet<String>,
+    functions: Vec<Function>,
+}
+
+impl Collector<'_> {
+    fn decls(&mut self, decls: &[Decl], scope: &str) {
+        for decl in decls { self.decl(decl, scope); }
+    }
+
+    fn decl(&mut self, decl: &Decl, scope: &str) {
+        match decl {
+            // What an unnamed namespace declares has internal linkage.
+            Decl::Namespace(n) => {
+                if let Some(name) = n.name { self.decls(&n.decls, &qualify(scope, name)); }
+            }
+            Decl::Export(e) => self.decls(&e.decls, scope),
+            Decl::Class(c) => {
+                let Some(name) = c.name else { return };
+                let qualified = qualify(scope, name);
+                for member in c.members.iter().flatten() {
+                    if let Decl::Class(_) = member.decl { self.decl(&member.decl, &qualified); }
+                }
+                self.classes.insert(qualified);
+            }
+            Decl::Function(f) => self.function(f, scope),
+            _ => {}
+        }
+    }
+
+    fn function(&mut self, f: &FunctionDecl, scope: &str) {
+        let internal = f.specifiers.storage == StorageClass::Static;
+        let inline = f.specifiers.is_inline || f.specifiers.is_constexpr || f.qualifiers.is_deleted || f.qualifiers.is_defaulted;
+        if internal || inline || f.kind != FunctionKind::Normal || f.name == Symbol::intern("main") { return; }
+        let name = qualify(scope, f.name);
+        if name.rsplit_once("::").is_some_and(|(owner, _)| self.classes.contains(owner)) { return; }
+        let Some(file) = self.sources.file_at(f.name_span.start).map(|file| file.id) else { return };
+        self.functions.push(Function { name, ty: f.ty, defined: f.body.is_some(), file, span: f.name_span });
+    }
+}
+
+pub(super) fn qualify(scope: &str, name: Symbol) -> String {
+    if scope.is_empty() { name.as_str().to_string() } else { format!("{}::{}", scope, name) }
+}
@@ -0,0 +1,270 @@
+//! Spell checking of comments and identifiers.
+//!
+//! Comments are split into words at anything but letters, digits,
+//! underscores and apostrophes, and identifiers, and the words of comments
+//! written like them, at underscores, digits and changes of case:
+//! `parseHTTPHeader` checks `parse`, `HTTP` and `Header`. Words shorter
+//! than four letters are not checked, and neither are words of comments
+//! that contain digits, belong to a URL or name a Doxygen command, nor
+//! identifiers the standard library declares.
+//!
+//! A word is known if the built-in wordlist or a project dictionary has
+//! it, with or without common prefixes and suffixes (`un-`, `-ing`,
+//! `-ed`, ...), or if it joins two known words, like `filename`. Each
+//! unknown word is reported once per file, where it is first used, with
+//! the known words one edit away from it; a word of a comment with a
+//! single suggestion gets a fix-it.
+
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::diagnostics::{Diagnostic, Diagnostics, Label};
+use crate::lexer::token::{Span, SpannedToken, Token};
+use crate::sema::includes;
+use crate::source::SourceFile;
+use crate::vfs::FileSystem;
+
+/// The file a project keeps its words in, found in the directory of the
+/// file checked or one above it.
+pub const PROJECT_DICTIONARY: &str = ".ruscom-dictionary";
+
+const WORDS: &str = include_str!("words.txt");
+
+const MIN_LEN: usize = 4;
+
+const SUFFIXES: &[&str] = &[
+    "s", "es", "ed", "ing", "er", "est", "ly", "ness", "ment", "able", "ible", "less", "ful", "al", "ity", "ize", "ise", "ion", "ation", "ive", "ism",
+    "ist", "ish",
+];
+
+const PREFIXES: &[&str] = &["un", "re", "pre", "non", "sub", "de", "dis", "mis", "over", "under", "multi", "inter", "co", "auto", "in", "im", "post"];
+
+/// Known words, lowercase.
+#[derive(Debug, Clone)]
+pub struct Dictionary {
+    words: HashSet<String>,
+}
+
+impl Default for Dictionary {
+    fn default() -> Self { Self::builtin() }
+}
+
+/// A word that is not in the dictionary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Misspelling {
+    pub span: Span,
+    /// As written.
+    pub word: String,
+    /// Known words one edit away, in the case it is written in.
+    pub suggestions: Vec<String>,
+    pub in_comment: bool,
+}
+
+impl Dictionary {
+    /// The built-in wordlist: common English words and programming terms.
+    pub fn builtin() -> Self { Self { words: WORDS.split_whitespace().map(str::to_owned).collect() } }
+
+    pub fn add(&mut self, word: &str) { self.words.insert(word.to_lowercase()); }
+
+    /// Adds the words of a dictionary file: whitespace-separated, with `#`
+    /// starting a comment that runs to the end of the line.
+    pub fn add_words(&mut self, text: &str) {
+        for line in text.lines() {
+            let line = line.split_once('#').map_or(line, |(words, _)| words);
+            for word in line.split_whitespace() { self.add(word); }
+        }
+    }
+
+    /// Whether `word`, in any case, is known.
+    pub fn knows(&self, word: &str) -> bool {
+        let word = word.to_lowercase();
+        self.stem(&word, 2) || self.compound(&word)
+    }
+
+    /// The known words one edit away from `word`, lowercase and sorted:
+    /// those in the dictionary as they are if there are any, and those with
+    /// an affix otherwise.
+    pub fn suggestions(&self, word: &str) -> Vec<String> {
+        let word: Vec<char> = word.to_lowercase().chars().collect();
+        let mut candidates = BTreeSet::new();
+        for i in 0..=word.len() {
+            if i < word.len() {
+                candidates.insert([&word[..i], &word[i + 1..]].concat());
+            }
+            if i + 1 < word.len() {
+                let mut swapped = word.clone();
+                swapped.swap(i, i + 1);
+                candidates.insert(swapped);
+            }
+            for c in 'a'..='z' {
+                if i < word.len() {
+                    let mut replaced = word.clone();
+                    replaced[i] = c;
+                    candidates.insert(replaced);
+                }
+                candidates.insert([&word[..i], &[c], &word[i..]].concat());
+            }
+        }
+        let candidates: Vec<String> = candidates.into_iter().map(|c| c.into_iter().collect::<String>()).filter(|c| c.chars().count() + 1 >= MIN_LEN).collect();
+        let plain: Vec<String> = candidates.iter().filter(|c| self.words.contains(*c)).cloned().collect();
+        if !plain.is_empty() { return plain; }
+        candidates.into_iter().filter(|c| self.stem(c, 2)).collect()
+    }
+
+    /// Whether `word` is known, taking off at most `depth` affixes.
+    fn stem(&self, word: &str, depth: usize) -> bool {
+        if self.words.contains(word) { return true; }
+        if depth == 0 { return false; }
+        for suffix in SUFFIXES {
+            let Some(base) = word.strip_suffix(suffix).filter(|b| b.len() >= 2) else { continue };
+            if bases(base, suffix).iter().any(|b| self.stem(b, depth - 1)) { return true; }
+        }
+        PREFIXES.iter().any(|prefix| word.strip_prefix(prefix).is_some_and(|rest| rest.len() >= 3 && self.stem(rest, depth - 1)))
+    }
+
+    /// Whether `word` joins two known words of three letters or more.
+    fn compound(&self, word: &str) -> bool {
+        (3..word.len().saturating_sub(2)).filter(|&i| word.is_char_boundary(i)).any(|i| self.stem(&word[..i], 1) && self.stem(&word[i..], 2))
+    }
+}
+
+/// What a word was before `suffix` was added to `base`: `base` itself,
+/// and before a vowel `use` for `us-ed` and `stop` for `stopp-ed`, and
+/// `copy` for `copi-es` and `happi-ly`.
+fn bases(base: &str, suffix: &str) -> Vec<String> {
+    let mut out = vec![base.to_owned()];
+    if suffix.starts_with(['a', 'e', 'i', 'o', 'u']) {
+        out.push(format!("{}e", base));
+        let bytes = base.as_bytes();
+        if bytes.len() >= 2 && bytes[bytes.len() - 1] == bytes[bytes.len() - 2] { out.push(base[..base.len() - 1].to_owned()); }
+    }
+    if let Some(stem) = base.strip_suffix('i').filter(|_| matches!(suffix, "es" | "ed" | "er" | "est" | "ly" | "ness" | "ful" | "less" | "ment")) {
+        out.push(format!("{}y", stem));
+    }
+    out
+}
+
+/// The dictionary file of the project `file` belongs to: the nearest
+/// `.ruscom-dictionary` in its directory or one above it.
+pub fn project_dictionary(fs: &dyn FileSystem, file: &Path) -> Option<PathBuf> {
+    file.parent()?.ancestors().map(|dir| dir.join(PROJECT_DICTIONARY)).find(|path| fs.is_file(path))
+}
+
+/// The words of an identifier, with their byte offsets: split at anything
+/// but a letter and where the case changes, keeping an acronym before a
+/// capitalized word apart from it.
+pub fn split_identifier(ident: &str) -> Vec<(usize, &str)> {
+    let chars: Vec<(usize, char)> = ident.char_indices().collect();
+    let mut words = Vec::new();
+    let mut start: Option<usize> = None;
+    for (i, &(at, c)) in chars.iter().enumerate() {
+        if !c.is_alphabetic() {
+            if let Some(s) = start.take() { words.push((s, &ident[s..at])); }
+            continue;
+        }
+        let prev = i.checked_sub(1).map(|j| chars[j].1).filter(|p| p.is_alphabetic());
+        let next = chars.get(i + 1).map(|&(_, n)| n);
+        // `aB`, and `ABc` before its `B`.
+        let boundary = c.is_uppercase()
+            && prev.is_some_and(|p| p.is_lowercase() || (p.is_uppercase() && next.is_some_and(char::is_lowercase)));
+        if boundary {
+            if let Some(s) = start.take() { words.push((s, &ident[s..at])); }
+        }
+        start.get_or_insert(at);
+    }
+    if let Some(s) = start { words.push((s, &ident[s..])); }
+    words
+}
+
+/// The words of a comment's text worth checking, with their byte offsets.
+fn comment_words(text: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut offset = 0;
+    for chunk in text.split_inclusive(char::is_whitespace) {
+        let at = offset;
+        offset += chunk.len();
+        if chunk.contains("://") || (chunk.contains('@') && !chunk.starts_with('@')) { continue; }
+        let mut rest = chunk;
+        let mut base = at;
+        while let Some(start) = rest.find(is_word_char) {
+            let end = rest[start..].find(|c: char| !is_word_char(c)).map_or(rest.len(), |e| start + e);
+            let run = &rest[start..end];
+            // `@param` and `\brief`.
+            let command = rest[..start].ends_with(['@', '\\']);
+            if !command && !run.contains(|c: char| c.is_ascii_digit()) {
+                let run = contraction(run.trim_matches('\''));
+                let lead = rest[start..].find(run).unwrap_or(0);
+                words.extend(split_identifier(run).into_iter().map(|(i, w)| (base + start + lead + i, w)));
+            }
+            base += end;
+            rest = &rest[end..];
+        }
+    }
+    words
+}
+
+fn is_word_char(c: char) -> bool { c.is_alphanumeric() || c == '_' || c == '\'' }
+
+/// The word before an apostrophe: `does` for `doesn't`, `author` for
+/// `author's`.
+fn contraction(word: &str) -> &str {
+    match word.split_once('\'') {
+        Some((before, "t")) => before.strip_suffix('n').unwrap_or(before),
+        Some((before, _)) => before,
+        None => word,
+    }
+}
+
+/// The unknown words of `file`, whose tokens are `tokens`, each at its
+/// first use.
+pub fn misspellings(file: &SourceFile, tokens: &[SpannedToken], dictionary: &Dictionary) -> Vec<Misspelling> {
+    let mut seen = HashSet::new();
+    let mut out = Vec::new();
+    for t in tokens {
+        let Some(text) = file.contents.get(t.span.start - file.start..t.span.end - file.start) else { continue };
+        let (words, in_comment) = match &t.token {
+            Token::Comment { .. } => (comment_words(text), true),
+            // Spliced identifiers, and the standard library's names, are
+            // left alone.
+            Token::Identifier(name) if *name == text && !is_library_name(text) => (split_identifier(text), false),
+            _ => continue,
+        };
+        for (at, word) in words {
+            if word.chars().count() < MIN_LEN || !seen.insert(word.to_lowercase()) || dictionary.knows(word) { continue; }
+            let suggestions = dictionary.suggestions(word).into_iter().map(|s| match_case(&s, word)).collect();
+            let start = t.span.start + at;
+            out.push(Misspelling { span: Span::new(start, start + word.len()), word: word.to_owned(), suggestions, in_comment });
+        }
+    }
+    out
+}
+
+/// Reports the unknown words of `file`.
+pub fn check(file: &SourceFile, tokens: &[SpannedToken], dictionary: &Dictionary, diagnostics: &mut Diagnostics) {
+    for m in misspellings(file, tokens, dictionary) {
+        let mut diag = Diagnostic::warning(format!("unknown word `{}`", m.word)).with_label(Label::primary(m.span, ""));
+        match m.suggestions.as_slice() {
+            [] => {}
+            [only] if m.in_comment => diag = diag.with_help(format!("did you mean `{}`?", only)).with_fixit(m.span, only.clone()),
+            suggestions => {
+                let quoted: Vec<String> = suggestions.iter().map(|s| format!("`{}`", s)).collect();
+                diag = diag.with_help(format!("did you mean {}?", quoted.join(", ")));
+            }
+        }
+        diagnostics.emit(diag);
+    }
+}
+
+/// Whether the standard library declares `name`, with or without `std::`.
+fn is_library_name(name: &str) -> bool { includes::header_for(name).is_some() || includes::header_for(&format!("std::{}", name)).is_some() }
+
+/// `suggestion` in the case of `word`: capitalized or in capitals.
+fn match_case(suggestion: &str, word: &str) -> String {
+    let upper = |c: char| c.is_uppercase();
+    if word.chars().all(upper) { return suggestion.to_uppercase(); }
+    if word.starts_with(upper) {
+        let mut chars = suggestion.chars();
+        return chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default();
+    }
+    suggestion.to_owned()
+}
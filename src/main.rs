@@ -1,6 +1,35 @@
-use clap::{Parser, Subcommand};
-use anyhow::Result;
-use ruscom::lexer::{self, Lexer};
+use clap::{Args, Parser, Subcommand};
+use anyhow::{Context, Result};
+use std::ffi::OsString;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::Arc;
+
+use ruscom::abi::Interface;
+use ruscom::api;
+use ruscom::arena::TuArena;
+use ruscom::bindgen;
+use ruscom::cheader;
+use ruscom::artifact::{self, ArtifactKind};
+use ruscom::ast;
+use ruscom::charset::{Charset, ExecCharsets};
+use ruscom::coverage::{self, Coverage, Report};
+use ruscom::daemon::Daemon;
+use ruscom::depfile::DepfileOptions;
+use ruscom::diagnostics::{Diagnostic, DiagnosticFormat, Emitter, Label, Level, WarningOption};
+use ruscom::driver::{Driver, Emit};
+use ruscom::format::{self, FormatOptions};
+use ruscom::intern::Symbol;
+use ruscom::lexer::{token::Token, LexerOptions};
+use ruscom::lint::{self, naming::NamingConfig, pairing::{self, ProjectIndex}, spelling::{self, Dictionary}, LintOptions};
+use ruscom::preprocessor::{headers, print, stats};
+use ruscom::query::{self, Matcher};
+use ruscom::sema::layout::{LayoutError, Layouts};
+use ruscom::session::{Options, Session, Standard, Target};
+use ruscom::symbols::{self, Visibility};
+use ruscom::sysroot::{self, Sysroot, SysrootSpec};
+use ruscom::types::{Type, TypeId};
+use ruscom::vfs::{OverlayFileSystem, RealFileSystem};
 
 /// RusCom — C++ compiler prototype in Rust (scaffold)
 #[derive(Parser)]
@@ -8,65 +37,705 @@ use ruscom::lexer::{self, Lexer};
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Write a Chrome trace-event profile of the compiler phases, each
+    /// included header, each function's checks and the syntax tree's
+    /// memory use to this file, with `-j` workers on their own threads
+    #[arg(long = "profile-out", global = true)]
+    profile_out: Option<String>,
+    /// Read `path` from `file` instead (repeatable), e.g. to replay unsaved IDE buffers
+    #[arg(long = "overlay", global = true, value_name = "PATH=FILE")]
+    overlay: Vec<String>,
+    /// How to print diagnostics: human, json (one object per line) or sarif
+    #[arg(long = "diagnostic-format", global = true, default_value = "human")]
+    diagnostic_format: DiagnosticFormat,
+    /// Check the spans of every parsed syntax tree, reporting parser bugs as errors
+    #[arg(long = "verify-ast", global = true)]
+    verify_ast: bool,
+    /// Stop after this many errors; 0 for no limit (-ferror-limit, -fmax-errors)
+    #[arg(long = "error-limit", global = true, value_name = "N", default_value_t = 20, aliases = ["ferror-limit", "fmax-errors"])]
+    error_limit: usize,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Compile C++ source to object / executable
     Compile {
-        /// Input source file
-        input: String,
+        /// Input source files
+        #[arg(required = true)]
+        inputs: Vec<String>,
         /// Output file
         #[arg(short, long)]
         output: Option<String>,
+        /// Use headers and libraries from this sysroot instead of the host's
+        #[arg(long = "sysroot")]
+        sysroot: Option<String>,
+        #[command(flatten)]
+        pp: PreprocessorArgs,
+        /// Number of inputs to process in parallel
+        #[arg(short = 'j', long = "jobs", default_value_t = 1)]
+        jobs: usize,
+        /// Write compiled module interfaces to DIR and load imported modules from it
+        #[arg(long = "module-cache", value_name = "DIR")]
+        module_cache: Option<String>,
+        /// Write this instead of compiling: c-header, a C header for the input's extern "C" functions and variables and the types they use
+        #[arg(long = "emit", value_name = "KIND")]
+        emit: Option<Emit>,
+    },
+    /// Preprocess, parse and check files and report their diagnostics, writing no output files (like `cc -fsyntax-only`)
+    Check {
+        #[arg(required = true)]
+        inputs: Vec<String>,
+        #[command(flatten)]
+        pp: PreprocessorArgs,
+        /// Number of inputs to process in parallel
+        #[arg(short = 'j', long = "jobs", default_value_t = 1)]
+        jobs: usize,
+    },
+    /// Print the preprocessed source of a file (like `cc -E`)
+    Preprocess {
+        input: String,
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Omit `# LINE "FILE"` line markers
+        #[arg(short = 'P', long = "no-line-markers")]
+        no_line_markers: bool,
+        #[command(flatten)]
+        pp: PreprocessorArgs,
+    },
+    /// Report statistics about files
+    Stats {
+        #[command(subcommand)]
+        report: StatsReport,
+    },
+    /// Parse a file and print its syntax tree
+    AstDump {
+        input: String,
+        /// Output format: tree, json or dot
+        #[arg(long = "format", default_value = "tree")]
+        format: ast::DumpFormat,
+        /// Print the source range of every node
+        #[arg(long = "spans")]
+        spans: bool,
+        /// Also write the tree to FILE as a binary syntax tree artifact
+        #[arg(long = "emit-artifact", value_name = "FILE")]
+        emit_artifact: Option<String>,
+        #[command(flatten)]
+        pp: PreprocessorArgs,
+    },
+    /// Print the syntax tree nodes of files that match a query, like `functionDecl(hasName("main"))`
+    AstQuery {
+        query: String,
+        #[arg(required = true)]
+        inputs: Vec<String>,
+        #[command(flatten)]
+        pp: PreprocessorArgs,
+    },
+    /// List the symbols a file defines, with their binding and visibility
+    Symbols {
+        input: String,
+        /// Only those a shared library built from the file would export
+        #[arg(long = "exported")]
+        exported: bool,
+        #[command(flatten)]
+        pp: PreprocessorArgs,
+    },
+    /// Print the layout of a class, enum or typedef a file declares: the offset and size of each member, the padding, and the size and alignment
+    Layout {
+        /// The type, like `Packet` or `net::Header`
+        name: String,
+        input: String,
+        #[command(flatten)]
+        pp: PreprocessorArgs,
+    },
+    /// Describe the functions, classes, enums, typedefs, constants and variables a header declares, with layouts, enumerator and constant values and mangled names
+    Interface {
+        input: String,
+        /// Output format: text or json
+        #[arg(long = "format", default_value = "text")]
+        format: api::Format,
+        #[command(flatten)]
+        pp: PreprocessorArgs,
+    },
+    /// Write Rust bindings for a header: `extern` declarations of its functions and variables and `#[repr(C)]` structs for its classes, warning about what is not FFI-safe and so skipped
+    Bindgen {
+        input: String,
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+        #[command(flatten)]
+        pp: PreprocessorArgs,
+    },
+    /// Compare two versions of a library, given as the headers or sources each was built from: its exported functions and variables by mangled name, its record layouts and its vtables. Exits with 1 if a change breaks programs built against the old version
+    Abidiff {
+        old: String,
+        new: String,
+        #[command(flatten)]
+        pp: PreprocessorArgs,
+    },
+    /// Parse files, skipping constructs the parser does not support yet, and report how much of them it handles
+    Analyze {
+        #[arg(required = true)]
+        inputs: Vec<String>,
+        #[command(flatten)]
+        pp: PreprocessorArgs,
+    },
+    /// Parse every source file and header under a directory and summarize which constructs are supported
+    CoverageReport {
+        dir: String,
+        #[command(flatten)]
+        pp: PreprocessorArgs,
+    },
+    /// Re-indent a file by its nesting and print it
+    Format {
+        input: String,
+        /// Only re-indent lines N to M, counted from 1
+        #[arg(long = "lines", value_name = "N:M")]
+        lines: Option<String>,
+        /// Columns per indentation level
+        #[arg(long = "indent-width", default_value_t = 4)]
+        indent_width: usize,
+        /// Indent with tabs instead of spaces
+        #[arg(long = "use-tabs")]
+        use_tabs: bool,
+    },
+    /// Spell-check the comments and identifiers of files, check their names against the `[naming]` rules of the nearest `ruscom.toml`, match the functions source files define with those their headers declare, and find classes and inline functions defined differently by different files
+    Lint {
+        #[arg(required = true)]
+        inputs: Vec<String>,
+        /// Also accept the words of this file, one or more per line (repeatable); a `.ruscom-dictionary` next to or above each input is read too
+        #[arg(long = "dictionary", value_name = "FILE")]
+        dictionary: Vec<String>,
     },
-    /// Dump AST (placeholder)
-    AstDump { input: String },
     /// Dump lexical tokens from input
     Lex {
         input: String,
         /// Print only the number of tokens instead of dumping them
         #[arg(long = "count")]
         count: bool,
+        /// Emit comments as tokens instead of skipping them
+        #[arg(long = "comments")]
+        comments: bool,
+        /// Also write the tokens to FILE as a binary token stream artifact
+        #[arg(long = "emit-artifact", value_name = "FILE")]
+        emit_artifact: Option<String>,
+    },
+    /// Decode a binary artifact (token stream, module interface or syntax tree) and print its contents
+    DumpArtifact { file: String },
+    /// Serve JSON-RPC analysis requests on stdin/stdout, one per line
+    Daemon,
+    /// Manage hermetic sysroots
+    Sysroot {
+        #[command(subcommand)]
+        action: SysrootAction,
     },
 }
 
+/// Options that control the preprocessor.
+#[derive(Args)]
+struct PreprocessorArgs {
+    /// Add a directory to the #include search path (repeatable)
+    #[arg(short = 'I', value_name = "DIR")]
+    include: Vec<String>,
+    /// Add a system include directory, searched after the -I ones (repeatable)
+    #[arg(long = "isystem", value_name = "DIR")]
+    isystem: Vec<String>,
+    /// Add a directory to the #embed search path (repeatable)
+    #[arg(long = "embed-dir", value_name = "DIR")]
+    embed_dir: Vec<String>,
+    /// Define a macro, as if by `#define NAME VALUE` (VALUE defaults to 1)
+    #[arg(short = 'D', value_name = "NAME[=VALUE]")]
+    define: Vec<String>,
+    /// Undefine a macro after the -D options
+    #[arg(short = 'U', value_name = "NAME")]
+    undefine: Vec<String>,
+    /// Write a Make dependency file listing the included headers (-MD)
+    #[arg(long = "MD")]
+    md: bool,
+    /// Like -MD, but leave out system headers (-MMD)
+    #[arg(long = "MMD")]
+    mmd: bool,
+    /// Write the dependency file here instead of next to the output (-MF)
+    #[arg(long = "MF", value_name = "FILE")]
+    mf: Option<String>,
+    /// Target CPU whose instruction set may be used, e.g. x86-64-v3 or haswell (-march)
+    #[arg(long = "march", value_name = "CPU")]
+    march: Option<String>,
+    /// Target CPU to generate code for, used for its instruction set without -march (-mcpu)
+    #[arg(long = "mcpu", value_name = "CPU")]
+    mcpu: Option<String>,
+    /// Compile for a freestanding environment, without a hosted C++ library (-ffreestanding)
+    #[arg(long = "ffreestanding")]
+    ffreestanding: bool,
+    /// Search only the -I and -isystem directories for headers (-nostdinc)
+    #[arg(long = "nostdinc")]
+    nostdinc: bool,
+    /// Encode narrow string and character literals in this character set, e.g. IBM037 (-fexec-charset)
+    #[arg(long = "fexec-charset", value_name = "CHARSET")]
+    fexec_charset: Option<Charset>,
+    /// Encode wide string and character literals in this character set (-fwide-exec-charset)
+    #[arg(long = "fwide-exec-charset", value_name = "CHARSET")]
+    fwide_exec_charset: Option<Charset>,
+    /// The C++ standard to follow: c++17 (the default) or c++20 (-std)
+    #[arg(long = "std", value_name = "STANDARD")]
+    std: Option<Standard>,
+    /// Visibility of symbols not given one by an attribute: default, protected, hidden or internal (-fvisibility)
+    #[arg(long = "fvisibility", value_name = "VISIBILITY")]
+    fvisibility: Option<Visibility>,
+    /// Turn a warning on, like -Wshadow, or off, like -Wno-shadow; -Wall and -Wextra turn on groups of them, -Werror makes warnings errors and -Werror=shadow one kind (repeatable)
+    #[arg(short = 'W', value_name = "WARNING")]
+    warning: Vec<WarningOption>,
+    /// Turn every warning off, whatever the -W options say (-w)
+    #[arg(short = 'w')]
+    no_warnings: bool,
+}
+
+impl PreprocessorArgs {
+    fn apply(self, options: &mut Options) {
+        options.include_dirs = self.include.into_iter().map(Into::into).collect();
+        options.system_include_dirs = self.isystem.into_iter().map(Into::into).collect();
+        if !self.nostdinc { options.system_include_dirs.extend(headers::include_dirs_from_env()); }
+        options.embed_dirs = self.embed_dir.into_iter().map(Into::into).collect();
+        options.defines = self.define;
+        options.undefines = self.undefine;
+        options.march = self.march;
+        options.mcpu = self.mcpu;
+        options.freestanding = self.ffreestanding;
+        options.nostdinc = self.nostdinc;
+        options.exec_charset = self.fexec_charset.unwrap_or_default();
+        options.wide_exec_charset = self.fwide_exec_charset;
+        options.visibility = self.fvisibility.unwrap_or_default();
+        options.std = self.std.unwrap_or_default();
+        for option in self.warning { options.warnings.apply(option); }
+        if self.no_warnings { options.warnings.inhibit(); }
+        if self.md || self.mmd {
+            options.depfile = Some(DepfileOptions { path: self.mf.map(Into::into), skip_system_headers: self.mmd });
+        }
+    }
+}
+
+/// Spellings of GCC's multi-letter single-dash options, which clap only
+/// parses with two dashes.
+const GCC_OPTIONS: [&str; 14] = [
+    "-MD", "-MMD", "-MF", "-isystem", "-march", "-mcpu", "-ffreestanding", "-nostdinc", "-fexec-charset", "-fwide-exec-charset", "-fvisibility",
+    "-ferror-limit", "-fmax-errors", "-std",
+];
+
+/// Rewrites `-MD` and friends to `--MD`, and `-march=x` to `--march=x`, so
+/// they parse.
+fn gcc_style_args(args: impl Iterator<Item = OsString>) -> Vec<OsString> {
+    args.map(|a| match a.to_str() {
+        Some(s) if GCC_OPTIONS.contains(&s.split('=').next().unwrap_or(s)) => format!("-{}", s).into(),
+        _ => a,
+    })
+    .collect()
+}
+
+#[derive(Subcommand)]
+enum StatsReport {
+    /// Preprocess files and report, for each, how often every macro was expanded, the tokens the expansions produced, and the deepest chains of macros expanded inside other macros
+    Macros {
+        #[arg(required = true)]
+        inputs: Vec<String>,
+        /// List this many macros and chains per file
+        #[arg(long = "top", value_name = "N", default_value_t = 10)]
+        top: usize,
+        #[command(flatten)]
+        pp: PreprocessorArgs,
+    },
+}
+
+#[derive(Subcommand)]
+enum SysrootAction {
+    /// Snapshot the host's system headers and runtime libraries into DIR
+    Create { dir: String },
+}
+
+/// Builds the driver, layering any `--overlay` files over the real disk.
+/// Fails if `-march` or `-mcpu` names a CPU the target does not have, or
+/// an execution character set has code units of the wrong size.
+fn make_driver(options: Options, overlays: &[String]) -> Result<Driver> {
+    let mut session = Session::new(options);
+    if let Some(cpu) = session.options.target_cpu() {
+        if session.target.cpu_features(cpu).is_none() { anyhow::bail!("unknown target CPU `{}` for {}", cpu, session.target.triple()); }
+    }
+    let ExecCharsets { narrow, wide, .. } = session.exec_charsets();
+    if !narrow.is_narrow() { anyhow::bail!("execution character set {} does not have single-byte code units", narrow); }
+    if wide.unit_size() != session.target.wchar_charset().unit_size() {
+        anyhow::bail!("wide execution character set {} does not match the {}-byte `wchar_t` of {}", wide, session.target.wchar_charset().unit_size(), session.target.triple());
+    }
+    if !overlays.is_empty() {
+        let fs = OverlayFileSystem::new(Arc::new(RealFileSystem));
+        fs.add_specs(overlays)?;
+        session = session.with_file_system(Arc::new(fs));
+    }
+    Ok(Driver::from_session(session))
+}
+
 fn main() -> Result<()> {
     env_logger::init();
-    let cli = Cli::parse();
+    let cli = Cli::parse_from(gcc_style_args(std::env::args_os()));
+    let mut options = Options {
+        profile_out: cli.profile_out.map(Into::into),
+        diagnostic_format: cli.diagnostic_format,
+        verify_ast: cli.verify_ast,
+        error_limit: cli.error_limit,
+        ..Options::default()
+    };
 
     match cli.command {
-        Commands::Compile { input, output } => {
-            println!("Compile: input={} output={:?}", input, output);
-        }
-        Commands::AstDump { input } => {
-            println!("AST dump: input={}", input);
-        }
-        Commands::Lex { input, count } => {
-            let src = std::fs::read_to_string(&input)?;
-            let mut lexer = Lexer::new(&src);
-            if count {
-                let mut n = 0usize;
-                while let Some(tok) = lexer.next() {
-                    match tok {
-                        Ok(t) => {
-                            if t == lexer::token::Token::Eof { break; }
-                            n += 1;
+        Commands::Compile { inputs, output, sysroot, pp, jobs, module_cache, emit } => {
+            if emit.is_some() && inputs.len() != 1 { anyhow::bail!("--emit takes one input, not {}", inputs.len()); }
+            if emit.is_none() { println!("Compile: input={} output={:?}", inputs.join(" "), output); }
+            pp.apply(&mut options);
+            if let Some(dir) = &sysroot {
+                let root = Sysroot::open(Path::new(dir))
+                    .with_context(|| format!("{} is not a sysroot (missing {})", dir, ruscom::sysroot::MANIFEST))?;
+                log::info!("using sysroot {} for {}", root.root.display(), root.triple);
+                if !options.nostdinc { options.system_include_dirs.extend(root.include_dirs); }
+            }
+            options.inputs = inputs.into_iter().map(Into::into).collect();
+            options.jobs = jobs;
+            options.output = output.clone().map(Into::into);
+            options.sysroot = sysroot.map(Into::into);
+            options.module_cache = module_cache.map(Into::into);
+            let mut driver = make_driver(options, &cli.overlay)?;
+            let arena = TuArena::new();
+            for file in driver.check(&arena)? {
+                let Some(Emit::CHeader) = emit else { continue };
+                let session = driver.session_mut();
+                let Some(id) = session.sources.lookup_path(&file.path) else { continue };
+                let header = cheader::generate(&file.unit, id, &session.sources, &session.target, &mut session.diagnostics);
+                match &output {
+                    Some(path) => std::fs::write(path, header).with_context(|| format!("cannot write {}", path))?,
+                    None => print!("{}", header),
+                }
+            }
+            driver.finish()?;
+            if driver.flush_diagnostics() { std::process::exit(1); }
+            return Ok(());
+        }
+        Commands::Check { inputs, pp, jobs } => {
+            pp.apply(&mut options);
+            // Not even the dependency files `-MD` asks for.
+            options.depfile = None;
+            options.inputs = inputs.into_iter().map(Into::into).collect();
+            options.jobs = jobs;
+            let mut driver = make_driver(options, &cli.overlay)?;
+            driver.check(&TuArena::new())?;
+            driver.finish()?;
+            if driver.flush_diagnostics() { std::process::exit(1); }
+            return Ok(());
+        }
+        Commands::Sysroot { action: SysrootAction::Create { dir } } => {
+            let spec = SysrootSpec::host(Target::host());
+            let stats = sysroot::create(&spec, Path::new(&dir))?;
+            println!("created sysroot for {} in {}: {} headers, {} libraries", spec.target.triple(), dir, stats.headers, stats.libraries);
+        }
+        Commands::Daemon => {
+            let session = make_driver(options, &cli.overlay)?.into_session();
+            let mut daemon = Daemon::new(session);
+            daemon.serve(std::io::stdin().lock(), std::io::stdout().lock())?;
+            daemon.session().finish()?;
+            return Ok(());
+        }
+        Commands::Preprocess { input, output, no_line_markers, pp } => {
+            options.inputs = vec![input.into()];
+            pp.apply(&mut options);
+            let mut driver = make_driver(options, &cli.overlay)?;
+            let files = driver.preprocess()?;
+            let text: String = files.iter().map(|f| print::print(&f.output, &driver.session().sources, !no_line_markers)).collect();
+            match &output {
+                Some(path) => std::fs::write(path, text).with_context(|| format!("cannot write {}", path))?,
+                None => print!("{}", text),
+            }
+            driver.finish()?;
+            if driver.flush_diagnostics() { std::process::exit(1); }
+            return Ok(());
+        }
+        Commands::Stats { report: StatsReport::Macros { inputs, top, pp } } => {
+            options.inputs = inputs.into_iter().map(Into::into).collect();
+            options.macro_stats = true;
+            pp.apply(&mut options);
+            let mut driver = make_driver(options, &cli.overlay)?;
+            for file in driver.preprocess()? {
+                let macros = file.output.macro_stats.unwrap_or_default();
+                print!("{}", stats::report(&file.path.display().to_string(), &macros, &driver.session().sources, top));
+            }
+            driver.finish()?;
+            if driver.flush_diagnostics() { std::process::exit(1); }
+            return Ok(());
+        }
+        Commands::AstDump { input, format, spans, emit_artifact, pp } => {
+            options.inputs = vec![input.into()];
+            pp.apply(&mut options);
+            let mut driver = make_driver(options, &cli.overlay)?;
+            let arena = TuArena::new();
+            for file in driver.parse(&arena)? {
+                print!("{}", ast::dump_as(&file.unit, &driver.session().sources, ast::DumpOptions { format, spans }));
+                if let Some(path) = &emit_artifact {
+                    std::fs::write(path, artifact::encode_ast(&file.unit)).with_context(|| format!("cannot write {}", path))?;
+                }
+            }
+            driver.finish()?;
+            if driver.flush_diagnostics() { std::process::exit(1); }
+            return Ok(());
+        }
+        Commands::AstQuery { query, inputs, pp } => {
+            let matcher = Matcher::parse(&query).with_context(|| format!("invalid query `{}`", query))?;
+            options.inputs = inputs.iter().map(Into::into).collect();
+            pp.apply(&mut options);
+            let mut driver = make_driver(options, &cli.overlay)?;
+            let arena = TuArena::new();
+            let emitter = Emitter::new(std::io::stdout().is_terminal());
+            let mut count = 0;
+            for file in driver.parse(&arena)? {
+                for found in query::find(&file.unit, &matcher) {
+                    count += 1;
+                    let note = Diagnostic::new(Level::Note, found.to_string()).with_label(Label::primary(found.span, ""));
+                    println!("Match #{}:\n\n{}", count, emitter.render(&note, &driver.session().sources));
+                }
+            }
+            println!("{} match{}.", count, if count == 1 { "" } else { "es" });
+            driver.finish()?;
+            if driver.flush_diagnostics() { std::process::exit(1); }
+            return Ok(());
+        }
+        Commands::Symbols { input, exported, pp } => {
+            options.inputs = vec![input.into()];
+            pp.apply(&mut options);
+            let mut driver = make_driver(options, &cli.overlay)?;
+            let arena = TuArena::new();
+            for file in driver.check(&arena)? {
+                for symbol in symbols::defined(&file.unit, driver.session().options.visibility) {
+                    if !exported || symbol.is_exported() { println!("{}", symbol); }
+                }
+            }
+            driver.finish()?;
+            if driver.flush_diagnostics() { std::process::exit(1); }
+            return Ok(());
+        }
+        Commands::Layout { name, input, pp } => {
+            options.inputs = vec![input.clone().into()];
+            pp.apply(&mut options);
+            let mut driver = make_driver(options, &cli.overlay)?;
+            let arena = TuArena::new();
+            for file in driver.check(&arena)? {
+                let layouts = Layouts::of_unit(&file.unit, &driver.session().target);
+                let ty = TypeId::intern(Type::Named(Symbol::intern(&name)));
+                match layouts.layout(ty) {
+                    Ok(layout) => print!("{}\n{}", name, layout),
+                    Err(LayoutError::Unknown(unknown)) if unknown == ty => anyhow::bail!("{} declares no class, enum or typedef named `{}`", input, name),
+                    Err(error) => anyhow::bail!("{}", error),
+                }
+            }
+            driver.finish()?;
+            if driver.flush_diagnostics() { std::process::exit(1); }
+            return Ok(());
+        }
+        Commands::Interface { input, format, pp } => {
+            options.inputs = vec![input.into()];
+            pp.apply(&mut options);
+            let mut driver = make_driver(options, &cli.overlay)?;
+            let arena = TuArena::new();
+            for file in driver.check(&arena)? {
+                let session = driver.session();
+                let Some(id) = session.sources.lookup_path(&file.path) else { continue };
+                let description = api::describe(&file.unit, id, &session.sources, &session.target);
+                match format {
+                    api::Format::Text => print!("{}", api::render(&description)),
+                    api::Format::Json => println!("{}", serde_json::to_string_pretty(&description)?),
+                }
+            }
+            driver.finish()?;
+            if driver.flush_diagnostics() { std::process::exit(1); }
+            return Ok(());
+        }
+        Commands::Bindgen { input, output, pp } => {
+            options.inputs = vec![input.into()];
+            pp.apply(&mut options);
+            let mut driver = make_driver(options, &cli.overlay)?;
+            let arena = TuArena::new();
+            for file in driver.check(&arena)? {
+                let session = driver.session_mut();
+                let Some(id) = session.sources.lookup_path(&file.path) else { continue };
+                let bindings = bindgen::generate(&file.unit, id, &session.sources, &session.target, &mut session.diagnostics);
+                match &output {
+                    Some(path) => std::fs::write(path, bindings).with_context(|| format!("cannot write {}", path))?,
+                    None => print!("{}", bindings),
+                }
+            }
+            driver.finish()?;
+            if driver.flush_diagnostics() { std::process::exit(1); }
+            return Ok(());
+        }
+        Commands::Abidiff { old, new, pp } => {
+            for input in [&old, &new] {
+                let magic = std::fs::read(input).map(|bytes| bytes.starts_with(b"\x7fELF")).unwrap_or(false);
+                if magic || [".so", ".a", ".o", ".dll", ".dylib"].iter().any(|ext| input.ends_with(ext) || input.contains(".so.")) {
+                    anyhow::bail!("{} is a binary, which ruscom cannot read; compare the headers or sources each version was built from", input);
+                }
+            }
+            options.inputs = vec![old.into(), new.into()];
+            pp.apply(&mut options);
+            let mut driver = make_driver(options, &cli.overlay)?;
+            let arena = TuArena::new();
+            let files = driver.parse(&arena)?;
+            let (target, visibility) = (&driver.session().target, driver.session().options.visibility);
+            let interfaces: Vec<Interface> = files.iter().map(|file| Interface::of(&file.unit, target, visibility)).collect();
+            let mut breaking = 0;
+            if let [old, new] = interfaces.as_slice() {
+                let changes = old.diff(new);
+                for change in &changes { println!("{}", change); }
+                breaking = changes.iter().filter(|c| c.breaking).count();
+                let plural = |n: usize| if n == 1 { "" } else { "s" };
+                let compatible = changes.len() - breaking;
+                println!("{} breaking change{}, {} compatible change{}", breaking, plural(breaking), compatible, plural(compatible));
+            }
+            driver.finish()?;
+            if driver.flush_diagnostics() || breaking > 0 { std::process::exit(1); }
+            return Ok(());
+        }
+        Commands::Analyze { inputs, pp } => {
+            options.inputs = inputs.iter().map(Into::into).collect();
+            options.skip_unsupported = true;
+            pp.apply(&mut options);
+            let mut driver = make_driver(options, &cli.overlay)?;
+            let arena = TuArena::new();
+            let files = driver.parse(&arena)?;
+            let mut total = Coverage::default();
+            for file in &files {
+                let coverage = Coverage::of(&file.unit);
+                for u in &coverage.unsupported {
+                    if let Some(at) = driver.session().sources.presumed_location(u.span.start) { println!("{}: unsupported {}", at, u.category); }
+                }
+                println!("{}: {}", file.path.display(), coverage);
+                total.merge(coverage);
+            }
+            if files.len() > 1 { println!("total: {}", total); }
+            for (category, count) in total.by_category() { println!("  {} {}", count, category); }
+            driver.finish()?;
+            if driver.flush_diagnostics() { std::process::exit(1); }
+            return Ok(());
+        }
+        Commands::CoverageReport { dir, pp } => {
+            let files = coverage::sources(dir.as_ref()).with_context(|| format!("cannot read {}", dir))?;
+            options.skip_unsupported = true;
+            pp.apply(&mut options);
+            let mut driver = make_driver(options, &cli.overlay)?;
+            // One file at a time, so the arena holds one tree at most.
+            let mut arena = TuArena::new();
+            let mut report = Report::default();
+            for path in files {
+                driver.session_mut().options.inputs = vec![path];
+                for file in driver.parse(&arena)? { report.add(Coverage::of(&file.unit)); }
+                arena.reset();
+            }
+            print!("{}", report);
+            driver.finish()?;
+            if driver.flush_diagnostics() { std::process::exit(1); }
+            return Ok(());
+        }
+        Commands::DumpArtifact { file } => {
+            let bytes = std::fs::read(&file).with_context(|| format!("cannot read {}", file))?;
+            let (kind, _) = artifact::Reader::open(&bytes).with_context(|| format!("cannot decode {}", file))?;
+            match kind {
+                ArtifactKind::Tokens => {
+                    let tokens = artifact::decode_tokens(&bytes).with_context(|| format!("cannot decode {}", file))?;
+                    println!("RusCom artifact v{}: {}, {} tokens in {} bytes", artifact::VERSION, kind, tokens.len(), bytes.len());
+                    for t in &tokens { println!("{}..{} {:?}", t.span.start, t.span.end, t.token); }
+                }
+                ArtifactKind::ModuleInterface => {
+                    let interface = artifact::decode_module(&bytes).with_context(|| format!("cannot decode {}", file))?;
+                    println!("RusCom artifact v{}: {} of `{}`, {} declarations in {} bytes", artifact::VERSION, kind, interface.name, interface.decls.len(), bytes.len());
+                    for module in &interface.imports { println!("export import {}", module); }
+                    for d in &interface.decls {
+                        match d.ty {
+                            Some(ty) => println!("{} {} '{}'", d.kind, d.name, ty),
+                            None => println!("{} {}", d.kind, d.name),
                         }
-                        Err(e) => { eprintln!("Lex error: {}", e); break; }
                     }
                 }
-                println!("{}", n);
-            } else {
-                while let Some(tok) = lexer.next() {
-                    match tok {
-                        Ok(t) => println!("{:?}", t),
-                        Err(e) => { eprintln!("Lex error: {}", e); break; }
+                ArtifactKind::Ast => {
+                    let arena = TuArena::new();
+                    let tu = artifact::decode_ast(&bytes, &arena).with_context(|| format!("cannot decode {}", file))?;
+                    println!("RusCom artifact v{}: {}, {} declarations in {} bytes", artifact::VERSION, kind, tu.decls.len(), bytes.len());
+                    print!("{}", ast::dump_offsets(&tu, ast::DumpOptions::default()));
+                }
+            }
+        }
+        Commands::Format { input, lines, indent_width, use_tabs } => {
+            let driver = make_driver(options, &cli.overlay)?;
+            let source = driver.session().fs.read_to_string(Path::new(&input)).with_context(|| format!("cannot read {}", input))?;
+            let range = match lines {
+                Some(spec) => {
+                    let bounds = spec.split_once(':').and_then(|(n, m)| Some((n.parse::<usize>().ok()?, m.parse::<usize>().ok()?)));
+                    match bounds {
+                        Some((n, m)) if n >= 1 && n <= m => n - 1..m,
+                        _ => anyhow::bail!("invalid line range `{}`, expected N:M with 1 <= N <= M", spec),
                     }
                 }
+                None => 0..usize::MAX,
+            };
+            let edits = format::format_lines(&source, range, FormatOptions { indent_width, use_tabs });
+            print!("{}", format::apply(&source, &edits));
+            return Ok(());
+        }
+        Commands::Lint { inputs, dictionary } => {
+            let mut driver = make_driver(options, &cli.overlay)?;
+            let fs = driver.session().fs.clone();
+            let mut base = Dictionary::builtin();
+            for path in &dictionary {
+                base.add_words(&fs.read_to_string(Path::new(path)).with_context(|| format!("cannot read {}", path))?);
+            }
+            // The project's index spans every input, so their symbols do too.
+            let arena = TuArena::new();
+            let mut project = ProjectIndex::new();
+            for input in &inputs {
+                let path = Path::new(input);
+                let mut options = LintOptions { dictionary: base.clone(), naming: NamingConfig::default() };
+                if let Some(words) = spelling::project_dictionary(fs.as_ref(), path) {
+                    options.dictionary.add_words(&fs.read_to_string(&words).with_context(|| format!("cannot read {}", words.display()))?);
+                }
+                if let Some(config) = lint::project_config(fs.as_ref(), path) {
+                    let text = fs.read_to_string(&config).with_context(|| format!("cannot read {}", config.display()))?;
+                    options.naming = NamingConfig::parse(&text).with_context(|| format!("invalid {}", config.display()))?;
+                }
+                let source = fs.read_to_string(path).with_context(|| format!("cannot read {}", input))?;
+                let session = driver.session_mut();
+                let file = session.sources.add_file(path, source);
+                let header = pairing::paired_header(fs.as_ref(), path);
+                let index = if options.naming.is_empty() && header.is_none() && inputs.len() == 1 { None } else { Some(lint::index(session, &arena, file, Some(&mut project))?) };
+                if let Some(header) = header { project.pair(file, header); }
+                lint::check(session.sources.file(file), index.as_ref(), &options, &mut session.diagnostics);
+            }
+            let session = driver.session_mut();
+            project.check(&session.sources, &mut session.diagnostics);
+            if driver.flush_diagnostics() { std::process::exit(1); }
+            return Ok(());
+        }
+        Commands::Lex { input, count, comments, emit_artifact } => {
+            options.inputs = vec![input.into()];
+            options.lexer = LexerOptions { preserve_comments: comments };
+            let mut driver = make_driver(options, &cli.overlay)?;
+            for file in driver.lex()? {
+                let out = file.output;
+                if count {
+                    println!("{}", out.tokens.len());
+                } else {
+                    for t in &out.tokens { println!("{:?}", t.token); }
+                    println!("{:?}", Token::Eof);
+                }
+                if let Some(path) = &emit_artifact {
+                    std::fs::write(path, artifact::encode_tokens(&out.tokens)).with_context(|| format!("cannot write {}", path))?;
+                }
             }
+            driver.finish()?;
+            if driver.flush_diagnostics() { std::process::exit(1); }
+            return Ok(());
         }
     }
 
+    make_driver(options, &cli.overlay)?.finish()?;
     Ok(())
 }
@@ -0,0 +1,331 @@
+//! Name mangling as the Itanium C++ ABI specifies it, which GCC and Clang
+//! use on every target but Windows: the symbol a function or variable is
+//! linked by, like `_ZNK3geo5Point4normEv` for `geo::Point::norm() const`.
+//!
+//! Names are mangled from qualified source names and types whose class,
+//! enum and typedef names the caller has resolved: a `Type::Named` must
+//! name a class or enum by its qualified name, and no typedef. Complete
+//! constructors and destructors (`C1`, `D1`) are the ones mangled, and
+//! repeated names and types are replaced by substitutions (`S_`, `S0_`)
+//! as the ABI requires. Templates, `auto` and non-type template arguments
+//! other than integers and `bool` are not mangled: those give `None`.
+
+use crate::ast::FunctionKind;
+use crate::types::{Qualifiers, TemplateArgument, Type, TypeId};
+
+/// The symbol of the function `name`, qualified like `geo::Point::norm`,
+/// of kind `kind` and type `ty`; `this` is the cv-qualification of a
+/// member function's `this`, `None` for a function that is not a member.
+/// `main` keeps its name.
+pub fn function(name: &str, kind: FunctionKind, ty: TypeId, this: Option<Qualifiers>) -> Option<String> {
+    if name == "main" { return Some(name.to_string()); }
+    let Type::Function { ret, params, variadic } = ty.get() else { return None };
+    let components: Vec<&str> = name.split("::").collect();
+    let (scope, last) = components.split_at(components.len() - 1);
+    let mut m = Mangler { out: "_Z".to_string(), subs: Vec::new() };
+    let quals = this.unwrap_or_default();
+    if scope.is_empty() {
+        m.unqualified(last[0], kind, ret, this.is_some(), params.len())?;
+    } else if scope == ["std"] && this.is_none() {
+        m.out.push_str("St");
+        m.unqualified(last[0], kind, ret, false, params.len())?;
+    } else {
+        m.out.push('N');
+        m.qualifiers(quals);
+        m.prefix(scope)?;
+        m.unqualified(last[0], kind, ret, this.is_some(), params.len())?;
+        m.out.push('E');
+    }
+    m.parameters(&params, variadic)?;
+    Some(m.out)
+}
+
+/// The symbol of the function or variable `name` declared `extern "C"`:
+/// its unqualified name, as C does not mangle names.
+pub fn extern_c(name: &str) -> String { name.rsplit("::").next().unwrap_or(name).to_string() }
+
+/// The symbol of the variable `name`, qualified like `geo::origin`: a
+/// variable of the global namespace keeps its name.
+pub fn variable(name: &str) -> Option<String> {
+    let components: Vec<&str> = name.split("::").collect();
+    if components.len() == 1 { return Some(name.to_string()); }
+    let mut m = Mangler { out: "_Z".to_string(), subs: Vec::new() };
+    let (scope, last) = components.split_at(components.len() - 1);
+    if scope == ["std"] {
+        m.out.push_str("St");
+    } else {
+        m.out.push('N');
+        m.prefix(scope)?;
+    }
+    m.source_name(last[0])?;
+    if scope != ["std"] { m.out.push('E'); }
+    Some(m.out)
+}
+
+/// The code of a builtin type, which is never substituted.
+fn builtin(ty: &Type) -> Option<&'static str> {
+    Some(match ty {
+        Type::Void => "v",
+        Type::Bool => "b",
+        Type::Char => "c",
+        Type::SignedChar => "a",
+        Type::UnsignedChar => "h",
+        Type::Short => "s",
+        Type::UnsignedShort => "t",
+        Type::Int => "i",
+        Type::UnsignedInt => "j",
+        Type::Long => "l",
+        Type::UnsignedLong => "m",
+        Type::LongLong => "x",
+        Type::UnsignedLongLong => "y",
+        Type::WChar => "w",
+        Type::Char8 => "Du",
+        Type::Char16 => "Ds",
+        Type::Char32 => "Di",
+        Type::Float => "f",
+        Type::Double => "d",
+        Type::LongDouble => "e",
+        _ => return None,
+    })
+}
+
+/// The code of the operator `op`, spelled as after `operator`, taking
+/// `arity` operands where that tells unary and binary forms apart.
+fn operator(op: &str, arity: usize) -> Option<&'static str> {
+    Some(match (op, arity) {
+        ("new", _) => "nw",
+        ("new[]", _) => "na",
+        ("delete", _) => "dl",
+        ("delete[]", _) => "da",
+        ("+", 1) => "ps",
+        ("-", 1) => "ng",
+        ("&", 1) => "ad",
+        ("*", 1) => "de",
+        ("~", _) => "co",
+        ("+", _) => "pl",
+        ("-", _) => "mi",
+        ("*", _) => "ml",
+        ("/", _) => "dv",
+        ("%", _) => "rm",
+        ("&", _) => "an",
+        ("|", _) => "or",
+        ("^", _) => "eo",
+        ("=", _) => "aS",
+        ("+=", _) => "pL",
+        ("-=", _) => "mI",
+        ("*=", _) => "mL",
+        ("/=", _) => "dV",
+        ("%=", _) => "rM",
+        ("&=", _) => "aN",
+        ("|=", _) => "oR",
+        ("^=", _) => "eO",
+        ("<<", _) => "ls",
+        (">>", _) => "rs",
+        ("<<=", _) => "lS",
+        (">>=", _) => "rS",
+        ("==", _) => "eq",
+        ("!=", _) => "ne",
+        ("<", _) => "lt",
+        (">", _) => "gt",
+        ("<=", _) => "le",
+        (">=", _) => "ge",
+        ("<=>", _) => "ss",
+        ("!", _) => "nt",
+        ("&&", _) => "aa",
+        ("||", _) => "oo",
+        ("++", _) => "pp",
+        ("--", _) => "mm",
+        (",", _) => "cm",
+        ("->*", _) => "pm",
+        ("->", _) => "pt",
+        ("()", _) => "cl",
+        ("[]", _) => "ix",
+        _ => return None,
+    })
+}
+
+/// The `seq-id` of the `i`th substitution: `S_`, then `S0_` to `S9_`,
+/// `SA_` to `SZ_`, `S10_` and so on in base 36.
+fn substitution(i: usize) -> String {
+    if i == 0 { return "S_".to_string(); }
+    let (mut n, mut digits) = (i - 1, Vec::new());
+    loop {
+        digits.push(std::char::from_digit((n % 36) as u32, 36).unwrap_or('0').to_ascii_uppercase());
+        n /= 36;
+        if n == 0 { break; }
+    }
+    format!("S{}_", digits.iter().rev().collect::<String>())
+}
+
+struct Mangler {
+    out: String,
+    /// What may be substituted, in the order the ABI numbers it: a prefix
+    /// or class by its qualified name, another type as `#` and its id.
+    subs: Vec<String>,
+}
+
+impl Mangler {
+    /// Writes the substitution for `key`, if it has one.
+    fn substitute(&mut self, key: &str) -> bool {
+        match self.subs.iter().position(|s| s == key) {
+            Some(i) => {
+                self.out.push_str(&substitution(i));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn source_name(&mut self, name: &str) -> Option<()> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') { return None; }
+        self.out.push_str(&format!("{}{}", name.len(), name));
+        Some(())
+    }
+
+    /// `[V][K]`, as a member function's `this` and a qualified type have
+    /// them.
+    fn qualifiers(&mut self, quals: Qualifiers) {
+        if quals.is_volatile { self.out.push('V'); }
+        if quals.is_const { self.out.push('K'); }
+    }
+
+    /// The namespaces and classes `scope` names, from the longest that
+    /// was already substituted; each one after it may be substituted next.
+    fn prefix(&mut self, scope: &[&str]) -> Option<()> {
+        let mut start = (1..=scope.len()).rev().find(|&k| self.substitute(&scope[..k].join("::"))).unwrap_or(0);
+        if start == 0 && scope[0] == "std" {
+            self.out.push_str("St");
+            start = 1;
+        }
+        for k in start..scope.len() {
+            self.source_name(scope[k])?;
+            self.subs.push(scope[..=k].join("::"));
+        }
+        Some(())
+    }
+
+    /// The last component of a function's name: `arity` counts the
+    /// operands of an operator, `member` telling whether `this` is one.
+    fn unqualified(&mut self, name: &str, kind: FunctionKind, ret: TypeId, member: bool, params: usize) -> Option<()> {
+        match kind {
+            FunctionKind::Constructor => self.out.push_str("C1"),
+            FunctionKind::Destructor => self.out.push_str("D1"),
+            FunctionKind::Conversion => {
+                self.out.push_str("cv");
+                self.ty(ret)?;
+            }
+            FunctionKind::Normal => match name.strip_prefix("operator").filter(|rest| !rest.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_')) {
+                Some(rest) if rest.trim_start().starts_with("\"\"") => {
+                    self.out.push_str("li");
+                    self.source_name(rest.trim_start()[2..].trim())?;
+                }
+                Some(rest) => {
+                    let op: String = rest.chars().filter(|c| !c.is_whitespace()).collect();
+                    self.out.push_str(operator(&op, params + member as usize)?);
+                }
+                None => self.source_name(name)?,
+            },
+        }
+        Some(())
+    }
+
+    /// A function's parameter types, `v` for none and `z` for `...`;
+    /// their top-level cv-qualifiers are not part of its type.
+    fn parameters(&mut self, params: &[TypeId], variadic: bool) -> Option<()> {
+        if params.is_empty() && !variadic { self.out.push('v'); }
+        for &param in params { self.ty(param.unqualified())?; }
+        if variadic { self.out.push('z'); }
+        Some(())
+    }
+
+    /// A class or enum, or a class template, by its qualified name.
+    fn type_name(&mut self, name: &str, args: Option<&[TemplateArgument]>) -> Option<()> {
+        let name = name.trim_start_matches("::");
+        let components: Vec<&str> = name.split("::").collect();
+        let nested = components.len() > 1 && !(components.len() == 2 && components[0] == "std");
+        if nested {
+            self.out.push('N');
+            self.prefix(&components)?;
+        } else if !self.substitute(name) {
+            if components.len() == 2 { self.out.push_str("St"); }
+            self.source_name(components[components.len() - 1])?;
+            self.subs.push(name.to_string());
+        }
+        if let Some(args) = args { self.template_arguments(args)?; }
+        if nested { self.out.push('E'); }
+        Some(())
+    }
+
+    fn template_arguments(&mut self, args: &[TemplateArgument]) -> Option<()> {
+        self.out.push('I');
+        for arg in args {
+            match arg {
+                TemplateArgument::Type(ty) => self.ty(*ty)?,
+                TemplateArgument::Value(text) => match text.as_str() {
+                    "true" => self.out.push_str("Lb1E"),
+                    "false" => self.out.push_str("Lb0E"),
+                    text => {
+                        let value: i128 = text.trim_end_matches(['u', 'U', 'l', 'L']).parse().ok()?;
+                        let digits = if value < 0 { format!("n{}", value.unsigned_abs()) } else { value.to_string() };
+                        self.out.push_str(&format!("Li{}E", digits));
+                    }
+                },
+            }
+        }
+        self.out.push('E');
+        Some(())
+    }
+
+    fn ty(&mut self, ty: TypeId) -> Option<()> {
+        let t = ty.get();
+        if let Some(code) = builtin(&t) {
+            self.out.push_str(code);
+            return Some(());
+        }
+        if let Type::Named(name) = t {
+            // Nested or not, a class is substituted by its qualified name,
+            // the same key its use as a prefix has.
+            let name = name.as_str().trim_start_matches("::").to_string();
+            if self.substitute(&name) { return Some(()); }
+            self.type_name(&name, None)?;
+            if !self.subs.contains(&name) { self.subs.push(name); }
+            return Some(());
+        }
+        let key = format!("#{}", ty.as_u32());
+        if self.substitute(&key) { return Some(()); }
+        match t {
+            Type::Pointer(inner) => {
+                self.out.push('P');
+                self.ty(inner)?;
+            }
+            Type::Reference(inner) => {
+                self.out.push('R');
+                self.ty(inner)?;
+            }
+            Type::RvalueReference(inner) => {
+                self.out.push('O');
+                self.ty(inner)?;
+            }
+            Type::Qualified(inner, quals) => {
+                self.qualifiers(quals);
+                self.ty(inner)?;
+            }
+            Type::Array(element, bound) => {
+                self.out.push('A');
+                if let Some(n) = bound { self.out.push_str(&n.to_string()); }
+                self.out.push('_');
+                self.ty(element)?;
+            }
+            Type::Function { ret, params, variadic } => {
+                self.out.push('F');
+                self.ty(ret)?;
+                self.parameters(&params, variadic)?;
+                self.out.push('E');
+            }
+            Type::Specialization(name, args) => self.type_name(&name.as_str(), Some(&args))?,
+            _ => return None,
+        }
+        self.subs.push(key);
+        Some(())
+    }
+}
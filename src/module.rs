@@ -0,0 +1,175 @@
+//! C++20 module interfaces: the declarations an `export module` unit
+//! exports, and where their compiled form lives in the module cache.
+//!
+//! A compiled module interface records each exported name with what kind
+//! of entity it is and, for variables, functions and aliases, its type.
+//! That is all an importer's parser needs to read the module's types and
+//! templates as such, so importing a module does not re-parse it. Modules
+//! the interface re-exports with `export import` are listed too, and are
+//! loaded with it. Header units (`import <vector>;`) have no interface.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::ast::{Decl, TranslationUnit};
+use crate::intern::Symbol;
+use crate::types::TypeId;
+
+/// The extension of compiled module interfaces in the module cache.
+pub const EXTENSION: &str = "rcm";
+
+/// What a module exports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleInterface {
+    /// `m`, or `m:part` for a partition.
+    pub name: Symbol,
+    /// The modules it re-exports, by full name.
+    pub imports: Vec<Symbol>,
+    pub decls: Vec<ExportedDecl>,
+}
+
+/// One exported declaration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportedDecl {
+    pub kind: ExportKind,
+    /// Qualified by the enclosing namespaces, like `geo::Point`.
+    pub name: Symbol,
+    /// The type of a variable, function or alias, or of a template of one.
+    pub ty: Option<TypeId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportKind {
+    Variable = 0,
+    Function = 1,
+    Class = 2,
+    Enum = 3,
+    Alias = 4,
+    FunctionTemplate = 5,
+    ClassTemplate = 6,
+    AliasTemplate = 7,
+    VariableTemplate = 8,
+    Concept = 9,
+}
+
+impl ExportKind {
+    pub fn from_byte(b: u8) -> Option<Self> {
+        Some(match b {
+            0 => ExportKind::Variable,
+            1 => ExportKind::Function,
+            2 => ExportKind::Class,
+            3 => ExportKind::Enum,
+            4 => ExportKind::Alias,
+            5 => ExportKind::FunctionTemplate,
+            6 => ExportKind::ClassTemplate,
+            7 => ExportKind::AliasTemplate,
+            8 => ExportKind::VariableTemplate,
+            9 => ExportKind::Concept,
+            _ => return None,
+        })
+    }
+
+    /// Whether the name begins a type.
+    pub fn is_type(self) -> bool {
+        matches!(self, ExportKind::Class | ExportKind::Enum | ExportKind::Alias | ExportKind::ClassTemplate | ExportKind::AliasTemplate)
+    }
+
+    /// Whether a `<` after the name opens template arguments.
+    pub fn is_template(self) -> bool {
+        matches!(self, ExportKind::FunctionTemplate | ExportKind::ClassTemplate | ExportKind::AliasTemplate | ExportKind::VariableTemplate | ExportKind::Concept)
+    }
+}
+
+impl fmt::Display for ExportKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ExportKind::Variable => "variable",
+            ExportKind::Function => "function",
+            ExportKind::Class => "class",
+            ExportKind::Enum => "enum",
+            ExportKind::Alias => "alias",
+            ExportKind::FunctionTemplate => "function template",
+            ExportKind::ClassTemplate => "class template",
+            ExportKind::AliasTemplate => "alias template",
+            ExportKind::VariableTemplate => "variable template",
+            ExportKind::Concept => "concept",
+        })
+    }
+}
+
+impl ModuleInterface {
+    /// The interface `tu` exports, if it is a module interface unit.
+    pub fn of(tu: &TranslationUnit) -> Option<Self> {
+        let name = tu.decls.iter().find_map(|d| match d {
+            Decl::Module(m) if m.is_export => m.full_name(),
+            _ => None,
+        })?;
+        let mut interface = Self { name, imports: Vec::new(), decls: Vec::new() };
+        interface.collect(&tu.decls, "", false);
+        Some(interface)
+    }
+
+    /// Adds the exported declarations among `decls`, which are in the
+    /// namespace `scope` and inside an `export` if `exported`.
+    fn collect(&mut self, decls: &[Decl], scope: &str, exported: bool) {
+        for decl in decls {
+            match decl {
+                Decl::Export(e) => self.collect(&e.decls, scope, true),
+                // Unnamed namespaces have internal linkage and export nothing.
+                Decl::Namespace(n) => {
+                    if let Some(name) = n.name { self.collect(&n.decls, &qualify(scope, name), exported); }
+                }
+                Decl::Import(i) if i.is_export && !is_header_unit(i.module) => self.imports.push(resolve(self.name, i.module)),
+                _ if exported => {
+                    if let Some((kind, ty)) = export_kind(decl) { self.decls.push(ExportedDecl { kind, name: Symbol::intern(&qualify(scope, decl.name())), ty }); }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The full name of `module` imported from a unit of `current`, which
+/// may be a partition itself: `:part` is a partition of the same module.
+pub fn resolve(current: Symbol, module: Symbol) -> Symbol {
+    match module.as_str().strip_prefix(':') {
+        Some(partition) => {
+            let current = current.as_str();
+            let primary = current.split(':').next().unwrap_or_default();
+            Symbol::intern(&format!("{}:{}", primary, partition))
+        }
+        None => module,
+    }
+}
+
+/// Whether `module` names a header unit, like `<vector>`.
+pub fn is_header_unit(module: Symbol) -> bool { module.as_str().starts_with(['<', '"']) }
+
+/// Where `module`'s compiled interface is kept in the cache `dir`. A
+/// partition's `:` becomes `-`, which file names allow everywhere.
+pub fn cache_path(dir: &Path, module: Symbol) -> PathBuf {
+    dir.join(format!("{}.{}", module.as_str().replace(':', "-"), EXTENSION))
+}
+
+fn export_kind(decl: &Decl) -> Option<(ExportKind, Option<TypeId>)> {
+    Some(match decl {
+        Decl::Var(v) => (ExportKind::Variable, Some(v.ty)),
+        Decl::Function(f) => (ExportKind::Function, Some(f.ty)),
+        Decl::Class(c) if c.name.is_some() => (ExportKind::Class, None),
+        Decl::Enum(e) if e.name.is_some() => (ExportKind::Enum, None),
+        Decl::Typedef(t) => (ExportKind::Alias, Some(t.ty)),
+        Decl::Template(t) => match &*t.decl {
+            Decl::Function(f) => (ExportKind::FunctionTemplate, Some(f.ty)),
+            Decl::Class(_) => (ExportKind::ClassTemplate, None),
+            Decl::Typedef(a) => (ExportKind::AliasTemplate, Some(a.ty)),
+            Decl::Var(v) => (ExportKind::VariableTemplate, Some(v.ty)),
+            Decl::Concept(_) => (ExportKind::Concept, None),
+            _ => return None,
+        },
+        _ => return None,
+    })
+}
+
+fn qualify(scope: &str, name: Symbol) -> String {
+    if scope.is_empty() { name.as_str().to_string() } else { format!("{}::{}", scope, name) }
+}
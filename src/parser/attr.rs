@@ -0,0 +1,55 @@
+//! Attributes: `[[nodiscard]]`, `[[gnu::cold]]` and GNU `__attribute__`.
+//!
+//! Arguments are parsed as expressions, which covers the standard
+//! attributes and the common vendor ones like `gnu::format(printf, 1, 2)`.
+
+use crate::ast::{Attribute, QualifiedName};
+use crate::intern::Symbol;
+use crate::lexer::token::Token;
+use crate::parser::{PResult, Parser};
+
+impl<'s, 'a> Parser<'s, 'a> {
+    /// Whether `[[` or `__attribute__` is next.
+    pub(super) fn attributes_follow(&mut self) -> bool {
+        self.peek_keyword("__attribute__") || (self.ts.check(&Token::Punct('[')) && self.ts.peek_nth(1).token == Token::Punct('['))
+    }
+
+    /// Any number of attribute specifiers, possibly none.
+    pub(super) fn attribute_specifiers(&mut self) -> PResult<Vec<Attribute<'a>>> {
+        let mut attrs = Vec::new();
+        while self.attributes_follow() {
+            let gnu = self.peek_keyword("__attribute__");
+            if gnu { self.ts.bump(); }
+            let (open, close) = if gnu { ('(', ')') } else { ('[', ']') };
+            self.expect_punct(open)?;
+            self.expect_punct(open)?;
+            // `[[using gnu: hot, cold]]`
+            let mut namespace = if gnu { Some("gnu".to_string()) } else { None };
+            if !gnu && self.peek_keyword("using") {
+                self.ts.bump();
+                namespace = Some(self.qualified_name()?.to_string());
+                if !self.eat_op(":") { return Err(self.expected("`:`")); }
+            }
+            // Empty entries, as in `[[a,,b]]`, are allowed.
+            loop {
+                if let Token::Identifier(_) = self.peek() { attrs.push(self.attribute(namespace.as_deref())?); }
+                if !self.ts.eat(&Token::Punct(',')) { break; }
+            }
+            self.expect_punct(close)?;
+            self.expect_punct(close)?;
+        }
+        Ok(attrs)
+    }
+
+    fn attribute(&mut self, namespace: Option<&str>) -> PResult<Attribute<'a>> {
+        let start = self.ts.peek().span.start;
+        let name = self.qualified_name()?;
+        let name = match namespace {
+            Some(ns) => Symbol::intern(&format!("{}::{}", ns, name)),
+            None => name,
+        };
+        let args = if self.ts.eat(&Token::Punct('(')) { Some(self.with_template_args(false, |p| p.arguments())?) } else { None };
+        self.eat_ellipsis();
+        Ok(Attribute { name: QualifiedName::new(name), args, span: self.span_from(start) })
+    }
+}
@@ -0,0 +1,285 @@
+//! Classes: definitions, members, constructors, destructors and
+//! conversion functions.
+
+use crate::ast::{Access, Attribute, BaseSpecifier, ClassDecl, ClassKey, Decl, Expr, ExprKind, FunctionDecl, FunctionKind, FunctionQualifiers, Member, MemberInit, Specifiers};
+use crate::intern::Symbol;
+use crate::lexer::token::Token;
+use crate::parser::decl::names_variable;
+use crate::parser::{PResult, ParseError, Parser};
+use crate::sema::constant::{self, Unevaluated};
+use crate::types::{Type, TypeId};
+
+/// Function specifiers a constructor, destructor or conversion function
+/// may have.
+const FUNCTION_SPECIFIERS: &[&str] = &["inline", "constexpr", "explicit", "virtual"];
+
+impl<'s, 'a> Parser<'s, 'a> {
+    /// `struct`, `class` or `union`, its name and, if present, `final`, its
+    /// base clause and body. Without a body this is an elaborated type name,
+    /// or a forward declaration if a `;` follows.
+    pub(super) fn class_specifier(&mut self, allow_definition: bool) -> PResult<ClassDecl<'a>> {
+        let t = self.ts.bump();
+        let key = match &t.token {
+            Token::Identifier(w) if w == "class" => ClassKey::Class,
+            Token::Identifier(w) if w == "union" => ClassKey::Union,
+            _ => ClassKey::Struct,
+        };
+        let attrs = self.attribute_specifiers()?;
+        let named = matches!(self.peek(), Token::Identifier(_)) || self.scope_follows();
+        let name = if named { Some(self.qualified_name()?) } else { None };
+        if let Some(name) = name { self.declare_type(name); }
+        // A specialization's arguments, or those of a template named.
+        let args = match name {
+            Some(name) if self.is_template(name) && self.ts.eat(&Token::Punct('<')) => Some(self.template_arguments()?),
+            _ => None,
+        };
+        // Only a definition is `final`; elsewhere it names a variable.
+        let next = self.ts.peek_nth(1).token.clone();
+        let is_final = name.is_some() && self.peek_keyword("final") && (next == Token::Punct('{') || next == Token::Operator(":"));
+        if is_final { self.ts.bump(); }
+        let defines = self.ts.check(&Token::Punct('{')) || (self.peek_is_op(":") && !self.scope_follows());
+        if !defines || !allow_definition {
+            if name.is_none() { return Err(self.expected("a class name")); }
+            return Ok(ClassDecl { key, name, args, is_final, bases: Vec::new(), members: None, attrs, span: self.span_from(t.span.start) });
+        }
+        let bases = if self.eat_op(":") { self.base_clause(key)? } else { Vec::new() };
+        self.expect_punct('{')?;
+        // Member functions of a local class may have bodies.
+        let depth = std::mem::take(&mut self.block_depth);
+        self.classes.push(name.unwrap_or_else(|| Symbol::intern("")));
+        let members = self.member_specification(key.default_access());
+        self.classes.pop();
+        self.block_depth = depth;
+        self.expect_punct('}')?;
+        Ok(ClassDecl { key, name, args, is_final, bases, members: Some(members), attrs, span: self.span_from(t.span.start) })
+    }
+
+    /// `: public A, virtual B` after a class name.
+    fn base_clause(&mut self, key: ClassKey) -> PResult<Vec<BaseSpecifier>> {
+        let mut bases = Vec::new();
+        loop {
+            let start = self.ts.peek().span.start;
+            let mut access = None;
+            let mut is_virtual = false;
+            loop {
+                match self.peek() {
+                    Token::Identifier(w) if w == "virtual" && !is_virtual => is_virtual = true,
+                    Token::Identifier(w) if access.is_none() && matches!(&*w.as_str(), "public" | "protected" | "private") => access = Some(parse_access(&w.as_str())),
+                    _ => break,
+                }
+                self.ts.bump();
+            }
+            let name = self.qualified_name()?;
+            bases.push(BaseSpecifier { name, access: access.unwrap_or(key.default_access()), is_virtual, span: self.span_from(start) });
+            if !self.ts.eat(&Token::Punct(',')) { return Ok(bases); }
+        }
+    }
+
+    /// Member declarations up to the closing `}`, which is left in place.
+    /// Errors are reported and parsing resumes at the next member.
+    fn member_specification(&mut self, mut access: Access) -> Vec<Member<'a>> {
+        let mut members = Vec::new();
+        while !self.ts.check(&Token::Punct('}')) && !self.ts.is_eof() && !self.stopped() {
+            if let Token::Identifier(w) = self.peek().clone() {
+                if matches!(&*w.as_str(), "public" | "protected" | "private") && self.ts.peek_nth(1).token == Token::Operator(":") {
+                    access = parse_access(&w.as_str());
+                    self.ts.bump();
+                    self.ts.bump();
+                    continue;
+                }
+            }
+            if self.ts.eat(&Token::Punct(';')) { continue; }
+            let (cp, start) = (self.ts.checkpoint(), self.ts.peek().span.start);
+            let mut decls = Vec::new();
+            let result = self.declaration(&mut decls);
+            if result.is_err() && self.session.options.skip_unsupported {
+                members.push(Member { access, decl: Decl::Unsupported(self.skip_unsupported(cp)) });
+                continue;
+            }
+            members.extend(decls.into_iter().map(|decl| Member { access, decl }));
+            if let Err(error) = result {
+                members.extend(self.report(error, start).map(|span| Member { access, decl: Decl::Invalid(span) }));
+            }
+        }
+        members
+    }
+
+    /// Whether a constructor, destructor or conversion function
+    /// declaration starts here: `S(`, `~S(` or `operator T(` inside class
+    /// `S`, or `S::S(`, `S::~S(` and `S::operator T(` anywhere. Consumes
+    /// nothing.
+    pub(super) fn special_member_follows(&mut self) -> bool {
+        let cp = self.ts.checkpoint();
+        while matches!(self.peek(), Token::Identifier(w) if FUNCTION_SPECIFIERS.contains(&&*w.as_str())) { self.ts.bump(); }
+        let found = self.special_member_name().is_some() && self.ts.check(&Token::Punct('('));
+        self.ts.rollback(cp);
+        found
+    }
+
+    /// The name of a constructor, destructor or conversion function, if
+    /// one is next, and its return type.
+    fn special_member_name(&mut self) -> Option<(Symbol, FunctionKind, TypeId)> {
+        let mut parts: Vec<String> = Vec::new();
+        let mut text = String::new();
+        if self.eat_scope() { text.push_str("::"); }
+        loop {
+            let tilde = self.eat_op("~");
+            let Token::Identifier(part) = self.peek().clone() else { return None };
+            self.ts.bump();
+            if part == "operator" && !tilde {
+                if (parts.is_empty() && self.classes.is_empty()) || !self.type_id_follows() { return None; }
+                let ret = self.conversion_type().ok()?;
+                text.push_str(&format!("operator {}", ret));
+                return Some((Symbol::intern(&text), FunctionKind::Conversion, ret));
+            }
+            let part = if tilde { format!("~{}", part) } else { part.to_string() };
+            text.push_str(&part);
+            parts.push(part);
+            if tilde || !self.eat_scope() { break; }
+            text.push_str("::");
+        }
+        let last = parts.last()?;
+        let class = match parts.len() {
+            1 => self.classes.last()?.as_str().to_string(),
+            n => parts[n - 2].clone(),
+        };
+        let kind = if *last == class {
+            FunctionKind::Constructor
+        } else if last.strip_prefix('~') == Some(class.as_str()) {
+            FunctionKind::Destructor
+        } else {
+            return None;
+        };
+        Some((Symbol::intern(&text), kind, TypeId::intern(Type::Void)))
+    }
+
+    /// A constructor, destructor or conversion function declaration or
+    /// definition.
+    /// `attrs` are the ones before it, which have been consumed.
+    pub(super) fn special_member(&mut self, decls: &mut Vec<Decl<'a>>, attrs: Vec<Attribute<'a>>) -> PResult<()> {
+        let start = attrs.first().map_or(self.ts.peek().span.start, |a| a.span.start);
+        let mut specifiers = Specifiers::default();
+        while let Token::Identifier(w) = self.peek().clone() {
+            let flag = match &*w.as_str() {
+                "inline" => &mut specifiers.is_inline,
+                "constexpr" => &mut specifiers.is_constexpr,
+                "explicit" => &mut specifiers.is_explicit,
+                "virtual" => &mut specifiers.is_virtual,
+                _ => break,
+            };
+            if *flag { return Err((ParseError::DuplicateSpecifier(w.to_string()), self.ts.peek().span)); }
+            *flag = true;
+            self.ts.bump();
+        }
+        let name_start = self.ts.peek().span.start;
+        let (name, kind, ret) = self.special_member_name().ok_or_else(|| self.expected("a constructor or destructor"))?;
+        let name_span = self.span_from(name_start);
+        self.expect_punct('(')?;
+        let (params, variadic) = self.parameters()?;
+        let ty = TypeId::intern(Type::Function { ret, params: params.iter().map(|p| p.ty.unqualified()).collect(), variadic });
+        let (qualifiers, requires) = self.function_qualifiers()?;
+        let initializers = if kind == FunctionKind::Constructor && self.eat_op(":") { self.member_initializers()? } else { Vec::new() };
+        let (body, is_coroutine) = if self.ts.check(&Token::Punct('{')) {
+            let (body, is_coroutine) = self.function_body()?;
+            (Some(body), is_coroutine)
+        } else {
+            self.expect_punct(';')?;
+            (None, false)
+        };
+        let span = self.span_from(start);
+        decls.push(Decl::Function(FunctionDecl { name, kind, ty, params, specifiers, qualifiers, initializers, body, attrs, requires, is_coroutine, span, name_span }));
+        Ok(())
+    }
+
+    /// `const`, `noexcept`, `override`, `= 0` and the like after a
+    /// parameter list, and a trailing `requires` clause before the `=`.
+    pub(super) fn function_qualifiers(&mut self) -> PResult<(FunctionQualifiers, Option<Expr<'a>>)> {
+        let mut q = FunctionQualifiers::default();
+        let mut seen_noexcept = false;
+        while let Token::Identifier(w) = self.peek().clone() {
+            if w == "noexcept" {
+                if seen_noexcept { return Err((ParseError::DuplicateSpecifier(w.to_string()), self.ts.peek().span)); }
+                seen_noexcept = true;
+                self.ts.bump();
+                q.is_noexcept = self.noexcept_operand()?;
+                continue;
+            }
+            let flag = match &*w.as_str() {
+                "const" => &mut q.quals.is_const,
+                "volatile" => &mut q.quals.is_volatile,
+                "override" => &mut q.is_override,
+                "final" => &mut q.is_final,
+                _ => break,
+            };
+            if *flag { return Err((ParseError::DuplicateSpecifier(w.to_string()), self.ts.peek().span)); }
+            *flag = true;
+            self.ts.bump();
+        }
+        let requires = self.requires_clause()?;
+        // `= 0`, `= default` and `= delete`; any other `=` is left for an
+        // initializer.
+        let next = self.ts.peek_nth(1).token.clone();
+        if self.peek_is_op("=") {
+            let flag = match &next {
+                Token::Number(n) if n == "0" => &mut q.is_pure,
+                Token::Identifier(w) if w == "default" => &mut q.is_defaulted,
+                Token::Identifier(w) if w == "delete" => &mut q.is_deleted,
+                _ => return Ok((q, requires)),
+            };
+            *flag = true;
+            self.ts.bump();
+            self.ts.bump();
+        }
+        Ok((q, requires))
+    }
+
+    /// Whether a function is `noexcept`, from the optional `(e)` after the
+    /// keyword. An `e` that depends on what the parser does not know, like
+    /// a template parameter, leaves the function potentially throwing.
+    fn noexcept_operand(&mut self) -> PResult<bool> {
+        if !self.ts.eat(&Token::Punct('(')) { return Ok(true); }
+        let e = self.with_template_args(false, |p| p.assignment())?;
+        self.expect_punct(')')?;
+        let invalid = |p: &Self| (ParseError::InvalidNoexcept(p.session.sources.snippet(e.span).unwrap_or_default().to_string()), e.span);
+        match constant::value(&e, &self.constants) {
+            Ok(c) => c.as_int().map(|n| n != 0).ok_or_else(|| invalid(self)),
+            Err(Unevaluated::Error(error, span)) => Err((ParseError::Constant(error), span)),
+            Err(Unevaluated::Unknown) if names_variable(&e, &self.value_params) => Err(invalid(self)),
+            Err(Unevaluated::Unknown) => Ok(false),
+        }
+    }
+
+    /// `a(1), Base(x, y)` after a constructor's `:`.
+    fn member_initializers(&mut self) -> PResult<Vec<MemberInit<'a>>> {
+        let mut inits = Vec::new();
+        loop {
+            let start = self.ts.peek().span.start;
+            let name = self.qualified_name()?;
+            let braced = self.ts.check(&Token::Punct('{'));
+            let args = if braced {
+                let ExprKind::InitList(items) = self.braced_init_list()?.kind else { unreachable!("a braced list") };
+                items
+            } else {
+                self.expect_punct('(')?;
+                self.arguments()?
+            };
+            inits.push(MemberInit { name, args, braced, span: self.span_from(start) });
+            if !self.ts.eat(&Token::Punct(',')) { return Ok(inits); }
+        }
+    }
+
+    /// Whether `::` is next.
+    fn scope_follows(&mut self) -> bool {
+        let first = self.ts.peek_nth(0).clone();
+        let second = self.ts.peek_nth(1);
+        first.token == Token::Operator(":") && second.token == Token::Operator(":") && first.span.end == second.span.start
+    }
+}
+
+fn parse_access(word: &str) -> Access {
+    match word {
+        "public" => Access::Public,
+        "protected" => Access::Protected,
+        _ => Access::Private,
+    }
+}
@@ -0,0 +1,134 @@
+//! Concepts: concept definitions, type-constraints, requires-clauses and
+//! requires-expressions.
+//!
+//! Constraints are kept as written and never checked. In a requires-clause
+//! a name followed by `<` is taken as a template-id even if the template
+//! was not declared, since a comparison there would need parentheses.
+
+use crate::ast::{BinaryOp, ConceptDecl, Decl, Expr, ExprKind, QualifiedName, Requirement};
+use crate::intern::Symbol;
+use crate::lexer::token::Token;
+use crate::parser::decl::RESERVED;
+use crate::parser::{PResult, Parser};
+
+impl<'s, 'a> Parser<'s, 'a> {
+    /// `concept C = constraint;` after its template header.
+    pub(super) fn concept_definition(&mut self) -> PResult<Decl<'a>> {
+        let start = self.ts.bump().span.start;
+        let name_span = self.ts.peek().span;
+        let Token::Identifier(name) = self.peek().clone() else { return Err(self.expected("a concept name")) };
+        self.ts.bump();
+        self.declare_concept(name);
+        if !self.eat_op("=") { return Err(self.expected("`=`")); }
+        let constraint = self.with_template_args(false, |p| p.assignment())?;
+        self.expect_punct(';')?;
+        Ok(Decl::Concept(ConceptDecl { name, constraint, span: self.span_from(start), name_span }))
+    }
+
+    /// A `requires` clause, if one is next: primary expressions joined by
+    /// `&&` and `||`.
+    pub(super) fn requires_clause(&mut self) -> PResult<Option<Expr<'a>>> {
+        if !self.peek_keyword("requires") { return Ok(None); }
+        self.ts.bump();
+        self.with_template_args(false, |p| p.constraint(BinaryOp::LogicalOr)).map(Some)
+    }
+
+    /// A disjunction of conjunctions, or with `LogicalAnd`, just one
+    /// conjunction.
+    fn constraint(&mut self, op: BinaryOp) -> PResult<Expr<'a>> {
+        let operand = |p: &mut Self| if op == BinaryOp::LogicalOr { p.constraint(BinaryOp::LogicalAnd) } else { p.constraint_primary() };
+        let mut lhs = operand(self)?;
+        while let Some((_, len)) = self.operator([op.spelling()].into_iter()) {
+            self.bump_n(len);
+            let rhs = operand(self)?;
+            lhs = self.binary_expr(op, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    fn constraint_primary(&mut self) -> PResult<Expr<'a>> {
+        let named = matches!(self.peek(), Token::Identifier(w) if !RESERVED.contains(&&*w.as_str())) || self.peek_is_op(":");
+        if named {
+            let cp = self.ts.checkpoint();
+            let is_template_id = self.qualified_name().is_ok() && self.ts.check(&Token::Punct('<'));
+            self.ts.rollback(cp);
+            if is_template_id { return self.template_id(); }
+        }
+        self.primary()
+    }
+
+    /// `requires (params) { requirements }`, at the `requires`.
+    pub(super) fn requires_expression(&mut self) -> PResult<Expr<'a>> {
+        let start = self.ts.bump().span.start;
+        let params = if self.ts.eat(&Token::Punct('(')) { self.parameters()?.0 } else { Vec::new() };
+        self.expect_punct('{')?;
+        let mut requirements = Vec::new();
+        while !self.ts.check(&Token::Punct('}')) && !self.ts.is_eof() {
+            requirements.push(self.with_template_args(false, |p| p.requirement())?);
+        }
+        self.expect_punct('}')?;
+        Ok(Expr { kind: ExprKind::Requires { params, requirements }, span: self.span_from(start) })
+    }
+
+    fn requirement(&mut self) -> PResult<Requirement<'a>> {
+        let start = self.ts.peek().span.start;
+        let requirement = if self.peek_keyword("typename") {
+            let ty = self.type_id()?;
+            Requirement::Type(ty, self.span_from(start))
+        } else if self.peek_keyword("requires") {
+            self.ts.bump();
+            Requirement::Nested(self.assignment()?)
+        } else if self.ts.eat(&Token::Punct('{')) {
+            let expr = self.expression()?;
+            self.expect_punct('}')?;
+            let is_noexcept = self.peek_keyword("noexcept");
+            if is_noexcept { self.ts.bump(); }
+            let constraint = match self.operator(["->"].into_iter()) {
+                Some((_, len)) => { self.bump_n(len); Some(self.type_constraint()?) }
+                None => None,
+            };
+            Requirement::Compound { expr, is_noexcept, constraint, span: self.span_from(start) }
+        } else {
+            Requirement::Simple(self.expression()?)
+        };
+        self.expect_punct(';')?;
+        Ok(requirement)
+    }
+
+    /// A concept's name and any arguments after the first, as in `C` or
+    /// `std::same_as<int>`.
+    pub(super) fn type_constraint(&mut self) -> PResult<Expr<'a>> {
+        let start = self.ts.peek().span.start;
+        let name = QualifiedName::new(self.qualified_name()?);
+        let kind = if self.ts.eat(&Token::Punct('<')) {
+            ExprKind::TemplateId { name, args: self.template_arguments()? }
+        } else {
+            ExprKind::Name(name)
+        };
+        Ok(Expr { kind, span: self.span_from(start) })
+    }
+
+    /// Whether a type-constraint is next, and if so whether `auto`
+    /// follows it: `Some(false)` for `C` in `template<C T>`, `Some(true)`
+    /// for a placeholder like `C<int> auto`. Consumes nothing.
+    pub(super) fn constraint_follows(&mut self) -> Option<bool> {
+        let cp = self.ts.checkpoint();
+        let found = self.qualified_name().is_ok_and(|n| self.is_concept(n))
+            && (!self.ts.eat(&Token::Punct('<')) || self.template_arguments().is_ok());
+        let placeholder = self.peek_keyword("auto");
+        self.ts.rollback(cp);
+        found.then_some(placeholder)
+    }
+
+    pub(super) fn is_concept(&self, name: Symbol) -> bool {
+        self.concepts.contains(&name) || self.concepts.contains(&Symbol::intern(name.as_str().trim_start_matches("::")))
+    }
+
+    /// Records `name` as a concept and a template, qualified as
+    /// `declare_type` does.
+    fn declare_concept(&mut self, name: Symbol) {
+        self.declare_template(name);
+        let names = self.scoped_names(name);
+        self.concepts.extend(names);
+    }
+}
@@ -0,0 +1,973 @@
+//! Declarations: specifiers, declarators and function definitions.
+//!
+//! A declarator is parsed into a list of type operations (pointer, array,
+//! function, ...) in the order they apply to the base type, so
+//! `int (*fp)(int)` is "function taking int, then pointer to that". The
+//! declared type is the base type folded through the list.
+
+use std::collections::HashSet;
+
+use crate::ast::{Attribute, Binding, BitWidth, Decl, DecompositionDecl, EnumDecl, Enumerator, Expr, ExprKind, FunctionDecl, FunctionKind, InitStyle, NamespaceDecl, ParamDecl, QualifiedName, Specifiers, StaticAssertDecl, StorageClass, TemplateArg, TypedefDecl, UsingDecl, UsingDirective, VarDecl};
+use crate::cancel::Cancelled;
+use crate::diagnostics::Diagnostics;
+use crate::intern::Symbol;
+use crate::lexer::token::{Span, Token};
+use crate::parser::{PResult, ParseError, Parser};
+use crate::sema::constant::{self, Unevaluated};
+use crate::types::{Qualifiers, TemplateArgument, Type, TypeId};
+
+/// Keywords that can start a declaration.
+pub(super) const SPECIFIER_KEYWORDS: &[&str] = &[
+    "void", "bool", "char", "wchar_t", "char8_t", "char16_t", "char32_t", "short", "int", "long", "signed", "unsigned", "float", "double", "auto",
+    "const", "volatile", "static", "extern", "inline", "constexpr", "typedef", "virtual", "explicit", "mutable",
+    "friend", "struct", "class", "union", "enum", "typename",
+];
+
+/// Keywords that begin a type-id.
+const TYPE_KEYWORDS: &[&str] = &[
+    "void", "bool", "char", "wchar_t", "char8_t", "char16_t", "char32_t", "short", "int", "long", "signed", "unsigned", "float", "double", "auto",
+    "const", "volatile", "struct", "class", "union", "enum", "typename",
+];
+
+/// Reserved words that cannot name a declaration.
+pub(super) const RESERVED: &[&str] = &[
+    "break", "case", "continue", "default", "delete", "do", "else", "for", "goto", "if", "namespace", "new",
+    "noexcept", "nullptr", "operator", "return", "sizeof", "alignof", "static_cast", "dynamic_cast", "const_cast",
+    "reinterpret_cast", "switch", "this", "true", "false", "using", "while", "public", "protected", "private",
+    "template", "concept", "requires", "co_await", "co_yield", "co_return", "export", "static_assert",
+];
+
+/// One step of a declarator, applied to the type to its left.
+#[derive(Debug, Clone)]
+enum DeclOp<'a> {
+    Pointer(Qualifiers),
+    Reference,
+    RvalueReference,
+    Array(Option<u64>),
+    Function { params: Vec<ParamDecl<'a>>, variadic: bool },
+}
+
+/// A parsed declarator before its type is known.
+#[derive(Debug)]
+struct Declarator<'a> {
+    name: Option<(Symbol, Span)>,
+    /// Declares a function parameter pack, `Ts... ts`.
+    is_pack: bool,
+    ops: Vec<DeclOp<'a>>,
+    /// Attributes after the name or the whole declarator.
+    attrs: Vec<Attribute<'a>>,
+    span: Span,
+}
+
+/// The specifiers of a declaration: the base type and everything else.
+struct DeclSpecs<'a> {
+    ty: TypeId,
+    specifiers: Specifiers,
+    is_typedef: bool,
+    /// A class or enum defined or forward-declared by the specifiers.
+    tag: Option<Decl<'a>>,
+    span: Span,
+}
+
+/// Builtin type keywords seen so far in a specifier list.
+#[derive(Default)]
+struct TypeKeywords {
+    /// The one keyword (or type name) naming the fundamental type.
+    base: Option<(String, Span)>,
+    /// Set when `base` is a class, enum or typedef name.
+    named: Option<Symbol>,
+    /// The arguments when `named` is a template, as in `vector<int>`.
+    args: Option<Vec<TemplateArgument>>,
+    signed: Option<bool>,
+    short: bool,
+    longs: u8,
+}
+
+impl TypeKeywords {
+    fn is_empty(&self) -> bool { self.base.is_none() && self.signed.is_none() && !self.short && self.longs == 0 }
+
+    /// The type these keywords name, or why they don't combine.
+    fn resolve(&self, span: Span) -> PResult<TypeId> {
+        let conflict = |previous: &str, new: &str| Err((ParseError::ConflictingSpecifiers { previous: previous.into(), new: new.into() }, span));
+        let base = self.base.as_ref().map(|(b, _)| b.as_str());
+        let sign = self.signed.map(|s| if s { "signed" } else { "unsigned" });
+        let size = if self.short { Some("short") } else if self.longs > 0 { Some("long") } else { None };
+        let unsigned = self.signed == Some(false);
+        let ty = match base {
+            Some(b) if self.named.is_some() || matches!(b, "void" | "bool" | "wchar_t" | "char8_t" | "char16_t" | "char32_t" | "float" | "auto") => {
+                if let Some(other) = sign.or(size) { return conflict(b, other); }
+                match (b, self.named) {
+                    (_, Some(name)) => match &self.args {
+                        Some(args) => Type::Specialization(name, args.clone()),
+                        None => Type::Named(name),
+                    },
+                    ("void", _) => Type::Void,
+                    ("bool", _) => Type::Bool,
+                    ("wchar_t", _) => Type::WChar,
+                    ("char8_t", _) => Type::Char8,
+                    ("char16_t", _) => Type::Char16,
+                    ("char32_t", _) => Type::Char32,
+                    ("auto", _) => Type::Auto,
+                    _ => Type::Float,
+                }
+            }
+            Some("char") => {
+                if let Some(other) = size { return conflict("char", other); }
+                match self.signed {
+                    None => Type::Char,
+                    Some(true) => Type::SignedChar,
+                    Some(false) => Type::UnsignedChar,
+                }
+            }
+            Some("double") => {
+                if let Some(other) = sign.or(if self.short { Some("short") } else { None }) { return conflict("double", other); }
+                match self.longs {
+                    0 => Type::Double,
+                    1 => Type::LongDouble,
+                    _ => return conflict("long double", "long"),
+                }
+            }
+            _ => match (self.short, self.longs) {
+                (true, 0) => if unsigned { Type::UnsignedShort } else { Type::Short },
+                (false, 0) => if unsigned { Type::UnsignedInt } else { Type::Int },
+                (false, 1) => if unsigned { Type::UnsignedLong } else { Type::Long },
+                (false, 2) => if unsigned { Type::UnsignedLongLong } else { Type::LongLong },
+                (true, _) => return conflict("short", "long"),
+                (false, _) => return conflict("long long", "long"),
+            },
+        };
+        Ok(TypeId::intern(ty))
+    }
+}
+
+impl<'s, 'a> Parser<'s, 'a> {
+    /// Parses one declaration, which may declare several names, appending
+    /// them to `decls`.
+    pub(super) fn declaration(&mut self, decls: &mut Vec<Decl<'a>>) -> PResult<()> {
+        if self.peek_keyword("using") { return self.using_declaration(decls); }
+        if self.peek_keyword("template") { return self.template_declaration(decls); }
+        if self.peek_keyword("static_assert") { return self.static_assert_declaration(decls); }
+        // Leading attributes apply to every name declared.
+        let start = self.ts.peek().span.start;
+        let attrs = self.attribute_specifiers()?;
+        if self.special_member_follows() { return self.special_member(decls, attrs); }
+        let mut specs = self.decl_specifiers(true)?;
+        if !attrs.is_empty() { specs.span = Span::new(start, specs.span.end); }
+        if let Some(tag) = specs.tag.take() {
+            match &tag {
+                // Sema reports what is wrong with the values.
+                Decl::Enum(e) => self.constants.declare_enum(e, &mut Diagnostics::new()),
+                Decl::Class(c) => self.constants.layouts.declare_class(c),
+                _ => {}
+            }
+            decls.push(tag);
+            if self.ts.eat(&Token::Punct(';')) { return Ok(()); }
+        }
+        if self.binding_follows() { return self.decomposition(specs, attrs, decls); }
+        let member = !self.classes.is_empty() && self.block_depth == 0;
+        let mut first = true;
+        loop {
+            // An unnamed bit-field has only its width.
+            let d = if member && self.peek_is_op(":") {
+                let at = Span::new(self.ts.peek().span.start, self.ts.peek().span.start);
+                Declarator { name: Some((Symbol::intern(""), at)), is_pack: false, ops: Vec::new(), attrs: Vec::new(), span: at }
+            } else {
+                self.declarator(false)?
+            };
+            let (name, name_span) = d.name.expect("named declarator");
+            let ty = self.apply(specs.ty, &d.ops, d.span)?;
+            let span = Span::new(specs.span.start.min(d.span.start), d.span.end);
+            let attrs = attrs.iter().chain(&d.attrs).cloned().collect();
+            if specs.is_typedef {
+                self.declare_type(name);
+                let t = TypedefDecl { name, ty, is_alias: false, attrs, span, name_span };
+                self.constants.layouts.declare_typedef(&t);
+                decls.push(Decl::Typedef(t));
+            } else if let Some(DeclOp::Function { params, .. }) = d.ops.last() {
+                let (qualifiers, requires) = self.function_qualifiers()?;
+                let mut f = FunctionDecl {
+                    name, kind: FunctionKind::Normal, ty, params: params.clone(), specifiers: specs.specifiers,
+                    qualifiers, initializers: Vec::new(), body: None, attrs, requires, is_coroutine: false, span, name_span,
+                };
+                // A definition ends the declaration; there is no `;` after it.
+                if first && self.ts.check(&Token::Punct('{')) {
+                    if self.block_depth > 0 { return Err((ParseError::InvalidDeclarator("a function cannot be defined inside another function"), d.span)); }
+                    let (body, is_coroutine) = self.function_body()?;
+                    f.span = Span::new(span.start, body.span.end);
+                    f.body = Some(body);
+                    f.is_coroutine = is_coroutine;
+                    // Array bounds after it may call it.
+                    self.constants.declare_function(&f);
+                    decls.push(Decl::Function(f));
+                    return Ok(());
+                }
+                f.span = self.span_from(span.start);
+                // An array bound taking its size is an error.
+                self.constants.declare_function(&f);
+                decls.push(Decl::Function(f));
+            } else {
+                let bit_width = if member && self.eat_op(":") { Some(self.bit_width(name, ty, &specs.specifiers)?) } else { None };
+                let span = bit_width.as_ref().map_or(span, |w| Span::new(span.start, w.expr.span.end));
+                let (init, init_style) = self.var_initializer()?;
+                let span = init.as_ref().map_or(span, |e| Span::new(span.start, e.span.end));
+                let var = VarDecl { name, ty, specifiers: specs.specifiers, init, init_style, bit_width, attrs, span, name_span };
+                self.constants.declare_variable(&var);
+                decls.push(Decl::Var(var));
+            }
+            if !self.ts.eat(&Token::Punct(',')) { break; }
+            first = false;
+        }
+        self.expect_punct(';')?;
+        Ok(())
+    }
+
+    /// Whether `[`, but not `[[`, follows, possibly after `&` or `&&`:
+    /// the bindings of a structured binding declaration.
+    fn binding_follows(&mut self) -> bool {
+        let cp = self.ts.checkpoint();
+        if !self.eat_and_and() { self.eat_op("&"); }
+        let follows = self.ts.check(&Token::Punct('[')) && self.ts.peek_nth(1).token != Token::Punct('[');
+        self.ts.rollback(cp);
+        follows
+    }
+
+    /// The rest of `auto [a, b] = e;` after the specifiers.
+    fn decomposition(&mut self, specs: DeclSpecs<'a>, mut attrs: Vec<Attribute<'a>>, decls: &mut Vec<Decl<'a>>) -> PResult<()> {
+        let (ty, bindings) = self.bindings(&specs)?;
+        attrs.extend(self.attribute_specifiers()?);
+        let (Some(init), init_style) = self.var_initializer()? else { return Err(self.expected("an initializer")) };
+        let span = Span::new(specs.span.start, init.span.end);
+        self.expect_punct(';')?;
+        decls.push(Decl::Decomposition(DecompositionDecl { ty, specifiers: specs.specifiers, bindings, init: Some(init), init_style, attrs, span }));
+        Ok(())
+    }
+
+    /// `[a, b]`, possibly after `&` or `&&`, and the declared type.
+    fn bindings(&mut self, specs: &DeclSpecs<'a>) -> PResult<(TypeId, Vec<Binding<'a>>)> {
+        if specs.is_typedef { return Err((ParseError::InvalidDeclarator("a structured binding declaration cannot be a typedef"), specs.span)); }
+        if specs.ty.unqualified().get() != Type::Auto {
+            return Err((ParseError::InvalidDeclarator("the type of a structured binding declaration must be `auto`"), specs.span));
+        }
+        let ty = if self.eat_and_and() {
+            TypeId::intern(Type::RvalueReference(specs.ty))
+        } else if self.eat_op("&") {
+            TypeId::intern(Type::Reference(specs.ty))
+        } else {
+            specs.ty
+        };
+        self.expect_punct('[')?;
+        let mut bindings = Vec::new();
+        loop {
+            let span = self.ts.peek().span;
+            match self.peek().clone() {
+                Token::Identifier(word) if !RESERVED.contains(&&*word.as_str()) && !SPECIFIER_KEYWORDS.contains(&&*word.as_str()) => {
+                    self.ts.bump();
+                    let attrs = self.attribute_specifiers()?;
+                    bindings.push(Binding { name: word, attrs, span });
+                }
+                _ => return Err(self.expected("a binding name")),
+            }
+            if !self.ts.eat(&Token::Punct(',')) { break; }
+        }
+        self.expect_punct(']')?;
+        Ok((ty, bindings))
+    }
+
+    /// The declaration of a range-based `for` up to and including its `:`,
+    /// or `None`, consuming nothing, if something else follows.
+    pub(super) fn range_declaration(&mut self) -> Option<Decl<'a>> {
+        let cp = self.ts.checkpoint();
+        let decl = self.range_declarator().ok().flatten();
+        if decl.is_none() { self.ts.rollback(cp); }
+        decl
+    }
+
+    fn range_declarator(&mut self) -> PResult<Option<Decl<'a>>> {
+        let start = self.ts.peek().span.start;
+        let mut attrs = self.attribute_specifiers()?;
+        let mut specs = self.decl_specifiers(true)?;
+        if specs.tag.is_some() || specs.is_typedef { return Ok(None); }
+        if !attrs.is_empty() { specs.span = Span::new(start, specs.span.end); }
+        let decl = if self.binding_follows() {
+            let (ty, bindings) = self.bindings(&specs)?;
+            attrs.extend(self.attribute_specifiers()?);
+            let span = self.span_from(specs.span.start);
+            Decl::Decomposition(DecompositionDecl { ty, specifiers: specs.specifiers, bindings, init: None, init_style: InitStyle::Copy, attrs, span })
+        } else {
+            let d = self.declarator(false)?;
+            if let Some(DeclOp::Function { .. }) = d.ops.last() { return Ok(None); }
+            let (name, name_span) = d.name.expect("named declarator");
+            let ty = self.apply(specs.ty, &d.ops, d.span)?;
+            attrs.extend(d.attrs);
+            let span = Span::new(specs.span.start, d.span.end);
+            Decl::Var(VarDecl { name, ty, specifiers: specs.specifiers, init: None, init_style: InitStyle::Copy, bit_width: None, attrs, span, name_span })
+        };
+        if !self.eat_op(":") { return Ok(None); }
+        Ok(Some(decl))
+    }
+
+    /// A variable's initializer, if any: `= e`, `(args)` or `{args}`.
+    fn var_initializer(&mut self) -> PResult<(Option<Expr<'a>>, InitStyle)> {
+        match self.peek() {
+            Token::Operator(op) if *op == "=" => {
+                self.ts.bump();
+                Ok((Some(self.initializer()?), InitStyle::Copy))
+            }
+            Token::Punct('(') => {
+                let start = self.ts.bump().span.start;
+                let args = self.with_template_args(false, |p| p.arguments())?;
+                Ok((Some(Expr { kind: ExprKind::ParenList(args), span: self.span_from(start) }), InitStyle::Direct))
+            }
+            Token::Punct('{') => Ok((Some(self.braced_init_list()?), InitStyle::List)),
+            _ => Ok((None, InitStyle::Copy)),
+        }
+    }
+
+    /// Parses declaration specifiers. Storage classes and `typedef` are
+    /// only allowed if `allow_storage` (not in parameters).
+    fn decl_specifiers(&mut self, allow_storage: bool) -> PResult<DeclSpecs<'a>> {
+        let start = self.ts.peek().span;
+        let mut kw = TypeKeywords::default();
+        let mut quals = Qualifiers::NONE;
+        // Members and locals keep C++ linkage.
+        let is_extern_c = allow_storage && self.classes.is_empty() && self.block_depth == 0 && self.linkages.last() == Some(&true);
+        let mut specifiers = Specifiers { is_extern_c, ..Specifiers::default() };
+        let mut is_typedef = false;
+        let mut tag = None;
+        loop {
+            let t = self.ts.peek().clone();
+            let Token::Identifier(word) = &t.token else { break };
+            let word = word.as_str();
+            let dup = |what: &str| Err((ParseError::DuplicateSpecifier(what.into()), t.span));
+            let set_base = |kw: &mut TypeKeywords, base: &str| match &kw.base {
+                Some((prev, _)) => Err((ParseError::ConflictingSpecifiers { previous: prev.clone(), new: base.into() }, t.span)),
+                None => { kw.base = Some((base.into(), t.span)); Ok(()) }
+            };
+            match &*word {
+                "void" | "bool" | "char" | "wchar_t" | "char8_t" | "char16_t" | "char32_t" | "int" | "float" | "double" | "auto" => set_base(&mut kw, &word)?,
+                "signed" | "unsigned" => {
+                    if kw.signed.is_some() { return Err((ParseError::ConflictingSpecifiers { previous: "signed".into(), new: word.to_string() }, t.span)); }
+                    kw.signed = Some(&*word == "signed");
+                }
+                "short" => { if kw.short { return dup("short"); } kw.short = true; }
+                "long" => kw.longs += 1,
+                "const" => { if quals.is_const { return dup("const"); } quals.is_const = true; }
+                "volatile" => { if quals.is_volatile { return dup("volatile"); } quals.is_volatile = true; }
+                "static" | "extern" if allow_storage => {
+                    if specifiers.storage != StorageClass::None { return dup(&word); }
+                    specifiers.storage = if &*word == "static" { StorageClass::Static } else { StorageClass::Extern };
+                }
+                "inline" if allow_storage => { if specifiers.is_inline { return dup("inline"); } specifiers.is_inline = true; }
+                "constexpr" if allow_storage => { if specifiers.is_constexpr { return dup("constexpr"); } specifiers.is_constexpr = true; }
+                "virtual" if allow_storage => { if specifiers.is_virtual { return dup("virtual"); } specifiers.is_virtual = true; }
+                "explicit" if allow_storage => { if specifiers.is_explicit { return dup("explicit"); } specifiers.is_explicit = true; }
+                "mutable" if allow_storage => { if specifiers.is_mutable { return dup("mutable"); } specifiers.is_mutable = true; }
+                "friend" if allow_storage => { if specifiers.is_friend { return dup("friend"); } specifiers.is_friend = true; }
+                "typedef" if allow_storage => { if is_typedef { return dup("typedef"); } is_typedef = true; }
+                "struct" | "class" | "union" => {
+                    if let Some((prev, _)) = &kw.base { return Err((ParseError::ConflictingSpecifiers { previous: prev.clone(), new: word.to_string() }, t.span)); }
+                    let c = self.class_specifier(allow_storage)?;
+                    let name = c.name.unwrap_or_else(|| Symbol::intern(&format!("(anonymous {})", c.key.keyword())));
+                    kw.base = Some((name.to_string(), t.span));
+                    kw.named = Some(name);
+                    kw.args = c.args.as_ref().map(|args| template_arguments(args));
+                    if c.members.is_some() || self.ts.check(&Token::Punct(';')) { tag = Some(Decl::Class(c)); }
+                    continue;
+                }
+                "enum" => {
+                    if let Some((prev, _)) = &kw.base { return Err((ParseError::ConflictingSpecifiers { previous: prev.clone(), new: word.to_string() }, t.span)); }
+                    let e = self.enum_specifier(allow_storage)?;
+                    let name = e.name.unwrap_or_else(|| Symbol::intern("(anonymous enum)"));
+                    kw.base = Some((name.to_string(), t.span));
+                    kw.named = Some(name);
+                    if e.enumerators.is_some() || self.ts.check(&Token::Punct(';')) { tag = Some(Decl::Enum(e)); }
+                    continue;
+                }
+                _ if kw.is_empty() && (&*word == "typename" || (!RESERVED.contains(&&*word) && !SPECIFIER_KEYWORDS.contains(&&*word))) => {
+                    // Any other name in type position is taken as a type;
+                    // `typename` only says so explicitly.
+                    if &*word == "typename" { self.ts.bump(); }
+                    let name = self.qualified_name()?;
+                    if self.is_concept(name) {
+                        // A constrained placeholder, `C auto` or `C<U> auto`;
+                        // the constraint is not kept.
+                        if self.ts.eat(&Token::Punct('<')) { self.template_arguments()?; }
+                        if !self.peek_keyword("auto") { return Err(self.expected("`auto`")); }
+                        continue;
+                    }
+                    if self.is_template(name) && self.ts.eat(&Token::Punct('<')) {
+                        let args = self.template_arguments()?;
+                        kw.args = Some(template_arguments(&args));
+                    }
+                    kw.base = Some((name.to_string(), t.span));
+                    kw.named = Some(name);
+                    continue;
+                }
+                _ => break,
+            }
+            self.ts.bump();
+        }
+        let span = Span::new(start.start, self.ts.prev_span().end.max(start.start));
+        if kw.is_empty() { return Err((ParseError::MissingTypeSpecifier, start)); }
+        let ty = kw.resolve(span)?.qualified(quals);
+        Ok(DeclSpecs { ty, specifiers, is_typedef, tag, span })
+    }
+
+    /// `enum`, `enum class` or `enum struct`, its name, an optional
+    /// underlying type and, if present, the enumerator list.
+    fn enum_specifier(&mut self, allow_definition: bool) -> PResult<EnumDecl<'a>> {
+        let start = self.ts.bump().span.start;
+        let is_scoped = matches!(self.peek(), Token::Identifier(w) if w == "class" || w == "struct");
+        if is_scoped { self.ts.bump(); }
+        let attrs = self.attribute_specifiers()?;
+        let name = if matches!(self.peek(), Token::Identifier(_)) { Some(self.qualified_name()?) } else { None };
+        if let Some(name) = name { self.declare_type(name); }
+        if name.is_none() && (is_scoped || !allow_definition) { return Err(self.expected("an enum name")); }
+        let underlying = if allow_definition && self.eat_op(":") { Some(self.simple_type()?) } else { None };
+        if !allow_definition || !self.ts.eat(&Token::Punct('{')) {
+            return Ok(EnumDecl { name, is_scoped, underlying, enumerators: None, attrs, span: self.span_from(start) });
+        }
+        let mut enumerators = Vec::new();
+        // A trailing `,` is allowed.
+        while !self.ts.check(&Token::Punct('}')) {
+            let span = self.ts.peek().span;
+            let Token::Identifier(word) = self.peek().clone() else { return Err(self.expected("an enumerator")) };
+            if RESERVED.contains(&&*word.as_str()) || SPECIFIER_KEYWORDS.contains(&&*word.as_str()) { return Err(self.expected("an enumerator")); }
+            self.ts.bump();
+            let attrs = self.attribute_specifiers()?;
+            let value = if self.eat_op("=") { Some(self.assignment()?) } else { None };
+            enumerators.push(Enumerator { name: word, value, attrs, span: self.span_from(span.start) });
+            if !self.ts.eat(&Token::Punct(',')) { break; }
+        }
+        self.expect_punct('}')?;
+        Ok(EnumDecl { name, is_scoped, underlying, enumerators: Some(enumerators), attrs, span: self.span_from(start) })
+    }
+
+    /// `name` or `a::b::name`, as one symbol. The last name may be an
+    /// operator function's, like `S::operator+` or `operator bool`.
+    pub(super) fn qualified_name(&mut self) -> PResult<Symbol> {
+        let mut text = String::new();
+        if self.eat_scope() { text.push_str("::"); }
+        loop {
+            match self.peek().clone() {
+                Token::Identifier(part) if part == "operator" => {
+                    self.ts.bump();
+                    text.push_str("operator");
+                    text.push_str(&self.operator_function_id()?);
+                    return Ok(Symbol::intern(&text));
+                }
+                Token::Identifier(part) => { self.ts.bump(); text.push_str(&part.as_str()); }
+                _ => return Err(self.expected("a name")),
+            }
+            if !self.eat_scope() { return Ok(Symbol::intern(&text)); }
+            text.push_str("::");
+        }
+    }
+
+    /// Whether a namespace definition starts here.
+    pub(super) fn namespace_follows(&mut self) -> bool {
+        self.peek_keyword("namespace") || (self.peek_keyword("inline") && self.ts.peek_nth(1).token == Token::Identifier("namespace".into()))
+    }
+
+    /// `[inline] namespace [attrs] [name] [attrs] { declarations }`, or the nested
+    /// `namespace a::inline b { ... }`. Errors inside the body are reported
+    /// there; only a malformed head or a missing `}` comes back.
+    pub(super) fn namespace(&mut self, decls: &mut Vec<Decl<'a>>) -> Result<PResult<()>, Cancelled> {
+        let start = self.ts.peek().span.start;
+        let mut is_inline = self.peek_keyword("inline");
+        if is_inline { self.ts.bump(); }
+        self.ts.bump();
+        let mut attrs = match self.attribute_specifiers() {
+            Ok(attrs) => attrs,
+            Err(e) => return Ok(Err(e)),
+        };
+        // Each namespace the head opens, outermost first.
+        let mut names = Vec::new();
+        loop {
+            match self.peek().clone() {
+                Token::Identifier(name) if !RESERVED.contains(&&*name.as_str()) => { self.ts.bump(); names.push((Some(name), is_inline)); }
+                _ if names.is_empty() => { names.push((None, is_inline)); break; }
+                _ => return Ok(Err(self.expected("a namespace name"))),
+            }
+            if !self.eat_scope() { break; }
+            is_inline = self.peek_keyword("inline");
+            if is_inline { self.ts.bump(); }
+        }
+        match self.attribute_specifiers() {
+            Ok(more) => attrs.extend(more),
+            Err(e) => return Ok(Err(e)),
+        }
+        if let Err(e) = self.expect_punct('{') { return Ok(Err(e)); }
+        self.namespaces.extend(names.iter().copied());
+        let body = self.declaration_seq(true);
+        self.namespaces.truncate(self.namespaces.len() - names.len());
+        let body = body?;
+        let end = match self.expect_punct('}') {
+            Ok(span) => span.end,
+            Err(e) => return Ok(Err(e)),
+        };
+        let span = Span::new(start, end);
+        let mut decls_inside = body;
+        for &(name, is_inline) in names.iter().rev() {
+            decls_inside = vec![Decl::Namespace(NamespaceDecl { name, is_inline, decls: decls_inside, attrs: std::mem::take(&mut attrs), span })];
+        }
+        decls.extend(decls_inside);
+        Ok(Ok(()))
+    }
+
+    /// Whether a linkage specification, like `extern "C"`, starts here.
+    pub(super) fn linkage_follows(&mut self) -> bool { self.peek_keyword("extern") && matches!(self.ts.peek_nth(1).token, Token::StringLiteral(_)) }
+
+    /// `extern "C" { declarations }` or `extern "C" declaration`, whose
+    /// functions and variables at namespace scope get C linkage, or the same
+    /// with `"C++"`. The declarations go into `decls` as if written without
+    /// it, except that a variable declared by the form without braces is
+    /// `extern`. Errors inside the braces are reported there.
+    pub(super) fn linkage_specification(&mut self, decls: &mut Vec<Decl<'a>>) -> Result<PResult<()>, Cancelled> {
+        self.ts.bump();
+        let t = self.ts.bump();
+        let is_c = match &t.token {
+            Token::StringLiteral(language) if language.text() == "C" => true,
+            Token::StringLiteral(language) if language.text() == "C++" => false,
+            Token::StringLiteral(language) => return Ok(Err((ParseError::UnknownLinkage(language.text()), t.span))),
+            _ => unreachable!("checked by linkage_follows"),
+        };
+        self.linkages.push(is_c);
+        if !self.ts.eat(&Token::Punct('{')) {
+            let len = decls.len();
+            let result = self.declaration(decls);
+            self.linkages.pop();
+            for decl in &mut decls[len..] {
+                if let Decl::Var(v) = decl {
+                    if v.specifiers.storage == StorageClass::None { v.specifiers.storage = StorageClass::Extern; }
+                }
+            }
+            return Ok(result);
+        }
+        let body = self.declaration_seq(true);
+        self.linkages.pop();
+        decls.extend(body?);
+        Ok(self.expect_punct('}').map(|_| ()))
+    }
+
+    /// `using name;` with a qualified name, or `using namespace name;`.
+    fn using_declaration(&mut self, decls: &mut Vec<Decl<'a>>) -> PResult<()> {
+        let start = self.ts.bump().span.start;
+        if self.alias_follows() { return self.alias_declaration(start, decls); }
+        let directive = self.peek_keyword("namespace");
+        if directive { self.ts.bump(); }
+        let name = QualifiedName::new(self.qualified_name()?);
+        let end = self.expect_punct(';')?.end;
+        let span = Span::new(start, end);
+        if directive {
+            self.use_namespace(name);
+            decls.push(Decl::UsingDirective(UsingDirective { namespace: name, span }));
+            return Ok(());
+        }
+        let text = name.symbol().as_str();
+        if self.typedefs.contains(&Symbol::intern(text.trim_start_matches("::"))) || self.typedefs.contains(&name.last()) {
+            self.declare_type(name.last());
+        }
+        if self.is_template(name.symbol()) || self.templates.contains(&name.last()) {
+            self.declare_template(name.last());
+        }
+        decls.push(Decl::Using(UsingDecl { name, span }));
+        Ok(())
+    }
+
+    /// The value of an array bound, a constant expression whose names are
+    /// constants and `constexpr` functions declared before it; `None` if it
+    /// needs what the parser does not know, like the size of a type, or
+    /// depends on a template parameter.
+    fn array_bound(&mut self) -> PResult<Option<u64>> {
+        let e = self.with_template_args(false, |p| p.assignment())?;
+        let invalid = |p: &Self| (ParseError::InvalidArrayBound(p.session.sources.snippet(e.span).unwrap_or_default().to_string()), e.span);
+        match constant::value(&e, &self.constants) {
+            Ok(c) => match c.as_int() {
+                Some(n) if n < 0 => Err((ParseError::NegativeArrayBound(n), e.span)),
+                Some(n) => Ok(Some(n as u64)),
+                None => Err(invalid(self)),
+            },
+            Err(Unevaluated::Error(error, span)) => Err((ParseError::Constant(error), span)),
+            Err(Unevaluated::Unknown) if names_variable(&e, &self.value_params) => Err(invalid(self)),
+            Err(Unevaluated::Unknown) => Ok(None),
+        }
+    }
+
+    /// A bit-field's width after the `:`, for the member `name` of type
+    /// `ty`.
+    fn bit_width(&mut self, name: Symbol, ty: TypeId, specifiers: &Specifiers) -> PResult<BitWidth<'a>> {
+        let start = self.ts.prev_span().start;
+        let expr = self.with_template_args(false, |p| p.conditional())?;
+        if specifiers.storage == StorageClass::Static { return Err((ParseError::InvalidDeclarator("a static data member cannot be a bit-field"), self.span_from(start))); }
+        // A name may be an enum's, or depend on a template parameter.
+        let canonical = self.constants.layouts.canonical(ty);
+        if canonical.integer_range().is_none() && !matches!(canonical.unqualified().get(), Type::Named(_) | Type::Specialization(..)) { return Err((ParseError::InvalidDeclarator("a bit-field must have an integral or enumeration type"), self.span_from(start))); }
+        let invalid = |p: &Self| (ParseError::InvalidBitWidth(p.session.sources.snippet(expr.span).unwrap_or_default().to_string()), expr.span);
+        let bits = match constant::value(&expr, &self.constants) {
+            Ok(c) => match c.as_int() {
+                Some(width) if width < 0 => return Err((ParseError::NegativeBitWidth { name, width }, expr.span)),
+                Some(0) if !name.as_str().is_empty() => return Err((ParseError::ZeroBitWidth(name), expr.span)),
+                Some(width) => Some(width as u64),
+                None => return Err(invalid(self)),
+            },
+            Err(Unevaluated::Error(error, span)) => return Err((ParseError::Constant(error), span)),
+            Err(Unevaluated::Unknown) if names_variable(&expr, &self.value_params) => return Err(invalid(self)),
+            Err(Unevaluated::Unknown) => None,
+        };
+        Ok(BitWidth { expr, bits })
+    }
+
+    /// `static_assert(cond);` or `static_assert(cond, "message");`.
+    fn static_assert_declaration(&mut self, decls: &mut Vec<Decl<'a>>) -> PResult<()> {
+        let start = self.ts.bump().span.start;
+        self.expect_punct('(')?;
+        let cond = self.with_template_args(false, |p| p.assignment())?;
+        let mut message = None;
+        if self.ts.eat(&Token::Punct(',')) {
+            if !matches!(self.peek(), Token::StringLiteral(_)) { return Err(self.expected("a string literal")); }
+            if let ExprKind::StringLiteral(text) = self.primary()?.kind { message = Some(text.text()); }
+        }
+        self.expect_punct(')')?;
+        self.expect_punct(';')?;
+        decls.push(Decl::StaticAssert(StaticAssertDecl { cond, message, span: self.span_from(start) }));
+        Ok(())
+    }
+
+    /// Whether `name =` follows `using`, possibly with attributes between.
+    fn alias_follows(&mut self) -> bool {
+        let cp = self.ts.checkpoint();
+        let follows = matches!(self.peek(), Token::Identifier(w) if !RESERVED.contains(&&*w.as_str()))
+            && { self.ts.bump(); self.attribute_specifiers().is_ok() }
+            && self.peek_is_op("=");
+        self.ts.rollback(cp);
+        follows
+    }
+
+    /// `using name = type;`, after the `using`. Declares `name` as a type,
+    /// as `typedef` would.
+    fn alias_declaration(&mut self, start: usize, decls: &mut Vec<Decl<'a>>) -> PResult<()> {
+        let t = self.ts.bump();
+        let (Token::Identifier(name), name_span) = (t.token, t.span) else { unreachable!("checked by alias_follows") };
+        let attrs = self.attribute_specifiers()?;
+        self.eat_op("=");
+        let ty = self.type_id()?;
+        let end = self.expect_punct(';')?.end;
+        self.declare_type(name);
+        let t = TypedefDecl { name, ty, is_alias: true, attrs, span: Span::new(start, end), name_span };
+        self.constants.layouts.declare_typedef(&t);
+        decls.push(Decl::Typedef(t));
+        Ok(())
+    }
+
+    /// Makes the types and templates declared in `namespace` visible
+    /// without its qualifier.
+    fn use_namespace(&mut self, namespace: QualifiedName) {
+        let prefix = format!("{}::", namespace.symbol().as_str().trim_start_matches("::"));
+        let unqualified = |names: &HashSet<Symbol>| -> Vec<Symbol> { names.iter().filter_map(|t| t.as_str().strip_prefix(&prefix).map(Symbol::intern)).collect() };
+        let (types, templates) = (unqualified(&self.typedefs), unqualified(&self.templates));
+        self.typedefs.extend(types);
+        self.templates.extend(templates);
+    }
+
+    /// Records `name` as a type, both as written and qualified by the
+    /// enclosing namespaces, with and without the inline ones.
+    pub(super) fn declare_type(&mut self, name: Symbol) {
+        let names = self.scoped_names(name);
+        self.typedefs.extend(names);
+    }
+
+    /// Records `name` as a template, qualified as `declare_type` does.
+    pub(super) fn declare_template(&mut self, name: Symbol) {
+        let names = self.scoped_names(name);
+        self.templates.extend(names);
+    }
+
+    /// `name` as written and qualified by the enclosing namespaces, with
+    /// and without the inline ones.
+    pub(super) fn scoped_names(&self, name: Symbol) -> Vec<Symbol> {
+        let mut names = vec![name];
+        for skip_inline in [false, true] {
+            let mut path: Vec<String> = self.namespaces.iter().filter(|(_, inline)| !(skip_inline && *inline)).filter_map(|(n, _)| n.map(|n| n.to_string())).collect();
+            if path.is_empty() { continue; }
+            path.push(name.to_string());
+            names.push(Symbol::intern(&path.join("::")));
+        }
+        names
+    }
+
+    /// Whether a type-id starts here: a type keyword, a cv-qualifier or a
+    /// name declared as a type. Consumes nothing.
+    pub(super) fn type_id_follows(&mut self) -> bool {
+        let Token::Identifier(word) = self.peek().clone() else { return self.peek_is_op(":") && self.typedef_name_follows() };
+        TYPE_KEYWORDS.contains(&&*word.as_str()) || self.typedef_name_follows()
+    }
+
+    /// Whether the (possibly qualified) name here was declared as a type.
+    fn typedef_name_follows(&mut self) -> bool {
+        let cp = self.ts.checkpoint();
+        let name = self.qualified_name();
+        self.ts.rollback(cp);
+        name.is_ok_and(|n| self.typedefs.contains(&n) || self.typedefs.contains(&Symbol::intern(n.as_str().trim_start_matches("::"))))
+    }
+
+    /// A type-id, as in a cast or `sizeof`: specifiers and an abstract
+    /// declarator.
+    pub(super) fn type_id(&mut self) -> PResult<TypeId> {
+        let specs = self.decl_specifiers(false)?;
+        let d = self.declarator(true)?;
+        if let Some((_, span)) = d.name { return Err((ParseError::Expected { expected: "a type".into(), found: "a declarator name".into() }, span)); }
+        self.apply(specs.ty, &d.ops, d.span)
+    }
+
+    /// The type named by simple type specifiers only, as in `unsigned(x)`.
+    pub(super) fn simple_type(&mut self) -> PResult<TypeId> { Ok(self.decl_specifiers(false)?.ty) }
+
+    /// Parses a declarator. An abstract one (no name, as in a parameter
+    /// `int*`) is accepted only if `allow_abstract`.
+    fn declarator(&mut self, allow_abstract: bool) -> PResult<Declarator<'a>> {
+        let start = self.ts.peek().span.start;
+        let prefix = self.ptr_operators();
+        // Only a named pack: `int...` is C's `int, ...`.
+        let is_pack = allow_abstract && matches!(self.ts.peek_nth(3).token, Token::Identifier(_)) && self.eat_ellipsis();
+        let mut name = None;
+        let mut inner = Vec::new();
+        let mut attrs = Vec::new();
+        match self.peek().clone() {
+            Token::Identifier(word) if word == "operator" || (!RESERVED.contains(&&*word.as_str()) && !SPECIFIER_KEYWORDS.contains(&&*word.as_str())
+                && (!self.typedef_name_follows() || self.template_member_follows()) && word != "__attribute__") => {
+                let span = self.ts.peek().span;
+                let sym = self.declarator_id()?;
+                name = Some((sym, Span::new(span.start, self.ts.prev_span().end)));
+                attrs = self.attribute_specifiers()?;
+            }
+            Token::Punct('(') if self.nested_declarator_follows() => {
+                self.ts.bump();
+                let d = self.declarator(allow_abstract)?;
+                self.expect_punct(')')?;
+                name = d.name;
+                inner = d.ops;
+                attrs = d.attrs;
+            }
+            _ if allow_abstract => {}
+            _ => return Err(self.expected("a declarator")),
+        }
+        let mut suffixes = Vec::new();
+        loop {
+            if self.ts.eat(&Token::Punct('[')) {
+                let bound = if self.ts.check(&Token::Punct(']')) { None } else { self.array_bound()? };
+                self.expect_punct(']')?;
+                suffixes.push(DeclOp::Array(bound));
+            } else if self.ts.check(&Token::Punct('(')) && (allow_abstract || self.parameters_follow()) {
+                self.ts.bump();
+                let (params, variadic) = self.parameters()?;
+                suffixes.push(DeclOp::Function { params, variadic });
+            } else {
+                break;
+            }
+        }
+        // GNU attributes may follow the whole declarator.
+        if self.peek_keyword("__attribute__") { attrs.extend(self.attribute_specifiers()?); }
+        let mut ops = prefix;
+        ops.extend(suffixes.into_iter().rev());
+        ops.extend(inner);
+        let end = self.ts.prev_span().end.max(start);
+        Ok(Declarator { name, is_pack, ops, attrs, span: Span::new(start, end) })
+    }
+
+    /// A declarator's name, which may be qualified by a class template's
+    /// name and arguments, as `S<T>::f` is; the arguments are dropped, to
+    /// name the member as the template declares it.
+    fn declarator_id(&mut self) -> PResult<Symbol> {
+        let mut text = String::new();
+        loop {
+            let part = self.qualified_name()?;
+            text.push_str(&part.as_str());
+            if !self.is_template(part) || !self.ts.check(&Token::Punct('<')) { return Ok(Symbol::intern(&text)); }
+            let cp = self.ts.checkpoint();
+            self.ts.bump();
+            self.template_arguments()?;
+            if !self.eat_scope() {
+                self.ts.rollback(cp);
+                return Ok(Symbol::intern(&text));
+            }
+            text.push_str("::");
+        }
+    }
+
+    /// Whether a member of a class template, `S<T>::f`, is named here.
+    /// Consumes nothing.
+    fn template_member_follows(&mut self) -> bool {
+        let cp = self.ts.checkpoint();
+        let found = self.declarator_id().is_ok_and(|name| name.as_str().contains("::") && !self.typedefs.contains(&name));
+        self.ts.rollback(cp);
+        found
+    }
+
+    /// `*`, `* const`, `&` and `&&` before a declarator's name.
+    fn ptr_operators(&mut self) -> Vec<DeclOp<'a>> {
+        let mut ops = Vec::new();
+        loop {
+            if self.eat_op("*") {
+                let mut quals = Qualifiers::NONE;
+                loop {
+                    match self.peek() {
+                        Token::Identifier(w) if w == "const" => quals.is_const = true,
+                        Token::Identifier(w) if w == "volatile" => quals.is_volatile = true,
+                        _ => break,
+                    }
+                    self.ts.bump();
+                }
+                ops.push(DeclOp::Pointer(quals));
+            } else if self.eat_and_and() {
+                ops.push(DeclOp::RvalueReference);
+            } else if self.eat_op("&") {
+                ops.push(DeclOp::Reference);
+            } else {
+                return ops;
+            }
+        }
+    }
+
+    /// The type a conversion function converts to, after `operator`:
+    /// specifiers and pointer operators only, so `operator int*()` stops
+    /// before its parameters.
+    pub(super) fn conversion_type(&mut self) -> PResult<TypeId> {
+        let specs = self.decl_specifiers(false)?;
+        let ops = self.ptr_operators();
+        self.apply(specs.ty, &ops, self.span_from(specs.span.start))
+    }
+
+    /// At the `(` after a declarator's name: whether it opens a parameter
+    /// list rather than a direct initializer like `int x(5)`. An empty
+    /// list declares a function, as in `int x();`.
+    fn parameters_follow(&mut self) -> bool {
+        let cp = self.ts.checkpoint();
+        self.ts.bump();
+        let found = self.ts.check(&Token::Punct(')')) || self.eat_ellipsis() || self.attributes_follow()
+            || matches!(self.peek(), Token::Identifier(w) if SPECIFIER_KEYWORDS.contains(&&*w.as_str()))
+            || self.type_id_follows() || self.constraint_follows() == Some(true);
+        self.ts.rollback(cp);
+        found
+    }
+
+    /// After a `(` in declarator position: whether it opens a nested
+    /// declarator like `(*fp)` rather than a parameter list.
+    fn nested_declarator_follows(&mut self) -> bool {
+        match &self.ts.peek_nth(1).token {
+            Token::Operator(op) => matches!(*op, "*" | "&" | "&&" | ":"),
+            Token::Punct('(') => true,
+            Token::Identifier(word) => {
+                !SPECIFIER_KEYWORDS.contains(&&*word.as_str()) && !self.typedefs.contains(word)
+            }
+            _ => false,
+        }
+    }
+
+    /// Parses a parameter list after its `(`, through the `)`.
+    pub(super) fn parameters(&mut self) -> PResult<(Vec<ParamDecl<'a>>, bool)> {
+        let mut params = Vec::new();
+        let mut variadic = false;
+        // `(void)` declares no parameters.
+        if self.peek() == &Token::Identifier("void".into()) && self.ts.peek_nth(1).token == Token::Punct(')') {
+            self.ts.bump();
+        }
+        while !self.ts.check(&Token::Punct(')')) {
+            if self.eat_ellipsis() { variadic = true; break; }
+            let start = self.ts.peek().span.start;
+            let mut attrs = self.attribute_specifiers()?;
+            let specs = self.decl_specifiers(false)?;
+            let d = self.declarator(true)?;
+            let ty = self.apply(specs.ty, &d.ops, d.span)?;
+            let ty = adjust_parameter(ty);
+            let default = if self.eat_op("=") { Some(self.initializer()?) } else { None };
+            let end = default.as_ref().map_or(self.ts.prev_span().end, |e| e.span.end);
+            attrs.extend(d.attrs);
+            params.push(ParamDecl { name: d.name.map(|(n, _)| n), ty, is_pack: d.is_pack, default, attrs, span: Span::new(start, end) });
+            if !self.ts.eat(&Token::Punct(',')) {
+                if self.eat_ellipsis() { variadic = true; }
+                break;
+            }
+        }
+        self.expect_punct(')')?;
+        Ok((params, variadic))
+    }
+
+    /// A non-type template parameter's type, name and whether it is a
+    /// pack, as in `int... Ns`. Its default is left to the caller.
+    pub(super) fn value_parameter(&mut self) -> PResult<(TypeId, Option<Symbol>, bool)> {
+        let specs = self.decl_specifiers(false)?;
+        let is_pack = self.eat_ellipsis();
+        let d = self.declarator(true)?;
+        let ty = adjust_parameter(self.apply(specs.ty, &d.ops, d.span)?);
+        Ok((ty, d.name.map(|(n, _)| n), is_pack))
+    }
+
+    /// Folds `ops` over `base`, rejecting types C++ does not allow.
+    fn apply(&mut self, base: TypeId, ops: &[DeclOp], span: Span) -> PResult<TypeId> {
+        let invalid = |reason| Err((ParseError::InvalidDeclarator(reason), span));
+        let mut ty = base;
+        for op in ops {
+            let is_ref = matches!(ty.get(), Type::Reference(_) | Type::RvalueReference(_));
+            ty = match op {
+                DeclOp::Pointer(quals) => {
+                    if is_ref { return invalid("pointer to a reference is not allowed"); }
+                    TypeId::intern(Type::Pointer(ty)).qualified(*quals)
+                }
+                DeclOp::Reference | DeclOp::RvalueReference => {
+                    if is_ref { return invalid("reference to a reference is not allowed"); }
+                    if ty.unqualified() == TypeId::intern(Type::Void) { return invalid("cannot form a reference to `void`"); }
+                    let ty = if matches!(op, DeclOp::Reference) { Type::Reference(ty) } else { Type::RvalueReference(ty) };
+                    TypeId::intern(ty)
+                }
+                DeclOp::Array(bound) => match ty.get() {
+                    Type::Function { .. } => return invalid("array of functions is not allowed"),
+                    Type::Void => return invalid("array of `void` is not allowed"),
+                    _ if is_ref => return invalid("array of references is not allowed"),
+                    _ => TypeId::intern(Type::Array(ty, *bound)),
+                },
+                DeclOp::Function { params, variadic } => match ty.get() {
+                    Type::Function { .. } => return invalid("function cannot return a function type"),
+                    Type::Array(..) => return invalid("function cannot return an array type"),
+                    _ => {
+                        let params = params.iter().map(|p| p.ty.unqualified()).collect();
+                        TypeId::intern(Type::Function { ret: ty, params, variadic: *variadic })
+                    }
+                },
+            };
+        }
+        Ok(ty)
+    }
+}
+
+/// Whether `e` names something other than a constant outside the
+/// operands of `sizeof`, calls and the like, which the parser cannot
+/// evaluate either. The non-type template parameters `params` are
+/// constants, on which `e` depends.
+pub(super) fn names_variable(e: &Expr, params: &HashSet<Symbol>) -> bool {
+    let names = |e: &Expr| names_variable(e, params);
+    match &e.kind {
+        ExprKind::Name(name) => !params.contains(&name.symbol()),
+        ExprKind::Paren(inner) | ExprKind::Unary { operand: inner, .. } | ExprKind::Cast { operand: inner, .. } => names(inner),
+        ExprKind::Binary { lhs, rhs, .. } => names(lhs) || names(rhs),
+        ExprKind::Conditional { cond, then, otherwise } => names(cond) || names(then) || names(otherwise),
+        _ => false,
+    }
+}
+
+/// Template arguments as a type holds them.
+fn template_arguments(args: &[TemplateArg]) -> Vec<TemplateArgument> {
+    args.iter().map(|a| match a {
+        TemplateArg::Type(ty, _) => TemplateArgument::Type(*ty),
+        TemplateArg::Expr(e) => TemplateArgument::Value(e.to_string()),
+    }).collect()
+}
+
+/// A parameter declared as an array or function is a pointer.
+fn adjust_parameter(ty: TypeId) -> TypeId {
+    match ty.get() {
+        Type::Array(elem, _) => TypeId::intern(Type::Pointer(elem)),
+        Type::Function { .. } => TypeId::intern(Type::Pointer(ty)),
+        _ => ty,
+    }
+}
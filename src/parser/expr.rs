@@ -0,0 +1,498 @@
+//! Expressions, by precedence climbing.
+//!
+//! The lexer only joins a few two-character operators, so `<<=`, `>=`,
+//! `->*` and the like arrive as several adjacent tokens; `operator` puts
+//! them back together, taking the longest spelling that ends on a token
+//! boundary.
+
+use crate::ast::{BinaryOp, CastKind, Expr, ExprKind, IntSuffix, QualifiedName, UnaryOp};
+use crate::intern::Symbol;
+use crate::lexer::token::{Encoding, Literal, Span, Token};
+use crate::parser::decl::RESERVED;
+use crate::parser::{PResult, ParseError, Parser};
+use crate::preprocessor::expr::parse_int;
+
+/// Binary operators and their precedence; higher binds tighter.
+/// Assignments are right-associative and handled by `assignment`.
+const BINARY: &[(&str, BinaryOp, u8)] = &[
+    (".*", BinaryOp::PtrMem, 13),
+    ("->*", BinaryOp::PtrMemArrow, 13),
+    ("*", BinaryOp::Mul, 12),
+    ("/", BinaryOp::Div, 12),
+    ("%", BinaryOp::Rem, 12),
+    ("+", BinaryOp::Add, 11),
+    ("-", BinaryOp::Sub, 11),
+    ("<<", BinaryOp::Shl, 10),
+    (">>", BinaryOp::Shr, 10),
+    ("<=>", BinaryOp::ThreeWay, 9),
+    ("<", BinaryOp::Lt, 8),
+    (">", BinaryOp::Gt, 8),
+    ("<=", BinaryOp::Le, 8),
+    (">=", BinaryOp::Ge, 8),
+    ("==", BinaryOp::Eq, 7),
+    ("!=", BinaryOp::Ne, 7),
+    ("&", BinaryOp::BitAnd, 6),
+    ("^", BinaryOp::BitXor, 5),
+    ("|", BinaryOp::BitOr, 4),
+    ("&&", BinaryOp::LogicalAnd, 3),
+    ("||", BinaryOp::LogicalOr, 2),
+    ("=", BinaryOp::Assign, 1),
+    ("*=", BinaryOp::MulAssign, 1),
+    ("/=", BinaryOp::DivAssign, 1),
+    ("%=", BinaryOp::RemAssign, 1),
+    ("+=", BinaryOp::AddAssign, 1),
+    ("-=", BinaryOp::SubAssign, 1),
+    ("<<=", BinaryOp::ShlAssign, 1),
+    (">>=", BinaryOp::ShrAssign, 1),
+    ("&=", BinaryOp::AndAssign, 1),
+    ("^=", BinaryOp::XorAssign, 1),
+    ("|=", BinaryOp::OrAssign, 1),
+];
+
+/// Precedence of the operand of `?:` and of assignments.
+const LOGICAL_OR: u8 = 2;
+
+const PREFIX: &[(&str, UnaryOp)] = &[
+    ("++", UnaryOp::PreIncrement),
+    ("--", UnaryOp::PreDecrement),
+    ("+", UnaryOp::Plus),
+    ("-", UnaryOp::Minus),
+    ("!", UnaryOp::Not),
+    ("~", UnaryOp::BitNot),
+    ("*", UnaryOp::Deref),
+    ("&", UnaryOp::AddressOf),
+];
+
+/// Everything that may follow an operand; `.*` and `->*` are here so they
+/// are not mistaken for member access.
+const POSTFIX: &[&str] = &[".", "->", ".*", "->*", "++", "--"];
+
+/// The operators an operator function may be named after, besides `()`,
+/// `[]`, `,`, `new` and `delete`.
+const OVERLOADABLE: &[&str] = &[
+    "+", "-", "*", "/", "%", "^", "&", "|", "~", "!", "=", "<", ">", "+=", "-=", "*=", "/=", "%=", "^=", "&=", "|=",
+    "<<", ">>", "<<=", ">>=", "==", "!=", "<=", ">=", "<=>", "&&", "||", "++", "--", "->*", "->",
+];
+
+/// Suffixes the standard library defines literal operators for, which
+/// are spelled without an underscore.
+const STANDARD_SUFFIXES: &[&str] = &["min", "ms", "us", "ns", "il", "if", "h", "s", "y", "d", "i"];
+
+const NAMED_CASTS: &[(&str, CastKind)] = &[
+    ("static_cast", CastKind::Static),
+    ("dynamic_cast", CastKind::Dynamic),
+    ("const_cast", CastKind::Const),
+    ("reinterpret_cast", CastKind::Reinterpret),
+];
+
+impl<'s, 'a> Parser<'s, 'a> {
+    /// An expression or a braced list, as after `=` in a declaration or as
+    /// a call argument.
+    pub(super) fn initializer(&mut self) -> PResult<Expr<'a>> {
+        if self.ts.check(&Token::Punct('{')) { self.braced_init_list() } else { self.assignment() }
+    }
+
+    /// `{a, b, .field = c}`, with an optional trailing comma.
+    pub(super) fn braced_init_list(&mut self) -> PResult<Expr<'a>> {
+        let start = self.expect_punct('{')?.start;
+        let items = self.with_template_args(false, |p| {
+            let mut items = Vec::new();
+            while !p.ts.check(&Token::Punct('}')) {
+                items.push(p.init_list_item()?);
+                p.eat_ellipsis();
+                if !p.ts.eat(&Token::Punct(',')) { break; }
+            }
+            Ok(items)
+        })?;
+        self.expect_punct('}')?;
+        Ok(Expr { kind: ExprKind::InitList(items), span: self.span_from(start) })
+    }
+
+    /// One element of a braced list, possibly designated.
+    fn init_list_item(&mut self) -> PResult<Expr<'a>> {
+        let designated = self.peek_is_op(".") && matches!(self.ts.peek_nth(1).token, Token::Identifier(_));
+        if !designated { return self.initializer(); }
+        let start = self.ts.bump().span.start;
+        let Token::Identifier(field) = self.ts.bump().token else { unreachable!("checked above") };
+        let init = if self.ts.check(&Token::Punct('{')) {
+            self.braced_init_list()?
+        } else if self.eat_op("=") {
+            self.initializer()?
+        } else {
+            return Err(self.expected("`=` or `{`"));
+        };
+        Ok(Expr { kind: ExprKind::Designated { field, init: self.arena.boxed(init) }, span: self.span_from(start) })
+    }
+
+    /// A full expression, commas included.
+    pub(super) fn expression(&mut self) -> PResult<Expr<'a>> {
+        let mut lhs = self.assignment()?;
+        while self.ts.eat(&Token::Punct(',')) {
+            let rhs = self.assignment()?;
+            lhs = self.binary_expr(BinaryOp::Comma, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    /// An assignment-expression: a conditional or an assignment, both
+    /// right-associative, or a `co_yield`.
+    pub(super) fn assignment(&mut self) -> PResult<Expr<'a>> {
+        if self.peek_keyword("co_yield") {
+            let start = self.ts.bump().span.start;
+            self.coroutine = true;
+            let operand = self.initializer()?;
+            return Ok(Expr { kind: ExprKind::CoYield(self.arena.boxed(operand)), span: self.span_from(start) });
+        }
+        let lhs = self.conditional()?;
+        if matches!(lhs.kind, ExprKind::Conditional { .. }) { return Ok(lhs); }
+        match self.binary_operator() {
+            Some((op, 1, len)) => {
+                self.bump_n(len);
+                let rhs = self.initializer()?;
+                Ok(self.binary_expr(op, lhs, rhs))
+            }
+            _ => Ok(lhs),
+        }
+    }
+
+    /// A conditional-expression, which an assignment does not continue,
+    /// as a bit-field's width is.
+    pub(super) fn conditional(&mut self) -> PResult<Expr<'a>> {
+        let lhs = self.binary(LOGICAL_OR)?;
+        if !self.eat_op("?") { return Ok(lhs); }
+        let then = self.expression()?;
+        if !self.eat_op(":") { return Err(self.expected("`:`")); }
+        let otherwise = self.assignment()?;
+        let span = Span::new(lhs.span.start, otherwise.span.end);
+        Ok(Expr { kind: ExprKind::Conditional { cond: self.arena.boxed(lhs), then: self.arena.boxed(then), otherwise: self.arena.boxed(otherwise) }, span })
+    }
+
+    /// Left-associative binary operators of precedence `min` and above.
+    fn binary(&mut self, min: u8) -> PResult<Expr<'a>> {
+        let mut lhs = self.unary()?;
+        while let Some((op, prec, len)) = self.binary_operator() {
+            if prec < min || op.is_assignment() { break; }
+            self.bump_n(len);
+            let rhs = self.binary(prec + 1)?;
+            lhs = self.binary_expr(op, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> PResult<Expr<'a>> {
+        let start = self.ts.peek().span.start;
+        if let Some((op, len)) = self.operator(PREFIX.iter().map(|(s, _)| *s)) {
+            let op = PREFIX.iter().find(|(s, _)| *s == op).map(|(_, op)| *op).expect("prefix operator");
+            self.bump_n(len);
+            let operand = self.unary()?;
+            let span = Span::new(start, operand.span.end);
+            return Ok(Expr { kind: ExprKind::Unary { op, operand: self.arena.boxed(operand) }, span });
+        }
+        if self.peek_keyword("co_await") {
+            self.ts.bump();
+            self.coroutine = true;
+            let operand = self.unary()?;
+            return Ok(Expr { kind: ExprKind::CoAwait(self.arena.boxed(operand)), span: self.span_from(start) });
+        }
+        if self.peek_keyword("sizeof") {
+            self.ts.bump();
+            if self.ts.check(&Token::Punct('(')) && self.type_id_after_paren() {
+                let ty = self.parenthesized_type()?;
+                return Ok(Expr { kind: ExprKind::SizeofType(ty), span: self.span_from(start) });
+            }
+            let operand = self.unary()?;
+            let span = Span::new(start, operand.span.end);
+            return Ok(Expr { kind: ExprKind::SizeofExpr(self.arena.boxed(operand)), span });
+        }
+        if self.peek_keyword("alignof") {
+            self.ts.bump();
+            let ty = self.parenthesized_type()?;
+            return Ok(Expr { kind: ExprKind::Alignof(ty), span: self.span_from(start) });
+        }
+        if self.ts.check(&Token::Punct('(')) && self.type_id_after_paren() {
+            let ty = self.parenthesized_type()?;
+            let operand = self.unary()?;
+            let span = Span::new(start, operand.span.end);
+            return Ok(Expr { kind: ExprKind::Cast { kind: CastKind::CStyle, ty, operand: self.arena.boxed(operand) }, span });
+        }
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> PResult<Expr<'a>> {
+        let mut e = self.primary()?;
+        loop {
+            let start = e.span.start;
+            if self.ts.eat(&Token::Punct('(')) {
+                let args = self.with_template_args(false, |p| p.arguments())?;
+                e = Expr { kind: ExprKind::Call { callee: self.arena.boxed(e), args }, span: self.span_from(start) };
+            } else if self.ts.eat(&Token::Punct('[')) {
+                let index = self.with_template_args(false, |p| p.expression())?;
+                self.expect_punct(']')?;
+                e = Expr { kind: ExprKind::Index { base: self.arena.boxed(e), index: self.arena.boxed(index) }, span: self.span_from(start) };
+            } else {
+                let op = match self.operator(POSTFIX.iter().copied()) {
+                    Some((op @ ("." | "->" | "++" | "--"), len)) => { self.bump_n(len); op }
+                    _ => return Ok(e),
+                };
+                e = match op {
+                    "++" | "--" => {
+                        let op = if op == "++" { UnaryOp::PostIncrement } else { UnaryOp::PostDecrement };
+                        Expr { kind: ExprKind::Unary { op, operand: self.arena.boxed(e) }, span: self.span_from(start) }
+                    }
+                    _ => {
+                        let member = self.qualified_name()?;
+                        Expr { kind: ExprKind::Member { base: self.arena.boxed(e), member, arrow: op == "->" }, span: self.span_from(start) }
+                    }
+                };
+            }
+        }
+    }
+
+    pub(super) fn primary(&mut self) -> PResult<Expr<'a>> {
+        let t = self.ts.peek().clone();
+        let kind = match &t.token {
+            Token::Number(text) => match number(text) {
+                Some(kind) => kind,
+                None => {
+                    let (kind, suffix) = user_literal(text).ok_or_else(|| (ParseError::InvalidNumber(text.clone()), t.span))?;
+                    self.ts.bump();
+                    let literal = self.arena.boxed(Expr { kind, span: t.span });
+                    return Ok(Expr { kind: ExprKind::UserLiteral { literal, suffix }, span: t.span });
+                }
+            },
+            Token::CharLiteral(c) => {
+                self.ts.bump();
+                let units = self.encode(c, t.span)?;
+                if units.len() > 1 && c.encoding != Encoding::Ordinary { return Err((ParseError::CharacterTooLarge, t.span)); }
+                let literal = Expr { kind: ExprKind::CharLiteral(c.clone()), span: t.span };
+                return Ok(self.literal_suffix(literal));
+            }
+            Token::Embed(bytes) => ExprKind::Embed(bytes.clone()),
+            Token::StringLiteral(s) => {
+                // Adjacent string literals are one literal, with the
+                // prefix of any that has one.
+                let mut literal = s.clone();
+                let mut end = self.ts.bump().span.end;
+                self.encode(s, t.span)?;
+                while let Token::StringLiteral(more) = self.peek().clone() {
+                    let span = self.ts.bump().span;
+                    match (literal.encoding, more.encoding) {
+                        (a, b) if a == b || b == Encoding::Ordinary => {}
+                        (Encoding::Ordinary, b) => literal.encoding = b,
+                        (a, b) => return Err((ParseError::IncompatibleStringLiterals(a.prefix(), b.prefix()), span)),
+                    }
+                    self.encode(&more, span)?;
+                    literal.chars.extend(more.chars);
+                    end = span.end;
+                }
+                return Ok(self.literal_suffix(Expr { kind: ExprKind::StringLiteral(literal), span: Span::new(t.span.start, end) }));
+            }
+            Token::Punct('(') => {
+                self.ts.bump();
+                let inner = self.with_template_args(false, |p| p.expression())?;
+                self.expect_punct(')')?;
+                return Ok(Expr { kind: ExprKind::Paren(self.arena.boxed(inner)), span: self.span_from(t.span.start) });
+            }
+            Token::Identifier(word) => match &*word.as_str() {
+                "true" => ExprKind::BoolLiteral(true),
+                "false" => ExprKind::BoolLiteral(false),
+                "nullptr" => ExprKind::Nullptr,
+                "this" => ExprKind::This,
+                word if NAMED_CASTS.iter().any(|(k, _)| *k == word) => return self.named_cast(),
+                "requires" => return self.requires_expression(),
+                "operator" => {
+                    let name = self.qualified_name()?;
+                    return Ok(Expr { kind: ExprKind::Name(QualifiedName::new(name)), span: self.span_from(t.span.start) });
+                }
+                _ if self.type_id_follows() => {
+                    // Functional notation: `int(x)`, `T(a, b)`, `T{a, b}`.
+                    let ty = self.simple_type()?;
+                    let braced = self.ts.check(&Token::Punct('{'));
+                    let args = if braced {
+                        let ExprKind::InitList(items) = self.braced_init_list()?.kind else { unreachable!("a braced list") };
+                        items
+                    } else {
+                        self.expect_punct('(')?;
+                        self.with_template_args(false, |p| p.arguments())?
+                    };
+                    return Ok(Expr { kind: ExprKind::Construct { ty, args, braced }, span: self.span_from(t.span.start) });
+                }
+                _ if self.template_id_follows() => return self.template_id(),
+                word if RESERVED.contains(&word) => return Err(self.expected("an expression")),
+                _ => {
+                    let name = self.qualified_name()?;
+                    return Ok(Expr { kind: ExprKind::Name(QualifiedName::new(name)), span: self.span_from(t.span.start) });
+                }
+            },
+            Token::Operator(op) if *op == ":" => {
+                let name = self.qualified_name()?;
+                return Ok(Expr { kind: ExprKind::Name(QualifiedName::new(name)), span: self.span_from(t.span.start) });
+            }
+            _ => return Err(self.expected("an expression")),
+        };
+        self.ts.bump();
+        Ok(Expr { kind, span: t.span })
+    }
+
+    /// The code units of `literal` in the execution character set its
+    /// prefix picks; a character the set lacks is an error.
+    fn encode(&self, literal: &Literal, span: Span) -> PResult<Vec<u32>> {
+        let charset = self.session.exec_charsets().charset(literal.encoding);
+        literal.units(charset).map_err(|c| (ParseError::UnencodableCharacter { c, charset }, span))
+    }
+
+    /// `literal` with the suffix written right after it, if any: `"abc"_x`
+    /// or `'c'_x`. Only `_` suffixes and the standard `s` and `sv` are
+    /// taken, so `"%"PRIu64` from old C headers still concatenates.
+    fn literal_suffix(&mut self, literal: Expr<'a>) -> Expr<'a> {
+        let next = self.ts.peek().clone();
+        let Token::Identifier(suffix) = &next.token else { return literal };
+        if next.span.start != literal.span.end || !(suffix.as_str().starts_with('_') || suffix == "s" || suffix == "sv") { return literal; }
+        self.ts.bump();
+        let span = Span::new(literal.span.start, next.span.end);
+        Expr { kind: ExprKind::UserLiteral { literal: self.arena.boxed(literal), suffix: *suffix }, span }
+    }
+
+    /// What follows `operator` in an operator function's name, spelled
+    /// as it is appended to `operator`: `+`, `()`, ` new[]`, `""_km`, or
+    /// a space and the type of a conversion function.
+    pub(super) fn operator_function_id(&mut self) -> PResult<String> {
+        let t = self.ts.peek().clone();
+        match &t.token {
+            Token::Punct(open @ ('(' | '[')) => {
+                let close = if *open == '(' { ')' } else { ']' };
+                self.ts.bump();
+                self.expect_punct(close)?;
+                return Ok(format!("{}{}", open, close));
+            }
+            Token::Punct(',') => { self.ts.bump(); return Ok(",".into()); }
+            Token::Identifier(word) if word == "new" || word == "delete" => {
+                self.ts.bump();
+                let array = self.ts.check(&Token::Punct('[')) && self.ts.peek_nth(1).token == Token::Punct(']');
+                if array { self.bump_n(2); }
+                return Ok(format!(" {}{}", word, if array { "[]" } else { "" }));
+            }
+            Token::StringLiteral(s) if s.is_empty() && s.encoding == Encoding::Ordinary => {
+                self.ts.bump();
+                return match self.peek().clone() {
+                    Token::Identifier(suffix) => { self.ts.bump(); Ok(format!("\"\"{}", suffix)) }
+                    _ => Err(self.expected("a literal suffix")),
+                };
+            }
+            _ => {}
+        }
+        if let Some((op, len)) = self.operator(OVERLOADABLE.iter().copied()) {
+            self.bump_n(len);
+            return Ok(op.to_string());
+        }
+        if self.type_id_follows() { return Ok(format!(" {}", self.conversion_type()?)); }
+        Err(self.expected("an overloadable operator"))
+    }
+
+    /// `static_cast<T>(e)` and the other named casts.
+    fn named_cast(&mut self) -> PResult<Expr<'a>> {
+        let t = self.ts.bump();
+        let kind = NAMED_CASTS.iter().find(|(k, _)| t.token == Token::Identifier((*k).into())).map(|(_, kind)| *kind).expect("named cast");
+        self.expect_punct('<')?;
+        let ty = self.type_id()?;
+        self.expect_punct('>')?;
+        self.expect_punct('(')?;
+        let operand = self.with_template_args(false, |p| p.expression())?;
+        self.expect_punct(')')?;
+        Ok(Expr { kind: ExprKind::Cast { kind, ty, operand: self.arena.boxed(operand) }, span: self.span_from(t.span.start) })
+    }
+
+    /// Call arguments after the `(`, through the `)`.
+    pub(super) fn arguments(&mut self) -> PResult<Vec<Expr<'a>>> {
+        let mut args = Vec::new();
+        if !self.ts.check(&Token::Punct(')')) {
+            loop {
+                args.push(self.initializer()?);
+                self.eat_ellipsis();
+                if !self.ts.eat(&Token::Punct(',')) { break; }
+            }
+        }
+        self.expect_punct(')')?;
+        Ok(args)
+    }
+
+    /// `( type-id )`.
+    fn parenthesized_type(&mut self) -> PResult<crate::types::TypeId> {
+        self.expect_punct('(')?;
+        let ty = self.type_id()?;
+        self.expect_punct(')')?;
+        Ok(ty)
+    }
+
+    /// At a `(`: whether a type-id follows it, making it a cast or
+    /// `sizeof(T)` rather than a parenthesized expression.
+    fn type_id_after_paren(&mut self) -> bool {
+        let cp = self.ts.checkpoint();
+        self.ts.bump();
+        let is_type = self.type_id_follows();
+        self.ts.rollback(cp);
+        is_type
+    }
+
+    /// The binary operator at the cursor with its precedence and token
+    /// count. In template arguments, none starts with `>`.
+    fn binary_operator(&mut self) -> Option<(BinaryOp, u8, usize)> {
+        let (text, len) = self.operator(BINARY.iter().map(|(s, _, _)| *s))?;
+        if self.in_template_args && text.starts_with('>') { return None; }
+        BINARY.iter().find(|(s, _, _)| *s == text).map(|&(_, op, prec)| (op, prec, len))
+    }
+
+    /// The longest of `spellings` formed by up to three adjacent operator
+    /// tokens at the cursor, and how many tokens it takes.
+    pub(super) fn operator<'o>(&mut self, spellings: impl Iterator<Item = &'o str> + Clone) -> Option<(&'o str, usize)> {
+        let mut text = String::new();
+        let mut ends = Vec::new();
+        let mut prev_end = None;
+        for n in 0..3 {
+            let t = self.ts.peek_nth(n);
+            let piece = match &t.token {
+                Token::Operator(op) => op.to_string(),
+                Token::Punct(c @ ('<' | '>')) => c.to_string(),
+                _ => break,
+            };
+            if prev_end.is_some_and(|end| end != t.span.start) { break; }
+            prev_end = Some(t.span.end);
+            text.push_str(&piece);
+            ends.push(text.len());
+        }
+        ends.iter().enumerate().rev().find_map(|(i, &len)| spellings.clone().find(|s| *s == &text[..len]).map(|s| (s, i + 1)))
+    }
+
+    pub(super) fn bump_n(&mut self, n: usize) {
+        for _ in 0..n { self.ts.bump(); }
+    }
+
+    pub(super) fn binary_expr(&self, op: BinaryOp, lhs: Expr<'a>, rhs: Expr<'a>) -> Expr<'a> {
+        let span = Span::new(lhs.span.start, rhs.span.end);
+        Expr { kind: ExprKind::Binary { op, lhs: self.arena.boxed(lhs), rhs: self.arena.boxed(rhs) }, span }
+    }
+}
+
+/// A pp-number that is a numeric literal with a suffix, split into the
+/// literal and the suffix: `12_km`, or `10ms` with a standard suffix.
+fn user_literal<'a>(text: &str) -> Option<(ExprKind<'a>, Symbol)> {
+    let at = match text.find('_') {
+        Some(at) => at,
+        None => STANDARD_SUFFIXES.iter().filter(|s| text.ends_with(*s)).map(|s| text.len() - s.len()).find(|&at| number(&text[..at]).is_some())?,
+    };
+    let (literal, suffix) = text.split_at(at);
+    if !suffix.chars().all(|c| c == '_' || c.is_ascii_alphanumeric()) { return None; }
+    Some((number(literal)?, Symbol::intern(suffix)))
+}
+
+/// The literal a pp-number spells, if it is a valid integer or floating
+/// literal.
+fn number<'a>(text: &str) -> Option<ExprKind<'a>> {
+    let is_hex = text.starts_with("0x") || text.starts_with("0X");
+    let is_float = text.contains('.') || (!is_hex && text.contains(['e', 'E'])) || (is_hex && text.contains(['p', 'P']));
+    if !is_float { return Some(ExprKind::IntLiteral(parse_int(text)?, IntSuffix::of(text)?)); }
+    if is_hex { return None; }
+    let digits = text.replace('\'', "");
+    let digits = digits.trim_end_matches(['f', 'F', 'l', 'L']);
+    digits.parse::<f64>().ok().map(ExprKind::FloatLiteral)
+}
@@ -0,0 +1,364 @@
+//! The parser: turns a preprocessed token stream into an AST.
+//!
+//! A hand-written recursive-descent parser over a `TokenStream`. Errors are
+//! reported to the session's diagnostics; the parser then skips to the end
+//! of the declaration it was in and carries on, so one mistake does not
+//! hide the rest of the file.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::arena::TuArena;
+use crate::ast::{Decl, TranslationUnit};
+use crate::cancel::Cancelled;
+use crate::charset::Charset;
+use crate::diagnostics::{Diagnostic, Label};
+use crate::intern::Symbol;
+use crate::lexer::token::{Span, SpannedToken, Token};
+use crate::lexer::TokenStream;
+use crate::sema::constant::Constants;
+use crate::sema::SemaError;
+use crate::session::Session;
+
+mod attr;
+mod class;
+mod concept;
+mod decl;
+mod expr;
+mod module;
+mod stmt;
+mod template;
+mod unsupported;
+
+/// A syntax error. Reported as a diagnostic; parsing resumes after the
+/// declaration that contains it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// Something else was required here; `found` is already quoted.
+    Expected { expected: String, found: String },
+    MissingTypeSpecifier,
+    /// Two type specifiers that do not go together, like `int char`.
+    ConflictingSpecifiers { previous: String, new: String },
+    DuplicateSpecifier(String),
+    InvalidArrayBound(String),
+    NegativeArrayBound(i128),
+    /// An array bound or `noexcept` operand whose evaluation fails.
+    Constant(SemaError),
+    /// A `noexcept(e)` whose `e` is not a constant.
+    InvalidNoexcept(String),
+    /// A declarator that builds an impossible type; holds the reason.
+    InvalidDeclarator(&'static str),
+    InvalidNumber(String),
+    /// A module, import or export declaration out of place; holds the
+    /// reason.
+    InvalidModuleDeclaration(&'static str),
+    /// An imported module whose compiled interface could not be read.
+    ModuleNotLoaded { module: Symbol, reason: String },
+    /// A linkage specification for a language other than C and C++.
+    UnknownLinkage(String),
+    /// Adjacent string literals with different encoding prefixes.
+    IncompatibleStringLiterals(&'static str, &'static str),
+    /// A literal's character that has no code units in the execution
+    /// character set.
+    UnencodableCharacter { c: char, charset: Charset },
+    /// A prefixed character literal that takes more than one code unit.
+    CharacterTooLarge,
+    InvalidBitWidth(String),
+    NegativeBitWidth { name: Symbol, width: i128 },
+    /// A bit-field of width 0 that has a name.
+    ZeroBitWidth(Symbol),
+}
+
+impl ParseError {
+    /// Stable diagnostic code for this error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::Expected { .. } => "E0201",
+            ParseError::MissingTypeSpecifier => "E0202",
+            ParseError::ConflictingSpecifiers { .. } => "E0203",
+            ParseError::DuplicateSpecifier(_) => "E0204",
+            ParseError::InvalidArrayBound(_) => "E0205",
+            ParseError::InvalidDeclarator(_) => "E0206",
+            ParseError::InvalidNumber(_) => "E0207",
+            ParseError::InvalidModuleDeclaration(_) => "E0208",
+            ParseError::ModuleNotLoaded { .. } => "E0209",
+            ParseError::NegativeArrayBound(_) => "E0210",
+            ParseError::UnknownLinkage(_) => "E0211",
+            ParseError::InvalidNoexcept(_) => "E0212",
+            ParseError::IncompatibleStringLiterals(..) => "E0213",
+            ParseError::UnencodableCharacter { .. } => "E0214",
+            ParseError::CharacterTooLarge => "E0215",
+            ParseError::InvalidBitWidth(_) => "E0216",
+            ParseError::NegativeBitWidth { .. } => "E0217",
+            ParseError::ZeroBitWidth(_) => "E0218",
+            ParseError::Constant(e) => e.code(),
+        }
+    }
+
+    fn to_diagnostic(&self, span: Span) -> Diagnostic {
+        Diagnostic::error(self.to_string()).with_code(self.code()).with_label(Label::primary(span, ""))
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Expected { expected, found } => write!(f, "expected {}, found {}", expected, found),
+            ParseError::MissingTypeSpecifier => write!(f, "a type specifier is required for all declarations"),
+            ParseError::ConflictingSpecifiers { previous, new } => write!(f, "cannot combine `{}` with previous `{}`", new, previous),
+            ParseError::DuplicateSpecifier(s) => write!(f, "duplicate `{}` specifier", s),
+            ParseError::InvalidArrayBound(text) => write!(f, "array bound `{}` is not an integer constant", text),
+            ParseError::InvalidDeclarator(reason) => write!(f, "{}", reason),
+            ParseError::InvalidNumber(text) => write!(f, "invalid numeric literal `{}`", text),
+            ParseError::InvalidModuleDeclaration(reason) => write!(f, "{}", reason),
+            ParseError::ModuleNotLoaded { module, reason } => write!(f, "cannot load module `{}`: {}", module, reason),
+            ParseError::NegativeArrayBound(n) => write!(f, "array bound {} is negative", n),
+            ParseError::UnknownLinkage(language) => write!(f, "unknown language linkage `\"{}\"` (expected `\"C\"` or `\"C++\"`)", language),
+            ParseError::Constant(e) => write!(f, "{}", e),
+            ParseError::InvalidNoexcept(text) => write!(f, "`noexcept` operand `{}` is not a constant", text),
+            ParseError::IncompatibleStringLiterals(a, b) => write!(f, "cannot concatenate string literals with prefixes `{}` and `{}`", a, b),
+            ParseError::UnencodableCharacter { c, charset } => write!(f, "character `{}` cannot be encoded in the execution character set {}", c, charset),
+            ParseError::CharacterTooLarge => write!(f, "character too large for enclosing character literal type"),
+            ParseError::InvalidBitWidth(text) => write!(f, "bit-field width `{}` is not an integer constant", text),
+            ParseError::NegativeBitWidth { name, width } => write!(f, "bit-field `{}` has negative width {}", name, width),
+            ParseError::ZeroBitWidth(name) => write!(f, "named bit-field `{}` has zero width", name),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Whether `word` is a keyword, which cannot name a declaration.
+pub fn is_keyword(word: &str) -> bool { decl::RESERVED.contains(&word) || decl::SPECIFIER_KEYWORDS.contains(&word) }
+
+type PResult<T> = Result<T, (ParseError, Span)>;
+
+pub struct Parser<'s, 'a> {
+    session: &'s mut Session,
+    /// Where the tree's boxed nodes are allocated.
+    arena: &'a TuArena,
+    ts: TokenStream<'static>,
+    /// Names declared by `typedef` so far, which begin a type.
+    typedefs: HashSet<Symbol>,
+    /// Names declared as templates, which a `<` after them opens
+    /// arguments for.
+    templates: HashSet<Symbol>,
+    /// Names declared as concepts, which may constrain a template
+    /// parameter or `auto`. They are templates too.
+    concepts: HashSet<Symbol>,
+    /// The non-type template parameters in scope, which array bounds may
+    /// depend on.
+    value_params: HashSet<Symbol>,
+    /// The namespaces enclosing the current position, innermost last, and
+    /// whether each is inline; `None` for an unnamed one.
+    namespaces: Vec<(Option<Symbol>, bool)>,
+    /// Names of the classes whose bodies are being parsed, innermost last,
+    /// so constructors can be told apart from calls.
+    classes: Vec<Symbol>,
+    /// How many blocks enclose the current position.
+    block_depth: usize,
+    /// Set once `co_await`, `co_yield` or `co_return` appears in the
+    /// function body being parsed.
+    coroutine: bool,
+    /// The module this unit belongs to, once its module declaration is
+    /// parsed, which `import :part;` names a partition of.
+    module: Option<Symbol>,
+    /// Modules imported so far, by full name.
+    imported: HashSet<Symbol>,
+    /// The braced linkage specifications enclosing the current position,
+    /// innermost last, and whether each is `extern "C"`.
+    linkages: Vec<bool>,
+    /// Set inside an `export`, which may not be nested.
+    exporting: bool,
+    /// Set inside template arguments, where a `>` outside parentheses
+    /// closes them instead of being an operator.
+    in_template_args: bool,
+    /// Constant variables, enumerators and types declared so far, which
+    /// array bounds may name and measure.
+    constants: Constants<'a>,
+}
+
+impl<'s, 'a> Parser<'s, 'a> {
+    /// A parser over `tokens`, typically a `PreprocessOutput`'s, that
+    /// builds its tree in `arena`.
+    pub fn new(session: &'s mut Session, arena: &'a TuArena, tokens: Vec<SpannedToken>) -> Self {
+        let constants = Constants::new(&session.target).with_charsets(session.exec_charsets());
+        Self {
+            session,
+            arena,
+            ts: TokenStream::from_tokens(tokens),
+            typedefs: HashSet::new(),
+            templates: HashSet::new(),
+            concepts: HashSet::new(),
+            value_params: HashSet::new(),
+            namespaces: Vec::new(),
+            classes: Vec::new(),
+            block_depth: 0,
+            coroutine: false,
+            module: None,
+            imported: HashSet::new(),
+            linkages: Vec::new(),
+            exporting: false,
+            in_template_args: false,
+            constants,
+        }
+    }
+
+    /// Parses a whole translation unit.
+    pub fn parse(mut self) -> Result<TranslationUnit<'a>, Cancelled> {
+        let decls = self.declaration_seq(false)?;
+        // The end of file is the main file's.
+        let eof = self.ts.peek().span;
+        let span = self.session.sources.file_at(eof.start).map_or(eof, |f| Span::new(f.start, f.end()));
+        Ok(TranslationUnit { decls, span })
+    }
+
+    /// Parses declarations up to end of file, or up to (not past) the `}`
+    /// closing a namespace body if `in_braces`.
+    fn declaration_seq(&mut self, in_braces: bool) -> Result<Vec<Decl<'a>>, Cancelled> {
+        let mut decls: Vec<Decl> = Vec::new();
+        while !self.ts.is_eof() && !self.stopped() {
+            self.session.cancel.check()?;
+            let start = self.ts.peek().span.start;
+            if self.ts.check(&Token::Punct('}')) {
+                if in_braces { break; }
+                let error = self.expected("a declaration");
+                self.report(error, start);
+                continue;
+            }
+            if self.ts.eat(&Token::Punct(';')) { continue; }
+            let (cp, len) = (self.ts.checkpoint(), decls.len());
+            let result = if self.module_item_follows() {
+                self.module_item(&mut decls)?
+            } else if self.namespace_follows() {
+                self.namespace(&mut decls)?
+            } else if self.linkage_follows() {
+                self.linkage_specification(&mut decls)?
+            } else {
+                self.declaration(&mut decls)
+            };
+            match result {
+                Err(_) if self.session.options.skip_unsupported => {
+                    decls.truncate(len);
+                    decls.push(Decl::Unsupported(self.skip_unsupported(cp)));
+                }
+                Err(error) => decls.extend(self.report(error, start).map(Decl::Invalid)),
+                Ok(()) => {}
+            }
+        }
+        Ok(decls)
+    }
+
+    /// Emits `error` and skips past the declaration it is in, which began
+    /// at `start`. Returns the span of the declaration, for the node that
+    /// stands for it, unless nothing of it was consumed.
+    fn report(&mut self, (error, span): (ParseError, Span), start: usize) -> Option<Span> {
+        let mut diag = error.to_diagnostic(span);
+        // A `;` missing before the next token goes right after the last one.
+        if matches!(&error, ParseError::Expected { expected, .. } if expected == "`;`") && span == self.ts.peek().span && start < span.start {
+            let end = self.ts.prev_span().end;
+            diag = diag.with_fixit(Span::new(end, end), ";");
+        }
+        self.session.diagnostics.emit(diag);
+        self.recover();
+        let end = self.ts.prev_span().end;
+        (end > start).then(|| Span::new(start, end))
+    }
+
+    /// Whether the error limit has been reached, after which the rest of
+    /// the input is not parsed.
+    fn stopped(&self) -> bool { self.session.diagnostics.limit_reached() }
+
+    /// Skips to just past the `;` or `}` that ends the current declaration
+    /// or statement. A `}` that closes an enclosing namespace or block is
+    /// left in place.
+    fn recover(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            match self.peek() {
+                Token::Eof => return,
+                Token::Punct('}') if depth == 0 => {
+                    // Stray at file scope: drop it so parsing moves on.
+                    if self.namespaces.is_empty() && self.linkages.is_empty() && self.classes.is_empty() && self.block_depth == 0 { self.ts.bump(); }
+                    return;
+                }
+                _ => {}
+            }
+            match self.ts.bump().token {
+                Token::Punct('{') => depth += 1,
+                Token::Punct('}') if depth == 1 => return,
+                Token::Punct('}') => depth -= 1,
+                Token::Punct(';') if depth == 0 => return,
+                _ => {}
+            }
+        }
+    }
+
+    fn peek(&mut self) -> &Token { &self.ts.peek().token }
+
+    fn peek_keyword(&mut self, word: &str) -> bool { matches!(self.peek(), Token::Identifier(w) if w == word) }
+
+    fn peek_is_op(&mut self, op: &str) -> bool { matches!(self.peek(), Token::Operator(o) if *o == op) }
+
+    fn eat_op(&mut self, op: &str) -> bool {
+        if self.peek_is_op(op) { self.ts.bump(); true } else { false }
+    }
+
+    /// Consumes `::`, which the lexer produces as two adjacent `:`s.
+    fn eat_scope(&mut self) -> bool {
+        let first = self.ts.peek_nth(0).clone();
+        let second = self.ts.peek_nth(1);
+        let is_colon = |t: &SpannedToken| t.token == Token::Operator(":");
+        if is_colon(&first) && is_colon(second) && first.span.end == second.span.start {
+            self.ts.bump();
+            self.ts.bump();
+            return true;
+        }
+        false
+    }
+
+    /// Consumes `&&`, which may also arrive as two adjacent `&`s.
+    fn eat_and_and(&mut self) -> bool {
+        if self.eat_op("&&") { return true; }
+        let first = self.ts.peek_nth(0).clone();
+        let second = self.ts.peek_nth(1);
+        let is_amp = |t: &SpannedToken| t.token == Token::Operator("&");
+        if is_amp(&first) && is_amp(second) && first.span.end == second.span.start {
+            self.ts.bump();
+            self.ts.bump();
+            return true;
+        }
+        false
+    }
+
+    /// Consumes `...`: three adjacent `.`s.
+    fn eat_ellipsis(&mut self) -> bool {
+        let dot = |t: &SpannedToken| t.token == Token::Operator(".");
+        let a = self.ts.peek_nth(0).clone();
+        let b = self.ts.peek_nth(1).clone();
+        let c = self.ts.peek_nth(2);
+        if dot(&a) && dot(&b) && dot(c) && a.span.end == b.span.start && b.span.end == c.span.start {
+            for _ in 0..3 { self.ts.bump(); }
+            return true;
+        }
+        false
+    }
+
+    fn expect_punct(&mut self, c: char) -> PResult<Span> {
+        if self.ts.check(&Token::Punct(c)) { return Ok(self.ts.bump().span); }
+        Err(self.expected(&format!("`{}`", c)))
+    }
+
+    /// From `start` to the end of the last token consumed.
+    fn span_from(&self, start: usize) -> Span { Span::new(start, self.ts.prev_span().end) }
+
+    /// The error for finding the next token where `what` was required.
+    fn expected(&mut self, what: &str) -> (ParseError, Span) {
+        let t = self.ts.peek().clone();
+        let found = match t.token {
+            Token::Eof => "end of file".to_string(),
+            other => format!("`{}`", other.spelling()),
+        };
+        (ParseError::Expected { expected: what.to_string(), found }, t.span)
+    }
+}
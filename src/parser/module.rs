@@ -0,0 +1,158 @@
+//! C++20 modules: module and import declarations, and `export`.
+//!
+//! `module` and `import` are only keywords at the start of a declaration
+//! at file scope, and only when followed by what a module name or header
+//! unit begins with; anywhere else they are ordinary names. Importing a
+//! module reads its compiled interface from the module cache and declares
+//! the types, templates and concepts it exports, so code using them
+//! parses as it would with the declarations in view.
+
+use crate::ast::{Decl, ExportDecl, ImportDecl, ModuleDecl};
+use crate::cancel::Cancelled;
+use crate::artifact;
+use crate::intern::Symbol;
+use crate::lexer::token::{Span, Token};
+use crate::module::{self, ModuleInterface};
+use crate::parser::{PResult, ParseError, Parser};
+
+impl<'s, 'a> Parser<'s, 'a> {
+    /// Whether a module, import or export declaration is next. Consumes
+    /// nothing.
+    pub(super) fn module_item_follows(&mut self) -> bool {
+        if self.peek_keyword("export") { return true; }
+        let at_file_scope = self.namespaces.is_empty() && !self.exporting;
+        if !at_file_scope || !(self.peek_keyword("module") || self.peek_keyword("import")) { return false; }
+        let is_module = self.peek_keyword("module");
+        match &self.ts.peek_nth(1).token {
+            Token::Identifier(_) => true,
+            Token::Operator(op) => *op == ":",
+            Token::Punct(';') => is_module,
+            Token::Punct('<') | Token::StringLiteral(_) => !is_module,
+            _ => false,
+        }
+    }
+
+    /// A module or import declaration, possibly exported, or an `export`
+    /// of other declarations. Errors inside an `export { ... }` are
+    /// reported there; only a malformed head or a missing `}` comes back.
+    pub(super) fn module_item(&mut self, decls: &mut Vec<Decl<'a>>) -> Result<PResult<()>, Cancelled> {
+        let start = self.ts.peek().span.start;
+        let is_export = self.peek_keyword("export");
+        if is_export {
+            let span = self.ts.bump().span;
+            if self.exporting { return Ok(Err((ParseError::InvalidModuleDeclaration("`export` declarations cannot be nested"), span))); }
+        }
+        if self.peek_keyword("module") { return Ok(self.module_declaration(start, is_export, decls)); }
+        if self.peek_keyword("import") { return Ok(self.import_declaration(start, is_export, decls)); }
+        self.exporting = true;
+        let result = self.exported(start, decls);
+        self.exporting = false;
+        result
+    }
+
+    /// `export decl` or `export { decls }`, after the `export`.
+    fn exported(&mut self, start: usize, decls: &mut Vec<Decl<'a>>) -> Result<PResult<()>, Cancelled> {
+        let braced = self.ts.eat(&Token::Punct('{'));
+        let mut exported = Vec::new();
+        let result = if braced {
+            exported = self.declaration_seq(true)?;
+            self.expect_punct('}').map(|_| ())
+        } else if self.namespace_follows() {
+            self.namespace(&mut exported)?
+        } else {
+            self.declaration(&mut exported)
+        };
+        decls.push(Decl::Export(ExportDecl { decls: exported, braced, span: self.span_from(start) }));
+        Ok(result)
+    }
+
+    /// `module name[:partition];`, `module;` or `module :private;`, after
+    /// any `export`.
+    fn module_declaration(&mut self, start: usize, is_export: bool, decls: &mut Vec<Decl<'a>>) -> PResult<()> {
+        self.ts.bump();
+        let name = if matches!(self.peek(), Token::Identifier(_)) { Some(self.module_name()?) } else { None };
+        let partition = if self.eat_op(":") { Some(self.module_name()?) } else { None };
+        if is_export && name.is_none() { return Err(self.expected("a module name")); }
+        self.expect_punct(';')?;
+        let m = ModuleDecl { name, partition, is_export, span: self.span_from(start) };
+        self.module = m.full_name().or(self.module);
+        decls.push(Decl::Module(m));
+        // An implementation unit imports its module's interface.
+        if let (Some(name), None, false) = (name, partition, is_export) { self.import(name, self.span_from(start)); }
+        Ok(())
+    }
+
+    /// `import m;`, `import :part;` or `import <header>;`, after any
+    /// `export`.
+    fn import_declaration(&mut self, start: usize, is_export: bool, decls: &mut Vec<Decl<'a>>) -> PResult<()> {
+        self.ts.bump();
+        let name_span = self.ts.peek().span;
+        let module = match self.peek().clone() {
+            Token::StringLiteral(path) => {
+                self.ts.bump();
+                format!("\"{}\"", path.text())
+            }
+            Token::Punct('<') => {
+                self.ts.bump();
+                let mut header = String::from("<");
+                while !matches!(self.peek(), Token::Punct('>' | ';') | Token::Eof) { header.push_str(&self.ts.bump().token.spelling()); }
+                self.expect_punct('>')?;
+                header.push('>');
+                header
+            }
+            _ if self.eat_op(":") => format!(":{}", self.module_name()?),
+            _ => self.module_name()?.to_string(),
+        };
+        self.expect_punct(';')?;
+        let module = Symbol::intern(&module);
+        decls.push(Decl::Import(ImportDecl { module, is_export, span: self.span_from(start) }));
+        if !module::is_header_unit(module) {
+            let module = match self.module { Some(current) => module::resolve(current, module), None => module };
+            self.import(module, self.span_from(name_span.start));
+        }
+        Ok(())
+    }
+
+    /// A dotted module name like `std.core`.
+    fn module_name(&mut self) -> PResult<Symbol> {
+        let mut name = String::new();
+        loop {
+            let Token::Identifier(part) = self.peek().clone() else { return Err(self.expected("a module name")) };
+            self.ts.bump();
+            name.push_str(&part.as_str());
+            if !self.eat_op(".") { break; }
+            name.push('.');
+        }
+        Ok(Symbol::intern(&name))
+    }
+
+    /// Declares what `module` exports, and what the modules it re-exports
+    /// do, from their compiled interfaces. Reports a module that is not in
+    /// the module cache; without a cache, nothing is loaded.
+    fn import(&mut self, module: Symbol, span: Span) {
+        let Some(dir) = self.session.options.module_cache.clone() else { return };
+        if !self.imported.insert(module) { return; }
+        let path = module::cache_path(&dir, module);
+        let interface = match self.session.fs.read(&path) {
+            Ok(bytes) => artifact::decode_module(&bytes).map_err(|e| format!("{}: {}", path.display(), e)),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        };
+        match interface {
+            Ok(interface) => self.declare_exports(&interface, span),
+            Err(reason) => self.session.diagnostics.emit(ParseError::ModuleNotLoaded { module, reason }.to_diagnostic(span)),
+        }
+    }
+
+    fn declare_exports(&mut self, interface: &ModuleInterface, span: Span) {
+        for d in &interface.decls {
+            // Visible by its qualified name, and by its own name for code
+            // that uses the namespace.
+            let last = d.name.as_str().rsplit("::").next().map(Symbol::intern);
+            let names = [Some(d.name), last].into_iter().flatten();
+            if d.kind.is_type() { self.typedefs.extend(names.clone()); }
+            if d.kind.is_template() { self.templates.extend(names.clone()); }
+            if d.kind == module::ExportKind::Concept { self.concepts.extend(names); }
+        }
+        for &module in &interface.imports { self.import(module, span); }
+    }
+}
@@ -0,0 +1,226 @@
+//! Statements and function bodies.
+
+use crate::arena::ArenaBox;
+use crate::ast::{Block, Decl, Stmt, StmtKind};
+use crate::lexer::token::{Span, Token};
+use crate::parser::decl::{RESERVED, SPECIFIER_KEYWORDS};
+use crate::parser::{PResult, Parser};
+
+impl<'s, 'a> Parser<'s, 'a> {
+    /// A function definition's body, and whether it makes the function a
+    /// coroutine.
+    pub(super) fn function_body(&mut self) -> PResult<(Block<'a>, bool)> {
+        let outer = std::mem::replace(&mut self.coroutine, false);
+        let body = self.compound();
+        let is_coroutine = std::mem::replace(&mut self.coroutine, outer);
+        Ok((body?, is_coroutine))
+    }
+
+    /// A `{ ... }` block. An error in one statement is reported and parsing
+    /// resumes at the next, so only a missing `}` fails the block.
+    pub(super) fn compound(&mut self) -> PResult<Block<'a>> {
+        let open = self.expect_punct('{')?;
+        self.block_depth += 1;
+        let mut stmts = Vec::new();
+        while !self.ts.check(&Token::Punct('}')) && !self.ts.is_eof() && !self.stopped() {
+            let (cp, start) = (self.ts.checkpoint(), self.ts.peek().span.start);
+            match self.statement() {
+                Ok(stmt) => stmts.push(stmt),
+                Err(_) if self.session.options.skip_unsupported => {
+                    let skipped = self.skip_unsupported(cp);
+                    stmts.push(Stmt { kind: StmtKind::Unsupported(skipped), span: skipped.span });
+                }
+                Err(error) => stmts.extend(self.report(error, start).map(|span| Stmt { kind: StmtKind::Invalid, span })),
+            }
+        }
+        self.block_depth -= 1;
+        let close = self.expect_punct('}')?;
+        Ok(Block { stmts, span: Span::new(open.start, close.end) })
+    }
+
+    fn statement(&mut self) -> PResult<Stmt<'a>> {
+        let start = self.ts.peek().span.start;
+        // Attributes on a declaration belong to the names it declares.
+        if self.attributes_follow() {
+            if self.declaration_follows() { return self.simple_statement(); }
+            let attrs = self.attribute_specifiers()?;
+            let stmt = self.arena.boxed(self.statement()?);
+            return Ok(Stmt { kind: StmtKind::Attributed { attrs, stmt }, span: self.span_from(start) });
+        }
+        let word = match self.peek() {
+            Token::Punct('{') => {
+                let block = self.compound()?;
+                let span = block.span;
+                return Ok(Stmt { kind: StmtKind::Compound(block), span });
+            }
+            Token::Punct(';') => {
+                let span = self.ts.bump().span;
+                return Ok(Stmt { kind: StmtKind::Null, span });
+            }
+            Token::Identifier(word) => word.as_str(),
+            _ => return self.expression_statement(),
+        };
+        let kind = match &*word {
+            "if" => {
+                self.ts.bump();
+                let cond = self.condition()?;
+                let then = self.arena.boxed(self.statement()?);
+                let otherwise = if self.peek_keyword("else") {
+                    self.ts.bump();
+                    Some(self.arena.boxed(self.statement()?))
+                } else {
+                    None
+                };
+                StmtKind::If { cond, then, otherwise }
+            }
+            "while" => {
+                self.ts.bump();
+                let cond = self.condition()?;
+                StmtKind::While { cond, body: self.arena.boxed(self.statement()?) }
+            }
+            "do" => {
+                self.ts.bump();
+                let body = self.arena.boxed(self.statement()?);
+                self.expect_keyword("while")?;
+                let cond = self.condition()?;
+                self.expect_punct(';')?;
+                StmtKind::DoWhile { body, cond }
+            }
+            "for" => {
+                self.ts.bump();
+                self.expect_punct('(')?;
+                if let Some(decl) = self.range_for_declaration() { return self.range_for(start, None, decl); }
+                // The init statement brings its own `;`.
+                let init = if self.ts.eat(&Token::Punct(';')) { None } else { Some(self.arena.boxed(self.simple_statement()?)) };
+                if let Some(decl) = self.range_for_declaration() { return self.range_for(start, init, decl); }
+                let cond = if self.ts.check(&Token::Punct(';')) { None } else { Some(self.expression()?) };
+                self.expect_punct(';')?;
+                let step = if self.ts.check(&Token::Punct(')')) { None } else { Some(self.expression()?) };
+                self.expect_punct(')')?;
+                StmtKind::For { init, cond, step, body: self.arena.boxed(self.statement()?) }
+            }
+            "switch" => {
+                self.ts.bump();
+                let cond = self.condition()?;
+                StmtKind::Switch { cond, body: self.arena.boxed(self.statement()?) }
+            }
+            "case" => {
+                self.ts.bump();
+                let value = self.assignment()?;
+                self.expect_colon()?;
+                StmtKind::Case { value, body: self.arena.boxed(self.statement()?) }
+            }
+            "default" => {
+                self.ts.bump();
+                self.expect_colon()?;
+                StmtKind::Default(self.arena.boxed(self.statement()?))
+            }
+            "break" | "continue" => {
+                self.ts.bump();
+                self.expect_punct(';')?;
+                if &*word == "break" { StmtKind::Break } else { StmtKind::Continue }
+            }
+            "return" => {
+                self.ts.bump();
+                let value = match self.peek() {
+                    Token::Punct(';') => None,
+                    Token::Punct('{') => Some(self.braced_init_list()?),
+                    _ => Some(self.expression()?),
+                };
+                self.expect_punct(';')?;
+                StmtKind::Return(value)
+            }
+            "co_return" => {
+                self.ts.bump();
+                self.coroutine = true;
+                let value = match self.peek() {
+                    Token::Punct(';') => None,
+                    Token::Punct('{') => Some(self.braced_init_list()?),
+                    _ => Some(self.expression()?),
+                };
+                self.expect_punct(';')?;
+                StmtKind::CoReturn(value)
+            }
+            _ => return self.simple_statement(),
+        };
+        Ok(Stmt { kind, span: self.span_from(start) })
+    }
+
+    /// A declaration or expression statement, with its `;`.
+    /// The declaration and `:` of a range-based `for`, if next.
+    fn range_for_declaration(&mut self) -> Option<Decl<'a>> {
+        if self.declaration_follows() { self.range_declaration() } else { None }
+    }
+
+    /// The rest of a range-based `for` after the `:`.
+    fn range_for(&mut self, start: usize, init: Option<ArenaBox<'a, Stmt<'a>>>, decl: Decl<'a>) -> PResult<Stmt<'a>> {
+        let range = self.initializer()?;
+        self.expect_punct(')')?;
+        let body = self.arena.boxed(self.statement()?);
+        Ok(Stmt { kind: StmtKind::RangeFor { init, decl: self.arena.boxed(decl), range, body }, span: self.span_from(start) })
+    }
+
+    fn simple_statement(&mut self) -> PResult<Stmt<'a>> {
+        if !self.declaration_follows() { return self.expression_statement(); }
+        let start = self.ts.peek().span.start;
+        let mut decls = Vec::new();
+        self.declaration(&mut decls)?;
+        Ok(Stmt { kind: StmtKind::Decl(decls), span: self.span_from(start) })
+    }
+
+    fn expression_statement(&mut self) -> PResult<Stmt<'a>> {
+        let start = self.ts.peek().span.start;
+        let e = self.expression()?;
+        self.expect_punct(';')?;
+        Ok(Stmt { kind: StmtKind::Expr(e), span: self.span_from(start) })
+    }
+
+    /// `( expression )` after `if`, `while`, `switch` and `do ... while`.
+    fn condition(&mut self) -> PResult<crate::ast::Expr<'a>> {
+        self.expect_punct('(')?;
+        let cond = self.expression()?;
+        self.expect_punct(')')?;
+        Ok(cond)
+    }
+
+    /// Whether a statement starting here is a declaration: it starts with
+    /// a specifier keyword or a type name, or is a name followed by
+    /// another name, as in `Widget w;`.
+    fn declaration_follows(&mut self) -> bool {
+        if self.attributes_follow() {
+            let cp = self.ts.checkpoint();
+            let follows = self.attribute_specifiers().is_ok() && self.declaration_follows();
+            self.ts.rollback(cp);
+            return follows;
+        }
+        let Token::Identifier(word) = self.peek().clone() else { return self.type_id_follows() && !self.braced_construct_follows() };
+        if SPECIFIER_KEYWORDS.contains(&&*word.as_str()) || word == "using" || word == "static_assert" { return true; }
+        if self.type_id_follows() { return !self.braced_construct_follows(); }
+        if self.constraint_follows() == Some(true) { return true; }
+        if RESERVED.contains(&&*word.as_str()) { return false; }
+        let cp = self.ts.checkpoint();
+        let named = self.qualified_name().is_ok();
+        let follows = named && matches!(self.peek(), Token::Identifier(w) if !RESERVED.contains(&&*w.as_str()));
+        self.ts.rollback(cp);
+        follows
+    }
+
+    /// Whether a type and then a `{` follow, as in `S{1}.f();`, which is an
+    /// expression: a declaration has a declarator after its type.
+    fn braced_construct_follows(&mut self) -> bool {
+        let cp = self.ts.checkpoint();
+        let found = self.simple_type().is_ok() && self.ts.check(&Token::Punct('{'));
+        self.ts.rollback(cp);
+        found
+    }
+
+    fn expect_keyword(&mut self, word: &str) -> PResult<()> {
+        if self.peek_keyword(word) { self.ts.bump(); return Ok(()); }
+        Err(self.expected(&format!("`{}`", word)))
+    }
+
+    fn expect_colon(&mut self) -> PResult<()> {
+        if self.eat_op(":") { return Ok(()); }
+        Err(self.expected("`:`"))
+    }
+}
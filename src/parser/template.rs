@@ -0,0 +1,198 @@
+//! Templates: `template<...>` headers and template arguments.
+//!
+//! Nothing is instantiated; templates, their explicit and partial
+//! specializations and the members defined outside them are kept as
+//! written. The lexer never joins `>>`, so `vector<vector<int>>` closes
+//! two argument lists. Inside template arguments a `>` outside
+//! parentheses is a closer, never an operator.
+
+use crate::ast::{Decl, Expr, ExprKind, QualifiedName, TemplateArg, TemplateDecl, TemplateParam, TemplateParamKind};
+use crate::intern::Symbol;
+use crate::lexer::token::Token;
+use crate::parser::{PResult, ParseError, Parser};
+
+/// What a template parameter's name was declared as.
+#[derive(Debug, Clone, Copy)]
+enum Declared {
+    Type,
+    Template,
+    Value,
+}
+
+impl<'s, 'a> Parser<'s, 'a> {
+    /// `template<params>` and the one declaration it applies to.
+    pub(super) fn template_declaration(&mut self, decls: &mut Vec<Decl<'a>>) -> PResult<()> {
+        let start = self.ts.bump().span.start;
+        // Names the parameters make types, templates or values, which go
+        // out of scope with the declaration.
+        let mut scope = Vec::new();
+        let result = self.template_head_and_declaration(&mut scope);
+        for (name, kind) in scope {
+            match kind {
+                Declared::Type => self.typedefs.remove(&name),
+                Declared::Template => self.templates.remove(&name),
+                Declared::Value => self.value_params.remove(&name),
+            };
+        }
+        let (params, requires, decl) = result?;
+        if let Some(name) = template_name(&decl) { self.declare_template(name); }
+        decls.push(Decl::Template(TemplateDecl { params, requires, decl: self.arena.boxed(decl), span: self.span_from(start) }));
+        Ok(())
+    }
+
+    fn template_head_and_declaration(&mut self, scope: &mut Vec<(Symbol, Declared)>) -> PResult<(Vec<TemplateParam<'a>>, Option<Expr<'a>>, Decl<'a>)> {
+        self.expect_punct('<')?;
+        let params = self.template_parameters(scope)?;
+        let requires = self.requires_clause()?;
+        if self.peek_keyword("concept") { return Ok((params, requires, self.concept_definition()?)); }
+        // A class template's name takes arguments inside its own body.
+        let key = matches!(self.peek(), Token::Identifier(w) if matches!(&*w.as_str(), "struct" | "class" | "union"));
+        if let (true, Token::Identifier(name)) = (key, self.ts.peek_nth(1).token.clone()) { self.declare_template(name); }
+        let span = self.ts.peek().span;
+        let mut inner = Vec::new();
+        self.declaration(&mut inner)?;
+        match <[Decl; 1]>::try_from(inner) {
+            Ok([decl]) => Ok((params, requires, decl)),
+            Err(_) => Err((ParseError::InvalidDeclarator("a template declaration must declare exactly one name"), span)),
+        }
+    }
+
+    /// Template parameters after the `<`, through the `>`. Their names are
+    /// declared, and added to `scope` unless they were already.
+    fn template_parameters(&mut self, scope: &mut Vec<(Symbol, Declared)>) -> PResult<Vec<TemplateParam<'a>>> {
+        let mut params = Vec::new();
+        if !self.ts.check(&Token::Punct('>')) {
+            loop {
+                params.push(self.template_parameter(scope)?);
+                if !self.ts.eat(&Token::Punct(',')) { break; }
+            }
+        }
+        self.expect_punct('>')?;
+        Ok(params)
+    }
+
+    fn template_parameter(&mut self, scope: &mut Vec<(Symbol, Declared)>) -> PResult<TemplateParam<'a>> {
+        let start = self.ts.peek().span.start;
+        let mut constraint = None;
+        let (kind, name, is_pack) = if self.peek_keyword("template") {
+            self.ts.bump();
+            self.expect_punct('<')?;
+            let params = self.template_parameters(scope)?;
+            if !self.peek_keyword("class") && !self.peek_keyword("typename") { return Err(self.expected("`class` or `typename`")); }
+            self.ts.bump();
+            let is_pack = self.eat_ellipsis();
+            (TemplateParamKind::Template(params), self.parameter_name(), is_pack)
+        } else if self.type_parameter_follows() {
+            self.ts.bump();
+            let is_pack = self.eat_ellipsis();
+            (TemplateParamKind::Type, self.parameter_name(), is_pack)
+        } else if self.constraint_follows() == Some(false) {
+            constraint = Some(self.type_constraint()?);
+            let is_pack = self.eat_ellipsis();
+            (TemplateParamKind::Type, self.parameter_name(), is_pack)
+        } else {
+            let (ty, name, is_pack) = self.value_parameter()?;
+            (TemplateParamKind::Value(ty), name, is_pack)
+        };
+        if let Some(name) = name {
+            if !matches!(kind, TemplateParamKind::Value(_)) && self.typedefs.insert(name) { scope.push((name, Declared::Type)); }
+            if matches!(kind, TemplateParamKind::Template(_)) && self.templates.insert(name) { scope.push((name, Declared::Template)); }
+            if matches!(kind, TemplateParamKind::Value(_)) && self.value_params.insert(name) { scope.push((name, Declared::Value)); }
+        }
+        let default = if !self.eat_op("=") {
+            None
+        } else {
+            Some(match kind {
+                TemplateParamKind::Type => self.type_argument()?,
+                // The name of a template, which may also be a type.
+                TemplateParamKind::Template(_) => {
+                    let start = self.ts.peek().span.start;
+                    let name = QualifiedName::new(self.qualified_name()?);
+                    TemplateArg::Expr(Expr { kind: ExprKind::Name(name), span: self.span_from(start) })
+                }
+                TemplateParamKind::Value(_) => TemplateArg::Expr(self.with_template_args(true, |p| p.assignment())?),
+            })
+        };
+        Ok(TemplateParam { kind, name, is_pack, constraint, default, span: self.span_from(start) })
+    }
+
+    /// At `typename` or `class`: whether it introduces a type parameter,
+    /// rather than starting the type of a non-type one like
+    /// `typename T::size_type N`.
+    fn type_parameter_follows(&mut self) -> bool {
+        if !self.peek_keyword("typename") && !self.peek_keyword("class") { return false; }
+        let ends = |t: &Token| matches!(t, Token::Punct(',' | '>')) || *t == Token::Operator("=");
+        match self.ts.peek_nth(1).token.clone() {
+            Token::Identifier(_) => ends(&self.ts.peek_nth(2).token),
+            Token::Operator(".") => true,
+            other => ends(&other),
+        }
+    }
+
+    fn parameter_name(&mut self) -> Option<Symbol> {
+        let Token::Identifier(name) = self.peek().clone() else { return None };
+        self.ts.bump();
+        Some(name)
+    }
+
+    fn type_argument(&mut self) -> PResult<TemplateArg<'a>> {
+        let start = self.ts.peek().span.start;
+        let ty = self.type_id()?;
+        Ok(TemplateArg::Type(ty, self.span_from(start)))
+    }
+
+    /// Template arguments after the `<`, through the `>`.
+    pub(super) fn template_arguments(&mut self) -> PResult<Vec<TemplateArg<'a>>> {
+        let mut args = Vec::new();
+        if !self.ts.check(&Token::Punct('>')) {
+            loop {
+                let arg = if self.type_id_follows() { self.type_argument()? } else { TemplateArg::Expr(self.with_template_args(true, |p| p.assignment())?) };
+                args.push(arg);
+                if !self.ts.eat(&Token::Punct(',')) { break; }
+            }
+        }
+        self.expect_punct('>')?;
+        Ok(args)
+    }
+
+    /// Whether the name here was declared as a template and a `<` follows
+    /// it. Consumes nothing.
+    pub(super) fn template_id_follows(&mut self) -> bool {
+        let cp = self.ts.checkpoint();
+        let name = self.qualified_name();
+        let found = name.is_ok_and(|n| self.is_template(n)) && self.ts.check(&Token::Punct('<'));
+        self.ts.rollback(cp);
+        found
+    }
+
+    /// A function or variable template's name and its arguments.
+    pub(super) fn template_id(&mut self) -> PResult<Expr<'a>> {
+        let start = self.ts.peek().span.start;
+        let name = QualifiedName::new(self.qualified_name()?);
+        self.expect_punct('<')?;
+        let args = self.template_arguments()?;
+        Ok(Expr { kind: ExprKind::TemplateId { name, args }, span: self.span_from(start) })
+    }
+
+    pub(super) fn is_template(&self, name: Symbol) -> bool {
+        self.templates.contains(&name) || self.templates.contains(&Symbol::intern(name.as_str().trim_start_matches("::")))
+    }
+
+    /// Runs `f` inside template arguments if `inside`, or else outside
+    /// them, as within parentheses, where `>` is an operator again.
+    pub(super) fn with_template_args<T>(&mut self, inside: bool, f: impl FnOnce(&mut Self) -> PResult<T>) -> PResult<T> {
+        let outer = std::mem::replace(&mut self.in_template_args, inside);
+        let result = f(self);
+        self.in_template_args = outer;
+        result
+    }
+}
+
+/// The name a template declaration makes a template, if any. A member
+/// of a class template defined outside it, `S<T>::f`, is not one.
+fn template_name(decl: &Decl) -> Option<Symbol> {
+    match decl {
+        Decl::Class(_) | Decl::Function(_) | Decl::Var(_) | Decl::Typedef(_) | Decl::Concept(_) => Some(decl.name()).filter(|name| !name.as_str().contains("::")),
+        _ => None,
+    }
+}
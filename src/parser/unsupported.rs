@@ -0,0 +1,109 @@
+//! Skipping constructs the parser does not handle yet, for
+//! `Options::skip_unsupported`.
+//!
+//! Where a declaration, member or statement fails to parse, the parser
+//! goes back to where it began and skips it whole, with its balanced
+//! brackets, and the tree keeps an `UnsupportedConstruct` in its place
+//! instead of a syntax error. The category is a guess from the skipped
+//! tokens, good enough to tell which missing feature a project needs most.
+
+use crate::ast::{ConstructCategory, UnsupportedConstruct};
+use crate::lexer::token::Token;
+use crate::lexer::Checkpoint;
+use crate::parser::Parser;
+
+/// What may follow the `}` of a braced part of a construct without
+/// starting the next one, like the `;` after a lambda's body or the
+/// `catch` after a `try` block.
+fn continues(token: &Token) -> bool {
+    match token {
+        Token::Punct(';' | ',' | '(' | '[') | Token::Operator(_) => true,
+        Token::Identifier(w) => matches!(&*w.as_str(), "else" | "catch"),
+        _ => false,
+    }
+}
+
+impl<'s, 'a> Parser<'s, 'a> {
+    /// Goes back to `cp`, where a construct that failed to parse began,
+    /// and skips it: up to and including its `;` or the `}` that ends its
+    /// last braced part, and never past a bracket that closes an enclosing
+    /// one. Always consumes at least one token.
+    pub(super) fn skip_unsupported(&mut self, cp: Checkpoint) -> UnsupportedConstruct {
+        self.ts.rollback(cp);
+        let start = self.ts.peek().span.start;
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut depth = 0usize;
+        loop {
+            match self.peek() {
+                Token::Eof => break,
+                Token::Punct('}' | ')' | ']') if depth == 0 && !tokens.is_empty() => break,
+                _ => {}
+            }
+            let token = self.ts.bump().token;
+            tokens.push(token.clone());
+            match token {
+                Token::Punct('{' | '(' | '[') => depth += 1,
+                Token::Punct('}') if depth == 1 => {
+                    depth = 0;
+                    if !continues(self.peek()) { break; }
+                }
+                Token::Punct('}' | ')' | ']') => depth = depth.saturating_sub(1),
+                Token::Punct(';') if depth == 0 => break,
+                _ => {}
+            }
+        }
+        UnsupportedConstruct { category: category(&tokens), span: self.span_from(start) }
+    }
+}
+
+/// The feature the construct spelled by `tokens` most likely needs: what
+/// it begins with, else the first telling token inside it.
+fn category(tokens: &[Token]) -> ConstructCategory {
+    let word = |i: usize| match tokens.get(i) {
+        Some(Token::Identifier(w)) => w.as_str(),
+        _ => "".into(),
+    };
+    match (&*word(0), tokens.get(1)) {
+        ("static_assert", _) => return ConstructCategory::StaticAssert,
+        ("extern", Some(Token::StringLiteral(_))) => return ConstructCategory::LinkageSpecification,
+        ("asm" | "__asm__" | "__asm", _) => return ConstructCategory::InlineAssembly,
+        ("using", _) if &*word(1) == "enum" => return ConstructCategory::UsingEnum,
+        ("if", _) if &*word(1) == "constexpr" => return ConstructCategory::ConstexprIf,
+        ("try", _) => return ConstructCategory::Exceptions,
+        ("goto", _) => return ConstructCategory::Labels,
+        (w, Some(Token::Operator(op))) if !w.is_empty() && *op == ":" && !matches!(w, "public" | "protected" | "private" | "default") => {
+            return ConstructCategory::Labels;
+        }
+        _ => {}
+    }
+    for (i, token) in tokens.iter().enumerate() {
+        let prev = i.checked_sub(1).map(|p| &tokens[p]);
+        match token {
+            // A `[` that is not a subscript or part of an attribute's
+            // `[[` introduces a lambda.
+            Token::Punct('[') => {
+                let subscript = match prev {
+                    Some(Token::Identifier(w)) => !matches!(&*w.as_str(), "return" | "co_return" | "co_yield" | "throw"),
+                    prev => matches!(prev, Some(Token::Punct(')' | ']'))),
+                };
+                let attribute = matches!(prev, Some(Token::Punct('['))) || matches!(tokens.get(i + 1), Some(Token::Punct('[')));
+                if !subscript && !attribute { return ConstructCategory::Lambda; }
+            }
+            Token::Identifier(w) => match &*w.as_str() {
+                "throw" | "catch" => return ConstructCategory::Exceptions,
+                "decltype" => return ConstructCategory::Decltype,
+                "new" | "delete" => return ConstructCategory::NewDelete,
+                "asm" | "__asm__" | "__asm" => return ConstructCategory::InlineAssembly,
+                "static_assert" => return ConstructCategory::StaticAssert,
+                "goto" => return ConstructCategory::Labels,
+                _ => {}
+            },
+            Token::Operator(op) if *op == "..." => return ConstructCategory::PackExpansion,
+            Token::Operator(op) if *op == "." && matches!(tokens.get(i + 1), Some(Token::Operator(o)) if *o == ".") => {
+                return ConstructCategory::PackExpansion;
+            }
+            _ => {}
+        }
+    }
+    ConstructCategory::Other
+}
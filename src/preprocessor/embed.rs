@@ -0,0 +1,125 @@
+//! `#embed`: a file's bytes as a comma-separated list of integers.
+//!
+//! The bytes become a single `Token::Embed` rather than one literal and one
+//! comma each, so embedding a large asset costs its size and no more. The
+//! C23 parameters `limit(n)`, `prefix(...)`, `suffix(...)` and
+//! `if_empty(...)` are supported, also spelled `__limit__` and so on.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::lexer::token::{Span, SpannedToken, Token};
+use crate::preprocessor::{expr, PreprocessError, Preprocessor};
+use crate::vfs::normalize;
+
+/// The parameters after the file name.
+#[derive(Default)]
+struct Parameters {
+    limit: Option<usize>,
+    prefix: Vec<SpannedToken>,
+    suffix: Vec<SpannedToken>,
+    if_empty: Vec<SpannedToken>,
+}
+
+impl Preprocessor<'_> {
+    pub(super) fn embed(&mut self, directive: Span, line: &[SpannedToken]) {
+        let Some((name, angled, end)) = self.header_name(line) else {
+            let span = line.first().map_or(directive, |t| t.span);
+            self.error(PreprocessError::ExpectedHeaderName, span);
+            return;
+        };
+        let span = Span::new(directive.start, end);
+        let rest: Vec<SpannedToken> = line.iter().filter(|t| t.span.start >= end).cloned().collect();
+        let Some(params) = self.embed_parameters(&rest) else { return };
+        let contents = self.resolve_embed(&name, angled).and_then(|path| Some((self.session.fs.read(&path).ok()?, path)));
+        let Some((mut bytes, path)) = contents else {
+            self.error(PreprocessError::FileNotFound(name), span);
+            return;
+        };
+        if !self.embedded.contains(&path) { self.embedded.push(path); }
+        if let Some(limit) = params.limit { bytes.truncate(limit); }
+        if bytes.is_empty() {
+            self.out.extend(params.if_empty);
+            return;
+        }
+        self.out.extend(params.prefix);
+        self.out.push(SpannedToken { token: Token::Embed(Arc::from(bytes)), span });
+        self.out.extend(params.suffix);
+    }
+
+    /// Parses `name(tokens)` parameters, reporting the first bad one.
+    fn embed_parameters(&mut self, tokens: &[SpannedToken]) -> Option<Parameters> {
+        let mut params = Parameters::default();
+        let mut i = 0;
+        while i < tokens.len() {
+            let Token::Identifier(word) = &tokens[i].token else {
+                self.error(PreprocessError::InvalidEmbedParameter(tokens[i].token.spelling()), tokens[i].span);
+                return None;
+            };
+            let start = tokens[i].span;
+            let mut name = word.to_string();
+            i += 1;
+            // A vendor parameter, like `clang::offset`.
+            let colon = |t: Option<&SpannedToken>| t.is_some_and(|t| t.token == Token::Operator(":"));
+            if colon(tokens.get(i)) && colon(tokens.get(i + 1)) {
+                if let Some(Token::Identifier(rest)) = tokens.get(i + 2).map(|t| &t.token) { name = format!("{}::{}", name, rest); }
+                i += 3;
+            }
+            if tokens.get(i).map(|t| &t.token) != Some(&Token::Punct('(')) {
+                self.error(PreprocessError::InvalidEmbedParameter(name), start);
+                return None;
+            }
+            // The balanced tokens up to the matching `)`.
+            let open = i;
+            let mut depth = 0;
+            loop {
+                match tokens.get(i).map(|t| &t.token) {
+                    Some(Token::Punct('(')) => depth += 1,
+                    Some(Token::Punct(')')) => depth -= 1,
+                    Some(_) => {}
+                    None => {
+                        self.error(PreprocessError::InvalidEmbedParameter(name), start);
+                        return None;
+                    }
+                }
+                i += 1;
+                if depth == 0 { break; }
+            }
+            let clause = tokens[open + 1..i - 1].to_vec();
+            let close = tokens[i - 1].span;
+            match name.trim_start_matches("__").trim_end_matches("__") {
+                "limit" => {
+                    let value = self.expand_condition(&clause).and_then(|t| expr::evaluate(&t, close, self.session.exec_charsets()));
+                    match value {
+                        Ok(n) if n >= 0 => params.limit = Some(n as usize),
+                        Ok(n) => {
+                            self.error(PreprocessError::InvalidExpression(format!("embed limit {} is negative", n)), start);
+                            return None;
+                        }
+                        Err(e) => {
+                            self.error(PreprocessError::InvalidExpression(e.message), e.span);
+                            return None;
+                        }
+                    }
+                }
+                "prefix" => params.prefix = clause,
+                "suffix" => params.suffix = clause,
+                "if_empty" => params.if_empty = clause,
+                _ => {
+                    self.error(PreprocessError::InvalidEmbedParameter(name), start);
+                    return None;
+                }
+            }
+        }
+        Some(params)
+    }
+
+    /// Finds the file an `#embed` names: a quoted name next to the
+    /// embedding file first, then in the `--embed-dir` directories.
+    fn resolve_embed(&self, name: &str, angled: bool) -> Option<PathBuf> {
+        let current = self.stack.last()?.file;
+        let here = self.session.sources.file(current).path.parent().map(Path::to_path_buf);
+        let dirs = (!angled).then_some(here).flatten().into_iter().chain(self.session.options.embed_dirs.iter().cloned());
+        dirs.map(|dir| normalize(&dir.join(name))).find(|p| self.session.fs.is_file(p))
+    }
+}
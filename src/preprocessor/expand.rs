@@ -0,0 +1,337 @@
+//! Macro expansion.
+//!
+//! This is Prosser's algorithm: every token carries a hide set, the names
+//! of the macros whose expansion produced it, and a name in its own hide
+//! set is never expanded again. Expansion results are pushed back onto the
+//! input, so a function-like macro name at the end of a body can take its
+//! arguments from the source text that follows.
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use crate::diagnostics::{Diagnostic, Label, Warning};
+use crate::intern::Symbol;
+use crate::lexer::token::{Span, SpannedToken, Token};
+use crate::lexer::Lexer;
+use crate::preprocessor::macros::{BuiltinMacro, Macro};
+use crate::preprocessor::{PreprocessError, Preprocessor};
+
+/// A token during expansion.
+#[derive(Debug, Clone)]
+pub(crate) struct PpToken {
+    pub token: SpannedToken,
+    pub hide: Rc<Vec<Symbol>>,
+}
+
+impl PpToken {
+    pub fn new(token: SpannedToken) -> Self { Self { token, hide: Rc::default() } }
+}
+
+/// Supplies tokens following the expansion input, or `None` at the end of
+/// what may be used (end of a directive line, or a directive in the file).
+pub(crate) type More<'a, 's> = dyn FnMut(&mut Preprocessor<'s>) -> Option<SpannedToken> + 'a;
+
+impl<'s> Preprocessor<'s> {
+    /// Expands `input` completely into `out`, pulling from `more` when a
+    /// function-like macro name needs its arguments.
+    pub(crate) fn expand(&mut self, input: &mut VecDeque<PpToken>, more: &mut More<'_, 's>, out: &mut Vec<PpToken>) {
+        while let Some(t) = input.pop_front() {
+            let Token::Identifier(name) = &t.token.token else { out.push(t); continue };
+            let Some(m) = self.macros.get(*name).filter(|_| !t.hide.contains(name)).cloned() else {
+                out.push(t);
+                continue;
+            };
+            if let Some(builtin) = m.builtin {
+                if let Some(stats) = &mut self.stats { stats.record(chain(&t.hide, m.name), 1, t.token.span); }
+                let token = self.builtin_value(builtin, t.token.span);
+                out.push(PpToken { token: SpannedToken { token, span: t.token.span }, hide: t.hide });
+                continue;
+            }
+            let (args, span, hide) = if m.is_function_like() {
+                let next = input.pop_front().or_else(|| more(self).map(PpToken::new));
+                match next {
+                    Some(n) if n.token.token == Token::Punct('(') => {}
+                    other => {
+                        // A function-like macro name without `(` is an ordinary identifier.
+                        out.push(t);
+                        if let Some(n) = other { input.push_front(n); }
+                        continue;
+                    }
+                }
+                let Some((args, rparen)) = self.collect_args(&m, &t, input, more) else { continue };
+                let span = if rparen.token.span.end >= t.token.span.start {
+                    Span::new(t.token.span.start, rparen.token.span.end)
+                } else {
+                    t.token.span
+                };
+                let hide: Vec<Symbol> = t.hide.iter().filter(|h| rparen.hide.contains(h)).cloned().collect();
+                (args, span, hide)
+            } else {
+                (Vec::new(), t.token.span, (*t.hide).clone())
+            };
+            let body = self.substitute(&m, args, span);
+            if let Some(stats) = &mut self.stats { stats.record(chain(&hide, m.name), body.len(), span); }
+            let mut hide = hide;
+            hide.push(m.name);
+            let hide = Rc::new(hide);
+            for mut b in body.into_iter().rev() {
+                b.hide = if b.hide.is_empty() {
+                    hide.clone()
+                } else {
+                    let mut h = (*b.hide).clone();
+                    h.extend(hide.iter().filter(|n| !b.hide.contains(n)).cloned());
+                    Rc::new(h)
+                };
+                input.push_front(b);
+            }
+        }
+    }
+
+    /// Expands `tokens` on their own, as for a macro argument or a
+    /// directive line.
+    pub(crate) fn expand_all(&mut self, tokens: Vec<PpToken>) -> Vec<PpToken> {
+        let mut input = VecDeque::from(tokens);
+        let mut out = Vec::new();
+        self.expand(&mut input, &mut |_| None, &mut out);
+        out
+    }
+
+    /// Reads the arguments of an invocation of `m` whose `(` was consumed.
+    /// Returns them (unexpanded) with the closing `)`, or `None` after
+    /// reporting an error.
+    fn collect_args(&mut self, m: &Macro, name: &PpToken, input: &mut VecDeque<PpToken>, more: &mut More<'_, 's>)
+        -> Option<(Vec<Vec<PpToken>>, PpToken)>
+    {
+        let named = m.params.as_ref().map_or(0, Vec::len);
+        let slots = named + m.variadic as usize;
+        let mut args: Vec<Vec<PpToken>> = vec![Vec::new()];
+        let mut depth = 0;
+        let rparen = loop {
+            let Some(t) = input.pop_front().or_else(|| more(self).map(PpToken::new)) else {
+                self.error(PreprocessError::UnterminatedInvocation(m.name.to_string()), name.token.span);
+                return None;
+            };
+            match t.token.token {
+                Token::Punct('(') => depth += 1,
+                Token::Punct(')') if depth == 0 => break t,
+                Token::Punct(')') => depth -= 1,
+                // Commas past the named parameters belong to `__VA_ARGS__`.
+                Token::Punct(',') if depth == 0 && !(m.variadic && args.len() == slots) => {
+                    args.push(Vec::new());
+                    continue;
+                }
+                _ => {}
+            }
+            args.last_mut().expect("starts non-empty").push(t);
+        };
+        // `F()` passes no arguments to a macro without parameters.
+        if slots == 0 && args.len() == 1 && args[0].is_empty() { args.clear(); }
+        if m.variadic && args.len() == named { args.push(Vec::new()); }
+        if args.len() != slots {
+            let error = PreprocessError::ArgumentCount { name: m.name.to_string(), expected: named, given: args.len() };
+            self.error(error, Span::new(name.token.span.start, rparen.token.span.end));
+            return None;
+        }
+        Some((args, rparen))
+    }
+
+    /// The body of `m` with its parameters replaced and `#`/`##` applied.
+    /// Body tokens take `span`, the invocation; argument tokens keep theirs.
+    fn substitute(&mut self, m: &Macro, args: Vec<Vec<PpToken>>, span: Span) -> Vec<PpToken> {
+        let param = |t: &SpannedToken| match &t.token {
+            Token::Identifier(n) => m.param_index(*n),
+            _ => None,
+        };
+        let is_op = |t: Option<&SpannedToken>, op: &str| matches!(t, Some(SpannedToken { token: Token::Operator(o), .. }) if *o == op);
+        let va_args = m.variadic.then(|| m.params.as_ref().map_or(0, Vec::len));
+        let body = match va_args {
+            Some(idx) => va_opt(&m.body, args[idx].is_empty()),
+            None => Cow::Borrowed(&m.body[..]),
+        };
+        let mut out: Vec<PpToken> = Vec::with_capacity(body.len());
+        // The left operand of a pending `##` was an empty argument.
+        let mut placemarker = false;
+        let mut i = 0;
+        while i < body.len() {
+            let b = &body[i];
+            if m.is_function_like() && is_op(Some(b), "#") {
+                if let Some(idx) = body.get(i + 1).and_then(param) {
+                    let text = stringify(&args[idx]);
+                    out.push(PpToken::new(SpannedToken { token: Token::StringLiteral(text.into()), span }));
+                    i += 2;
+                    continue;
+                }
+            }
+            // GNU `, ## __VA_ARGS__`: the comma goes if there are no
+            // variable arguments, and nothing is pasted if there are.
+            if is_op(Some(b), "##") && i > 0 && body[i - 1].token == Token::Punct(',') && body.get(i + 1).and_then(param).is_some_and(|idx| Some(idx) == va_args) {
+                let rest = &args[va_args.expect("checked")];
+                if rest.is_empty() { out.pop(); } else { out.extend(rest.iter().cloned()); }
+                i += 2;
+                continue;
+            }
+            if is_op(Some(b), "##") && i + 1 < body.len() {
+                let rhs = match param(&body[i + 1]) {
+                    Some(idx) => args[idx].clone(),
+                    None => vec![PpToken::new(SpannedToken { token: body[i + 1].token.clone(), span })],
+                };
+                i += 2;
+                let mut rhs = rhs.into_iter();
+                let Some(first) = rhs.next() else { continue };
+                let lhs = if std::mem::take(&mut placemarker) { None } else { out.pop() };
+                match lhs {
+                    Some(lhs) => out.extend(self.paste(lhs, first)),
+                    None => out.push(first),
+                }
+                out.extend(rhs);
+                continue;
+            }
+            if let Some(idx) = param(b) {
+                if is_op(body.get(i + 1), "##") {
+                    placemarker = args[idx].is_empty();
+                    out.extend(args[idx].iter().cloned());
+                } else {
+                    out.extend(self.expand_all(args[idx].clone()));
+                }
+                i += 1;
+                continue;
+            }
+            out.push(PpToken::new(SpannedToken { token: b.token.clone(), span }));
+            i += 1;
+        }
+        out
+    }
+
+    /// Joins two tokens with `##`. If they do not spell a single token
+    /// that is reported, and both are kept as they were.
+    fn paste(&mut self, lhs: PpToken, rhs: PpToken) -> Vec<PpToken> {
+        let text = lhs.token.token.spelling() + &rhs.token.token.spelling();
+        // The result is never a directive, even if it starts with `#`.
+        let mut lexer = Lexer::new(&text).mid_line();
+        let token = match (lexer.next_token(), lexer.next_token()) {
+            (Ok(t), Ok(SpannedToken { token: Token::Eof, .. })) => Some(t.token),
+            _ => None,
+        };
+        let span = lhs.token.span;
+        match token {
+            Some(token) => {
+                let mut hide = (*lhs.hide).clone();
+                hide.extend(rhs.hide.iter().filter(|n| !lhs.hide.contains(n)).cloned());
+                vec![PpToken { token: SpannedToken { token, span }, hide: Rc::new(hide) }]
+            }
+            None => {
+                self.error(PreprocessError::InvalidPaste(text), span);
+                vec![lhs, rhs]
+            }
+        }
+    }
+
+    /// The value of `__FILE__` and friends used at `at`.
+    fn builtin_value(&mut self, builtin: BuiltinMacro, at: Span) -> Token {
+        match builtin {
+            BuiltinMacro::File => {
+                let path = self.session.sources.presumed_location(at.start).map(|l| l.path.display().to_string());
+                Token::StringLiteral(path.unwrap_or_default().into())
+            }
+            BuiltinMacro::Line => {
+                let line = self.session.sources.presumed_location(at.start).map_or(0, |l| l.line);
+                Token::Number(line.to_string())
+            }
+            BuiltinMacro::Date => Token::StringLiteral(self.date.as_str().into()),
+            BuiltinMacro::Time => Token::StringLiteral(self.time.as_str().into()),
+            BuiltinMacro::Counter => {
+                self.counter += 1;
+                Token::Number((self.counter - 1).to_string())
+            }
+        }
+    }
+
+    /// Reports a macro redefinition that changes the definition.
+    pub(crate) fn warn_redefinition(&mut self, name: &str, at: Span, previous: Span) {
+        let mut diag = Diagnostic::warning(format!("`{}` macro redefined", name)).with_warning(Warning::MacroRedefined).with_label(Label::primary(at, ""));
+        if previous != Span::default() {
+            diag = diag.with_label(Label::secondary(previous, "previous definition is here"));
+        }
+        self.session.diagnostics.emit(diag);
+    }
+}
+
+/// `body` with each `__VA_OPT__(tokens)` replaced by its tokens, or by
+/// nothing if the variable arguments are `empty`. Nothing also drops a
+/// `##` next to it, as an empty argument would be.
+fn va_opt(body: &[SpannedToken], empty: bool) -> Cow<'_, [SpannedToken]> {
+    let is = |t: Option<&SpannedToken>, token: &Token| t.is_some_and(|t| t.token == *token);
+    if !body.iter().any(|t| matches!(&t.token, Token::Identifier(n) if *n == "__VA_OPT__")) { return Cow::Borrowed(body); }
+    let mut out = Vec::with_capacity(body.len());
+    let mut i = 0;
+    while i < body.len() {
+        let t = &body[i];
+        let close = match &t.token {
+            Token::Identifier(n) if *n == "__VA_OPT__" && is(body.get(i + 1), &Token::Punct('(')) => {
+                let mut depth = 0;
+                body[i + 1..].iter().position(|t| {
+                    match t.token {
+                        Token::Punct('(') => depth += 1,
+                        Token::Punct(')') => depth -= 1,
+                        _ => {}
+                    }
+                    depth == 0
+                })
+            }
+            _ => None,
+        };
+        let Some(close) = close.map(|n| i + 1 + n) else {
+            out.push(t.clone());
+            i += 1;
+            continue;
+        };
+        if !empty {
+            out.extend_from_slice(&body[i + 2..close]);
+        } else if is(out.last(), &Token::Operator("##")) {
+            out.pop();
+        } else if is(body.get(close + 1), &Token::Operator("##")) {
+            i = close + 2;
+            continue;
+        }
+        i = close + 1;
+    }
+    Cow::Owned(out)
+}
+
+/// The chain of macros expanding `name`, a token with hide set `hide`.
+fn chain(hide: &[Symbol], name: Symbol) -> Vec<String> {
+    hide.iter().chain([&name]).map(|n| n.to_string()).collect()
+}
+
+/// The text of `#arg`: the argument's spelling, with one space wherever its
+/// tokens were separated.
+fn stringify(arg: &[PpToken]) -> String {
+    let mut text = String::new();
+    for (i, t) in arg.iter().enumerate() {
+        if i > 0 && arg[i - 1].token.span.end != t.token.span.start { text.push(' '); }
+        text.push_str(&t.token.token.spelling());
+    }
+    text
+}
+
+/// `__DATE__` and `__TIME__` for `secs` since the Unix epoch, in UTC:
+/// `"Oct 16 2026"` and `"09:05:00"`.
+pub(crate) fn date_time(secs: u64) -> (String, String) {
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    // Civil-from-days, after Howard Hinnant's date algorithms.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    let date = format!("{} {:>2} {}", MONTHS[(month - 1) as usize], day, year);
+    let time = format!("{:02}:{:02}:{:02}", rem / 3600, rem / 60 % 60, rem % 60);
+    (date, time)
+}
@@ -0,0 +1,249 @@
+//! Evaluation of `#if`/`#elif` controlling expressions.
+//!
+//! The input has already had `defined` operators and macros replaced, so
+//! any identifier left over evaluates to 0 (except `true`). As [cpp.cond]
+//! says, signed values are `intmax_t` and unsigned ones `uintmax_t`, both
+//! 64 bits here, and the usual arithmetic conversions make an operation
+//! unsigned if either operand is. Arithmetic wraps instead of
+//! overflowing.
+
+use crate::charset::ExecCharsets;
+use crate::lexer::token::{Encoding, Span, SpannedToken, Token};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExprError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl ExprError {
+    fn new(message: impl Into<String>, span: Span) -> Self { Self { message: message.into(), span } }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Num(Value),
+    Op(String),
+}
+
+/// A value of `intmax_t`, or of `uintmax_t` if `unsigned`, as its bits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Value {
+    bits: i64,
+    unsigned: bool,
+}
+
+impl Value {
+    fn signed(bits: i64) -> Self { Self { bits, unsigned: false } }
+
+    fn bool(b: bool) -> Self { Self::signed(b as i64) }
+
+    fn is_true(self) -> bool { self.bits != 0 }
+}
+
+/// Evaluates `tokens`, with character literals in `charsets`; `end` is
+/// where errors at end of line point. An unsigned value is returned as its
+/// bits.
+pub fn evaluate(tokens: &[SpannedToken], end: Span, charsets: ExecCharsets) -> Result<i64, ExprError> {
+    let toks = convert(tokens, charsets)?;
+    if toks.is_empty() { return Err(ExprError::new("expected value in expression", end)); }
+    let mut parser = Parser { toks, pos: 0, end };
+    let value = parser.conditional(true)?;
+    match parser.toks.get(parser.pos) {
+        None => Ok(value.bits),
+        Some((_, span)) => Err(ExprError::new("unexpected token in preprocessor expression", *span)),
+    }
+}
+
+/// Turns tokens into numbers and operator strings, joining the `<`/`>`
+/// puncts the lexer keeps separate for templates into `<<`, `<=`, etc.
+fn convert(tokens: &[SpannedToken], charsets: ExecCharsets) -> Result<Vec<(Tok, Span)>, ExprError> {
+    let mut out: Vec<(Tok, Span)> = Vec::with_capacity(tokens.len());
+    for t in tokens {
+        let tok = match &t.token {
+            Token::Number(text) => {
+                let n = parse_int(text).ok_or_else(|| ExprError::new(format!("invalid integer constant `{}` in preprocessor expression", text), t.span))?;
+                // One too large for `intmax_t` is a `uintmax_t`.
+                let unsigned = text.contains(['u', 'U']) || n > i64::MAX as u64;
+                Tok::Num(Value { bits: n as i64, unsigned })
+            }
+            Token::CharLiteral(c) => {
+                let value = c.char_value(charsets).map_err(|ch| ExprError::new(format!("character `{}` cannot be encoded in the execution character set {}", ch, charsets.charset(c.encoding)), t.span))?;
+                // `char` and a 32-bit `wchar_t` are signed, the other
+                // character types unsigned.
+                let signed = match c.encoding {
+                    Encoding::Ordinary => true,
+                    Encoding::Wide => charsets.wide.unit_size() == 4,
+                    Encoding::Utf8 | Encoding::Utf16 | Encoding::Utf32 => false,
+                };
+                Tok::Num(Value { bits: value as i64, unsigned: !signed })
+            }
+            Token::Identifier(name) => Tok::Num(Value::bool(name == "true")),
+            Token::Punct(c @ ('(' | ')' | '<' | '>')) => {
+                if let Some((Tok::Op(prev), span)) = out.last_mut() {
+                    if span.end == t.span.start && matches!((prev.as_str(), c), ("<", '<') | (">", '>')) {
+                        prev.push(*c);
+                        span.end = t.span.end;
+                        continue;
+                    }
+                }
+                Tok::Op(c.to_string())
+            }
+            Token::Operator(op) => {
+                if let Some((Tok::Op(prev), span)) = out.last_mut() {
+                    if span.end == t.span.start && *op == "=" && matches!(prev.as_str(), "<" | ">") {
+                        prev.push('=');
+                        span.end = t.span.end;
+                        continue;
+                    }
+                }
+                Tok::Op(op.to_string())
+            }
+            other => return Err(ExprError::new(format!("unexpected {} in preprocessor expression", other.kind()), t.span)),
+        };
+        out.push((tok, t.span));
+    }
+    Ok(out)
+}
+
+/// Parses an integer literal with optional `0x`/`0b`/octal prefix, digit
+/// separators and `u`/`l`/`z` suffixes. Also used by the parser.
+pub(crate) fn parse_int(text: &str) -> Option<u64> {
+    let text = text.replace('\'', "");
+    let digits = text.trim_end_matches(['u', 'U', 'l', 'L', 'z', 'Z']);
+    let (radix, digits) = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        (16, hex)
+    } else if let Some(bin) = digits.strip_prefix("0b").or_else(|| digits.strip_prefix("0B")) {
+        (2, bin)
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        (8, &digits[1..])
+    } else {
+        (10, digits)
+    };
+    u64::from_str_radix(digits, radix).ok()
+}
+
+fn precedence(op: &str) -> Option<u8> {
+    Some(match op {
+        "||" => 1,
+        "&&" => 2,
+        "|" => 3,
+        "^" => 4,
+        "&" => 5,
+        "==" | "!=" => 6,
+        "<" | ">" | "<=" | ">=" => 7,
+        "<<" | ">>" => 8,
+        "+" | "-" => 9,
+        "*" | "/" | "%" => 10,
+        _ => return None,
+    })
+}
+
+struct Parser {
+    toks: Vec<(Tok, Span)>,
+    pos: usize,
+    end: Span,
+}
+
+impl Parser {
+    fn peek_op(&self) -> Option<&str> {
+        match self.toks.get(self.pos) {
+            Some((Tok::Op(op), _)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn span(&self) -> Span { self.toks.get(self.pos).map_or(self.end, |(_, s)| *s) }
+
+    fn expect(&mut self, op: &str) -> Result<(), ExprError> {
+        if self.peek_op() == Some(op) { self.pos += 1; return Ok(()); }
+        Err(ExprError::new(format!("expected `{}` in preprocessor expression", op), self.span()))
+    }
+
+    /// `cond ? a : b`. `live` is false in operands that short-circuiting
+    /// discards, where division by zero is not an error.
+    fn conditional(&mut self, live: bool) -> Result<Value, ExprError> {
+        let cond = self.binary(0, live)?;
+        if self.peek_op() != Some("?") { return Ok(cond); }
+        self.pos += 1;
+        let a = self.conditional(live && cond.is_true())?;
+        self.expect(":")?;
+        let b = self.conditional(live && !cond.is_true())?;
+        let unsigned = a.unsigned || b.unsigned;
+        Ok(Value { unsigned, ..if cond.is_true() { a } else { b } })
+    }
+
+    fn binary(&mut self, min: u8, live: bool) -> Result<Value, ExprError> {
+        let mut lhs = self.unary(live)?;
+        while let Some(op) = self.peek_op() {
+            let Some(prec) = precedence(op) else { break };
+            if prec <= min { break; }
+            let op = op.to_string();
+            let span = self.span();
+            self.pos += 1;
+            let rhs_live = match op.as_str() {
+                "&&" => live && lhs.is_true(),
+                "||" => live && !lhs.is_true(),
+                _ => live,
+            };
+            let rhs = self.binary(prec, rhs_live)?;
+            // Shifts have the left operand's type; the others convert
+            // both to a common one.
+            let unsigned = lhs.unsigned || rhs.unsigned;
+            let (l, r) = (lhs.bits, rhs.bits);
+            let (ul, ur) = (l as u64, r as u64);
+            let value = |bits: i64| Value { bits, unsigned };
+            lhs = match op.as_str() {
+                "||" => Value::bool(lhs.is_true() || rhs.is_true()),
+                "&&" => Value::bool(lhs.is_true() && rhs.is_true()),
+                "|" => value(l | r),
+                "^" => value(l ^ r),
+                "&" => value(l & r),
+                "==" => Value::bool(l == r),
+                "!=" => Value::bool(l != r),
+                "<" => Value::bool(if unsigned { ul < ur } else { l < r }),
+                ">" => Value::bool(if unsigned { ul > ur } else { l > r }),
+                "<=" => Value::bool(if unsigned { ul <= ur } else { l <= r }),
+                ">=" => Value::bool(if unsigned { ul >= ur } else { l >= r }),
+                "<<" => Value { bits: l.checked_shl(r as u32).unwrap_or(0), ..lhs },
+                ">>" if lhs.unsigned => Value { bits: ul.checked_shr(r as u32).unwrap_or(0) as i64, ..lhs },
+                ">>" => Value { bits: l.checked_shr(r as u32).unwrap_or(0), ..lhs },
+                "+" => value(l.wrapping_add(r)),
+                "-" => value(l.wrapping_sub(r)),
+                "*" => value(l.wrapping_mul(r)),
+                _ if r == 0 => {
+                    if live { return Err(ExprError::new("division by zero in preprocessor expression", span)); }
+                    value(0)
+                }
+                "/" if unsigned => value((ul / ur) as i64),
+                "/" => value(l.wrapping_div(r)),
+                _ if unsigned => value((ul % ur) as i64),
+                _ => value(l.wrapping_rem(r)),
+            };
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self, live: bool) -> Result<Value, ExprError> {
+        let span = self.span();
+        match self.toks.get(self.pos).cloned() {
+            Some((Tok::Num(n), _)) => { self.pos += 1; Ok(n) }
+            Some((Tok::Op(op), _)) => {
+                self.pos += 1;
+                match op.as_str() {
+                    "(" => {
+                        let v = self.conditional(live)?;
+                        self.expect(")")?;
+                        Ok(v)
+                    }
+                    "+" => self.unary(live),
+                    "-" => self.unary(live).map(|v| Value { bits: v.bits.wrapping_neg(), ..v }),
+                    "~" => self.unary(live).map(|v| Value { bits: !v.bits, ..v }),
+                    "!" => Ok(Value::bool(!self.unary(live)?.is_true())),
+                    _ => Err(ExprError::new(format!("unexpected `{}` in preprocessor expression", op), span)),
+                }
+            }
+            None => Err(ExprError::new("expected value in expression", span)),
+        }
+    }
+}
@@ -0,0 +1,44 @@
+//! Headers built into the binary, so simple programs preprocess without a
+//! host toolchain installed, and the environment variable that adds
+//! default include directories.
+//!
+//! Built-in headers are searched after every include directory and appear
+//! under `<ruscom>/` in paths and diagnostics.
+
+use std::path::{Path, PathBuf};
+
+/// The directory built-in headers appear to live in.
+pub const BUILTIN_DIR: &str = "<ruscom>";
+
+/// Colon-separated (`;` on Windows) directories searched after `-isystem`
+/// ones, as system directories.
+pub const INCLUDE_PATH_VAR: &str = "RUSCOM_INCLUDE_PATH";
+
+const HEADERS: &[(&str, &str)] = &[
+    ("cstddef", include_str!("include/cstddef")),
+    ("cstdint", include_str!("include/cstdint")),
+    ("stddef.h", include_str!("include/stddef.h")),
+    ("stdint.h", include_str!("include/stdint.h")),
+];
+
+/// The path the built-in header `name` is registered under, if there is one.
+pub fn builtin_path(name: &str) -> Option<PathBuf> {
+    HEADERS.iter().any(|&(n, _)| n == name).then(|| Path::new(BUILTIN_DIR).join(name))
+}
+
+/// The contents of the built-in header at `path`, as returned by
+/// `builtin_path`.
+pub fn builtin_contents(path: &Path) -> Option<&'static str> {
+    let name = path.strip_prefix(BUILTIN_DIR).ok()?.to_str()?;
+    HEADERS.iter().find(|&&(n, _)| n == name).map(|&(_, contents)| contents)
+}
+
+/// Whether `path` is a built-in header.
+pub fn is_builtin(path: &Path) -> bool { path.starts_with(BUILTIN_DIR) }
+
+/// The directories named by `RUSCOM_INCLUDE_PATH`.
+pub fn include_dirs_from_env() -> Vec<PathBuf> {
+    std::env::var_os(INCLUDE_PATH_VAR)
+        .map(|v| std::env::split_paths(&v).filter(|p| !p.as_os_str().is_empty()).collect())
+        .unwrap_or_default()
+}
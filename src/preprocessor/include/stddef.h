@@ -0,0 +1,20 @@
+/* Built-in <stddef.h> shipped with RusCom for freestanding compilation. */
+#ifndef __RUSCOM_STDDEF_H
+#define __RUSCOM_STDDEF_H
+
+#if __SIZEOF_LONG__ == 8
+typedef unsigned long size_t;
+typedef long ptrdiff_t;
+#else
+typedef unsigned long long size_t;
+typedef long long ptrdiff_t;
+#endif
+typedef long double max_align_t;
+
+#ifndef NULL
+#define NULL 0
+#endif
+
+#define offsetof(type, member) __builtin_offsetof(type, member)
+
+#endif
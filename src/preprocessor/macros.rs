@@ -0,0 +1,141 @@
+//! Macro definitions recorded by `#define`.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::intern::Symbol;
+use crate::lexer::token::{Span, SpannedToken, Token};
+
+/// Macros whose value depends on where they are used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinMacro {
+    File,
+    Line,
+    Date,
+    Time,
+    Counter,
+}
+
+impl BuiltinMacro {
+    pub const ALL: [(&'static str, BuiltinMacro); 5] = [
+        ("__FILE__", BuiltinMacro::File),
+        ("__LINE__", BuiltinMacro::Line),
+        ("__DATE__", BuiltinMacro::Date),
+        ("__TIME__", BuiltinMacro::Time),
+        ("__COUNTER__", BuiltinMacro::Counter),
+    ];
+}
+
+/// One `#define`d macro.
+#[derive(Debug, Clone)]
+pub struct Macro {
+    pub name: Symbol,
+    /// Parameter names for a function-like macro; `None` if object-like.
+    pub params: Option<Vec<Symbol>>,
+    /// Whether the parameter list ends in `...`.
+    pub variadic: bool,
+    pub body: Vec<SpannedToken>,
+    /// The macro name in its `#define`.
+    pub span: Span,
+    /// Set for `__FILE__` and friends, whose body is computed per use.
+    pub builtin: Option<BuiltinMacro>,
+}
+
+impl Macro {
+    pub fn builtin(name: &str, kind: BuiltinMacro) -> Self {
+        Self { name: Symbol::intern(name), params: None, variadic: false, body: Vec::new(), span: Span::default(), builtin: Some(kind) }
+    }
+
+    pub fn is_function_like(&self) -> bool { self.params.is_some() }
+
+    /// Index of the parameter `name` names; `__VA_ARGS__` comes after the
+    /// named ones.
+    pub fn param_index(&self, name: Symbol) -> Option<usize> {
+        let params = self.params.as_ref()?;
+        if self.variadic && name == "__VA_ARGS__" { return Some(params.len()); }
+        params.iter().position(|&p| p == name)
+    }
+
+    /// Whether `other` is the same definition, which may be repeated
+    /// without a diagnostic.
+    pub fn same_definition(&self, other: &Macro) -> bool {
+        self.params == other.params
+            && self.builtin == other.builtin
+            && self.variadic == other.variadic
+            && self.body.len() == other.body.len()
+            && self.body.iter().zip(&other.body).all(|(a, b)| a.token == b.token)
+    }
+}
+
+/// The macros defined at some point during preprocessing.
+#[derive(Debug, Clone, Default)]
+pub struct MacroTable {
+    macros: HashMap<Symbol, Rc<Macro>>,
+}
+
+impl MacroTable {
+    pub fn new() -> Self { Self::default() }
+
+    /// Defines `m`, returning the definition it replaced.
+    pub fn define(&mut self, m: Macro) -> Option<Rc<Macro>> { self.macros.insert(m.name, Rc::new(m)) }
+
+    pub fn undefine(&mut self, name: Symbol) -> Option<Rc<Macro>> { self.macros.remove(&name) }
+
+    pub fn get(&self, name: Symbol) -> Option<&Rc<Macro>> { self.macros.get(&name) }
+
+    pub fn is_defined(&self, name: Symbol) -> bool { self.macros.contains_key(&name) }
+
+    pub fn len(&self) -> usize { self.macros.len() }
+
+    pub fn is_empty(&self) -> bool { self.macros.is_empty() }
+}
+
+/// Why a `#define` line was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefineError {
+    MissingName,
+    BadParameterList,
+}
+
+/// Parses the tokens after `#define` into a macro. A `(` directly after
+/// the name (no whitespace) makes it function-like.
+pub fn parse_define(line: &[SpannedToken]) -> Result<Macro, (DefineError, Span)> {
+    let Some(first) = line.first() else {
+        return Err((DefineError::MissingName, Span::default()));
+    };
+    let Token::Identifier(name) = &first.token else {
+        return Err((DefineError::MissingName, first.span));
+    };
+    let mut rest = &line[1..];
+    let mut params = None;
+    let mut variadic = false;
+    if let Some(open) = rest.first().filter(|t| t.token == Token::Punct('(') && t.span.start == first.span.end) {
+        let mut names = Vec::new();
+        let mut i = 1;
+        let bad = |i: usize| (DefineError::BadParameterList, rest.get(i).map_or(open.span, |t: &SpannedToken| t.span));
+        loop {
+            match rest.get(i).map(|t| &t.token) {
+                Some(Token::Punct(')')) if names.is_empty() && !variadic => { i += 1; break; }
+                Some(Token::Identifier(p)) if !variadic => { names.push(*p); i += 1; }
+                Some(Token::Operator(op)) if *op == "." && is_ellipsis(&rest[i..]) && !variadic => { variadic = true; i += 3; }
+                _ => return Err(bad(i)),
+            }
+            match rest.get(i).map(|t| &t.token) {
+                Some(Token::Punct(',')) if !variadic => i += 1,
+                Some(Token::Punct(')')) => { i += 1; break; }
+                _ => return Err(bad(i)),
+            }
+        }
+        params = Some(names);
+        rest = &rest[i..];
+    }
+    Ok(Macro { name: *name, params, variadic, body: rest.to_vec(), span: first.span, builtin: None })
+}
+
+/// Whether `tokens` start with three adjacent `.`s.
+fn is_ellipsis(tokens: &[SpannedToken]) -> bool {
+    tokens.len() >= 3
+        && tokens[..3].iter().all(|t| t.token == Token::Operator("."))
+        && tokens[0].span.end == tokens[1].span.start
+        && tokens[1].span.end == tokens[2].span.start
+}
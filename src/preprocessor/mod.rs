@@ -0,0 +1,743 @@
+//! The preprocessor: turns a file's raw tokens into the token stream later
+//! phases see, splicing in the tokens of every `#include`d header and
+//! dropping groups excluded by `#if` and friends.
+//!
+//! Each header is registered with the session's `SourceManager`, so tokens
+//! keep spans into the file they were written in, and the `#include` that
+//! pulled a header in is recorded as its `included_from` span.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::cancel::Cancelled;
+use crate::diagnostics::{Diagnostic, Label, Warning};
+use crate::intern::Symbol;
+use crate::lexer::token::{Span, SpannedLexError, SpannedToken, Token};
+use crate::lexer::Lexer;
+use crate::session::{Session, Standard, CANCEL_CHECK_INTERVAL};
+use crate::source::FileId;
+use crate::vfs::normalize;
+
+mod embed;
+mod expand;
+pub mod expr;
+pub mod headers;
+pub mod macros;
+pub mod print;
+pub mod stats;
+
+use expand::PpToken;
+use macros::{BuiltinMacro, DefineError, Macro, MacroTable};
+use stats::MacroStats;
+
+/// An error in a preprocessing directive. Reported as a diagnostic; the
+/// offending directive is dropped and preprocessing continues.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PreprocessError {
+    /// No include directory contains the header.
+    FileNotFound(String),
+    /// The header is already being included further up the stack.
+    IncludeCycle(String),
+    /// `#include` or `#embed` not followed by `"file"` or `<file>`.
+    ExpectedHeaderName,
+    /// An `#if` without its `#endif` by the end of the file.
+    UnterminatedConditional,
+    /// `#else`, `#elif` or `#endif` in the wrong place; holds the message.
+    MisplacedConditional(&'static str),
+    InvalidDirective(String),
+    /// Bad `#if` expression; holds the evaluator's message.
+    InvalidExpression(String),
+    MacroNameMissing,
+    BadMacroParameters,
+    /// `#error`, with its text.
+    ErrorDirective(String),
+    /// A function-like macro's argument list runs past the end of the
+    /// file or into a directive.
+    UnterminatedInvocation(String),
+    ArgumentCount { name: String, expected: usize, given: usize },
+    /// `##` formed text that is not one token.
+    InvalidPaste(String),
+    /// An `#embed` parameter that is unknown or has no `(...)`.
+    InvalidEmbedParameter(String),
+    /// A `#line` directive or line marker that is not a line number and
+    /// an optional file name; holds the message.
+    InvalidLineDirective(&'static str),
+}
+
+impl PreprocessError {
+    /// Stable diagnostic code for this error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            PreprocessError::FileNotFound(_) => "E0101",
+            PreprocessError::IncludeCycle(_) => "E0102",
+            PreprocessError::ExpectedHeaderName => "E0103",
+            PreprocessError::UnterminatedConditional => "E0104",
+            PreprocessError::MisplacedConditional(_) => "E0105",
+            PreprocessError::InvalidDirective(_) => "E0106",
+            PreprocessError::InvalidExpression(_) => "E0107",
+            PreprocessError::MacroNameMissing => "E0108",
+            PreprocessError::BadMacroParameters => "E0109",
+            PreprocessError::ErrorDirective(_) => "E0110",
+            PreprocessError::UnterminatedInvocation(_) => "E0111",
+            PreprocessError::ArgumentCount { .. } => "E0112",
+            PreprocessError::InvalidPaste(_) => "E0113",
+            PreprocessError::InvalidEmbedParameter(_) => "E0114",
+            PreprocessError::InvalidLineDirective(_) => "E0115",
+        }
+    }
+
+    fn to_diagnostic(&self, span: Span) -> Diagnostic {
+        Diagnostic::error(self.to_string()).with_code(self.code()).with_label(Label::primary(span, ""))
+    }
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreprocessError::FileNotFound(name) => write!(f, "`{}` file not found", name),
+            PreprocessError::IncludeCycle(name) => write!(f, "#include cycle: `{}` includes itself", name),
+            PreprocessError::ExpectedHeaderName => write!(f, "expected \"FILENAME\" or <FILENAME>"),
+            PreprocessError::UnterminatedConditional => write!(f, "unterminated conditional directive"),
+            PreprocessError::MisplacedConditional(msg) => write!(f, "{}", msg),
+            PreprocessError::InvalidDirective(name) => write!(f, "invalid preprocessing directive `#{}`", name),
+            PreprocessError::InvalidExpression(msg) => write!(f, "{}", msg),
+            PreprocessError::MacroNameMissing => write!(f, "macro name missing"),
+            PreprocessError::BadMacroParameters => write!(f, "invalid macro parameter list"),
+            PreprocessError::ErrorDirective(text) => write!(f, "#error {}", text),
+            PreprocessError::UnterminatedInvocation(name) => write!(f, "unterminated argument list invoking macro `{}`", name),
+            PreprocessError::ArgumentCount { name, expected, given } => {
+                let s = if *expected == 1 { "" } else { "s" };
+                write!(f, "macro `{}` requires {} argument{}, but {} given", name, expected, s, given)
+            }
+            PreprocessError::InvalidPaste(text) => write!(f, "pasting formed `{}`, an invalid preprocessing token", text),
+            PreprocessError::InvalidEmbedParameter(name) => write!(f, "invalid #embed parameter `{}`", name),
+            PreprocessError::InvalidLineDirective(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for PreprocessError {}
+
+/// The preprocessed token stream of one main file.
+#[derive(Debug)]
+pub struct PreprocessOutput {
+    pub file: FileId,
+    pub tokens: Vec<SpannedToken>,
+    /// Every file that contributed tokens, main file first, in the order
+    /// they were first entered.
+    pub files: Vec<FileId>,
+    /// Files read by `#embed`, in the order first embedded.
+    pub embedded: Vec<PathBuf>,
+    /// The macro expansions of the file and its headers, with
+    /// `Options::macro_stats`.
+    pub macro_stats: Option<MacroStats>,
+}
+
+/// One open `#if`/`#ifdef`/`#ifndef`.
+#[derive(Debug)]
+struct Conditional {
+    /// The directive that opened it.
+    span: Span,
+    /// Tokens in the current group are kept.
+    active: bool,
+    /// Some group so far was taken, so later `#elif`/`#else` are skipped.
+    taken: bool,
+    seen_else: bool,
+    /// The enclosing group is active; otherwise nothing here can be.
+    parent_active: bool,
+}
+
+/// Progress towards recognizing a whole-file `#ifndef X / #define X ...
+/// #endif` include guard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Guard {
+    /// Nothing but whitespace and comments yet.
+    Start,
+    /// Inside the `#ifndef X` group that opened the file.
+    Open(Symbol),
+    /// That group ended with `#endif`; nothing may follow.
+    Closed(Symbol),
+    NotGuarded,
+}
+
+/// A file being preprocessed.
+struct Frame {
+    file: FileId,
+    /// Open conditionals when the file was entered; those deeper are its own.
+    depth: usize,
+    guard: Guard,
+}
+
+pub struct Preprocessor<'s> {
+    session: &'s mut Session,
+    macros: MacroTable,
+    /// Files currently being preprocessed, outermost first.
+    stack: Vec<Frame>,
+    conditionals: Vec<Conditional>,
+    /// Include guard macro of each fully guarded file seen so far.
+    guards: HashMap<FileId, Symbol>,
+    /// Files marked `#pragma once`.
+    once: HashSet<FileId>,
+    files: Vec<FileId>,
+    embedded: Vec<PathBuf>,
+    out: Vec<SpannedToken>,
+    /// A directive, end of file or lex error that ended a macro's argument
+    /// search, to be handled next.
+    pushed_back: Option<Result<SpannedToken, SpannedLexError>>,
+    /// Values of `__DATE__` and `__TIME__`.
+    date: String,
+    time: String,
+    counter: u64,
+    /// Expansions after the predefined macros, if counted.
+    stats: Option<MacroStats>,
+}
+
+impl<'s> Preprocessor<'s> {
+    pub fn new(session: &'s mut Session) -> Self {
+        let mut macros = MacroTable::new();
+        for (name, kind) in BuiltinMacro::ALL { macros.define(Macro::builtin(name, kind)); }
+        // `SOURCE_DATE_EPOCH` pins the build time for reproducible builds.
+        let now = std::env::var("SOURCE_DATE_EPOCH").ok().and_then(|s| s.parse().ok()).unwrap_or_else(|| {
+            std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs())
+        });
+        let (date, time) = expand::date_time(now);
+        Self {
+            session,
+            macros,
+            stack: Vec::new(),
+            conditionals: Vec::new(),
+            guards: HashMap::new(),
+            once: HashSet::new(),
+            files: Vec::new(),
+            embedded: Vec::new(),
+            out: Vec::new(),
+            pushed_back: None,
+            date,
+            time,
+            counter: 0,
+            stats: None,
+        }
+    }
+
+    pub fn macros(&self) -> &MacroTable { &self.macros }
+
+    /// Preprocesses `file`, which must already be loaded into the session,
+    /// after the predefined macros and `-D`/`-U` options.
+    pub fn run(mut self, file: FileId) -> Result<PreprocessOutput, Cancelled> {
+        let predefines = predefines(self.session);
+        let builtin = self.session.sources.set_file("<built-in>", predefines);
+        self.process_file(builtin)?;
+        self.files.clear();
+        self.stats = self.session.options.macro_stats.then(MacroStats::default);
+        self.process_file(file)?;
+        Ok(PreprocessOutput { file, tokens: self.out, files: self.files, embedded: self.embedded, macro_stats: self.stats })
+    }
+
+    fn process_file(&mut self, file: FileId) -> Result<(), Cancelled> {
+        self.session.cancel.check()?;
+        if !self.files.contains(&file) { self.files.push(file); }
+        self.stack.push(Frame { file, depth: self.conditionals.len(), guard: Guard::Start });
+        let (src, base) = {
+            let f = self.session.sources.file(file);
+            (f.contents.clone(), f.start)
+        };
+        let mut lexer = Lexer::with_options(&src, self.session.options.lexer).with_base_offset(base);
+        let mut count = 0usize;
+        loop {
+            count += 1;
+            if count.is_multiple_of(CANCEL_CHECK_INTERVAL) { self.session.cancel.check()?; }
+            if !self.active() { lexer.skip_to_directive(); }
+            let t = match self.pushed_back.take().unwrap_or_else(|| lexer.next_token()) {
+                Ok(t) => t,
+                Err(e) => { self.lex_error(e); break; }
+            };
+            match &t.token {
+                Token::Eof => break,
+                Token::Directive(name) => {
+                    let name = name.clone();
+                    if !self.handle_directive(&name, t.span, &mut lexer)? { break; }
+                }
+                Token::Identifier(name) if self.macros.is_defined(*name) => {
+                    self.saw_content();
+                    let mut input = VecDeque::from([PpToken::new(t)]);
+                    let mut expanded = Vec::new();
+                    self.expand(&mut input, &mut |pp| pp.pull(&mut lexer), &mut expanded);
+                    for t in expanded { self.emit(t.token); }
+                }
+                Token::Comment { .. } => self.out.push(t),
+                _ => { self.saw_content(); self.emit(t); }
+            }
+        }
+        let frame = self.stack.pop().expect("frame pushed above");
+        while self.conditionals.len() > frame.depth {
+            let c = self.conditionals.pop().expect("checked length");
+            self.error(PreprocessError::UnterminatedConditional, c.span);
+        }
+        if let Guard::Closed(name) = frame.guard { self.guards.insert(file, name); }
+        Ok(())
+    }
+
+    /// Next token of the file for a macro's argument list. Directives and
+    /// end of file end the list; they are pushed back to be handled next.
+    fn pull(&mut self, lexer: &mut Lexer) -> Option<SpannedToken> {
+        loop {
+            match lexer.next_token() {
+                Ok(t) if matches!(t.token, Token::Comment { .. }) => continue,
+                Ok(t) if matches!(t.token, Token::Directive(_) | Token::Eof) => {
+                    self.pushed_back = Some(Ok(t));
+                    return None;
+                }
+                Ok(t) => return Some(t),
+                Err(e) => {
+                    self.pushed_back = Some(Err(e));
+                    return None;
+                }
+            }
+        }
+    }
+
+    fn emit(&mut self, t: SpannedToken) {
+        if let Token::Identifier(name) = &t.token { self.session.interner.record(&name.as_str()); }
+        self.out.push(t);
+    }
+
+    /// Whether tokens at this point are kept.
+    fn active(&self) -> bool { self.conditionals.last().is_none_or(|c| c.active) }
+
+    fn frame(&mut self) -> &mut Frame { self.stack.last_mut().expect("inside a file") }
+
+    /// Tokens or a directive other than the guard's own appeared at file
+    /// level, so the file is not wholly guarded.
+    fn saw_content(&mut self) {
+        let depth = self.conditionals.len();
+        let frame = self.frame();
+        if depth == frame.depth { frame.guard = Guard::NotGuarded; }
+    }
+
+    /// Handles the directive `#name`, consuming the rest of its line.
+    /// Returns false if a lex error ended the file.
+    fn handle_directive(&mut self, name: &str, span: Span, lexer: &mut Lexer) -> Result<bool, Cancelled> {
+        if !self.active() {
+            // Only conditionals matter inside a skipped group, and only
+            // `#elif` needs its line lexed.
+            match name {
+                "if" | "ifdef" | "ifndef" => {
+                    self.conditionals.push(Conditional { span, active: false, taken: true, seen_else: false, parent_active: false });
+                }
+                "elif" => {
+                    let Some(line) = self.directive_line(lexer) else { return Ok(false) };
+                    self.elif(span, &line);
+                    return Ok(true);
+                }
+                "else" | "endif" => {
+                    // The group may become active, so the line must be
+                    // consumed here rather than skipped.
+                    if self.directive_line(lexer).is_none() { return Ok(false); }
+                    if name == "else" { self.else_(span) } else { self.endif(span) }
+                }
+                _ => {}
+            }
+            return Ok(true);
+        }
+        let Some(line) = self.directive_line(lexer) else { return Ok(false) };
+        match name {
+            "if" | "ifdef" | "ifndef" => {
+                let guard_candidate = self.guard_candidate(name, &line);
+                let taken = match name {
+                    "if" => self.evaluate(span, &line),
+                    _ => {
+                        // Without a name the group is skipped, but the
+                        // conditional is still opened for its `#endif`.
+                        self.macro_name(span, &line).is_some_and(|m| self.macros.is_defined(m) == (name == "ifdef"))
+                    }
+                };
+                match guard_candidate {
+                    Some(guard) => self.frame().guard = Guard::Open(guard),
+                    None => self.saw_content(),
+                }
+                self.conditionals.push(Conditional { span, active: taken, taken, seen_else: false, parent_active: true });
+            }
+            "elif" => { self.at_guard_level(); self.elif(span, &line); }
+            "else" => { self.at_guard_level(); self.else_(span); }
+            "endif" => self.endif(span),
+            "" => {}
+            _ => {
+                self.saw_content();
+                match name {
+                    "include" => self.include(span, &line)?,
+                    "embed" => self.embed(span, &line),
+                    "define" => self.define(span, &line),
+                    "undef" => {
+                        if let Some(macro_name) = self.macro_name(span, &line) { self.macros.undefine(macro_name); }
+                    }
+                    "pragma" => {
+                        if matches!(line.first(), Some(t) if t.token == Token::Identifier("once".into())) {
+                            let file = self.frame().file;
+                            self.once.insert(file);
+                        }
+                    }
+                    "error" => {
+                        let text = self.line_text(&line);
+                        self.error(PreprocessError::ErrorDirective(text), span);
+                    }
+                    "warning" => {
+                        let text = self.line_text(&line);
+                        let diag = Diagnostic::warning(format!("#warning {}", text)).with_warning(Warning::Cpp).with_label(Label::primary(span, ""));
+                        self.session.diagnostics.emit(diag);
+                    }
+                    "line" => {
+                        let line = self.expand_all(line.into_iter().map(PpToken::new).collect()).into_iter().map(|t| t.token).collect::<Vec<_>>();
+                        self.line(span, &line, false);
+                    }
+                    // A GNU line marker, `# 12 "file" 1`, as `ruscom preprocess` writes.
+                    number if number.bytes().all(|b| b.is_ascii_digit()) => {
+                        let number = SpannedToken { token: Token::Number(number.to_string()), span };
+                        self.line(span, &[&[number][..], &line].concat(), true);
+                    }
+                    // Only an object file would keep the text.
+                    "ident" => {}
+                    other => self.error(PreprocessError::InvalidDirective(other.to_string()), span),
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Renumbers the lines after a `#line` directive, or a line marker if
+    /// `marker`, whose tokens after the directive name are `line`: a line
+    /// number, then optionally a file name and, for a marker, flags.
+    fn line(&mut self, span: Span, line: &[SpannedToken], marker: bool) {
+        let number = match line.first().map(|t| &t.token) {
+            Some(Token::Number(n)) if n.bytes().all(|b| b.is_ascii_digit()) => n.parse::<usize>().ok().filter(|&n| n <= i32::MAX as usize && (marker || n > 0)),
+            _ => None,
+        };
+        let Some(number) = number else {
+            let message = if marker { "line marker directive requires a non-negative integer argument" } else { "#line directive requires a positive integer argument" };
+            self.error(PreprocessError::InvalidLineDirective(message), line.first().map_or(span, |t| t.span));
+            return;
+        };
+        let path = match line.get(1) {
+            None => None,
+            Some(SpannedToken { token: Token::StringLiteral(path), .. }) => Some(PathBuf::from(path.text())),
+            Some(t) => {
+                let message = if marker { "invalid filename for line marker directive" } else { "invalid filename for #line directive" };
+                self.error(PreprocessError::InvalidLineDirective(message), t.span);
+                return;
+            }
+        };
+        let flags = line.get(2..).unwrap_or_default();
+        let valid_flags = marker && flags.iter().all(|t| matches!(&t.token, Token::Number(n) if matches!(n.as_str(), "1" | "2" | "3" | "4")));
+        if !flags.is_empty() && !valid_flags {
+            self.error(PreprocessError::InvalidLineDirective("extra tokens after the file name"), flags[0].span);
+            return;
+        }
+        // The directive's last line is followed by the first line renumbered.
+        let end = line.last().map_or(span.end, |t| t.span.end);
+        let sources = &mut self.session.sources;
+        let Some(loc) = sources.location(end) else { return };
+        let Some(next) = sources.file(loc.file).position(loc.line + 1, 1) else { return };
+        sources.remap_lines(next, number, path);
+    }
+
+    /// Consumes the rest of a directive line, returning it without the
+    /// terminating `DirectiveEnd`, or `None` after reporting a lex error.
+    fn directive_line(&mut self, lexer: &mut Lexer) -> Option<Vec<SpannedToken>> {
+        let mut line = Vec::new();
+        loop {
+            match lexer.next_token() {
+                Ok(t) if matches!(t.token, Token::DirectiveEnd | Token::Eof) => return Some(line),
+                Ok(t) => line.push(t),
+                Err(e) => { self.lex_error(e); return None; }
+            }
+        }
+    }
+
+    /// The source text of a directive line, for `#error` and `#warning`.
+    fn line_text(&self, line: &[SpannedToken]) -> String {
+        match (line.first(), line.last()) {
+            (Some(first), Some(last)) => {
+                self.session.sources.snippet(Span::new(first.span.start, last.span.end)).unwrap_or_default().to_string()
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// The macro name a `#ifdef`, `#ifndef` or `#undef` line names.
+    fn macro_name(&mut self, span: Span, line: &[SpannedToken]) -> Option<Symbol> {
+        match line.first() {
+            Some(SpannedToken { token: Token::Identifier(name), .. }) => Some(*name),
+            other => {
+                self.error(PreprocessError::MacroNameMissing, other.map_or(span, |t| t.span));
+                None
+            }
+        }
+    }
+
+    /// The guard macro if this `#ifndef` (or `#if !defined`) could open an
+    /// include guard: it is the first thing in the file.
+    fn guard_candidate(&self, name: &str, line: &[SpannedToken]) -> Option<Symbol> {
+        let frame = self.stack.last()?;
+        if frame.guard != Guard::Start || self.conditionals.len() != frame.depth { return None; }
+        let tokens: Vec<&Token> = line.iter().map(|t| &t.token).collect();
+        let ident = match (name, tokens.as_slice()) {
+            ("ifndef", [Token::Identifier(n)]) => n,
+            ("if", [Token::Operator(not), Token::Identifier(d), Token::Identifier(n)]) if *not == "!" && d == "defined" => n,
+            ("if", [Token::Operator(not), Token::Identifier(d), Token::Punct('('), Token::Identifier(n), Token::Punct(')')])
+                if *not == "!" && d == "defined" => n,
+            _ => return None,
+        };
+        Some(*ident)
+    }
+
+    /// `#elif`/`#else` at file level means the file has more than a guard.
+    fn at_guard_level(&mut self) {
+        let depth = self.conditionals.len();
+        let frame = self.frame();
+        if depth == frame.depth + 1 && matches!(frame.guard, Guard::Open(_)) { frame.guard = Guard::NotGuarded; }
+    }
+
+    fn elif(&mut self, span: Span, line: &[SpannedToken]) {
+        let Some(c) = self.conditionals.last() else {
+            self.error(PreprocessError::MisplacedConditional("#elif without #if"), span);
+            return;
+        };
+        if c.seen_else {
+            self.error(PreprocessError::MisplacedConditional("#elif after #else"), span);
+            return;
+        }
+        let take = c.parent_active && !c.taken && self.evaluate(span, line);
+        let c = self.conditionals.last_mut().expect("checked above");
+        c.active = take;
+        c.taken |= take;
+    }
+
+    fn else_(&mut self, span: Span) {
+        let Some(c) = self.conditionals.last_mut() else {
+            self.error(PreprocessError::MisplacedConditional("#else without #if"), span);
+            return;
+        };
+        if c.seen_else {
+            self.error(PreprocessError::MisplacedConditional("#else after #else"), span);
+            return;
+        }
+        c.seen_else = true;
+        c.active = c.parent_active && !c.taken;
+        c.taken = true;
+    }
+
+    fn endif(&mut self, span: Span) {
+        let depth = self.stack.last().map_or(0, |f| f.depth);
+        if self.conditionals.len() <= depth {
+            self.error(PreprocessError::MisplacedConditional("#endif without #if"), span);
+            return;
+        }
+        self.conditionals.pop();
+        let depth = self.conditionals.len();
+        let frame = self.frame();
+        if depth == frame.depth {
+            if let Guard::Open(name) = frame.guard { frame.guard = Guard::Closed(name); }
+        }
+    }
+
+    /// Evaluates a `#if`/`#elif` condition, reporting errors as false.
+    fn evaluate(&mut self, span: Span, line: &[SpannedToken]) -> bool {
+        let end = line.last().map_or(span, |t| Span::new(t.span.end, t.span.end));
+        let tokens = self.expand_condition(line);
+        match tokens.and_then(|tokens| expr::evaluate(&tokens, end, self.session.exec_charsets())) {
+            Ok(value) => value != 0,
+            Err(e) => {
+                self.error(PreprocessError::InvalidExpression(e.message), e.span);
+                false
+            }
+        }
+    }
+
+    /// Replaces `defined X` / `defined(X)` with 1 or 0, then expands
+    /// macros, ready for `expr::evaluate`.
+    fn expand_condition(&mut self, line: &[SpannedToken]) -> Result<Vec<SpannedToken>, expr::ExprError> {
+        let mut out = Vec::with_capacity(line.len());
+        let mut i = 0;
+        while i < line.len() {
+            let t = &line[i];
+            i += 1;
+            match &t.token {
+                Token::Identifier(name) if name == "defined" => {
+                    let paren = line.get(i).is_some_and(|t| t.token == Token::Punct('('));
+                    if paren { i += 1; }
+                    let Some(SpannedToken { token: Token::Identifier(target), span }) = line.get(i) else {
+                        let span = line.get(i).map_or(t.span, |t| t.span);
+                        return Err(expr::ExprError { message: "macro name missing after `defined`".into(), span });
+                    };
+                    i += 1;
+                    if paren {
+                        if line.get(i).is_none_or(|t| t.token != Token::Punct(')')) {
+                            return Err(expr::ExprError { message: "missing `)` after `defined`".into(), span: *span });
+                        }
+                        i += 1;
+                    }
+                    let value = if self.macros.is_defined(*target) { "1" } else { "0" };
+                    out.push(PpToken::new(SpannedToken { token: Token::Number(value.into()), span: Span::new(t.span.start, span.end) }));
+                }
+                _ => out.push(PpToken::new(t.clone())),
+            }
+        }
+        Ok(self.expand_all(out).into_iter().map(|t| t.token).collect())
+    }
+
+    fn define(&mut self, span: Span, line: &[SpannedToken]) {
+        let m = match macros::parse_define(line) {
+            Ok(m) => m,
+            Err((error, at)) => {
+                let at = if at == Span::default() { span } else { at };
+                let error = match error {
+                    DefineError::MissingName => PreprocessError::MacroNameMissing,
+                    DefineError::BadParameterList => PreprocessError::BadMacroParameters,
+                };
+                self.error(error, at);
+                return;
+            }
+        };
+        let at = m.span;
+        let name = m.name;
+        if let Some(prev) = self.macros.define(m) {
+            if !prev.same_definition(self.macros.get(name).expect("just defined")) {
+                self.warn_redefinition(&name.as_str(), at, prev.span);
+            }
+        }
+    }
+
+    /// Includes the header `line` names, timing it in the profile from the
+    /// directive through the end of the header unless it is skipped.
+    fn include(&mut self, directive: Span, line: &[SpannedToken]) -> Result<(), Cancelled> {
+        let start = Instant::now();
+        let Some((name, angled, end)) = self.header_name(line) else {
+            let span = line.first().map_or(directive, |t| t.span);
+            self.error(PreprocessError::ExpectedHeaderName, span);
+            return Ok(());
+        };
+        let span = Span::new(directive.start, end);
+        let name = &name;
+        let Some(path) = self.resolve(name, angled) else {
+            self.error(PreprocessError::FileNotFound(name.clone()), span);
+            return Ok(());
+        };
+        let id = match self.session.sources.lookup_path(&path) {
+            Some(id) => id,
+            None => match headers::builtin_contents(&path).map_or_else(|| self.session.fs.read_to_string(&path), |text| Ok(text.into())) {
+                Ok(contents) => self.session.sources.add_included_file(path, contents, span),
+                Err(_) => {
+                    self.error(PreprocessError::FileNotFound(name.clone()), span);
+                    return Ok(());
+                }
+            },
+        };
+        // Guarded and `#pragma once` headers are not even re-lexed.
+        if self.once.contains(&id) { return Ok(()); }
+        if self.guards.get(&id).is_some_and(|&g| self.macros.is_defined(g)) { return Ok(()); }
+        if let Some(pos) = self.stack.iter().position(|f| f.file == id) {
+            // Re-entering a file whose guard is already defined is harmless.
+            let guarded = matches!(&self.stack[pos].guard, Guard::Open(g) if self.macros.is_defined(*g));
+            if !guarded {
+                let mut diag = PreprocessError::IncludeCycle(name.clone()).to_diagnostic(span);
+                let chain: Vec<FileId> = self.stack[pos..].iter().map(|f| f.file).chain([id]).collect();
+                for pair in chain.windows(2) {
+                    let (a, b) = (self.session.sources.file(pair[0]), self.session.sources.file(pair[1]));
+                    diag = diag.with_note(format!("`{}` includes `{}`", a.path.display(), b.path.display()));
+                }
+                self.session.diagnostics.emit(diag);
+                return Ok(());
+            }
+        }
+        let result = self.process_file(id);
+        let header = format!("include {}", self.session.sources.file(id).path.display());
+        self.session.profiler.record(&header, "preprocess", start);
+        result
+    }
+
+    /// The header an `#include` line names, whether it is `<angled>`, and
+    /// where the name ends. A line not starting with a header name is
+    /// macro-expanded first and must then give a string or `<...>`.
+    fn header_name(&mut self, line: &[SpannedToken]) -> Option<(String, bool, usize)> {
+        if let Some(SpannedToken { token: Token::HeaderName { name, angled }, span }) = line.first() {
+            return Some((name.clone(), *angled, span.end));
+        }
+        let end = line.last()?.span.end;
+        let tokens = self.expand_all(line.iter().cloned().map(PpToken::new).collect());
+        match tokens.first().map(|t| &t.token.token) {
+            Some(Token::StringLiteral(name)) if tokens.len() == 1 => Some((name.text(), false, end)),
+            Some(Token::Punct('<')) if tokens.last().is_some_and(|t| t.token.token == Token::Punct('>')) => {
+                let name: String = tokens[1..tokens.len() - 1].iter().map(|t| t.token.token.spelling()).collect();
+                Some((name, true, end))
+            }
+            _ => None,
+        }
+    }
+
+    /// Finds the header named by an `#include`. Quoted names are looked up
+    /// next to the including file first; then come the `-I` directories,
+    /// the system directories and finally the built-in headers, unless
+    /// `-nostdinc`.
+    fn resolve(&self, name: &str, angled: bool) -> Option<PathBuf> {
+        let current = self.stack.last()?.file;
+        let here = self.session.sources.file(current).path.parent().map(Path::to_path_buf);
+        // Built-in headers have no directory on disk to search.
+        let here = here.filter(|dir| !headers::is_builtin(dir));
+        let options = &self.session.options;
+        let dirs = (!angled).then_some(here).flatten().into_iter()
+            .chain(options.include_dirs.iter().cloned())
+            .chain(options.system_include_dirs.iter().cloned());
+        dirs.map(|dir| normalize(&dir.join(name)))
+            .find(|p| self.session.fs.is_file(p))
+            .or_else(|| headers::builtin_path(name).filter(|_| !options.nostdinc))
+    }
+
+    fn lex_error(&mut self, e: SpannedLexError) {
+        self.session.diagnostics.emit(e.to_diagnostic());
+    }
+
+    fn error(&mut self, error: PreprocessError, span: Span) {
+        self.session.diagnostics.emit(error.to_diagnostic(span));
+    }
+}
+
+/// Source for the `<built-in>` file run before every main file: the
+/// predefined macros, then `-D` and `-U` options in that order.
+fn predefines(session: &Session) -> String {
+    let target = &session.target;
+    let mut text = String::from(concat!(
+        "#define __STDC__ 1\n",
+        "#define __ruscom__ 1\n",
+        "#define __CHAR_BIT__ 8\n",
+        "#define __SIZEOF_INT__ 4\n",
+        "#define __SIZEOF_POINTER__ 8\n",
+    ));
+    text.insert_str(0, &format!("#define __cplusplus {}\n", session.options.std.cplusplus()));
+    if session.options.std >= Standard::Cxx20 { text.push_str("#define __cpp_char8_t 201811L\n"); }
+    text.push_str(&format!("#define __STDC_HOSTED__ {}\n", u8::from(!session.options.freestanding)));
+    let long = if target.os == "windows" { 4 } else { 8 };
+    text.push_str(&format!("#define __SIZEOF_LONG__ {}\n", long));
+    let arch = match target.arch.as_str() {
+        "x86_64" => &["__x86_64__", "__x86_64"][..],
+        "aarch64" => &["__aarch64__"][..],
+        _ => &[][..],
+    };
+    let os = match target.os.as_str() {
+        "linux" => &["__linux__", "__linux", "__unix__", "__unix"][..],
+        "macos" => &["__APPLE__", "__MACH__"][..],
+        "windows" => &["_WIN32", "_WIN64"][..],
+        _ => &[][..],
+    };
+    for name in arch.iter().chain(os) { text.push_str(&format!("#define {} 1\n", name)); }
+    let features = session.options.target_cpu().and_then(|cpu| target.cpu_features(cpu)).unwrap_or_default();
+    for feature in features { text.push_str(&format!("#define {} 1\n", target.feature_macro(feature))); }
+    for define in &session.options.defines {
+        match define.split_once('=') {
+            Some((name, value)) => text.push_str(&format!("#define {} {}\n", name, value)),
+            None => text.push_str(&format!("#define {} 1\n", define)),
+        }
+    }
+    for name in &session.options.undefines { text.push_str(&format!("#undef {}\n", name)); }
+    text
+}
@@ -0,0 +1,93 @@
+//! Printing a preprocessed token stream back out as source text, the
+//! output of `ruscom preprocess` (like `cc -E`).
+//!
+//! Tokens stay on the line they were written on, indented to their column,
+//! and are separated by a space wherever the source had whitespace between
+//! them. Tokens from a macro body all share the invocation's span, so they
+//! come out space-separated. Line markers use the GCC form
+//! `# LINE "FILE" FLAG`, where flag 1 enters an include and 2 returns to the
+//! file that included it.
+
+use std::fmt::Write;
+
+use crate::lexer::token::{SpannedToken, Token};
+use crate::preprocessor::PreprocessOutput;
+use crate::source::SourceManager;
+
+/// Gaps of up to this many lines are reproduced with blank lines instead of
+/// a line marker.
+const MAX_BLANK_LINES: usize = 8;
+
+/// Renders `output` as source text, with line markers if `line_markers`.
+/// Markers give lines the numbers and files `#line` directives gave them,
+/// so the output is reported on as its source was.
+pub fn print(output: &PreprocessOutput, sources: &SourceManager, line_markers: bool) -> String {
+    let mut out = String::new();
+    let main = sources.file(output.file);
+    if line_markers { marker(&mut out, 1, &main.path.display().to_string(), ""); }
+    // The file and line the output is currently on, and where that line
+    // is presumed to be.
+    let mut current = (output.file, 1);
+    let mut presumed = (main.path.clone(), 1);
+    // Files entered and not yet returned from, for marker flags.
+    let mut stack = vec![output.file];
+    let mut prev: Option<&SpannedToken> = None;
+    for t in &output.tokens {
+        let text = match &t.token {
+            Token::Comment { .. } => sources.snippet(t.span).unwrap_or_default().to_string(),
+            other => other.spelling(),
+        };
+        let (Some(loc), Some(at)) = (sources.location(t.span.start), sources.presumed_location(t.span.start)) else {
+            out.push(' ');
+            out.push_str(&text);
+            continue;
+        };
+        // Lines numbered on from the current one, in the same file.
+        let continues = at.path == presumed.0 && at.line as isize - presumed.1 as isize == loc.line as isize - current.1 as isize;
+        match current {
+            // Macro arguments can come from earlier lines than the tokens
+            // before them; keep going on the current line.
+            (file, line) if file == loc.file && loc.line <= line => {
+                if prev.is_none() {
+                    out.extend(std::iter::repeat_n(' ', loc.column - 1));
+                } else if prev.is_some_and(|p| p.span.end != t.span.start) {
+                    out.push(' ');
+                }
+                prev = Some(t);
+                out.push_str(&text);
+                continue;
+            }
+            (file, line) if file == loc.file && continues && loc.line - line <= MAX_BLANK_LINES => {
+                out.extend(std::iter::repeat_n('\n', loc.line - line));
+            }
+            (file, _) if file == loc.file => {
+                out.push('\n');
+                if line_markers { marker(&mut out, at.line, &at.path.display().to_string(), ""); }
+            }
+            _ => {
+                if prev.is_some() { out.push('\n'); }
+                let flag = match stack.iter().position(|&f| f == loc.file) {
+                    Some(i) => {
+                        stack.truncate(i + 1);
+                        " 2"
+                    }
+                    None => " 1",
+                };
+                if flag != " 2" { stack.push(loc.file); }
+                if line_markers { marker(&mut out, at.line, &at.path.display().to_string(), flag); }
+            }
+        }
+        current = (loc.file, loc.line);
+        presumed = (at.path, at.line);
+        out.extend(std::iter::repeat_n(' ', loc.column - 1));
+        out.push_str(&text);
+        prev = Some(t);
+    }
+    if prev.is_some() { out.push('\n'); }
+    out
+}
+
+fn marker(out: &mut String, line: usize, path: &str, flag: &str) {
+    let path = path.replace('\\', "\\\\").replace('"', "\\\"");
+    let _ = writeln!(out, "# {} \"{}\"{}", line, path, flag);
+}
@@ -0,0 +1,86 @@
+//! Macro usage statistics, the report of `ruscom stats macros`.
+//!
+//! Every expansion is counted against its macro with the number of tokens
+//! its replacement produced, before they are rescanned. The chain of an
+//! expansion is the macros whose expansions produced its name, outermost
+//! first, read off the name's hide set: `A`'s body using `B` whose body
+//! uses `C` expands `C` in the chain `A -> B -> C`. Expansions inside
+//! macro arguments start chains of their own.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::lexer::token::Span;
+use crate::source::SourceManager;
+
+/// How often one macro was expanded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MacroUse {
+    pub expansions: u64,
+    /// Tokens its replacements produced, over all its expansions.
+    pub tokens: u64,
+}
+
+/// The macro expansions of one translation unit.
+#[derive(Debug, Clone, Default)]
+pub struct MacroStats {
+    pub macros: HashMap<String, MacroUse>,
+    /// Each distinct chain of two or more macros, with where it was first
+    /// expanded.
+    pub chains: HashMap<Vec<String>, Span>,
+}
+
+impl MacroStats {
+    /// Records an expansion of the last macro of `chain`, invoked at `span`,
+    /// that produced `tokens` tokens.
+    pub(crate) fn record(&mut self, chain: Vec<String>, tokens: usize, span: Span) {
+        let name = chain.last().expect("a chain ends in the macro expanded");
+        let entry = self.macros.entry(name.clone()).or_default();
+        entry.expansions += 1;
+        entry.tokens += tokens as u64;
+        if chain.len() > 1 { self.chains.entry(chain).or_insert(span); }
+    }
+
+    pub fn expansions(&self) -> u64 { self.macros.values().map(|m| m.expansions).sum() }
+
+    pub fn tokens(&self) -> u64 { self.macros.values().map(|m| m.tokens).sum() }
+
+    /// The macros, the most tokens produced first, then the most
+    /// expansions, then by name.
+    pub fn by_tokens(&self) -> Vec<(&str, MacroUse)> {
+        let mut macros: Vec<_> = self.macros.iter().map(|(name, m)| (name.as_str(), *m)).collect();
+        macros.sort_by(|a, b| b.1.tokens.cmp(&a.1.tokens).then(b.1.expansions.cmp(&a.1.expansions)).then(a.0.cmp(b.0)));
+        macros
+    }
+
+    /// The longest chains, longest first and then by their macros, leaving
+    /// out those that only begin a longer one.
+    pub fn deepest_chains(&self) -> Vec<(&[String], Span)> {
+        let mut chains: Vec<_> = self.chains.iter().filter(|(chain, _)| {
+            !self.chains.keys().any(|other| other.len() > chain.len() && other.starts_with(chain))
+        }).map(|(chain, span)| (chain.as_slice(), *span)).collect();
+        chains.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then(a.0.cmp(b.0)));
+        chains
+    }
+}
+
+/// Renders `stats` for the file at `path`: at most `top` macros, the ones
+/// producing the most tokens, and `top` of the deepest chains.
+pub fn report(path: &str, stats: &MacroStats, sources: &SourceManager, top: usize) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{}: {} expansions of {} macros producing {} tokens", path, stats.expansions(), stats.macros.len(), stats.tokens());
+    if stats.macros.is_empty() { return out; }
+    let _ = writeln!(out, "  expansions   tokens  macro");
+    for (name, m) in stats.by_tokens().into_iter().take(top) {
+        let _ = writeln!(out, "  {:>10} {:>8}  {}", m.expansions, m.tokens, name);
+    }
+    let chains = stats.deepest_chains();
+    if !chains.is_empty() {
+        let _ = writeln!(out, "  deepest expansion chains:");
+        for (chain, span) in chains.into_iter().take(top) {
+            let at = sources.location(span.start).map(|l| format!(" at {}", l)).unwrap_or_default();
+            let _ = writeln!(out, "  {:>10}  {}{}", chain.len(), chain.join(" -> "), at);
+        }
+    }
+    out
+}
@@ -0,0 +1,121 @@
+use std::fmt::Write as _;
+use std::time::Instant;
+
+/// A completed timed region, recorded as a Chrome trace "complete" event.
+#[derive(Debug, Clone)]
+pub struct TraceEvent {
+    pub name: String,
+    pub category: &'static str,
+    /// Start time in microseconds since the profiler was created.
+    pub start_us: u64,
+    pub duration_us: u64,
+    /// The thread it ran on: 1 for the main one.
+    pub thread: u32,
+}
+
+/// Values sampled at one time, recorded as a Chrome trace "counter" event
+/// and drawn as a graph over time.
+#[derive(Debug, Clone)]
+pub struct CounterEvent {
+    pub name: String,
+    /// Microseconds since the profiler was created.
+    pub at_us: u64,
+    pub values: Vec<(&'static str, u64)>,
+    pub thread: u32,
+}
+
+/// Collects timings of compiler phases, and counters such as memory use,
+/// and serializes them in the Chrome trace-event format understood by
+/// chrome://tracing and Speedscope.
+pub struct Profiler {
+    origin: Instant,
+    events: Vec<TraceEvent>,
+    counters: Vec<CounterEvent>,
+    /// Names of the threads other than the main one, from `absorb`.
+    threads: Vec<(u32, String)>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self { Self::new() }
+}
+
+impl Profiler {
+    pub fn new() -> Self { Self { origin: Instant::now(), events: Vec::new(), counters: Vec::new(), threads: Vec::new() } }
+
+    /// Runs `f`, recording how long it took under `name`.
+    pub fn time<T>(&mut self, name: &str, category: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let out = f();
+        self.record(name, category, start);
+        out
+    }
+
+    /// Records a region under `name` that started at `start` and ends now,
+    /// for work that needs what owns the profiler and so cannot run inside
+    /// `time`.
+    pub fn record(&mut self, name: &str, category: &'static str, start: Instant) {
+        let start_us = start.duration_since(self.origin).as_micros() as u64;
+        let duration_us = start.elapsed().as_micros() as u64;
+        self.events.push(TraceEvent { name: name.to_string(), category, start_us, duration_us, thread: 1 });
+    }
+
+    /// Records the current `values` of counter `name`.
+    pub fn count(&mut self, name: &str, values: &[(&'static str, u64)]) {
+        let at_us = self.origin.elapsed().as_micros() as u64;
+        self.counters.push(CounterEvent { name: name.to_string(), at_us, values: values.to_vec(), thread: 1 });
+    }
+
+    /// Adds what `other`, a worker's profiler, recorded, on its own
+    /// `thread` named `name` and with its times moved onto this one's
+    /// clock.
+    pub fn absorb(&mut self, other: Profiler, thread: u32, name: &str) {
+        let shift = other.origin.saturating_duration_since(self.origin).as_micros() as u64;
+        self.events.extend(other.events.into_iter().map(|e| TraceEvent { start_us: e.start_us + shift, thread, ..e }));
+        self.counters.extend(other.counters.into_iter().map(|c| CounterEvent { at_us: c.at_us + shift, thread, ..c }));
+        if !self.threads.iter().any(|&(t, _)| t == thread) { self.threads.push((thread, name.to_string())); }
+    }
+
+    pub fn events(&self) -> &[TraceEvent] { &self.events }
+
+    pub fn counters(&self) -> &[CounterEvent] { &self.counters }
+
+    pub fn to_chrome_json(&self) -> String {
+        let mut items = Vec::new();
+        for e in &self.events {
+            items.push(format!(
+                "{{\"name\":\"{}\",\"cat\":\"{}\",\"ph\":\"X\",\"ts\":{},\"dur\":{},\"pid\":1,\"tid\":{}}}",
+                escape_json(&e.name), escape_json(e.category), e.start_us, e.duration_us, e.thread
+            ));
+        }
+        for c in &self.counters {
+            let args: Vec<String> = c.values.iter().map(|(k, v)| format!("\"{}\":{}", escape_json(k), v)).collect();
+            items.push(format!(
+                "{{\"name\":\"{}\",\"ph\":\"C\",\"ts\":{},\"pid\":1,\"tid\":{},\"args\":{{{}}}}}",
+                escape_json(&c.name), c.at_us, c.thread, args.join(",")
+            ));
+        }
+        // Metadata events name the workers' rows.
+        for (thread, name) in &self.threads {
+            items.push(format!("{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":1,\"tid\":{},\"args\":{{\"name\":\"{}\"}}}}", thread, escape_json(name)));
+        }
+        format!("{{\"traceEvents\":[{}],\"displayTimeUnit\":\"ms\"}}", items.join(","))
+    }
+
+    pub fn write_to(&self, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, self.to_chrome_json())
+    }
+}
+
+fn escape_json(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => { let _ = write!(out, "\\u{:04x}", c as u32); }
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,688 @@
+//! Matching syntax tree nodes with expressions in the style of
+//! clang-query's matchers, for `ruscom ast-query`:
+//! `functionDecl(hasName("main"))`,
+//! `callExpr(callee(declRefExpr(hasName("free"))), argumentCountIs(1))`.
+//!
+//! A node matcher is a kind `ast-dump` prints with its first letter
+//! lowered, or `cxx` for `CXX`, like `varDecl` or `cxxRecordDecl`, or one
+//! of `decl`, `stmt` and `expr`, which match any node of their sort. It
+//! holds if the node is of that kind and every matcher inside it holds. A
+//! few of clang-query's names for kinds are accepted too, with its
+//! meaning: `functionDecl` also matches methods, and `parmVarDecl` is
+//! `paramDecl`. Nothing is resolved, so `callee` matches the called
+//! expression rather than a declaration. Matches are reported in the main
+//! file only, in source order.
+
+use std::fmt;
+
+use crate::ast::{Access, Block, Decl, Enumerator, Expr, ExprKind, FunctionKind, ParamDecl, StmtKind, Stmt, StorageClass, TranslationUnit};
+use crate::intern::Symbol;
+use crate::lexer::token::Span;
+use crate::types::TypeId;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    /// Something the grammar does not allow at byte `offset`.
+    Syntax { offset: usize, expected: &'static str },
+    UnknownMatcher(String),
+    /// A matcher given arguments it does not take.
+    Arguments { matcher: String, expected: &'static str },
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::Syntax { offset, expected } => write!(f, "expected {} at column {}", expected, offset + 1),
+            QueryError::UnknownMatcher(name) => write!(f, "unknown matcher `{}`", name),
+            QueryError::Arguments { matcher, expected } => write!(f, "`{}` takes {}", matcher, expected),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Matcher {
+    /// A node of one of `kinds`, or of any kind if empty, that every inner
+    /// matcher holds for.
+    Node { kinds: &'static [&'static str], sort: Option<Sort>, inner: Vec<Matcher> },
+    /// `hasName("x")`: the node's name is `x`. A name with `::` is matched
+    /// against the end of the qualified name, or all of it if it starts
+    /// with `::`.
+    Name(String),
+    /// `hasType("int")`: the node's type prints as given.
+    Type(String),
+    /// `hasOperatorName("+")`
+    Operator(String),
+    ParameterCount(usize),
+    ArgumentCount(usize),
+    /// `equals(0)` on an integer literal.
+    EqualsInt(u64),
+    /// `equals("s")` on a string literal.
+    EqualsString(String),
+    /// A function with a body, or a class or enum with its members.
+    IsDefinition,
+    /// `isPublic()` and friends, for class members.
+    Access(Access),
+    /// A direct child matches.
+    Has(Box<Matcher>),
+    HasDescendant(Box<Matcher>),
+    HasParent(Box<Matcher>),
+    HasAncestor(Box<Matcher>),
+    /// The expression a call calls.
+    Callee(Box<Matcher>),
+    /// The `n`th argument of a call, from 0.
+    Argument(usize, Box<Matcher>),
+    /// The condition of an `if`, a loop, a `switch` or `?:`.
+    Condition(Box<Matcher>),
+    Lhs(Box<Matcher>),
+    Rhs(Box<Matcher>),
+    AllOf(Vec<Matcher>),
+    AnyOf(Vec<Matcher>),
+    Unless(Box<Matcher>),
+}
+
+/// Which nodes `decl()`, `stmt()` and `expr()` match. Expressions are
+/// statements, as in clang.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sort {
+    Decl,
+    Stmt,
+    Expr,
+}
+
+/// Every kind a node can have, as `ast-dump` prints it.
+const KINDS: &[&str] = &[
+    "VarDecl", "FieldDecl", "DecompositionDecl", "FunctionDecl", "CXXMethodDecl", "CXXConstructorDecl", "CXXDestructorDecl",
+    "CXXConversionDecl", "ParamDecl", "TypedefDecl", "TypeAliasDecl", "NamespaceDecl", "UsingDecl", "UsingDirectiveDecl",
+    "CXXRecordDecl", "EnumDecl", "EnumConstantDecl", "FunctionTemplateDecl", "ClassTemplateDecl", "VarTemplateDecl",
+    "TypeAliasTemplateDecl", "TemplateDecl", "ConceptDecl", "ModuleDecl", "ImportDecl", "ExportDecl",
+    "CompoundStmt", "NullStmt", "DeclStmt", "IfStmt", "WhileStmt", "DoStmt", "ForStmt", "CXXForRangeStmt", "SwitchStmt", "CaseStmt",
+    "DefaultStmt", "BreakStmt", "ContinueStmt", "ReturnStmt", "CoreturnStmt", "AttributedStmt",
+    "IntegerLiteral", "FloatingLiteral", "CharacterLiteral", "StringLiteral", "BoolLiteral", "NullPtrLiteral", "CXXThisExpr",
+    "DeclRefExpr", "ParenExpr", "UnaryOperator", "BinaryOperator", "ConditionalOperator", "CallExpr", "ArraySubscriptExpr",
+    "MemberExpr", "CStyleCastExpr", "CXXStaticCastExpr", "CXXDynamicCastExpr", "CXXConstCastExpr", "CXXReinterpretCastExpr",
+    "CXXFunctionalCastExpr", "InitListExpr", "DesignatedInitExpr", "ParenListExpr", "UserDefinedLiteral", "RequiresExpr",
+    "CoawaitExpr", "CoyieldExpr", "EmbedExpr", "UnaryExprOrTypeTraitExpr",
+];
+
+const FUNCTIONS: &[&str] = &["FunctionDecl", "CXXMethodDecl", "CXXConstructorDecl", "CXXDestructorDecl", "CXXConversionDecl"];
+
+/// clang-query's names for kinds `ast-dump` names differently or groups.
+const ALIASES: &[(&str, &[&str])] = &[
+    ("functionDecl", FUNCTIONS),
+    ("cxxMethodDecl", &["CXXMethodDecl", "CXXConstructorDecl", "CXXDestructorDecl", "CXXConversionDecl"]),
+    ("recordDecl", &["CXXRecordDecl"]),
+    ("parmVarDecl", &["ParamDecl"]),
+    ("typedefNameDecl", &["TypedefDecl", "TypeAliasDecl"]),
+    ("cxxBoolLiteral", &["BoolLiteral"]),
+    ("cxxNullPtrLiteralExpr", &["NullPtrLiteral"]),
+    ("explicitCastExpr", &["CStyleCastExpr", "CXXStaticCastExpr", "CXXDynamicCastExpr", "CXXConstCastExpr", "CXXReinterpretCastExpr", "CXXFunctionalCastExpr"]),
+];
+
+impl Matcher {
+    /// Parses a query, optionally starting with clang-query's `match` or
+    /// `m`.
+    pub fn parse(query: &str) -> Result<Matcher, QueryError> {
+        let mut p = QueryParser { text: query, pos: 0 };
+        p.skip_space();
+        let rest = &query[p.pos..];
+        for command in ["match ", "m "] {
+            if rest.starts_with(command) { p.pos += command.len(); }
+        }
+        let matcher = p.matcher()?;
+        p.skip_space();
+        if p.pos < query.len() { return Err(p.error("the end of the query")); }
+        Ok(matcher)
+    }
+
+    /// Whether the matcher holds for `node`, below `path`.
+    fn matches(&self, node: Node, path: Option<&Path>) -> bool {
+        let here = Path { node, parent: path };
+        let child = |m: &Matcher, c: Option<Node>| c.is_some_and(|c| m.matches(c, Some(&here)));
+        match self {
+            Matcher::Node { kinds, sort, inner } => {
+                let kind_ok = match sort {
+                    Some(sort) => node.is(*sort),
+                    None => kinds.contains(&node.kind()),
+                };
+                kind_ok && inner.iter().all(|m| m.matches(node, path))
+            }
+            Matcher::Name(name) => node.name().is_some_and(|n| {
+                if !name.contains("::") { return &*n.as_str() == name; }
+                let scopes = std::iter::successors(path, |p| p.parent).filter_map(|p| match p.node {
+                    Node::Decl(Decl::Namespace(_) | Decl::Class(_), _) => p.node.name(),
+                    _ => None,
+                });
+                let mut qualified = n.to_string();
+                for scope in scopes { qualified = format!("{}::{}", scope, qualified); }
+                match name.strip_prefix("::") {
+                    Some(full) => qualified == full,
+                    None => qualified == *name || qualified.ends_with(&format!("::{}", name)),
+                }
+            }),
+            Matcher::Type(ty) => node.ty().is_some_and(|t| t.to_string() == *ty),
+            Matcher::Operator(op) => match node {
+                Node::Expr(e) => match &e.kind {
+                    ExprKind::Unary { op: o, .. } => o.spelling() == op,
+                    ExprKind::Binary { op: o, .. } => o.spelling() == op,
+                    _ => false,
+                },
+                _ => false,
+            },
+            Matcher::ParameterCount(n) => matches!(node, Node::Decl(Decl::Function(f), _) if f.params.len() == *n),
+            Matcher::ArgumentCount(n) => matches!(node, Node::Expr(Expr { kind: ExprKind::Call { args, .. }, .. }) if args.len() == *n),
+            Matcher::EqualsInt(v) => matches!(node, Node::Expr(Expr { kind: ExprKind::IntLiteral(i, _), .. }) if i == v),
+            Matcher::EqualsString(s) => matches!(node, Node::Expr(Expr { kind: ExprKind::StringLiteral(t), .. }) if t.text() == *s),
+            Matcher::IsDefinition => match node {
+                Node::Decl(Decl::Function(f), _) => f.body.is_some(),
+                Node::Decl(Decl::Class(c), _) => c.members.is_some(),
+                Node::Decl(Decl::Enum(e), _) => e.enumerators.is_some(),
+                _ => false,
+            },
+            Matcher::Access(access) => matches!(node, Node::Decl(_, Some(a)) if a == *access),
+            Matcher::Has(m) => node.children().into_iter().any(|c| m.matches(c, Some(&here))),
+            Matcher::HasDescendant(m) => node.children().into_iter().any(|c| m.matches(c, Some(&here)) || Matcher::HasDescendant(m.clone()).matches(c, Some(&here))),
+            Matcher::HasParent(m) => path.is_some_and(|p| m.matches(p.node, p.parent)),
+            Matcher::HasAncestor(m) => std::iter::successors(path, |p| p.parent).any(|p| m.matches(p.node, p.parent)),
+            Matcher::Callee(m) => match node {
+                Node::Expr(Expr { kind: ExprKind::Call { callee, .. }, .. }) => child(m, Some(Node::Expr(callee))),
+                _ => false,
+            },
+            Matcher::Argument(i, m) => match node {
+                Node::Expr(Expr { kind: ExprKind::Call { args, .. }, .. }) => child(m, args.get(*i).map(Node::Expr)),
+                _ => false,
+            },
+            Matcher::Condition(m) => {
+                let cond = match node {
+                    Node::Stmt(s) => match &s.kind {
+                        StmtKind::If { cond, .. } | StmtKind::While { cond, .. } | StmtKind::DoWhile { cond, .. } | StmtKind::Switch { cond, .. } => Some(cond),
+                        StmtKind::For { cond, .. } => cond.as_ref(),
+                        _ => None,
+                    },
+                    Node::Expr(Expr { kind: ExprKind::Conditional { cond, .. }, .. }) => Some(&**cond),
+                    _ => None,
+                };
+                child(m, cond.map(Node::Expr))
+            }
+            Matcher::Lhs(m) | Matcher::Rhs(m) => match node {
+                Node::Expr(Expr { kind: ExprKind::Binary { lhs, rhs, .. }, .. }) => {
+                    child(m, Some(Node::Expr(if matches!(self, Matcher::Lhs(_)) { lhs } else { rhs })))
+                }
+                _ => false,
+            },
+            Matcher::AllOf(ms) => ms.iter().all(|m| m.matches(node, path)),
+            Matcher::AnyOf(ms) => ms.iter().any(|m| m.matches(node, path)),
+            Matcher::Unless(m) => !m.matches(node, path),
+        }
+    }
+}
+
+/// A node a query found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match {
+    /// As `ast-dump` prints it, like `FunctionDecl`.
+    pub kind: &'static str,
+    pub name: Option<Symbol>,
+    pub span: Span,
+}
+
+/// `FunctionDecl main`.
+impl fmt::Display for Match {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.kind)?;
+        if let Some(name) = self.name { write!(f, " {}", name)?; }
+        Ok(())
+    }
+}
+
+/// The nodes in `tu`'s main file that `matcher` holds for, in source
+/// order, outer nodes before the ones inside them.
+pub fn find(tu: &TranslationUnit, matcher: &Matcher) -> Vec<Match> {
+    fn search(node: Node, path: Option<&Path>, matcher: &Matcher, main: Span, found: &mut Vec<Match>) {
+        let span = node.span();
+        if main.start <= span.start && span.end <= main.end && matcher.matches(node, path) {
+            found.push(Match { kind: node.kind(), name: node.name(), span });
+        }
+        let here = Path { node, parent: path };
+        for child in node.children() { search(child, Some(&here), matcher, main, found); }
+    }
+    let mut found = Vec::new();
+    for decl in &tu.decls { search(Node::Decl(decl, None), None, matcher, tu.span, &mut found); }
+    found
+}
+
+/// A node and the ones it is inside.
+struct Path<'p, 't, 'a> {
+    node: Node<'t, 'a>,
+    parent: Option<&'p Path<'p, 't, 'a>>,
+}
+
+/// A node of any sort, as matchers see the tree: the nodes `ast-dump`
+/// prints, without attributes and template parameters.
+#[derive(Clone, Copy)]
+enum Node<'t, 'a> {
+    /// With the access it was declared with, for a class member.
+    Decl(&'t Decl<'a>, Option<Access>),
+    Param(&'t ParamDecl<'a>),
+    Enumerator(&'t Enumerator<'a>),
+    /// A function body.
+    Block(&'t Block<'a>),
+    Stmt(&'t Stmt<'a>),
+    Expr(&'t Expr<'a>),
+}
+
+impl<'t, 'a> Node<'t, 'a> {
+    /// An expression statement is its expression, as in `ast-dump`.
+    fn stmt(stmt: &'t Stmt<'a>) -> Self {
+        match &stmt.kind {
+            StmtKind::Expr(e) => Node::Expr(e),
+            _ => Node::Stmt(stmt),
+        }
+    }
+
+    fn is(self, sort: Sort) -> bool {
+        match self {
+            Node::Decl(..) | Node::Param(_) | Node::Enumerator(_) => sort == Sort::Decl,
+            Node::Block(_) | Node::Stmt(_) => sort == Sort::Stmt,
+            Node::Expr(_) => sort != Sort::Decl,
+        }
+    }
+
+    fn kind(self) -> &'static str {
+        match self {
+            Node::Decl(decl, access) => match decl {
+                Decl::Var(v) if access.is_some() && v.specifiers.storage != StorageClass::Static => "FieldDecl",
+                Decl::Var(_) => "VarDecl",
+                Decl::Decomposition(_) => "DecompositionDecl",
+                Decl::Function(f) => match f.kind {
+                    FunctionKind::Constructor => "CXXConstructorDecl",
+                    FunctionKind::Destructor => "CXXDestructorDecl",
+                    FunctionKind::Conversion => "CXXConversionDecl",
+                    FunctionKind::Normal if access.is_some() && !f.specifiers.is_friend => "CXXMethodDecl",
+                    FunctionKind::Normal => "FunctionDecl",
+                },
+                Decl::Typedef(t) if t.is_alias => "TypeAliasDecl",
+                Decl::Typedef(_) => "TypedefDecl",
+                Decl::Namespace(_) => "NamespaceDecl",
+                Decl::Using(_) => "UsingDecl",
+                Decl::UsingDirective(_) => "UsingDirectiveDecl",
+                Decl::Class(_) => "CXXRecordDecl",
+                Decl::Enum(_) => "EnumDecl",
+                Decl::Template(t) => match &*t.decl {
+                    Decl::Function(_) => "FunctionTemplateDecl",
+                    Decl::Class(_) => "ClassTemplateDecl",
+                    Decl::Var(_) => "VarTemplateDecl",
+                    Decl::Typedef(_) => "TypeAliasTemplateDecl",
+                    _ => "TemplateDecl",
+                },
+                Decl::Concept(_) => "ConceptDecl",
+                Decl::Module(_) => "ModuleDecl",
+                Decl::Import(_) => "ImportDecl",
+                Decl::Export(_) => "ExportDecl",
+                Decl::StaticAssert(_) => "StaticAssertDecl",
+                Decl::Unsupported(_) => "UnsupportedConstruct",
+                Decl::Invalid(_) => "InvalidDecl",
+            },
+            Node::Param(_) => "ParamDecl",
+            Node::Enumerator(_) => "EnumConstantDecl",
+            Node::Block(_) => "CompoundStmt",
+            Node::Stmt(s) => match &s.kind {
+                StmtKind::Compound(_) => "CompoundStmt",
+                StmtKind::Null => "NullStmt",
+                StmtKind::Expr(_) | StmtKind::Decl(_) => "DeclStmt",
+                StmtKind::If { .. } => "IfStmt",
+                StmtKind::While { .. } => "WhileStmt",
+                StmtKind::DoWhile { .. } => "DoStmt",
+                StmtKind::For { .. } => "ForStmt",
+                StmtKind::RangeFor { .. } => "CXXForRangeStmt",
+                StmtKind::Switch { .. } => "SwitchStmt",
+                StmtKind::Case { .. } => "CaseStmt",
+                StmtKind::Default(_) => "DefaultStmt",
+                StmtKind::Break => "BreakStmt",
+                StmtKind::Continue => "ContinueStmt",
+                StmtKind::Return(_) => "ReturnStmt",
+                StmtKind::CoReturn(_) => "CoreturnStmt",
+                StmtKind::Attributed { .. } => "AttributedStmt",
+                StmtKind::Unsupported(_) => "UnsupportedConstruct",
+                StmtKind::Invalid => "InvalidStmt",
+            },
+            Node::Expr(e) => match &e.kind {
+                ExprKind::IntLiteral(..) => "IntegerLiteral",
+                ExprKind::FloatLiteral(_) => "FloatingLiteral",
+                ExprKind::CharLiteral(_) => "CharacterLiteral",
+                ExprKind::StringLiteral(_) => "StringLiteral",
+                ExprKind::BoolLiteral(_) => "BoolLiteral",
+                ExprKind::Nullptr => "NullPtrLiteral",
+                ExprKind::This => "CXXThisExpr",
+                ExprKind::Name(_) | ExprKind::TemplateId { .. } => "DeclRefExpr",
+                ExprKind::Paren(_) => "ParenExpr",
+                ExprKind::Unary { .. } => "UnaryOperator",
+                ExprKind::Binary { .. } => "BinaryOperator",
+                ExprKind::Conditional { .. } => "ConditionalOperator",
+                ExprKind::Call { .. } => "CallExpr",
+                ExprKind::Index { .. } => "ArraySubscriptExpr",
+                ExprKind::Member { .. } => "MemberExpr",
+                ExprKind::Cast { kind, .. } => match kind.keyword() {
+                    Some("static_cast") => "CXXStaticCastExpr",
+                    Some("dynamic_cast") => "CXXDynamicCastExpr",
+                    Some("const_cast") => "CXXConstCastExpr",
+                    Some(_) => "CXXReinterpretCastExpr",
+                    None => "CStyleCastExpr",
+                },
+                ExprKind::Construct { .. } => "CXXFunctionalCastExpr",
+                ExprKind::InitList(_) => "InitListExpr",
+                ExprKind::Designated { .. } => "DesignatedInitExpr",
+                ExprKind::ParenList(_) => "ParenListExpr",
+                ExprKind::UserLiteral { .. } => "UserDefinedLiteral",
+                ExprKind::Requires { .. } => "RequiresExpr",
+                ExprKind::CoAwait(_) => "CoawaitExpr",
+                ExprKind::CoYield(_) => "CoyieldExpr",
+                ExprKind::Embed(_) => "EmbedExpr",
+                ExprKind::SizeofExpr(_) | ExprKind::SizeofType(_) | ExprKind::Alignof(_) => "UnaryExprOrTypeTraitExpr",
+            },
+        }
+    }
+
+    fn span(self) -> Span {
+        match self {
+            Node::Decl(d, _) => d.span(),
+            Node::Param(p) => p.span,
+            Node::Enumerator(e) => e.span,
+            Node::Block(b) => b.span,
+            Node::Stmt(s) => s.span,
+            Node::Expr(e) => e.span,
+        }
+    }
+
+    fn name(self) -> Option<Symbol> {
+        let name = match self {
+            Node::Decl(d, _) => d.name(),
+            Node::Param(p) => p.name?,
+            Node::Enumerator(e) => e.name,
+            Node::Expr(e) => match &e.kind {
+                ExprKind::Name(name) | ExprKind::TemplateId { name, .. } => name.symbol(),
+                ExprKind::Member { member, .. } => *member,
+                _ => return None,
+            },
+            Node::Block(_) | Node::Stmt(_) => return None,
+        };
+        (!name.as_str().is_empty()).then_some(name)
+    }
+
+    fn ty(self) -> Option<TypeId> {
+        match self {
+            Node::Decl(Decl::Var(v), _) => Some(v.ty),
+            Node::Decl(Decl::Decomposition(d), _) => Some(d.ty),
+            Node::Decl(Decl::Function(f), _) => Some(f.ty),
+            Node::Decl(Decl::Typedef(t), _) => Some(t.ty),
+            Node::Param(p) => Some(p.ty),
+            Node::Expr(e) => match &e.kind {
+                ExprKind::Cast { ty, .. } | ExprKind::Construct { ty, .. } => Some(*ty),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The nodes directly inside this one, in source order.
+    fn children(self) -> Vec<Node<'t, 'a>> {
+        let mut out = Vec::new();
+        match self {
+            Node::Decl(decl, access) => match decl {
+                Decl::Var(v) => out.extend(v.bit_width.iter().map(|w| &w.expr).chain(&v.init).map(Node::Expr)),
+                Decl::Decomposition(d) => out.extend(d.init.iter().map(Node::Expr)),
+                Decl::Function(f) => {
+                    out.extend(f.params.iter().map(Node::Param));
+                    out.extend(f.requires.iter().map(Node::Expr));
+                    out.extend(f.initializers.iter().flat_map(|i| &i.args).map(Node::Expr));
+                    out.extend(f.body.iter().map(Node::Block));
+                }
+                Decl::Namespace(n) => out.extend(n.decls.iter().map(|d| Node::Decl(d, None))),
+                Decl::Export(e) => out.extend(e.decls.iter().map(|d| Node::Decl(d, None))),
+                Decl::Class(c) => out.extend(c.members.iter().flatten().map(|m| Node::Decl(&m.decl, Some(m.access)))),
+                Decl::Enum(e) => out.extend(e.enumerators.iter().flatten().map(Node::Enumerator)),
+                Decl::Template(t) => {
+                    out.extend(t.requires.iter().map(Node::Expr));
+                    out.push(Node::Decl(&t.decl, access));
+                }
+                Decl::Concept(c) => out.push(Node::Expr(&c.constraint)),
+                Decl::StaticAssert(a) => out.push(Node::Expr(&a.cond)),
+                Decl::Typedef(_) | Decl::Using(_) | Decl::UsingDirective(_) | Decl::Module(_) | Decl::Import(_) | Decl::Unsupported(_)
+                | Decl::Invalid(_) => {}
+            },
+            Node::Param(p) => out.extend(p.default.iter().map(Node::Expr)),
+            Node::Enumerator(e) => out.extend(e.value.iter().map(Node::Expr)),
+            Node::Block(b) => out.extend(b.stmts.iter().map(Node::stmt)),
+            Node::Stmt(s) => match &s.kind {
+                StmtKind::Compound(b) => out.extend(b.stmts.iter().map(Node::stmt)),
+                StmtKind::Expr(e) => out.push(Node::Expr(e)),
+                StmtKind::Decl(decls) => out.extend(decls.iter().map(|d| Node::Decl(d, None))),
+                StmtKind::If { cond, then, otherwise } => {
+                    out.push(Node::Expr(cond));
+                    out.push(Node::stmt(then));
+                    out.extend(otherwise.iter().map(|s| Node::stmt(s)));
+                }
+                StmtKind::While { cond, body } | StmtKind::Switch { cond, body } | StmtKind::Case { value: cond, body } => {
+                    out.push(Node::Expr(cond));
+                    out.push(Node::stmt(body));
+                }
+                StmtKind::DoWhile { body, cond } => {
+                    out.push(Node::stmt(body));
+                    out.push(Node::Expr(cond));
+                }
+                StmtKind::For { init, cond, step, body } => {
+                    out.extend(init.iter().map(|s| Node::stmt(s)));
+                    out.extend(cond.iter().chain(step).map(Node::Expr));
+                    out.push(Node::stmt(body));
+                }
+                StmtKind::RangeFor { init, decl, range, body } => {
+                    out.extend(init.iter().map(|s| Node::stmt(s)));
+                    out.push(Node::Decl(decl, None));
+                    out.push(Node::Expr(range));
+                    out.push(Node::stmt(body));
+                }
+                StmtKind::Default(body) | StmtKind::Attributed { stmt: body, .. } => out.push(Node::stmt(body)),
+                StmtKind::Return(value) | StmtKind::CoReturn(value) => out.extend(value.iter().map(Node::Expr)),
+                StmtKind::Null | StmtKind::Break | StmtKind::Continue | StmtKind::Unsupported(_) | StmtKind::Invalid => {}
+            },
+            Node::Expr(e) => match &e.kind {
+                ExprKind::TemplateId { args, .. } => out.extend(args.iter().filter_map(|a| match a {
+                    crate::ast::TemplateArg::Expr(e) => Some(Node::Expr(e)),
+                    crate::ast::TemplateArg::Type(..) => None,
+                })),
+                ExprKind::Paren(inner) | ExprKind::Unary { operand: inner, .. } | ExprKind::Member { base: inner, .. }
+                | ExprKind::Cast { operand: inner, .. } | ExprKind::Designated { init: inner, .. } | ExprKind::UserLiteral { literal: inner, .. }
+                | ExprKind::CoAwait(inner) | ExprKind::CoYield(inner) | ExprKind::SizeofExpr(inner) => out.push(Node::Expr(inner)),
+                ExprKind::Binary { lhs, rhs, .. } => out.extend([Node::Expr(lhs), Node::Expr(rhs)]),
+                ExprKind::Conditional { cond, then, otherwise } => out.extend([Node::Expr(cond), Node::Expr(then), Node::Expr(otherwise)]),
+                ExprKind::Call { callee, args } => {
+                    out.push(Node::Expr(callee));
+                    out.extend(args.iter().map(Node::Expr));
+                }
+                ExprKind::Index { base, index } => out.extend([Node::Expr(base), Node::Expr(index)]),
+                ExprKind::Construct { args: items, .. } | ExprKind::InitList(items) | ExprKind::ParenList(items) => {
+                    out.extend(items.iter().map(Node::Expr));
+                }
+                ExprKind::Requires { params, requirements } => {
+                    out.extend(params.iter().map(Node::Param));
+                    for r in requirements {
+                        match r {
+                            crate::ast::Requirement::Simple(e) | crate::ast::Requirement::Nested(e) => out.push(Node::Expr(e)),
+                            crate::ast::Requirement::Compound { expr, constraint, .. } => out.extend(std::iter::once(expr).chain(constraint).map(Node::Expr)),
+                            crate::ast::Requirement::Type(..) => {}
+                        }
+                    }
+                }
+                ExprKind::IntLiteral(..) | ExprKind::FloatLiteral(_) | ExprKind::CharLiteral(_) | ExprKind::StringLiteral(_)
+                | ExprKind::BoolLiteral(_) | ExprKind::Nullptr | ExprKind::This | ExprKind::Name(_) | ExprKind::Embed(_)
+                | ExprKind::SizeofType(_) | ExprKind::Alignof(_) => {}
+            },
+        }
+        out
+    }
+}
+
+/// The argument of a matcher: another matcher, a string or a number.
+enum Arg {
+    Matcher(Matcher),
+    Str(String),
+    Int(u64),
+}
+
+struct QueryParser<'q> {
+    text: &'q str,
+    pos: usize,
+}
+
+impl QueryParser<'_> {
+    fn error(&self, expected: &'static str) -> QueryError { QueryError::Syntax { offset: self.pos, expected } }
+
+    fn skip_space(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&self) -> Option<char> { self.text[self.pos..].chars().next() }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_space();
+        let ate = self.peek() == Some(c);
+        if ate { self.pos += 1; }
+        ate
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &str {
+        let start = self.pos;
+        while self.peek().is_some_and(&f) { self.pos += self.peek().map_or(0, char::len_utf8); }
+        &self.text[start..self.pos]
+    }
+
+    /// `name(args)`.
+    fn matcher(&mut self) -> Result<Matcher, QueryError> {
+        self.skip_space();
+        let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_').to_string();
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) { return Err(self.error("a matcher")); }
+        if !self.eat('(') { return Err(self.error("`(`")); }
+        let mut args = Vec::new();
+        if !self.eat(')') {
+            loop {
+                args.push(self.arg()?);
+                if self.eat(')') { break; }
+                if !self.eat(',') { return Err(self.error("`,` or `)`")); }
+            }
+        }
+        build(name, args)
+    }
+
+    fn arg(&mut self) -> Result<Arg, QueryError> {
+        self.skip_space();
+        match self.peek() {
+            Some('"') => {
+                self.pos += 1;
+                let mut s = String::new();
+                loop {
+                    match self.peek() {
+                        None => return Err(self.error("`\"`")),
+                        Some('"') => { self.pos += 1; return Ok(Arg::Str(s)); }
+                        Some('\\') => {
+                            self.pos += 1;
+                            let Some(c) = self.peek() else { return Err(self.error("an escaped character")) };
+                            s.push(c);
+                            self.pos += c.len_utf8();
+                        }
+                        Some(c) => { s.push(c); self.pos += c.len_utf8(); }
+                    }
+                }
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let start = self.pos;
+                let digits = self.take_while(|c| c.is_ascii_digit());
+                digits.parse().map(Arg::Int).map_err(|_| QueryError::Syntax { offset: start, expected: "a number that fits in 64 bits" })
+            }
+            _ => self.matcher().map(Arg::Matcher),
+        }
+    }
+}
+
+/// The matcher `name` with `args`, checking it takes them.
+fn build(name: String, args: Vec<Arg>) -> Result<Matcher, QueryError> {
+    let wrong = |expected| Err(QueryError::Arguments { matcher: name.clone(), expected });
+    let matchers = |args: Vec<Arg>| args.into_iter().map(|a| match a { Arg::Matcher(m) => Some(m), _ => None }).collect::<Option<Vec<_>>>();
+    let one_matcher = |args: Vec<Arg>| match <[Arg; 1]>::try_from(args) {
+        Ok([Arg::Matcher(m)]) => Some(Box::new(m)),
+        _ => None,
+    };
+    let one_string = |args: Vec<Arg>| match <[Arg; 1]>::try_from(args) {
+        Ok([Arg::Str(s)]) => Some(s),
+        _ => None,
+    };
+    let one_int = |args: &[Arg]| match args {
+        [Arg::Int(n)] => Some(*n as usize),
+        _ => None,
+    };
+    let none = |args: &[Arg], m: Matcher| if args.is_empty() { Ok(m) } else { wrong("no arguments") };
+    let m = match name.as_str() {
+        "hasName" | "name" => one_string(args).map(Matcher::Name),
+        "hasType" => one_string(args).map(Matcher::Type),
+        "hasOperatorName" => one_string(args).map(Matcher::Operator),
+        "parameterCountIs" => one_int(&args).map(Matcher::ParameterCount),
+        "argumentCountIs" => one_int(&args).map(Matcher::ArgumentCount),
+        "equals" => match <[Arg; 1]>::try_from(args) {
+            Ok([Arg::Int(n)]) => Some(Matcher::EqualsInt(n)),
+            Ok([Arg::Str(s)]) => Some(Matcher::EqualsString(s)),
+            _ => return wrong("a number or a string"),
+        },
+        "isDefinition" => return none(&args, Matcher::IsDefinition),
+        "isPublic" => return none(&args, Matcher::Access(Access::Public)),
+        "isProtected" => return none(&args, Matcher::Access(Access::Protected)),
+        "isPrivate" => return none(&args, Matcher::Access(Access::Private)),
+        "has" => one_matcher(args).map(Matcher::Has),
+        "hasDescendant" => one_matcher(args).map(Matcher::HasDescendant),
+        "hasParent" => one_matcher(args).map(Matcher::HasParent),
+        "hasAncestor" => one_matcher(args).map(Matcher::HasAncestor),
+        "callee" => one_matcher(args).map(Matcher::Callee),
+        "hasCondition" => one_matcher(args).map(Matcher::Condition),
+        "hasLHS" => one_matcher(args).map(Matcher::Lhs),
+        "hasRHS" => one_matcher(args).map(Matcher::Rhs),
+        "unless" => one_matcher(args).map(Matcher::Unless),
+        "hasArgument" => match <[Arg; 2]>::try_from(args) {
+            Ok([Arg::Int(i), Arg::Matcher(m)]) => Some(Matcher::Argument(i as usize, Box::new(m))),
+            _ => return wrong("an index and a matcher"),
+        },
+        "allOf" | "anyOf" => match matchers(args) {
+            Some(ms) if !ms.is_empty() => Some(if name == "allOf" { Matcher::AllOf(ms) } else { Matcher::AnyOf(ms) }),
+            _ => return wrong("one or more matchers"),
+        },
+        _ => {
+            let (kinds, sort) = node_kinds(&name).ok_or_else(|| QueryError::UnknownMatcher(name.clone()))?;
+            return match matchers(args) {
+                Some(inner) => Ok(Matcher::Node { kinds, sort, inner }),
+                None => wrong("matchers"),
+            };
+        }
+    };
+    let expected = match name.as_str() {
+        "hasName" | "name" | "hasType" | "hasOperatorName" => "a string",
+        "parameterCountIs" | "argumentCountIs" => "a number",
+        _ => "a matcher",
+    };
+    m.map_or_else(|| wrong(expected), Ok)
+}
+
+/// The kinds a node matcher named `name` matches, or the sort of node.
+fn node_kinds(name: &str) -> Option<(&'static [&'static str], Option<Sort>)> {
+    match name {
+        "decl" => return Some((&[], Some(Sort::Decl))),
+        "stmt" => return Some((&[], Some(Sort::Stmt))),
+        "expr" => return Some((&[], Some(Sort::Expr))),
+        _ => {}
+    }
+    if let Some((_, kinds)) = ALIASES.iter().find(|(alias, _)| *alias == name) { return Some((kinds, None)); }
+    let kind = match name.strip_prefix("cxx") {
+        Some(rest) => format!("CXX{}", rest),
+        None => name[..1].to_uppercase() + &name[1..],
+    };
+    let i = KINDS.iter().position(|k| *k == kind)?;
+    Some((&KINDS[i..i + 1], None))
+}
@@ -0,0 +1,114 @@
+//! Source rewriting: edits to the files of a session, checked against each
+//! other and applied as a batch, for fix-its and the daemon's edit
+//! responses.
+//!
+//! Edits are made by session position, so one rewrite can touch several
+//! files. Insertions at the same position are kept in the order they were
+//! added; an edit identical to one already added is dropped, so fix-its
+//! that two diagnostics share are made once.
+
+use std::fmt;
+
+use crate::diagnostics::Diagnostic;
+use crate::lexer::token::Span;
+use crate::source::{FileId, SourceManager};
+
+/// Replace `span` with `text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub span: Span,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RewriteError {
+    /// Two edits change the same text.
+    Overlap { first: Span, second: Span },
+    /// An edit not within a single file.
+    OutsideFile(Span),
+}
+
+impl fmt::Display for RewriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RewriteError::Overlap { .. } => write!(f, "edits overlap"),
+            RewriteError::OutsideFile(_) => write!(f, "edit is not within one file"),
+        }
+    }
+}
+
+impl std::error::Error for RewriteError {}
+
+/// The edits of one file, in position order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEdits<'r> {
+    pub file: FileId,
+    pub edits: Vec<&'r Edit>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Rewriter {
+    edits: Vec<Edit>,
+}
+
+impl Rewriter {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn replace(&mut self, span: Span, text: impl Into<String>) {
+        let edit = Edit { span, text: text.into() };
+        if !self.edits.contains(&edit) { self.edits.push(edit); }
+    }
+
+    pub fn insert(&mut self, pos: usize, text: impl Into<String>) { self.replace(Span::new(pos, pos), text) }
+
+    pub fn remove(&mut self, span: Span) { self.replace(span, "") }
+
+    /// Adds the fix-its of `diag`.
+    pub fn fix(&mut self, diag: &Diagnostic) {
+        for fixit in &diag.fixits { self.replace(fixit.span, fixit.replacement.clone()); }
+    }
+
+    pub fn is_empty(&self) -> bool { self.edits.is_empty() }
+
+    /// The edits by file, in file order, checking that none overlap.
+    /// Insertions at either end of a replaced span do not overlap it.
+    pub fn files(&self, sources: &SourceManager) -> Result<Vec<FileEdits<'_>>, RewriteError> {
+        let mut sorted: Vec<(FileId, &Edit)> = Vec::with_capacity(self.edits.len());
+        for edit in &self.edits {
+            let file = sources.file_at(edit.span.start)
+                .filter(|f| edit.span.end <= f.end())
+                .ok_or(RewriteError::OutsideFile(edit.span))?;
+            sorted.push((file.id, edit));
+        }
+        sorted.sort_by_key(|(file, e)| (*file, e.span.start, e.span.end));
+        let mut files: Vec<FileEdits> = Vec::new();
+        for (file, edit) in sorted {
+            match files.last_mut() {
+                Some(last) if last.file == file => {
+                    let prev = last.edits[last.edits.len() - 1];
+                    if prev.span.end > edit.span.start { return Err(RewriteError::Overlap { first: prev.span, second: edit.span }); }
+                    last.edits.push(edit);
+                }
+                _ => files.push(FileEdits { file, edits: vec![edit] }),
+            }
+        }
+        Ok(files)
+    }
+
+    /// The new contents of every file the edits change.
+    pub fn apply(&self, sources: &SourceManager) -> Result<Vec<(FileId, String)>, RewriteError> {
+        Ok(self.files(sources)?.into_iter().map(|f| {
+            let file = sources.file(f.file);
+            let mut out = String::with_capacity(file.contents.len());
+            let mut at = 0;
+            for edit in f.edits {
+                let (start, end) = (edit.span.start - file.start, edit.span.end - file.start);
+                out.push_str(&file.contents[at..start]);
+                out.push_str(&edit.text);
+                at = end;
+            }
+            out.push_str(&file.contents[at..]);
+            (f.file, out)
+        }).collect())
+    }
+}
@@ -0,0 +1,43 @@
+//! Weak symbols and aliases: `__attribute__((weak))`, which lets a
+//! definition elsewhere replace this one at link time, and
+//! `__attribute__((alias("target")))`, which declares another name for a
+//! function or variable defined in the same translation unit.
+
+use crate::ast::{Attribute, TranslationUnit};
+use crate::diagnostics::{Diagnostic, Diagnostics, Label, Warning};
+use crate::sema::SemaError;
+use crate::symbols::{self, Binding, Visibility};
+
+/// Checks the `alias` attribute among the `attrs` of a declaration, which
+/// also defines what it declares if `is_definition`.
+pub fn check_declaration(attrs: &[Attribute], is_definition: bool, diagnostics: &mut Diagnostics) {
+    let Some(a) = symbols::alias(attrs) else { return };
+    let rule = if symbols::argument(a).is_none_or(|t| t.is_empty()) {
+        "`alias` takes the name of its target as a string"
+    } else if is_definition {
+        "a definition cannot also be an alias"
+    } else {
+        return;
+    };
+    diagnostics.emit(SemaError::InvalidAlias(rule).to_diagnostic(a.span));
+}
+
+/// Checks that each alias `tu` defines names a function or variable it
+/// defines, with the same type, and that weak definitions are external.
+pub fn check(tu: &TranslationUnit, diagnostics: &mut Diagnostics) {
+    let defined = symbols::defined(tu, Visibility::Default);
+    for symbol in &defined {
+        if symbol.weak && symbol.binding == Binding::Local { diagnostics.emit(SemaError::WeakInternal(symbol.name).to_diagnostic(symbol.span)); }
+        let Some(target) = symbol.alias else { continue };
+        match defined.iter().find(|d| d.name == target) {
+            None => diagnostics.emit(SemaError::UndefinedAlias { alias: symbol.name, target }.to_diagnostic(symbol.span)),
+            Some(t) if t.kind != symbol.kind || t.ty != symbol.ty => diagnostics.emit(
+                Diagnostic::warning(format!("{} alias `{}` of type `{}` does not match its target, {} `{}` of type `{}`", symbol.kind, symbol.name, symbol.ty, t.kind, t.name, t.ty))
+                    .with_warning(Warning::AttributeAlias)
+                    .with_label(Label::primary(symbol.span, ""))
+                    .with_label(Label::secondary(t.span, "target defined here")),
+            ),
+            Some(_) => {}
+        }
+    }
+}
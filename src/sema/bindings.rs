@@ -0,0 +1,216 @@
+//! Structured bindings: the type of each name `auto [a, b] = e;` binds.
+//!
+//! Without expression types, `e` must name a variable or parameter
+//! declared earlier, dereference one, or be a literal. Arrays bind their elements,
+//! `std::pair`, `std::tuple` and `std::array` their template arguments,
+//! and classes without bases their non-static data members, in order.
+//! `auto` variables initialized the same way get their types too, which
+//! is what a lowered range-based `for` needs. Like the other names sema
+//! knows, variables and classes are known by their unqualified name.
+
+use std::collections::HashMap;
+
+use crate::ast::{ClassDecl, Decl, DecompositionDecl, Expr, ExprKind, InitStyle, StorageClass, TypedefDecl, UnaryOp, VarDecl};
+use crate::charset::ExecCharsets;
+use crate::sema::range_for::Ends;
+use crate::diagnostics::Diagnostics;
+use crate::intern::Symbol;
+use crate::lexer::token::Span;
+use crate::sema::typeck::literal_type;
+use crate::sema::SemaError;
+use crate::types::{TemplateArgument, Type, TypeId};
+
+/// The variables, classes and aliases declared so far.
+#[derive(Debug, Default)]
+pub struct Bindings {
+    variables: HashMap<Symbol, TypeId>,
+    /// The types of each class's non-static data members.
+    classes: HashMap<Symbol, Vec<TypeId>>,
+    /// Every class defined, and whether it may have a `begin` member.
+    ranges: HashMap<Symbol, bool>,
+    aliases: HashMap<Symbol, TypeId>,
+    /// The character sets literals are encoded in.
+    charsets: ExecCharsets,
+}
+
+impl Bindings {
+    /// No declarations yet, with literals encoded in `charsets`.
+    pub fn new(charsets: ExecCharsets) -> Self { Self { charsets, ..Self::default() } }
+
+    /// Records a variable. One whose type is still to be deduced hides
+    /// any earlier variable of the same name.
+    pub fn declare_variable(&mut self, name: Symbol, ty: TypeId) {
+        if referenced(ty).unqualified().get() == Type::Auto {
+            self.variables.remove(&unqualified(name));
+        } else {
+            self.variables.insert(unqualified(name), ty);
+        }
+    }
+
+    /// Records `v`, deducing its type from the initializer if declared
+    /// `auto`, `auto&` or `auto&&`.
+    pub fn declare_var(&mut self, v: &VarDecl) {
+        let ty = match (referenced(v.ty).unqualified().get(), &v.init) {
+            // `auto x = {1};` is a `std::initializer_list`.
+            (Type::Auto, Some(Expr { kind: ExprKind::InitList(_), .. })) if v.init_style == InitStyle::Copy => v.ty,
+            (Type::Auto, Some(init)) => self.type_of(init).map_or(v.ty, |init| deduce(v.ty, init)),
+            _ => v.ty,
+        };
+        self.declare_variable(v.name, ty);
+    }
+
+    pub fn declare_class(&mut self, c: &ClassDecl) {
+        let (Some(name), Some(members)) = (c.name, &c.members) else { return };
+        let begin = Symbol::intern("begin");
+        let has_begin = members.iter().any(|m| matches!(&m.decl, Decl::Function(f) if f.name == begin));
+        // A base may have `begin` too, and its members would bind; those
+        // are not looked up.
+        self.ranges.insert(unqualified(name), has_begin || !c.bases.is_empty());
+        if !c.bases.is_empty() { return; }
+        let fields = members.iter().filter_map(|m| match &m.decl {
+            Decl::Var(v) if v.specifiers.storage != StorageClass::Static => Some(v.ty),
+            _ => None,
+        });
+        self.classes.insert(unqualified(name), fields.collect());
+    }
+
+    pub fn declare_alias(&mut self, t: &TypedefDecl) {
+        self.aliases.insert(unqualified(t.name), t.ty);
+    }
+
+    /// The type of a variable declared so far, including bound names.
+    pub fn variable(&self, name: Symbol) -> Option<TypeId> { self.variables.get(&unqualified(name)).copied() }
+
+    /// The class, defined so far, that `ty` or the type it references
+    /// names, by its unqualified name.
+    pub fn class_named(&self, ty: TypeId) -> Option<Symbol> {
+        match self.resolve(referenced(ty)).unqualified().get() {
+            Type::Named(name) if self.ranges.contains_key(&unqualified(name)) => Some(unqualified(name)),
+            _ => None,
+        }
+    }
+
+    /// The class of the object `e` names and its type, if both are known.
+    pub fn class_of(&self, e: &Expr) -> Option<(Symbol, TypeId)> {
+        let ty = self.type_of(e)?;
+        Some((self.class_named(ty)?, ty.unqualified()))
+    }
+
+    /// Deduces the type of each name `d` binds and declares them, or
+    /// reports why the initializer cannot be decomposed. Names whose
+    /// initializer has an unknown type are declared without one.
+    pub fn bind(&mut self, d: &DecompositionDecl, diagnostics: &mut Diagnostics) {
+        let auto = TypeId::intern(Type::Auto);
+        for binding in &d.bindings { self.declare_variable(binding.name, auto); }
+        let Some(e) = &d.init else { return };
+        let Some(init) = self.type_of(e) else { return };
+        let Some(elements) = self.elements(init) else {
+            diagnostics.emit(SemaError::NotDecomposable(init).to_diagnostic(e.span));
+            return;
+        };
+        let Some(elements) = elements else { return };
+        if elements.len() != d.bindings.len() {
+            let (first, last) = (d.bindings[0].span, d.bindings[d.bindings.len() - 1].span);
+            let error = SemaError::BindingCount { ty: init, elements: elements.len(), names: d.bindings.len() };
+            diagnostics.emit(error.to_diagnostic(Span::new(first.start, last.end)));
+            return;
+        }
+        // `const auto& [a, b]` binds `const` elements.
+        let quals = referenced(d.ty).qualifiers().union(init.qualifiers());
+        for (binding, ty) in d.bindings.iter().zip(elements) { self.variables.insert(binding.name, ty.qualified(quals)); }
+    }
+
+    /// How a range-based `for` over `range` finds its iterators, or the
+    /// type of a range that has none.
+    pub fn range_ends(&self, range: &Expr) -> Result<Ends, TypeId> {
+        let Some(ty) = self.type_of(range) else { return Ok(Ends::Members) };
+        match self.resolve(ty).unqualified().get() {
+            Type::Array(_, Some(n)) => Ok(Ends::Array(n)),
+            Type::Named(name) => match self.ranges.get(&unqualified(name)) {
+                Some(false) => Ok(Ends::Functions),
+                _ => Ok(Ends::Members),
+            },
+            Type::Specialization(..) => Ok(Ends::Members),
+            _ => Err(ty),
+        }
+    }
+
+    /// The type of the object `e` names, without references, or of the
+    /// literal it is.
+    fn type_of(&self, e: &Expr) -> Option<TypeId> {
+        match &e.kind {
+            ExprKind::Name(name) => self.variable(name.symbol()).map(referenced),
+            ExprKind::Paren(inner) => self.type_of(inner),
+            ExprKind::Unary { op: UnaryOp::Deref, operand } => match self.type_of(operand)?.unqualified().get() {
+                Type::Pointer(elem) | Type::Array(elem, _) => Some(elem),
+                _ => None,
+            },
+            ExprKind::InitList(items) | ExprKind::ParenList(items) if items.len() == 1 => self.type_of(&items[0]),
+            _ => literal_type(e, self.charsets),
+        }
+    }
+
+    /// The element types of `ty`; `Some(None)` if it is a class that is
+    /// not known, and `None` if it cannot be decomposed at all.
+    fn elements(&self, ty: TypeId) -> Option<Option<Vec<TypeId>>> {
+        match self.resolve(ty).unqualified().get() {
+            Type::Array(elem, Some(n)) => Some(Some(vec![elem; n as usize])),
+            Type::Named(name) => Some(self.classes.get(&unqualified(name)).cloned()),
+            Type::Specialization(name, args) => Some(tuple_elements(&unqualified(name).as_str(), &args)),
+            _ => None,
+        }
+    }
+
+    /// `ty` with typedefs and aliases replaced by what they name.
+    pub fn resolve(&self, ty: TypeId) -> TypeId {
+        let Type::Named(name) = ty.unqualified().get() else { return ty };
+        match self.aliases.get(&unqualified(name)) {
+            // `typedef struct S S;` aliases a class to itself.
+            Some(&aliased) if aliased.unqualified() != ty.unqualified() => self.resolve(aliased).qualified(ty.qualifiers()),
+            _ => ty,
+        }
+    }
+}
+
+/// The type a variable declared `declared`, which has `auto` in it, gets
+/// from an lvalue of type `init`: a reference to `init`, or `init`
+/// decayed and without its cv-qualifiers.
+fn deduce(declared: TypeId, init: TypeId) -> TypeId {
+    let quals = referenced(declared).qualifiers();
+    match declared.get() {
+        Type::Reference(_) | Type::RvalueReference(_) => TypeId::intern(Type::Reference(init.qualified(quals))),
+        _ => match init.unqualified().get() {
+            Type::Array(elem, _) => TypeId::intern(Type::Pointer(elem)),
+            _ => init.unqualified(),
+        }
+        .qualified(quals),
+    }
+}
+
+/// The elements of the standard tuple-like types.
+fn tuple_elements(name: &str, args: &[TemplateArgument]) -> Option<Vec<TypeId>> {
+    let types = || args.iter().map(|a| match a {
+        TemplateArgument::Type(ty) => Some(*ty),
+        TemplateArgument::Value(_) => None,
+    }).collect();
+    match (name, args) {
+        ("pair" | "tuple", _) => types(),
+        ("array", [TemplateArgument::Type(elem), TemplateArgument::Value(n)]) => Some(vec![*elem; n.parse().ok()?]),
+        _ => None,
+    }
+}
+
+/// `ty` without a reference.
+fn referenced(ty: TypeId) -> TypeId {
+    match ty.get() {
+        Type::Reference(inner) | Type::RvalueReference(inner) => inner,
+        _ => ty,
+    }
+}
+
+fn unqualified(name: Symbol) -> Symbol {
+    match name.as_str().rsplit_once("::") {
+        Some((_, last)) => Symbol::intern(last),
+        None => name,
+    }
+}
@@ -0,0 +1,326 @@
+//! Control-flow graphs of function bodies.
+//!
+//! A body's statements are split into basic blocks, each a sequence of
+//! elements run one after the other and ending in a jump to one or more
+//! successors. Conditions end the blocks they branch from; `break`,
+//! `continue` and `return` jump to where they go, and so does a call of a
+//! function that never returns, like `abort()`, which goes to the exit.
+//! Whatever follows a jump starts a block with no predecessors, which is
+//! how unreachable code shows.
+//!
+//! Loops whose condition is a constant true value, or missing in a `for`,
+//! never leave except by a jump; the conditions of `if` statements always
+//! take both branches, so that code disabled by a constant like
+//! `if (DEBUG)` is not unreachable. Statements the parser skipped or could
+//! not parse could jump anywhere, so bodies with them have no graph.
+
+use std::collections::VecDeque;
+
+use crate::ast::{Block, Decl, Expr, Stmt, StmtKind};
+use crate::sema::constant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub usize);
+
+/// Something a basic block runs.
+#[derive(Debug, Clone, Copy)]
+pub enum Element<'s, 'a> {
+    /// A statement run whole: an expression or declaration statement, or
+    /// a `return`, `break` or `continue` with any operand.
+    Stmt(&'s Stmt<'a>),
+    /// The condition of an `if`, loop or `switch`, the step of a `for`
+    /// loop or the range of a range-based one.
+    Expr(&'s Expr<'a>),
+    /// The variable of a range-based `for`, declared for each element.
+    Decl(&'s Decl<'a>),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BasicBlock<'s, 'a> {
+    pub elements: Vec<Element<'s, 'a>>,
+    pub successors: Vec<BlockId>,
+}
+
+/// A two-way branch on a condition, from the block that tests it.
+#[derive(Debug, Clone, Copy)]
+pub struct Branch<'s, 'a> {
+    pub cond: &'s Expr<'a>,
+    pub from: BlockId,
+    pub on_true: BlockId,
+    pub on_false: BlockId,
+}
+
+/// A loop, with the block that tests its condition and the one after it.
+#[derive(Debug, Clone, Copy)]
+pub struct Loop<'s, 'a> {
+    pub stmt: &'s Stmt<'a>,
+    pub header: BlockId,
+    pub after: BlockId,
+    /// Whether its condition is always true, so that only a jump leaves it.
+    pub is_endless: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Cfg<'s, 'a> {
+    pub blocks: Vec<BasicBlock<'s, 'a>>,
+    pub entry: BlockId,
+    /// Where `return` statements and calls that never return go; `end`
+    /// leads there too.
+    pub exit: BlockId,
+    /// The block reached by running off the end of the body.
+    pub end: BlockId,
+    pub loops: Vec<Loop<'s, 'a>>,
+    /// The branches of `if` statements and of loops that can end.
+    pub branches: Vec<Branch<'s, 'a>>,
+}
+
+impl<'s, 'a> Cfg<'s, 'a> {
+    /// The graph of `body`, in which a call `e` for which `never_returns(e)`
+    /// is true jumps to the exit; `None` if the body has a statement that
+    /// was skipped or failed to parse.
+    pub fn build(body: &'s Block<'a>, never_returns: &dyn Fn(&Expr) -> bool) -> Option<Self> {
+        let mut builder = Builder {
+            blocks: vec![BasicBlock::default(), BasicBlock::default()],
+            current: BlockId(0),
+            exit: BlockId(1),
+            breaks: Vec::new(),
+            continues: Vec::new(),
+            switches: Vec::new(),
+            loops: Vec::new(),
+            branches: Vec::new(),
+            never_returns,
+            opaque: false,
+        };
+        builder.block(body);
+        let end = builder.current;
+        builder.edge(end, builder.exit);
+        if builder.opaque { return None; }
+        Some(Cfg { blocks: builder.blocks, entry: BlockId(0), exit: BlockId(1), end, loops: builder.loops, branches: builder.branches })
+    }
+
+    pub fn block(&self, id: BlockId) -> &BasicBlock<'s, 'a> { &self.blocks[id.0] }
+
+    /// Whether each block can be reached from the entry.
+    pub fn reachable(&self) -> Vec<bool> { self.reachable_from(self.entry) }
+
+    /// Whether each block can be reached from `start`, which can.
+    pub fn reachable_from(&self, start: BlockId) -> Vec<bool> {
+        let mut seen = vec![false; self.blocks.len()];
+        let mut queue = VecDeque::from([start]);
+        seen[start.0] = true;
+        while let Some(id) = queue.pop_front() {
+            for &next in &self.blocks[id.0].successors {
+                if !seen[next.0] {
+                    seen[next.0] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+        seen
+    }
+
+    /// The predecessors of each block.
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut predecessors = vec![Vec::new(); self.blocks.len()];
+        for (i, block) in self.blocks.iter().enumerate() {
+            for next in &block.successors { predecessors[next.0].push(BlockId(i)); }
+        }
+        predecessors
+    }
+}
+
+struct Builder<'s, 'a, 'n> {
+    blocks: Vec<BasicBlock<'s, 'a>>,
+    /// Where the next element goes.
+    current: BlockId,
+    exit: BlockId,
+    /// Where `break` and `continue` go, innermost last.
+    breaks: Vec<BlockId>,
+    continues: Vec<BlockId>,
+    /// The `switch` statements being built, innermost last: the block
+    /// that branches to their labels, and whether one is `default`.
+    switches: Vec<(BlockId, bool)>,
+    loops: Vec<Loop<'s, 'a>>,
+    branches: Vec<Branch<'s, 'a>>,
+    never_returns: &'n dyn Fn(&Expr) -> bool,
+    opaque: bool,
+}
+
+impl<'s, 'a> Builder<'s, 'a, '_> {
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push(BasicBlock::default());
+        BlockId(self.blocks.len() - 1)
+    }
+
+    fn edge(&mut self, from: BlockId, to: BlockId) {
+        let successors = &mut self.blocks[from.0].successors;
+        if !successors.contains(&to) { successors.push(to); }
+    }
+
+    fn push(&mut self, element: Element<'s, 'a>) { self.blocks[self.current.0].elements.push(element); }
+
+    /// Jumps from the current block to `to`; what follows starts a block
+    /// nothing jumps to.
+    fn jump(&mut self, to: Option<BlockId>) {
+        if let Some(to) = to { self.edge(self.current, to); }
+        self.current = self.new_block();
+    }
+
+    /// Continues in a new block that the current one falls into.
+    fn follow(&mut self) -> BlockId {
+        let next = self.new_block();
+        self.edge(self.current, next);
+        self.current = next;
+        next
+    }
+
+    fn block(&mut self, block: &'s Block<'a>) {
+        for stmt in &block.stmts { self.stmt(stmt); }
+    }
+
+    /// Builds a loop's `body` from a new block after `from`, which it
+    /// returns, with `continue` going to `next` and `break` to `after`.
+    fn loop_body(&mut self, body: &'s Stmt<'a>, from: BlockId, next: BlockId, after: BlockId) -> BlockId {
+        let first = self.new_block();
+        self.edge(from, first);
+        self.current = first;
+        self.breaks.push(after);
+        self.continues.push(next);
+        self.stmt(body);
+        self.breaks.pop();
+        self.continues.pop();
+        self.edge(self.current, next);
+        first
+    }
+
+    fn stmt(&mut self, stmt: &'s Stmt<'a>) {
+        match &stmt.kind {
+            StmtKind::Compound(block) => self.block(block),
+            StmtKind::Null => {}
+            StmtKind::Expr(e) => {
+                self.push(Element::Stmt(stmt));
+                if (self.never_returns)(e) { self.jump(Some(self.exit)); }
+            }
+            StmtKind::Decl(_) => self.push(Element::Stmt(stmt)),
+            StmtKind::If { cond, then, otherwise } => {
+                self.push(Element::Expr(cond));
+                let branch = self.current;
+                let on_true = self.new_block();
+                self.current = on_true;
+                self.edge(branch, on_true);
+                self.stmt(then);
+                let then_end = self.current;
+                let (on_false, otherwise_end) = match otherwise {
+                    Some(otherwise) => {
+                        let on_false = self.new_block();
+                        self.current = on_false;
+                        self.edge(branch, on_false);
+                        self.stmt(otherwise);
+                        (Some(on_false), self.current)
+                    }
+                    None => (None, branch),
+                };
+                self.current = self.new_block();
+                self.edge(then_end, self.current);
+                self.edge(otherwise_end, self.current);
+                self.branches.push(Branch { cond, from: branch, on_true, on_false: on_false.unwrap_or(self.current) });
+            }
+            StmtKind::While { cond, body } => {
+                let header = self.follow();
+                self.push(Element::Expr(cond));
+                let after = self.new_block();
+                let is_endless = is_true(cond);
+                if !is_endless { self.edge(header, after); }
+                let first = self.loop_body(body, header, header, after);
+                if !is_endless { self.branches.push(Branch { cond, from: header, on_true: first, on_false: after }); }
+                self.loops.push(Loop { stmt, header, after, is_endless });
+                self.current = after;
+            }
+            StmtKind::DoWhile { body, cond } => {
+                let start = self.current;
+                let (test, after) = (self.new_block(), self.new_block());
+                let first = self.loop_body(body, start, test, after);
+                self.current = test;
+                self.push(Element::Expr(cond));
+                self.edge(test, first);
+                let is_endless = is_true(cond);
+                if !is_endless {
+                    self.edge(test, after);
+                    self.branches.push(Branch { cond, from: test, on_true: first, on_false: after });
+                }
+                self.loops.push(Loop { stmt, header: test, after, is_endless });
+                self.current = after;
+            }
+            StmtKind::For { init, cond, step, body } => {
+                if let Some(init) = init { self.stmt(init); }
+                let header = self.follow();
+                if let Some(cond) = cond { self.push(Element::Expr(cond)); }
+                let (next, after) = (self.new_block(), self.new_block());
+                let is_endless = cond.as_ref().is_none_or(is_true);
+                if !is_endless { self.edge(header, after); }
+                let first = self.loop_body(body, header, next, after);
+                if let (Some(cond), false) = (cond, is_endless) { self.branches.push(Branch { cond, from: header, on_true: first, on_false: after }); }
+                self.current = next;
+                if let Some(step) = step { self.push(Element::Expr(step)); }
+                self.edge(next, header);
+                self.loops.push(Loop { stmt, header, after, is_endless });
+                self.current = after;
+            }
+            StmtKind::RangeFor { init, decl, range, body } => {
+                if let Some(init) = init { self.stmt(init); }
+                self.push(Element::Expr(range));
+                let header = self.follow();
+                let after = self.new_block();
+                self.edge(header, after);
+                let element = self.new_block();
+                self.edge(header, element);
+                self.current = element;
+                self.push(Element::Decl(decl));
+                self.loop_body(body, element, header, after);
+                self.loops.push(Loop { stmt, header, after, is_endless: false });
+                self.current = after;
+            }
+            StmtKind::Switch { cond, body } => {
+                self.push(Element::Expr(cond));
+                let branch = self.current;
+                let after = self.new_block();
+                self.switches.push((branch, false));
+                self.breaks.push(after);
+                // Statements before the first label are never run.
+                self.current = self.new_block();
+                self.stmt(body);
+                self.edge(self.current, after);
+                self.breaks.pop();
+                let has_default = self.switches.pop().is_some_and(|(_, has_default)| has_default);
+                if !has_default { self.edge(branch, after); }
+                self.current = after;
+            }
+            StmtKind::Case { body, .. } | StmtKind::Default(body) => {
+                let label = self.follow();
+                if let Some((branch, has_default)) = self.switches.last_mut() {
+                    *has_default |= matches!(stmt.kind, StmtKind::Default(_));
+                    let branch = *branch;
+                    self.edge(branch, label);
+                }
+                self.stmt(body);
+            }
+            StmtKind::Break => {
+                self.push(Element::Stmt(stmt));
+                self.jump(self.breaks.last().copied());
+            }
+            StmtKind::Continue => {
+                self.push(Element::Stmt(stmt));
+                self.jump(self.continues.last().copied());
+            }
+            StmtKind::Return(_) | StmtKind::CoReturn(_) => {
+                self.push(Element::Stmt(stmt));
+                self.jump(Some(self.exit));
+            }
+            StmtKind::Attributed { stmt, .. } => self.stmt(stmt),
+            StmtKind::Unsupported(_) | StmtKind::Invalid => self.opaque = true,
+        }
+    }
+}
+
+/// Whether `cond` is a constant that is not zero.
+fn is_true(cond: &Expr) -> bool { constant::evaluate(cond, &|_| None).is_ok_and(|value| value != 0) }
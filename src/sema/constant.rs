@@ -0,0 +1,756 @@
+//! Constant expressions.
+//!
+//! Covers what enumerator values, array bounds, case labels,
+//! `static_assert` and `constexpr` variables need: integer, character,
+//! boolean and floating literals, the arithmetic, bitwise, relational and
+//! logical operators, `?:`, casts to arithmetic types, names of constants
+//! the caller knows, `sizeof` and `alignof` of the types whose layout it
+//! knows, and calls of `constexpr` functions whose bodies declare, assign
+//! and return locals and loop and branch over them.
+//!
+//! Every value has a type, computed as the compiler would, from the types
+//! of the operands and never their values: operands are promoted and
+//! converted to a common type, and a result is wrapped into an unsigned
+//! type's range, as is a left shift into its type's, as C++20 defines it
+//! (and C++14 for the values it defines it for). What is undefined behavior at run time is an
+//! error here, as it makes an expression not a constant: a signed result
+//! outside its type's range, division by zero, a shift by a negative count
+//! or by the type's width or more, and a floating value converted to an
+//! integral type that cannot hold it.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{BinaryOp, Block, CastKind, Decl, EnumDecl, Expr, ExprKind, FunctionDecl, Stmt, StmtKind, UnaryOp, VarDecl};
+use crate::charset::ExecCharsets;
+use crate::diagnostics::Diagnostics;
+use crate::intern::Symbol;
+use crate::lexer::token::Span;
+use crate::sema::enums;
+use crate::sema::layout::{LayoutError, Layouts};
+use crate::sema::typeck::{self, arithmetic_conversion, promote};
+use crate::session::Target;
+use crate::sema::SemaError;
+use crate::types::{Type, TypeId};
+
+/// How deeply `constexpr` calls may nest, as Clang's default
+/// `-fconstexpr-depth`.
+pub const MAX_DEPTH: usize = 512;
+
+/// Each nested call is evaluated by recursion: when less than this much
+/// stack is left for one, another segment of `STACK_SEGMENT` bytes is
+/// allocated for it.
+const RED_ZONE: usize = 128 * 1024;
+const STACK_SEGMENT: usize = 4 * 1024 * 1024;
+
+/// How many statements and calls one evaluation may run, against loops
+/// that never end.
+pub const MAX_STEPS: usize = 1 << 20;
+
+/// Why an expression has no value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Unevaluated {
+    /// It depends on something not known here, such as a `constexpr`
+    /// variable or function, so it may still be constant.
+    Unknown,
+    /// It is not a constant expression, or evaluating it fails.
+    Error(SemaError, Span),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Int(i128),
+    Float(f64),
+}
+
+/// A value and its type, which is arithmetic, or an enum's that is kept
+/// as written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Constant {
+    pub value: Value,
+    pub ty: TypeId,
+}
+
+impl Constant {
+    pub fn int(value: i128, ty: TypeId) -> Self { Self { value: Value::Int(value), ty } }
+
+    pub fn float(value: f64, ty: TypeId) -> Self { Self { value: Value::Float(value), ty } }
+
+    /// The value, if it is an integer.
+    pub fn as_int(self) -> Option<i128> {
+        match self.value {
+            Value::Int(n) => Some(n),
+            Value::Float(_) => None,
+        }
+    }
+
+    /// Whether the value is zero, which a condition takes as false.
+    pub fn is_zero(self) -> bool {
+        match self.value {
+            Value::Int(n) => n == 0,
+            Value::Float(v) => v == 0.0,
+        }
+    }
+
+    fn as_float(self) -> f64 {
+        match self.value {
+            Value::Int(n) => n as f64,
+            Value::Float(v) => v,
+        }
+    }
+}
+
+/// What names stand for in the expressions evaluated.
+pub trait Environment<'a> {
+    /// The value of `name`, if it names a constant.
+    fn constant(&self, name: Symbol) -> Option<Constant>;
+
+    /// The `constexpr` function `name` that takes `args` arguments.
+    fn function(&self, _name: Symbol, _args: usize) -> Option<&FunctionDecl<'a>> { None }
+
+    /// The declared type of the variable `name`, for `sizeof`.
+    fn variable_type(&self, _name: Symbol) -> Option<TypeId> { None }
+
+    /// The type of the function `name`, for `sizeof`, which has none.
+    fn function_type(&self, _name: Symbol) -> Option<TypeId> { None }
+
+    /// The sizes and alignments of types.
+    fn layouts(&self) -> Option<&Layouts> { None }
+
+    /// Why `name`, which is neither a constant nor a `constexpr` function
+    /// here, has no value where it is used at `span`: by default because
+    /// it may be one not known here.
+    fn unresolved(&self, _name: Symbol, _span: Span) -> Unevaluated { Unevaluated::Unknown }
+}
+
+/// The constants a translation unit has declared so far.
+#[derive(Debug, Clone, Default)]
+pub struct Constants<'a> {
+    /// `constexpr` variables, and `const` ones of arithmetic type, whose
+    /// initializers are constant.
+    pub variables: HashMap<Symbol, Constant>,
+    /// Enumerator values, by name for an unscoped enum and as `E::name`
+    /// for a named one.
+    pub enumerators: HashMap<Symbol, i128>,
+    /// `constexpr` functions with bodies, by name.
+    functions: HashMap<Symbol, Vec<FunctionDecl<'a>>>,
+    /// The type of every variable, constant or not.
+    types: HashMap<Symbol, TypeId>,
+    /// `const` variables whose initializers may be constant, but depend
+    /// on what is not known here.
+    unknown: HashSet<Symbol>,
+    /// Functions without `constexpr` bodies, whose calls are not constant,
+    /// with their types.
+    others: HashMap<Symbol, TypeId>,
+    /// For each block scope entered, what its declarations hid, to be
+    /// restored when it is left.
+    shadowed: Vec<Vec<Shadowed>>,
+    /// How many templates enclose the declarations seen, whose parameters
+    /// and dependent bases may name constants.
+    templates: usize,
+    /// Whether every declaration before the expressions evaluated was
+    /// seen, so that a name not known here is known not to be a constant.
+    /// The parser's are not, nor those after an import or a declaration
+    /// that was skipped.
+    complete: bool,
+    pub layouts: Layouts,
+}
+
+/// What a declaration in a block scope hid of the name it declares.
+#[derive(Debug, Clone)]
+struct Shadowed {
+    name: Symbol,
+    variable: Option<Constant>,
+    ty: Option<TypeId>,
+    unknown: bool,
+}
+
+impl<'a> Constants<'a> {
+    /// No constants yet, with the layouts of `target`.
+    pub fn new(target: &Target) -> Self { Self { layouts: Layouts::new(target), ..Self::default() } }
+
+    /// These constants, with literals encoded in `charsets`.
+    pub fn with_charsets(mut self, charsets: ExecCharsets) -> Self {
+        self.layouts = self.layouts.with_charsets(charsets);
+        self
+    }
+
+    /// Records `v` if it is a constant. A `constexpr` variable must be
+    /// one: returns why its initializer is not constant, if evaluating it
+    /// fails.
+    pub fn declare_variable(&mut self, v: &VarDecl) -> Option<(SemaError, Span)> {
+        self.declare_parameter(v.name, v.ty);
+        if !is_arithmetic(v.ty.unqualified()) || !(v.specifiers.is_constexpr || v.ty.is_const()) { return None; }
+        let value = match &v.init {
+            Some(init) => value(init, self).and_then(|c| convert(c, v.ty.unqualified(), init.span)),
+            None => Err(Unevaluated::Unknown),
+        };
+        match value {
+            Ok(c) => {
+                self.variables.insert(v.name, c);
+                None
+            }
+            Err(Unevaluated::Unknown) => {
+                self.unknown.insert(v.name);
+                None
+            }
+            Err(Unevaluated::Error(error, span)) => v.specifiers.is_constexpr.then_some((error, span)),
+        }
+    }
+
+    /// Records a variable `name` of type `ty` that is not a constant, like
+    /// a function parameter.
+    pub fn declare_parameter(&mut self, name: Symbol, ty: TypeId) {
+        if let Some(scope) = self.shadowed.last_mut() {
+            scope.push(Shadowed { name, variable: self.variables.get(&name).copied(), ty: self.types.get(&name).copied(), unknown: self.unknown.contains(&name) });
+        }
+        self.types.insert(name, ty);
+        self.variables.remove(&name);
+        self.unknown.remove(&name);
+    }
+
+    /// Enters a block scope, whose variables hide those outside it until
+    /// it is left.
+    pub fn enter_scope(&mut self) { self.shadowed.push(Vec::new()); }
+
+    pub fn exit_scope(&mut self) {
+        let Some(scope) = self.shadowed.pop() else { return };
+        for Shadowed { name, variable, ty, unknown } in scope.into_iter().rev() {
+            match variable {
+                Some(c) => self.variables.insert(name, c),
+                None => self.variables.remove(&name),
+            };
+            match ty {
+                Some(ty) => self.types.insert(name, ty),
+                None => self.types.remove(&name),
+            };
+            if unknown { self.unknown.insert(name); } else { self.unknown.remove(&name); }
+        }
+    }
+
+    /// Enters the declaration of a template, where names may depend on its
+    /// parameters.
+    pub fn enter_template(&mut self) { self.templates += 1; }
+
+    pub fn exit_template(&mut self) { self.templates -= 1; }
+
+    /// These constants, which will be told of every declaration.
+    pub fn complete(mut self) -> Self {
+        self.complete = true;
+        self
+    }
+
+    /// Notes that declarations were imported or skipped, unseen.
+    pub fn declare_unseen(&mut self) { self.complete = false; }
+
+    /// Records the values of `e`'s enumerators and its underlying type,
+    /// reporting what is wrong with them.
+    pub fn declare_enum(&mut self, e: &EnumDecl, diagnostics: &mut Diagnostics) {
+        let values = enums::values(e, &self.layouts, &mut self.enumerators, diagnostics);
+        self.layouts.declare_enum(e, &values);
+    }
+
+    /// Records `f` if it is a `constexpr` function with a body.
+    pub fn declare_function(&mut self, f: &FunctionDecl<'a>) {
+        if f.specifiers.is_constexpr && f.body.is_some() {
+            self.functions.entry(f.name).or_default().push(f.clone());
+        } else {
+            self.others.insert(f.name, f.ty);
+        }
+    }
+}
+
+impl<'a> Environment<'a> for Constants<'a> {
+    fn constant(&self, name: Symbol) -> Option<Constant> {
+        self.variables.get(&name).copied().or_else(|| self.enumerators.get(&name).map(|&n| Constant::int(n, literal_type(n))))
+    }
+
+    fn function(&self, name: Symbol, args: usize) -> Option<&FunctionDecl<'a>> {
+        self.functions.get(&name)?.iter().find(|f| f.params.len() >= args && f.params[args..].iter().all(|p| p.default.is_some()))
+    }
+
+    fn variable_type(&self, name: Symbol) -> Option<TypeId> { self.types.get(&name).copied() }
+
+    fn function_type(&self, name: Symbol) -> Option<TypeId> { self.functions.get(&name).and_then(|f| f.first()).map(|f| f.ty).or_else(|| self.others.get(&name).copied()) }
+
+    fn layouts(&self) -> Option<&Layouts> { Some(&self.layouts) }
+
+    /// A variable or a function without a `constexpr` body is not a
+    /// constant, and any other name is undeclared, unless it is qualified
+    /// or in a template, or declarations may not have been seen.
+    fn unresolved(&self, name: Symbol, span: Span) -> Unevaluated {
+        let text = name.as_str();
+        // Names reserved to the implementation may be built-ins.
+        if !self.complete || self.templates > 0 || text.contains("::") || text.starts_with("__") || self.unknown.contains(&name) { return Unevaluated::Unknown; }
+        if self.types.contains_key(&name) || self.others.contains_key(&name) || self.functions.contains_key(&name) {
+            return Unevaluated::Error(SemaError::NotConstant, span);
+        }
+        Unevaluated::Error(SemaError::Undeclared(name), span)
+    }
+}
+
+/// Names looked up with a function, as integers.
+struct Lookup<'f>(&'f dyn Fn(Symbol) -> Option<i128>);
+
+impl Environment<'_> for Lookup<'_> {
+    fn constant(&self, name: Symbol) -> Option<Constant> { (self.0)(name).map(|n| Constant::int(n, literal_type(n))) }
+}
+
+/// The integer value of `e`, looking names up with `lookup`; a floating
+/// value is not one.
+pub fn evaluate(e: &Expr, lookup: &dyn Fn(Symbol) -> Option<i128>) -> Result<i128, Unevaluated> {
+    value(e, &Lookup(lookup))?.as_int().ok_or(Unevaluated::Error(SemaError::NotConstant, e.span))
+}
+
+/// The value of `e`, with its names looked up in `env`.
+pub fn value<'a>(e: &Expr, env: &dyn Environment<'a>) -> Result<Constant, Unevaluated> {
+    Evaluator { env, frames: Vec::new(), steps: 0 }.expr(e)
+}
+
+/// How a statement of a `constexpr` function ends.
+enum Flow {
+    Next,
+    Break,
+    Continue,
+    Return(Option<Constant>),
+}
+
+struct Evaluator<'e, 'a> {
+    env: &'e dyn Environment<'a>,
+    /// The parameters and locals of the `constexpr` calls being evaluated,
+    /// innermost last.
+    frames: Vec<HashMap<Symbol, Constant>>,
+    steps: usize,
+}
+
+impl<'a> Evaluator<'_, 'a> {
+    fn expr(&mut self, e: &Expr) -> Result<Constant, Unevaluated> {
+        let not_constant = || Err(Unevaluated::Error(SemaError::NotConstant, e.span));
+        match &e.kind {
+            ExprKind::IntLiteral(n, suffix) => Ok(Constant::int(*n as i128, builtin(suffix.ty(*n)))),
+            ExprKind::FloatLiteral(v) => Ok(Constant::float(*v, builtin(Type::Double))),
+            ExprKind::CharLiteral(c) => {
+                let charsets = self.charsets();
+                let ty = typeck::literal_type(e, charsets).ok_or(Unevaluated::Unknown)?;
+                c.char_value(charsets).map(|n| Constant::int(n, ty)).or_else(|_| not_constant())
+            }
+            ExprKind::BoolLiteral(b) => Ok(Constant::int(*b as i128, builtin(Type::Bool))),
+            ExprKind::Name(name) => {
+                let local = self.frames.last().and_then(|frame| frame.get(&name.symbol()).copied());
+                local.or_else(|| self.env.constant(name.symbol())).ok_or_else(|| self.env.unresolved(name.symbol(), e.span))
+            }
+            ExprKind::Paren(inner) => self.expr(inner),
+            ExprKind::Unary { op, operand } => self.unary(e, *op, operand),
+            ExprKind::Binary { op, lhs, rhs } if op.is_assignment() => self.assign(e, *op, lhs, rhs),
+            ExprKind::Binary { op, lhs, rhs } => self.binary(e, *op, lhs, rhs),
+            // Either operand has the type the two have in common.
+            ExprKind::Conditional { cond, then, otherwise } => {
+                let value = if !self.expr(cond)?.is_zero() { self.expr(then)? } else { self.expr(otherwise)? };
+                match self.type_of(e) {
+                    Ok(ty) if is_arithmetic(ty) => convert(value, ty, e.span),
+                    Err(error @ Unevaluated::Error(..)) => Err(error),
+                    _ => Ok(value),
+                }
+            }
+            ExprKind::Cast { kind: CastKind::Reinterpret | CastKind::Dynamic, .. } => not_constant(),
+            ExprKind::Cast { ty, operand, .. } => convert(self.expr(operand)?, *ty, e.span),
+            ExprKind::Construct { ty, args, .. } => match args.as_slice() {
+                [] => convert(Constant::int(0, builtin(Type::Int)), *ty, e.span),
+                [arg] => convert(self.expr(arg)?, *ty, e.span),
+                _ => not_constant(),
+            },
+            ExprKind::Call { callee, args } => self.call(e, callee, args),
+            ExprKind::SizeofType(ty) => self.size(e, *ty, Layouts::size_of),
+            ExprKind::Alignof(ty) => self.size(e, *ty, Layouts::align_of),
+            ExprKind::SizeofExpr(operand) => {
+                let ty = self.type_of(operand)?;
+                self.size(e, ty, Layouts::size_of)
+            }
+            // These need types or lookup.
+            ExprKind::TemplateId { .. } | ExprKind::Member { .. } | ExprKind::Index { .. } | ExprKind::UserLiteral { .. } | ExprKind::Requires { .. } => {
+                Err(Unevaluated::Unknown)
+            }
+            ExprKind::StringLiteral(_) | ExprKind::Nullptr | ExprKind::This | ExprKind::InitList(_) | ExprKind::Designated { .. } | ExprKind::ParenList(_)
+            | ExprKind::CoAwait(_) | ExprKind::CoYield(_) => not_constant(),
+            // One byte is a constant; more are a list.
+            ExprKind::Embed(bytes) => match **bytes {
+                [b] => Ok(Constant::int(b as i128, builtin(Type::Int))),
+                _ => not_constant(),
+            },
+        }
+    }
+
+    /// The character sets literals are encoded in.
+    fn charsets(&self) -> ExecCharsets { self.env.layouts().map_or_else(ExecCharsets::default, Layouts::charsets) }
+
+    /// `sizeof` or `alignof` of `ty`, as `measure` finds it: a `size_t`.
+    /// A type known to have no size, or too large a one, is an error.
+    fn size(&self, e: &Expr, ty: TypeId, measure: fn(&Layouts, TypeId) -> Result<u64, LayoutError>) -> Result<Constant, Unevaluated> {
+        let layouts = self.env.layouts().ok_or(Unevaluated::Unknown)?;
+        match measure(layouts, ty) {
+            Ok(n) => Ok(Constant::int(n as i128, builtin(Type::UnsignedLong))),
+            Err(error @ (LayoutError::Incomplete(_) | LayoutError::Function(_) | LayoutError::TooLarge(_))) => Err(Unevaluated::Error(SemaError::NoLayout(error), e.span)),
+            Err(_) => Err(Unevaluated::Unknown),
+        }
+    }
+
+    /// The type of `e`, which is not evaluated, as the compiler gives it:
+    /// with operands promoted and brought to a common type, whatever
+    /// their values.
+    fn type_of(&self, e: &Expr) -> Result<TypeId, Unevaluated> {
+        if let Some(ty) = typeck::literal_type(e, self.charsets()) { return Ok(ty); }
+        let bool_ty = builtin(Type::Bool);
+        let arithmetic = |ty: TypeId| Some(ty.unqualified()).filter(|&ty| is_arithmetic(ty)).ok_or(Unevaluated::Unknown);
+        match &e.kind {
+            ExprKind::Paren(inner) => self.type_of(inner),
+            ExprKind::Name(name) => {
+                let symbol = name.symbol();
+                let local = self.frames.last().and_then(|frame| frame.get(&symbol)).map(|c| c.ty);
+                let ty = local.or_else(|| self.env.variable_type(symbol)).or_else(|| self.env.constant(symbol).map(|c| c.ty)).or_else(|| self.env.function_type(symbol));
+                ty.ok_or_else(|| self.env.unresolved(symbol, e.span))
+            }
+            ExprKind::Index { base, .. } | ExprKind::Unary { op: UnaryOp::Deref, operand: base } => match self.type_of(base)?.unqualified().get() {
+                Type::Array(element, _) | Type::Pointer(element) => Ok(element),
+                _ => Err(Unevaluated::Unknown),
+            },
+            ExprKind::Unary { op: UnaryOp::Not, .. } => Ok(bool_ty),
+            ExprKind::Unary { op: UnaryOp::AddressOf, operand } => Ok(builtin(Type::Pointer(self.type_of(operand)?))),
+            ExprKind::Unary { op: UnaryOp::Plus | UnaryOp::Minus | UnaryOp::BitNot, operand } => Ok(promote(arithmetic(self.type_of(operand)?)?)),
+            ExprKind::Unary { operand, .. } => self.type_of(operand),
+            ExprKind::Binary { op, lhs, rhs } => match op {
+                BinaryOp::Lt | BinaryOp::Gt | BinaryOp::Le | BinaryOp::Ge | BinaryOp::Eq | BinaryOp::Ne | BinaryOp::LogicalAnd | BinaryOp::LogicalOr => Ok(bool_ty),
+                BinaryOp::ThreeWay => Ok(builtin(Type::Int)),
+                BinaryOp::Comma => self.type_of(rhs),
+                op if op.is_assignment() => self.type_of(lhs),
+                BinaryOp::Shl | BinaryOp::Shr => Ok(promote(arithmetic(self.type_of(lhs)?)?)),
+                _ => Ok(arithmetic_conversion(arithmetic(self.type_of(lhs)?)?, arithmetic(self.type_of(rhs)?)?)),
+            },
+            ExprKind::Conditional { then, otherwise, .. } => {
+                let (a, b) = (self.type_of(then)?, self.type_of(otherwise)?);
+                if a.unqualified() == b.unqualified() { return Ok(a.unqualified()); }
+                Ok(arithmetic_conversion(arithmetic(a)?, arithmetic(b)?))
+            }
+            ExprKind::Cast { ty, .. } | ExprKind::Construct { ty, .. } => Ok(*ty),
+            ExprKind::Call { callee, args } => match &callee.kind {
+                ExprKind::Name(name) => match self.env.function(name.symbol(), args.len()).map(|f| f.ty.get()) {
+                    Some(Type::Function { ret, .. }) => Ok(ret),
+                    _ => Err(Unevaluated::Unknown),
+                },
+                _ => Err(Unevaluated::Unknown),
+            },
+            ExprKind::SizeofExpr(_) | ExprKind::SizeofType(_) | ExprKind::Alignof(_) => Ok(builtin(Type::UnsignedLong)),
+            ExprKind::Embed(_) => Ok(builtin(Type::Int)),
+            _ => Err(Unevaluated::Unknown),
+        }
+    }
+
+    fn unary(&mut self, e: &Expr, op: UnaryOp, operand: &Expr) -> Result<Constant, Unevaluated> {
+        let step = match op {
+            UnaryOp::PreIncrement | UnaryOp::PostIncrement => Some(BinaryOp::Add),
+            UnaryOp::PreDecrement | UnaryOp::PostDecrement => Some(BinaryOp::Sub),
+            _ => None,
+        };
+        if let Some(step) = step {
+            let old = self.expr(operand)?;
+            let one = Constant::int(1, builtin(Type::Int));
+            let new = arithmetic(e, step, old, one)?;
+            self.store(e, operand, new)?;
+            return Ok(if matches!(op, UnaryOp::PostIncrement | UnaryOp::PostDecrement) { old } else { new });
+        }
+        let v = self.expr(operand)?;
+        let ty = if is_floating(v.ty) { v.ty } else { promote(v.ty) };
+        match (op, v.value) {
+            (UnaryOp::Not, _) => Ok(Constant::int(v.is_zero() as i128, builtin(Type::Bool))),
+            (UnaryOp::Plus, Value::Int(n)) => Ok(Constant::int(n, ty)),
+            (UnaryOp::Minus, Value::Int(n)) => fit(-n, ty, e.span),
+            (UnaryOp::BitNot, Value::Int(n)) => fit(!n, ty, e.span),
+            (UnaryOp::Plus, Value::Float(x)) => Ok(Constant::float(x, ty)),
+            (UnaryOp::Minus, Value::Float(x)) => Ok(Constant::float(-x, ty)),
+            _ => Err(Unevaluated::Error(SemaError::NotConstant, e.span)),
+        }
+    }
+
+    fn binary(&mut self, e: &Expr, op: BinaryOp, lhs: &Expr, rhs: &Expr) -> Result<Constant, Unevaluated> {
+        let l = self.expr(lhs)?;
+        // The right operand of `&&` and `||` is only evaluated if needed.
+        let bool = |b: bool| Ok(Constant::int(b as i128, builtin(Type::Bool)));
+        match op {
+            BinaryOp::LogicalAnd if l.is_zero() => return bool(false),
+            BinaryOp::LogicalOr if !l.is_zero() => return bool(true),
+            BinaryOp::LogicalAnd | BinaryOp::LogicalOr => return bool(!self.expr(rhs)?.is_zero()),
+            BinaryOp::Comma => return self.expr(rhs),
+            _ => {}
+        }
+        let r = self.expr(rhs)?;
+        arithmetic(e, op, l, r).map_err(|error| match error {
+            Unevaluated::Error(SemaError::DivisionByZero, _) => Unevaluated::Error(SemaError::DivisionByZero, rhs.span),
+            Unevaluated::Error(error @ SemaError::ShiftCount { .. }, _) => Unevaluated::Error(error, rhs.span),
+            other => other,
+        })
+    }
+
+    fn assign(&mut self, e: &Expr, op: BinaryOp, lhs: &Expr, rhs: &Expr) -> Result<Constant, Unevaluated> {
+        let value = match compound(op) {
+            Some(op) => {
+                let l = self.expr(lhs)?;
+                let r = self.expr(rhs)?;
+                arithmetic(e, op, l, r)?
+            }
+            None => self.expr(rhs)?,
+        };
+        self.store(e, lhs, value)
+    }
+
+    /// Assigns `value` to the local `target` names, converted to its type.
+    fn store(&mut self, e: &Expr, target: &Expr, value: Constant) -> Result<Constant, Unevaluated> {
+        let name = match &target.kind {
+            ExprKind::Name(name) => name.symbol(),
+            ExprKind::Paren(inner) => return self.store(e, inner, value),
+            _ => return Err(Unevaluated::Error(SemaError::NotConstant, e.span)),
+        };
+        // Only a `constexpr` function's own locals may change.
+        let Some(old) = self.frames.last().and_then(|frame| frame.get(&name).copied()) else { return Err(Unevaluated::Error(SemaError::NotConstant, e.span)) };
+        let new = convert(value, old.ty, e.span)?;
+        if let Some(frame) = self.frames.last_mut() { frame.insert(name, new); }
+        Ok(new)
+    }
+
+    fn call(&mut self, e: &Expr, callee: &Expr, args: &[Expr]) -> Result<Constant, Unevaluated> {
+        let ExprKind::Name(name) = &callee.kind else { return Err(Unevaluated::Unknown) };
+        let Some(f) = self.env.function(name.symbol(), args.len()) else { return Err(self.env.unresolved(name.symbol(), callee.span)) };
+        let Type::Function { ret, params, .. } = f.ty.get() else { return Err(Unevaluated::Unknown) };
+        self.tick(e.span)?;
+        if self.frames.len() >= MAX_DEPTH { return Err(Unevaluated::Error(SemaError::ConstexprLimit { limit: MAX_DEPTH, what: "nested calls" }, e.span)); }
+        let mut frame = HashMap::new();
+        for (i, p) in f.params.iter().enumerate() {
+            let arg = match (args.get(i), &p.default) {
+                (Some(arg), _) => self.expr(arg)?,
+                (None, Some(default)) => self.expr(default)?,
+                (None, None) => return Err(Unevaluated::Unknown),
+            };
+            let ty = params.get(i).copied().unwrap_or(p.ty);
+            if let Some(name) = p.name { frame.insert(name, convert(arg, ty.unqualified(), e.span)?); }
+        }
+        self.frames.push(frame);
+        // The stack grows as needed, so the limit is on nesting alone.
+        let flow = stacker::maybe_grow(RED_ZONE, STACK_SEGMENT, || f.body.as_ref().map_or(Err(Unevaluated::Unknown), |body| self.block(body)));
+        self.frames.pop();
+        match flow? {
+            Flow::Return(Some(v)) => convert(v, ret.unqualified(), e.span),
+            Flow::Return(None) if ret.get() == Type::Void => Ok(Constant::int(0, ret)),
+            // Flowing off the end of a non-void function.
+            _ => Err(Unevaluated::Error(SemaError::NotConstant, e.span)),
+        }
+    }
+
+    fn block(&mut self, block: &Block) -> Result<Flow, Unevaluated> {
+        for stmt in &block.stmts {
+            match self.stmt(stmt)? {
+                Flow::Next => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result<Flow, Unevaluated> {
+        self.tick(stmt.span)?;
+        match &stmt.kind {
+            StmtKind::Compound(block) => self.block(block),
+            StmtKind::Null => Ok(Flow::Next),
+            StmtKind::Expr(e) => self.expr(e).map(|_| Flow::Next),
+            StmtKind::Decl(decls) => {
+                for decl in decls {
+                    let Decl::Var(v) = decl else { return Err(Unevaluated::Unknown) };
+                    let Some(init) = &v.init else { return Err(Unevaluated::Unknown) };
+                    let value = convert(self.expr(init)?, v.ty.unqualified(), init.span)?;
+                    if let Some(frame) = self.frames.last_mut() { frame.insert(v.name, value); }
+                }
+                Ok(Flow::Next)
+            }
+            StmtKind::Return(value) => Ok(Flow::Return(value.as_ref().map(|e| self.expr(e)).transpose()?)),
+            StmtKind::If { cond, then, otherwise } => {
+                if !self.expr(cond)?.is_zero() { return self.stmt(then); }
+                otherwise.as_ref().map_or(Ok(Flow::Next), |s| self.stmt(s))
+            }
+            StmtKind::While { cond, body } => {
+                while !self.expr(cond)?.is_zero() {
+                    match self.stmt(body)? {
+                        Flow::Break => break,
+                        Flow::Return(v) => return Ok(Flow::Return(v)),
+                        Flow::Next | Flow::Continue => {}
+                    }
+                }
+                Ok(Flow::Next)
+            }
+            StmtKind::DoWhile { body, cond } => {
+                loop {
+                    match self.stmt(body)? {
+                        Flow::Break => break,
+                        Flow::Return(v) => return Ok(Flow::Return(v)),
+                        Flow::Next | Flow::Continue => {}
+                    }
+                    if self.expr(cond)?.is_zero() { break; }
+                }
+                Ok(Flow::Next)
+            }
+            StmtKind::For { init, cond, step, body } => {
+                if let Some(init) = init { self.stmt(init)?; }
+                while cond.as_ref().map_or(Ok(true), |c| self.expr(c).map(|v| !v.is_zero()))? {
+                    match self.stmt(body)? {
+                        Flow::Break => break,
+                        Flow::Return(v) => return Ok(Flow::Return(v)),
+                        Flow::Next | Flow::Continue => {}
+                    }
+                    if let Some(step) = step { self.expr(step)?; }
+                }
+                Ok(Flow::Next)
+            }
+            StmtKind::Break => Ok(Flow::Break),
+            StmtKind::Continue => Ok(Flow::Continue),
+            StmtKind::Attributed { stmt, .. } => self.stmt(stmt),
+            _ => Err(Unevaluated::Unknown),
+        }
+    }
+
+    fn tick(&mut self, span: Span) -> Result<(), Unevaluated> {
+        self.steps += 1;
+        if self.steps > MAX_STEPS { return Err(Unevaluated::Error(SemaError::ConstexprLimit { limit: MAX_STEPS, what: "steps" }, span)); }
+        Ok(())
+    }
+}
+
+/// `l op r` for a binary operator other than an assignment, `&&`, `||`
+/// and `,`, after the usual arithmetic conversions.
+fn arithmetic(e: &Expr, op: BinaryOp, l: Constant, r: Constant) -> Result<Constant, Unevaluated> {
+    let error = |error: SemaError| Err(Unevaluated::Error(error, e.span));
+    let bool = |b: bool| Ok(Constant::int(b as i128, builtin(Type::Bool)));
+    if matches!(op, BinaryOp::Shl | BinaryOp::Shr) {
+        let (Value::Int(n), Value::Int(count)) = (l.value, r.value) else { return error(SemaError::NotConstant) };
+        let ty = promote(l.ty);
+        if count < 0 || count >= width(ty) as i128 { return error(SemaError::ShiftCount { count, ty }); }
+        // A left shift is taken modulo 2^N, so `1 << 31` is `INT_MIN`.
+        return Ok(Constant::int(if op == BinaryOp::Shl { wrap(n.wrapping_mul(1i128 << count), ty) } else { n >> count }, ty));
+    }
+    let ty = arithmetic_conversion(l.ty, r.ty);
+    if is_floating(ty) {
+        let (x, y) = (l.as_float(), r.as_float());
+        let result = match op {
+            BinaryOp::Mul => x * y,
+            BinaryOp::Div if y == 0.0 => return error(SemaError::DivisionByZero),
+            BinaryOp::Div => x / y,
+            BinaryOp::Add => x + y,
+            BinaryOp::Sub => x - y,
+            BinaryOp::Lt => return bool(x < y),
+            BinaryOp::Gt => return bool(x > y),
+            BinaryOp::Le => return bool(x <= y),
+            BinaryOp::Ge => return bool(x >= y),
+            BinaryOp::Eq => return bool(x == y),
+            BinaryOp::Ne => return bool(x != y),
+            _ => return error(SemaError::NotConstant),
+        };
+        return Ok(Constant::float(round(result, ty), ty));
+    }
+    let (Value::Int(x), Value::Int(y)) = (l.value, r.value) else { return error(SemaError::NotConstant) };
+    // Both in the common type, so `-1 < 0u` compares 2^32 - 1 with 0.
+    let (x, y) = (wrap(x, ty), wrap(y, ty));
+    let result = match op {
+        BinaryOp::Mul => x.wrapping_mul(y),
+        BinaryOp::Div | BinaryOp::Rem if y == 0 => return error(SemaError::DivisionByZero),
+        BinaryOp::Div => x / y,
+        BinaryOp::Rem => x % y,
+        BinaryOp::Add => x + y,
+        BinaryOp::Sub => x - y,
+        BinaryOp::ThreeWay => return Ok(Constant::int(x.cmp(&y) as i128, builtin(Type::Int))),
+        BinaryOp::Lt => return bool(x < y),
+        BinaryOp::Gt => return bool(x > y),
+        BinaryOp::Le => return bool(x <= y),
+        BinaryOp::Ge => return bool(x >= y),
+        BinaryOp::Eq => return bool(x == y),
+        BinaryOp::Ne => return bool(x != y),
+        BinaryOp::BitAnd => x & y,
+        BinaryOp::BitXor => x ^ y,
+        BinaryOp::BitOr => x | y,
+        _ => return error(SemaError::NotConstant),
+    };
+    fit(result, ty, e.span)
+}
+
+/// The operator a compound assignment applies.
+fn compound(op: BinaryOp) -> Option<BinaryOp> {
+    Some(match op {
+        BinaryOp::MulAssign => BinaryOp::Mul,
+        BinaryOp::DivAssign => BinaryOp::Div,
+        BinaryOp::RemAssign => BinaryOp::Rem,
+        BinaryOp::AddAssign => BinaryOp::Add,
+        BinaryOp::SubAssign => BinaryOp::Sub,
+        BinaryOp::ShlAssign => BinaryOp::Shl,
+        BinaryOp::ShrAssign => BinaryOp::Shr,
+        BinaryOp::AndAssign => BinaryOp::BitAnd,
+        BinaryOp::XorAssign => BinaryOp::BitXor,
+        BinaryOp::OrAssign => BinaryOp::BitOr,
+        _ => return None,
+    })
+}
+
+/// `value` as a result of type `ty`: wrapped if `ty` is unsigned, and an
+/// error if it is signed and cannot hold it.
+fn fit(value: i128, ty: TypeId, span: Span) -> Result<Constant, Unevaluated> {
+    match ty.integer_range() {
+        Some((0, _)) => Ok(Constant::int(wrap(value, ty), ty)),
+        Some((min, max)) if !(min..=max).contains(&value) => Err(Unevaluated::Error(SemaError::OutOfRange { value, ty }, span)),
+        _ => Ok(Constant::int(value, ty)),
+    }
+}
+
+/// `c` converted to `ty`: an integer wrapped modulo the width of an
+/// integral type, a floating value truncated to one, which must hold it,
+/// and either rounded to a floating type. Other types, such as enums,
+/// keep the value as is.
+pub fn convert(c: Constant, ty: TypeId, span: Span) -> Result<Constant, Unevaluated> {
+    let ty = ty.unqualified();
+    if is_floating(ty) { return Ok(Constant::float(round(c.as_float(), ty), ty)); }
+    let Some((min, max)) = ty.integer_range() else { return Ok(Constant { value: c.value, ty }) };
+    match c.value {
+        _ if (min, max) == (0, 1) => Ok(Constant::int(!c.is_zero() as i128, ty)),
+        Value::Int(n) => Ok(Constant::int(wrap(n, ty), ty)),
+        Value::Float(x) => {
+            let truncated = x.trunc();
+            if !(min as f64..=max as f64).contains(&truncated) {
+                return Err(Unevaluated::Error(SemaError::OutOfRange { value: truncated.clamp(i128::MIN as f64, i128::MAX as f64) as i128, ty }, span));
+            }
+            Ok(Constant::int(truncated as i128, ty))
+        }
+    }
+}
+
+/// `value` modulo the width of the integral type `ty`, in its range.
+fn wrap(value: i128, ty: TypeId) -> i128 {
+    let Some((min, max)) = ty.integer_range() else { return value };
+    let width = max - min + 1;
+    (value - min).rem_euclid(width) + min
+}
+
+/// `value` rounded to the precision of the floating type `ty`.
+fn round(value: f64, ty: TypeId) -> f64 { if ty.get() == Type::Float { value as f32 as f64 } else { value } }
+
+/// The bits of an integral type.
+fn width(ty: TypeId) -> u32 {
+    let (min, max) = ty.integer_range().unwrap_or((0, 1));
+    (max - min + 1).ilog2()
+}
+
+fn is_floating(ty: TypeId) -> bool { matches!(ty.get(), Type::Float | Type::Double | Type::LongDouble) }
+
+fn is_arithmetic(ty: TypeId) -> bool { ty.integer_range().is_some() || is_floating(ty) }
+
+/// The type given an enumerator or a looked-up constant with value `n`:
+/// the first of `int`, `long` and `unsigned long long` that holds it.
+fn literal_type(n: i128) -> TypeId {
+    builtin(match n {
+        n if (i32::MIN as i128..=i32::MAX as i128).contains(&n) => Type::Int,
+        n if (i64::MIN as i128..=i64::MAX as i128).contains(&n) => Type::Long,
+        _ => Type::UnsignedLongLong,
+    })
+}
+
+fn builtin(ty: Type) -> TypeId { TypeId::intern(ty) }
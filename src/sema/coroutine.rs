@@ -0,0 +1,75 @@
+//! Coroutines: functions whose bodies use `co_await`, `co_yield` or
+//! `co_return`.
+//!
+//! Nothing is lowered yet. These are the checks that need no promise
+//! type: the operators appear only in a function body, and a coroutine is
+//! not a constructor, destructor, `main` or `constexpr`, takes no C
+//! variadic arguments, does not deduce its return type and returns with
+//! `co_return` only.
+
+use crate::ast::{Block, Decl, Expr, ExprKind, FunctionDecl, FunctionKind, Stmt, StmtKind};
+use crate::diagnostics::Diagnostics;
+use crate::sema::SemaError;
+use crate::types::Type;
+use crate::visit::Visitor;
+
+/// Checks `f` if it is a coroutine.
+pub fn check(f: &FunctionDecl, diagnostics: &mut Diagnostics) {
+    if !f.is_coroutine { return; }
+    if let Some(rule) = signature_error(f) { diagnostics.emit(SemaError::Coroutine(rule).to_diagnostic(f.name_span)); }
+    if let Some(body) = &f.body { returns(body, diagnostics); }
+}
+
+/// Reports each `co_await` and `co_yield` in `e`, which is outside any
+/// function body: a default argument or a variable's initializer at
+/// namespace or class scope.
+pub fn outside_body(e: &Expr, diagnostics: &mut Diagnostics) { OutsideBody(diagnostics).visit_expr(e) }
+
+struct OutsideBody<'d>(&'d mut Diagnostics);
+
+impl Visitor for OutsideBody<'_> {
+    fn visit_expr(&mut self, e: &Expr) {
+        let keyword = match &e.kind {
+            ExprKind::CoAwait(_) => "co_await",
+            ExprKind::CoYield(_) => "co_yield",
+            // Unevaluated.
+            ExprKind::SizeofExpr(_) | ExprKind::Requires { .. } => return,
+            _ => return self.walk_expr(e),
+        };
+        self.0.emit(SemaError::CoroutineOutsideFunction(keyword).to_diagnostic(e.span));
+        self.walk_expr(e);
+    }
+}
+
+fn signature_error(f: &FunctionDecl) -> Option<&'static str> {
+    let Type::Function { ret, variadic, .. } = f.ty.get() else { return None };
+    match f.kind {
+        FunctionKind::Constructor => return Some("a constructor cannot be a coroutine"),
+        FunctionKind::Destructor => return Some("a destructor cannot be a coroutine"),
+        FunctionKind::Normal | FunctionKind::Conversion => {}
+    }
+    if &*f.name.as_str() == "main" { return Some("`main` cannot be a coroutine"); }
+    if f.specifiers.is_constexpr { return Some("a `constexpr` function cannot be a coroutine"); }
+    if variadic { return Some("a coroutine cannot take variadic arguments"); }
+    if ret.unqualified().get() == Type::Auto { return Some("a coroutine cannot have a deduced return type"); }
+    None
+}
+
+/// Reports each `return` in a coroutine's `body`, outside any local
+/// class.
+fn returns(body: &Block, diagnostics: &mut Diagnostics) { Returns(diagnostics).visit_block(body) }
+
+struct Returns<'d>(&'d mut Diagnostics);
+
+impl Visitor for Returns<'_> {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if let StmtKind::Return(_) = stmt.kind { self.0.emit(SemaError::ReturnInCoroutine.to_diagnostic(stmt.span)); }
+        self.walk_stmt(stmt);
+    }
+
+    // Expressions hold no statements, and local classes' member functions
+    // are not part of the coroutine.
+    fn visit_expr(&mut self, _: &Expr) {}
+
+    fn visit_decl(&mut self, _: &Decl) {}
+}
@@ -0,0 +1,71 @@
+//! Enumerator values.
+//!
+//! An enumerator without `= value` is one more than the one before it, or
+//! 0 if it is the first. Every value must fit the enum's underlying type:
+//! the one written after `:`, which may be a typedef or alias of one,
+//! `int` for a scoped enum, and otherwise any type up to `long long` or
+//! `unsigned long long`.
+
+use std::collections::HashMap;
+
+use crate::ast::EnumDecl;
+use crate::diagnostics::Diagnostics;
+use crate::intern::Symbol;
+use crate::sema::constant::{self, Unevaluated};
+use crate::sema::layout::Layouts;
+use crate::sema::SemaError;
+use crate::types::{Type, TypeId};
+
+/// Assigns each enumerator of `e` its value, `None` where that depends on
+/// something not known here. Values of the enumerators of earlier enums
+/// come from `constants`, and these are added to it: by their own name for
+/// an unscoped enum, and as `E::name` for a named one. `layouts` knows the
+/// typedefs declared before `e`.
+pub fn values(e: &EnumDecl, layouts: &Layouts, constants: &mut HashMap<Symbol, i128>, diagnostics: &mut Diagnostics) -> Vec<Option<i128>> {
+    let fixed = match e.underlying.map(|ty| (ty, layouts.canonical(ty))) {
+        Some((ty, canonical)) if canonical.integer_range().is_none() => {
+            diagnostics.emit(SemaError::NotIntegral(ty).to_diagnostic(e.span));
+            None
+        }
+        Some((_, canonical)) => Some(canonical),
+        None if e.is_scoped => Some(TypeId::intern(Type::Int)),
+        None => None,
+    };
+    let (min, max) = match fixed {
+        Some(ty) => ty.integer_range().unwrap_or_default(),
+        None => (i64::MIN as i128, u64::MAX as i128),
+    };
+    let mut values = Vec::new();
+    // Inside the enum, even a scoped one's enumerators need no `E::`.
+    let mut own = HashMap::new();
+    let mut next = Some(0);
+    for c in e.enumerators.iter().flatten() {
+        let (value, span) = match &c.value {
+            Some(v) => match constant::evaluate(v, &|name| own.get(&name).or(constants.get(&name)).copied()) {
+                Ok(n) => (Some(n), v.span),
+                Err(Unevaluated::Unknown) => (None, v.span),
+                Err(Unevaluated::Error(error, span)) => {
+                    diagnostics.emit(error.to_diagnostic(span));
+                    (None, v.span)
+                }
+            },
+            None => (next, c.span),
+        };
+        let value = value.filter(|&n| {
+            let fits = (min..=max).contains(&n);
+            if !fits {
+                let ty = fixed.unwrap_or_else(|| TypeId::intern(if n < 0 { Type::LongLong } else { Type::UnsignedLongLong }));
+                diagnostics.emit(SemaError::OutOfRange { value: n, ty }.to_diagnostic(span));
+            }
+            fits
+        });
+        if let Some(n) = value {
+            own.insert(c.name, n);
+            if !e.is_scoped { constants.insert(c.name, n); }
+            if let Some(name) = e.name { constants.insert(Symbol::intern(&format!("{}::{}", name, c.name)), n); }
+        }
+        next = value.map(|n| n + 1);
+        values.push(value);
+    }
+    values
+}
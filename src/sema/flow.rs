@@ -0,0 +1,217 @@
+//! Checks over the control flow of each function body, on its [`Cfg`]:
+//! non-void functions that can run off their end (`-Wreturn-type`),
+//! statements no path reaches (`-Wunreachable-code`) and loops that never
+//! end and do nothing observable (`-Winfinite-loop`), which a C++
+//! implementation may assume terminate. The last two are in `-Wall`, as
+//! are the reads of locals that may come before they are assigned, which
+//! [`uninit`](super::uninit) finds on the same graphs.
+//!
+//! Calls of `abort`, `exit`, `std::terminate` and the like, and of the
+//! functions of the unit declared `[[noreturn]]`, never return. An
+//! unreachable `break` is not reported, as after a `return` at the end of
+//! a `case`. A loop does something observable if it calls a function,
+//! suspends a coroutine, constructs a class object or writes to anything
+//! but the function's own non-`volatile`, non-`static` variables.
+
+use std::collections::HashSet;
+
+use crate::ast::{Attribute, BinaryOp, Expr, ExprKind, FunctionDecl, FunctionKind, Stmt, StmtKind, StorageClass, TranslationUnit, UnaryOp, VarDecl};
+use crate::diagnostics::{Diagnostic, Diagnostics, Label, Warning};
+use crate::intern::Symbol;
+use crate::lexer::token::Span;
+use crate::sema::cfg::{BlockId, Cfg, Element};
+use crate::sema::uninit::{self, Signatures};
+use crate::types::{Type, TypeId};
+use crate::visit::Visitor;
+
+/// Functions of the standard library that never return.
+const NO_RETURN: [&str; 7] = ["abort", "exit", "_Exit", "quick_exit", "terminate", "unreachable", "__builtin_unreachable"];
+
+/// Reports what the control flow of each function body in `tu` shows, if
+/// those warnings are on.
+pub fn check(tu: &TranslationUnit, diagnostics: &mut Diagnostics) {
+    let checks = [Warning::ReturnType, Warning::UnreachableCode, Warning::InfiniteLoop, Warning::Uninitialized, Warning::MaybeUninitialized];
+    if !checks.into_iter().any(|w| diagnostics.warnings().is_enabled(w)) { return; }
+    let mut noreturn = NoReturn::default();
+    noreturn.visit_translation_unit(tu);
+    let mut checker = Checker { noreturn: noreturn.names, signatures: Signatures::of(tu), diagnostics };
+    checker.visit_translation_unit(tu);
+}
+
+/// Collects the names of the functions declared `[[noreturn]]`.
+#[derive(Default)]
+struct NoReturn {
+    names: HashSet<Symbol>,
+}
+
+impl Visitor for NoReturn {
+    fn visit_function(&mut self, f: &FunctionDecl) {
+        if Attribute::find(&f.attrs, &["noreturn", "gnu::noreturn", "_Noreturn"]).is_some() { self.names.insert(unqualified(f.name)); }
+        self.walk_function(f);
+    }
+}
+
+struct Checker<'d> {
+    noreturn: HashSet<Symbol>,
+    signatures: Signatures,
+    diagnostics: &'d mut Diagnostics,
+}
+
+impl Checker<'_> {
+    /// Whether `e` calls a function that never returns.
+    fn never_returns(&self, e: &Expr) -> bool {
+        let ExprKind::Call { callee, .. } = &e.kind else { return false };
+        let (ExprKind::Name(name) | ExprKind::TemplateId { name, .. }) = &callee.kind else { return false };
+        let text = name.symbol().as_str();
+        let spelling = text.trim_start_matches("::");
+        let name = spelling.strip_prefix("std::").unwrap_or(spelling);
+        NO_RETURN.contains(&name) || (!name.contains("::") && self.noreturn.contains(&Symbol::intern(name)))
+    }
+
+    fn function(&mut self, f: &FunctionDecl) {
+        let Some(body) = &f.body else { return };
+        let Some(cfg) = Cfg::build(body, &|e| self.never_returns(e)) else { return };
+        let reachable = cfg.reachable();
+
+        let returns_value = match f.ty.get() {
+            Type::Function { ret, .. } => !matches!(ret.unqualified().get(), Type::Void | Type::Auto),
+            _ => false,
+        };
+        if returns_value && reachable[cfg.end.0] && f.kind == FunctionKind::Normal && !f.is_coroutine && &*f.name.as_str() != "main" {
+            let brace = Span::new(body.span.end.saturating_sub(1), body.span.end);
+            self.diagnostics.emit(Diagnostic::warning(format!("non-void function `{}` does not return a value in all control paths", f.name))
+                .with_warning(Warning::ReturnType)
+                .with_label(Label::primary(brace, "control reaches the end of the function here")));
+        }
+
+        // Report where each unreachable stretch starts, in source order.
+        let mut covered = reachable.clone();
+        for (i, block) in cfg.blocks.iter().enumerate() {
+            if covered[i] { continue; }
+            let Some(first) = block.elements.iter().find(|e| !matches!(e, Element::Stmt(Stmt { kind: StmtKind::Break, .. }))) else { continue };
+            let span = match first {
+                Element::Stmt(stmt) => stmt.span,
+                Element::Expr(e) => e.span,
+                Element::Decl(decl) => decl.span(),
+            };
+            self.diagnostics.emit(Diagnostic::warning("code will never be executed").with_warning(Warning::UnreachableCode).with_label(Label::primary(span, "")));
+            for (seen, now) in covered.iter_mut().zip(cfg.reachable_from(BlockId(i))) { *seen |= now; }
+        }
+
+        let mut locals = Locals::default();
+        locals.params(f);
+        locals.visit_block(body);
+        for l in cfg.loops.iter().filter(|l| l.is_endless && reachable[l.header.0] && !reachable[l.after.0]) {
+            let mut effects = SideEffects { locals: &locals.names, found: false };
+            effects.visit_stmt(l.stmt);
+            if effects.found || returns_from(l.stmt) { continue; }
+            self.diagnostics.emit(Diagnostic::warning("infinite loop without side effects")
+                .with_warning(Warning::InfiniteLoop)
+                .with_label(Label::primary(l.stmt.span, ""))
+                .with_note("the behavior is undefined: a C++ implementation may assume that such a loop terminates"));
+        }
+
+        uninit::check(f, body, &cfg, &self.signatures, self.diagnostics);
+    }
+}
+
+impl Visitor for Checker<'_> {
+    fn visit_function(&mut self, f: &FunctionDecl) {
+        self.function(f);
+        self.walk_function(f);
+    }
+}
+
+/// The function's own parameters and variables that a loop may write to
+/// without being observed.
+#[derive(Default)]
+struct Locals {
+    names: HashSet<Symbol>,
+}
+
+impl Locals {
+    fn params(&mut self, f: &FunctionDecl) {
+        for p in &f.params {
+            if let Some(name) = p.name.filter(|_| !is_observable(p.ty)) { self.names.insert(name); }
+        }
+    }
+}
+
+impl Visitor for Locals {
+    fn visit_var(&mut self, v: &VarDecl) {
+        if v.specifiers.storage != StorageClass::Static && !is_observable(v.ty) { self.names.insert(v.name); }
+        self.walk_var(v);
+    }
+
+    // A local class's functions have locals of their own.
+    fn visit_function(&mut self, _f: &FunctionDecl) {}
+}
+
+/// Finds what a loop does that can be observed.
+struct SideEffects<'l> {
+    locals: &'l HashSet<Symbol>,
+    found: bool,
+}
+
+impl Visitor for SideEffects<'_> {
+    fn visit_expr(&mut self, e: &Expr) {
+        let written = match &e.kind {
+            ExprKind::Call { .. } | ExprKind::CoAwait(_) | ExprKind::CoYield(_) => {
+                self.found = true;
+                return;
+            }
+            ExprKind::Construct { ty, .. } if is_class(*ty) => {
+                self.found = true;
+                return;
+            }
+            ExprKind::Unary { op: UnaryOp::PreIncrement | UnaryOp::PreDecrement | UnaryOp::PostIncrement | UnaryOp::PostDecrement, operand } => Some(operand),
+            ExprKind::Binary { op, lhs, .. } if op.is_assignment() && *op != BinaryOp::Comma => Some(lhs),
+            _ => None,
+        };
+        if let Some(target) = written {
+            if !matches!(&strip_parens(target).kind, ExprKind::Name(name) if self.locals.contains(&name.symbol())) { self.found = true; }
+        }
+        self.walk_expr(e);
+    }
+
+    fn visit_var(&mut self, v: &VarDecl) {
+        if is_class(v.ty) { self.found = true; }
+        self.walk_var(v);
+    }
+}
+
+/// Whether `stmt` has a `return` or `co_return` in it.
+fn returns_from(stmt: &Stmt) -> bool {
+    struct Returns(bool);
+    impl Visitor for Returns {
+        fn visit_stmt(&mut self, stmt: &Stmt) {
+            if matches!(stmt.kind, StmtKind::Return(_) | StmtKind::CoReturn(_)) { self.0 = true; }
+            self.walk_stmt(stmt);
+        }
+    }
+    let mut returns = Returns(false);
+    returns.visit_stmt(stmt);
+    returns.0
+}
+
+fn strip_parens<'e, 'a>(e: &'e Expr<'a>) -> &'e Expr<'a> {
+    match &e.kind {
+        ExprKind::Paren(inner) => strip_parens(inner),
+        _ => e,
+    }
+}
+
+/// Whether writing to a variable of type `ty` can be observed: it is
+/// `volatile`, or a reference to something else.
+fn is_observable(ty: TypeId) -> bool { ty.qualifiers().is_volatile || matches!(ty.get(), Type::Reference(_) | Type::RvalueReference(_)) }
+
+/// Whether `ty` may be a class, whose construction may do anything.
+fn is_class(ty: TypeId) -> bool {
+    match ty.unqualified().get() {
+        Type::Array(element, _) => is_class(element),
+        Type::Named(_) | Type::Specialization(..) | Type::Auto => true,
+        _ => false,
+    }
+}
+
+fn unqualified(name: Symbol) -> Symbol { Symbol::intern(name.as_str().rsplit("::").next().unwrap_or_default()) }
@@ -0,0 +1,155 @@
+//! Inlay hints for editors: the parameter name each argument of a call
+//! initializes, and the type an `auto` variable or structured binding
+//! deduces.
+//!
+//! Both come from sema: a call is hinted with the parameters of the
+//! overload it resolves to, among the functions declared before it, and
+//! gets none if that is not known; a variable's type is the one its
+//! initializer's type deduces, or that `Bindings` deduces where that is
+//! not known, as for a braced initializer. Hints are for the main file
+//! only, in source order.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::ast::{Decl, DecompositionDecl, Expr, ExprKind, FunctionDecl, TranslationUnit, VarDecl};
+use crate::charset::ExecCharsets;
+use crate::diagnostics::Diagnostics;
+use crate::intern::Symbol;
+use crate::lexer::token::Span;
+use crate::sema::{self, Resolved};
+use crate::session::Target;
+use crate::types::{Type, TypeId};
+use crate::visit::Visitor;
+
+/// Which hints to produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HintOptions {
+    pub parameter_names: bool,
+    pub deduced_types: bool,
+}
+
+impl Default for HintOptions {
+    fn default() -> Self { Self { parameter_names: true, deduced_types: true } }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HintKind {
+    /// `name:` before an argument.
+    Parameter,
+    /// `: type` after a name declared `auto`.
+    Type,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlayHint {
+    /// The position the label is shown at, before the character there.
+    pub position: usize,
+    pub label: String,
+    pub kind: HintKind,
+}
+
+/// `name:` or `: int`.
+impl fmt::Display for InlayHint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str(&self.label) }
+}
+
+/// The hints `options` asks for in `tu`'s main file, compiled for
+/// `target` with literals in `charsets`.
+pub fn inlay_hints(tu: &TranslationUnit, target: &Target, charsets: ExecCharsets, options: HintOptions) -> Vec<InlayHint> {
+    // Errors are the checks' to report.
+    let resolved = sema::resolve(tu, target, charsets, &mut Diagnostics::new());
+    let mut hinter = Hinter { options, main: tu.span, resolved, params: HashMap::new(), hints: Vec::new() };
+    hinter.visit_translation_unit(tu);
+    hinter.hints.sort_by_key(|h| h.position);
+    hinter.hints
+}
+
+struct Hinter {
+    options: HintOptions,
+    main: Span,
+    resolved: Resolved,
+    /// The parameter names of each function, by unqualified name and
+    /// type, from whichever declarations name them.
+    params: HashMap<(Symbol, TypeId), Vec<Option<Symbol>>>,
+    hints: Vec<InlayHint>,
+}
+
+impl Hinter {
+    fn hint(&mut self, position: usize, label: String, kind: HintKind) {
+        if self.main.start <= position && position <= self.main.end { self.hints.push(InlayHint { position, label, kind }); }
+    }
+
+    /// `: type` after the name at `span`, if its type was deduced.
+    fn deduced(&mut self, span: Span) {
+        let Some(&ty) = self.resolved.types.get(&span) else { return };
+        if !has_auto(ty) { self.hint(span.end, format!(": {}", ty), HintKind::Type); }
+    }
+
+    fn decomposition(&mut self, d: &DecompositionDecl) {
+        for a in &d.attrs { self.visit_attribute(a); }
+        if let Some(init) = &d.init { self.visit_expr(init); }
+        if !self.options.deduced_types { return; }
+        for b in &d.bindings { self.deduced(b.span); }
+    }
+
+    /// `name:` before each argument of the call `e`, if the function it
+    /// calls is known.
+    fn call(&mut self, e: &Expr, args: &[Expr]) {
+        if !self.options.parameter_names { return; }
+        let Some(&(name, ty)) = self.resolved.calls.get(&e.span) else { return };
+        let Some(params) = self.params.get(&(unqualified(name), ty)) else { return };
+        let hints: Vec<(usize, String)> = params.iter().zip(args)
+            .filter_map(|(param, arg)| {
+                let param = (*param)?;
+                // `f(size)` for a parameter `size` says it already.
+                let same = matches!(&arg.kind, ExprKind::Name(n) if unqualified(n.symbol()) == param);
+                (!same).then(|| (arg.span.start, format!("{}:", param)))
+            })
+            .collect();
+        for (position, label) in hints { self.hint(position, label, HintKind::Parameter); }
+    }
+}
+
+impl Visitor for Hinter {
+    fn visit_decl(&mut self, decl: &Decl) {
+        match decl {
+            Decl::Decomposition(d) => self.decomposition(d),
+            _ => self.walk_decl(decl),
+        }
+    }
+
+    fn visit_function(&mut self, f: &FunctionDecl) {
+        let names = self.params.entry((unqualified(f.name), f.ty)).or_default();
+        names.resize(names.len().max(f.params.len()), None);
+        for (name, p) in names.iter_mut().zip(&f.params) {
+            if p.name.is_some() { *name = p.name; }
+        }
+        self.walk_function(f);
+    }
+
+    fn visit_var(&mut self, v: &VarDecl) {
+        self.walk_var(v);
+        if self.options.deduced_types && has_auto(v.ty) { self.deduced(v.name_span); }
+    }
+
+    fn visit_expr(&mut self, e: &Expr) {
+        if let ExprKind::Call { args, .. } = &e.kind { self.call(e, args); }
+        self.walk_expr(e);
+    }
+}
+
+fn has_auto(ty: TypeId) -> bool {
+    match ty.unqualified().get() {
+        Type::Auto => true,
+        Type::Reference(inner) | Type::RvalueReference(inner) | Type::Pointer(inner) => has_auto(inner),
+        _ => false,
+    }
+}
+
+fn unqualified(name: Symbol) -> Symbol {
+    match name.as_str().rsplit_once("::") {
+        Some((_, last)) => Symbol::intern(last),
+        None => name,
+    }
+}
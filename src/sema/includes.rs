@@ -0,0 +1,111 @@
+//! Standard library names used without the header that declares them.
+//!
+//! A name is missing its header when the reference index cannot resolve
+//! it and a builtin table maps it to a standard header: `std::` names by
+//! their qualified spelling, and the C library's by their bare one, so a
+//! program's own `printf` or `vector` is never taken for the library's.
+//! Only the main file is searched; names in headers are the headers'
+//! business.
+
+use std::collections::HashSet;
+
+use crate::lexer::token::{Span, SpannedToken, Token};
+use crate::sema::references::ReferenceIndex;
+use crate::source::{FileId, SourceFile, SourceManager};
+
+/// A use of `name`, which `header` declares.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingInclude {
+    pub span: Span,
+    pub name: String,
+    pub header: &'static str,
+}
+
+const HEADERS: &[(&str, &[&str])] = &[
+    ("algorithm", &["std::sort", "std::stable_sort", "std::find", "std::find_if", "std::count", "std::count_if", "std::min", "std::max", "std::reverse", "std::unique", "std::fill", "std::copy", "std::transform", "std::any_of", "std::all_of", "std::none_of", "std::lower_bound", "std::upper_bound"]),
+    ("array", &["std::array"]),
+    ("cassert", &["assert"]),
+    ("cmath", &["std::sqrt", "std::pow", "std::abs", "std::floor", "std::ceil", "std::round", "sqrt", "pow", "fabs", "floor", "ceil"]),
+    ("cstddef", &["std::size_t", "std::ptrdiff_t", "std::nullptr_t", "std::byte", "size_t", "ptrdiff_t", "NULL"]),
+    ("cstdint", &["std::int8_t", "std::int16_t", "std::int32_t", "std::int64_t", "std::uint8_t", "std::uint16_t", "std::uint32_t", "std::uint64_t", "std::intptr_t", "std::uintptr_t", "int8_t", "int16_t", "int32_t", "int64_t", "uint8_t", "uint16_t", "uint32_t", "uint64_t", "intptr_t", "uintptr_t"]),
+    ("cstdio", &["std::printf", "std::puts", "std::FILE", "printf", "fprintf", "sprintf", "snprintf", "puts", "putchar", "getchar", "fopen", "fclose", "fread", "fwrite", "FILE", "stdin", "stdout", "stderr"]),
+    ("cstdlib", &["std::malloc", "std::free", "std::exit", "std::abort", "malloc", "calloc", "realloc", "free", "exit", "abort", "atoi", "getenv", "EXIT_SUCCESS", "EXIT_FAILURE"]),
+    ("cstring", &["std::strlen", "std::memcpy", "std::memset", "strlen", "strcmp", "strncmp", "strcpy", "strncpy", "strcat", "strchr", "strstr", "memcpy", "memmove", "memset", "memcmp"]),
+    ("functional", &["std::function", "std::hash", "std::invoke"]),
+    ("iostream", &["std::cout", "std::cin", "std::cerr", "std::clog", "std::endl"]),
+    ("map", &["std::map", "std::multimap"]),
+    ("memory", &["std::unique_ptr", "std::shared_ptr", "std::weak_ptr", "std::make_unique", "std::make_shared"]),
+    ("optional", &["std::optional", "std::nullopt", "std::make_optional"]),
+    ("set", &["std::set", "std::multiset"]),
+    ("string", &["std::string", "std::wstring", "std::to_string", "std::stoi", "std::stol", "std::stod", "std::getline"]),
+    ("string_view", &["std::string_view"]),
+    ("tuple", &["std::tuple", "std::make_tuple", "std::tie"]),
+    ("unordered_map", &["std::unordered_map"]),
+    ("unordered_set", &["std::unordered_set"]),
+    ("utility", &["std::move", "std::forward", "std::swap", "std::pair", "std::make_pair", "std::exchange"]),
+    ("variant", &["std::variant", "std::visit", "std::holds_alternative"]),
+    ("vector", &["std::vector"]),
+];
+
+/// The standard header that declares `name`, spelled `std::x` for names
+/// in namespace `std`.
+pub fn header_for(name: &str) -> Option<&'static str> {
+    HEADERS.iter().find(|(_, names)| names.contains(&name)).map(|&(header, _)| header)
+}
+
+/// The uses in `file` of standard names that `index` cannot resolve and
+/// whose header `file` does not include, in token order.
+pub fn missing(tokens: &[SpannedToken], index: &ReferenceIndex, sources: &SourceManager, file: FileId) -> Vec<MissingInclude> {
+    let resolved: HashSet<usize> = index.references().iter().map(|r| r.span.start).collect();
+    let source = sources.file(file);
+    let mut out = Vec::new();
+    for (i, t) in tokens.iter().enumerate() {
+        let Token::Identifier(text) = &t.token else { continue };
+        if resolved.contains(&t.span.start) || sources.file_at(t.span.start).map(|f| f.id) != Some(file) { continue; }
+        // Spelled here, not expanded from a macro.
+        if sources.snippet(t.span).is_none_or(|s| *text != s) { continue; }
+        let prev = |back: usize| i.checked_sub(back).map(|j| &tokens[j]);
+        let spelled = |back: usize, s: &str| prev(back).is_some_and(|p| p.token.spelling() == s);
+        // The lexer splits `::` into two `:`s.
+        let after_scope = spelled(1, ":") && spelled(2, ":") && tokens[i - 2].span.end == tokens[i - 1].span.start;
+        let name = if after_scope && spelled(3, "std") {
+            format!("std::{}", text)
+        } else if after_scope || spelled(1, ".") || spelled(1, "->") {
+            continue;
+        } else {
+            text.to_string()
+        };
+        let Some(header) = header_for(&name) else { continue };
+        if includes(source, header) { continue; }
+        out.push(MissingInclude { span: t.span, name, header });
+    }
+    out
+}
+
+/// Whether `file` has an `#include <header>` line.
+fn includes(file: &SourceFile, header: &str) -> bool {
+    file.contents.lines().filter_map(include_target).any(|target| target == format!("<{}>", header))
+}
+
+/// The `<header>` or `"header"` an `#include` line names.
+fn include_target(line: &str) -> Option<&str> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start().strip_prefix("include")?;
+    Some(rest.trim())
+}
+
+/// Where to insert `#include <header>` in `file`, and the text to insert:
+/// after its last `#include`, or at the top when it has none.
+pub fn include_edit(file: &SourceFile, header: &str) -> (usize, String) {
+    let directive = format!("#include <{}>\n", header);
+    let mut at = None;
+    let mut offset = 0;
+    for line in file.contents.split_inclusive('\n') {
+        offset += line.len();
+        if include_target(line).is_some() { at = Some(offset); }
+    }
+    match at {
+        Some(at) if !file.contents[..at].ends_with('\n') => (file.start + at, format!("\n{}", directive.trim_end())),
+        Some(at) => (file.start + at, directive),
+        None => (file.start, directive),
+    }
+}
@@ -0,0 +1,38 @@
+//! Interrupt handlers and naked functions, as embedded code uses them in
+//! vector tables.
+//!
+//! On x86-64 an `interrupt` handler returns `void` and takes a pointer to
+//! the interrupt frame, plus the error code for exceptions that push one.
+//! Other targets ignore the attribute, as GCC does. A `naked` function has
+//! no prologue or epilogue, so its body may only be inline assembly.
+
+use crate::ast::{Attribute, FunctionDecl};
+use crate::diagnostics::{Diagnostic, Diagnostics, Label, Warning};
+use crate::sema::SemaError;
+use crate::session::Target;
+use crate::types::Type;
+
+/// Checks `f` if it has the `interrupt` or `naked` attribute.
+pub fn check(f: &FunctionDecl, target: &Target, diagnostics: &mut Diagnostics) {
+    if let Some(a) = Attribute::find(&f.attrs, &["interrupt", "gnu::interrupt"]) {
+        if target.arch == "x86_64" {
+            if let Some(reason) = signature_error(f) { diagnostics.emit(SemaError::InterruptHandler(reason).to_diagnostic(f.name_span)); }
+        } else {
+            diagnostics.emit(Diagnostic::warning(format!("`{}` attribute ignored on {}", a.name, target.arch)).with_warning(Warning::Attributes).with_label(Label::primary(a.span, "")));
+        }
+    }
+    if Attribute::find(&f.attrs, &["naked", "gnu::naked"]).is_some() {
+        for stmt in f.body.iter().flat_map(|b| &b.stmts) { diagnostics.emit(SemaError::NakedStatement.to_diagnostic(stmt.span)); }
+    }
+}
+
+fn signature_error(f: &FunctionDecl) -> Option<&'static str> {
+    let Type::Function { ret, params, variadic } = f.ty.get() else { return None };
+    if ret.get() != Type::Void { return Some("an interrupt handler must return `void`"); }
+    let frame = params.first().is_some_and(|p| matches!(p.unqualified().get(), Type::Pointer(_)));
+    let code = params.get(1).is_none_or(|p| p.unqualified().get() == Type::UnsignedLong);
+    if !frame || !code || params.len() > 2 || variadic {
+        return Some("an interrupt handler takes a pointer to the interrupt frame and, for exceptions, an `unsigned long` error code");
+    }
+    None
+}
@@ -0,0 +1,530 @@
+//! Object layout: the size and alignment of types, and where a class puts
+//! its members, as the Itanium C++ ABI lays them out.
+//!
+//! A class is laid out in order: its virtual table pointer, if it is
+//! polymorphic and no base provides one, then its primary base (the first
+//! polymorphic one) at offset 0, its other bases, and its non-static data
+//! members, each at the next offset its alignment allows. An empty base
+//! takes no space; a base that is not POD lends its tail padding to what
+//! follows it. Two subobjects of the same empty class type never share an
+//! address: a base or member that would put one where another already is
+//! moves to the next offset its alignment allows instead. A member declared
+//! `[[no_unique_address]]` is placed like a base: one of an empty class
+//! takes no space, and one of a class that is not POD lends its tail
+//! padding. A bit-field goes at the next free bit, unless it would
+//! straddle a boundary of its type's alignment, when it starts at the next
+//! one; a zero-width bit-field ends the unit it is in. Only named
+//! bit-fields count toward the class's alignment. Classes with virtual
+//! bases are not laid out.
+//!
+//! The sizes and alignments of the scalar types come from the target's
+//! data model: LP64 on 64-bit Unix targets, LLP64 on 64-bit Windows and
+//! ILP32 elsewhere, with the 32-bit x86 quirks of 4-byte alignment for
+//! `double` and `long long` and a 12-byte `long double`.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::ast::{Access, Attribute, ClassDecl, ClassKey, Decl, EnumDecl, FunctionKind, StorageClass, TranslationUnit, TypedefDecl};
+use crate::charset::ExecCharsets;
+use crate::intern::Symbol;
+use crate::session::Target;
+use crate::types::{Type, TypeId};
+
+/// How deeply classes may contain one another before one is taken to
+/// contain itself.
+const MAX_NESTING: usize = 64;
+
+/// Why a type has no layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutError {
+    /// A name sema does not know, a template parameter or specialization,
+    /// or `auto`.
+    Unknown(TypeId),
+    /// `void`, an array without a bound, or a class that is only declared
+    /// or contains itself.
+    Incomplete(TypeId),
+    /// A function type, which has no size.
+    Function(TypeId),
+    /// An array bigger than `PTRDIFF_MAX` bytes.
+    TooLarge(TypeId),
+    /// A class with a virtual base.
+    VirtualBase(Symbol),
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::Unknown(ty) => write!(f, "the layout of `{}` is not known", ty),
+            LayoutError::Incomplete(ty) => write!(f, "`{}` is an incomplete type", ty),
+            LayoutError::Function(ty) => write!(f, "`{}` is a function type", ty),
+            LayoutError::TooLarge(ty) => write!(f, "`{}` is too large", ty),
+            LayoutError::VirtualBase(name) => write!(f, "`{}` has a virtual base, whose layout is not computed", name),
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+/// What a class holds at some offset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slot {
+    VtablePointer,
+    Base(Symbol),
+    Field(Symbol, TypeId),
+}
+
+/// One of the things a class holds, and where.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placed {
+    pub offset: u64,
+    /// The bytes it occupies: none for an empty base or overlapping
+    /// member, and a base's data size rather than its size.
+    pub size: u64,
+    /// A bit-field's first bit, counted from `offset`, and its width.
+    pub bits: Option<(u64, u64)>,
+    pub slot: Slot,
+}
+
+impl Placed {
+    /// The bits a bit-field takes, from `offset`: `bits 3-7`, or `bit 2`.
+    pub fn bit_range(&self) -> Option<String> {
+        self.bits.map(|(bit, width)| if width == 1 { format!("bit {}", bit) } else { format!("bits {}-{}", bit, bit + width - 1) })
+    }
+}
+
+/// The size and alignment of an object type and, for a class, where its
+/// bases and members are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    pub size: u64,
+    pub align: u64,
+    /// Where what follows the type as a base may start: its size, or for
+    /// a class that is not POD, the end of its last member.
+    pub data_size: u64,
+    /// A class with no members but empty overlapping ones, and no virtual
+    /// functions or non-empty bases.
+    pub is_empty: bool,
+    /// A class with a virtual table pointer at offset 0, its own or its
+    /// primary base's.
+    pub is_polymorphic: bool,
+    /// Whether the type is POD as the ABI defines it for layout; only
+    /// such a base keeps its tail padding to itself.
+    pub is_pod: bool,
+    /// A class's bases and members by offset; nothing for other types.
+    pub placed: Vec<Placed>,
+    /// Where the type has a subobject of an empty class type, including
+    /// itself, and that type; what may not be put at the same place again.
+    pub empty_subobjects: Vec<(u64, Symbol)>,
+}
+
+impl Layout {
+    fn scalar(size: u64, align: u64) -> Self {
+        Self { size, align, data_size: size, is_empty: false, is_polymorphic: false, is_pod: true, placed: Vec::new(), empty_subobjects: Vec::new() }
+    }
+}
+
+/// The table `ruscom layout` prints: each member and the padding between
+/// them, then the size and alignment.
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.placed.is_empty() {
+            writeln!(f, "  offset  size  member")?;
+            let mut end = 0;
+            for p in &self.placed {
+                if p.offset > end { writeln!(f, "  {:>6}  {:>4}  (padding)", end, p.offset - end)?; }
+                let what = match p.slot {
+                    Slot::VtablePointer => "(vtable pointer)".to_string(),
+                    Slot::Base(name) => format!("(base) {}", name),
+                    Slot::Field(name, ty) => match (p.bits, p.bit_range()) {
+                        (Some((_, width)), Some(range)) => format!("{} {} : {} ({})", ty, name, width, range),
+                        _ => format!("{} {}", ty, name),
+                    },
+                };
+                writeln!(f, "  {:>6}  {:>4}  {}", p.offset, p.size, what)?;
+                end = end.max(p.offset + p.size);
+            }
+            if self.size > end { writeln!(f, "  {:>6}  {:>4}  (tail padding)", end, self.size - end)?; }
+        }
+        writeln!(f, "  size {}, align {}", self.size, self.align)
+    }
+}
+
+/// The sizes and alignments the target gives the scalar types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DataModel {
+    pointer: u64,
+    long: u64,
+    long_long_align: u64,
+    double_align: u64,
+    long_double: (u64, u64),
+    /// `wchar_t`'s, as big as a unit of the wide execution character set.
+    wchar: u64,
+}
+
+impl DataModel {
+    fn of(target: &Target) -> Self {
+        let arch = target.arch.as_str();
+        let windows = target.os == "windows";
+        let wide = matches!(arch, "x86_64" | "aarch64" | "riscv64" | "powerpc64" | "powerpc64le" | "s390x" | "mips64" | "loongarch64" | "sparc64" | "wasm64");
+        let x86 = matches!(arch, "x86" | "i386" | "i586" | "i686") && !windows;
+        let long_double = match arch {
+            "x86_64" if !windows => (16, 16),
+            _ if x86 => (12, 4),
+            "aarch64" | "riscv64" | "powerpc64" | "powerpc64le" | "s390x" | "loongarch64" if target.os == "linux" => (16, 16),
+            _ => (8, 8),
+        };
+        Self {
+            pointer: if wide { 8 } else { 4 },
+            long: if wide && !windows { 8 } else { 4 },
+            long_long_align: if x86 { 4 } else { 8 },
+            double_align: if x86 { 4 } else { 8 },
+            long_double,
+            wchar: target.wchar_charset().unit_size() as u64,
+        }
+    }
+
+    fn scalar(&self, ty: &Type) -> Option<(u64, u64)> {
+        Some(match ty {
+            Type::Bool | Type::Char | Type::SignedChar | Type::UnsignedChar | Type::Char8 => (1, 1),
+            Type::Short | Type::UnsignedShort | Type::Char16 => (2, 2),
+            Type::Int | Type::UnsignedInt | Type::Char32 | Type::Float => (4, 4),
+            Type::WChar => (self.wchar, self.wchar),
+            Type::Long | Type::UnsignedLong => (self.long, self.long),
+            Type::LongLong | Type::UnsignedLongLong => (8, self.long_long_align),
+            Type::Double => (8, self.double_align),
+            Type::LongDouble => self.long_double,
+            Type::Pointer(_) | Type::Reference(_) | Type::RvalueReference(_) => (self.pointer, self.pointer),
+            _ => return None,
+        })
+    }
+
+    /// The largest size of an object, `PTRDIFF_MAX`, so that any two
+    /// pointers into it can be subtracted.
+    fn max_size(&self) -> u64 { (1 << (self.pointer * 8 - 1)) - 1 }
+}
+
+/// What a class definition says about its layout.
+#[derive(Debug, Clone)]
+struct Class {
+    key: ClassKey,
+    bases: Vec<(Symbol, bool)>,
+    /// Non-static data members.
+    fields: Vec<Field>,
+    /// Declares or overrides a virtual function.
+    is_polymorphic: bool,
+    /// Declares a constructor, destructor or copy assignment operator,
+    /// which makes it not POD.
+    declares_special: bool,
+}
+
+/// A non-static data member.
+#[derive(Debug, Clone, Copy)]
+struct Field {
+    name: Symbol,
+    ty: TypeId,
+    access: Access,
+    /// Declared `[[no_unique_address]]`, so it may overlap what is around it.
+    overlapping: bool,
+    /// A bit-field's width, `None` within if it is not known.
+    width: Option<Option<u64>>,
+}
+
+/// The classes, enums and typedefs declared so far, by unqualified name,
+/// and the target's data model.
+#[derive(Debug, Clone)]
+pub struct Layouts {
+    model: DataModel,
+    classes: HashMap<Symbol, Class>,
+    /// Classes declared but not yet defined.
+    declared: HashSet<Symbol>,
+    aliases: HashMap<Symbol, TypeId>,
+    enums: HashMap<Symbol, TypeId>,
+    /// The character sets literals are encoded in, for their lengths.
+    charsets: ExecCharsets,
+}
+
+impl Default for Layouts {
+    fn default() -> Self { Self::new(&Target::host()) }
+}
+
+impl Layouts {
+    pub fn new(target: &Target) -> Self {
+        Self {
+            model: DataModel::of(target), classes: HashMap::new(), declared: HashSet::new(), aliases: HashMap::new(), enums: HashMap::new(),
+            charsets: ExecCharsets::of(target),
+        }
+    }
+
+    /// These layouts, with literals encoded in `charsets`.
+    pub fn with_charsets(mut self, charsets: ExecCharsets) -> Self {
+        self.charsets = charsets;
+        self
+    }
+
+    pub fn charsets(&self) -> ExecCharsets { self.charsets }
+
+    /// The types `unit` declares, anywhere in it, for `target`.
+    pub fn of_unit(unit: &TranslationUnit, target: &Target) -> Self {
+        fn walk(layouts: &mut Layouts, decls: &[&Decl]) {
+            for decl in decls {
+                match decl {
+                    Decl::Namespace(n) => walk(layouts, &n.decls.iter().collect::<Vec<_>>()),
+                    Decl::Export(e) => walk(layouts, &e.decls.iter().collect::<Vec<_>>()),
+                    Decl::Class(c) => {
+                        walk(layouts, &c.members.iter().flatten().map(|m| &m.decl).collect::<Vec<_>>());
+                        layouts.declare_class(c);
+                    }
+                    Decl::Enum(e) => {
+                        let values = super::enums::values(e, layouts, &mut HashMap::new(), &mut crate::diagnostics::Diagnostics::new());
+                        layouts.declare_enum(e, &values);
+                    }
+                    Decl::Typedef(t) => layouts.declare_typedef(t),
+                    _ => {}
+                }
+            }
+        }
+        let mut layouts = Self::new(target);
+        walk(&mut layouts, &unit.decls.iter().collect::<Vec<_>>());
+        layouts
+    }
+
+    /// Records `c`, a definition or a declaration; its nested types are
+    /// declared on their own.
+    pub fn declare_class(&mut self, c: &ClassDecl) {
+        let Some(name) = c.name.map(unqualified) else { return };
+        let Some(members) = &c.members else {
+            if !self.classes.contains_key(&name) { self.declared.insert(name); }
+            return;
+        };
+        let mut class = Class { key: c.key, bases: c.bases.iter().map(|b| (unqualified(b.name), b.is_virtual)).collect(), fields: Vec::new(), is_polymorphic: false, declares_special: false };
+        for member in members {
+            match &member.decl {
+                Decl::Var(v) if v.specifiers.storage != StorageClass::Static => class.fields.push(Field {
+                    name: v.name,
+                    ty: v.ty,
+                    access: member.access,
+                    overlapping: Attribute::find(&v.attrs, &["no_unique_address", "msvc::no_unique_address"]).is_some(),
+                    width: v.bit_width.as_ref().map(|w| w.bits),
+                }),
+                Decl::Function(f) => {
+                    let q = &f.qualifiers;
+                    class.is_polymorphic |= f.specifiers.is_virtual || q.is_override || q.is_final || q.is_pure;
+                    let special = matches!(f.kind, FunctionKind::Constructor | FunctionKind::Destructor) || &*f.name.as_str() == "operator=";
+                    class.declares_special |= special && !f.specifiers.is_friend;
+                }
+                _ => {}
+            }
+        }
+        self.declared.remove(&name);
+        self.classes.insert(name, class);
+    }
+
+    /// Records the underlying type of `e`, whose enumerators have
+    /// `values`: the one given, else the first of `int`, `unsigned int`,
+    /// `long` and `unsigned long` that holds every value.
+    pub fn declare_enum(&mut self, e: &EnumDecl, values: &[Option<i128>]) {
+        let Some(name) = e.name.map(unqualified) else { return };
+        let ty = e.underlying.unwrap_or_else(|| {
+            let (min, max) = values.iter().flatten().fold((0, 0), |(lo, hi), &v| (v.min(lo), v.max(hi)));
+            [Type::Int, Type::UnsignedInt, Type::Long, Type::UnsignedLong]
+                .into_iter()
+                .map(TypeId::intern)
+                .find(|ty| ty.integer_range().is_some_and(|(lo, hi)| lo <= min && max <= hi))
+                .unwrap_or_else(|| TypeId::intern(Type::LongLong))
+        });
+        self.enums.insert(name, ty);
+    }
+
+    pub fn declare_typedef(&mut self, t: &TypedefDecl) { self.aliases.insert(unqualified(t.name), t.ty); }
+
+    /// `ty` with the typedef and alias names it is spelled with replaced
+    /// by the types they stand for, keeping its qualifiers.
+    pub fn canonical(&self, ty: TypeId) -> TypeId { self.canonical_at(ty, 0) }
+
+    fn canonical_at(&self, ty: TypeId, depth: usize) -> TypeId {
+        let Type::Named(name) = ty.unqualified().get() else { return ty };
+        match self.aliases.get(&unqualified(name)) {
+            Some(&aliased) if depth <= MAX_NESTING => self.canonical_at(aliased, depth + 1).qualified(ty.qualifiers()),
+            _ => ty,
+        }
+    }
+
+    /// The underlying type of the enum `name`.
+    pub fn underlying(&self, name: Symbol) -> Option<TypeId> { self.enums.get(&unqualified(name)).copied() }
+
+    /// `sizeof(ty)`: a reference's is that of the type it refers to.
+    pub fn size_of(&self, ty: TypeId) -> Result<u64, LayoutError> { Ok(self.layout(strip_reference(ty))?.size) }
+
+    /// `alignof(ty)`.
+    pub fn align_of(&self, ty: TypeId) -> Result<u64, LayoutError> { Ok(self.layout(strip_reference(ty))?.align) }
+
+    /// The layout of an object of type `ty`; a reference's is that of a
+    /// pointer, as a member holds it.
+    pub fn layout(&self, ty: TypeId) -> Result<Layout, LayoutError> { self.layout_at(ty, 0) }
+
+    fn layout_at(&self, ty: TypeId, depth: usize) -> Result<Layout, LayoutError> {
+        if depth > MAX_NESTING { return Err(LayoutError::Incomplete(ty)); }
+        let t = ty.unqualified().get();
+        if let Some((size, align)) = self.model.scalar(&t) { return Ok(Layout::scalar(size, align)); }
+        match t {
+            Type::Void => Err(LayoutError::Incomplete(ty)),
+            Type::Function { .. } => Err(LayoutError::Function(ty)),
+            Type::Array(_, None) => Err(LayoutError::Incomplete(ty)),
+            Type::Array(element, Some(n)) => {
+                let e = self.layout_at(element, depth + 1)?;
+                let size = e.size.checked_mul(n).filter(|&size| size <= self.model.max_size()).ok_or(LayoutError::TooLarge(ty))?;
+                let empty_subobjects = if e.empty_subobjects.is_empty() { Vec::new() } else {
+                    (0..n).flat_map(|i| e.empty_subobjects.iter().map(move |&(at, t)| (i * e.size + at, t))).collect()
+                };
+                Ok(Layout { size, align: e.align, data_size: size, is_empty: false, is_polymorphic: false, is_pod: e.is_pod, placed: Vec::new(), empty_subobjects })
+            }
+            Type::Named(name) => {
+                let name = unqualified(name);
+                if let Some(class) = self.classes.get(&name) { return self.class(name, class, depth); }
+                if let Some(&underlying) = self.enums.get(&name) { return self.layout_at(underlying, depth + 1); }
+                if let Some(&aliased) = self.aliases.get(&name) { return self.layout_at(aliased, depth + 1); }
+                Err(if self.declared.contains(&name) { LayoutError::Incomplete(ty) } else { LayoutError::Unknown(ty) })
+            }
+            _ => Err(LayoutError::Unknown(ty)),
+        }
+    }
+
+    fn class(&self, name: Symbol, class: &Class, depth: usize) -> Result<Layout, LayoutError> {
+        let mut bases = Vec::new();
+        for &(base, is_virtual) in &class.bases {
+            if is_virtual { return Err(LayoutError::VirtualBase(name)); }
+            bases.push((base, self.layout_at(TypeId::intern(Type::Named(base)), depth + 1)?));
+        }
+        let is_polymorphic = class.is_polymorphic || bases.iter().any(|(_, b)| b.is_polymorphic);
+        let (mut placed, mut end, mut align) = (Vec::new(), 0, 1);
+        // What an empty base placed past `end` needs the size to cover.
+        let mut min_size = 0;
+        let mut empties = EmptySubobjects::default();
+        // The first polymorphic base is the primary one, sharing its table
+        // pointer; without one, the class has its own.
+        let primary = bases.iter().position(|(_, b)| b.is_polymorphic);
+        if let Some(i) = primary {
+            let (base, layout) = &bases[i];
+            placed.push(Placed { offset: 0, size: layout.data_size, bits: None, slot: Slot::Base(*base) });
+            empties.add(0, layout);
+            (end, align) = (layout.data_size, layout.align);
+        } else if is_polymorphic {
+            placed.push(Placed { offset: 0, size: self.model.pointer, bits: None, slot: Slot::VtablePointer });
+            (end, align) = (self.model.pointer, self.model.pointer);
+        }
+        for (i, (base, layout)) in bases.iter().enumerate() {
+            if Some(i) == primary { continue; }
+            align = align.max(layout.align);
+            if layout.is_empty {
+                // At offset 0 if that puts no two of a type together, else
+                // past the data.
+                let offset = if empties.fits(0, layout) { 0 } else { empties.first_fit(end.next_multiple_of(layout.align), layout) };
+                placed.push(Placed { offset, size: 0, bits: None, slot: Slot::Base(*base) });
+                empties.add(offset, layout);
+                if offset > 0 { min_size = min_size.max(offset + layout.size); }
+                continue;
+            }
+            let offset = empties.first_fit(end.next_multiple_of(layout.align), layout);
+            placed.push(Placed { offset, size: layout.data_size, bits: None, slot: Slot::Base(*base) });
+            empties.add(offset, layout);
+            end = offset + layout.data_size;
+        }
+        let mut is_pod = bases.is_empty() && !is_polymorphic && !class.declares_special;
+        let mut is_empty = !is_polymorphic && bases.iter().all(|(_, b)| b.is_empty);
+        // Where the last bit-field ends, in bits.
+        let mut bit_end: u64 = 0;
+        for field in &class.fields {
+            if let Some(width) = field.width {
+                let width = width.ok_or(LayoutError::Unknown(field.ty))?;
+                let layout = self.layout_at(field.ty, depth + 1)?;
+                let (unit, unit_align) = (layout.size * 8, layout.align * 8);
+                let from = if class.key == ClassKey::Union { 0 } else if bit_end.div_ceil(8) == end { bit_end } else { end * 8 };
+                let bit = if width == 0 || from % unit_align + width > unit { from.next_multiple_of(unit_align) } else { from };
+                bit_end = bit + width;
+                end = end.max(bit_end.div_ceil(8));
+                if width == 0 { continue; }
+                is_empty = false;
+                if field.name.as_str().is_empty() { continue; }
+                align = align.max(layout.align);
+                placed.push(Placed { offset: bit / 8, size: (bit % 8 + width).div_ceil(8), bits: Some((bit % 8, width)), slot: Slot::Field(field.name, field.ty) });
+                continue;
+            }
+            let layout = match field.ty.get() {
+                Type::Reference(_) | Type::RvalueReference(_) => {
+                    is_pod = false;
+                    Layout::scalar(self.model.pointer, self.model.pointer)
+                }
+                _ => self.layout_at(field.ty, depth + 1)?,
+            };
+            is_pod &= layout.is_pod && field.access == Access::Public;
+            align = align.max(layout.align);
+            let slot = Slot::Field(field.name, field.ty);
+            if field.overlapping && layout.is_empty {
+                // Like an empty base: from offset 0, and past the data if
+                // it cannot go there.
+                let offset = if empties.fits(0, &layout) { 0 } else { empties.first_fit(end.next_multiple_of(layout.align), &layout) };
+                placed.push(Placed { offset, size: 0, bits: None, slot });
+                empties.add(offset, &layout);
+                if offset > 0 { min_size = min_size.max(offset + layout.size); }
+                continue;
+            }
+            is_empty = false;
+            let offset = if class.key == ClassKey::Union { 0 } else { empties.first_fit(end.next_multiple_of(layout.align), &layout) };
+            // Only the data of an overlapping member is its own.
+            let size = if field.overlapping { layout.data_size } else { layout.size };
+            placed.push(Placed { offset, size, bits: None, slot });
+            empties.add(offset, &layout);
+            end = end.max(offset + size);
+        }
+        placed.sort_by_key(|p| p.offset);
+        let size = end.max(min_size).max(1).next_multiple_of(align);
+        let mut empty_subobjects = empties.into_sorted();
+        if is_empty { empty_subobjects.insert(0, (0, name)); }
+        Ok(Layout { size, align, data_size: if is_pod { size } else { end }, is_empty, is_polymorphic, is_pod, placed, empty_subobjects })
+    }
+}
+
+/// The empty class subobjects of a class being laid out, by offset.
+#[derive(Default)]
+struct EmptySubobjects(HashSet<(u64, Symbol)>);
+
+impl EmptySubobjects {
+    /// Whether `layout` can go at `offset` without an empty subobject of it
+    /// landing on one of the same type.
+    fn fits(&self, offset: u64, layout: &Layout) -> bool {
+        layout.empty_subobjects.iter().all(|&(at, ty)| !self.0.contains(&(offset + at, ty)))
+    }
+
+    /// The first offset from `from`, stepping by `layout`'s alignment,
+    /// where it fits.
+    fn first_fit(&self, from: u64, layout: &Layout) -> u64 {
+        let mut offset = from;
+        while !self.fits(offset, layout) { offset += layout.align; }
+        offset
+    }
+
+    fn add(&mut self, offset: u64, layout: &Layout) {
+        self.0.extend(layout.empty_subobjects.iter().map(|&(at, ty)| (offset + at, ty)));
+    }
+
+    fn into_sorted(self) -> Vec<(u64, Symbol)> {
+        let mut all: Vec<_> = self.0.into_iter().collect();
+        all.sort_by_key(|&(at, ty)| (at, ty.as_str().to_string()));
+        all
+    }
+}
+
+fn strip_reference(ty: TypeId) -> TypeId {
+    match ty.get() {
+        Type::Reference(inner) | Type::RvalueReference(inner) => inner,
+        _ => ty,
+    }
+}
+
+fn unqualified(name: Symbol) -> Symbol {
+    match name.as_str().rsplit_once("::") {
+        Some((_, last)) => Symbol::intern(last),
+        None => name,
+    }
+}
@@ -0,0 +1,200 @@
+//! Local variables and parameters that are never used, as GCC's
+//! `-Wunused-variable` and `-Wunused-parameter` find them, and ones that
+//! hide another declaration, as `-Wshadow` finds them.
+//!
+//! A local is used where an expression in its scope names it, unqualified;
+//! `(void)x` is a use, and `[[maybe_unused]]` keeps a declaration quiet.
+//! Variables whose type is named, a class, enum or typedef, or deduced
+//! from `auto` are not warned about, since constructing one may be its
+//! point, as with a lock guard, and neither is anything in a function
+//! with a construct the parser skipped, where a use may hide. A local
+//! shadows the locals and parameters of its function declared before it,
+//! the members of the function's class and the variables of namespace
+//! scope.
+
+use std::collections::HashMap;
+
+use crate::ast::{Attribute, Block, ClassDecl, Decl, Expr, ExprKind, FunctionDecl, Stmt, StmtKind, StorageClass, TranslationUnit, UnsupportedConstruct};
+use crate::diagnostics::{Diagnostic, Diagnostics, Label, Warning};
+use crate::intern::Symbol;
+use crate::lexer::token::Span;
+use crate::types::{Type, TypeId};
+use crate::visit::Visitor;
+
+/// Reports the unused locals and shadowing declarations in `tu`, if any
+/// of those warnings are on.
+pub fn check(tu: &TranslationUnit, diagnostics: &mut Diagnostics) {
+    if ![Warning::UnusedVariable, Warning::UnusedParameter, Warning::Shadow].into_iter().any(|w| diagnostics.warnings().is_enabled(w)) { return; }
+    let mut checker = Checker::default();
+    checker.visit_translation_unit(tu);
+    for diag in checker.found { diagnostics.emit(diag); }
+}
+
+struct Local {
+    name: Symbol,
+    span: Span,
+    is_parameter: bool,
+    used: bool,
+    /// Not warned about if unused.
+    quiet: bool,
+}
+
+#[derive(Default)]
+struct Checker {
+    /// The block scopes of the function being checked, innermost last;
+    /// none outside a function.
+    scopes: Vec<Vec<Local>>,
+    /// The class of the function being checked.
+    member_of: Option<Symbol>,
+    /// Unused locals of the function being checked, reported at its end.
+    unused: Vec<Diagnostic>,
+    /// Whether the function being checked has a construct the parser
+    /// skipped.
+    opaque: bool,
+    /// The classes being defined, innermost last, `None` for one without
+    /// a name.
+    classes: Vec<Option<Symbol>>,
+    /// The data members of each class defined so far, by unqualified name.
+    fields: HashMap<Symbol, HashMap<Symbol, Span>>,
+    /// Variables of namespace scope.
+    globals: HashMap<Symbol, Span>,
+    found: Vec<Diagnostic>,
+}
+
+impl Checker {
+    fn enter_scope(&mut self) { self.scopes.push(Vec::new()); }
+
+    fn exit_scope(&mut self) {
+        for local in self.scopes.pop().into_iter().flatten().filter(|l| !l.used && !l.quiet) {
+            let (what, warning) = if local.is_parameter { ("parameter", Warning::UnusedParameter) } else { ("variable", Warning::UnusedVariable) };
+            self.unused.push(Diagnostic::warning(format!("unused {} `{}`", what, local.name)).with_warning(warning).with_label(Label::primary(local.span, "")));
+        }
+    }
+
+    fn declare(&mut self, name: Symbol, span: Span, is_parameter: bool, quiet: bool) {
+        if let Some((shadowed, at)) = self.shadowed(name) {
+            self.found.push(Diagnostic::warning(format!("declaration of `{}` shadows {}", name, shadowed))
+                .with_warning(Warning::Shadow)
+                .with_label(Label::primary(span, ""))
+                .with_label(Label::secondary(at, "shadowed declaration is here")));
+        }
+        if let Some(scope) = self.scopes.last_mut() { scope.push(Local { name, span, is_parameter, used: false, quiet }); }
+    }
+
+    /// What a new local `name` would hide, and where that is declared.
+    fn shadowed(&self, name: Symbol) -> Option<(String, Span)> {
+        if let Some(local) = self.scopes.iter().rev().flat_map(|s| s.iter().rev()).find(|l| l.name == name) {
+            return Some((if local.is_parameter { "a parameter" } else { "a previous local" }.to_string(), local.span));
+        }
+        let class = self.member_of.filter(|class| self.fields.get(class).is_some_and(|f| f.contains_key(&name)));
+        if let Some(class) = class { return Some((format!("a member of `{}`", class), self.fields[&class][&name])); }
+        self.globals.get(&name).map(|&at| ("a global declaration".to_string(), at))
+    }
+
+    fn used(&mut self, name: Symbol) {
+        if let Some(local) = self.scopes.iter_mut().rev().flat_map(|s| s.iter_mut().rev()).find(|l| l.name == name) { local.used = true; }
+    }
+}
+
+impl Visitor for Checker {
+    fn visit_function(&mut self, f: &FunctionDecl) {
+        let Some(body) = &f.body else { return self.walk_function(f) };
+        let class = match self.classes.last() {
+            Some(&class) if !f.specifiers.is_friend => class,
+            _ => f.name.as_str().rsplit_once("::").map(|(scope, _)| unqualified(scope)).filter(|c| self.fields.contains_key(c)),
+        };
+        let scopes = std::mem::take(&mut self.scopes);
+        let member_of = std::mem::replace(&mut self.member_of, class);
+        let unused = std::mem::take(&mut self.unused);
+        let opaque = std::mem::take(&mut self.opaque);
+        self.enter_scope();
+        for p in &f.params {
+            self.visit_param(p);
+            if let Some(name) = p.name { self.declare(name, p.span, true, is_maybe_unused(&p.attrs)); }
+        }
+        for init in &f.initializers {
+            for arg in &init.args { self.visit_expr(arg); }
+        }
+        self.visit_block(body);
+        self.exit_scope();
+        if !self.opaque { self.found.append(&mut self.unused); }
+        (self.scopes, self.member_of, self.unused, self.opaque) = (scopes, member_of, unused, opaque);
+    }
+
+    fn visit_class(&mut self, c: &ClassDecl) {
+        let name = c.name.map(|n| unqualified(&n.as_str()));
+        if let (Some(name), Some(members)) = (name, &c.members) {
+            let fields = members.iter().filter_map(|m| match &m.decl {
+                Decl::Var(v) => Some((v.name, v.name_span)),
+                _ => None,
+            });
+            self.fields.insert(name, fields.collect());
+        }
+        // A local class's members are not locals of the function around it.
+        let scopes = std::mem::take(&mut self.scopes);
+        self.classes.push(name);
+        self.walk_class(c);
+        self.classes.pop();
+        self.scopes = scopes;
+    }
+
+    fn visit_decl(&mut self, decl: &Decl) {
+        match decl {
+            Decl::Var(v) if !self.scopes.is_empty() => {
+                self.walk_var(v);
+                let quiet = v.specifiers.storage == StorageClass::Extern || is_maybe_unused(&v.attrs) || is_named(v.ty);
+                self.declare(v.name, v.name_span, false, quiet);
+            }
+            Decl::Var(v) => {
+                if self.classes.is_empty() && !v.name.as_str().contains("::") { self.globals.insert(v.name, v.name_span); }
+                self.walk_var(v);
+            }
+            Decl::Decomposition(d) if !self.scopes.is_empty() => {
+                self.walk_decl(decl);
+                for b in &d.bindings { self.declare(b.name, b.span, false, true); }
+            }
+            Decl::Invalid(_) => self.opaque = true,
+            _ => self.walk_decl(decl),
+        }
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        self.enter_scope();
+        self.walk_block(block);
+        self.exit_scope();
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            // What the init-statement declares is in scope in the body.
+            StmtKind::For { .. } | StmtKind::RangeFor { .. } => {
+                self.enter_scope();
+                self.walk_stmt(stmt);
+                self.exit_scope();
+            }
+            StmtKind::Invalid => self.opaque = true,
+            _ => self.walk_stmt(stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, e: &Expr) {
+        if let ExprKind::Name(name) = &e.kind { self.used(name.symbol()); }
+        self.walk_expr(e);
+    }
+
+    fn visit_unsupported(&mut self, _u: &UnsupportedConstruct) { self.opaque = true; }
+}
+
+fn is_maybe_unused(attrs: &[Attribute]) -> bool { Attribute::find(attrs, &["maybe_unused", "gnu::unused"]).is_some() }
+
+/// Whether `ty` is, or is an array of, a class, enum or typedef name or
+/// `auto`, whose construction may have effects.
+fn is_named(ty: TypeId) -> bool {
+    match ty.unqualified().get() {
+        Type::Array(element, _) => is_named(element),
+        Type::Named(_) | Type::Specialization(..) | Type::Auto => true,
+        _ => false,
+    }
+}
+
+fn unqualified(name: &str) -> Symbol { Symbol::intern(name.rsplit("::").next().unwrap_or(name)) }
@@ -0,0 +1,590 @@
+//! Semantic checks over the AST.
+//!
+//! For now these are the checks that need no name lookup: the shape of
+//! each full-expression, enumerator values, discarded results of
+//! `[[nodiscard]]` functions, interrupt handler and naked function bodies
+//! and signatures, the types structured bindings deduce, whether an
+//! operator applied to a class object has an operator function to call,
+//! where coroutine operators may appear, `visibility` attributes, and
+//! that weak definitions are external and aliases name definitions.
+//! Range-based `for` loops are checked as the loops they are lowered to.
+//!
+//! Every expression is also given a type where the names in it are known,
+//! which rejects calls of non-functions, assignments to rvalues, invalid
+//! operands and conversions between arithmetic and pointer types, picks
+//! the overload a call's argument types match best and, with
+//! `-Wconversion`, warns about conversions that may change a value; see
+//! [`typeck`] for which names those are. [`locals`] finds unused and
+//! shadowing local variables and parameters, and [`flow`] what the
+//! control flow of each function body shows: missing returns,
+//! unreachable code and infinite loops.
+//!
+//! Constant expressions are evaluated where the language requires them:
+//! `static_assert` conditions, `case` labels, enumerator values and the
+//! initializers of `constexpr` variables, calling `constexpr` functions
+//! declared before them; see [`constant`] for what it can evaluate, and
+//! [`layout`] for the sizes and alignments `sizeof` and `alignof` give.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Instant;
+
+use crate::ast::{Block, Decl, Expr, StorageClass, Stmt, StmtKind, TranslationUnit};
+use crate::charset::ExecCharsets;
+use crate::diagnostics::{Diagnostic, Diagnostics, Label};
+use crate::intern::Symbol;
+use crate::lexer::token::Span;
+use crate::profile::Profiler;
+use crate::sema::bindings::Bindings;
+use crate::sema::constant::{Constants, Unevaluated};
+use crate::sema::layout::LayoutError;
+use crate::sema::nodiscard::NoDiscard;
+use crate::sema::operators::Operators;
+use crate::sema::typeck::{ExprType, TypeChecker};
+use crate::session::Target;
+use crate::types::{Qualifiers, Type, TypeId};
+
+pub mod alias;
+pub mod bindings;
+pub mod cfg;
+pub mod constant;
+pub mod coroutine;
+pub mod enums;
+pub mod flow;
+pub mod hints;
+pub mod includes;
+pub mod interrupt;
+pub mod layout;
+pub mod locals;
+pub mod nodiscard;
+pub mod operators;
+pub mod range_for;
+pub mod references;
+pub mod sequence;
+pub mod typeck;
+pub mod uninit;
+pub mod visibility;
+
+/// A semantic error in otherwise well-formed code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SemaError {
+    /// An expression that must be an integer constant is not.
+    NotConstant,
+    DivisionByZero,
+    /// A constant that does not fit the type it must have.
+    OutOfRange { value: i128, ty: TypeId },
+    /// A type that must be integral, like an enum's underlying type.
+    NotIntegral(TypeId),
+    /// An `interrupt` handler with the wrong signature; holds the rule.
+    InterruptHandler(&'static str),
+    /// A statement other than inline assembly in a `naked` function.
+    NakedStatement,
+    /// A structured binding with a name for other than each element.
+    BindingCount { ty: TypeId, elements: usize, names: usize },
+    /// A structured binding of a type that has no elements.
+    NotDecomposable(TypeId),
+    /// A range-based `for` over a type without `begin` and `end`.
+    NotARange(TypeId),
+    /// An operator applied to a class object with no operator function
+    /// for it; `op` is spelled as after `operator`.
+    NoViableOperator { op: String, ty: TypeId },
+    /// A literal whose suffix has no literal operator.
+    NoLiteralOperator(Symbol),
+    /// `co_await` or `co_yield`, the keyword held, outside a function
+    /// body.
+    CoroutineOutsideFunction(&'static str),
+    /// A coroutine that cannot be one; holds the rule.
+    Coroutine(&'static str),
+    /// A `return` statement in a coroutine.
+    ReturnInCoroutine,
+    /// A `visibility` attribute whose argument is not a visibility.
+    InvalidVisibility,
+    /// A misused `alias` attribute; holds the rule.
+    InvalidAlias(&'static str),
+    /// An alias for a name the translation unit does not define.
+    UndefinedAlias { alias: Symbol, target: Symbol },
+    /// A `weak` definition with internal linkage.
+    WeakInternal(Symbol),
+    /// A call of an expression of a type that is not a function or a
+    /// pointer to one.
+    NotCallable(TypeId),
+    /// An assignment, increment or decrement of an rvalue.
+    NotAssignable,
+    AssignToConst(TypeId),
+    /// A binary operator, spelled `op`, with operands it does not take.
+    InvalidOperands { op: &'static str, lhs: TypeId, rhs: TypeId },
+    /// A unary operator, spelled `op`, with an operand it does not take.
+    InvalidOperand { op: &'static str, ty: TypeId },
+    /// `*` applied to a value that is not a pointer.
+    NotPointer(TypeId),
+    AddressOfRvalue(TypeId),
+    NotSubscriptable(TypeId),
+    /// A call with the wrong number of arguments; `max` is `None` for a
+    /// variadic function.
+    ArgumentCount { min: usize, max: Option<usize>, found: usize },
+    /// A call with a number of arguments none of the overloads of `name`
+    /// takes.
+    NoOverload { name: Symbol, found: usize },
+    InvalidConversion { from: TypeId, to: TypeId },
+    /// A non-const lvalue reference initialized with an rvalue.
+    BindToTemporary { ty: TypeId, from: TypeId },
+    /// An rvalue reference initialized with an lvalue.
+    BindToLvalue { ty: TypeId, from: TypeId },
+    NoMember { ty: TypeId, member: Symbol },
+    MemberOfNonClass(TypeId),
+    /// `.` applied to a pointer.
+    MemberOfPointer(TypeId),
+    /// `return;` in a function with a non-void return type.
+    MissingReturnValue(Symbol),
+    /// `return` with a value in a void function.
+    UnexpectedReturnValue(Symbol),
+    /// A call whose arguments, of types `args`, convert implicitly to the
+    /// parameters of none of the overloads of `name` that take that many.
+    NoMatchingOverload { name: Symbol, args: Vec<TypeId> },
+    /// A call that the overloads of `name` of types `candidates` match
+    /// equally well.
+    AmbiguousCall { name: Symbol, candidates: Vec<TypeId> },
+    /// A shift in a constant expression by a negative count or by at
+    /// least the width of `ty`, the promoted left operand's type.
+    ShiftCount { count: i128, ty: TypeId },
+    /// A constant expression that ran into one of the evaluator's limits:
+    /// `limit` nested calls or steps.
+    ConstexprLimit { limit: usize, what: &'static str },
+    /// A `static_assert` whose condition is false, with its message.
+    StaticAssertFailed(Option<String>),
+    /// `sizeof` or `alignof` of a type that has no size.
+    NoLayout(LayoutError),
+    /// A name that is not declared.
+    Undeclared(Symbol),
+    /// A non-static member named in a static member function, which has
+    /// no object to take it from.
+    NonStaticMember(Symbol),
+    /// A member function called on an object more cv-qualified than its
+    /// `this`, like a non-`const` one on a `const` object.
+    DiscardsQualifiers { name: Symbol, quals: Qualifiers },
+}
+
+impl SemaError {
+    /// Stable diagnostic code for this error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            SemaError::NotConstant => "E0301",
+            SemaError::DivisionByZero => "E0302",
+            SemaError::OutOfRange { .. } => "E0303",
+            SemaError::NotIntegral(_) => "E0304",
+            SemaError::InterruptHandler(_) => "E0305",
+            SemaError::NakedStatement => "E0306",
+            SemaError::BindingCount { .. } => "E0307",
+            SemaError::NotDecomposable(_) => "E0308",
+            SemaError::NotARange(_) => "E0309",
+            SemaError::NoViableOperator { .. } => "E0310",
+            SemaError::NoLiteralOperator(_) => "E0311",
+            SemaError::CoroutineOutsideFunction(_) => "E0312",
+            SemaError::Coroutine(_) => "E0313",
+            SemaError::ReturnInCoroutine => "E0314",
+            SemaError::InvalidVisibility => "E0315",
+            SemaError::InvalidAlias(_) => "E0316",
+            SemaError::UndefinedAlias { .. } => "E0317",
+            SemaError::WeakInternal(_) => "E0318",
+            SemaError::NotCallable(_) => "E0319",
+            SemaError::NotAssignable => "E0320",
+            SemaError::AssignToConst(_) => "E0321",
+            SemaError::InvalidOperands { .. } => "E0322",
+            SemaError::InvalidOperand { .. } => "E0323",
+            SemaError::NotPointer(_) => "E0324",
+            SemaError::AddressOfRvalue(_) => "E0325",
+            SemaError::NotSubscriptable(_) => "E0326",
+            SemaError::ArgumentCount { .. } => "E0327",
+            SemaError::NoOverload { .. } => "E0328",
+            SemaError::InvalidConversion { .. } => "E0329",
+            SemaError::BindToTemporary { .. } => "E0330",
+            SemaError::BindToLvalue { .. } => "E0331",
+            SemaError::NoMember { .. } => "E0332",
+            SemaError::MemberOfNonClass(_) => "E0333",
+            SemaError::MemberOfPointer(_) => "E0334",
+            SemaError::MissingReturnValue(_) => "E0335",
+            SemaError::UnexpectedReturnValue(_) => "E0336",
+            SemaError::NoMatchingOverload { .. } => "E0337",
+            SemaError::ShiftCount { .. } => "E0338",
+            SemaError::ConstexprLimit { .. } => "E0339",
+            SemaError::StaticAssertFailed(_) => "E0340",
+            SemaError::NoLayout(_) => "E0341",
+            SemaError::AmbiguousCall { .. } => "E0342",
+            SemaError::Undeclared(_) => "E0343",
+            SemaError::NonStaticMember(_) => "E0344",
+            SemaError::DiscardsQualifiers { .. } => "E0345",
+        }
+    }
+
+    pub fn to_diagnostic(&self, span: Span) -> Diagnostic {
+        Diagnostic::error(self.to_string()).with_code(self.code()).with_label(Label::primary(span, ""))
+    }
+}
+
+impl fmt::Display for SemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SemaError::NotConstant => write!(f, "expression is not an integer constant expression"),
+            SemaError::DivisionByZero => write!(f, "division by zero in a constant expression"),
+            SemaError::OutOfRange { value, ty } => write!(f, "value {} is outside the range of `{}`", value, ty),
+            SemaError::NotIntegral(ty) => write!(f, "underlying type `{}` is not an integral type", ty),
+            SemaError::InterruptHandler(rule) => write!(f, "{}", rule),
+            SemaError::NakedStatement => write!(f, "only inline assembly may appear in a naked function"),
+            SemaError::BindingCount { ty, elements, names } => write!(f, "type `{}` decomposes into {} elements, but {} names were provided", ty, elements, names),
+            SemaError::NotDecomposable(ty) => write!(f, "cannot decompose non-class, non-array type `{}`", ty),
+            SemaError::NotARange(ty) => write!(f, "invalid range expression of type `{}`; no viable `begin` function available", ty),
+            SemaError::NoViableOperator { op, ty } => write!(f, "no viable `operator{}` for an operand of type `{}`", op, ty),
+            SemaError::NoLiteralOperator(suffix) => write!(f, "no matching literal operator for suffix `{}`", suffix),
+            SemaError::CoroutineOutsideFunction(keyword) => write!(f, "`{}` cannot be used outside a function body", keyword),
+            SemaError::Coroutine(rule) => write!(f, "{}", rule),
+            SemaError::ReturnInCoroutine => write!(f, "return statement not allowed in coroutine; did you mean `co_return`?"),
+            SemaError::InvalidVisibility => write!(f, "visibility must be \"default\", \"protected\", \"hidden\" or \"internal\""),
+            SemaError::InvalidAlias(rule) => write!(f, "{}", rule),
+            SemaError::UndefinedAlias { alias, target } => write!(f, "`{}` is an alias for `{}`, which is not defined in this translation unit", alias, target),
+            SemaError::WeakInternal(name) => write!(f, "weak definition of `{}` must have external linkage", name),
+            SemaError::NotCallable(ty) => write!(f, "called object type `{}` is not a function or function pointer", ty),
+            SemaError::NotAssignable => write!(f, "expression is not assignable"),
+            SemaError::AssignToConst(ty) => write!(f, "cannot assign to a value of const-qualified type `{}`", ty),
+            SemaError::InvalidOperands { op, lhs, rhs } => write!(f, "invalid operands to binary `{}` (`{}` and `{}`)", op, lhs, rhs),
+            SemaError::InvalidOperand { op, ty } => write!(f, "invalid argument type `{}` to unary `{}`", ty, op),
+            SemaError::NotPointer(ty) => write!(f, "indirection requires pointer operand (`{}` invalid)", ty),
+            SemaError::AddressOfRvalue(ty) => write!(f, "cannot take the address of an rvalue of type `{}`", ty),
+            SemaError::NotSubscriptable(ty) => write!(f, "subscripted value of type `{}` is not an array or pointer", ty),
+            SemaError::ArgumentCount { min, max, found } => {
+                let few = found < min;
+                let expected = match max {
+                    Some(max) if min == max => format!("expected {}", min),
+                    _ if few => format!("expected at least {}", min),
+                    Some(max) => format!("expected at most {}", max),
+                    None => format!("expected at least {}", min),
+                };
+                write!(f, "too {} arguments to function call, {}, have {}", if few { "few" } else { "many" }, expected, found)
+            }
+            SemaError::NoOverload { name, found } => write!(f, "no overload of `{}` takes {} arguments", name, found),
+            SemaError::InvalidConversion { from, to } => write!(f, "no viable conversion from `{}` to `{}`", from, to),
+            SemaError::BindToTemporary { ty, from } => write!(f, "non-const lvalue reference to type `{}` cannot bind to a temporary of type `{}`", ty, from),
+            SemaError::BindToLvalue { ty, from } => write!(f, "rvalue reference to type `{}` cannot bind to lvalue of type `{}`", ty, from),
+            SemaError::NoMember { ty, member } => write!(f, "no member named `{}` in `{}`", member, ty),
+            SemaError::MemberOfNonClass(ty) => write!(f, "member reference base type `{}` is not a structure or union", ty),
+            SemaError::MemberOfPointer(ty) => write!(f, "member reference type `{}` is a pointer; did you mean to use `->`?", ty),
+            SemaError::MissingReturnValue(name) => write!(f, "non-void function `{}` should return a value", name),
+            SemaError::UnexpectedReturnValue(name) => write!(f, "void function `{}` should not return a value", name),
+            SemaError::NoMatchingOverload { name, args } => {
+                let args: Vec<String> = args.iter().map(|a| format!("`{}`", a)).collect();
+                write!(f, "no overload of `{}` accepts arguments of type ({})", name, args.join(", "))
+            }
+            SemaError::ShiftCount { count, ty } => write!(f, "shift count {} is negative or not less than the width of `{}`", count, ty),
+            SemaError::ConstexprLimit { limit, what } => write!(f, "constant evaluation exceeded the limit of {} {}", limit, what),
+            SemaError::StaticAssertFailed(Some(message)) if !message.is_empty() => write!(f, "static assertion failed: {}", message),
+            SemaError::StaticAssertFailed(_) => write!(f, "static assertion failed"),
+            SemaError::NoLayout(error) => write!(f, "invalid `sizeof` or `alignof`: {}", error),
+            SemaError::AmbiguousCall { name, candidates } => {
+                let candidates: Vec<String> = candidates.iter().map(|c| format!("`{}`", c)).collect();
+                write!(f, "call to `{}` is ambiguous between {}", name, candidates.join(", "))
+            }
+            SemaError::Undeclared(name) => write!(f, "use of undeclared identifier `{}`", name),
+            SemaError::NonStaticMember(name) => write!(f, "invalid use of member `{}` in static member function", name),
+            SemaError::DiscardsQualifiers { name, quals } => write!(f, "member function `{}` cannot be called on a `{}` object", name, quals),
+        }
+    }
+}
+
+impl std::error::Error for SemaError {}
+
+/// Runs every check over `tu`, compiled for `target` with literals in
+/// `charsets`, reporting to `diagnostics`; warnings are checked for only
+/// if its options turn them on.
+pub fn check(tu: &TranslationUnit, target: &Target, charsets: ExecCharsets, diagnostics: &mut Diagnostics) { run(tu, target, charsets, diagnostics, None); }
+
+/// What checking a unit found the functions its calls call and the types
+/// its `auto` declarations deduce to be, for editors.
+#[derive(Debug, Clone, Default)]
+pub struct Resolved {
+    /// The name and type of the function each call of a name calls, by the
+    /// call's span; a call whose overload is not known has none.
+    pub calls: HashMap<Span, (Symbol, TypeId)>,
+    /// The type of each variable and structured binding, by the span of
+    /// its name.
+    pub types: HashMap<Span, TypeId>,
+}
+
+/// Like [`check`], also returning what it resolved.
+pub fn resolve(tu: &TranslationUnit, target: &Target, charsets: ExecCharsets, diagnostics: &mut Diagnostics) -> Resolved { run(tu, target, charsets, diagnostics, None) }
+
+/// Like [`check`], also recording in `profiler` how long each function
+/// definition took to check.
+pub fn check_profiled(tu: &TranslationUnit, target: &Target, charsets: ExecCharsets, diagnostics: &mut Diagnostics, profiler: &mut Profiler) {
+    run(tu, target, charsets, diagnostics, Some(profiler));
+}
+
+fn run(tu: &TranslationUnit, target: &Target, charsets: ExecCharsets, diagnostics: &mut Diagnostics, profiler: Option<&mut Profiler>) -> Resolved {
+    let mut checker = Checker {
+        diagnostics, target, constants: Constants::new(target).with_charsets(charsets).complete(), nodiscard: NoDiscard::default(), bindings: Bindings::new(charsets),
+        operators: Operators::default(), in_body: false, types: TypeChecker::new(charsets), class: None, profiler, variables: HashMap::new(),
+    };
+    for decl in &tu.decls { checker.decl(decl); }
+    alias::check(tu, checker.diagnostics);
+    locals::check(tu, checker.diagnostics);
+    flow::check(tu, checker.diagnostics);
+    let calls = checker.types.take_calls().into_iter().map(|(span, name, ty)| (span, (name, ty))).collect();
+    Resolved { calls, types: checker.variables }
+}
+
+struct Checker<'d, 'a> {
+    diagnostics: &'d mut Diagnostics,
+    target: &'d Target,
+    /// Enumerator values, constant variables and `constexpr` functions
+    /// seen so far.
+    constants: Constants<'a>,
+    nodiscard: NoDiscard,
+    bindings: Bindings,
+    operators: Operators,
+    /// Whether a function body encloses the current position.
+    in_body: bool,
+    types: TypeChecker,
+    /// The class whose members are being checked.
+    class: Option<Symbol>,
+    profiler: Option<&'d mut Profiler>,
+    /// The types of the variables and structured bindings declared, by
+    /// the spans of their names.
+    variables: HashMap<Span, TypeId>,
+}
+
+impl<'a> Checker<'_, 'a> {
+    fn decl(&mut self, decl: &Decl<'a>) {
+        match decl {
+            Decl::Var(v) => {
+                let init = v.init.as_ref().and_then(|init| self.initializer(init));
+                let ty = self.types.declare_var(v, init, &self.bindings, self.diagnostics);
+                self.bindings.declare_var(v);
+                // `Bindings` deduces some types `typeck` cannot, like a
+                // braced initializer's.
+                if let Some(ty) = ty.filter(|ty| ty.get() != Type::Auto).or_else(|| self.bindings.variable(v.name)) { self.variables.insert(v.name_span, ty); }
+                if let Some((error, span)) = self.constants.declare_variable(v) { self.constant_error(error, span); }
+                visibility::check(&v.attrs, self.diagnostics);
+                alias::check_declaration(&v.attrs, v.specifiers.storage != StorageClass::Extern || v.init.is_some(), self.diagnostics);
+            }
+            Decl::Decomposition(d) => {
+                if let Some(init) = &d.init { self.initializer(init); }
+                self.bindings.bind(d, self.diagnostics);
+                for binding in &d.bindings {
+                    let ty = self.bindings.variable(binding.name).filter(|ty| ty.get() != Type::Auto);
+                    if let Some(ty) = ty { self.variables.insert(binding.span, ty); }
+                    self.types.declare_variable(binding.name, ty);
+                    self.constants.declare_parameter(binding.name, ty.unwrap_or_else(|| TypeId::intern(Type::Auto)));
+                }
+            }
+            Decl::Function(f) => {
+                let start = Instant::now();
+                self.nodiscard.declare_function(f);
+                self.operators.declare_function(f, &self.bindings);
+                self.types.declare_function(f);
+                self.constants.declare_function(f);
+                self.types.enter_function(f, self.class.filter(|_| !f.specifiers.is_friend));
+                self.constants.enter_scope();
+                for p in &f.params {
+                    if let Some(name) = p.name {
+                        self.bindings.declare_variable(name, p.ty);
+                        self.constants.declare_parameter(name, p.ty);
+                    }
+                }
+                interrupt::check(f, self.target, self.diagnostics);
+                coroutine::check(f, self.diagnostics);
+                visibility::check(&f.attrs, self.diagnostics);
+                alias::check_declaration(&f.attrs, f.body.is_some(), self.diagnostics);
+                for default in f.params.iter().filter_map(|p| p.default.as_ref()) {
+                    self.expr(default);
+                    coroutine::outside_body(default, self.diagnostics);
+                }
+                for init in &f.initializers {
+                    for arg in &init.args {
+                        self.expr(arg);
+                        coroutine::outside_body(arg, self.diagnostics);
+                    }
+                }
+                if let Some(body) = &f.body {
+                    let outer = std::mem::replace(&mut self.in_body, true);
+                    self.block(body);
+                    self.in_body = outer;
+                }
+                self.constants.exit_scope();
+                self.types.exit_function();
+                if let (Some(profiler), Some(_)) = (self.profiler.as_deref_mut(), &f.body) {
+                    let name = match self.class.filter(|_| !f.name.as_str().contains("::")) {
+                        Some(class) => format!("sema {}::{}", class, f.name),
+                        None => format!("sema {}", f.name),
+                    };
+                    profiler.record(&name, "sema", start);
+                }
+            }
+            Decl::Namespace(n) => {
+                visibility::check(&n.attrs, self.diagnostics);
+                self.types.enter_namespace(n.name);
+                for decl in &n.decls { self.decl(decl); }
+                self.types.exit_namespace();
+            }
+            Decl::Export(e) => {
+                for decl in &e.decls { self.decl(decl); }
+            }
+            Decl::Class(c) => {
+                self.nodiscard.declare_class(c);
+                self.bindings.declare_class(c);
+                self.operators.declare_class(c, &self.bindings);
+                visibility::check(&c.attrs, self.diagnostics);
+                self.types.declare_class(c);
+                // Member function bodies may take the size of a nested
+                // class defined after them.
+                for member in c.members.iter().flatten() {
+                    if let Decl::Class(nested) = &member.decl { self.constants.layouts.declare_class(nested); }
+                }
+                self.constants.layouts.declare_class(c);
+                self.types.enter_class();
+                let outer = std::mem::replace(&mut self.class, c.name);
+                for member in c.members.iter().flatten() { self.decl(&member.decl); }
+                self.class = outer;
+                self.types.exit_class();
+            }
+            Decl::Enum(e) => {
+                self.types.declare_enum(e);
+                self.constants.declare_enum(e, self.diagnostics);
+            }
+            Decl::StaticAssert(a) => {
+                self.expr(&a.cond);
+                match constant::value(&a.cond, &self.constants) {
+                    Ok(c) if c.is_zero() => self.diagnostics.emit(SemaError::StaticAssertFailed(a.message.clone()).to_diagnostic(a.cond.span)),
+                    Ok(_) | Err(Unevaluated::Unknown) => {}
+                    Err(Unevaluated::Error(error, span)) => self.constant_error(error, span),
+                }
+            }
+            Decl::Template(t) => {
+                self.constants.enter_template();
+                self.types.enter_template();
+                self.decl(&t.decl);
+                self.types.exit_template();
+                self.constants.exit_template();
+            }
+            Decl::Typedef(t) => {
+                self.nodiscard.declare_alias(t);
+                self.bindings.declare_alias(t);
+                self.types.declare_name(t.name);
+                self.constants.layouts.declare_typedef(t);
+            }
+            Decl::Import(_) | Decl::Unsupported(_) => {
+                self.constants.declare_unseen();
+                self.types.declare_unseen();
+            }
+            Decl::Using(_) | Decl::UsingDirective(_) | Decl::Invalid(_) => self.types.declare_unseen(),
+            Decl::Concept(c) => self.types.declare_name(c.name),
+            Decl::Module(_) => {}
+        }
+    }
+
+    /// The checks on a full-expression, and its type if it has one.
+    fn expr(&mut self, e: &Expr) -> Option<ExprType> {
+        sequence::check(e, self.diagnostics);
+        self.operators.check(e, &self.bindings, self.diagnostics);
+        let ty = self.types.type_of(e, &self.bindings, self.diagnostics);
+        for (sized, span) in self.types.take_sized() {
+            if let Err(error @ (LayoutError::Incomplete(_) | LayoutError::Function(_) | LayoutError::TooLarge(_))) = self.constants.layouts.size_of(sized) {
+                self.diagnostics.emit(SemaError::NoLayout(error).to_diagnostic(span));
+            }
+        }
+        ty
+    }
+
+    /// Reports why a constant expression has no value, except for a
+    /// `sizeof` without one, which `expr` reported as it checked it.
+    fn constant_error(&mut self, error: SemaError, span: Span) {
+        if !matches!(error, SemaError::NoLayout(_)) { self.diagnostics.emit(error.to_diagnostic(span)); }
+    }
+
+    /// A variable's initializer, which is outside any function body at
+    /// namespace or class scope.
+    fn initializer(&mut self, init: &Expr) -> Option<ExprType> {
+        let ty = self.expr(init);
+        if !self.in_body { coroutine::outside_body(init, self.diagnostics); }
+        ty
+    }
+
+    fn block(&mut self, block: &Block<'a>) {
+        self.types.enter_scope();
+        self.constants.enter_scope();
+        for stmt in &block.stmts { self.stmt(stmt); }
+        self.constants.exit_scope();
+        self.types.exit_scope();
+    }
+
+    fn stmt(&mut self, stmt: &Stmt<'a>) {
+        match &stmt.kind {
+            StmtKind::Compound(block) => self.block(block),
+            StmtKind::Expr(e) => {
+                self.expr(e);
+                self.nodiscard.check(e, self.diagnostics);
+            }
+            StmtKind::Return(Some(e)) => {
+                let ty = self.expr(e);
+                self.types.check_return(Some(e), ty, stmt.span, &self.bindings, self.diagnostics);
+            }
+            StmtKind::CoReturn(Some(e)) => {
+                self.expr(e);
+            }
+            StmtKind::Return(None) => self.types.check_return(None, None, stmt.span, &self.bindings, self.diagnostics),
+            StmtKind::Decl(decls) => {
+                for decl in decls { self.decl(decl); }
+            }
+            StmtKind::If { cond, then, otherwise } => {
+                self.expr(cond);
+                self.stmt(then);
+                if let Some(otherwise) = otherwise { self.stmt(otherwise); }
+            }
+            StmtKind::While { cond, body } | StmtKind::DoWhile { body, cond } | StmtKind::Switch { cond, body } => {
+                self.expr(cond);
+                self.stmt(body);
+            }
+            StmtKind::Case { value, body } => {
+                self.expr(value);
+                match constant::value(value, &self.constants) {
+                    Ok(c) if c.as_int().is_none() => self.diagnostics.emit(SemaError::NotConstant.to_diagnostic(value.span)),
+                    Ok(_) | Err(Unevaluated::Unknown) => {}
+                    Err(Unevaluated::Error(error, span)) => self.constant_error(error, span),
+                }
+                self.stmt(body);
+            }
+            StmtKind::For { init, cond, step, body } => {
+                self.types.enter_scope();
+                self.constants.enter_scope();
+                if let Some(init) = init { self.stmt(init); }
+                for e in cond.iter().chain(step) { self.expr(e); }
+                self.stmt(body);
+                self.constants.exit_scope();
+                self.types.exit_scope();
+            }
+            StmtKind::RangeFor { init, decl, range, body } => match self.bindings.range_ends(range) {
+                Ok(ends) => self.stmt(&range_for::lower(stmt, ends)),
+                // The lowered loop would only repeat the error; the body is
+                // checked with the loop variable's type unknown.
+                Err(ty) => {
+                    self.diagnostics.emit(SemaError::NotARange(ty).to_diagnostic(range.span));
+                    self.types.enter_scope();
+                    self.constants.enter_scope();
+                    if let Some(init) = init { self.stmt(init); }
+                    self.expr(range);
+                    let names = match &**decl {
+                        Decl::Decomposition(d) => d.bindings.iter().map(|b| b.name).collect(),
+                        decl => vec![decl.name()],
+                    };
+                    for name in names {
+                        self.types.declare_variable(name, None);
+                        self.constants.declare_parameter(name, TypeId::intern(Type::Auto));
+                    }
+                    self.stmt(body);
+                    self.constants.exit_scope();
+                    self.types.exit_scope();
+                }
+            },
+            StmtKind::Default(body) | StmtKind::Attributed { stmt: body, .. } => self.stmt(body),
+            StmtKind::Unsupported(_) | StmtKind::Invalid => self.types.skip_statement(),
+            StmtKind::Null | StmtKind::Break | StmtKind::Continue | StmtKind::CoReturn(None) => {}
+        }
+    }
+}
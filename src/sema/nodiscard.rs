@@ -0,0 +1,89 @@
+//! Discarded results of `[[nodiscard]]` functions.
+//!
+//! A call is discarded when it is the whole of an expression statement.
+//! Functions are known by their unqualified name, so `s.f()` and `S::f()`
+//! both find a member `f`. Casting to `void` keeps the warning quiet.
+
+use std::collections::HashMap;
+
+use crate::ast::{Attribute, ClassDecl, Expr, ExprKind, FunctionDecl, TypedefDecl};
+use crate::diagnostics::{Diagnostic, Diagnostics, Label, Warning};
+use crate::intern::Symbol;
+use crate::lexer::token::Span;
+use crate::types::{Type, TypeId};
+
+const NAMES: [&str; 2] = ["nodiscard", "gnu::warn_unused_result"];
+
+/// The functions and classes declared `[[nodiscard]]` so far, with the
+/// reason given, if any.
+#[derive(Debug, Default)]
+pub struct NoDiscard {
+    functions: HashMap<Symbol, Option<String>>,
+    classes: HashMap<Symbol, Option<String>>,
+}
+
+impl NoDiscard {
+    pub fn declare_class(&mut self, c: &ClassDecl) {
+        if let (Some(name), Some(a)) = (c.name, Attribute::find(&c.attrs, &NAMES)) { self.classes.insert(unqualified(name), reason(a)); }
+    }
+
+    /// A typedef or alias of a `[[nodiscard]]` class is one too.
+    pub fn declare_alias(&mut self, t: &TypedefDecl) {
+        if let Some(reason) = self.class_of(t.ty) { self.classes.insert(unqualified(t.name), reason); }
+    }
+
+    /// Records `f` if it is `[[nodiscard]]` or returns a class that is.
+    pub fn declare_function(&mut self, f: &FunctionDecl) {
+        let by_class = match f.ty.get() {
+            Type::Function { ret, .. } => self.class_of(ret),
+            _ => None,
+        };
+        if let Some(reason) = Attribute::find(&f.attrs, &NAMES).map(reason).or(by_class) { self.functions.insert(unqualified(f.name), reason); }
+    }
+
+    /// The reason, if `ty` names a `[[nodiscard]]` class.
+    fn class_of(&self, ty: TypeId) -> Option<Option<String>> {
+        match ty.unqualified().get() {
+            Type::Named(class) => self.classes.get(&unqualified(class)).cloned(),
+            _ => None,
+        }
+    }
+
+    /// Warns if `e`, an expression statement, calls a `[[nodiscard]]`
+    /// function.
+    pub fn check(&self, e: &Expr, diagnostics: &mut Diagnostics) {
+        let callee = match &e.kind {
+            ExprKind::Paren(inner) => return self.check(inner, diagnostics),
+            ExprKind::Call { callee, .. } => callee,
+            _ => return,
+        };
+        let name = match &callee.kind {
+            ExprKind::Name(name) | ExprKind::TemplateId { name, .. } => unqualified(name.symbol()),
+            ExprKind::Member { member, .. } => *member,
+            _ => return,
+        };
+        let Some(reason) = self.functions.get(&name) else { return };
+        let mut diag = Diagnostic::warning(format!("ignoring return value of `{}`, declared with attribute `nodiscard`", name))
+            .with_warning(Warning::UnusedResult)
+            .with_label(Label::primary(e.span, ""))
+            .with_help("cast the result to `void` to ignore it")
+            .with_fixit(Span::new(e.span.start, e.span.start), "(void)");
+        if let Some(reason) = reason { diag = diag.with_note(reason.clone()); }
+        diagnostics.emit(diag);
+    }
+}
+
+/// The string a `nodiscard("reason")` gives.
+fn reason(a: &Attribute) -> Option<String> {
+    match a.args.as_deref() {
+        Some([Expr { kind: ExprKind::StringLiteral(s), .. }]) => Some(s.text()),
+        _ => None,
+    }
+}
+
+fn unqualified(name: Symbol) -> Symbol {
+    match name.as_str().rsplit_once("::") {
+        Some((_, last)) => Symbol::intern(last),
+        None => name,
+    }
+}
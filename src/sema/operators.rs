@@ -0,0 +1,184 @@
+//! Overloaded operators and user-defined literals.
+//!
+//! Without overload resolution proper, an operator applied to an object
+//! of a class sema knows (see `Bindings`) must find an operator function
+//! of that spelling: a member of the class, or a free function with a
+//! parameter of the class. Comparisons also find the operators C++20
+//! rewrites them to, `==` for `!=` and `<=>` for the relational ones, with
+//! the operands either way round. Classes with bases or conversion
+//! functions may get an operator from either, and free operators with a
+//! parameter of a type sema does not know, like a template parameter, may
+//! apply to anything; these are not checked. A literal with a `_` suffix
+//! must have its `operator""` declared.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{BinaryOp, ClassDecl, Decl, Expr, ExprKind, FunctionDecl, FunctionKind, UnaryOp};
+use crate::diagnostics::Diagnostics;
+use crate::intern::Symbol;
+use crate::sema::bindings::Bindings;
+use crate::sema::SemaError;
+use crate::types::{Type, TypeId};
+
+/// The operator functions and literal operators declared so far.
+#[derive(Debug, Default)]
+pub struct Operators {
+    /// The spellings of each class's member operators.
+    members: HashMap<Symbol, HashSet<String>>,
+    /// Classes that may get operators from a base or by conversion.
+    open: HashSet<Symbol>,
+    /// The classes each free operator's parameters name, by spelling.
+    free: HashMap<String, HashSet<Symbol>>,
+    /// Free operators that may apply to any class.
+    generic: HashSet<String>,
+    /// The suffixes of the literal operators, like `_km`.
+    literals: HashSet<Symbol>,
+}
+
+impl Operators {
+    /// Records `c`'s member operators, before any of its member functions'
+    /// bodies are checked.
+    pub fn declare_class(&mut self, c: &ClassDecl, bindings: &Bindings) {
+        let (Some(name), Some(members)) = (c.name, &c.members) else { return };
+        let Some(class) = bindings.class_named(TypeId::intern(Type::Named(name))) else { return };
+        if !c.bases.is_empty() { self.open.insert(class); }
+        for member in members {
+            let f = match &member.decl {
+                Decl::Function(f) => f,
+                Decl::Template(t) => match &*t.decl {
+                    Decl::Function(f) => f,
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            if f.kind == FunctionKind::Conversion { self.open.insert(class); }
+            if f.specifiers.is_friend { self.declare_function(f, bindings); continue; }
+            if let Some(spelling) = spelling(f) { self.members.entry(class).or_default().insert(spelling); }
+        }
+    }
+
+    /// Records `f` if it is an operator function. A member is recorded
+    /// here too, as if free, under the classes its parameters name.
+    pub fn declare_function(&mut self, f: &FunctionDecl, bindings: &Bindings) {
+        let Some(spelling) = spelling(f) else { return };
+        if let Some(suffix) = spelling.strip_prefix("\"\"") {
+            self.literals.insert(Symbol::intern(suffix));
+            return;
+        }
+        for p in &f.params {
+            match bindings.class_named(p.ty) {
+                Some(class) => { self.free.entry(spelling.to_string()).or_default().insert(class); }
+                None if is_named(p.ty) => { self.generic.insert(spelling.to_string()); }
+                None => {}
+            }
+        }
+    }
+
+    /// Reports each operator in `e` applied to a class that has no
+    /// operator function for it, and each literal whose suffix has no
+    /// literal operator.
+    pub fn check(&self, e: &Expr, bindings: &Bindings, diagnostics: &mut Diagnostics) {
+        match &e.kind {
+            ExprKind::Binary { op, lhs, rhs } => {
+                if let Some(spellings) = candidates(*op) {
+                    let (l, r) = (bindings.class_of(lhs), bindings.class_of(rhs));
+                    let operands: Vec<_> = l.iter().chain(&r).map(|&(c, _)| c).collect();
+                    // Only comparisons may call a member of the right operand.
+                    let reversible = *op == BinaryOp::Eq || spellings.len() > 1;
+                    let classes = if reversible { &operands[..] } else { &operands[..usize::from(l.is_some())] };
+                    if let Some((_, ty)) = l.or(r) {
+                        if !self.viable(&spellings, classes, &operands) {
+                            diagnostics.emit(SemaError::NoViableOperator { op: op.spelling().into(), ty }.to_diagnostic(e.span));
+                        }
+                    }
+                }
+                self.check(lhs, bindings, diagnostics);
+                self.check(rhs, bindings, diagnostics);
+            }
+            ExprKind::Unary { op, operand } => {
+                if *op != UnaryOp::AddressOf { self.check_operand(op.spelling(), operand, e, bindings, diagnostics); }
+                self.check(operand, bindings, diagnostics);
+            }
+            ExprKind::Index { base, index } => {
+                self.check_operand("[]", base, e, bindings, diagnostics);
+                self.check(base, bindings, diagnostics);
+                self.check(index, bindings, diagnostics);
+            }
+            ExprKind::Call { callee, args } => {
+                self.check_operand("()", callee, e, bindings, diagnostics);
+                self.check(callee, bindings, diagnostics);
+                for arg in args { self.check(arg, bindings, diagnostics); }
+            }
+            ExprKind::Member { base, arrow, .. } => {
+                if *arrow { self.check_operand("->", base, e, bindings, diagnostics); }
+                self.check(base, bindings, diagnostics);
+            }
+            ExprKind::UserLiteral { suffix, .. } => {
+                if suffix.as_str().starts_with('_') && !self.literals.contains(suffix) {
+                    diagnostics.emit(SemaError::NoLiteralOperator(*suffix).to_diagnostic(e.span));
+                }
+            }
+            ExprKind::Paren(inner) | ExprKind::Cast { operand: inner, .. } | ExprKind::Designated { init: inner, .. } => self.check(inner, bindings, diagnostics),
+            ExprKind::Conditional { cond, then, otherwise } => {
+                for e in [cond, then, otherwise] { self.check(e, bindings, diagnostics); }
+            }
+            ExprKind::Construct { args: items, .. } | ExprKind::InitList(items) | ExprKind::ParenList(items) => {
+                for e in items { self.check(e, bindings, diagnostics); }
+            }
+            ExprKind::SizeofExpr(operand) | ExprKind::CoAwait(operand) | ExprKind::CoYield(operand) => self.check(operand, bindings, diagnostics),
+            ExprKind::IntLiteral(..) | ExprKind::FloatLiteral(_) | ExprKind::CharLiteral(_) | ExprKind::StringLiteral(_)
+            | ExprKind::BoolLiteral(_) | ExprKind::Nullptr | ExprKind::This | ExprKind::Name(_) | ExprKind::TemplateId { .. }
+            | ExprKind::Embed(_) | ExprKind::SizeofType(_) | ExprKind::Alignof(_) | ExprKind::Requires { .. } => {}
+        }
+    }
+
+    /// Reports `e`, which applies the operator `spelling` to `operand`,
+    /// if that is an object of a class without one.
+    fn check_operand(&self, spelling: &str, operand: &Expr, e: &Expr, bindings: &Bindings, diagnostics: &mut Diagnostics) {
+        let Some((class, ty)) = bindings.class_of(operand) else { return };
+        if !self.viable(&[spelling], &[class], &[class]) {
+            diagnostics.emit(SemaError::NoViableOperator { op: spelling.into(), ty }.to_diagnostic(e.span));
+        }
+    }
+
+    /// Whether an operator spelled one of `spellings` may apply: a member
+    /// of one of `classes`, or a free one taking one of `operands`.
+    fn viable(&self, spellings: &[&str], classes: &[Symbol], operands: &[Symbol]) -> bool {
+        if operands.iter().any(|c| self.open.contains(c)) { return true; }
+        spellings.iter().any(|&s| {
+            self.generic.contains(s)
+                || classes.iter().any(|c| self.members.get(c).is_some_and(|m| m.contains(s)))
+                || self.free.get(s).is_some_and(|f| operands.iter().any(|c| f.contains(c)))
+        })
+    }
+}
+
+/// The spellings of the operator functions `op` may call, or `None` if
+/// it is built in for classes too.
+fn candidates(op: BinaryOp) -> Option<Vec<&'static str>> {
+    match op {
+        BinaryOp::Assign | BinaryOp::Comma | BinaryOp::PtrMem | BinaryOp::PtrMemArrow => None,
+        BinaryOp::Ne => Some(vec!["!=", "=="]),
+        BinaryOp::Lt | BinaryOp::Gt | BinaryOp::Le | BinaryOp::Ge => Some(vec![op.spelling(), "<=>"]),
+        op => Some(vec![op.spelling()]),
+    }
+}
+
+/// What `f`'s name spells after `operator`, if it is an operator function
+/// or a literal operator: `+`, `[]`, `new`, `""_km`.
+fn spelling(f: &FunctionDecl) -> Option<String> {
+    if f.kind != FunctionKind::Normal { return None; }
+    let name = f.name.as_str();
+    let rest = name.rsplit("::").next()?.strip_prefix("operator")?;
+    if rest.starts_with(|c: char| c == '_' || c.is_ascii_alphanumeric()) || rest.is_empty() { return None; }
+    Some(rest.trim_start().to_string())
+}
+
+/// Whether `ty`, without a reference, names a type by name.
+fn is_named(ty: TypeId) -> bool {
+    let ty = match ty.get() {
+        Type::Reference(inner) | Type::RvalueReference(inner) => inner,
+        _ => ty,
+    };
+    matches!(ty.unqualified().get(), Type::Named(_))
+}
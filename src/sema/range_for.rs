@@ -0,0 +1,81 @@
+//! Range-based `for`, lowered to the loop the standard defines it as:
+//!
+//! ```text
+//! {
+//!     init-statement
+//!     auto&& __range = range;
+//!     auto __begin = begin-expr;
+//!     auto __end = end-expr;
+//!     for (; __begin != __end; ++__begin) {
+//!         decl = *__begin;
+//!         body
+//!     }
+//! }
+//! ```
+//!
+//! For an array, `begin-expr` and `end-expr` are `__range` and
+//! `__range + N`; for a class with a `begin` member, `__range.begin()` and
+//! `__range.end()`; otherwise `begin(__range)` and `end(__range)`. The
+//! names the rewrite introduces are reserved, so they cannot clash with
+//! the program's.
+
+use crate::ast::{BinaryOp, Block, Decl, Expr, ExprKind, InitStyle, IntSuffix, QualifiedName, Specifiers, Stmt, StmtKind, UnaryOp, VarDecl};
+use crate::intern::Symbol;
+use crate::types::{Type, TypeId};
+
+/// Where a range's iterators come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ends {
+    /// An array with this many elements.
+    Array(u64),
+    /// `begin()` and `end()` members.
+    Members,
+    /// `begin` and `end` found by argument-dependent lookup.
+    Functions,
+}
+
+/// The block `stmt`, a `StmtKind::RangeFor`, stands for. Each node the
+/// rewrite adds takes the span of the part of the loop it comes from, and
+/// is allocated in the arena `stmt` was parsed into.
+pub fn lower<'a>(stmt: &Stmt<'a>, ends: Ends) -> Stmt<'a> {
+    let StmtKind::RangeFor { init, decl, range, body } = &stmt.kind else { panic!("not a range-based for") };
+    let arena = body.arena();
+    let at = |kind: ExprKind<'a>| Expr { kind, span: range.span };
+    let name = |text: &str| at(ExprKind::Name(QualifiedName::new(Symbol::intern(text))));
+    let var = |text: &str, ty: Type, init: Expr<'a>| Stmt {
+        kind: StmtKind::Decl(vec![Decl::Var(VarDecl {
+            name: Symbol::intern(text), ty: TypeId::intern(ty), specifiers: Specifiers::default(), init: Some(init),
+            init_style: InitStyle::Copy, bit_width: None, attrs: Vec::new(), span: range.span, name_span: range.span,
+        })]),
+        span: range.span,
+    };
+    let call = |callee: ExprKind<'a>, args: Vec<Expr<'a>>| at(ExprKind::Call { callee: arena.boxed(at(callee)), args });
+    let end = |which: &str| match ends {
+        Ends::Array(_) if which == "begin" => name("__range"),
+        Ends::Array(n) => at(ExprKind::Binary { op: BinaryOp::Add, lhs: arena.boxed(name("__range")), rhs: arena.boxed(at(ExprKind::IntLiteral(n, IntSuffix::default()))) }),
+        Ends::Members => call(ExprKind::Member { base: arena.boxed(name("__range")), member: Symbol::intern(which), arrow: false }, Vec::new()),
+        Ends::Functions => call(ExprKind::Name(QualifiedName::new(Symbol::intern(which))), vec![name("__range")]),
+    };
+    let element = at(ExprKind::Unary { op: UnaryOp::Deref, operand: arena.boxed(name("__begin")) });
+    let mut decl = (**decl).clone();
+    match &mut decl {
+        Decl::Var(v) => v.init = Some(element),
+        Decl::Decomposition(d) => d.init = Some(element),
+        _ => unreachable!("the parser only makes variables and structured bindings here"),
+    }
+    let decl = Stmt { span: decl.span(), kind: StmtKind::Decl(vec![decl]) };
+    let loop_body = Block { stmts: vec![decl, (**body).clone()], span: body.span };
+    let looped = Stmt {
+        kind: StmtKind::For {
+            init: None,
+            cond: Some(at(ExprKind::Binary { op: BinaryOp::Ne, lhs: arena.boxed(name("__begin")), rhs: arena.boxed(name("__end")) })),
+            step: Some(at(ExprKind::Unary { op: UnaryOp::PreIncrement, operand: arena.boxed(name("__begin")) })),
+            body: arena.boxed(Stmt { kind: StmtKind::Compound(loop_body), span: body.span }),
+        },
+        span: stmt.span,
+    };
+    let auto_ref = Type::RvalueReference(TypeId::intern(Type::Auto));
+    let mut stmts: Vec<Stmt> = init.iter().map(|s| (**s).clone()).collect();
+    stmts.extend([var("__range", auto_ref, range.clone()), var("__begin", Type::Auto, end("begin")), var("__end", Type::Auto, end("end")), looped]);
+    Stmt { kind: StmtKind::Compound(Block { stmts, span: stmt.span }), span: stmt.span }
+}
@@ -0,0 +1,530 @@
+//! The reference index: where each name in a translation unit is declared
+//! and every place it is used, for renaming.
+//!
+//! Names are resolved by scope, as far as that goes without types. A name
+//! is looked up in the scopes enclosing it, innermost first, among the
+//! declarations before it or anywhere in a class; a name after `::` among
+//! the members of the class, enum or namespace before it; and a member
+//! after `.` or `->` among the members of every class, if only one class
+//! has a member of that name. Every identifier token is resolved, so names
+//! in types are found as well as those in expressions. Overloads of a
+//! function are one entity, and constructors and destructors are uses of
+//! their class's name. Tokens that came out of a macro expansion are left
+//! alone, as are names the index cannot resolve.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::ast::{Block, ClassDecl, Decl, Expr, ExprKind, FunctionDecl, FunctionKind, ParamDecl, QualifiedName, Stmt, StmtKind, TemplateParam, TranslationUnit};
+use crate::intern::Symbol;
+use crate::lexer::token::{Span, SpannedToken, Token};
+use crate::parser;
+use crate::source::SourceManager;
+use crate::visit::Visitor;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EntityId(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct ScopeId(usize);
+
+/// The global namespace.
+const GLOBAL: ScopeId = ScopeId(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityKind {
+    Variable,
+    Parameter,
+    Function,
+    Class,
+    Enum,
+    Enumerator,
+    Typedef,
+    Namespace,
+    Concept,
+    TemplateParam,
+}
+
+/// Something a name declares.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entity {
+    pub name: Symbol,
+    pub kind: EntityKind,
+    /// The name in its first declaration.
+    pub span: Span,
+    /// The scope of a class, enum or namespace's members.
+    members: Option<ScopeId>,
+    /// The index in the token stream of the first declaration, which
+    /// unlike a position orders declarations in headers before the code
+    /// after their `#include`.
+    declared: usize,
+}
+
+/// A place an entity's name is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reference {
+    pub span: Span,
+    pub entity: EntityId,
+    /// Whether the name declares the entity rather than uses it.
+    pub is_declaration: bool,
+    /// The innermost scope enclosing a use found by lookup in the scopes
+    /// around it, rather than after `::`, `.` or `->`.
+    scope: Option<ScopeId>,
+    /// The index of the name in the token stream.
+    token: usize,
+}
+
+#[derive(Debug, Default)]
+struct Scope {
+    parent: Option<ScopeId>,
+    names: HashMap<Symbol, EntityId>,
+    /// A class, whose members are visible throughout it.
+    is_class: bool,
+    /// A template's parameter list, which declares only the parameters:
+    /// the templated declaration belongs to the scope around it.
+    is_template: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct ReferenceIndex {
+    entities: Vec<Entity>,
+    scopes: Vec<Scope>,
+    /// The source range of each scope; a namespace has one per definition.
+    regions: Vec<(Span, ScopeId)>,
+    references: Vec<Reference>,
+}
+
+/// Why a rename was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenameError {
+    /// Not an identifier, or a keyword.
+    InvalidName(String),
+    /// The new name is declared in a scope the entity is declared in.
+    Redeclaration { name: String, existing: Span },
+    /// A declaration of the new name between a use and the entity, which
+    /// the use would refer to instead.
+    Hidden { name: String, declaration: Span, use_span: Span },
+    /// A use of the new name that would refer to the renamed entity
+    /// instead of what it refers to now.
+    Hides { name: String, use_span: Span },
+}
+
+impl RenameError {
+    /// Where the conflict is.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            RenameError::InvalidName(_) => None,
+            RenameError::Redeclaration { existing: span, .. } | RenameError::Hidden { use_span: span, .. } | RenameError::Hides { use_span: span, .. } => Some(*span),
+        }
+    }
+}
+
+impl fmt::Display for RenameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenameError::InvalidName(name) => write!(f, "`{}` is not a valid identifier", name),
+            RenameError::Redeclaration { name, .. } => write!(f, "`{}` is already declared in this scope", name),
+            RenameError::Hidden { name, .. } => write!(f, "this use would refer to another `{}` declared in an inner scope", name),
+            RenameError::Hides { name, .. } => write!(f, "this use of `{}` would refer to the renamed declaration", name),
+        }
+    }
+}
+
+impl std::error::Error for RenameError {}
+
+impl ReferenceIndex {
+    /// Indexes `tu`, which was parsed from `tokens`.
+    pub fn build(tu: &TranslationUnit, tokens: &[SpannedToken], sources: &SourceManager) -> Self {
+        let mut idents: Vec<(Span, Symbol)> = tokens.iter()
+            .filter_map(|t| match &t.token {
+                Token::Identifier(name) => Some((t.span, *name)),
+                _ => None,
+            })
+            .collect();
+        idents.sort_by_key(|(span, _)| span.start);
+        let order = tokens.iter().enumerate().rev().map(|(i, t)| (t.span.start, i)).collect();
+        let mut index = ReferenceIndex { scopes: vec![Scope::default()], ..Default::default() };
+        let mut builder = Builder { index: &mut index, stack: vec![GLOBAL], idents, order };
+        builder.visit_translation_unit(tu);
+        index.resolve(tokens, sources);
+        index
+    }
+
+    pub fn entity(&self, id: EntityId) -> &Entity { &self.entities[id.0] }
+
+    /// Whether `entity` is declared in a class, as a member.
+    pub fn is_member(&self, entity: EntityId) -> bool {
+        let name = self.entity(entity).name;
+        self.scopes.iter().any(|s| s.is_class && s.names.get(&name) == Some(&entity))
+    }
+
+    /// Every reference, declarations first, then uses in token order.
+    pub fn references(&self) -> &[Reference] { &self.references }
+
+    /// The entity whose name is written at `pos`, including just past it.
+    pub fn at(&self, pos: usize) -> Option<EntityId> {
+        self.references.iter().find(|r| r.span.start <= pos && pos <= r.span.end).map(|r| r.entity)
+    }
+
+    /// Where `entity`'s name is written, in position order.
+    pub fn references_to(&self, entity: EntityId) -> Vec<Span> {
+        let mut spans: Vec<Span> = self.references.iter().filter(|r| r.entity == entity).map(|r| r.span).collect();
+        spans.sort_by_key(|s| s.start);
+        spans.dedup();
+        spans
+    }
+
+    /// The spans to replace with `new_name` to rename `entity`, unless
+    /// that would make a name refer to something else: a redeclaration in
+    /// the entity's scope, a use captured by an inner declaration of
+    /// `new_name`, or a use of `new_name` the entity would hide.
+    pub fn rename(&self, entity: EntityId, new_name: &str) -> Result<Vec<Span>, RenameError> {
+        let valid = new_name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && new_name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid || parser::is_keyword(new_name) { return Err(RenameError::InvalidName(new_name.to_string())); }
+        let new = Symbol::intern(new_name);
+        let old = self.entity(entity);
+        if new == old.name { return Ok(self.references_to(entity)); }
+        let homes: Vec<ScopeId> = (0..self.scopes.len()).map(ScopeId).filter(|s| self.scopes[s.0].names.get(&old.name) == Some(&entity)).collect();
+        for &home in &homes {
+            if let Some(&existing) = self.scopes[home.0].names.get(&new) {
+                return Err(RenameError::Redeclaration { name: new_name.to_string(), existing: self.entity(existing).span });
+            }
+        }
+        for r in &self.references {
+            let Some(scope) = r.scope else { continue };
+            let pos = r.token;
+            if r.entity == entity {
+                // Scopes between the use and the entity's.
+                for s in self.chain(scope).take_while(|s| !homes.contains(s)) {
+                    if let Some(&other) = self.scopes[s.0].names.get(&new) {
+                        if self.visible(s, other, pos) {
+                            return Err(RenameError::Hidden { name: new_name.to_string(), declaration: self.entity(other).span, use_span: r.span });
+                        }
+                    }
+                }
+            } else if self.entity(r.entity).name == new {
+                for s in self.chain(scope) {
+                    if self.scopes[s.0].names.get(&new) == Some(&r.entity) { break; }
+                    if homes.contains(&s) && self.visible(s, entity, pos) {
+                        return Err(RenameError::Hides { name: new_name.to_string(), use_span: r.span });
+                    }
+                }
+            }
+        }
+        Ok(self.references_to(entity))
+    }
+
+    /// `scope` and the scopes around it, innermost first.
+    fn chain(&self, scope: ScopeId) -> impl Iterator<Item = ScopeId> + '_ {
+        std::iter::successors(Some(scope), |s| self.scopes[s.0].parent)
+    }
+
+    /// Whether `entity`, a member of `scope`, can be named by token `token`.
+    fn visible(&self, scope: ScopeId, entity: EntityId, token: usize) -> bool {
+        self.scopes[scope.0].is_class || self.entity(entity).declared <= token
+    }
+
+    /// The entity `name` as token `token` refers to, looked up from `scope`
+    /// out.
+    fn lookup(&self, scope: ScopeId, name: Symbol, token: usize) -> Option<EntityId> {
+        self.chain(scope).find_map(|s| self.scopes[s.0].names.get(&name).copied().filter(|&e| self.visible(s, e, token)))
+    }
+
+    /// Resolves every identifier token that is not a declaration.
+    fn resolve(&mut self, tokens: &[SpannedToken], sources: &SourceManager) {
+        let declared: HashMap<usize, EntityId> = self.references.iter().map(|r| (r.span.start, r.entity)).collect();
+        // Members by name, for `.` and `->`.
+        let mut members: HashMap<Symbol, Vec<EntityId>> = HashMap::new();
+        for scope in self.scopes.iter().filter(|s| s.is_class) {
+            for (&name, &entity) in &scope.names { members.entry(name).or_default().push(entity); }
+        }
+        let scopes = self.innermost_scopes(tokens);
+        let mut resolved: Vec<Option<EntityId>> = vec![None; tokens.len()];
+        for (i, t) in tokens.iter().enumerate() {
+            let Token::Identifier(text) = &t.token else { continue };
+            if sources.snippet(t.span).is_none_or(|s| *text != s) { continue; }
+            let name = *text;
+            if let Some(&entity) = declared.get(&t.span.start) {
+                resolved[i] = Some(entity);
+                continue;
+            }
+            let prev = |back: usize| i.checked_sub(back).map(|j| tokens[j].token.spelling());
+            // The lexer splits `::` into two `:`s.
+            let after_scope = prev(1).as_deref() == Some(":") && prev(2).as_deref() == Some(":") && tokens[i - 2].span.end == tokens[i - 1].span.start;
+            let (entity, scope) = match prev(1).as_deref() {
+                Some("." | "->") => match members.get(&name).map(Vec::as_slice) {
+                    Some([only]) => (Some(*only), None),
+                    _ => (None, None),
+                },
+                Some(":") if after_scope => {
+                    let qualifier = match i.checked_sub(3).map(|j| &tokens[j].token) {
+                        Some(Token::Identifier(_)) => resolved[i - 3].and_then(|q| self.entity(q).members),
+                        Some(Token::Operator(op)) if *op == ">" => None,
+                        _ => Some(GLOBAL),
+                    };
+                    (qualifier.and_then(|q| self.scopes[q.0].names.get(&name).copied()), None)
+                }
+                _ => (self.lookup(scopes[i], name, i), Some(scopes[i])),
+            };
+            resolved[i] = entity;
+            if let Some(entity) = entity { self.references.push(Reference { span: t.span, entity, is_declaration: false, scope, token: i }); }
+        }
+    }
+
+    /// The innermost scope around each token, by a sweep over the nested
+    /// regions in position order.
+    fn innermost_scopes(&self, tokens: &[SpannedToken]) -> Vec<ScopeId> {
+        let mut regions: Vec<(usize, &(Span, ScopeId))> = self.regions.iter().enumerate().collect();
+        regions.sort_by_key(|(i, (span, _))| (span.start, *i));
+        let mut order: Vec<usize> = (0..tokens.len()).collect();
+        order.sort_by_key(|&i| tokens[i].span.start);
+        let mut out = vec![GLOBAL; tokens.len()];
+        let mut open: Vec<&(Span, ScopeId)> = Vec::new();
+        let mut next = 0;
+        for i in order {
+            let pos = tokens[i].span.start;
+            while let Some((_, region)) = regions.get(next).filter(|(_, (span, _))| span.start <= pos) {
+                while open.last().is_some_and(|(span, _)| span.end <= region.0.start) { open.pop(); }
+                open.push(region);
+                next += 1;
+            }
+            while open.last().is_some_and(|(span, _)| span.end <= pos) { open.pop(); }
+            if let Some((_, scope)) = open.last() { out[i] = *scope; }
+        }
+        out
+    }
+}
+
+/// Collects the scopes and declarations of a tree.
+struct Builder<'x> {
+    index: &'x mut ReferenceIndex,
+    /// The scopes around the current node, innermost last.
+    stack: Vec<ScopeId>,
+    /// Identifier tokens in position order, to find names in declarations
+    /// that keep only the span of the whole.
+    idents: Vec<(Span, Symbol)>,
+    /// The index in the token stream of the token at each position.
+    order: HashMap<usize, usize>,
+}
+
+impl Builder<'_> {
+    fn current(&self) -> ScopeId { *self.stack.last().unwrap_or(&GLOBAL) }
+
+    /// The scope declarations go in: the innermost that is not a
+    /// template's parameter list.
+    fn declaring(&self) -> ScopeId {
+        self.stack.iter().rev().copied().find(|s| !self.index.scopes[s.0].is_template).unwrap_or(GLOBAL)
+    }
+
+    fn new_scope(&mut self, parent: ScopeId, span: Span) -> ScopeId {
+        let id = ScopeId(self.index.scopes.len());
+        self.index.scopes.push(Scope { parent: Some(parent), ..Scope::default() });
+        self.index.regions.push((span, id));
+        id
+    }
+
+    /// Runs `f` inside `scope`.
+    fn within(&mut self, scope: ScopeId, f: impl FnOnce(&mut Self)) {
+        self.stack.push(scope);
+        f(self);
+        self.stack.pop();
+    }
+
+    /// Where `name` is written in `span`.
+    fn find(&self, span: Span, name: Symbol) -> Option<Span> {
+        let first = self.idents.partition_point(|(s, _)| s.start < span.start);
+        self.idents[first..].iter().take_while(|(s, _)| s.end <= span.end).find(|(_, n)| *n == name).map(|(s, _)| *s)
+    }
+
+    /// Declares `name` in `scope`, or adds a declaration to the entity it
+    /// already names there.
+    fn declare(&mut self, scope: ScopeId, name: Symbol, at: Option<Span>, kind: EntityKind) -> Option<EntityId> {
+        let text = name.as_str();
+        if text.is_empty() || !text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') { return None; }
+        let at = at?;
+        let token = self.order.get(&at.start).copied().unwrap_or(0);
+        let id = match self.index.scopes[scope.0].names.get(&name) {
+            Some(&id) => id,
+            None => {
+                let id = EntityId(self.index.entities.len());
+                self.index.entities.push(Entity { name, kind, span: at, members: None, declared: token });
+                self.index.scopes[scope.0].names.insert(name, id);
+                id
+            }
+        };
+        self.index.references.push(Reference { span: at, entity: id, is_declaration: true, scope: None, token });
+        Some(id)
+    }
+
+    /// The scope of `entity`'s members, made on first use.
+    fn members(&mut self, entity: EntityId, parent: ScopeId, span: Span) -> ScopeId {
+        match self.index.entities[entity.0].members {
+            Some(scope) => {
+                self.index.regions.push((span, scope));
+                scope
+            }
+            None => {
+                let scope = self.new_scope(parent, span);
+                self.index.entities[entity.0].members = Some(scope);
+                scope
+            }
+        }
+    }
+
+    /// The scope a qualifier like `a::S` names, looked up from here.
+    fn qualifier_scope(&self, qualifier: QualifiedName) -> Option<ScopeId> {
+        let mut segments = qualifier.segments().filter(|s| !s.is_empty());
+        let mut scope = if qualifier.is_global() {
+            GLOBAL
+        } else {
+            let first = Symbol::intern(&segments.next()?);
+            let entity = self.index.chain(self.current()).find_map(|s| self.index.scopes[s.0].names.get(&first).copied())?;
+            self.index.entity(entity).members?
+        };
+        for segment in segments {
+            let entity = *self.index.scopes[scope.0].names.get(&Symbol::intern(&segment))?;
+            scope = self.index.entity(entity).members?;
+        }
+        Some(scope)
+    }
+
+    /// Declares a possibly qualified name, like `S::count` in a definition
+    /// of a static member, in the scope it names. Returns that scope.
+    fn declare_qualified(&mut self, name: Symbol, name_span: Span, kind: EntityKind) -> ScopeId {
+        let qualified = QualifiedName::new(name);
+        let scope = match qualified.qualifier() {
+            Some(q) => self.qualifier_scope(q).unwrap_or_else(|| self.declaring()),
+            None => self.declaring(),
+        };
+        let last = qualified.last();
+        let at = self.find(name_span, last);
+        self.declare(scope, last, at, kind);
+        scope
+    }
+}
+
+impl Visitor for Builder<'_> {
+    fn visit_decl(&mut self, decl: &Decl) {
+        match decl {
+            // An unnamed bit-field declares nothing.
+            Decl::Var(v) if v.name.as_str().is_empty() => {}
+            Decl::Var(v) => { self.declare_qualified(v.name, v.name_span, EntityKind::Variable); }
+            Decl::Decomposition(d) => {
+                let scope = self.declaring();
+                for b in &d.bindings { self.declare(scope, b.name, Some(b.span), EntityKind::Variable); }
+            }
+            Decl::Typedef(t) => { self.declare(self.declaring(), t.name, Some(t.name_span), EntityKind::Typedef); }
+            Decl::Concept(c) => { self.declare(self.declaring(), c.name, Some(c.name_span), EntityKind::Concept); }
+            Decl::Namespace(n) => {
+                // An unnamed namespace's members are found as if they were
+                // the enclosing namespace's.
+                let Some(name) = n.name else { return self.walk_decl(decl) };
+                let at = self.find(n.span, name);
+                let Some(entity) = self.declare(self.declaring(), name, at, EntityKind::Namespace) else { return self.walk_decl(decl) };
+                let scope = self.members(entity, self.current(), n.span);
+                return self.within(scope, |b| b.walk_decl(decl));
+            }
+            Decl::Enum(e) => {
+                let home = self.declaring();
+                let entity = e.name.and_then(|name| {
+                    let at = self.find(e.span, name);
+                    self.declare(home, name, at, EntityKind::Enum)
+                });
+                let scope = match entity {
+                    Some(entity) => self.members(entity, self.current(), e.span),
+                    None => self.new_scope(self.current(), e.span),
+                };
+                for en in e.enumerators.iter().flatten() {
+                    let at = self.find(en.span, en.name);
+                    if let Some(id) = self.declare(scope, en.name, at, EntityKind::Enumerator) {
+                        // Unscoped enumerators are also the enclosing scope's.
+                        if !e.is_scoped { self.index.scopes[home.0].names.entry(en.name).or_insert(id); }
+                    }
+                    if let Some(value) = &en.value { self.within(scope, |b| b.visit_expr(value)); }
+                }
+                return;
+            }
+            Decl::Template(t) => {
+                let scope = self.new_scope(self.current(), t.span);
+                self.index.scopes[scope.0].is_template = true;
+                return self.within(scope, |b| b.walk_decl(decl));
+            }
+            _ => {}
+        }
+        self.walk_decl(decl);
+    }
+
+    fn visit_class(&mut self, c: &ClassDecl) {
+        let entity = c.name.and_then(|name| {
+            let at = self.find(c.span, name);
+            self.declare(self.declaring(), name, at, EntityKind::Class)
+        });
+        if c.members.is_none() { return; }
+        let scope = match entity {
+            Some(entity) => self.members(entity, self.current(), c.span),
+            None => self.new_scope(self.current(), c.span),
+        };
+        self.index.scopes[scope.0].is_class = true;
+        self.within(scope, |b| b.walk_class(c));
+    }
+
+    fn visit_function(&mut self, f: &FunctionDecl) {
+        let qualified = QualifiedName::new(f.name);
+        // An out-of-line member's body sees the class's members.
+        let parent = match f.kind {
+            FunctionKind::Normal => self.declare_qualified(f.name, f.name_span, EntityKind::Function),
+            _ => qualified.qualifier().and_then(|q| self.qualifier_scope(q)).unwrap_or_else(|| self.current()),
+        };
+        let parent = if parent == self.declaring() { self.current() } else { parent };
+        let scope = self.new_scope(parent, f.span);
+        self.within(scope, |b| {
+            for p in &f.params { b.visit_param(p); }
+            if let Some(requires) = &f.requires { b.visit_expr(requires); }
+            for init in &f.initializers {
+                for arg in &init.args { b.visit_expr(arg); }
+            }
+            // The body shares the parameters' scope.
+            for stmt in f.body.iter().flat_map(|body| &body.stmts) { b.visit_stmt(stmt); }
+        });
+    }
+
+    fn visit_param(&mut self, p: &ParamDecl) {
+        if let Some(name) = p.name {
+            let at = self.find(p.span, name);
+            self.declare(self.current(), name, at, EntityKind::Parameter);
+        }
+        self.walk_param(p);
+    }
+
+    fn visit_template_param(&mut self, p: &TemplateParam) {
+        if let Some(name) = p.name {
+            let at = self.find(p.span, name);
+            self.declare(self.current(), name, at, EntityKind::TemplateParam);
+        }
+        self.walk_template_param(p);
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        let scope = self.new_scope(self.current(), block.span);
+        self.within(scope, |b| b.walk_block(block));
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::If { .. } | StmtKind::While { .. } | StmtKind::For { .. } | StmtKind::RangeFor { .. } | StmtKind::Switch { .. } => {
+                let scope = self.new_scope(self.current(), stmt.span);
+                self.within(scope, |b| b.walk_stmt(stmt));
+            }
+            _ => self.walk_stmt(stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, e: &Expr) {
+        if let ExprKind::Requires { .. } = e.kind {
+            let scope = self.new_scope(self.current(), e.span);
+            return self.within(scope, |b| b.walk_expr(e));
+        }
+        self.walk_expr(e);
+    }
+}
@@ -0,0 +1,126 @@
+//! Unsequenced modifications, as GCC's `-Wsequence-point` finds them.
+//!
+//! Modifying a variable twice, or modifying and reading it, without a
+//! sequencing rule between the two is undefined behavior: `i++ + i++`,
+//! `i + i++`. This follows the C++17 rules, under which `&&`, `||`, `,`,
+//! `?:`, assignments, `<<`, `>>`, `.*`, `->*`, subscripts, calls and
+//! braced initializer lists order their operands (call arguments are indeterminately sequenced, which is
+//! unspecified rather than undefined); every other operator leaves its
+//! operands unsequenced. Only plain variable names are tracked.
+
+use std::collections::HashSet;
+
+use crate::ast::{BinaryOp, Expr, ExprKind, UnaryOp};
+use crate::diagnostics::{Diagnostic, Diagnostics, Label, Warning};
+use crate::intern::Symbol;
+use crate::lexer::token::Span;
+
+/// The variables an expression writes and reads.
+#[derive(Default)]
+struct Effects {
+    writes: Vec<(Symbol, Span)>,
+    reads: Vec<(Symbol, Span)>,
+}
+
+impl Effects {
+    fn merge(mut self, other: Effects) -> Effects {
+        self.writes.extend(other.writes);
+        self.reads.extend(other.reads);
+        self
+    }
+}
+
+/// Warns about unsequenced modifications in the full-expression `expr`,
+/// once per variable.
+pub fn check(expr: &Expr, diagnostics: &mut Diagnostics) {
+    let mut checker = Checker { reported: HashSet::new(), diagnostics };
+    checker.effects(expr);
+}
+
+struct Checker<'d> {
+    reported: HashSet<Symbol>,
+    diagnostics: &'d mut Diagnostics,
+}
+
+impl Checker<'_> {
+    fn effects(&mut self, e: &Expr) -> Effects {
+        match &e.kind {
+            ExprKind::Name(name) => Effects { reads: vec![(name.symbol(), e.span)], ..Effects::default() },
+            ExprKind::Paren(inner) | ExprKind::Member { base: inner, .. } | ExprKind::Cast { operand: inner, .. }
+            | ExprKind::CoAwait(inner) | ExprKind::CoYield(inner) => self.effects(inner),
+            ExprKind::Unary { op, operand } => {
+                let mut effects = self.effects(operand);
+                let modifies = matches!(op, UnaryOp::PreIncrement | UnaryOp::PreDecrement | UnaryOp::PostIncrement | UnaryOp::PostDecrement);
+                if let (true, Some(name)) = (modifies, variable(operand)) { effects.writes.push((name, e.span)); }
+                effects
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                let (l, r) = (self.effects(lhs), self.effects(rhs));
+                if op.is_assignment() {
+                    let mut effects = l.merge(r);
+                    if let Some(name) = variable(lhs) { effects.writes.push((name, e.span)); }
+                    return effects;
+                }
+                let sequenced = matches!(op, BinaryOp::Comma | BinaryOp::LogicalAnd | BinaryOp::LogicalOr
+                    | BinaryOp::Shl | BinaryOp::Shr | BinaryOp::PtrMem | BinaryOp::PtrMemArrow);
+                if !sequenced {
+                    self.conflicts(&l, &r);
+                    self.conflicts(&r, &l);
+                }
+                l.merge(r)
+            }
+            ExprKind::Conditional { cond, then, otherwise } => {
+                let effects = self.effects(cond).merge(self.effects(then));
+                effects.merge(self.effects(otherwise))
+            }
+            ExprKind::Call { callee, args } => {
+                let callee = self.effects(callee);
+                args.iter().fold(callee, |acc, arg| acc.merge(self.effects(arg)))
+            }
+            // Braced list elements are sequenced in order; constructor
+            // arguments are indeterminately sequenced, like a call's.
+            ExprKind::Construct { args, .. } | ExprKind::InitList(args) | ExprKind::ParenList(args) => {
+                args.iter().fold(Effects::default(), |acc, arg| acc.merge(self.effects(arg)))
+            }
+            ExprKind::Designated { init, .. } => self.effects(init),
+            ExprKind::Index { base, index } => {
+                let base = self.effects(base);
+                base.merge(self.effects(index))
+            }
+            // `sizeof` does not evaluate its operand.
+            ExprKind::SizeofExpr(_) | ExprKind::SizeofType(_) | ExprKind::Alignof(_) => Effects::default(),
+            ExprKind::IntLiteral(..) | ExprKind::FloatLiteral(_) | ExprKind::CharLiteral(_) | ExprKind::StringLiteral(_)
+            | ExprKind::BoolLiteral(_) | ExprKind::Nullptr | ExprKind::This | ExprKind::TemplateId { .. } | ExprKind::Embed(_)
+            | ExprKind::UserLiteral { .. } | ExprKind::Requires { .. } => Effects::default(),
+        }
+    }
+
+    /// Reports each write in `a` that `b` also writes or reads.
+    fn conflicts(&mut self, a: &Effects, b: &Effects) {
+        for &(name, span) in &a.writes {
+            if self.reported.contains(&name) { continue; }
+            let (message, other) = match b.writes.iter().find(|(n, _)| *n == name) {
+                Some(&(_, other)) => (format!("multiple unsequenced modifications to `{}`", name), other),
+                None => match b.reads.iter().find(|(n, _)| *n == name) {
+                    Some(&(_, other)) => (format!("unsequenced modification and access to `{}`", name), other),
+                    None => continue,
+                },
+            };
+            self.reported.insert(name);
+            self.diagnostics.emit(Diagnostic::warning(message)
+                .with_warning(Warning::SequencePoint)
+                .with_label(Label::primary(span, "modified here"))
+                .with_label(Label::secondary(other, "and here, in no particular order"))
+                .with_note("the behavior is undefined"));
+        }
+    }
+}
+
+/// The variable `e` names, looking through parentheses.
+fn variable(e: &Expr) -> Option<Symbol> {
+    match &e.kind {
+        ExprKind::Name(name) => Some(name.symbol()),
+        ExprKind::Paren(inner) => variable(inner),
+        _ => None,
+    }
+}
@@ -0,0 +1,1305 @@
+//! Expression types: the type and value category of each expression, and
+//! the errors of expressions that cannot have one, like calling an `int`
+//! or assigning to a temporary.
+//!
+//! Names are looked up in the block scopes around them, then among the
+//! members of the class whose member function encloses them, then among
+//! the functions declared so far, by unqualified name or, when qualified
+//! by a namespace, by their full name. A static member function has no
+//! object to take a non-static member from. In a member function of a
+//! class whose members are all known, outside templates, a name found
+//! nowhere is undeclared, unless declarations were skipped or brought in
+//! by `using`. Overloads are narrowed down by
+//! their number of parameters, then ranked by the implicit conversion
+//! sequences of the arguments, as overload resolution does: an exact match
+//! beats a promotion, which beats a conversion, which beats passing the
+//! argument through `...`. A call that no overload matches best is
+//! ambiguous.
+//!
+//! An implicit conversion is a standard conversion sequence: an
+//! lvalue-to-rvalue, array-to-pointer or function-to-pointer conversion,
+//! then a promotion or conversion between arithmetic types, a pointer or
+//! boolean conversion, then a qualification conversion. Only arithmetic
+//! and pointer types, and pointers to the same class, are converted;
+//! with `-Wconversion`, arithmetic conversions that may change a value,
+//! like `double` to `int` or `long` to `short`, are warned about, unless
+//! the value is a constant that converts exactly. With `-Wsign-compare`,
+//! so are comparisons that convert a signed operand to unsigned.
+//!
+//! An expression whose type depends on something sema does not know, like
+//! an undeclared name, a template parameter, a class with bases or an
+//! overloaded operator, has no type, and neither does anything built from
+//! it; no error is reported for those. Member access with `->` on a class
+//! object is not one of them: it goes through the class's `operator->`,
+//! and the result's while that is a class object too.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{BinaryOp, ClassDecl, Decl, EnumDecl, Expr, ExprKind, FunctionDecl, FunctionKind, InitStyle, StorageClass, UnaryOp, VarDecl};
+use crate::charset::ExecCharsets;
+use crate::diagnostics::{Diagnostic, Diagnostics, Label, Warning};
+use crate::intern::Symbol;
+use crate::lexer::token::{Encoding, Literal, Span};
+use crate::sema::bindings::Bindings;
+use crate::sema::constant;
+use crate::sema::SemaError;
+use crate::types::{Qualifiers, Type, TypeId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Lvalue,
+    Xvalue,
+    Prvalue,
+}
+
+/// The type of an expression, never a reference, and its value category.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExprType {
+    pub ty: TypeId,
+    pub category: Category,
+}
+
+impl ExprType {
+    fn lvalue(ty: TypeId) -> Self { Self { ty, category: Category::Lvalue } }
+
+    fn prvalue(ty: TypeId) -> Self { Self { ty, category: Category::Prvalue } }
+
+    /// The result of an expression of declared type `ty`, like a call to a
+    /// function returning `ty`: references give lvalues and xvalues.
+    fn of_declared(ty: TypeId) -> Self {
+        match ty.get() {
+            Type::Reference(inner) => Self::lvalue(inner),
+            Type::RvalueReference(inner) => Self { ty: inner, category: Category::Xvalue },
+            _ => Self::prvalue(ty),
+        }
+    }
+}
+
+/// One step of a standard conversion sequence ([conv]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conversion {
+    LvalueToRvalue,
+    ArrayToPointer,
+    FunctionToPointer,
+    /// `char` or `short` to `int`, say.
+    IntegralPromotion,
+    /// `float` to `double`.
+    FloatingPromotion,
+    IntegralConversion,
+    FloatingConversion,
+    /// Between a floating and an integral type.
+    FloatingIntegral,
+    /// To `void*`.
+    PointerConversion,
+    /// `0` or `nullptr` to a pointer.
+    NullPointer,
+    /// An arithmetic or pointer value to `bool`.
+    BooleanConversion,
+    /// Adding `const` or `volatile` below a pointer.
+    Qualification,
+}
+
+/// How good an argument's conversion to a parameter is, best first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rank {
+    Exact,
+    Promotion,
+    Conversion,
+    /// Passed through a function's `...`.
+    Ellipsis,
+}
+
+impl Conversion {
+    pub fn rank(self) -> Rank {
+        match self {
+            Conversion::LvalueToRvalue | Conversion::ArrayToPointer | Conversion::FunctionToPointer | Conversion::Qualification => Rank::Exact,
+            Conversion::IntegralPromotion | Conversion::FloatingPromotion => Rank::Promotion,
+            _ => Rank::Conversion,
+        }
+    }
+}
+
+/// How an expression converts to a type, as far as sema can tell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Implicit {
+    /// By these steps, in order; none for the same type.
+    Standard(Vec<Conversion>),
+    /// Not at all.
+    Invalid,
+    /// Through a type sema does not know, or maybe a user-defined
+    /// conversion.
+    Unknown,
+}
+
+impl Implicit {
+    fn rank(&self) -> Option<Rank> {
+        match self {
+            Implicit::Standard(steps) => Some(steps.iter().map(|s| s.rank()).max().unwrap_or(Rank::Exact)),
+            _ => None,
+        }
+    }
+}
+
+/// What overload resolution found among the candidates.
+enum Resolution<'s> {
+    Best(&'s Signature),
+    /// None of them accepts the arguments.
+    NoneViable,
+    /// These accept them equally well, and better than the rest.
+    Ambiguous(Vec<&'s Signature>),
+    /// Not known for some argument.
+    Unknown,
+}
+
+/// One function a name may call.
+#[derive(Debug, Clone, PartialEq)]
+struct Signature {
+    /// A `Type::Function`.
+    ty: TypeId,
+    /// Parameters without a default argument.
+    required: usize,
+    /// A non-static member function's cv-qualifiers, which those of the
+    /// object it is called on must be among.
+    this: Option<Qualifiers>,
+}
+
+impl Signature {
+    fn of(f: &FunctionDecl) -> Self {
+        Self { ty: f.ty, required: f.params.iter().take_while(|p| p.default.is_none()).count(), this: None }
+    }
+
+    fn method(f: &FunctionDecl) -> Self {
+        let this = (f.specifiers.storage != StorageClass::Static).then_some(f.qualifiers.quals);
+        Self { this, ..Self::of(f) }
+    }
+
+    fn accepts(&self, args: usize) -> bool {
+        let Type::Function { params, variadic, .. } = self.ty.get() else { return true };
+        self.required <= args && (variadic || args <= params.len())
+    }
+}
+
+/// What an expression is, as far as its type goes.
+enum Operand {
+    Value(ExprType),
+    /// A name of functions, not yet called.
+    Functions(Symbol, Vec<Signature>),
+}
+
+#[derive(Debug, Default)]
+struct Class {
+    /// Data members, static or not.
+    fields: HashMap<Symbol, TypeId>,
+    /// Data members declared `mutable`.
+    mutables: HashSet<Symbol>,
+    methods: HashMap<Symbol, Vec<Signature>>,
+    /// Members whose type is not known, like member templates and nested
+    /// types.
+    others: HashSet<Symbol>,
+    /// Members declared `static`: data members, and functions with a
+    /// static overload.
+    statics: HashSet<Symbol>,
+    /// The static member functions, by name and type, for definitions
+    /// outside the class, which do not repeat `static`.
+    static_methods: HashSet<(Symbol, TypeId)>,
+    /// The result types of its `operator->` overloads, with the
+    /// qualifiers of their `this`.
+    arrows: Vec<(Qualifiers, TypeId)>,
+    /// Whether every member is declared in the class itself.
+    complete: bool,
+    /// Whether it is defined in another class or a function, whose names
+    /// its member functions see too.
+    nested: bool,
+}
+
+/// The enclosing function, for `return` and `this`.
+#[derive(Debug, Clone, Copy)]
+struct Function {
+    name: Symbol,
+    ret: TypeId,
+    /// The class of a member function, and the qualifiers of its `this`.
+    class: Option<(Symbol, Qualifiers)>,
+    /// A static member function has no `this`.
+    is_static: bool,
+    /// Whether a statement of its body was skipped, which may have
+    /// declared a local.
+    skipped: bool,
+    /// Coroutines return through `co_return`.
+    is_coroutine: bool,
+}
+
+/// The names declared so far and their types.
+#[derive(Debug)]
+pub struct TypeChecker {
+    /// Block scopes, innermost last, with `None` for a variable whose type
+    /// is not known.
+    scopes: Vec<HashMap<Symbol, Option<TypeId>>>,
+    /// Functions by unqualified name, and by namespace-qualified name for
+    /// those declared in a namespace.
+    functions: HashMap<Symbol, Vec<Signature>>,
+    classes: HashMap<Symbol, Class>,
+    /// The other names declared outside classes: types, enumerators and
+    /// concepts.
+    names: HashSet<Symbol>,
+    /// Whether declarations were skipped or imported, or names brought in
+    /// by `using`, so that a name found nowhere may still be declared.
+    unseen: bool,
+    /// Depth of templates around the current declaration, whose names may
+    /// be dependent.
+    templates: usize,
+    /// Depth of operands that are not evaluated, like `sizeof`'s.
+    unevaluated: usize,
+    namespaces: Vec<String>,
+    /// Depth of class definitions around the current declaration.
+    in_class: usize,
+    function: Option<Function>,
+    /// The functions `function` is nested in, as for a local class's
+    /// member functions.
+    enclosing: Vec<Option<Function>>,
+    /// The character sets literals are encoded in.
+    charsets: ExecCharsets,
+    /// The types `sizeof` and `alignof` were taken of, and where, for
+    /// the checker to measure.
+    sized: Vec<(TypeId, Span)>,
+    /// The function each call of a name resolved to, by the call's span:
+    /// its name and type.
+    calls: Vec<(Span, Symbol, TypeId)>,
+}
+
+impl Default for TypeChecker {
+    fn default() -> Self {
+        Self {
+            scopes: vec![HashMap::new()], functions: HashMap::new(), classes: HashMap::new(), names: HashSet::new(), unseen: false, templates: 0,
+            unevaluated: 0, namespaces: Vec::new(), in_class: 0, function: None, enclosing: Vec::new(), charsets: ExecCharsets::default(),
+            sized: Vec::new(), calls: Vec::new(),
+        }
+    }
+}
+
+impl TypeChecker {
+    /// A checker with literals encoded in `charsets`.
+    pub fn new(charsets: ExecCharsets) -> Self { Self { charsets, ..Self::default() } }
+
+    /// The types `sizeof` and `alignof` were taken of since this was last
+    /// called, with the spans of the expressions.
+    pub fn take_sized(&mut self) -> Vec<(TypeId, Span)> { std::mem::take(&mut self.sized) }
+
+    /// The functions calls resolved to so far, by the calls' spans, with
+    /// their names and types.
+    pub fn take_calls(&mut self) -> Vec<(Span, Symbol, TypeId)> { std::mem::take(&mut self.calls) }
+
+    pub fn enter_scope(&mut self) { self.scopes.push(HashMap::new()); }
+
+    pub fn exit_scope(&mut self) { if self.scopes.len() > 1 { self.scopes.pop(); } }
+
+    pub fn enter_namespace(&mut self, name: Option<Symbol>) { self.namespaces.push(name.map_or_else(String::new, |n| n.to_string())); }
+
+    pub fn exit_namespace(&mut self) { self.namespaces.pop(); }
+
+    pub fn enter_template(&mut self) { self.templates += 1; }
+
+    pub fn exit_template(&mut self) { self.templates -= 1; }
+
+    /// Notes that declarations were imported, skipped or brought in by
+    /// `using`, unseen.
+    pub fn declare_unseen(&mut self) { self.unseen = true; }
+
+    /// Notes that a statement of the current function's body was skipped.
+    pub fn skip_statement(&mut self) {
+        if let Some(f) = &mut self.function { f.skipped = true; }
+    }
+
+    /// Records a name declared as something other than a variable or a
+    /// function, like a type or a concept. A class's members are recorded
+    /// with the class.
+    pub fn declare_name(&mut self, name: Symbol) {
+        if self.outside_class() { self.names.insert(unqualified(name)); }
+    }
+
+    /// Records the names of `e` and of its enumerators, which an unscoped
+    /// enum declares around it.
+    pub fn declare_enum(&mut self, e: &EnumDecl) {
+        if let Some(name) = e.name { self.declare_name(name); }
+        if !e.is_scoped {
+            for enumerator in e.enumerators.iter().flatten() { self.declare_name(enumerator.name); }
+        }
+    }
+
+    /// Whether declarations here are in a namespace or a block, not
+    /// members of a class.
+    fn outside_class(&self) -> bool { self.in_class == 0 || self.function.is_some() }
+
+    /// Records a variable; `None` hides any outer one of the same name.
+    pub fn declare_variable(&mut self, name: Symbol, ty: Option<TypeId>) {
+        let scope = self.scopes.last_mut().expect("the global scope is never left");
+        scope.insert(unqualified(name), ty);
+    }
+
+    /// Checks `v`'s initializer, of type `init`, against its type and
+    /// declares it, deducing an `auto` type from `init`: the type, if
+    /// known.
+    pub fn declare_var(&mut self, v: &VarDecl, init: Option<ExprType>, bindings: &Bindings, diagnostics: &mut Diagnostics) -> Option<TypeId> {
+        let ty = match (strip_reference(v.ty).unqualified().get(), init) {
+            (Type::Auto, Some(init)) => Some(deduce(v.ty, init)),
+            (Type::Auto, None) => None,
+            _ => {
+                let single = match (&v.init, v.init_style) {
+                    (Some(e), InitStyle::Copy) if !matches!(e.kind, ExprKind::InitList(_)) => Some(e),
+                    (Some(Expr { kind: ExprKind::ParenList(items), .. }), InitStyle::Direct) if items.len() == 1 => Some(&items[0]),
+                    _ => None,
+                };
+                if let (Some(e), Some(init)) = (single, init) { self.convert(e, init, v.ty, bindings, diagnostics); }
+                Some(v.ty)
+            }
+        };
+        // A class's members are not in scope outside it.
+        if self.outside_class() { self.declare_variable(v.name, ty); }
+        ty
+    }
+
+    /// Records `f` among the functions if it is a free function that calls
+    /// can name, and its name as declared if it is declared in a block.
+    pub fn declare_function(&mut self, f: &FunctionDecl) {
+        if self.function.is_some() { self.names.insert(unqualified(f.name)); }
+        if self.in_class > 0 || f.kind != FunctionKind::Normal || f.name.as_str().contains("::") || f.name.as_str().starts_with("operator") { return; }
+        let signature = Signature::of(f);
+        let mut keys = vec![f.name];
+        let path: Vec<&str> = self.namespaces.iter().map(String::as_str).filter(|n| !n.is_empty()).collect();
+        if !path.is_empty() { keys.push(Symbol::intern(&format!("{}::{}", path.join("::"), f.name))); }
+        for key in keys {
+            let overloads = self.functions.entry(key).or_default();
+            if !overloads.contains(&signature) { overloads.push(signature.clone()); }
+        }
+    }
+
+    /// Records the members of `c`, before any of its member functions'
+    /// bodies are checked.
+    pub fn declare_class(&mut self, c: &ClassDecl) {
+        if let Some(name) = c.name { self.declare_name(name); }
+        let (Some(name), Some(members)) = (c.name, &c.members) else { return };
+        let mut class = Class { complete: c.bases.is_empty(), nested: self.in_class > 0 || self.function.is_some(), ..Class::default() };
+        for member in members {
+            match &member.decl {
+                Decl::Var(v) => {
+                    class.fields.insert(v.name, v.ty);
+                    if v.specifiers.is_mutable { class.mutables.insert(v.name); }
+                    if v.specifiers.storage == StorageClass::Static { class.statics.insert(v.name); }
+                }
+                Decl::Function(f) if f.specifiers.is_friend => {}
+                Decl::Function(f) if f.name.as_str().strip_prefix("operator").is_some_and(|op| op.trim_start() == "->") => {
+                    if let Type::Function { ret, .. } = f.ty.get() { class.arrows.push((f.qualifiers.quals, ret)); }
+                }
+                Decl::Function(f) if f.kind == FunctionKind::Normal && !f.name.as_str().starts_with("operator") => {
+                    class.methods.entry(f.name).or_default().push(Signature::method(f));
+                    if f.specifiers.storage == StorageClass::Static {
+                        class.statics.insert(f.name);
+                        class.static_methods.insert((f.name, f.ty));
+                    }
+                }
+                Decl::Function(_) => {}
+                Decl::Template(t) => { class.others.insert(t.decl.name()); }
+                Decl::Class(inner) if inner.name.is_none() => class.complete = false,
+                Decl::Enum(e) => {
+                    class.others.extend(e.name);
+                    if !e.is_scoped { class.others.extend(e.enumerators.iter().flatten().map(|e| e.name)); }
+                }
+                Decl::Using(_) | Decl::Unsupported(_) | Decl::Invalid(_) => class.complete = false,
+                decl => { class.others.insert(decl.name()); }
+            }
+        }
+        self.classes.insert(unqualified(name), class);
+    }
+
+    pub fn enter_class(&mut self) {
+        self.in_class += 1;
+        self.enter_scope();
+    }
+
+    pub fn exit_class(&mut self) {
+        self.exit_scope();
+        self.in_class -= 1;
+    }
+
+    /// Enters `f`, declaring its parameters. `class` is the class being
+    /// defined around it, if any; an out-of-line member function finds its
+    /// class by its qualified name.
+    pub fn enter_function(&mut self, f: &FunctionDecl, class: Option<Symbol>) {
+        let class = class.map(unqualified).or_else(|| {
+            let name = f.name.as_str();
+            let name = unqualified(Symbol::intern(name.rsplit_once("::")?.0));
+            self.classes.contains_key(&name).then_some(name)
+        });
+        let is_static = f.specifiers.storage == StorageClass::Static
+            || class.and_then(|c| self.classes.get(&c)).is_some_and(|c| c.static_methods.contains(&(unqualified(f.name), f.ty)));
+        let function = match f.ty.get() {
+            Type::Function { ret, .. } => Some(Function {
+                name: unqualified(f.name),
+                ret,
+                class: class.map(|c| (c, f.qualifiers.quals)),
+                is_static,
+                skipped: false,
+                is_coroutine: f.is_coroutine || f.kind != FunctionKind::Normal,
+            }),
+            _ => None,
+        };
+        self.enclosing.push(std::mem::replace(&mut self.function, function));
+        self.enter_scope();
+        for p in &f.params {
+            if let Some(name) = p.name { self.declare_variable(name, Some(p.ty)); }
+        }
+    }
+
+    pub fn exit_function(&mut self) {
+        self.exit_scope();
+        self.function = self.enclosing.pop().flatten();
+    }
+
+    /// Checks a `return` of `value`, of type `ty`, against the enclosing
+    /// function's return type.
+    pub fn check_return(&mut self, value: Option<&Expr>, ty: Option<ExprType>, span: Span, bindings: &Bindings, diagnostics: &mut Diagnostics) {
+        let Some(f) = self.function.filter(|f| !f.is_coroutine) else { return };
+        if f.ret.get() == Type::Auto { return; }
+        let returns_void = f.ret.unqualified().get() == Type::Void;
+        match (value, ty) {
+            (None, _) if !returns_void => diagnostics.emit(SemaError::MissingReturnValue(f.name).to_diagnostic(span)),
+            (Some(e), Some(ty)) if returns_void && ty.ty.unqualified().get() != Type::Void => {
+                diagnostics.emit(SemaError::UnexpectedReturnValue(f.name).to_diagnostic(e.span));
+            }
+            (Some(e), Some(ty)) if !returns_void => self.convert(e, ty, f.ret, bindings, diagnostics),
+            _ => {}
+        }
+    }
+
+    /// The type of `e`, reporting what makes it ill-typed.
+    pub fn type_of(&mut self, e: &Expr, bindings: &Bindings, diagnostics: &mut Diagnostics) -> Option<ExprType> {
+        match self.operand(e, bindings, diagnostics)? {
+            Operand::Value(ty) => Some(ty),
+            // A function named without being called: only one is meant.
+            Operand::Functions(_, overloads) => match overloads.as_slice() {
+                [only] => Some(ExprType::lvalue(only.ty)),
+                _ => None,
+            },
+        }
+    }
+
+    fn operand(&mut self, e: &Expr, bindings: &Bindings, diagnostics: &mut Diagnostics) -> Option<Operand> {
+        let ty = match &e.kind {
+            ExprKind::Name(name) => return self.lookup(name.symbol(), e.span, diagnostics),
+            ExprKind::TemplateId { name, .. } => return self.lookup(name.symbol(), e.span, diagnostics).filter(|o| matches!(o, Operand::Functions(..))),
+            ExprKind::Paren(inner) => return self.operand(inner, bindings, diagnostics),
+            ExprKind::Call { callee, args } => {
+                let callee = self.operand(callee, bindings, diagnostics);
+                let arg_types: Vec<Option<ExprType>> = args.iter().map(|a| self.type_of(a, bindings, diagnostics)).collect();
+                self.call(e, callee?, args, &arg_types, bindings, diagnostics)?
+            }
+            ExprKind::Member { base, member, arrow } => {
+                let base = self.type_of(base, bindings, diagnostics)?;
+                return self.member(e, base, *member, *arrow, bindings, diagnostics);
+            }
+            _ => self.value(e, bindings, diagnostics)?,
+        };
+        Some(Operand::Value(ty))
+    }
+
+    /// The type of an expression other than a name, call or member access.
+    fn value(&mut self, e: &Expr, bindings: &Bindings, diagnostics: &mut Diagnostics) -> Option<ExprType> {
+        let builtin = |ty: Type| TypeId::intern(ty);
+        match &e.kind {
+            ExprKind::IntLiteral(..) | ExprKind::FloatLiteral(_) | ExprKind::CharLiteral(_) | ExprKind::BoolLiteral(_) | ExprKind::Nullptr => {
+                literal_type(e, self.charsets).map(ExprType::prvalue)
+            }
+            ExprKind::StringLiteral(_) => literal_type(e, self.charsets).map(ExprType::lvalue),
+            ExprKind::Requires { .. } => Some(ExprType::prvalue(builtin(Type::Bool))),
+            ExprKind::This => {
+                let (class, quals) = self.function.filter(|f| !f.is_static)?.class?;
+                Some(ExprType::prvalue(builtin(Type::Pointer(builtin(Type::Named(class)).qualified(quals)))))
+            }
+            ExprKind::Unary { op, operand } => {
+                let operand_ty = self.type_of(operand, bindings, diagnostics)?;
+                self.unary(e, *op, operand, operand_ty, bindings, diagnostics)
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                let l = self.type_of(lhs, bindings, diagnostics);
+                let r = self.type_of(rhs, bindings, diagnostics);
+                self.binary(e, *op, lhs, rhs, l?, r?, bindings, diagnostics)
+            }
+            ExprKind::Conditional { cond, then, otherwise } => {
+                self.type_of(cond, bindings, diagnostics);
+                let then = self.type_of(then, bindings, diagnostics);
+                let otherwise = self.type_of(otherwise, bindings, diagnostics);
+                let (then, otherwise) = (then?, otherwise?);
+                if then == otherwise { return Some(then); }
+                let (t, o) = (scalar(then.ty, bindings), scalar(otherwise.ty, bindings));
+                match (t, o) {
+                    (Scalar::Arithmetic(a), Scalar::Arithmetic(b)) => Some(ExprType::prvalue(arithmetic_conversion(a, b))),
+                    (Scalar::Pointer(p), Scalar::Null) | (Scalar::Null, Scalar::Pointer(p)) => Some(ExprType::prvalue(p)),
+                    (Scalar::Pointer(p), Scalar::Pointer(q)) if p == q => Some(ExprType::prvalue(p)),
+                    _ => None,
+                }
+            }
+            ExprKind::Index { base, index } => {
+                let b = self.type_of(base, bindings, diagnostics);
+                let i = self.type_of(index, bindings, diagnostics);
+                let (b, i) = (b?, i?);
+                match (scalar(b.ty, bindings), scalar(i.ty, bindings)) {
+                    (Scalar::Pointer(p), Scalar::Arithmetic(_)) | (Scalar::Arithmetic(_), Scalar::Pointer(p)) => {
+                        let Type::Pointer(elem) = p.get() else { return None };
+                        Some(ExprType::lvalue(elem))
+                    }
+                    (Scalar::Arithmetic(_) | Scalar::Null, Scalar::Arithmetic(_) | Scalar::Null) => {
+                        diagnostics.emit(SemaError::NotSubscriptable(b.ty).to_diagnostic(base.span));
+                        None
+                    }
+                    _ => None,
+                }
+            }
+            ExprKind::Cast { ty, operand, .. } => {
+                self.type_of(operand, bindings, diagnostics);
+                Some(ExprType::of_declared(*ty))
+            }
+            ExprKind::Construct { ty, args, .. } => {
+                for arg in args { self.type_of(arg, bindings, diagnostics); }
+                Some(ExprType::prvalue(*ty))
+            }
+            ExprKind::SizeofExpr(operand) => {
+                self.unevaluated += 1;
+                // Any function named has no size, whichever is meant.
+                let ty = match self.operand(operand, bindings, diagnostics) {
+                    Some(Operand::Value(ty)) => Some(ty.ty),
+                    Some(Operand::Functions(_, overloads)) => overloads.first().map(|f| f.ty),
+                    None => None,
+                };
+                self.unevaluated -= 1;
+                if let Some(ty) = ty { self.sized.push((ty, e.span)); }
+                Some(ExprType::prvalue(builtin(Type::UnsignedLong)))
+            }
+            ExprKind::SizeofType(ty) | ExprKind::Alignof(ty) => {
+                self.sized.push((*ty, e.span));
+                Some(ExprType::prvalue(builtin(Type::UnsignedLong)))
+            }
+            ExprKind::InitList(items) | ExprKind::ParenList(items) => {
+                for item in items { self.type_of(item, bindings, diagnostics); }
+                None
+            }
+            ExprKind::Designated { init: inner, .. } | ExprKind::CoAwait(inner) | ExprKind::CoYield(inner) | ExprKind::UserLiteral { literal: inner, .. } => {
+                self.type_of(inner, bindings, diagnostics);
+                None
+            }
+            ExprKind::Embed(_) | ExprKind::Name(_) | ExprKind::TemplateId { .. } | ExprKind::Paren(_) | ExprKind::Call { .. } | ExprKind::Member { .. } => None,
+        }
+    }
+
+    fn lookup(&self, name: Symbol, span: Span, diagnostics: &mut Diagnostics) -> Option<Operand> {
+        let text = name.as_str();
+        if let Some((qualifier, last)) = text.rsplit_once("::") {
+            let last = Symbol::intern(last);
+            if let Some(class) = self.classes.get(&unqualified(Symbol::intern(qualifier))) {
+                if let Some(&ty) = class.fields.get(&last) { return Some(Operand::Value(ExprType::lvalue(strip_reference(ty)))); }
+                return class.methods.get(&last).map(|m| Operand::Functions(last, m.clone()));
+            }
+            let key = Symbol::intern(text.trim_start_matches("::"));
+            return self.functions.get(&key).map(|f| Operand::Functions(last, f.clone()));
+        }
+        let variable = |scope: &HashMap<Symbol, Option<TypeId>>| scope.get(&name).map(|ty| ty.map(|ty| Operand::Value(ExprType::lvalue(strip_reference(ty)))));
+        let (global, blocks) = self.scopes.split_first().expect("the global scope is never left");
+        if let Some(found) = blocks.iter().rev().find_map(variable) { return found; }
+        let Some(Function { class: Some((class, quals)), is_static, skipped, .. }) = self.function else {
+            if let Some(found) = variable(global) { return found; }
+            return self.functions.get(&name).map(|f| Operand::Functions(name, f.clone()));
+        };
+        let class = self.classes.get(&class)?;
+        let is_member = class.fields.contains_key(&name) || class.methods.contains_key(&name);
+        if is_member && is_static && !class.statics.contains(&name) && self.unevaluated == 0 {
+            diagnostics.emit(SemaError::NonStaticMember(name).to_diagnostic(span));
+            return None;
+        }
+        if let Some(&ty) = class.fields.get(&name) { return Some(Operand::Value(ExprType::lvalue(member_type(ty, quals, class.mutables.contains(&name))))); }
+        if let Some(methods) = class.methods.get(&name) { return on_object(name, methods, quals, span, diagnostics); }
+        if class.others.contains(&name) || !class.complete { return None; }
+        if let Some(found) = variable(global) { return found; }
+        if let Some(f) = self.functions.get(&name) { return Some(Operand::Functions(name, f.clone())); }
+        // Names reserved to the implementation may be built-ins.
+        let known = self.unseen || skipped || self.templates > 0 || class.nested || self.names.contains(&name) || text.starts_with("__");
+        if !known { diagnostics.emit(SemaError::Undeclared(name).to_diagnostic(span)); }
+        None
+    }
+
+    fn call(&mut self, e: &Expr, callee: Operand, args: &[Expr], arg_types: &[Option<ExprType>], bindings: &Bindings, diagnostics: &mut Diagnostics) -> Option<ExprType> {
+        let signature = match callee {
+            Operand::Functions(name, overloads) => {
+                let viable: Vec<&Signature> = overloads.iter().filter(|s| s.accepts(args.len())).collect();
+                let signature = match (viable.as_slice(), overloads.as_slice()) {
+                    ([only], _) => (*only).clone(),
+                    ([], [only]) => {
+                        let Type::Function { params, variadic, .. } = only.ty.get() else { return None };
+                        let max = (!variadic).then_some(params.len());
+                        diagnostics.emit(SemaError::ArgumentCount { min: only.required, max, found: args.len() }.to_diagnostic(e.span));
+                        return None;
+                    }
+                    ([], _) => {
+                        diagnostics.emit(SemaError::NoOverload { name, found: args.len() }.to_diagnostic(e.span));
+                        return None;
+                    }
+                    (viable, _) => match resolve_overload(viable, args, arg_types, bindings) {
+                        Resolution::Best(best) => best.clone(),
+                        Resolution::NoneViable => {
+                            let args = arg_types.iter().flatten().map(|t| t.ty).collect();
+                            diagnostics.emit(SemaError::NoMatchingOverload { name, args }.to_diagnostic(e.span));
+                            return None;
+                        }
+                        Resolution::Ambiguous(candidates) => {
+                            let candidates = candidates.iter().map(|c| c.ty).collect();
+                            diagnostics.emit(SemaError::AmbiguousCall { name, candidates }.to_diagnostic(e.span));
+                            return None;
+                        }
+                        // Overloads with the same result leave it known.
+                        Resolution::Unknown => {
+                            let results: HashSet<TypeId> = viable.iter().filter_map(|s| match s.ty.get() { Type::Function { ret, .. } => Some(ret), _ => None }).collect();
+                            let [ret] = results.into_iter().collect::<Vec<_>>()[..] else { return None };
+                            return Some(ExprType::of_declared(ret));
+                        }
+                    },
+                };
+                self.calls.push((e.span, name, signature.ty));
+                signature
+            }
+            Operand::Value(callee) => {
+                let resolved = bindings.resolve(callee.ty).unqualified();
+                let function = match resolved.get() {
+                    Type::Function { .. } => resolved,
+                    Type::Pointer(f) if matches!(f.get(), Type::Function { .. }) => f,
+                    _ if matches!(scalar(callee.ty, bindings), Scalar::Other) => return None,
+                    _ => {
+                        diagnostics.emit(SemaError::NotCallable(callee.ty).to_diagnostic(e.span));
+                        return None;
+                    }
+                };
+                let Type::Function { params, .. } = function.get() else { return None };
+                Signature { ty: function, required: params.len(), this: None }
+            }
+        };
+        let Type::Function { ret, params, variadic } = signature.ty.get() else { return None };
+        if !signature.accepts(args.len()) {
+            let max = (!variadic).then_some(params.len());
+            diagnostics.emit(SemaError::ArgumentCount { min: signature.required, max, found: args.len() }.to_diagnostic(e.span));
+        }
+        for ((arg, ty), param) in args.iter().zip(arg_types).zip(params) {
+            if let Some(ty) = ty { self.convert(arg, *ty, param, bindings, diagnostics); }
+        }
+        Some(ExprType::of_declared(ret))
+    }
+
+    fn member(&mut self, e: &Expr, base: ExprType, member: Symbol, arrow: bool, bindings: &Bindings, diagnostics: &mut Diagnostics) -> Option<Operand> {
+        let object = match (scalar(base.ty, bindings), arrow) {
+            (Scalar::Pointer(p), true) => match p.get() { Type::Pointer(pointee) => pointee, _ => return None },
+            (Scalar::Pointer(_), false) => {
+                diagnostics.emit(SemaError::MemberOfPointer(base.ty).to_diagnostic(e.span));
+                return None;
+            }
+            (Scalar::Other, false) => base.ty,
+            // `->` on a class object calls its `operator->`, and so on
+            // while that returns a class object.
+            (Scalar::Other, true) => return self.member(e, self.arrow(base.ty, bindings)?, member, true, bindings, diagnostics),
+            (Scalar::Arithmetic(_) | Scalar::Null, _) => {
+                diagnostics.emit(SemaError::MemberOfNonClass(base.ty).to_diagnostic(e.span));
+                return None;
+            }
+        };
+        let object = bindings.resolve(object);
+        let Type::Named(name) = object.unqualified().get() else { return None };
+        let class = self.classes.get(&unqualified(name))?;
+        if let Some(&ty) = class.fields.get(&member) {
+            // A reference member names an lvalue, whatever the object is.
+            let is_reference = matches!(ty.get(), Type::Reference(_) | Type::RvalueReference(_));
+            let category = if arrow || is_reference { Category::Lvalue } else { base.category };
+            return Some(Operand::Value(ExprType { ty: member_type(ty, object.qualifiers(), class.mutables.contains(&member)), category }));
+        }
+        if let Some(methods) = class.methods.get(&member) { return on_object(member, methods, object.qualifiers(), e.span, diagnostics); }
+        if class.complete && !class.others.contains(&member) {
+            diagnostics.emit(SemaError::NoMember { ty: object.unqualified(), member }.to_diagnostic(e.span));
+        }
+        None
+    }
+
+    /// The result of applying `operator->` to an object of class type `ty`
+    /// until it is not a class object, which `->` takes a member from.
+    /// `None` if a class on the way has no `operator->` known here, or the
+    /// classes go round in a circle.
+    fn arrow(&self, ty: TypeId, bindings: &Bindings) -> Option<ExprType> {
+        let mut seen = HashSet::new();
+        let mut object = bindings.resolve(ty);
+        loop {
+            let Type::Named(name) = object.unqualified().get() else { return None };
+            let name = unqualified(name);
+            if !seen.insert(name) { return None; }
+            let class = self.classes.get(&name)?;
+            // A non-const object prefers the overload without `const`.
+            let viable = class.arrows.iter().filter(|(quals, _)| quals.contains(object.qualifiers()));
+            let &(_, ret) = viable.min_by_key(|(quals, _)| *quals != object.qualifiers())?;
+            let result = ExprType::of_declared(ret);
+            if scalar(result.ty, bindings) != Scalar::Other { return Some(result); }
+            object = bindings.resolve(result.ty);
+        }
+    }
+
+    fn unary(&mut self, e: &Expr, op: UnaryOp, operand: &Expr, ty: ExprType, bindings: &Bindings, diagnostics: &mut Diagnostics) -> Option<ExprType> {
+        let kind = scalar(ty.ty, bindings);
+        // Class operands call operator functions.
+        if kind == Scalar::Other { return None; }
+        let invalid = |diagnostics: &mut Diagnostics| {
+            diagnostics.emit(SemaError::InvalidOperand { op: unary_spelling(op), ty: ty.ty }.to_diagnostic(e.span));
+            None
+        };
+        match op {
+            UnaryOp::Plus | UnaryOp::Minus | UnaryOp::BitNot => match kind {
+                Scalar::Arithmetic(a) if op != UnaryOp::BitNot || is_integral(a) => Some(ExprType::prvalue(promote(a))),
+                Scalar::Pointer(p) if op == UnaryOp::Plus => Some(ExprType::prvalue(p)),
+                _ => invalid(diagnostics),
+            },
+            UnaryOp::Not => Some(ExprType::prvalue(TypeId::intern(Type::Bool))),
+            UnaryOp::Deref => match kind {
+                Scalar::Pointer(p) => match p.get() { Type::Pointer(pointee) => Some(ExprType::lvalue(pointee)), _ => None },
+                _ => {
+                    diagnostics.emit(SemaError::NotPointer(ty.ty).to_diagnostic(e.span));
+                    None
+                }
+            },
+            UnaryOp::AddressOf if ty.category == Category::Prvalue => {
+                diagnostics.emit(SemaError::AddressOfRvalue(ty.ty).to_diagnostic(e.span));
+                None
+            }
+            UnaryOp::AddressOf => Some(ExprType::prvalue(TypeId::intern(Type::Pointer(ty.ty)))),
+            UnaryOp::PreIncrement | UnaryOp::PreDecrement | UnaryOp::PostIncrement | UnaryOp::PostDecrement => {
+                if !matches!(kind, Scalar::Arithmetic(_) | Scalar::Pointer(_)) { return invalid(diagnostics); }
+                if !self.assignable(operand, ty, diagnostics) { return None; }
+                match op {
+                    UnaryOp::PreIncrement | UnaryOp::PreDecrement => Some(ty),
+                    _ => Some(ExprType::prvalue(ty.ty.unqualified())),
+                }
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn binary(&mut self, e: &Expr, op: BinaryOp, lhs: &Expr, rhs: &Expr, l: ExprType, r: ExprType, bindings: &Bindings, diagnostics: &mut Diagnostics) -> Option<ExprType> {
+        let bool_ty = TypeId::intern(Type::Bool);
+        match op {
+            BinaryOp::Comma => return Some(r),
+            BinaryOp::LogicalAnd | BinaryOp::LogicalOr => return Some(ExprType::prvalue(bool_ty)),
+            BinaryOp::PtrMem | BinaryOp::PtrMemArrow | BinaryOp::ThreeWay => return None,
+            _ => {}
+        }
+        let (lk, rk) = (scalar(l.ty, bindings), scalar(r.ty, bindings));
+        if lk == Scalar::Other || rk == Scalar::Other { return None; }
+        let invalid = |diagnostics: &mut Diagnostics| {
+            diagnostics.emit(SemaError::InvalidOperands { op: op.spelling(), lhs: l.ty, rhs: r.ty }.to_diagnostic(e.span));
+            None
+        };
+        let plain = match op {
+            BinaryOp::Assign => {
+                if !self.assignable(lhs, l, diagnostics) { return None; }
+                self.convert(rhs, r, l.ty, bindings, diagnostics);
+                return Some(l);
+            }
+            BinaryOp::Lt | BinaryOp::Gt | BinaryOp::Le | BinaryOp::Ge | BinaryOp::Eq | BinaryOp::Ne => {
+                let null = |e: &Expr, k: Scalar| k == Scalar::Null || is_null_constant(e);
+                if let (Scalar::Arithmetic(a), Scalar::Arithmetic(b)) = (lk, rk) {
+                    if let Some(warning) = sign_compare(e, lhs, rhs, a, b, diagnostics) { diagnostics.emit(warning); }
+                }
+                return match (lk, rk) {
+                    (Scalar::Arithmetic(_), Scalar::Arithmetic(_)) | (Scalar::Pointer(_), Scalar::Pointer(_)) | (Scalar::Null, Scalar::Null) => Some(ExprType::prvalue(bool_ty)),
+                    (Scalar::Pointer(_), _) if null(rhs, rk) => Some(ExprType::prvalue(bool_ty)),
+                    (_, Scalar::Pointer(_)) if null(lhs, lk) => Some(ExprType::prvalue(bool_ty)),
+                    _ => invalid(diagnostics),
+                };
+            }
+            _ => compound_operator(op),
+        };
+        let integral = matches!(plain, BinaryOp::Rem | BinaryOp::Shl | BinaryOp::Shr | BinaryOp::BitAnd | BinaryOp::BitXor | BinaryOp::BitOr);
+        let result = match (lk, rk) {
+            (Scalar::Arithmetic(a), Scalar::Arithmetic(b)) if !integral || (is_integral(a) && is_integral(b)) => {
+                if matches!(plain, BinaryOp::Shl | BinaryOp::Shr) { promote(a) } else { arithmetic_conversion(a, b) }
+            }
+            (Scalar::Pointer(p), Scalar::Arithmetic(a)) if matches!(plain, BinaryOp::Add | BinaryOp::Sub) && is_integral(a) => p,
+            // `p += n` and `p -= n`, but not `n += p`.
+            (Scalar::Arithmetic(a), Scalar::Pointer(p)) if op == BinaryOp::Add && is_integral(a) => p,
+            (Scalar::Pointer(p), Scalar::Pointer(q)) if op == BinaryOp::Sub && pointee(p).unqualified() == pointee(q).unqualified() => TypeId::intern(Type::Long),
+            _ => return invalid(diagnostics),
+        };
+        if plain == op { return Some(ExprType::prvalue(result)); }
+        if !self.assignable(lhs, l, diagnostics) { return None; }
+        Some(l)
+    }
+
+    /// Whether `e`, of type `ty`, can be assigned to, reporting why not.
+    fn assignable(&self, e: &Expr, ty: ExprType, diagnostics: &mut Diagnostics) -> bool {
+        if ty.category != Category::Lvalue || matches!(ty.ty.unqualified().get(), Type::Array(..) | Type::Function { .. }) {
+            diagnostics.emit(SemaError::NotAssignable.to_diagnostic(e.span));
+            return false;
+        }
+        if ty.ty.is_const() {
+            diagnostics.emit(SemaError::AssignToConst(ty.ty).to_diagnostic(e.span));
+            return false;
+        }
+        true
+    }
+
+    /// Checks that `e`, of type `from`, converts implicitly to `to`.
+    fn convert(&self, e: &Expr, from: ExprType, to: TypeId, bindings: &Bindings, diagnostics: &mut Diagnostics) {
+        let error = match to.get() {
+            Type::Reference(target) | Type::RvalueReference(target) => {
+                let lvalue_ref = matches!(to.get(), Type::Reference(_));
+                let target = bindings.resolve(target);
+                let (t, f) = (scalar(target, bindings), scalar(from.ty, bindings));
+                if t == Scalar::Other || f == Scalar::Other { return; }
+                let same = bindings.resolve(from.ty).unqualified() == target.unqualified();
+                let const_ref = target.qualifiers() == Qualifiers::CONST;
+                match from.category {
+                    Category::Lvalue if !lvalue_ref && same => Some(SemaError::BindToLvalue { ty: target, from: from.ty }),
+                    Category::Lvalue if lvalue_ref && same => {
+                        if target.qualifiers().contains(from.ty.qualifiers()) { return; }
+                        Some(SemaError::InvalidConversion { from: from.ty, to })
+                    }
+                    _ if lvalue_ref && !const_ref && same => Some(SemaError::BindToTemporary { ty: target, from: from.ty }),
+                    // A temporary of the target type is made from the value.
+                    _ if !lvalue_ref || const_ref => return self.convert(e, ExprType::prvalue(from.ty), target.unqualified(), bindings, diagnostics),
+                    _ => Some(SemaError::InvalidConversion { from: from.ty, to }),
+                }
+            }
+            _ => match implicit(e, from, to, bindings) {
+                Implicit::Invalid => Some(SemaError::InvalidConversion { from: from.ty, to }),
+                Implicit::Standard(_) if diagnostics.warnings().is_enabled(Warning::Conversion) => {
+                    if let (Scalar::Arithmetic(a), Scalar::Arithmetic(b)) = (scalar(from.ty, bindings), scalar(to, bindings)) {
+                        if let Some(warning) = narrowing(e, a, b) { diagnostics.emit(warning); }
+                    }
+                    None
+                }
+                _ => None,
+            },
+        };
+        if let Some(error) = error { diagnostics.emit(error.to_diagnostic(e.span)); }
+    }
+}
+
+/// How an operand takes part in built-in operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scalar {
+    /// Of its type, unqualified.
+    Arithmetic(TypeId),
+    /// Of the pointer type it is or decays to, unqualified.
+    Pointer(TypeId),
+    /// `std::nullptr_t`.
+    Null,
+    /// A class, an enum, `void`, or a type sema does not know.
+    Other,
+}
+
+fn scalar(ty: TypeId, bindings: &Bindings) -> Scalar {
+    let ty = bindings.resolve(ty).unqualified();
+    match ty.get() {
+        Type::Bool | Type::Char | Type::SignedChar | Type::UnsignedChar | Type::Short | Type::UnsignedShort | Type::Int | Type::UnsignedInt
+        | Type::Long | Type::UnsignedLong | Type::LongLong | Type::UnsignedLongLong | Type::WChar | Type::Char8 | Type::Char16 | Type::Char32
+        | Type::Float | Type::Double | Type::LongDouble => Scalar::Arithmetic(ty),
+        Type::Pointer(_) => Scalar::Pointer(ty),
+        Type::Array(elem, _) => Scalar::Pointer(TypeId::intern(Type::Pointer(elem))),
+        Type::Function { .. } => Scalar::Pointer(TypeId::intern(Type::Pointer(ty))),
+        _ if ty == nullptr_t() => Scalar::Null,
+        _ => Scalar::Other,
+    }
+}
+
+/// The implicit conversion of `e`, of type `from`, to `to`, which is not
+/// a reference.
+fn implicit(e: &Expr, from: ExprType, to: TypeId, bindings: &Bindings) -> Implicit {
+    let (source, target) = (bindings.resolve(from.ty).unqualified(), bindings.resolve(to));
+    if source.get() == Type::Void {
+        return if target.unqualified().get() == Type::Void { Implicit::Standard(Vec::new()) } else { Implicit::Invalid };
+    }
+    let (f, t) = (scalar(source, bindings), scalar(target, bindings));
+    if f == Scalar::Other || t == Scalar::Other { return Implicit::Unknown; }
+    let mut steps = match source.get() {
+        Type::Array(..) => vec![Conversion::ArrayToPointer],
+        Type::Function { .. } => vec![Conversion::FunctionToPointer],
+        _ if from.category != Category::Prvalue => vec![Conversion::LvalueToRvalue],
+        _ => Vec::new(),
+    };
+    let step = match (f, t) {
+        (Scalar::Arithmetic(a), Scalar::Arithmetic(b)) => arithmetic_step(a, b),
+        (Scalar::Pointer(_) | Scalar::Null, Scalar::Arithmetic(b)) if b.get() == Type::Bool => Some(Conversion::BooleanConversion),
+        (Scalar::Null, Scalar::Pointer(_)) => Some(Conversion::NullPointer),
+        (Scalar::Arithmetic(_), Scalar::Pointer(_)) if is_null_constant(e) => Some(Conversion::NullPointer),
+        (Scalar::Null, Scalar::Null) => None,
+        (Scalar::Pointer(p), Scalar::Pointer(q)) => match pointer_step(p, q, bindings) {
+            Ok(step) => step,
+            Err(other) => return other,
+        },
+        _ => return Implicit::Invalid,
+    };
+    steps.extend(step);
+    Implicit::Standard(steps)
+}
+
+/// The conversion between two unqualified arithmetic types, if they
+/// differ.
+fn arithmetic_step(from: TypeId, to: TypeId) -> Option<Conversion> {
+    if from == to { return None; }
+    Some(match (is_integral(from), is_integral(to)) {
+        _ if to.get() == Type::Bool => Conversion::BooleanConversion,
+        (true, true) if promote(from) == to => Conversion::IntegralPromotion,
+        (true, true) => Conversion::IntegralConversion,
+        (false, false) if from.get() == Type::Float && to.get() == Type::Double => Conversion::FloatingPromotion,
+        (false, false) => Conversion::FloatingConversion,
+        _ => Conversion::FloatingIntegral,
+    })
+}
+
+/// The conversion between two unqualified pointer types, if they differ,
+/// or why there is none.
+fn pointer_step(from: TypeId, to: TypeId, bindings: &Bindings) -> Result<Option<Conversion>, Implicit> {
+    if from == to { return Ok(None); }
+    if from.converts_by_qualification(to) { return Ok(Some(Conversion::Qualification)); }
+    let (fp, tp) = (bindings.resolve(pointee(from)), bindings.resolve(pointee(to)));
+    let step = if tp.unqualified().get() == Type::Void {
+        Conversion::PointerConversion
+    } else if fp.unqualified() == tp.unqualified() {
+        Conversion::Qualification
+    } else {
+        // A pointer to a class may convert to a pointer to a base, and
+        // what other types sema does not know is not known.
+        let known = |ty: TypeId| scalar(ty, bindings) != Scalar::Other || ty.unqualified().get() == Type::Void;
+        return Err(if known(fp) && known(tp) { Implicit::Invalid } else { Implicit::Unknown });
+    };
+    if tp.qualifiers().contains(fp.qualifiers()) { Ok(Some(step)) } else { Err(Implicit::Invalid) }
+}
+
+/// How `e`, of type `from`, initializes a parameter of type `param`, which
+/// may be a reference.
+fn initialization(e: &Expr, from: ExprType, param: TypeId, bindings: &Bindings) -> Implicit {
+    let (Type::Reference(target) | Type::RvalueReference(target)) = param.get() else { return implicit(e, from, param, bindings) };
+    let lvalue_ref = matches!(param.get(), Type::Reference(_));
+    let target = bindings.resolve(target);
+    if scalar(target, bindings) == Scalar::Other || scalar(from.ty, bindings) == Scalar::Other { return Implicit::Unknown; }
+    let same = bindings.resolve(from.ty).unqualified() == target.unqualified();
+    let const_ref = target.qualifiers() == Qualifiers::CONST;
+    match from.category {
+        Category::Lvalue if same && lvalue_ref && target.qualifiers().contains(from.ty.qualifiers()) => Implicit::Standard(Vec::new()),
+        Category::Lvalue if same => Implicit::Invalid,
+        _ if lvalue_ref && !const_ref => Implicit::Invalid,
+        _ if same => Implicit::Standard(Vec::new()),
+        // A temporary of the target type is made from the value.
+        _ => implicit(e, ExprType::prvalue(from.ty), target.unqualified(), bindings),
+    }
+}
+
+/// The candidate whose parameters the arguments convert to best: one that
+/// is at least as good for every argument as each other candidate, and
+/// better for one. Without one, the call is ambiguous between those no
+/// other candidate is better than.
+fn resolve_overload<'s>(candidates: &[&'s Signature], args: &[Expr], arg_types: &[Option<ExprType>], bindings: &Bindings) -> Resolution<'s> {
+    let mut ranked: Vec<(&Signature, Vec<Rank>)> = Vec::new();
+    for &candidate in candidates {
+        let Type::Function { params, .. } = candidate.ty.get() else { return Resolution::Unknown };
+        let mut ranks = Vec::new();
+        for (i, (arg, ty)) in args.iter().zip(arg_types).enumerate() {
+            let Some(ty) = ty else { return Resolution::Unknown };
+            let rank = match params.get(i) {
+                None => Some(Rank::Ellipsis),
+                Some(&param) => match initialization(arg, *ty, param, bindings) {
+                    Implicit::Unknown => return Resolution::Unknown,
+                    implicit => implicit.rank(),
+                },
+            };
+            let Some(rank) = rank else { break };
+            ranks.push(rank);
+        }
+        if ranks.len() == args.len() { ranked.push((candidate, ranks)); }
+    }
+    let better = |a: &[Rank], b: &[Rank]| a.iter().zip(b).all(|(x, y)| x <= y) && a != b;
+    match ranked.iter().find(|(s, ranks)| ranked.iter().all(|(o, other)| std::ptr::eq(*s, *o) || better(ranks, other))) {
+        Some((best, _)) => Resolution::Best(best),
+        None if ranked.is_empty() => Resolution::NoneViable,
+        None => Resolution::Ambiguous(ranked.iter().filter(|(_, ranks)| !ranked.iter().any(|(_, other)| better(other, ranks))).map(|(s, _)| *s).collect()),
+    }
+}
+
+/// A warning for converting `e` from `from` to `to`, both arithmetic and
+/// unqualified, if that may change its value: to an integral type too
+/// small for it, from a floating type to an integral one, to a narrower
+/// floating type, or from an integral type with more bits than a
+/// floating type's mantissa. Constants that convert exactly are fine.
+fn narrowing(e: &Expr, from: TypeId, to: TypeId) -> Option<Diagnostic> {
+    if from == to || to.get() == Type::Bool { return None; }
+    let changes = |value: String, converted: String| format!("implicit conversion from `{}` to `{}` changes the value from {} to {}", from, to, value, converted);
+    let message = match (is_integral(from), is_integral(to)) {
+        (true, true) => match constant::evaluate(e, &|_| None) {
+            Ok(value) => {
+                let bits = width(to);
+                if -(1i128 << (bits - 1)) <= value && value < (1i128 << bits) { return None; }
+                changes(value.to_string(), wrap(value, to).to_string())
+            }
+            Err(_) if width(to) < width(from) => may_change(from, to),
+            Err(_) => return None,
+        },
+        (true, false) => {
+            let limit = 1i128 << mantissa(to);
+            match constant::evaluate(e, &|_| None) {
+                Ok(value) if value.abs() <= limit => return None,
+                Err(_) if value_bits(from) <= mantissa(to) => return None,
+                _ => may_change(from, to),
+            }
+        }
+        (false, true) => match float_constant(e) {
+            Some(value) if value.fract() == 0.0 && to.integer_range().is_some_and(|(min, max)| min as f64 <= value && value <= max as f64) => return None,
+            Some(value) if to.integer_range().is_some_and(|(min, max)| min as f64 <= value.trunc() && value.trunc() <= max as f64) => changes(value.to_string(), value.trunc().to_string()),
+            _ => may_change(from, to),
+        },
+        (false, false) => {
+            if mantissa(to) >= mantissa(from) { return None; }
+            match float_constant(e) {
+                Some(value) if to.get() == Type::Float && (value as f32) as f64 == value => return None,
+                _ => may_change(from, to),
+            }
+        }
+    };
+    Some(Diagnostic::warning(message).with_warning(Warning::Conversion).with_label(Label::primary(e.span, "")).with_help(format!("cast to `{}` explicitly if this is intended", to)))
+}
+
+/// A warning for comparing `lhs`, of type `a`, with `rhs`, of type `b`, if
+/// a signed operand is converted to an unsigned type, which changes a
+/// negative value; not for a constant that is not negative.
+fn sign_compare(e: &Expr, lhs: &Expr, rhs: &Expr, a: TypeId, b: TypeId, diagnostics: &Diagnostics) -> Option<Diagnostic> {
+    if !diagnostics.warnings().is_enabled(Warning::SignCompare) || !is_integral(a) || !is_integral(b) { return None; }
+    let signed = |ty: TypeId| ty.integer_range().is_some_and(|(min, _)| min < 0);
+    if signed(a) == signed(b) || signed(arithmetic_conversion(a, b)) { return None; }
+    let operand = if signed(a) { lhs } else { rhs };
+    if constant::evaluate(operand, &|_| None).is_ok_and(|value| value >= 0) { return None; }
+    Some(Diagnostic::warning(format!("comparison of integer expressions of different signedness: `{}` and `{}`", a, b))
+        .with_warning(Warning::SignCompare)
+        .with_label(Label::primary(e.span, ""))
+        .with_help(format!("cast the {} operand if this is intended", if signed(a) { "left" } else { "right" })))
+}
+
+fn may_change(from: TypeId, to: TypeId) -> String { format!("implicit conversion from `{}` to `{}` may change the value", from, to) }
+
+/// The bits of an integral type.
+fn width(ty: TypeId) -> u32 {
+    let (min, max) = ty.integer_range().unwrap_or((0, 1));
+    (max - min + 1).ilog2()
+}
+
+/// The bits of an integral type's magnitude: its width less any sign bit.
+fn value_bits(ty: TypeId) -> u32 { width(ty) - ty.integer_range().is_some_and(|(min, _)| min < 0) as u32 }
+
+/// The bits of a floating type's mantissa, with the implicit one; an
+/// integral type's as many as its width.
+fn mantissa(ty: TypeId) -> u32 {
+    match ty.get() {
+        Type::Float => 24,
+        Type::Double => 53,
+        Type::LongDouble => 64,
+        _ => width(ty),
+    }
+}
+
+/// `value` converted to the integral type `ty`, modulo its width.
+fn wrap(value: i128, ty: TypeId) -> i128 {
+    let bits = width(ty);
+    let wrapped = value.rem_euclid(1i128 << bits);
+    let signed = ty.integer_range().is_some_and(|(min, _)| min < 0);
+    if signed && wrapped >= 1i128 << (bits - 1) { wrapped - (1i128 << bits) } else { wrapped }
+}
+
+/// The value of a floating literal, possibly negated.
+fn float_constant(e: &Expr) -> Option<f64> {
+    match &e.kind {
+        ExprKind::FloatLiteral(v) => Some(*v),
+        ExprKind::Paren(inner) => float_constant(inner),
+        ExprKind::Unary { op: UnaryOp::Minus, operand } => float_constant(operand).map(|v| -v),
+        ExprKind::Unary { op: UnaryOp::Plus, operand } => float_constant(operand),
+        _ => None,
+    }
+}
+
+/// `0`, the integer literal that converts to any pointer.
+fn is_null_constant(e: &Expr) -> bool {
+    match &e.kind {
+        ExprKind::IntLiteral(0, _) => true,
+        ExprKind::Paren(inner) => is_null_constant(inner),
+        _ => false,
+    }
+}
+
+/// The type of a literal, with the code units of character and string
+/// literals in `charsets`; `None` for other expressions. A string literal
+/// is an lvalue array of its `const` character type, as long as its code
+/// units and the terminator, the others prvalues.
+pub(crate) fn literal_type(e: &Expr, charsets: ExecCharsets) -> Option<TypeId> {
+    let builtin = |ty: Type| TypeId::intern(ty);
+    let char_type = |encoding| match encoding {
+        Encoding::Ordinary => Type::Char,
+        Encoding::Wide => Type::WChar,
+        Encoding::Utf8 if charsets.char8 => Type::Char8,
+        Encoding::Utf8 => Type::Char,
+        Encoding::Utf16 => Type::Char16,
+        Encoding::Utf32 => Type::Char32,
+    };
+    let units = |l: &Literal| l.units(charsets.charset(l.encoding)).map_or(l.chars.len(), |u| u.len());
+    Some(match &e.kind {
+        ExprKind::IntLiteral(n, suffix) => builtin(suffix.ty(*n)),
+        ExprKind::FloatLiteral(_) => builtin(Type::Double),
+        // An ordinary literal of several code units is an `int`.
+        ExprKind::CharLiteral(c) if c.encoding == Encoding::Ordinary && units(c) > 1 => builtin(Type::Int),
+        ExprKind::CharLiteral(c) => builtin(char_type(c.encoding)),
+        ExprKind::BoolLiteral(_) => builtin(Type::Bool),
+        ExprKind::StringLiteral(s) => builtin(Type::Array(builtin(char_type(s.encoding)).qualified(Qualifiers::CONST), Some(units(s) as u64 + 1))),
+        ExprKind::Nullptr => nullptr_t(),
+        _ => return None,
+    })
+}
+
+fn nullptr_t() -> TypeId { TypeId::intern(Type::Named(Symbol::intern("std::nullptr_t"))) }
+
+fn pointee(pointer: TypeId) -> TypeId {
+    match pointer.get() {
+        Type::Pointer(p) => p,
+        _ => pointer,
+    }
+}
+
+fn is_integral(ty: TypeId) -> bool { !matches!(ty.get(), Type::Float | Type::Double | Type::LongDouble) }
+
+/// The integral promotion of an arithmetic type. A character type other
+/// than `char` promotes to the first of `int` and `unsigned int` that
+/// holds all its values.
+pub(crate) fn promote(ty: TypeId) -> TypeId {
+    match ty.get() {
+        Type::Bool | Type::Char | Type::SignedChar | Type::UnsignedChar | Type::Short | Type::UnsignedShort => TypeId::intern(Type::Int),
+        Type::WChar | Type::Char8 | Type::Char16 => TypeId::intern(Type::Int),
+        Type::Char32 => TypeId::intern(Type::UnsignedInt),
+        _ => ty,
+    }
+}
+
+/// The usual arithmetic conversions: the common type of two arithmetic
+/// operands, after promotion. Between a signed and an unsigned type, the
+/// unsigned one wins unless the signed one has a higher rank and holds
+/// all its values; if it has a higher rank but does not, as `long long`
+/// and `unsigned long` on LP64, the result is its unsigned counterpart.
+pub(crate) fn arithmetic_conversion(a: TypeId, b: TypeId) -> TypeId {
+    const FLOATING: [Type; 3] = [Type::Float, Type::Double, Type::LongDouble];
+    let (a, b) = (promote(a), promote(b));
+    let floating = |ty: TypeId| FLOATING.iter().position(|t| *t == ty.get());
+    match (floating(a), floating(b)) {
+        (Some(x), Some(y)) => return if x >= y { a } else { b },
+        (Some(_), None) => return a,
+        (None, Some(_)) => return b,
+        (None, None) => {}
+    }
+    let rank = |ty: TypeId| match ty.get() {
+        Type::Long | Type::UnsignedLong => 1,
+        Type::LongLong | Type::UnsignedLongLong => 2,
+        _ => 0,
+    };
+    let unsigned = |ty: TypeId| ty.integer_range().is_some_and(|(min, _)| min == 0);
+    if unsigned(a) == unsigned(b) { return if rank(a) >= rank(b) { a } else { b }; }
+    let (u, s) = if unsigned(a) { (a, b) } else { (b, a) };
+    if rank(u) >= rank(s) { return u; }
+    let (Some((_, s_max)), Some((_, u_max))) = (s.integer_range(), u.integer_range()) else { return s };
+    if s_max >= u_max { return s; }
+    TypeId::intern(match s.get() {
+        Type::Long => Type::UnsignedLong,
+        Type::LongLong => Type::UnsignedLongLong,
+        _ => Type::UnsignedInt,
+    })
+}
+
+fn compound_operator(op: BinaryOp) -> BinaryOp {
+    match op {
+        BinaryOp::MulAssign => BinaryOp::Mul,
+        BinaryOp::DivAssign => BinaryOp::Div,
+        BinaryOp::RemAssign => BinaryOp::Rem,
+        BinaryOp::AddAssign => BinaryOp::Add,
+        BinaryOp::SubAssign => BinaryOp::Sub,
+        BinaryOp::ShlAssign => BinaryOp::Shl,
+        BinaryOp::ShrAssign => BinaryOp::Shr,
+        BinaryOp::AndAssign => BinaryOp::BitAnd,
+        BinaryOp::XorAssign => BinaryOp::BitXor,
+        BinaryOp::OrAssign => BinaryOp::BitOr,
+        other => other,
+    }
+}
+
+fn unary_spelling(op: UnaryOp) -> &'static str {
+    match op {
+        UnaryOp::Plus => "+",
+        UnaryOp::Minus => "-",
+        UnaryOp::Not => "!",
+        UnaryOp::BitNot => "~",
+        UnaryOp::Deref => "*",
+        UnaryOp::AddressOf => "&",
+        UnaryOp::PreIncrement | UnaryOp::PostIncrement => "++",
+        UnaryOp::PreDecrement | UnaryOp::PostDecrement => "--",
+    }
+}
+
+/// The type a variable declared `declared`, which has `auto` in it, gets
+/// from an initializer of type `init`.
+fn deduce(declared: TypeId, init: ExprType) -> TypeId {
+    let quals = strip_reference(declared).qualifiers();
+    match declared.get() {
+        Type::Reference(_) => TypeId::intern(Type::Reference(init.ty.qualified(quals))),
+        // A forwarding reference: an lvalue reference for an lvalue.
+        Type::RvalueReference(_) if init.category == Category::Lvalue => TypeId::intern(Type::Reference(init.ty)),
+        Type::RvalueReference(_) => TypeId::intern(Type::RvalueReference(init.ty)),
+        _ => match init.ty.unqualified().get() {
+            Type::Array(elem, _) => TypeId::intern(Type::Pointer(elem)),
+            Type::Function { .. } => TypeId::intern(Type::Pointer(init.ty)),
+            _ => init.ty.unqualified(),
+        }
+        .qualified(quals),
+    }
+}
+
+/// The type of a member of type `ty` of an object with qualifiers `quals`:
+/// a reference member refers to the same object whatever the object it is
+/// a member of, and a `mutable` one is never `const`.
+fn member_type(ty: TypeId, quals: Qualifiers, is_mutable: bool) -> TypeId {
+    match ty.get() {
+        Type::Reference(inner) | Type::RvalueReference(inner) => inner,
+        _ if is_mutable => ty.qualified(Qualifiers { is_const: false, ..quals }),
+        _ => ty.qualified(quals),
+    }
+}
+
+/// The overloads of the member function `name` that an object with
+/// qualifiers `quals` can be the object of: the static ones and those
+/// whose `this` is at least as qualified, and of those that differ only in
+/// it, the least qualified. An error if there are none.
+fn on_object(name: Symbol, methods: &[Signature], quals: Qualifiers, span: Span, diagnostics: &mut Diagnostics) -> Option<Operand> {
+    let callable: Vec<&Signature> = methods.iter().filter(|s| s.this.is_none_or(|this| this.contains(quals))).collect();
+    if callable.is_empty() {
+        diagnostics.emit(SemaError::DiscardsQualifiers { name, quals }.to_diagnostic(span));
+        return None;
+    }
+    let params = |s: &Signature| match s.ty.get() {
+        Type::Function { params, variadic, .. } => Some((params, variadic)),
+        _ => None,
+    };
+    let worse = |s: &Signature, t: &Signature| params(t) == params(s) && matches!((s.this, t.this), (Some(a), Some(b)) if a != b && a.contains(b));
+    let best = callable.iter().filter(|s| !callable.iter().any(|t| worse(s, t))).map(|&s| s.clone()).collect();
+    Some(Operand::Functions(name, best))
+}
+
+fn strip_reference(ty: TypeId) -> TypeId {
+    match ty.get() {
+        Type::Reference(inner) | Type::RvalueReference(inner) => inner,
+        _ => ty,
+    }
+}
+
+fn unqualified(name: Symbol) -> Symbol {
+    match name.as_str().rsplit_once("::") {
+        Some((_, last)) => Symbol::intern(last),
+        None => name,
+    }
+}
@@ -0,0 +1,422 @@
+//! Local variables that may be read before they are assigned, as GCC's
+//! `-Wuninitialized` and `-Wmaybe-uninitialized` find them; both are in
+//! `-Wall`.
+//!
+//! Over a function body's [`Cfg`], a forward dataflow finds at each point
+//! which of the variables declared without an initializer are assigned on
+//! every path to it, and which on some. Reading one assigned on no path is
+//! `-Wuninitialized`; reading one assigned on some but not all is
+//! `-Wmaybe-uninitialized`, reported with the conditions of a path on which
+//! it is not. An assignment on one side of `&&`, `||` or `?:` is one on
+//! some paths only, except that one on the right of the `&&` a branch's
+//! condition ends with is one on every path where it is true, and
+//! likewise for `||` and false.
+//!
+//! Only variables of arithmetic and pointer types are tracked, since a
+//! class may have a constructor and a typedef or enum name may name one,
+//! and only names declared once in the function, so that every use is of
+//! the same variable. Taking a variable's address, binding a reference to
+//! it or passing it where a function may take it by reference, as an
+//! unknown function or `>>` on a stream might, counts as assigning it.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::ast::{BinaryOp, Block, ClassDecl, Decl, Expr, ExprKind, FunctionDecl, Stmt, StmtKind, StorageClass, TranslationUnit, UnaryOp, VarDecl};
+use crate::diagnostics::{Diagnostic, Diagnostics, Label, Warning};
+use crate::intern::Symbol;
+use crate::lexer::token::Span;
+use crate::sema::cfg::{BlockId, Cfg, Element};
+use crate::types::{Type, TypeId};
+use crate::visit::Visitor;
+
+/// The parameter types of the functions of a unit, by unqualified name.
+#[derive(Default)]
+pub struct Signatures {
+    functions: HashMap<Symbol, Vec<(Vec<TypeId>, bool)>>,
+}
+
+impl Signatures {
+    pub fn of(tu: &TranslationUnit) -> Self {
+        let mut signatures = Signatures::default();
+        signatures.visit_translation_unit(tu);
+        signatures
+    }
+
+    /// Whether the function `callee` names may take its argument `i` by
+    /// non-`const` reference, as one the unit does not declare may.
+    fn may_assign(&self, callee: &Expr, i: usize) -> bool {
+        let (ExprKind::Name(name) | ExprKind::TemplateId { name, .. }) = &strip_parens(callee).kind else { return true };
+        let Some(overloads) = self.functions.get(&name.last()) else { return true };
+        overloads.iter().any(|(params, variadic)| match params.get(i) {
+            Some(ty) => is_mutable_reference(*ty),
+            None => !variadic,
+        })
+    }
+}
+
+impl Visitor for Signatures {
+    fn visit_function(&mut self, f: &FunctionDecl) {
+        if let Type::Function { params, variadic, .. } = f.ty.get() {
+            let name = Symbol::intern(f.name.as_str().rsplit("::").next().unwrap_or_default());
+            self.functions.entry(name).or_default().push((params, variadic));
+        }
+        self.walk_function(f);
+    }
+}
+
+/// Reports the reads of `f`'s locals that may come before they are
+/// assigned, over the graph `cfg` of its body.
+pub fn check(f: &FunctionDecl, body: &Block, cfg: &Cfg, signatures: &Signatures, diagnostics: &mut Diagnostics) {
+    if ![Warning::Uninitialized, Warning::MaybeUninitialized].into_iter().any(|w| diagnostics.warnings().is_enabled(w)) { return; }
+    let mut declared = Declared::default();
+    for p in &f.params {
+        if let Some(name) = p.name { declared.names.push((name, None)); }
+    }
+    declared.visit_block(body);
+    let mut counts: HashMap<Symbol, usize> = HashMap::new();
+    for (name, _) in &declared.names { *counts.entry(*name).or_default() += 1; }
+    let tracked: Vec<(Symbol, Span)> = declared.names.iter().filter_map(|&(name, span)| Some((name, span?))).filter(|(name, _)| counts[name] == 1 && !declared.untracked.contains(name)).collect();
+    if tracked.is_empty() { return; }
+    let index: HashMap<Symbol, usize> = tracked.iter().enumerate().map(|(i, &(name, _))| (name, i)).collect();
+
+    // Forward to a fixed point: which variables are assigned on every path
+    // to the end of each block, and which on some.
+    let n = tracked.len();
+    let reachable = cfg.reachable();
+    let predecessors = cfg.predecessors();
+    let mut must_out = vec![vec![true; n]; cfg.blocks.len()];
+    let mut may_out = vec![vec![false; n]; cfg.blocks.len()];
+    // What a branch's condition assigns when it takes each way, as in
+    // `if (p && (x = *p))`.
+    let mut edges: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for branch in &cfg.branches {
+        for (to, value) in [(branch.on_true, true), (branch.on_false, false)] {
+            let mut assigned = Vec::new();
+            assigned_when(branch.cond, value, &index, &mut assigned);
+            if !assigned.is_empty() { edges.entry((branch.from.0, to.0)).or_default().extend(assigned); }
+        }
+    }
+    let entry_state = |b: usize, must_out: &[Vec<bool>], may_out: &[Vec<bool>]| {
+        // Variables not declared yet are as good as assigned.
+        if b == cfg.entry.0 { return (vec![true; n], vec![true; n]); }
+        let (mut must, mut may) = (vec![true; n], vec![false; n]);
+        for p in predecessors[b].iter().filter(|p| reachable[p.0]) {
+            let assigned = edges.get(&(p.0, b)).map_or(&[][..], Vec::as_slice);
+            for i in 0..n {
+                must[i] &= must_out[p.0][i] || assigned.contains(&i);
+                may[i] |= may_out[p.0][i];
+            }
+        }
+        (must, may)
+    };
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for b in (0..cfg.blocks.len()).filter(|&b| reachable[b]) {
+            let (must, may) = entry_state(b, &must_out, &may_out);
+            let mut transfer = Transfer::new(&index, signatures, must, may);
+            for element in &cfg.blocks[b].elements { transfer.element(element); }
+            if transfer.must != must_out[b] || transfer.may != may_out[b] {
+                (must_out[b], may_out[b]) = (transfer.must, transfer.may);
+                changed = true;
+            }
+        }
+    }
+
+    // Replay each block from its entry state to find the reads.
+    let mut reads: Vec<Option<Read>> = vec![None; n];
+    let mut declared_in = vec![None; n];
+    let mut assigned_in: Vec<HashSet<usize>> = vec![HashSet::new(); n];
+    for b in (0..cfg.blocks.len()).filter(|&b| reachable[b]) {
+        let (must, may) = entry_state(b, &must_out, &may_out);
+        let mut transfer = Transfer::new(&index, signatures, must, may);
+        transfer.recording = true;
+        for element in &cfg.blocks[b].elements { transfer.element(element); }
+        for i in transfer.declared { declared_in[i] = Some(b); }
+        for i in transfer.assigned { assigned_in[i].insert(b); }
+        for read in transfer.reads {
+            let first = &mut reads[read.variable];
+            if first.as_ref().is_none_or(|r| read.span.start < r.span.start) { *first = Some(Read { block: b, ..read }); }
+        }
+    }
+
+    for read in reads.into_iter().flatten() {
+        let (name, declared_at) = tracked[read.variable];
+        if read.never_assigned {
+            diagnostics.emit(Diagnostic::warning(format!("`{}` is used uninitialized", name))
+                .with_warning(Warning::Uninitialized)
+                .with_label(Label::primary(read.span, "read here before it is assigned"))
+                .with_label(Label::secondary(declared_at, "declared here without an initializer")));
+            continue;
+        }
+        let mut diag = Diagnostic::warning(format!("`{}` may be used uninitialized", name))
+            .with_warning(Warning::MaybeUninitialized)
+            .with_label(Label::primary(read.span, "read here"))
+            .with_label(Label::secondary(declared_at, "declared here without an initializer"));
+        let path = declared_in[read.variable].map(|from| path(cfg, &predecessors, &must_out, read.variable, from, read.block)).unwrap_or_default();
+        for pair in path.windows(2) {
+            let Some(branch) = cfg.branches.iter().find(|b| b.from == pair[0]) else { continue };
+            let (taken, other) = if pair[1] == branch.on_true { (true, branch.on_false) } else { (false, branch.on_true) };
+            // Only where the other way could assign it.
+            let reached = cfg.reachable_from(other);
+            let on_edge = edges.get(&(branch.from.0, other.0)).is_some_and(|a| a.contains(&read.variable));
+            if !on_edge && !assigned_in[read.variable].iter().any(|&b| reached[b]) { continue; }
+            diag = diag.with_label(Label::secondary(branch.cond.span, format!("`{}` is not assigned when this condition is {}", name, taken)));
+        }
+        diagnostics.emit(diag);
+    }
+}
+
+/// A path of blocks from `from`, where variable `i` is declared, to `to`,
+/// on which nothing assigns it.
+fn path(cfg: &Cfg, predecessors: &[Vec<BlockId>], must_out: &[Vec<bool>], i: usize, from: usize, to: usize) -> Vec<BlockId> {
+    let reachable = cfg.reachable();
+    let mut next: HashMap<usize, usize> = HashMap::new();
+    let mut queue = VecDeque::from([to]);
+    while let Some(b) = queue.pop_front() {
+        if b == from {
+            let mut path = vec![BlockId(b)];
+            let mut at = b;
+            while let Some(&after) = next.get(&at) {
+                path.push(BlockId(after));
+                at = after;
+            }
+            return path;
+        }
+        for p in &predecessors[b] {
+            if reachable[p.0] && !must_out[p.0][i] && p.0 != to && !next.contains_key(&p.0) {
+                next.insert(p.0, b);
+                queue.push_back(p.0);
+            }
+        }
+    }
+    Vec::new()
+}
+
+/// A read of a tracked variable that may not be assigned.
+#[derive(Debug, Clone)]
+struct Read {
+    variable: usize,
+    span: Span,
+    block: usize,
+    /// Whether no path assigns it.
+    never_assigned: bool,
+}
+
+/// The parameters and variables a function body declares, in order, with
+/// where those that may be tracked are declared.
+#[derive(Default)]
+struct Declared {
+    names: Vec<(Symbol, Option<Span>)>,
+    /// Variables initialized where they are declared by what the parser
+    /// does not see as an initializer, like a range-based `for`'s.
+    untracked: HashSet<Symbol>,
+}
+
+impl Visitor for Declared {
+    fn visit_decl(&mut self, decl: &Decl) {
+        match decl {
+            Decl::Var(v) => {
+                let tracked = v.init.is_none() && v.specifiers.storage == StorageClass::None && is_scalar(v.ty);
+                self.names.push((v.name, tracked.then_some(v.name_span)));
+            }
+            Decl::Decomposition(d) => {
+                for b in &d.bindings { self.names.push((b.name, None)); }
+            }
+            _ => {}
+        }
+        self.walk_decl(decl);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        if let StmtKind::RangeFor { decl, .. } = &stmt.kind { self.untracked.insert(decl.name()); }
+        self.walk_stmt(stmt);
+    }
+
+    // A local class's functions have locals of their own.
+    fn visit_function(&mut self, _f: &FunctionDecl) {}
+}
+
+/// What the elements of a block do to the tracked variables.
+struct Transfer<'t> {
+    index: &'t HashMap<Symbol, usize>,
+    signatures: &'t Signatures,
+    /// Whether each variable is assigned on every path to here.
+    must: Vec<bool>,
+    /// Whether each is assigned on some path to here.
+    may: Vec<bool>,
+    /// How many operands that are evaluated only sometimes this is in.
+    conditional: usize,
+    /// Whether to record what follows.
+    recording: bool,
+    reads: Vec<Read>,
+    declared: Vec<usize>,
+    assigned: Vec<usize>,
+}
+
+impl<'t> Transfer<'t> {
+    fn new(index: &'t HashMap<Symbol, usize>, signatures: &'t Signatures, must: Vec<bool>, may: Vec<bool>) -> Self {
+        Transfer { index, signatures, must, may, conditional: 0, recording: false, reads: Vec::new(), declared: Vec::new(), assigned: Vec::new() }
+    }
+
+    fn element(&mut self, element: &Element) {
+        match element {
+            Element::Stmt(stmt) => self.visit_stmt(stmt),
+            Element::Expr(e) => self.visit_expr(e),
+            Element::Decl(decl) => self.visit_decl(decl),
+        }
+    }
+
+    /// The tracked variable `e` names, if it names one.
+    fn variable(&self, e: &Expr) -> Option<usize> {
+        match &strip_parens(e).kind {
+            ExprKind::Name(name) if !name.is_global() => self.index.get(&name.symbol()).copied(),
+            _ => None,
+        }
+    }
+
+    fn read(&mut self, i: usize, span: Span) {
+        if self.recording && !self.must[i] { self.reads.push(Read { variable: i, span, block: 0, never_assigned: !self.may[i] }); }
+    }
+
+    fn assign(&mut self, i: usize) {
+        if self.conditional == 0 { self.must[i] = true; }
+        self.may[i] = true;
+        if self.recording { self.assigned.push(i); }
+    }
+
+    /// Visits `e`, which may be bound to a non-`const` reference if
+    /// `by_reference`, which counts as assigning the variable it names.
+    fn operand(&mut self, e: &Expr, by_reference: bool) {
+        match self.variable(e) {
+            Some(i) if by_reference => self.assign(i),
+            _ => self.visit_expr(e),
+        }
+    }
+
+    fn conditionally(&mut self, e: &Expr) {
+        self.conditional += 1;
+        self.visit_expr(e);
+        self.conditional -= 1;
+    }
+}
+
+impl Visitor for Transfer<'_> {
+    fn visit_expr(&mut self, e: &Expr) {
+        match &e.kind {
+            ExprKind::Name(_) => {
+                if let Some(i) = self.variable(e) { self.read(i, e.span); }
+            }
+            ExprKind::Unary { op: UnaryOp::AddressOf, operand } => self.operand(operand, true),
+            ExprKind::Binary { op: BinaryOp::Assign, lhs, rhs } => {
+                self.visit_expr(rhs);
+                self.operand(lhs, true);
+            }
+            ExprKind::Binary { op: BinaryOp::LogicalAnd | BinaryOp::LogicalOr, lhs, rhs } => {
+                self.visit_expr(lhs);
+                self.conditionally(rhs);
+            }
+            // `in >> x` on a stream reads into `x`.
+            ExprKind::Binary { op: BinaryOp::Shr, lhs, rhs } => {
+                self.visit_expr(lhs);
+                let stream = self.variable(lhs).is_none() && !matches!(strip_parens(lhs).kind, ExprKind::IntLiteral(..));
+                self.operand(rhs, stream);
+            }
+            ExprKind::Conditional { cond, then, otherwise } => {
+                self.visit_expr(cond);
+                self.conditionally(then);
+                self.conditionally(otherwise);
+            }
+            ExprKind::Call { callee, args } => {
+                self.visit_expr(callee);
+                for (i, arg) in args.iter().enumerate() { self.operand(arg, self.signatures.may_assign(callee, i)); }
+            }
+            ExprKind::Construct { ty, args, .. } => {
+                for arg in args { self.operand(arg, !is_scalar(*ty)); }
+            }
+            ExprKind::SizeofExpr(_) | ExprKind::Requires { .. } => {}
+            _ => self.walk_expr(e),
+        }
+    }
+
+    fn visit_var(&mut self, v: &VarDecl) {
+        let by_reference = !is_scalar(v.ty) || matches!(v.ty.get(), Type::Reference(_) | Type::RvalueReference(_));
+        match &v.init {
+            Some(Expr { kind: ExprKind::ParenList(args) | ExprKind::InitList(args), .. }) => {
+                for arg in args { self.operand(arg, by_reference); }
+            }
+            Some(init) => self.operand(init, by_reference),
+            None => {}
+        }
+        // Only variables declared without an initializer are tracked.
+        let Some(&i) = self.index.get(&v.name) else { return };
+        (self.must[i], self.may[i]) = (false, false);
+        if self.recording { self.declared.push(i); }
+    }
+
+    fn visit_class(&mut self, _c: &ClassDecl) {}
+
+    fn visit_function(&mut self, _f: &FunctionDecl) {}
+}
+
+/// Adds to `assigned` the tracked variables that `cond` assigns whenever
+/// it evaluates to `value`, besides those it always does.
+fn assigned_when(cond: &Expr, value: bool, index: &HashMap<Symbol, usize>, assigned: &mut Vec<usize>) {
+    match &cond.kind {
+        ExprKind::Paren(inner) => assigned_when(inner, value, index, assigned),
+        ExprKind::Unary { op: UnaryOp::Not, operand } => assigned_when(operand, !value, index, assigned),
+        // Both operands ran if `a && b` is true, or if `a || b` is false.
+        ExprKind::Binary { op: op @ (BinaryOp::LogicalAnd | BinaryOp::LogicalOr), lhs, rhs } => {
+            assigned_when(lhs, value, index, assigned);
+            if value == (*op == BinaryOp::LogicalAnd) { assigned_when(rhs, value, index, assigned); always_assigned(rhs, index, assigned); }
+        }
+        _ => {}
+    }
+}
+
+/// Adds to `assigned` the tracked variables `e` assigns whenever it runs.
+fn always_assigned(e: &Expr, index: &HashMap<Symbol, usize>, assigned: &mut Vec<usize>) {
+    match &e.kind {
+        ExprKind::Paren(inner) => always_assigned(inner, index, assigned),
+        ExprKind::Binary { op: BinaryOp::LogicalAnd | BinaryOp::LogicalOr, lhs, .. } => always_assigned(lhs, index, assigned),
+        ExprKind::Conditional { cond, .. } => always_assigned(cond, index, assigned),
+        ExprKind::Binary { op: BinaryOp::Assign, lhs, rhs } => {
+            always_assigned(rhs, index, assigned);
+            match &strip_parens(lhs).kind {
+                ExprKind::Name(name) if !name.is_global() => assigned.extend(index.get(&name.symbol())),
+                _ => always_assigned(lhs, index, assigned),
+            }
+        }
+        ExprKind::Binary { lhs, rhs, .. } => {
+            always_assigned(lhs, index, assigned);
+            always_assigned(rhs, index, assigned);
+        }
+        ExprKind::Unary { operand, .. } => always_assigned(operand, index, assigned),
+        _ => {}
+    }
+}
+
+fn strip_parens<'e, 'a>(e: &'e Expr<'a>) -> &'e Expr<'a> {
+    match &e.kind {
+        ExprKind::Paren(inner) => strip_parens(inner),
+        _ => e,
+    }
+}
+
+/// Whether `ty` is an arithmetic or pointer type, which has no constructor.
+fn is_scalar(ty: TypeId) -> bool {
+    match ty.unqualified().get() {
+        Type::Pointer(_) => true,
+        Type::Void | Type::Auto | Type::Named(_) | Type::Specialization(..) | Type::Reference(_) | Type::RvalueReference(_) | Type::Array(..) | Type::Function { .. } => false,
+        _ => true,
+    }
+}
+
+/// Whether a parameter of type `ty` is a reference through which the
+/// argument may be assigned.
+fn is_mutable_reference(ty: TypeId) -> bool {
+    match ty.get() {
+        Type::Reference(inner) | Type::RvalueReference(inner) => !inner.is_const(),
+        _ => false,
+    }
+}
@@ -0,0 +1,14 @@
+//! The `visibility` attribute, which `symbols` reads to decide what a
+//! shared library exports. Its argument must be one of the four ELF
+//! visibilities.
+
+use crate::ast::Attribute;
+use crate::diagnostics::Diagnostics;
+use crate::sema::SemaError;
+use crate::symbols::{self, Visibility};
+
+/// Checks the `visibility` attribute among `attrs`, if there is one.
+pub fn check(attrs: &[Attribute], diagnostics: &mut Diagnostics) {
+    let Some(a) = symbols::attribute(attrs) else { return };
+    if symbols::argument(a).and_then(|v| v.parse::<Visibility>().ok()).is_none() { diagnostics.emit(SemaError::InvalidVisibility.to_diagnostic(a.span)); }
+}
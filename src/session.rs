@@ -0,0 +1,316 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::cancel::{CancellationToken, Cancelled};
+use crate::charset::{Charset, ExecCharsets};
+use crate::depfile::DepfileOptions;
+use crate::diagnostics::{DiagnosticFormat, Diagnostics, WarningOptions};
+use crate::intern::Interner;
+use crate::lexer::token::{SpannedLexError, SpannedToken, Token};
+use crate::lexer::{Lexer, LexerOptions};
+use crate::preprocessor::headers;
+use crate::profile::Profiler;
+use crate::source::{FileId, SourceManager};
+use crate::symbols::Visibility;
+use crate::vfs::{normalize, FileSystem, RealFileSystem};
+
+/// User-facing settings for one compilation.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub inputs: Vec<PathBuf>,
+    pub output: Option<PathBuf>,
+    pub lexer: LexerOptions,
+    /// `-I` directories searched for `#include`d headers, in order.
+    pub include_dirs: Vec<PathBuf>,
+    /// `-isystem` and default directories, searched after `include_dirs`.
+    /// Headers found in them are system headers.
+    pub system_include_dirs: Vec<PathBuf>,
+    /// `--embed-dir` directories searched for `#embed`ed files.
+    pub embed_dirs: Vec<PathBuf>,
+    /// `-D` options: `NAME` or `NAME=VALUE`.
+    pub defines: Vec<String>,
+    /// `-U` options: macro names to undefine after the `-D`s.
+    pub undefines: Vec<String>,
+    /// Where to write a Chrome trace of the phases, if anywhere.
+    pub profile_out: Option<PathBuf>,
+    /// Sysroot whose headers and libraries replace the host's.
+    pub sysroot: Option<PathBuf>,
+    /// How `Driver::flush_diagnostics` writes diagnostics out.
+    pub diagnostic_format: DiagnosticFormat,
+    /// Inputs processed in parallel; 0 and 1 both mean one at a time.
+    pub jobs: usize,
+    /// `-MD`/`-MMD`: write a Make dependency file per input.
+    pub depfile: Option<DepfileOptions>,
+    /// `--verify-ast`: check every parsed tree's spans and report the nodes
+    /// that are out of place as errors.
+    pub verify_ast: bool,
+    /// `--error-limit`: the number of errors after which the rest are
+    /// dropped and parsing stops; 0 for no limit.
+    pub error_limit: usize,
+    /// `-march`: the CPU whose instruction set the code may use.
+    pub march: Option<String>,
+    /// `-mcpu`: the CPU to generate code for, used for its instruction set
+    /// when there is no `-march`.
+    pub mcpu: Option<String>,
+    /// `-ffreestanding`: no hosted C++ library is assumed, and
+    /// `__STDC_HOSTED__` is 0.
+    pub freestanding: bool,
+    /// `-nostdinc`: only `-I` and `-isystem` directories are searched, not
+    /// the default ones, the sysroot's or the built-in headers.
+    pub nostdinc: bool,
+    /// `-fexec-charset`: the encoding of narrow string and character
+    /// literals in the program.
+    pub exec_charset: Charset,
+    /// `-fwide-exec-charset`: the encoding of wide ones, if not the
+    /// target's `Target::wchar_charset`.
+    pub wide_exec_charset: Option<Charset>,
+    /// `--module-cache`: where compiled module interfaces are written and
+    /// imported modules are looked for. Without one, imports load nothing.
+    pub module_cache: Option<PathBuf>,
+    /// `-fvisibility`: the visibility of symbols whose declarations and
+    /// scopes give them none.
+    pub visibility: Visibility,
+    /// Set by `ruscom analyze`: constructs that fail to parse are skipped
+    /// and kept in the tree as `UnsupportedConstruct`s instead of being
+    /// reported as syntax errors.
+    pub skip_unsupported: bool,
+    /// Set by `ruscom stats macros`: the preprocessor counts the macro
+    /// expansions of each input.
+    pub macro_stats: bool,
+    /// `-W` options: which warnings are reported, and which as errors.
+    pub warnings: WarningOptions,
+    /// `-std`: the C++ standard the program is written to.
+    pub std: Standard,
+}
+
+impl Options {
+    /// The CPU whose instruction set extensions are enabled, if chosen.
+    pub fn target_cpu(&self) -> Option<&str> { self.march.as_deref().or(self.mcpu.as_deref()) }
+}
+
+/// A C++ standard, which decides `__cplusplus` and what some literals
+/// are.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Standard {
+    #[default]
+    Cxx17,
+    /// Where `u8` literals are of `char8_t`.
+    Cxx20,
+}
+
+impl Standard {
+    /// The value of `__cplusplus`.
+    pub fn cplusplus(self) -> &'static str {
+        match self {
+            Standard::Cxx17 => "201703L",
+            Standard::Cxx20 => "202002L",
+        }
+    }
+}
+
+impl FromStr for Standard {
+    type Err = String;
+
+    /// Accepts GCC's names, with their `gnu++` spellings.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix("c++").or_else(|| s.strip_prefix("gnu++")) {
+            Some("17" | "1z") => Ok(Standard::Cxx17),
+            Some("20" | "2a") => Ok(Standard::Cxx20),
+            _ => Err(format!("unsupported language standard `{}` (expected c++17 or c++20)", s)),
+        }
+    }
+}
+
+impl fmt::Display for Standard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Standard::Cxx17 => "c++17",
+            Standard::Cxx20 => "c++20",
+        })
+    }
+}
+
+/// The machine code is being generated for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub arch: String,
+    pub os: String,
+}
+
+impl Target {
+    /// The target the compiler itself was built for.
+    pub fn host() -> Self {
+        Self { arch: std::env::consts::ARCH.to_string(), os: std::env::consts::OS.to_string() }
+    }
+
+    /// The encoding of wide literals by default: UTF-16 where `wchar_t`
+    /// is 16 bits, UTF-32 elsewhere.
+    pub fn wchar_charset(&self) -> Charset {
+        if self.os == "windows" { Charset::Utf16 } else { Charset::Utf32 }
+    }
+
+    pub fn triple(&self) -> String {
+        let (vendor, env) = match self.os.as_str() {
+            "macos" | "ios" => ("apple", ""),
+            "linux" => ("unknown", "-gnu"),
+            "windows" => ("pc", "-msvc"),
+            _ => ("unknown", ""),
+        };
+        let os = if self.os == "macos" { "darwin" } else { self.os.as_str() };
+        format!("{}-{}-{}{}", self.arch, vendor, os, env)
+    }
+
+    /// The instruction set extensions of `cpu` on this architecture, or
+    /// `None` if the CPU is unknown. Later levels include the earlier ones.
+    pub fn cpu_features(&self, cpu: &str) -> Option<Vec<&'static str>> {
+        let levels: &[(&[&str], &[&str])] = match self.arch.as_str() {
+            "x86_64" => &[
+                (&["x86-64"], &["sse", "sse2"]),
+                (&["x86-64-v2", "nehalem"], &["sse3", "ssse3", "sse4.1", "sse4.2", "popcnt"]),
+                (&["x86-64-v3", "haswell"], &["avx", "avx2", "bmi", "bmi2", "fma"]),
+                (&["x86-64-v4", "skylake-avx512"], &["avx512f", "avx512bw", "avx512cd", "avx512dq", "avx512vl"]),
+            ],
+            "aarch64" => &[
+                (&["armv8-a", "generic"], &["neon"]),
+                (&["armv8.1-a"], &["crc"]),
+                (&["armv8.2-a", "apple-m1"], &["aes", "sha2", "dotprod"]),
+            ],
+            _ => &[],
+        };
+        let level = levels.iter().position(|(names, _)| names.contains(&cpu))?;
+        Some(levels[..=level].iter().flat_map(|(_, features)| features.iter().copied()).collect())
+    }
+
+    /// The macro predefined when `feature` is enabled, like `__AVX2__`.
+    pub fn feature_macro(&self, feature: &str) -> String {
+        match (self.arch.as_str(), feature) {
+            ("aarch64", "neon") => "__ARM_NEON".into(),
+            ("aarch64", "crc") => "__ARM_FEATURE_CRC32".into(),
+            ("aarch64", other) => format!("__ARM_FEATURE_{}", other.to_uppercase()),
+            (_, other) => format!("__{}__", other.to_uppercase().replace('.', "_")),
+        }
+    }
+}
+
+/// Tokens produced for one source, plus the error that stopped lexing early.
+#[derive(Debug)]
+pub struct LexOutput {
+    pub file: FileId,
+    pub tokens: Vec<SpannedToken>,
+    pub error: Option<SpannedLexError>,
+}
+
+/// All state belonging to one compilation.
+///
+/// Nothing in the library keeps per-compilation state in globals: each phase
+/// takes the session explicitly, so independent sessions can run side by
+/// side in one process (on different threads, for the LSP and tests).
+pub struct Session {
+    pub options: Options,
+    pub target: Target,
+    pub interner: Interner,
+    pub profiler: Profiler,
+    pub cancel: CancellationToken,
+    /// Where source files are read from.
+    pub fs: Arc<dyn FileSystem>,
+    pub sources: SourceManager,
+    pub diagnostics: Diagnostics,
+}
+
+/// How many tokens are lexed between cancellation checks.
+pub(crate) const CANCEL_CHECK_INTERVAL: usize = 1024;
+
+impl Session {
+    pub fn new(options: Options) -> Self { Self::with_target(options, Target::host()) }
+
+    pub fn with_target(options: Options, target: Target) -> Self {
+        let diagnostics = Diagnostics::with_error_limit(options.error_limit).with_warnings(options.warnings.clone());
+        Self {
+            options,
+            target,
+            interner: Interner::new(),
+            profiler: Profiler::new(),
+            cancel: CancellationToken::new(),
+            fs: Arc::new(RealFileSystem),
+            sources: SourceManager::new(),
+            diagnostics,
+        }
+    }
+
+    /// Replaces the file system the session reads sources from.
+    pub fn with_file_system(mut self, fs: Arc<dyn FileSystem>) -> Self {
+        self.fs = fs;
+        self
+    }
+
+    /// The character sets literals are encoded in: `-fexec-charset` and
+    /// `-fwide-exec-charset`, or their defaults for the target.
+    pub fn exec_charsets(&self) -> ExecCharsets {
+        ExecCharsets {
+            narrow: self.options.exec_charset,
+            wide: self.options.wide_exec_charset.unwrap_or_else(|| self.target.wchar_charset()),
+            char8: self.options.std >= Standard::Cxx20,
+        }
+    }
+
+    /// Runs `f` on the session, recording in its profiler how long it took
+    /// under `name`: `Profiler::time` for phases that need the whole session.
+    pub fn time<T>(&mut self, name: &str, category: &'static str, f: impl FnOnce(&mut Session) -> T) -> T {
+        let start = Instant::now();
+        let out = f(self);
+        self.profiler.record(name, category, start);
+        out
+    }
+
+    /// Registers `src` as an anonymous file and lexes it.
+    pub fn tokenize(&mut self, src: &str) -> Result<LexOutput, Cancelled> {
+        let file = self.sources.add_file("<input>", src);
+        self.tokenize_file(file)
+    }
+
+    /// Lexes a loaded file up to end of file or the first error, which is
+    /// also reported to `diagnostics`. Token spans are positions in the
+    /// session's `SourceManager`.
+    pub fn tokenize_file(&mut self, file: FileId) -> Result<LexOutput, Cancelled> {
+        self.cancel.check()?;
+        let (src, base) = {
+            let f = self.sources.file(file);
+            (f.contents.clone(), f.start)
+        };
+        let mut lexer = Lexer::with_options(&src, self.options.lexer).with_base_offset(base);
+        let mut tokens = Vec::new();
+        let cancel = &self.cancel;
+        let error = self.profiler.time("lex", "phase", || loop {
+            if tokens.len() % CANCEL_CHECK_INTERVAL == CANCEL_CHECK_INTERVAL - 1 { cancel.check()?; }
+            match lexer.next_token() {
+                Ok(t) if t.token == Token::Eof => break Ok(None),
+                Ok(t) => tokens.push(t),
+                Err(e) => break Ok(Some(e)),
+            }
+        })?;
+        for t in &tokens {
+            if let Token::Identifier(name) = &t.token { self.interner.record(&name.as_str()); }
+        }
+        if let Some(e) = &error { self.diagnostics.emit(e.to_diagnostic()); }
+        Ok(LexOutput { file, tokens, error })
+    }
+
+    /// Whether `path` is a system header, which `-MMD` leaves out of
+    /// dependency files: a built-in header, or one under the sysroot or a
+    /// system include directory.
+    pub fn is_system_header(&self, path: &Path) -> bool {
+        headers::is_builtin(path)
+            || self.options.sysroot.iter().chain(&self.options.system_include_dirs).any(|dir| path.starts_with(normalize(dir)))
+    }
+
+    /// Writes the profile to `options.profile_out`, if one was requested.
+    pub fn finish(&self) -> std::io::Result<()> {
+        match &self.options.profile_out {
+            Some(path) => self.profiler.write_to(&path.to_string_lossy()),
+            None => Ok(()),
+        }
+    }
+}
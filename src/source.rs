@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::lexer::token::Span;
+use crate::vfs::{normalize, FileSystem};
+
+/// Identifies a file loaded into a `SourceManager`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FileId(u32);
+
+impl FileId {
+    pub fn index(self) -> usize { self.0 as usize }
+}
+
+/// One loaded file.
+///
+/// Its bytes occupy `[start, start + len]` in the session-wide position
+/// space, so a bare `Span` identifies both the file and the offsets in it.
+#[derive(Debug)]
+pub struct SourceFile {
+    pub id: FileId,
+    pub path: PathBuf,
+    pub contents: Arc<str>,
+    pub start: usize,
+    /// Where the file was `#include`d from, if it was.
+    pub included_from: Option<Span>,
+    /// Byte offset (within the file) of the start of every line.
+    line_starts: Vec<usize>,
+    /// Renumberings by `#line` directives and line markers, in order.
+    line_remaps: Vec<LineRemap>,
+}
+
+/// From the line starting at `offset` within a file on, lines are
+/// numbered from `line`, and belong to `path` if it is given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LineRemap {
+    offset: usize,
+    line: usize,
+    path: Option<PathBuf>,
+}
+
+impl SourceFile {
+    /// Position just past the last byte; spans at end of file point here.
+    pub fn end(&self) -> usize { self.start + self.contents.len() }
+
+    pub fn line_count(&self) -> usize { self.line_starts.len() }
+
+    /// Text of 1-based line `line`, without its line terminator.
+    pub fn line_text(&self, line: usize) -> &str {
+        let start = self.line_starts[line - 1];
+        let end = self.line_starts.get(line).copied().unwrap_or(self.contents.len());
+        self.contents[start..end].trim_end_matches(['\n', '\r'])
+    }
+
+    /// The position of 1-based `line` and `column`, the inverse of
+    /// `SourceManager::location`. A column may be just past the end of the
+    /// line.
+    pub fn position(&self, line: usize, column: usize) -> Option<usize> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let text = self.line_text(line);
+        let before = column.checked_sub(1)?;
+        if before > text.chars().count() { return None; }
+        Some(self.start + start + text.chars().take(before).map(char::len_utf8).sum::<usize>())
+    }
+
+    /// Converts a file-relative byte offset into a 1-based line and column.
+    /// Columns count characters, not bytes.
+    fn line_col(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|&s| s <= offset);
+        let line_start = self.line_starts[line - 1];
+        let col = self.contents[line_start..offset.min(self.contents.len())].chars().count() + 1;
+        (line, col)
+    }
+}
+
+/// A resolved source position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: FileId,
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.path.display(), self.line, self.column)
+    }
+}
+
+/// Renumbering applied by `SourceManager::absorb`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Remap {
+    pub offset: usize,
+    pub first_file: u32,
+}
+
+impl Remap {
+    pub fn span(&self, span: Span) -> Span { Span::new(span.start + self.offset, span.end + self.offset) }
+
+    pub fn file(&self, file: FileId) -> FileId { FileId(file.0 + self.first_file) }
+}
+
+/// Owns every file loaded during a compilation and maps spans back to
+/// file/line/column.
+#[derive(Debug, Default)]
+pub struct SourceManager {
+    files: Vec<SourceFile>,
+    by_path: HashMap<PathBuf, FileId>,
+}
+
+impl SourceManager {
+    pub fn new() -> Self { Self::default() }
+
+    /// Registers `contents` under `path` and returns its new id.
+    ///
+    /// Adding the same path twice yields two distinct files; use `load` to
+    /// reuse an already loaded file.
+    pub fn add_file(&mut self, path: impl Into<PathBuf>, contents: impl Into<Arc<str>>) -> FileId {
+        self.add(path.into(), contents.into(), None)
+    }
+
+    /// Registers `contents` under `path`, replacing whatever was loaded
+    /// there before, for a long-lived session whose files are edited.
+    ///
+    /// The file keeps its id when the contents are unchanged, or when the
+    /// new contents fit in the old file's positions; otherwise it is added
+    /// anew and `path` looks up the new id. Spans into the old contents are
+    /// meaningless afterwards.
+    pub fn set_file(&mut self, path: impl Into<PathBuf>, contents: impl Into<Arc<str>>) -> FileId {
+        let (path, contents) = (path.into(), contents.into());
+        let Some(id) = self.lookup_path(&path) else { return self.add(path, contents, None) };
+        let is_last = id.index() + 1 == self.files.len();
+        let file = &mut self.files[id.index()];
+        if file.contents == contents && file.included_from.is_none() { return id; }
+        if !is_last && contents.len() > file.contents.len() { return self.add(path, contents, None); }
+        file.line_starts = line_starts(&contents);
+        file.line_remaps.clear();
+        file.contents = contents;
+        file.path = path;
+        file.included_from = None;
+        id
+    }
+
+    /// Like `add_file`, recording the `#include` directive that pulled it in.
+    pub fn add_included_file(&mut self, path: impl Into<PathBuf>, contents: impl Into<Arc<str>>, from: Span) -> FileId {
+        self.add(path.into(), contents.into(), Some(from))
+    }
+
+    fn add(&mut self, path: PathBuf, contents: Arc<str>, included_from: Option<Span>) -> FileId {
+        let id = FileId(self.files.len() as u32);
+        // Leave a one-byte gap so a file's end position never equals the
+        // next file's start.
+        let start = self.files.last().map_or(0, |f| f.end() + 1);
+        let line_starts = line_starts(&contents);
+        self.by_path.insert(normalize(&path), id);
+        self.files.push(SourceFile { id, path, contents, start, included_from, line_starts, line_remaps: Vec::new() });
+        id
+    }
+
+    /// Returns the id of `path`, reading it through `fs` the first time.
+    pub fn load(&mut self, fs: &dyn FileSystem, path: &Path) -> io::Result<FileId> {
+        if let Some(id) = self.lookup_path(path) { return Ok(id); }
+        let contents = fs.read_to_string(path)?;
+        Ok(self.add_file(path, contents))
+    }
+
+    /// Appends every file of `other`, a manager filled independently (say
+    /// on a worker thread), after this one's. Returns how its positions and
+    /// ids were renumbered, to apply to spans that point into it.
+    pub fn absorb(&mut self, other: SourceManager) -> Remap {
+        let remap = Remap {
+            offset: self.files.last().map_or(0, |f| f.end() + 1),
+            first_file: self.files.len() as u32,
+        };
+        for mut file in other.files {
+            file.id = remap.file(file.id);
+            file.start += remap.offset;
+            file.included_from = file.included_from.map(|s| remap.span(s));
+            self.by_path.entry(normalize(&file.path)).or_insert(file.id);
+            self.files.push(file);
+        }
+        remap
+    }
+
+    pub fn lookup_path(&self, path: &Path) -> Option<FileId> { self.by_path.get(&normalize(path)).copied() }
+
+    pub fn file(&self, id: FileId) -> &SourceFile { &self.files[id.index()] }
+
+    pub fn files(&self) -> &[SourceFile] { &self.files }
+
+    /// The file containing position `pos`.
+    pub fn file_at(&self, pos: usize) -> Option<&SourceFile> {
+        let idx = self.files.partition_point(|f| f.start <= pos).checked_sub(1)?;
+        let file = &self.files[idx];
+        (pos <= file.end()).then_some(file)
+    }
+
+    pub fn location(&self, pos: usize) -> Option<Location> {
+        let file = self.file_at(pos)?;
+        let (line, column) = file.line_col(pos - file.start);
+        Some(Location { file: file.id, path: file.path.clone(), line, column })
+    }
+
+    /// The location `pos` is presumed to have, as diagnostics, `__LINE__`
+    /// and `__FILE__` report it: its line numbered, and its file named, as
+    /// the last `#line` directive or line marker before it says.
+    pub fn presumed_location(&self, pos: usize) -> Option<Location> {
+        let mut loc = self.location(pos)?;
+        let file = self.file(loc.file);
+        if let Some(remap) = file.line_remaps.iter().rev().find(|r| r.offset <= pos - file.start) {
+            let (from, _) = file.line_col(remap.offset);
+            loc.line = remap.line + loc.line - from;
+            if let Some(path) = &remap.path { loc.path = path.clone(); }
+        }
+        Some(loc)
+    }
+
+    /// Numbers the lines from the one starting at `pos` on from `line`,
+    /// and names their file `path` if given, as `#line` does. Renumberings
+    /// from `pos` or later are replaced, so a file preprocessed again
+    /// records each directive once.
+    pub fn remap_lines(&mut self, pos: usize, line: usize, path: Option<PathBuf>) {
+        let Some(id) = self.file_at(pos).map(|f| f.id) else { return };
+        let file = &mut self.files[id.index()];
+        let offset = pos - file.start;
+        file.line_remaps.retain(|r| r.offset < offset);
+        // Without a name, the file keeps the one it was last given.
+        let path = path.or_else(|| file.line_remaps.last().and_then(|r| r.path.clone()));
+        file.line_remaps.push(LineRemap { offset, line, path });
+    }
+
+    /// The source text a span covers.
+    pub fn snippet(&self, span: Span) -> Option<&str> {
+        let file = self.file_at(span.start)?;
+        file.contents.get(span.start - file.start..span.end.checked_sub(file.start)?)
+    }
+}
+
+/// Byte offset of the start of every line of `contents`.
+fn line_starts(contents: &str) -> Vec<usize> {
+    let mut starts = vec![0];
+    starts.extend(contents.match_indices('\n').map(|(i, _)| i + 1));
+    starts
+}
@@ -0,0 +1,281 @@
+//! The symbols a translation unit defines, with their binding and
+//! visibility: what `ruscom symbols` lists and, with `--exported`, what a
+//! shared library built from the unit would export.
+//!
+//! Visibility comes from `__attribute__((visibility("...")))` or
+//! `[[gnu::visibility("...")]]` on the entity, else from its class or
+//! namespace, else from `-fvisibility`. Only definitions are listed:
+//! non-inline functions with bodies and variables that are not `extern`
+//! declarations, outside templates and class bodies, since inline
+//! functions and template specializations are only emitted where used.
+//! A declaration with `__attribute__((alias("target")))` defines another
+//! name for its target, and `weak` on any declaration makes the
+//! definition replaceable at link time. Names are qualified source names;
+//! nothing is mangled yet, so an alias names its target as written in the
+//! source.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::ast::{Attribute, Decl, ExprKind, StorageClass, TranslationUnit};
+use crate::intern::Symbol;
+use crate::lexer::token::Span;
+use crate::types::TypeId;
+
+/// ELF symbol visibility, most visible first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Visibility {
+    /// Exported, and may be preempted by another module's definition.
+    #[default]
+    Default,
+    /// Exported, but references from inside the module bind to it.
+    Protected,
+    /// Not exported.
+    Hidden,
+    /// Not exported, and never called from outside the module indirectly.
+    Internal,
+}
+
+impl Visibility {
+    /// The visibility the attributes among `attrs` give, if any. An
+    /// attribute with a bad argument gives none; sema reports it.
+    pub fn from_attributes(attrs: &[Attribute]) -> Option<Self> {
+        attribute(attrs).and_then(|a| argument(a)?.parse().ok())
+    }
+
+    /// Whether a symbol with it is visible outside its module.
+    pub fn is_exported(self) -> bool { matches!(self, Visibility::Default | Visibility::Protected) }
+}
+
+impl FromStr for Visibility {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Visibility::Default),
+            "protected" => Ok(Visibility::Protected),
+            "hidden" => Ok(Visibility::Hidden),
+            "internal" => Ok(Visibility::Internal),
+            other => Err(format!("unknown visibility `{}` (expected default, protected, hidden or internal)", other)),
+        }
+    }
+}
+
+impl fmt::Display for Visibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Visibility::Default => "default",
+            Visibility::Protected => "protected",
+            Visibility::Hidden => "hidden",
+            Visibility::Internal => "internal",
+        })
+    }
+}
+
+/// The first `visibility` attribute among `attrs`.
+pub fn attribute<'t, 'a>(attrs: &'t [Attribute<'a>]) -> Option<&'t Attribute<'a>> { Attribute::find(attrs, &["visibility", "gnu::visibility"]) }
+
+/// The first `alias` attribute among `attrs`.
+pub fn alias<'t, 'a>(attrs: &'t [Attribute<'a>]) -> Option<&'t Attribute<'a>> { Attribute::find(attrs, &["alias", "gnu::alias"]) }
+
+/// The target the `alias` attribute among `attrs` names, if it names one.
+fn alias_target(attrs: &[Attribute]) -> Option<Symbol> { alias(attrs).and_then(argument).filter(|t| !t.is_empty()).map(|t| Symbol::intern(&t)) }
+
+/// Whether `attrs` include `weak`.
+pub fn is_weak(attrs: &[Attribute]) -> bool { Attribute::find(attrs, &["weak", "gnu::weak"]).is_some() }
+
+/// The string an attribute like `visibility("hidden")` is given, if it
+/// has exactly one.
+pub fn argument(a: &Attribute) -> Option<String> {
+    match a.args.as_deref() {
+        Some([arg]) => match &arg.kind {
+            ExprKind::StringLiteral(s) => Some(s.text()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Whether other object files can link against a symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    /// External linkage.
+    Global,
+    /// Internal linkage: `static`, in an unnamed namespace, or a `const`
+    /// variable at namespace scope.
+    Local,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Variable,
+}
+
+impl fmt::Display for SymbolKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SymbolKind::Function => "function",
+            SymbolKind::Variable => "variable",
+        })
+    }
+}
+
+/// One symbol the unit defines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefinedSymbol {
+    /// Qualified, like `geo::Point::norm`.
+    pub name: Symbol,
+    pub kind: SymbolKind,
+    pub ty: TypeId,
+    pub binding: Binding,
+    /// A definition elsewhere replaces this one when linked.
+    pub weak: bool,
+    pub visibility: Visibility,
+    /// The symbol this one is another name for, if it is an alias.
+    pub alias: Option<Symbol>,
+    pub span: Span,
+}
+
+impl DefinedSymbol {
+    /// A definition with default visibility, not weak or an alias, until
+    /// its attributes say otherwise.
+    fn new(name: Symbol, kind: SymbolKind, ty: TypeId, binding: Binding, span: Span) -> Self {
+        Self { name, kind, ty, binding, weak: false, visibility: Visibility::Default, alias: None, span }
+    }
+
+    /// Whether a shared library built from the unit exports the symbol.
+    pub fn is_exported(&self) -> bool { self.binding == Binding::Global && self.visibility.is_exported() }
+}
+
+/// `global default function f 'int (int)'`, `global weak hidden variable
+/// x 'int' alias of y`, or `local variable x 'int'` for a symbol whose
+/// visibility does not matter.
+impl fmt::Display for DefinedSymbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.binding == Binding::Global { "global " } else { "local " })?;
+        if self.weak { f.write_str("weak ")?; }
+        if self.binding == Binding::Global { write!(f, "{} ", self.visibility)?; }
+        write!(f, "{} {} '{}'", self.kind, self.name, self.ty)?;
+        if let Some(target) = self.alias { write!(f, " alias of {}", target)?; }
+        Ok(())
+    }
+}
+
+/// The symbols `tu` defines, in source order, where `default` is the
+/// visibility of those that are given none.
+pub fn defined(tu: &TranslationUnit, default: Visibility) -> Vec<DefinedSymbol> {
+    let mut collector = Collector { scopes: HashMap::new(), declared: HashMap::new(), symbols: Vec::new() };
+    collector.decls(&tu.decls, &Scope { name: String::new(), visibility: default, local: false });
+    collector.symbols
+}
+
+/// Where declarations are: the namespace or class they are qualified by,
+/// the visibility they get by default, and whether their names have
+/// internal linkage there.
+#[derive(Clone)]
+struct Scope {
+    name: String,
+    visibility: Visibility,
+    local: bool,
+}
+
+impl Scope {
+    fn qualify(&self, name: Symbol) -> String {
+        if self.name.is_empty() { name.as_str().to_string() } else { format!("{}::{}", self.name, name) }
+    }
+
+    fn nested(&self, name: String, attrs: &[Attribute]) -> Scope {
+        Scope { name, visibility: Visibility::from_attributes(attrs).unwrap_or(self.visibility), local: self.local }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Declared {
+    visibility: Option<Visibility>,
+    weak: bool,
+}
+
+struct Collector {
+    /// The scope each named namespace and class seen so far opens, by
+    /// qualified name, for members defined outside it.
+    scopes: HashMap<String, Scope>,
+    /// What declarations that are not definitions say, by qualified name,
+    /// for the definitions that follow.
+    declared: HashMap<String, Declared>,
+    symbols: Vec<DefinedSymbol>,
+}
+
+impl Collector {
+    fn decls(&mut self, decls: &[Decl], scope: &Scope) {
+        for decl in decls { self.decl(decl, scope); }
+    }
+
+    fn decl(&mut self, decl: &Decl, scope: &Scope) {
+        match decl {
+            Decl::Namespace(n) => {
+                let inner = match n.name {
+                    Some(name) => scope.nested(scope.qualify(name), &n.attrs),
+                    None => Scope { local: true, ..scope.nested(scope.name.clone(), &n.attrs) },
+                };
+                if n.name.is_some() { self.scopes.insert(inner.name.clone(), inner.clone()); }
+                self.decls(&n.decls, &inner);
+            }
+            Decl::Export(e) => self.decls(&e.decls, scope),
+            Decl::Class(c) => {
+                let (Some(name), Some(members)) = (c.name, &c.members) else { return };
+                let inner = scope.nested(scope.qualify(name), &c.attrs);
+                for member in members {
+                    match &member.decl {
+                        Decl::Class(_) => self.decl(&member.decl, &inner),
+                        Decl::Function(f) => self.declare(f.name, &f.attrs, &inner),
+                        Decl::Var(v) => self.declare(v.name, &v.attrs, &inner),
+                        _ => {}
+                    }
+                }
+                self.scopes.insert(inner.name.clone(), inner);
+            }
+            Decl::Function(f) => {
+                let defined = (f.body.is_some() && !f.specifiers.is_inline && !f.specifiers.is_constexpr) || alias_target(&f.attrs).is_some();
+                if !defined { return self.declare(f.name, &f.attrs, scope); }
+                let binding = if f.specifiers.storage == StorageClass::Static { Binding::Local } else { Binding::Global };
+                self.define(DefinedSymbol::new(f.name, SymbolKind::Function, f.ty, binding, f.name_span), &f.attrs, scope);
+            }
+            Decl::Var(v) => {
+                let declared_only = (v.specifiers.storage == StorageClass::Extern && v.init.is_none()) || v.specifiers.is_inline;
+                if declared_only && alias_target(&v.attrs).is_none() { return self.declare(v.name, &v.attrs, scope); }
+                let is_member = v.name.as_str().contains("::");
+                let local = v.specifiers.storage == StorageClass::Static
+                    || (!is_member && v.specifiers.storage != StorageClass::Extern && (v.ty.is_const() || v.specifiers.is_constexpr));
+                let binding = if local { Binding::Local } else { Binding::Global };
+                self.define(DefinedSymbol::new(v.name, SymbolKind::Variable, v.ty, binding, v.name_span), &v.attrs, scope);
+            }
+            _ => {}
+        }
+    }
+
+    /// Records what a declaration of `name` that does not define it says.
+    fn declare(&mut self, name: Symbol, attrs: &[Attribute], scope: &Scope) {
+        let earlier = self.declared.entry(scope.qualify(name)).or_default();
+        earlier.visibility = Visibility::from_attributes(attrs).or(earlier.visibility);
+        earlier.weak |= is_weak(attrs);
+    }
+
+    /// Adds `symbol`, defined in `scope` by a declaration with `attrs`.
+    /// Its name is qualified only if it is a member defined outside its
+    /// class or namespace, and its binding is that of the declaration.
+    fn define(&mut self, mut symbol: DefinedSymbol, attrs: &[Attribute], scope: &Scope) {
+        let qualified = scope.qualify(symbol.name);
+        // A member gets its class's or namespace's visibility and linkage,
+        // unless it or an earlier declaration gives it its own.
+        let owner = qualified.rsplit_once("::").and_then(|(owner, _)| self.scopes.get(owner)).unwrap_or(scope);
+        let earlier = self.declared.get(&qualified).copied().unwrap_or_default();
+        symbol.visibility = Visibility::from_attributes(attrs).or(earlier.visibility).unwrap_or(owner.visibility);
+        symbol.weak = is_weak(attrs) || earlier.weak;
+        symbol.alias = alias_target(attrs);
+        if owner.local { symbol.binding = Binding::Local; }
+        symbol.name = Symbol::intern(&qualified);
+        self.symbols.push(symbol);
+    }
+}
@@ -0,0 +1,143 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::session::Target;
+
+/// Name of the manifest written at the root of every sysroot.
+pub const MANIFEST: &str = "ruscom-sysroot.toml";
+
+/// Library files needed to link hosted C++ programs.
+const RUNTIME_LIB_PREFIXES: &[&str] = &["crt1.", "crti.", "crtn.", "Scrt1.", "libc.", "libc_nonshared.", "libm.", "libstdc++.", "libgcc", "libpthread.", "libdl."];
+
+/// What to snapshot into a sysroot.
+#[derive(Debug, Clone)]
+pub struct SysrootSpec {
+    pub target: Target,
+    /// Directories whose headers are copied, preserving their absolute layout.
+    pub include_dirs: Vec<PathBuf>,
+    /// Directories scanned for the runtime libraries in `RUNTIME_LIB_PREFIXES`.
+    pub lib_dirs: Vec<PathBuf>,
+}
+
+impl SysrootSpec {
+    /// The header and library directories of the host toolchain that exist.
+    pub fn host(target: Target) -> Self {
+        let multiarch = format!("{}-{}-gnu", target.arch, target.os);
+        let mut include_dirs = vec![PathBuf::from("/usr/local/include"), PathBuf::from("/usr/include")];
+        include_dirs.push(Path::new("/usr/include").join(&multiarch));
+        let lib_dirs = vec![
+            PathBuf::from("/usr/lib"),
+            Path::new("/usr/lib").join(&multiarch),
+            PathBuf::from("/lib"),
+            Path::new("/lib").join(&multiarch),
+        ];
+        Self {
+            target,
+            include_dirs: include_dirs.into_iter().filter(|d| d.is_dir()).collect(),
+            lib_dirs: lib_dirs.into_iter().filter(|d| d.is_dir()).collect(),
+        }
+    }
+}
+
+/// A sysroot on disk: a directory mirroring the host's `/usr/include` and
+/// library layout, plus a manifest listing the search directories relative
+/// to the sysroot so the whole tree can be moved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sysroot {
+    pub root: PathBuf,
+    pub triple: String,
+    pub include_dirs: Vec<PathBuf>,
+    pub lib_dirs: Vec<PathBuf>,
+}
+
+/// What `create` copied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CreateStats {
+    pub headers: usize,
+    pub libraries: usize,
+}
+
+/// Maps an absolute host path to its location inside the sysroot.
+fn rebase(dest: &Path, path: &Path) -> PathBuf {
+    dest.join(path.strip_prefix("/").unwrap_or(path))
+}
+
+fn copy_tree(src: &Path, dst: &Path, count: &mut usize) -> io::Result<()> {
+    fs::create_dir_all(dst)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let path = entry.path();
+        let target = dst.join(entry.file_name());
+        // Follow symlinks so the sysroot doesn't point back at the host.
+        let meta = fs::metadata(&path)?;
+        if meta.is_dir() {
+            copy_tree(&path, &target, count)?;
+        } else if meta.is_file() {
+            fs::copy(&path, &target)?;
+            *count += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Snapshots the headers and runtime libraries described by `spec` into `dest`.
+pub fn create(spec: &SysrootSpec, dest: &Path) -> io::Result<CreateStats> {
+    let mut stats = CreateStats::default();
+    fs::create_dir_all(dest)?;
+    // A multiarch directory may sit inside another include dir; copying the
+    // parent covers it, so only copy top-level ones.
+    for dir in &spec.include_dirs {
+        if spec.include_dirs.iter().any(|other| other != dir && dir.starts_with(other)) { continue; }
+        copy_tree(dir, &rebase(dest, dir), &mut stats.headers)?;
+    }
+    for dir in &spec.lib_dirs {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !RUNTIME_LIB_PREFIXES.iter().any(|p| name.starts_with(p)) { continue; }
+            let path = entry.path();
+            if !path.is_file() { continue; }
+            let target_dir = rebase(dest, dir);
+            fs::create_dir_all(&target_dir)?;
+            fs::copy(&path, target_dir.join(&name))?;
+            stats.libraries += 1;
+        }
+    }
+
+    let mut manifest = format!("triple = \"{}\"\n", spec.target.triple());
+    let list = |dirs: &[PathBuf]| {
+        dirs.iter().map(|d| format!("\"{}\"", d.strip_prefix("/").unwrap_or(d).display())).collect::<Vec<_>>().join(", ")
+    };
+    manifest.push_str(&format!("include_dirs = [{}]\n", list(&spec.include_dirs)));
+    manifest.push_str(&format!("lib_dirs = [{}]\n", list(&spec.lib_dirs)));
+    fs::write(dest.join(MANIFEST), manifest)?;
+    Ok(stats)
+}
+
+fn parse_list(value: &str) -> Vec<PathBuf> {
+    value.trim().trim_start_matches('[').trim_end_matches(']')
+        .split(',')
+        .map(|s| s.trim().trim_matches('"'))
+        .filter(|s| !s.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+impl Sysroot {
+    /// Reads the manifest of a sysroot created by `create`.
+    pub fn open(root: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(root.join(MANIFEST))?;
+        let mut sysroot = Sysroot { root: root.to_path_buf(), triple: String::new(), include_dirs: Vec::new(), lib_dirs: Vec::new() };
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else { continue };
+            match key.trim() {
+                "triple" => sysroot.triple = value.trim().trim_matches('"').to_string(),
+                "include_dirs" => sysroot.include_dirs = parse_list(value).into_iter().map(|d| root.join(d)).collect(),
+                "lib_dirs" => sysroot.lib_dirs = parse_list(value).into_iter().map(|d| root.join(d)).collect(),
+                _ => {}
+            }
+        }
+        Ok(sysroot)
+    }
+}
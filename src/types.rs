@@ -0,0 +1,327 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::intern::Symbol;
+
+/// An argument of a `Type::Specialization`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TemplateArgument {
+    Type(TypeId),
+    /// A non-type argument, spelled as written.
+    Value(String),
+}
+
+impl fmt::Display for TemplateArgument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateArgument::Type(ty) => write!(f, "{}", ty),
+            TemplateArgument::Value(text) => f.write_str(text),
+        }
+    }
+}
+
+/// Handle to an interned `Type`. Structurally equal types share an id, so
+/// type equality is an integer comparison. Like symbols, types live in a
+/// per-thread table and are freed with the `TuArena` they were interned
+/// under, and are neither `Send` nor `Sync`:
+///
+/// ```compile_fail
+/// fn share<T: Sync>(_: T) {}
+/// share(ruscom::types::TypeId::intern(ruscom::types::Type::Int));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TypeId(u32, PhantomData<*const ()>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Type {
+    Void,
+    Bool,
+    Char,
+    SignedChar,
+    UnsignedChar,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+    Long,
+    UnsignedLong,
+    LongLong,
+    UnsignedLongLong,
+    /// `wchar_t`, whose size and signedness depend on the target.
+    WChar,
+    Char8,
+    Char16,
+    Char32,
+    Float,
+    Double,
+    LongDouble,
+    /// The `auto` placeholder, standing for a type deduced from an
+    /// initializer.
+    Auto,
+    /// A class, enum or typedef name not yet resolved further.
+    Named(Symbol),
+    /// A template specialization like `vector<int>`, not yet instantiated.
+    Specialization(Symbol, Vec<TemplateArgument>),
+    Pointer(TypeId),
+    Reference(TypeId),
+    RvalueReference(TypeId),
+    /// An array with its bound, if known.
+    Array(TypeId, Option<u64>),
+    Function { ret: TypeId, params: Vec<TypeId>, variadic: bool },
+    /// `const`/`volatile` applied to a type that is neither qualified nor a
+    /// reference. Build these with `TypeId::qualified`, which keeps them
+    /// canonical.
+    Qualified(TypeId, Qualifiers),
+}
+
+/// A set of cv-qualifiers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Qualifiers {
+    pub is_const: bool,
+    pub is_volatile: bool,
+}
+
+impl Qualifiers {
+    pub const NONE: Qualifiers = Qualifiers { is_const: false, is_volatile: false };
+    pub const CONST: Qualifiers = Qualifiers { is_const: true, is_volatile: false };
+    pub const VOLATILE: Qualifiers = Qualifiers { is_const: false, is_volatile: true };
+    pub const CONST_VOLATILE: Qualifiers = Qualifiers { is_const: true, is_volatile: true };
+
+    pub fn is_empty(self) -> bool { self == Self::NONE }
+
+    pub fn union(self, other: Qualifiers) -> Qualifiers {
+        Qualifiers { is_const: self.is_const || other.is_const, is_volatile: self.is_volatile || other.is_volatile }
+    }
+
+    /// Whether `self` has every qualifier `other` has ("at least as
+    /// cv-qualified").
+    pub fn contains(self, other: Qualifiers) -> bool {
+        (self.is_const || !other.is_const) && (self.is_volatile || !other.is_volatile)
+    }
+}
+
+impl fmt::Display for Qualifiers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.is_const, self.is_volatile) {
+            (true, true) => f.write_str("const volatile"),
+            (true, false) => f.write_str("const"),
+            (false, true) => f.write_str("volatile"),
+            (false, false) => Ok(()),
+        }
+    }
+}
+
+#[derive(Default)]
+struct TypeTable {
+    map: HashMap<Type, TypeId>,
+    types: BTreeMap<u32, Type>,
+    next: u32,
+}
+
+thread_local! {
+    static TABLE: RefCell<TypeTable> = RefCell::default();
+}
+
+impl TypeId {
+    pub fn intern(ty: Type) -> TypeId {
+        TABLE.with_borrow_mut(|table| {
+            if let Some(&id) = table.map.get(&ty) { return id; }
+            let id = TypeId(table.next, PhantomData);
+            table.next += 1;
+            table.types.insert(id.0, ty.clone());
+            table.map.insert(ty, id);
+            id
+        })
+    }
+
+    /// The type `self` stands for.
+    ///
+    /// # Panics
+    ///
+    /// If the arena `self` was interned under has been dropped or reset.
+    pub fn get(self) -> Type {
+        let ty = TABLE.with_borrow(|table| table.types.get(&self.0).cloned());
+        ty.unwrap_or_else(|| panic!("type {} outlived the translation unit it was interned for", self.0))
+    }
+
+    pub fn as_u32(self) -> u32 { self.0 }
+
+    /// This type with `quals` added. Qualifiers merge into an existing
+    /// qualified type and are dropped on references and functions, as when
+    /// they come in through a typedef.
+    pub fn qualified(self, quals: Qualifiers) -> TypeId {
+        match self.get() {
+            _ if quals.is_empty() => self,
+            Type::Reference(_) | Type::RvalueReference(_) | Type::Function { .. } => self,
+            // A qualified array is an array of qualified elements.
+            Type::Array(elem, bound) => TypeId::intern(Type::Array(elem.qualified(quals), bound)),
+            Type::Qualified(inner, q) => TypeId::intern(Type::Qualified(inner, q.union(quals))),
+            _ => TypeId::intern(Type::Qualified(self, quals)),
+        }
+    }
+
+    /// The top-level cv-qualifiers of this type.
+    pub fn qualifiers(self) -> Qualifiers {
+        match self.get() {
+            Type::Qualified(_, q) => q,
+            _ => Qualifiers::NONE,
+        }
+    }
+
+    /// This type without its top-level cv-qualifiers.
+    pub fn unqualified(self) -> TypeId {
+        match self.get() {
+            Type::Qualified(inner, _) => inner,
+            _ => self,
+        }
+    }
+
+    pub fn is_const(self) -> bool { self.qualifiers().is_const }
+
+    /// Whether a prvalue of type `self` converts to `to` by adding
+    /// qualifiers at any pointer level ([conv.qual]). Below the top level,
+    /// each qualifier added requires `const` at every level above it, so
+    /// `int**` does not convert to `const int**` but does to
+    /// `const int* const*`.
+    pub fn converts_by_qualification(self, to: TypeId) -> bool {
+        let (mut from, mut to) = (self.unqualified(), to.unqualified());
+        let mut all_const = true;
+        loop {
+            if from == to { return true; }
+            let (Type::Pointer(f), Type::Pointer(t)) = (from.get(), to.get()) else { return false };
+            let (fq, tq) = (f.qualifiers(), t.qualifiers());
+            if !tq.contains(fq) { return false; }
+            if fq != tq && !all_const { return false; }
+            all_const &= tq.is_const;
+            from = f.unqualified();
+            to = t.unqualified();
+        }
+    }
+
+    /// Whether a reference to `self` can bind directly to an lvalue of type
+    /// `init`: the same type, with `self` at least as cv-qualified.
+    pub fn reference_binds_to(self, init: TypeId) -> bool {
+        self.unqualified() == init.unqualified() && self.qualifiers().contains(init.qualifiers())
+    }
+
+    /// The smallest and largest values of an integral type, for an LP64
+    /// target with signed `char`; `None` for any other type.
+    pub fn integer_range(self) -> Option<(i128, i128)> {
+        let (bits, signed) = match self.unqualified().get() {
+            Type::Bool => return Some((0, 1)),
+            Type::Char | Type::SignedChar => (8, true),
+            Type::UnsignedChar => (8, false),
+            Type::Short => (16, true),
+            Type::UnsignedShort => (16, false),
+            Type::Int => (32, true),
+            Type::UnsignedInt => (32, false),
+            Type::Long | Type::LongLong => (64, true),
+            Type::UnsignedLong | Type::UnsignedLongLong => (64, false),
+            // As on Linux and macOS; Windows has a 16-bit unsigned one.
+            Type::WChar => (32, true),
+            Type::Char8 => (8, false),
+            Type::Char16 => (16, false),
+            Type::Char32 => (32, false),
+            _ => return None,
+        };
+        Some(if signed { (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1) } else { (0, (1i128 << bits) - 1) })
+    }
+}
+
+/// The id the next type interned on this thread gets.
+pub(crate) fn watermark() -> u32 { TABLE.with_borrow(|table| table.next) }
+
+/// Frees the types interned on this thread since `watermark`.
+pub(crate) fn release(watermark: u32) {
+    TABLE.with_borrow_mut(|table| {
+        for ty in table.types.split_off(&watermark).into_values() { table.map.remove(&ty); }
+    });
+}
+
+impl Type {
+    /// The C++ spelling of a declarator of this type around `inner`, the
+    /// part already spelled (e.g. `*` for a pointer to this type).
+    fn spell(&self, inner: &str) -> String {
+        let base = match self {
+            Type::Void => "void",
+            Type::Bool => "bool",
+            Type::Char => "char",
+            Type::SignedChar => "signed char",
+            Type::UnsignedChar => "unsigned char",
+            Type::Short => "short",
+            Type::UnsignedShort => "unsigned short",
+            Type::Int => "int",
+            Type::UnsignedInt => "unsigned int",
+            Type::Long => "long",
+            Type::UnsignedLong => "unsigned long",
+            Type::LongLong => "long long",
+            Type::UnsignedLongLong => "unsigned long long",
+            Type::WChar => "wchar_t",
+            Type::Char8 => "char8_t",
+            Type::Char16 => "char16_t",
+            Type::Char32 => "char32_t",
+            Type::Float => "float",
+            Type::Double => "double",
+            Type::LongDouble => "long double",
+            Type::Auto => "auto",
+            Type::Named(name) => &name.as_str(),
+            Type::Specialization(name, args) => {
+                let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+                let base = format!("{}<{}>", name, args.join(", "));
+                return if inner.starts_with('(') { format!("{} {}", base, inner) } else { format!("{}{}", base, inner) };
+            }
+            Type::Pointer(t) => return t.get().spell(&wrap(*t, format!("*{}{}", gap(inner), inner))),
+            Type::Reference(t) => return t.get().spell(&wrap(*t, format!("&{}{}", gap(inner), inner))),
+            Type::RvalueReference(t) => return t.get().spell(&wrap(*t, format!("&&{}{}", gap(inner), inner))),
+            Type::Qualified(t, quals) => return match t.get() {
+                Type::Pointer(p) => p.get().spell(&wrap(p, format!("* {}{}", quals, inner))),
+                other => format!("{} {}", quals, other.spell(inner)),
+            },
+            Type::Array(t, bound) => {
+                let bound = bound.map(|n| n.to_string()).unwrap_or_default();
+                return t.get().spell(&format!("{}[{}]", inner, bound));
+            }
+            Type::Function { ret, params, variadic } => {
+                let mut list: Vec<String> = params.iter().map(|p| p.to_string()).collect();
+                if *variadic { list.push("...".into()); }
+                return ret.get().spell(&format!("{}({})", inner, list.join(", ")));
+            }
+        };
+        if inner.starts_with('(') { format!("{} {}", base, inner) } else { format!("{}{}", base, inner) }
+    }
+}
+
+/// A space before a parenthesized declarator, as in `int* (*)[2]`, but
+/// not before a parameter list.
+fn gap(inner: &str) -> &'static str {
+    if inner.starts_with("(*") || inner.starts_with("(&") { " " } else { "" }
+}
+
+/// Parenthesizes `inner` when it applies to an array or function type, as
+/// in `int (*)[4]`.
+fn wrap(ty: TypeId, inner: String) -> String {
+    match ty.get() {
+        Type::Array(..) | Type::Function { .. } => format!("({})", inner),
+        _ => inner,
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.spell(""))
+    }
+}
+
+impl fmt::Display for TypeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.get())
+    }
+}
+
+impl fmt::Debug for TypeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TypeId({}, `{}`)", self.0, self)
+    }
+}
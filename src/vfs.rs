@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+/// Source of file contents for a compilation.
+///
+/// Every phase reads files through this trait rather than `std::fs`, so the
+/// compiler can run over unsaved editor buffers, in-memory test fixtures or
+/// archives without touching the disk.
+pub trait FileSystem: Send + Sync {
+    fn read_to_string(&self, path: &Path) -> io::Result<String>;
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.read_to_string(path).map(String::into_bytes)
+    }
+
+    fn is_file(&self, path: &Path) -> bool;
+
+    /// Last modification time, for backends that track one.
+    fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+        let _ = path;
+        Err(io::Error::new(io::ErrorKind::Unsupported, "modification times are not tracked"))
+    }
+}
+
+/// Normalizes `a/./b/../c` to `a/c` without consulting the disk, so
+/// in-memory backends match paths the way the real file system would.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for comp in path.components() {
+        match comp {
+            Component::CurDir => {}
+            // `..` cancels a name before it, but not another `..`, and
+            // goes nowhere above the root.
+            Component::ParentDir => match out.components().next_back() {
+                Some(Component::Normal(_)) => { out.pop(); }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => out.push(".."),
+            },
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("no such file: {}", path.display()))
+}
+
+/// The host file system.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RealFileSystem;
+
+impl FileSystem for RealFileSystem {
+    fn read_to_string(&self, path: &Path) -> io::Result<String> { std::fs::read_to_string(path) }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> { std::fs::read(path) }
+
+    fn is_file(&self, path: &Path) -> bool { path.is_file() }
+
+    fn modified(&self, path: &Path) -> io::Result<SystemTime> { std::fs::metadata(path)?.modified() }
+}
+
+/// Files held entirely in memory.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryFileSystem {
+    files: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl MemoryFileSystem {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn insert(&mut self, path: impl AsRef<Path>, contents: impl Into<Vec<u8>>) {
+        self.files.insert(normalize(path.as_ref()), contents.into());
+    }
+
+    pub fn remove(&mut self, path: impl AsRef<Path>) -> bool {
+        self.files.remove(&normalize(path.as_ref())).is_some()
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &Path> { self.files.keys().map(PathBuf::as_path) }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files.get(&normalize(path)).cloned().ok_or_else(|| not_found(path))
+    }
+
+    fn is_file(&self, path: &Path) -> bool { self.files.contains_key(&normalize(path)) }
+}
+
+/// Read-only view of the regular files in an uncompressed (ustar) tar archive.
+#[derive(Debug, Clone)]
+pub struct ArchiveFileSystem {
+    files: MemoryFileSystem,
+}
+
+impl ArchiveFileSystem {
+    pub fn open(path: &Path) -> io::Result<Self> { Self::from_tar_bytes(&std::fs::read(path)?) }
+
+    pub fn from_tar_bytes(data: &[u8]) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        let field = |bytes: &[u8]| -> String {
+            let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+            String::from_utf8_lossy(&bytes[..end]).into_owned()
+        };
+        let mut files = MemoryFileSystem::new();
+        let mut off = 0;
+        while off + 512 <= data.len() {
+            let header = &data[off..off + 512];
+            if header.iter().all(|&b| b == 0) { break; }
+            let name = field(&header[0..100]);
+            let prefix = field(&header[345..500]);
+            let size_field = field(&header[124..136]);
+            let size = usize::from_str_radix(size_field.trim(), 8).map_err(|_| invalid("bad tar entry size"))?;
+            let typeflag = header[156];
+            let body = off + 512;
+            if body + size > data.len() { return Err(invalid("truncated tar archive")); }
+            if typeflag == b'0' || typeflag == 0 {
+                let full = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+                files.insert(full, data[body..body + size].to_vec());
+            }
+            off = body + size.div_ceil(512) * 512;
+        }
+        Ok(Self { files })
+    }
+}
+
+impl FileSystem for ArchiveFileSystem {
+    fn read_to_string(&self, path: &Path) -> io::Result<String> { self.files.read_to_string(path) }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> { self.files.read(path) }
+
+    fn is_file(&self, path: &Path) -> bool { self.files.is_file(path) }
+}
+
+#[derive(Debug, Clone)]
+struct OverlayFile {
+    contents: String,
+    modified: SystemTime,
+}
+
+/// A file system where selected paths are shadowed by in-memory contents.
+///
+/// This is how unsaved editor buffers are compiled: the IDE registers each
+/// dirty buffer as an overlay and everything else falls through to `base`.
+/// Overlays can be updated through a shared reference while compilations
+/// hold the file system. Paths are made absolute against the current
+/// directory, so an overlay is found by a relative path and an absolute one
+/// alike.
+pub struct OverlayFileSystem {
+    base: Arc<dyn FileSystem>,
+    overlays: RwLock<HashMap<PathBuf, OverlayFile>>,
+    current_dir: PathBuf,
+}
+
+impl OverlayFileSystem {
+    pub fn new(base: Arc<dyn FileSystem>) -> Self {
+        let current_dir = std::env::current_dir().unwrap_or_default();
+        Self { base, overlays: RwLock::new(HashMap::new()), current_dir }
+    }
+
+    /// This file system, with relative paths resolved against `dir`
+    /// instead of the process's current directory.
+    pub fn with_current_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.current_dir = dir.into();
+        self
+    }
+
+    /// The key of the overlay for `path`: absolute and normalized.
+    fn key(&self, path: &Path) -> PathBuf { normalize(&self.current_dir.join(path)) }
+
+    /// Shadows `path` with `contents`, stamped with the current time.
+    pub fn set_overlay(&self, path: impl AsRef<Path>, contents: impl Into<String>) {
+        self.set_overlay_at(path, contents, SystemTime::now());
+    }
+
+    pub fn set_overlay_at(&self, path: impl AsRef<Path>, contents: impl Into<String>, modified: SystemTime) {
+        let file = OverlayFile { contents: contents.into(), modified };
+        self.overlays.write().unwrap_or_else(|e| e.into_inner()).insert(self.key(path.as_ref()), file);
+    }
+
+    /// Stops shadowing `path`; returns whether it was overlaid.
+    pub fn remove_overlay(&self, path: impl AsRef<Path>) -> bool {
+        self.overlays.write().unwrap_or_else(|e| e.into_inner()).remove(&self.key(path.as_ref())).is_some()
+    }
+
+    pub fn is_overlaid(&self, path: &Path) -> bool {
+        self.overlays.read().unwrap_or_else(|e| e.into_inner()).contains_key(&self.key(path))
+    }
+
+    /// Adds overlays from `path=file` specs (the `--overlay` CLI syntax),
+    /// reading each replacement `file` through the base file system.
+    pub fn add_specs(&self, specs: &[String]) -> io::Result<()> {
+        for spec in specs {
+            let (path, file) = spec.split_once('=').ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, format!("overlay `{}` is not of the form path=file", spec))
+            })?;
+            let contents = self.base.read_to_string(Path::new(file))?;
+            let modified = self.base.modified(Path::new(file)).unwrap_or_else(|_| SystemTime::now());
+            self.set_overlay_at(path, contents, modified);
+        }
+        Ok(())
+    }
+
+    fn overlay(&self, path: &Path) -> Option<OverlayFile> {
+        self.overlays.read().unwrap_or_else(|e| e.into_inner()).get(&self.key(path)).cloned()
+    }
+}
+
+impl FileSystem for OverlayFileSystem {
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        match self.overlay(path) {
+            Some(file) => Ok(file.contents),
+            None => self.base.read_to_string(path),
+        }
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match self.overlay(path) {
+            Some(file) => Ok(file.contents.into_bytes()),
+            None => self.base.read(path),
+        }
+    }
+
+    fn is_file(&self, path: &Path) -> bool { self.is_overlaid(path) || self.base.is_file(path) }
+
+    fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+        match self.overlay(path) {
+            Some(file) => Ok(file.modified),
+            None => self.base.modified(path),
+        }
+    }
+}
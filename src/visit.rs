@@ -0,0 +1,231 @@
+//! Traversal of the AST: `Visitor` over shared references and
+//! `MutVisitor` over mutable ones.
+//!
+//! Each `visit_*` method walks into the node's children by default, so an
+//! implementation overrides only the nodes it cares about and calls the
+//! matching `walk_*` method to keep going below them, or not, to skip the
+//! subtree. Children are visited in source order. Types are interned
+//! `TypeId`s, not nodes, and are not visited.
+
+use crate::ast::{
+    Attribute, Block, ClassDecl, Decl, Expr, ExprKind, FunctionDecl, ParamDecl, Requirement, Stmt, StmtKind, TemplateArg,
+    TemplateParam, TemplateParamKind, TranslationUnit, UnsupportedConstruct, VarDecl,
+};
+
+// Both traits are written once, with `$m` standing for `mut` or nothing.
+macro_rules! visitor {
+    ($(#[$doc:meta])* $name:ident, $($m:ident)?) => {
+        $(#[$doc])*
+        pub trait $name {
+            fn visit_translation_unit(&mut self, tu: &$($m)? TranslationUnit) { self.walk_translation_unit(tu) }
+            fn visit_decl(&mut self, decl: &$($m)? Decl) { self.walk_decl(decl) }
+            fn visit_function(&mut self, f: &$($m)? FunctionDecl) { self.walk_function(f) }
+            fn visit_var(&mut self, v: &$($m)? VarDecl) { self.walk_var(v) }
+            fn visit_class(&mut self, c: &$($m)? ClassDecl) { self.walk_class(c) }
+            fn visit_param(&mut self, p: &$($m)? ParamDecl) { self.walk_param(p) }
+            fn visit_template_param(&mut self, p: &$($m)? TemplateParam) { self.walk_template_param(p) }
+            fn visit_template_arg(&mut self, arg: &$($m)? TemplateArg) { self.walk_template_arg(arg) }
+            fn visit_attribute(&mut self, a: &$($m)? Attribute) { self.walk_attribute(a) }
+            fn visit_block(&mut self, block: &$($m)? Block) { self.walk_block(block) }
+            fn visit_stmt(&mut self, stmt: &$($m)? Stmt) { self.walk_stmt(stmt) }
+            fn visit_expr(&mut self, e: &$($m)? Expr) { self.walk_expr(e) }
+            fn visit_requirement(&mut self, r: &$($m)? Requirement) { self.walk_requirement(r) }
+            /// A declaration or statement the parser skipped, which has no
+            /// children.
+            fn visit_unsupported(&mut self, _u: &$($m)? UnsupportedConstruct) {}
+
+            fn walk_translation_unit(&mut self, tu: &$($m)? TranslationUnit) {
+                for decl in &$($m)? tu.decls { self.visit_decl(decl); }
+            }
+
+            fn walk_decl(&mut self, decl: &$($m)? Decl) {
+                match decl {
+                    Decl::Var(v) => self.visit_var(v),
+                    Decl::Decomposition(d) => {
+                        for a in &$($m)? d.attrs { self.visit_attribute(a); }
+                        for b in &$($m)? d.bindings {
+                            for a in &$($m)? b.attrs { self.visit_attribute(a); }
+                        }
+                        if let Some(init) = &$($m)? d.init { self.visit_expr(init); }
+                    }
+                    Decl::Function(f) => self.visit_function(f),
+                    Decl::Typedef(t) => {
+                        for a in &$($m)? t.attrs { self.visit_attribute(a); }
+                    }
+                    Decl::Namespace(n) => {
+                        for a in &$($m)? n.attrs { self.visit_attribute(a); }
+                        for decl in &$($m)? n.decls { self.visit_decl(decl); }
+                    }
+                    Decl::Class(c) => self.visit_class(c),
+                    Decl::Enum(e) => {
+                        for a in &$($m)? e.attrs { self.visit_attribute(a); }
+                        for enumerator in (&$($m)? e.enumerators).into_iter().flatten() {
+                            for a in &$($m)? enumerator.attrs { self.visit_attribute(a); }
+                            if let Some(value) = &$($m)? enumerator.value { self.visit_expr(value); }
+                        }
+                    }
+                    Decl::Template(t) => {
+                        for p in &$($m)? t.params { self.visit_template_param(p); }
+                        if let Some(requires) = &$($m)? t.requires { self.visit_expr(requires); }
+                        self.visit_decl(&$($m)? t.decl);
+                    }
+                    Decl::Concept(c) => self.visit_expr(&$($m)? c.constraint),
+                    Decl::StaticAssert(a) => self.visit_expr(&$($m)? a.cond),
+                    Decl::Export(e) => {
+                        for decl in &$($m)? e.decls { self.visit_decl(decl); }
+                    }
+                    Decl::Unsupported(u) => self.visit_unsupported(u),
+                    Decl::Using(_) | Decl::UsingDirective(_) | Decl::Module(_) | Decl::Import(_) | Decl::Invalid(_) => {}
+                }
+            }
+
+            fn walk_function(&mut self, f: &$($m)? FunctionDecl) {
+                for a in &$($m)? f.attrs { self.visit_attribute(a); }
+                for p in &$($m)? f.params { self.visit_param(p); }
+                if let Some(requires) = &$($m)? f.requires { self.visit_expr(requires); }
+                for init in &$($m)? f.initializers {
+                    for arg in &$($m)? init.args { self.visit_expr(arg); }
+                }
+                if let Some(body) = &$($m)? f.body { self.visit_block(body); }
+            }
+
+            fn walk_var(&mut self, v: &$($m)? VarDecl) {
+                for a in &$($m)? v.attrs { self.visit_attribute(a); }
+                if let Some(init) = &$($m)? v.init { self.visit_expr(init); }
+            }
+
+            fn walk_class(&mut self, c: &$($m)? ClassDecl) {
+                for a in &$($m)? c.attrs { self.visit_attribute(a); }
+                for member in (&$($m)? c.members).into_iter().flatten() { self.visit_decl(&$($m)? member.decl); }
+            }
+
+            fn walk_param(&mut self, p: &$($m)? ParamDecl) {
+                for a in &$($m)? p.attrs { self.visit_attribute(a); }
+                if let Some(default) = &$($m)? p.default { self.visit_expr(default); }
+            }
+
+            fn walk_template_param(&mut self, p: &$($m)? TemplateParam) {
+                if let TemplateParamKind::Template(params) = &$($m)? p.kind {
+                    for p in params { self.visit_template_param(p); }
+                }
+                if let Some(constraint) = &$($m)? p.constraint { self.visit_expr(constraint); }
+                if let Some(default) = &$($m)? p.default { self.visit_template_arg(default); }
+            }
+
+            fn walk_template_arg(&mut self, arg: &$($m)? TemplateArg) {
+                if let TemplateArg::Expr(e) = arg { self.visit_expr(e); }
+            }
+
+            fn walk_attribute(&mut self, a: &$($m)? Attribute) {
+                for arg in (&$($m)? a.args).into_iter().flatten() { self.visit_expr(arg); }
+            }
+
+            fn walk_block(&mut self, block: &$($m)? Block) {
+                for stmt in &$($m)? block.stmts { self.visit_stmt(stmt); }
+            }
+
+            fn walk_stmt(&mut self, stmt: &$($m)? Stmt) {
+                match &$($m)? stmt.kind {
+                    StmtKind::Compound(block) => self.visit_block(block),
+                    StmtKind::Expr(e) | StmtKind::Return(Some(e)) | StmtKind::CoReturn(Some(e)) => self.visit_expr(e),
+                    StmtKind::Decl(decls) => {
+                        for decl in decls { self.visit_decl(decl); }
+                    }
+                    StmtKind::If { cond, then, otherwise } => {
+                        self.visit_expr(cond);
+                        self.visit_stmt(then);
+                        if let Some(otherwise) = otherwise { self.visit_stmt(otherwise); }
+                    }
+                    StmtKind::While { cond, body } | StmtKind::Switch { cond, body } | StmtKind::Case { value: cond, body } => {
+                        self.visit_expr(cond);
+                        self.visit_stmt(body);
+                    }
+                    StmtKind::DoWhile { body, cond } => {
+                        self.visit_stmt(body);
+                        self.visit_expr(cond);
+                    }
+                    StmtKind::For { init, cond, step, body } => {
+                        if let Some(init) = init { self.visit_stmt(init); }
+                        if let Some(cond) = cond { self.visit_expr(cond); }
+                        if let Some(step) = step { self.visit_expr(step); }
+                        self.visit_stmt(body);
+                    }
+                    StmtKind::RangeFor { init, decl, range, body } => {
+                        if let Some(init) = init { self.visit_stmt(init); }
+                        self.visit_decl(decl);
+                        self.visit_expr(range);
+                        self.visit_stmt(body);
+                    }
+                    StmtKind::Default(body) => self.visit_stmt(body),
+                    StmtKind::Attributed { attrs, stmt } => {
+                        for a in attrs { self.visit_attribute(a); }
+                        self.visit_stmt(stmt);
+                    }
+                    StmtKind::Unsupported(u) => self.visit_unsupported(u),
+                    StmtKind::Null | StmtKind::Break | StmtKind::Continue | StmtKind::Return(None) | StmtKind::CoReturn(None)
+                    | StmtKind::Invalid => {}
+                }
+            }
+
+            fn walk_expr(&mut self, e: &$($m)? Expr) {
+                match &$($m)? e.kind {
+                    ExprKind::TemplateId { args, .. } => {
+                        for arg in args { self.visit_template_arg(arg); }
+                    }
+                    ExprKind::Paren(inner) | ExprKind::Unary { operand: inner, .. } | ExprKind::Member { base: inner, .. }
+                    | ExprKind::Cast { operand: inner, .. } | ExprKind::Designated { init: inner, .. } | ExprKind::UserLiteral { literal: inner, .. }
+                    | ExprKind::CoAwait(inner) | ExprKind::CoYield(inner) | ExprKind::SizeofExpr(inner) => self.visit_expr(inner),
+                    ExprKind::Binary { lhs, rhs, .. } => {
+                        self.visit_expr(lhs);
+                        self.visit_expr(rhs);
+                    }
+                    ExprKind::Conditional { cond, then, otherwise } => {
+                        self.visit_expr(cond);
+                        self.visit_expr(then);
+                        self.visit_expr(otherwise);
+                    }
+                    ExprKind::Call { callee, args } => {
+                        self.visit_expr(callee);
+                        for arg in args { self.visit_expr(arg); }
+                    }
+                    ExprKind::Index { base, index } => {
+                        self.visit_expr(base);
+                        self.visit_expr(index);
+                    }
+                    ExprKind::Construct { args: items, .. } | ExprKind::InitList(items) | ExprKind::ParenList(items) => {
+                        for item in items { self.visit_expr(item); }
+                    }
+                    ExprKind::Requires { params, requirements } => {
+                        for p in params { self.visit_param(p); }
+                        for r in requirements { self.visit_requirement(r); }
+                    }
+                    ExprKind::IntLiteral(..) | ExprKind::FloatLiteral(_) | ExprKind::CharLiteral(_) | ExprKind::StringLiteral(_)
+                    | ExprKind::BoolLiteral(_) | ExprKind::Nullptr | ExprKind::This | ExprKind::Name(_) | ExprKind::Embed(_)
+                    | ExprKind::SizeofType(_) | ExprKind::Alignof(_) => {}
+                }
+            }
+
+            fn walk_requirement(&mut self, r: &$($m)? Requirement) {
+                match r {
+                    Requirement::Simple(e) | Requirement::Nested(e) => self.visit_expr(e),
+                    Requirement::Compound { expr, constraint, .. } => {
+                        self.visit_expr(expr);
+                        if let Some(constraint) = constraint { self.visit_expr(constraint); }
+                    }
+                    Requirement::Type(..) => {}
+                }
+            }
+        }
+    };
+}
+
+visitor!(
+    /// Visits the nodes of a tree it cannot change, as checks and
+    /// analyses do.
+    Visitor,
+);
+
+visitor!(
+    /// Visits the nodes of a tree it may rewrite in place.
+    MutVisitor, mut
+);
@@ -0,0 +1,417 @@
+mod common;
+
+use ruscom::abi::Interface;
+use ruscom::api;
+use ruscom::bindgen;
+use ruscom::cheader;
+use ruscom::ast::{FunctionKind, TranslationUnit};
+use ruscom::intern::Symbol;
+use ruscom::mangle;
+use ruscom::session::{Options, Session};
+use ruscom::symbols::Visibility;
+use ruscom::types::{Qualifiers, TemplateArgument, Type, TypeId};
+
+/// Parses `source`, which must have no syntax errors, as `lib.h` beside
+/// `dep.h`.
+fn parse(source: &str) -> (Session, TranslationUnit<'static>) {
+    let (s, tu) = common::parse_files(&[("lib.h", source), ("dep.h", DEP)], Options::default());
+    assert!(s.diagnostics.is_empty(), "{:?}", s.diagnostics.iter().collect::<Vec<_>>());
+    (s, tu)
+}
+
+const DEP: &str = "typedef unsigned short ident;\nnamespace dep { struct Base { virtual ~Base(); int tag; }; int helper(); }\n";
+
+fn interface(source: &str) -> Interface {
+    let (s, tu) = parse(source);
+    Interface::of(&tu, &s.target, Visibility::Default)
+}
+
+fn diff(old: &str, new: &str) -> Vec<String> { interface(old).diff(&interface(new)).iter().map(ToString::to_string).collect() }
+
+fn named(name: &str) -> TypeId { TypeId::intern(Type::Named(Symbol::intern(name))) }
+
+fn function(ret: Type, params: Vec<TypeId>, variadic: bool) -> TypeId { TypeId::intern(Type::Function { ret: TypeId::intern(ret), params, variadic }) }
+
+#[test]
+fn names_are_mangled_as_the_itanium_abi_does() {
+    let point = named("geo::Point");
+    let const_ref = |ty: TypeId| TypeId::intern(Type::Reference(ty.qualified(Qualifiers::CONST)));
+    let pointer = |ty: Type| TypeId::intern(Type::Pointer(TypeId::intern(ty)));
+    let const_char = TypeId::intern(Type::Pointer(TypeId::intern(Type::Char).qualified(Qualifiers::CONST)));
+    let vector = TypeId::intern(Type::Specialization(Symbol::intern("std::vector"), vec![TemplateArgument::Type(TypeId::intern(Type::Int))]));
+    let cases = [
+        (mangle::function("geo::Point::norm", FunctionKind::Normal, function(Type::Double, vec![], false), Some(Qualifiers::CONST)), "_ZNK3geo5Point4normEv"),
+        (mangle::function("geo::Point::operator+", FunctionKind::Normal, function(Type::Named(Symbol::intern("geo::Point")), vec![const_ref(point)], false), Some(Qualifiers::CONST)), "_ZNK3geo5PointplERKS0_"),
+        (mangle::function("geo::Point::operator-", FunctionKind::Normal, function(Type::Named(Symbol::intern("geo::Point")), vec![], false), Some(Qualifiers::NONE)), "_ZN3geo5PointngEv"),
+        (mangle::function("geo::Point::Point", FunctionKind::Constructor, function(Type::Void, vec![TypeId::intern(Type::Double)], false), Some(Qualifiers::NONE)), "_ZN3geo5PointC1Ed"),
+        (mangle::function("geo::Point::~Point", FunctionKind::Destructor, function(Type::Void, vec![], false), Some(Qualifiers::NONE)), "_ZN3geo5PointD1Ev"),
+        (mangle::function("geo::Point::operator bool", FunctionKind::Conversion, function(Type::Bool, vec![], false), Some(Qualifiers::CONST)), "_ZNK3geo5PointcvbEv"),
+        (mangle::function("geo::area", FunctionKind::Normal, function(Type::Double, vec![const_ref(named("geo::Shape"))], false), None), "_ZN3geo4areaERKNS_5ShapeE"),
+        (mangle::function("copy", FunctionKind::Normal, function(Type::Void, vec![pointer(Type::Char), pointer(Type::Char)], false), None), "_Z4copyPcS_"),
+        (mangle::function("compare", FunctionKind::Normal, function(Type::Int, vec![const_char, const_char], false), None), "_Z7comparePKcS0_"),
+        (mangle::function("log", FunctionKind::Normal, function(Type::Void, vec![const_char], true), None), "_Z3logPKcz"),
+        (mangle::function("std::swap", FunctionKind::Normal, function(Type::Void, vec![point, point], false), None), "_ZSt4swapN3geo5PointES0_"),
+        (mangle::function("sum", FunctionKind::Normal, function(Type::Int, vec![const_ref(vector)], false), None), "_Z3sumRKSt6vectorIiE"),
+        (mangle::function("put", FunctionKind::Normal, function(Type::Void, vec![TypeId::intern(Type::WChar), pointer(Type::Char8), pointer(Type::Char16), TypeId::intern(Type::Char32)], false), None), "_Z3putwPDuPDsDi"),
+        (mangle::function("main", FunctionKind::Normal, function(Type::Int, vec![], false), None), "main"),
+        (mangle::variable("geo::Point::count"), "_ZN3geo5Point5countE"),
+        (mangle::variable("errno_value"), "errno_value"),
+    ];
+    for (mangled, expected) in cases {
+        eprintln!("{:?}", mangled);
+        assert_eq!(mangled.as_deref(), Some(expected));
+    }
+    assert_eq!(mangle::function("f", FunctionKind::Normal, function(Type::Auto, vec![TypeId::intern(Type::Auto)], false), None), None);
+}
+
+#[test]
+fn interface_changes_are_classified() {
+    let old = "\
+        namespace geo {\n\
+        typedef double coord;\n\
+        struct Point { coord x, y; double norm() const; static int count; };\n\
+        class Shape { public: virtual ~Shape(); virtual double area() const = 0; virtual void draw(); int id; };\n\
+        class Circle : public Shape { public: double area() const override; double r; };\n\
+        double area(const Shape &s);\n\
+        void scale(Point *p, coord by);\n\
+        extern int version;\n\
+        inline int twice(int x) { return 2 * x; }\n\
+        static int hidden(int x);\n\
+        }\n\
+        int legacy(int);\n";
+    let new = "\
+        namespace geo {\n\
+        typedef float coord;\n\
+        struct Point { coord x, y, z; double norm() const; static int count; };\n\
+        class Shape { public: virtual ~Shape(); virtual void draw(); virtual double area() const = 0; int id; };\n\
+        class Circle : public Shape { public: double area() const override; double r; };\n\
+        long area(const Shape &s);\n\
+        void scale(Point *p, coord by);\n\
+        extern long version;\n\
+        void extra();\n\
+        }\n";
+    let changes = diff(old, new);
+    eprintln!("{:#?}", changes);
+    assert_eq!(changes, [
+        "breaking: function `legacy(int)` (`_Z6legacyi`) was removed",
+        "breaking: function `geo::area(const geo::Shape&)` (`_ZN3geo4areaERKNS_5ShapeE`) now returns `long` instead of `double`",
+        "breaking: function `geo::scale(geo::Point*, double)` (`_ZN3geo5scaleEPNS_5PointEd`) was removed",
+        "breaking: variable `geo::version` (`_ZN3geo7versionE`) changed type from `int` to `long`",
+        "breaking: virtual function `area() const` moved from slot 2 to slot 3 of the vtable of `geo::Circle`",
+        "breaking: virtual function `draw()` moved from slot 3 to slot 2 of the vtable of `geo::Circle`",
+        "breaking: class `geo::Point` changed size from 16 to 12 bytes",
+        "breaking: class `geo::Point` changed alignment from 8 to 4",
+        "breaking: field `y` of `geo::Point` moved from offset 8 to 4",
+        "breaking: class `geo::Point` has a new field `z` at offset 8",
+        "breaking: virtual function `area() const` moved from slot 2 to slot 3 of the vtable of `geo::Shape`",
+        "breaking: virtual function `draw()` moved from slot 3 to slot 2 of the vtable of `geo::Shape`",
+        "compatible: function `geo::extra()` (`_ZN3geo5extraEv`) was added",
+        "compatible: function `geo::scale(geo::Point*, float)` (`_ZN3geo5scaleEPNS_5PointEf`) was added",
+    ]);
+    assert!(diff(old, old).is_empty());
+}
+
+#[test]
+fn overriders_keep_their_slots_and_new_virtuals_are_appended() {
+    let base = "struct Base { virtual void a(); virtual void b(); };\n";
+    let old = format!("{}struct Derived : Base {{ void b() override; virtual void c(); }};\n", base);
+    let new = format!("{}struct Derived : Base {{ virtual void d(); void b() override; virtual void c(); int extra; }};\n", base);
+    let changes = diff(&old, &new);
+    eprintln!("{:#?}", changes);
+    assert_eq!(changes, [
+        "breaking: class `Derived` changed size from 8 to 16 bytes",
+        "breaking: class `Derived` has a new field `extra` at offset 8",
+        "breaking: virtual function `c()` moved from slot 2 to slot 3 of the vtable of `Derived`",
+        "breaking: virtual function `d()` was added to the vtable of `Derived` at slot 2",
+        "compatible: function `Derived::d()` (`_ZN7Derived1dEv`) was added",
+    ]);
+    let hidden = diff("", "struct __attribute__((visibility(\"hidden\"))) Impl { void run(); };\nstatic void local();\n");
+    assert_eq!(hidden, ["compatible: class `Impl` was added"]);
+}
+
+#[test]
+fn headers_are_described_for_bindings() {
+    let (s, tu) = parse("\
+        #include \"dep.h\"\n\
+        namespace geo {\n\
+        typedef double coord;\n\
+        constexpr int max_points = 1 << 10;\n\
+        constexpr bool strict = max_points > 100;\n\
+        enum class Color : unsigned char { Red, Green = 4, Blue };\n\
+        struct Point { coord x, y; double norm() const; };\n\
+        class Shape : public dep::Base { public: Shape(ident id = 0); double area(const Point &origin) const; virtual void draw() = 0; private: char kind; };\n\
+        inline coord twice(coord c) { return 2 * c; }\n\
+        extern int version;\n\
+        namespace { int hidden(); }\n\
+        }\n");
+    let file = s.sources.lookup_path("lib.h".as_ref()).unwrap();
+    let description = api::describe(&tu, file, &s.sources, &s.target);
+    eprintln!("{}", serde_json::to_string_pretty(&description).unwrap());
+    assert_eq!(description["version"], api::VERSION);
+    let names = |key: &str| description[key].as_array().unwrap().iter().map(|v| v["name"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+    assert_eq!(names("functions"), ["geo::twice"]);
+    assert_eq!(names("records"), ["geo::Point", "geo::Shape"]);
+    assert_eq!(names("typedefs"), ["geo::coord"]);
+    assert_eq!(names("variables"), ["geo::version"]);
+    let twice = &description["functions"][0];
+    assert_eq!((twice["mangled"].as_str(), twice["returns"].as_str(), twice["inline"].as_bool()), (Some("_ZN3geo5twiceEd"), Some("double"), Some(true)));
+    assert_eq!(description["constants"], serde_json::json!([
+        { "name": "geo::max_points", "type": "int", "value": 1024, "line": 4 },
+        { "name": "geo::strict", "type": "bool", "value": true, "line": 5 },
+    ]));
+    assert_eq!(description["enums"][0]["underlying"], "unsigned char");
+    assert_eq!(description["enums"][0]["enumerators"][2], serde_json::json!({ "name": "Blue", "value": 5 }));
+    let shape = &description["records"][1];
+    assert_eq!((shape["size"].as_u64(), shape["align"].as_u64(), shape["polymorphic"].as_bool()), (Some(16), Some(8), Some(true)));
+    assert_eq!(shape["bases"][0], serde_json::json!({ "name": "dep::Base", "access": "public", "virtual": false, "offset": 0 }));
+    assert_eq!(shape["fields"][0], serde_json::json!({ "name": "kind", "type": "char", "access": "private", "offset": 12, "size": 1 }));
+    assert_eq!(shape["vtable"], serde_json::json!(["complete destructor", "deleting destructor", "draw()"]));
+    let constructor = &shape["methods"][0];
+    assert_eq!((constructor["kind"].as_str(), constructor["mangled"].as_str()), (Some("constructor"), Some("_ZN3geo5ShapeC1Et")));
+    assert_eq!(constructor["parameters"][0], serde_json::json!({ "name": "id", "type": "unsigned short", "default": true }));
+    assert_eq!(shape["methods"][2]["pure"], true);
+}
+
+#[test]
+fn redeclarations_are_described_once_as_their_definition() {
+    let (s, tu) = parse("\
+        int f(int);\n\
+        int f(int n);\n\
+        int f(double);\n\
+        extern int x;\n\
+        int x = 1;\n\
+        extern int x;\n\
+        struct S { static int count; };\n\
+        int S::count = 0;\n\
+        inline int f(int n) { return n; }\n");
+    let file = s.sources.lookup_path("lib.h".as_ref()).unwrap();
+    let description = api::describe(&tu, file, &s.sources, &s.target);
+    eprintln!("{}", serde_json::to_string_pretty(&description).unwrap());
+    let entries = |key: &str| description[key].as_array().unwrap().iter().map(|v| (v["name"].as_str().unwrap().to_string(), v["line"].as_u64().unwrap())).collect::<Vec<_>>();
+    assert_eq!(entries("functions"), [("f".to_string(), 9), ("f".to_string(), 3)]);
+    assert_eq!(description["functions"][0]["inline"], true);
+    assert_eq!(entries("variables"), [("x".to_string(), 5), ("S::count".to_string(), 8)]);
+}
+
+#[test]
+fn cli_describes_headers() {
+    let dir = std::env::temp_dir().join(format!("ruscom-interface-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let header = dir.join("packet.h");
+    std::fs::write(&header, "namespace net {\nenum Kind { Data, Ack };\nstruct Packet { char tag; int length; Kind kind; };\nint send(const Packet &p, int flags = 0);\n}\n").unwrap();
+    let run = |format: &str| assert_cmd::Command::cargo_bin("ruscom").unwrap().arg("interface").arg(&header).args(["--format", format]).output().unwrap();
+    let output = run("text");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    eprint!("{}", stdout);
+    let (first, rest) = stdout.split_once('\n').unwrap();
+    assert!(first.starts_with(&header.display().to_string()));
+    assert_eq!(rest, "\
+function net::send(const net::Packet&, int) -> int [_ZN3net4sendERKNS_6PacketEi]
+struct net::Packet: size 12, align 4
+  field tag: char at 0
+  field length: int at 4
+  field kind: net::Kind at 8
+enum net::Kind: int
+  Data = 0
+  Ack = 1
+");
+    let output = run("json");
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["records"][0]["fields"][2]["offset"], 8);
+    assert!(!run("yaml").status.success());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn cli_reports_breaking_changes() {
+    let dir = std::env::temp_dir().join(format!("ruscom-abidiff-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (old, new, library) = (dir.join("old.h"), dir.join("new.h"), dir.join("libgeo.so"));
+    std::fs::write(&old, "namespace geo { struct Point { double x, y; }; double norm(const Point &p); }\n").unwrap();
+    std::fs::write(&new, "namespace geo { struct Point { double x, y; }; double norm(const Point &p); double dot(const Point &a, const Point &b); }\n").unwrap();
+    std::fs::write(&library, b"\x7fELF").unwrap();
+    let run = |old: &std::path::Path, new: &std::path::Path| assert_cmd::Command::cargo_bin("ruscom").unwrap().arg("abidiff").arg(old).arg(new).output().unwrap();
+    let output = run(&old, &new);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    eprint!("{}", stdout);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(stdout, "compatible: function `geo::dot(const geo::Point&, const geo::Point&)` (`_ZN3geo3dotERKNS_5PointES2_`) was added\n0 breaking changes, 1 compatible change\n");
+    let output = run(&new, &old);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    eprint!("{}", stdout);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout.ends_with("1 breaking change, 0 compatible changes\n"));
+    let output = run(&library, &new);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is a binary, which ruscom cannot read"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn headers_are_bound_for_rust() {
+    let (mut s, tu) = parse("\
+        #include \"dep.h\"\n\
+        namespace geo {\n\
+        typedef double coord;\n\
+        constexpr int max_points = 1 << 10;\n\
+        enum class Color : unsigned char { Red, Green = 4, Blue };\n\
+        struct Point { coord x, y; double norm() const; static Point origin(); };\n\
+        struct Node { Node *next; int values[4]; Color color; };\n\
+        union Value { int i; double d; };\n\
+        struct Impl;\n\
+        class Shape : public dep::Base { public: virtual void draw() = 0; };\n\
+        double distance(const Point *a, const Point &b);\n\
+        Impl *open(const char *path, int flags, ...);\n\
+        void walk(Node *first, void (*visit)(Node *, void *), void *user);\n\
+        inline coord twice(coord c) { return 2 * c; }\n\
+        long double precise();\n\
+        int scale(int by);\n\
+        int scale(double by);\n\
+        int scale(int);\n\
+        extern ident version;\n\
+        ident version = 1;\n\
+        int type(Shape *shape);\n\
+        }\n");
+    let file = s.sources.lookup_path("lib.h".as_ref()).unwrap();
+    let bindings = bindgen::generate(&tu, file, &s.sources, &s.target, &mut s.diagnostics);
+    eprintln!("{}", bindings);
+    for expected in [
+        "pub type geo_coord = f64;\n",
+        "pub const geo_max_points: ::core::ffi::c_int = 1024;\n",
+        "pub type geo_Color = ::core::ffi::c_uchar;\npub const geo_Color_Red: geo_Color = 0;\npub const geo_Color_Green: geo_Color = 4;\npub const geo_Color_Blue: geo_Color = 5;\n",
+        "#[repr(C)]\n#[derive(Debug, Clone, Copy)]\npub struct geo_Point {\n    pub x: f64,\n    pub y: f64,\n}\nconst _: () = assert!(::core::mem::size_of::<geo_Point>() == 16 && ::core::mem::align_of::<geo_Point>() == 8);\n",
+        "pub struct geo_Node {\n    pub next: *mut geo_Node,\n    pub values: [::core::ffi::c_int; 4],\n    pub color: geo_Color,\n}\n",
+        "#[derive(Clone, Copy)]\npub union geo_Value {",
+        "pub struct geo_Impl {\n    _data: [u8; 0],",
+        "pub struct geo_Shape {\n    _data: [u8; 0],",
+        "    #[link_name = \"_ZN3geo5Point6originEv\"]\n    pub fn geo_Point_origin() -> geo_Point;\n",
+        "    pub fn geo_open(path: *const ::core::ffi::c_char, flags: ::core::ffi::c_int, ...) -> *mut geo_Impl;\n",
+        "    pub fn geo_walk(first: *mut geo_Node, visit: ::core::option::Option<unsafe extern \"C\" fn(*mut geo_Node, *mut ::core::ffi::c_void)>, user: *mut ::core::ffi::c_void);\n",
+        "    #[link_name = \"_ZN3geo5scaleEi\"]\n    pub fn geo_scale(by: ::core::ffi::c_int) -> ::core::ffi::c_int;\n",
+        "    #[link_name = \"_ZN3geo7versionE\"]\n    pub static mut geo_version: ::core::ffi::c_ushort;\n",
+        "    pub fn geo_type(shape: *mut geo_Shape) -> ::core::ffi::c_int;\n",
+    ] {
+        assert!(bindings.contains(expected), "missing {:?}", expected);
+    }
+    assert!(!bindings.contains("twice") && !bindings.contains("distance") && !bindings.contains("dep_"));
+    // Redeclarations are bound once, and quietly.
+    assert_eq!((bindings.matches("fn geo_scale").count(), bindings.matches("static mut geo_version").count()), (1, 1));
+    let skipped: Vec<String> = s.diagnostics.iter().map(|d| d.message.clone()).collect();
+    eprintln!("{:#?}", skipped);
+    assert_eq!(skipped, [
+        "skipped member function `geo::Point::norm`: only static member functions are FFI-safe",
+        "class `geo::Shape` is bound as an opaque type, usable only through pointers: it has base classes",
+        "skipped member function `geo::Shape::draw`: only static member functions are FFI-safe",
+        "skipped function `geo::distance`: its parameter `b` has type `const geo::Point&`, and references are not FFI-safe",
+        "skipped function `geo::twice`: it is inline, so the library need not define it",
+        "skipped function `geo::precise`: its return type is `long double`, and `long double` has no Rust equivalent",
+        "skipped function `geo::scale`: its Rust name `geo_scale` is taken, and Rust has no overloading",
+    ]);
+}
+
+#[test]
+fn cli_writes_bindings_that_compile() {
+    let dir = std::env::temp_dir().join(format!("ruscom-bindgen-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (header, bindings) = (dir.join("packet.h"), dir.join("bindings.rs"));
+    std::fs::write(&header, "namespace net {\nenum Kind { Data, Ack };\nstruct Packet { char tag; int length; Kind kind; };\nint send(const Packet *p, int flags);\nint send_all(const Packet &p);\n}\n").unwrap();
+    let output = assert_cmd::Command::cargo_bin("ruscom").unwrap().arg("bindgen").arg(&header).arg("-o").arg(&bindings).output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    eprint!("{}", stderr);
+    assert!(output.status.success());
+    assert!(stderr.contains("warning: skipped function `net::send_all`: its parameter `p` has type `const net::Packet&`, and references are not FFI-safe"));
+    let rust = std::fs::read_to_string(&bindings).unwrap();
+    eprint!("{}", rust);
+    assert!(rust.contains("#[link_name = \"_ZN3net4sendEPKNS_6PacketEi\"]\n    pub fn net_send(p: *const net_Packet, flags: ::core::ffi::c_int) -> ::core::ffi::c_int;"));
+    // The layout assertions hold only on the target the bindings are for.
+    let rustc = std::process::Command::new("rustc")
+        .args(["--edition", "2021", "--crate-type", "lib", "--emit", "metadata", "-A", "nonstandard_style", "--out-dir"])
+        .arg(&dir)
+        .arg(&bindings)
+        .output()
+        .unwrap();
+    assert!(rustc.status.success(), "{}", String::from_utf8_lossy(&rustc.stderr));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn c_headers_declare_the_c_functions_a_file_defines() {
+    let (mut s, tu) = parse("\
+        #include \"dep.h\"\n\
+        namespace geo {\n\
+        struct Point { double x, y; };\n\
+        enum class Unit { Metre, Foot = 3 };\n\
+        enum Flags : unsigned char { Bold = 1, Wide = 2 };\n\
+        struct Hidden { virtual void draw(); };\n\
+        struct Unused { int &r; };\n\
+        }\n\
+        extern \"C\" double geo_distance(geo::Point a, geo::Point b);\n\
+        double geo_distance(geo::Point a, geo::Point b) { return a.x - b.x; }\n\
+        extern \"C\" {\n\
+        int geo_count;\n\
+        extern const char *geo_name;\n\
+        void geo_scale(geo::Point *p, geo::Unit unit, ident id, void (*done)(int)) {}\n\
+        int (*geo_table())[4] { return 0; }\n\
+        dep::Base *geo_base() { return 0; }\n\
+        int geo_clamp(int &x) { return x; }\n\
+        inline int geo_twice(int x) { return 2 * x; }\n\
+        int geo_area(geo::Hidden h) { return 0; }\n\
+        static int geo_helper() { return 0; }\n\
+        }\n\
+        int geo_internal(int x) { return x; }\n");
+    let file = s.sources.lookup_path("lib.h".as_ref()).unwrap();
+    let header = cheader::generate(&tu, file, &s.sources, &s.target, &mut s.diagnostics);
+    eprintln!("{}", header);
+    for expected in [
+        "#ifndef LIB_H\n#define LIB_H\n\n#ifdef __cplusplus\nextern \"C\" {\n#endif\n",
+        "typedef struct Base Base;\ntypedef struct Point Point;\n\nstruct Point {",
+        "struct Point {\n    double x;\n    double y;\n};\n",
+        "typedef enum Unit {\n    Unit_Metre = 0,\n    Unit_Foot = 3\n} Unit;\n",
+        "typedef unsigned char Flags;\nenum {\n    Bold = 1,\n    Wide = 2\n};\n",
+        "double geo_distance(Point a, Point b);\n",
+        "extern int geo_count;\n",
+        "void geo_scale(Point *p, Unit unit, unsigned short id, void (*done)(int));\n",
+        "int (*geo_table(void))[4];\n",
+        "Base *geo_base(void);\n",
+    ] {
+        assert!(header.contains(expected), "missing {:?}", expected);
+    }
+    assert!(!header.contains("Hidden") && !header.contains("Unused") && !header.contains("geo_name") && !header.contains("geo_helper") && !header.contains("geo_internal"));
+    let skipped: Vec<String> = s.diagnostics.iter().map(|d| d.message.clone()).collect();
+    eprintln!("{:#?}", skipped);
+    assert_eq!(skipped, [
+        "skipped function `geo_clamp`: its parameter `x` has type `int&`, and references have no C equivalent",
+        "skipped function `geo_twice`: it is inline, so the library need not define it",
+        "skipped function `geo_area`: its parameter `h` has type `geo::Hidden`, and `geo::Hidden` is not a C struct: it has virtual functions",
+    ]);
+    // Their symbols are their names.
+    let description = api::render(&api::describe(&tu, file, &s.sources, &s.target));
+    assert!(description.contains("geo_distance(geo::Point, geo::Point) -> double [geo_distance]"), "{}", description);
+}
+
+#[test]
+fn cli_emits_c_headers_that_compile() {
+    let dir = std::env::temp_dir().join(format!("ruscom-cheader-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (source, header) = (dir.join("packet.cpp"), dir.join("packet.h"));
+    std::fs::write(&source, "namespace net {\nenum Kind { Data, Ack };\nstruct Packet { char tag; int length; Kind kind; bool urgent; };\n}\nextern \"C\" int net_send(const net::Packet *p, int flags) { return flags; }\nextern \"C\" int net_send_all(const net::Packet &p) { return 0; }\n").unwrap();
+    let output = assert_cmd::Command::cargo_bin("ruscom").unwrap().args(["compile", "--emit", "c-header"]).arg(&source).arg("-o").arg(&header).output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    eprint!("{}", stderr);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(stderr.contains("warning: skipped function `net_send_all`: its parameter `p` has type `const net::Packet&`, and references have no C equivalent"));
+    let c = std::fs::read_to_string(&header).unwrap();
+    eprint!("{}", c);
+    assert!(c.contains("#include <stdbool.h>\n") && c.contains("int net_send(const Packet *p, int flags);\n"));
+    std::fs::write(dir.join("use.c"), "#include \"packet.h\"\nint send_ack(void) { Packet p = { 'a', 0, Ack, false }; return net_send(&p, 0); }\n").unwrap();
+    match std::process::Command::new("cc").args(["-std=c99", "-pedantic-errors", "-Wall", "-Werror", "-fsyntax-only"]).arg(dir.join("use.c")).output() {
+        Ok(cc) => assert!(cc.status.success(), "{}", String::from_utf8_lossy(&cc.stderr)),
+        Err(e) => eprintln!("not compiling the header: {}", e),
+    }
+    let output = assert_cmd::Command::cargo_bin("ruscom").unwrap().args(["compile", "--emit", "object"]).arg(&source).output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown output kind `object` (expected c-header)"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
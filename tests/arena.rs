@@ -0,0 +1,79 @@
+use std::panic;
+
+use ruscom::arena::TuArena;
+use ruscom::intern::Symbol;
+use ruscom::types::{Type, TypeId};
+
+#[test]
+fn allocations_are_tracked_per_tu() {
+    let mut arena = TuArena::new();
+    let before = arena.stats();
+    let name = arena.alloc_str("translation_unit");
+    let n = arena.alloc(42u64);
+    let xs = arena.alloc_slice_copy(&[1u32, 2, 3]);
+    assert_eq!(name, "translation_unit");
+    assert_eq!(*n, 42);
+    assert_eq!(xs, &[1, 2, 3]);
+    let after = arena.stats();
+    eprintln!("arena stats before {:?} after {:?}", before, after);
+    assert!(after.allocated_bytes >= before.allocated_bytes + 16 + 8 + 12);
+    assert!(after.reserved_bytes >= after.allocated_bytes);
+
+    arena.reset();
+    assert!(arena.stats().allocated_bytes < after.allocated_bytes);
+}
+
+#[test]
+fn boxed_values_are_dropped_and_cloned_into_the_same_arena() {
+    use std::rc::Rc;
+
+    let arena = TuArena::new();
+    let shared = Rc::new(());
+    let mut a = arena.boxed((Rc::clone(&shared), vec![1, 2]));
+    a.1.push(3);
+    let b = a.clone();
+    eprintln!("{:?} {:?}", a, b);
+    assert_eq!(a, b);
+    assert!(std::ptr::eq(b.arena(), &arena));
+    assert_eq!(Rc::strong_count(&shared), 3);
+    drop(a);
+    assert_eq!(b.into_inner().1, [1, 2, 3]);
+    assert_eq!(Rc::strong_count(&shared), 1);
+}
+
+#[test]
+fn symbols_and_types_are_freed_with_their_arena() {
+    let kept = Symbol::intern("kept");
+    let outer = TuArena::new();
+    let shared = Symbol::intern("shared");
+    let mut arena = TuArena::new();
+    let name = Symbol::intern("only_in_this_tu");
+    let ty = TypeId::intern(Type::Pointer(TypeId::intern(Type::Named(name))));
+    assert_eq!(ty.to_string(), "only_in_this_tu*");
+    arena.reset();
+    assert!(panic::catch_unwind(|| name.as_str()).is_err());
+    assert!(panic::catch_unwind(|| ty.get()).is_err());
+    // Interned again, the name is a new symbol, freed with the arena.
+    let again = Symbol::intern("only_in_this_tu");
+    assert_ne!(again, name);
+    drop(arena);
+    assert!(panic::catch_unwind(|| again.as_str()).is_err());
+    assert_eq!(&*shared.as_str(), "shared");
+    drop(outer);
+    assert!(panic::catch_unwind(|| shared.as_str()).is_err());
+    assert_eq!(&*kept.as_str(), "kept");
+}
+
+#[test]
+fn an_arena_dropped_early_frees_its_symbols_with_the_newer_ones() {
+    let first = TuArena::new();
+    let a = Symbol::intern("first_tu");
+    let second = TuArena::new();
+    let b = Symbol::intern("second_tu");
+    drop(first);
+    // `second` is newer and still live, so nothing is freed yet.
+    assert_eq!((&*a.as_str(), &*b.as_str()), ("first_tu", "second_tu"));
+    drop(second);
+    assert!(panic::catch_unwind(|| a.as_str()).is_err());
+    assert!(panic::catch_unwind(|| b.as_str()).is_err());
+}
@@ -0,0 +1,259 @@
+mod common;
+
+use assert_cmd::Command;
+use predicates::str::contains;
+use ruscom::arena::TuArena;
+use ruscom::artifact::{self, ArtifactKind, DecodeError, Reader, Writer};
+use ruscom::ast::{self, TranslationUnit};
+use ruscom::intern::Symbol;
+use ruscom::lexer::token::{SpannedToken, Token};
+use ruscom::lexer::{Lexer, LexerOptions};
+use ruscom::module::{ExportKind, ExportedDecl, ModuleInterface};
+use ruscom::session::{Options, Session};
+use ruscom::types::{Qualifiers, TemplateArgument, Type, TypeId};
+
+fn lex_all(src: &str) -> Vec<SpannedToken> {
+    let mut lexer = Lexer::with_options(src, LexerOptions { preserve_comments: true });
+    let mut tokens = Vec::new();
+    loop {
+        let t = lexer.next_token().unwrap();
+        let eof = t.token == Token::Eof;
+        tokens.push(t);
+        if eof { break; }
+    }
+    tokens
+}
+
+#[test]
+fn varints_round_trip() {
+    let values = [0u64, 1, 127, 128, 300, u32::MAX as u64, u64::MAX];
+    let signed = [0i64, -1, 1, -64, 64, i64::MIN, i64::MAX];
+    let mut w = Writer::new(ArtifactKind::Tokens);
+    for v in values { w.uint(v); }
+    for v in signed { w.int(v); }
+    w.str("héllo");
+    let bytes = w.finish();
+    let (kind, mut r) = Reader::open(&bytes).unwrap();
+    assert_eq!(kind, ArtifactKind::Tokens);
+    for v in values { assert_eq!(r.uint().unwrap(), v); }
+    for v in signed { assert_eq!(r.int().unwrap(), v); }
+    assert_eq!(r.str().unwrap(), "héllo");
+    assert!(r.is_empty());
+}
+
+#[test]
+fn token_stream_round_trips() {
+    let src = "#include <vector>\n// note\nint main() { char c = 'x'; return x << 2 + 0x1f; } /* end */ \"s\"";
+    let tokens = lex_all(src);
+    let bytes = artifact::encode_tokens(&tokens);
+    eprintln!("{} tokens from {} source bytes encoded in {} bytes", tokens.len(), src.len(), bytes.len());
+    assert_eq!(artifact::decode_tokens(&bytes).unwrap(), tokens);
+}
+
+#[test]
+fn repeated_strings_are_stored_once() {
+    let one = artifact::encode_tokens(&lex_all("identifier_name"));
+    let many = artifact::encode_tokens(&lex_all(&"identifier_name ".repeat(100)));
+    eprintln!("one: {} bytes, many: {} bytes", one.len(), many.len());
+    assert!(many.len() < one.len() + 100 * 4);
+}
+
+#[test]
+fn spans_may_go_backwards() {
+    let tokens = vec![
+        SpannedToken { token: Token::Identifier("b".into()), span: ruscom::lexer::token::Span::new(10, 11) },
+        SpannedToken { token: Token::Identifier("a".into()), span: ruscom::lexer::token::Span::new(2, 3) },
+    ];
+    assert_eq!(artifact::decode_tokens(&artifact::encode_tokens(&tokens)).unwrap(), tokens);
+}
+
+#[test]
+fn embedded_bytes_round_trip() {
+    let tokens = vec![
+        SpannedToken { token: Token::Embed(vec![0u8, 1, 255].into()), span: ruscom::lexer::token::Span::new(0, 17) },
+        SpannedToken { token: Token::Punct(','), span: ruscom::lexer::token::Span::new(18, 19) },
+    ];
+    let bytes = artifact::encode_tokens(&tokens);
+    eprintln!("{:?}", bytes);
+    assert_eq!(artifact::decode_tokens(&bytes).unwrap(), tokens);
+}
+
+#[test]
+fn bad_input_is_rejected() {
+    assert_eq!(artifact::decode_tokens(b"nope").unwrap_err(), DecodeError::BadMagic);
+    let mut bytes = artifact::encode_tokens(&lex_all("int x;"));
+    bytes[4] = 99;
+    assert_eq!(artifact::decode_tokens(&bytes).unwrap_err(), DecodeError::UnsupportedVersion(99));
+    let bytes = artifact::encode_tokens(&lex_all("int x;"));
+    for len in 5..bytes.len() {
+        let err = artifact::decode_tokens(&bytes[..len]).unwrap_err();
+        assert!(matches!(err, DecodeError::Truncated | DecodeError::Invalid(_)), "{}: {:?}", len, err);
+    }
+}
+
+#[test]
+fn cli_emits_and_dumps_artifact() {
+    let dir = std::env::temp_dir().join(format!("ruscom_artifact_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let src = dir.join("a.cpp");
+    let out = dir.join("a.rtok");
+    std::fs::write(&src, "int main() { return 0; }\n").unwrap();
+    Command::cargo_bin("ruscom").unwrap()
+        .args(["lex", "--count", "--emit-artifact"]).arg(&out).arg(&src)
+        .assert().success();
+    let assert = Command::cargo_bin("ruscom").unwrap().arg("dump-artifact").arg(&out).assert().success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    eprintln!("{}", stdout);
+    assert!(stdout.contains("token stream, 9 tokens"));
+    assert!(stdout.contains("0..3 Identifier(\"int\")"));
+    Command::cargo_bin("ruscom").unwrap().arg("dump-artifact").arg(&src)
+        .assert().failure().stderr(contains("not a RusCom artifact"));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn module_interface_round_trips() {
+    let int = TypeId::intern(Type::Int);
+    let point = TypeId::intern(Type::Named(Symbol::intern("geo::Point")));
+    let array = TypeId::intern(Type::Specialization(Symbol::intern("std::array"), vec![TemplateArgument::Type(int), TemplateArgument::Value("4".into())]));
+    let types = [
+        TypeId::intern(Type::Function { ret: TypeId::intern(Type::Double), params: vec![TypeId::intern(Type::Reference(point.qualified(Qualifiers::CONST)))], variadic: true }),
+        TypeId::intern(Type::Pointer(TypeId::intern(Type::UnsignedChar).qualified(Qualifiers::CONST_VOLATILE))),
+        TypeId::intern(Type::Array(int, Some(0))),
+        TypeId::intern(Type::Array(TypeId::intern(Type::Array(int, Some(3))), None)),
+        TypeId::intern(Type::RvalueReference(array)),
+        TypeId::intern(Type::Auto),
+    ];
+    let mut decls: Vec<ExportedDecl> = types.iter().enumerate().map(|(i, &ty)| ExportedDecl { kind: ExportKind::Variable, name: Symbol::intern(&format!("v{}", i)), ty: Some(ty) }).collect();
+    decls.push(ExportedDecl { kind: ExportKind::ClassTemplate, name: Symbol::intern("geo::Box"), ty: None });
+    decls.push(ExportedDecl { kind: ExportKind::Concept, name: Symbol::intern("Small"), ty: None });
+    let interface = ModuleInterface { name: Symbol::intern("geo"), imports: vec![Symbol::intern("geo:shapes")], decls };
+    let bytes = artifact::encode_module(&interface);
+    eprintln!("{} declarations in {} bytes", interface.decls.len(), bytes.len());
+    assert_eq!(artifact::decode_module(&bytes).unwrap(), interface);
+    assert_eq!(artifact::decode_tokens(&bytes).unwrap_err(), DecodeError::WrongKind(ArtifactKind::ModuleInterface));
+    assert_eq!(artifact::decode_module(&artifact::encode_tokens(&lex_all("int x;"))).unwrap_err(), DecodeError::WrongKind(ArtifactKind::Tokens));
+    for len in 5..bytes.len() {
+        let err = artifact::decode_module(&bytes[..len]).unwrap_err();
+        assert!(matches!(err, DecodeError::Truncated | DecodeError::Invalid(_)), "{}: {:?}", len, err);
+    }
+}
+
+#[test]
+fn cli_compiles_and_imports_modules() {
+    let dir = std::env::temp_dir().join(format!("ruscom_modules_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let cache = dir.join("cache");
+    std::fs::write(dir.join("shapes.cppm"), "export module geo:shapes;\nexport template<class T> struct Shape { T size; };\n").unwrap();
+    std::fs::write(dir.join("geo.cppm"), "export module geo;\nexport import :shapes;\nexport namespace geo { struct Point { int x, y; }; }\nexport template<class T> struct Box { T value; };\nint hidden();\n").unwrap();
+    std::fs::write(dir.join("use.cpp"), "import geo;\nBox<int> b;\nShape<long> s;\ngeo::Point p;\n").unwrap();
+    Command::cargo_bin("ruscom").unwrap()
+        .args(["compile", "--module-cache"]).arg(&cache)
+        .arg(dir.join("shapes.cppm")).arg(dir.join("geo.cppm")).arg(dir.join("use.cpp"))
+        .assert().success();
+    let assert = Command::cargo_bin("ruscom").unwrap().arg("dump-artifact").arg(cache.join("geo.rcm")).assert().success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    eprintln!("{}", stdout);
+    assert!(stdout.contains("module interface of `geo`, 2 declarations"));
+    assert!(stdout.contains("export import geo:shapes\nclass geo::Point\nclass template Box\n"));
+    assert!(cache.join("geo-shapes.rcm").is_file());
+    // Without the cache, the templates are unknown and the importer does not parse.
+    Command::cargo_bin("ruscom").unwrap().arg("compile").arg(dir.join("use.cpp")).assert().failure();
+    Command::cargo_bin("ruscom").unwrap()
+        .args(["compile", "--module-cache"]).arg(&cache).arg(dir.join("shapes.cppm")).arg(dir.join("use.cpp"))
+        .assert().success();
+    std::fs::write(dir.join("other.cpp"), "import other;\n").unwrap();
+    Command::cargo_bin("ruscom").unwrap()
+        .args(["compile", "--module-cache"]).arg(&cache).arg(dir.join("other.cpp"))
+        .assert().failure().stderr(contains("E0209")).stderr(contains("cannot load module `other`"));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+fn parse(source: &str) -> (Session, TranslationUnit<'static>) {
+    common::parse_files(&[("main.cpp", source)], Options { skip_unsupported: true, ..Options::default() })
+}
+
+const TREE_SOURCE: &str = r#"export module shapes;
+import <vector>;
+template<class T, int N = 4, template<class> class C> requires (N > 0)
+struct [[nodiscard]] Box : public virtual Base {
+    static constexpr int size = N;
+    mutable T items[4];
+    explicit Box(T t) : items{t}, size(1) {}
+    ~Box() noexcept = default;
+    virtual operator bool() const = 0;
+    friend bool operator==(const Box&, const Box&) = delete;
+};
+template<class T> concept Small = sizeof(T) <= 8 && requires (T t) { t.f(); typename T::type; { t + 1 } noexcept -> Small; requires true; };
+namespace a::b { inline namespace v1 { using ::size_t; using namespace std; typedef unsigned long word; using byte = unsigned char; } }
+enum class Color : short { Red = 1, Green [[deprecated]], Blue };
+struct Leaf final : Base { void f() noexcept override final; };
+extern "C" { int puts(const char*); }
+export { int exported(int x, double y = 2.5, ...); }
+template<class T, int K> T max(T a, T b);
+Color paint(int n, Color c = Color::Red) {
+    auto [lo, hi] = pair(n, -n);
+    Point p{.x = 1, .y{2}};
+    for (int i = 0; i < n; ++i) { if (i % 2) continue; else if (!i) break; }
+    for (auto&& v : {1, 2, 3}) n += v;
+    while (n > 100) n >>= 1;
+    do { n--; } while (n);
+    switch (n) { case 1: [[fallthrough]]; default: ; }
+    const char* s = "hi\n";
+    auto* q = &p ? nullptr : this->ptr;
+    n = (int)'x' + reinterpret_cast<long>(q) + max<int, 3>(n, 2) + q[0] + 10_km + alignof(long) + sizeof n;
+    if constexpr (true) { }
+    return c;
+}
+Task run() { co_await tick(); co_yield 1; co_return; }
+int broken( = ;
+"#;
+
+#[test]
+fn syntax_tree_round_trips() {
+    let (s, tu) = parse(TREE_SOURCE);
+    eprintln!("{:?}", s.diagnostics.iter().map(|d| d.message.clone()).collect::<Vec<_>>());
+    let bytes = artifact::encode_ast(&tu);
+    eprintln!("{} source bytes, {} artifact bytes", TREE_SOURCE.len(), bytes.len());
+    let other = TuArena::new();
+    let decoded = artifact::decode_ast(&bytes, &other).unwrap();
+    assert_eq!(decoded, tu);
+    // `if constexpr` and the broken declaration.
+    assert_eq!(ruscom::coverage::Coverage::of(&tu).unsupported.len(), 2);
+    assert_eq!(ast::dump(&decoded, &s.sources), ast::dump(&tu, &s.sources));
+    assert_eq!(artifact::encode_ast(&decoded), bytes);
+}
+
+#[test]
+fn bad_syntax_tree_artifacts_are_rejected() {
+    let (_, tu) = parse("int f(int x) { return x * 2; }\nstruct S { int y; };\n");
+    let arena = TuArena::new();
+    let bytes = artifact::encode_ast(&tu);
+    assert_eq!(artifact::decode_tokens(&bytes).unwrap_err(), DecodeError::WrongKind(ArtifactKind::Ast));
+    assert_eq!(artifact::decode_ast(&artifact::encode_tokens(&lex_all("int x;")), &arena).unwrap_err(), DecodeError::WrongKind(ArtifactKind::Tokens));
+    for len in 5..bytes.len() {
+        let err = artifact::decode_ast(&bytes[..len], &arena).unwrap_err();
+        assert!(matches!(err, DecodeError::Truncated | DecodeError::Invalid(_)), "{}: {:?}", len, err);
+    }
+    let mut extra = bytes.clone();
+    extra.push(0);
+    assert_eq!(artifact::decode_ast(&extra, &arena).unwrap_err(), DecodeError::Invalid("trailing bytes"));
+}
+
+#[test]
+fn cli_emits_and_dumps_syntax_trees() {
+    let dir = std::env::temp_dir().join(format!("ruscom_ast_artifact_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let src = dir.join("a.cpp");
+    let out = dir.join("a.rast");
+    std::fs::write(&src, "int main() { return 0; }\n").unwrap();
+    Command::cargo_bin("ruscom").unwrap()
+        .args(["ast-dump", "--emit-artifact"]).arg(&out).arg(&src)
+        .assert().success();
+    let assert = Command::cargo_bin("ruscom").unwrap().arg("dump-artifact").arg(&out).assert().success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    eprintln!("{}", stdout);
+    std::fs::remove_dir_all(&dir).ok();
+    assert!(stdout.contains("syntax tree, 1 declarations"));
+    assert!(stdout.contains("`-FunctionDecl main 'int ()' <4>\n  `-CompoundStmt <11>\n    `-ReturnStmt <13>\n"));
+}
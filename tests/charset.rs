@@ -0,0 +1,96 @@
+use assert_cmd::Command;
+
+use ruscom::charset::Charset;
+use ruscom::session::Target;
+
+#[test]
+fn literals_encode_in_each_charset() {
+    let text = "Az\u{e9}";
+    let cases = [
+        (Charset::Utf8, Ok(vec![0x41, 0x7A, 0xC3, 0xA9])),
+        (Charset::Latin1, Ok(vec![0x41, 0x7A, 0xE9])),
+        (Charset::Ascii, Err('\u{e9}')),
+        (Charset::Ebcdic, Ok(vec![0xC1, 0xA9, 0x51])),
+        (Charset::Utf16, Ok(vec![0x41, 0x7A, 0xE9])),
+        (Charset::Utf32, Ok(vec![0x41, 0x7A, 0xE9])),
+    ];
+    for (charset, expected) in cases {
+        let units = charset.encode(text);
+        eprintln!("{}: {:x?}", charset, units);
+        assert_eq!(units, expected, "{}", charset);
+    }
+    assert_eq!(Charset::Ebcdic.encode("0\n "), Ok(vec![0xF0, 0x25, 0x40]));
+    assert_eq!(Charset::Utf16.encode("\u{1F600}"), Ok(vec![0xD83D, 0xDE00]));
+    assert_eq!(Charset::Latin1.encode("\u{20AC}"), Err('\u{20AC}'));
+    assert_eq!(Charset::Utf16.encode_bytes("A\u{e9}", false), Ok(vec![0x41, 0, 0xE9, 0]));
+    assert_eq!(Charset::Utf32.encode_bytes("A", true), Ok(vec![0, 0, 0, 0x41]));
+    assert_eq!(Charset::Ebcdic.encode_bytes("A", true), Ok(vec![0xC1]));
+}
+
+#[test]
+fn charset_names_and_wchar_defaults() {
+    for (name, charset) in [("utf-8", Charset::Utf8), ("ISO_8859-1", Charset::Latin1), ("latin1", Charset::Latin1), ("US-ASCII", Charset::Ascii), ("cp037", Charset::Ebcdic), ("UCS-4", Charset::Utf32)] {
+        assert_eq!(name.parse::<Charset>(), Ok(charset), "{}", name);
+    }
+    assert!("KOI8-R".parse::<Charset>().unwrap_err().contains("unknown character set `KOI8-R`"));
+    assert_eq!(Target { arch: "x86_64".into(), os: "linux".into() }.wchar_charset(), Charset::Utf32);
+    assert_eq!(Target { arch: "x86_64".into(), os: "windows".into() }.wchar_charset(), Charset::Utf16);
+}
+
+#[test]
+fn exec_charset_options_are_checked() {
+    let run = |args: &[&str]| Command::cargo_bin("ruscom").unwrap().args(["preprocess", "tests/data/pp_main.cpp", "-I", "tests/data/include"]).args(args).assert();
+    run(&["-fexec-charset=IBM037", "-fwide-exec-charset=UTF-32"]).success();
+    run(&["-fexec-charset=KOI8-R"]).failure().stderr(predicates::str::contains("unknown character set `KOI8-R`"));
+    run(&["-fexec-charset=UTF-16"]).failure().stderr(predicates::str::contains("does not have single-byte code units"));
+    run(&["-fwide-exec-charset=UTF-8"]).failure().stderr(predicates::str::contains("does not match the"));
+}
+
+#[test]
+fn literals_are_measured_and_valued_in_the_execution_charset() {
+    let dir = std::env::temp_dir().join(format!("ruscom_charset_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("lits.cpp");
+    std::fs::write(&file, concat!(
+        "static_assert(sizeof(\"\\xff\") == 2);\n",
+        "static_assert(sizeof(L\"ab\") == 3 * sizeof(wchar_t));\n",
+        "static_assert(sizeof(u\"\\U0001F600\") == 6 && sizeof(U\"ab\") == 12 && sizeof(u8\"\\u00e9\") == 3);\n",
+        "static_assert(sizeof(\"a\" L\"b\") == 3 * sizeof(wchar_t));\n",
+        "static_assert('\\xff' == -1 && u'\\xffff' == 0xffff && sizeof('ab') == 4);\n",
+        "#ifdef EBCDIC\n",
+        "static_assert(sizeof(\"\\u00e9\") == 2 && 'A' == -63);\n",
+        "#if 'A' != -63\n#error\n#endif\n",
+        "#else\n",
+        "static_assert(sizeof(\"\\u00e9\") == 3 && 'A' == 65);\n",
+        "#if 'A' != 65\n#error\n#endif\n",
+        "#endif\n",
+    )).unwrap();
+    let run = |args: &[&str]| Command::cargo_bin("ruscom").unwrap().arg("check").arg(&file).args(args).assert();
+    run(&[]).success();
+    run(&["-fexec-charset=IBM037", "-DEBCDIC"]).success();
+    run(&["-fexec-charset=ASCII"]).failure().stderr(predicates::str::contains("error[E0214]: character `\u{e9}` cannot be encoded in the execution character set ASCII"));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn u8_literals_are_char_before_cxx20() {
+    let dir = std::env::temp_dir().join(format!("ruscom_char8_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("u8.cpp");
+    std::fs::write(&file, concat!(
+        "#if __cplusplus >= 202002L\n",
+        "const char8_t *t = u8\"x\";\n",
+        "static_assert(__cpp_char8_t == 201811L);\n",
+        "#else\n",
+        "static_assert(__cplusplus == 201703L);\n",
+        "#endif\n",
+        "const char *s = u8\"x\";\n",
+        "char c = u8'a';\n",
+    )).unwrap();
+    let run = |args: &[&str]| Command::cargo_bin("ruscom").unwrap().arg("check").arg(&file).args(args).assert();
+    run(&[]).success();
+    run(&["-std=c++17"]).success();
+    run(&["-std=c++20"]).failure().stderr(predicates::str::contains("error[E0329]: no viable conversion from `const char8_t[2]` to `const char*`"));
+    run(&["-std=c++11"]).failure().stderr(predicates::str::contains("unsupported language standard `c++11`"));
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,37 @@
+//! Helpers shared by the integration tests; each test crate uses some.
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use ruscom::arena::TuArena;
+use ruscom::ast::{self, TranslationUnit};
+use ruscom::parser::Parser;
+use ruscom::preprocessor::Preprocessor;
+use ruscom::session::{Options, Session};
+use ruscom::vfs::MemoryFileSystem;
+
+/// Preprocesses and parses `source` as `main.cpp`, leaving any errors in
+/// the session's diagnostics; the tree must still be well formed.
+pub fn parse(source: &str) -> (Session, TranslationUnit<'static>) { parse_files(&[("main.cpp", source)], Options::default()) }
+
+/// Like `parse`, for `source` that must have no errors.
+pub fn parse_valid(source: &str) -> (Session, TranslationUnit<'static>) {
+    let (s, tu) = parse(source);
+    assert!(s.diagnostics.is_empty(), "{:?}", s.diagnostics.iter().map(|d| d.message.clone()).collect::<Vec<_>>());
+    (s, tu)
+}
+
+/// Like `parse`, for the first of `files`, given as paths and contents,
+/// with the others beside it to include, under `options`.
+pub fn parse_files(files: &[(&str, &str)], options: Options) -> (Session, TranslationUnit<'static>) {
+    let mut fs = MemoryFileSystem::new();
+    for &(path, text) in files { fs.insert(path, text); }
+    let mut s = Session::new(options).with_file_system(Arc::new(fs));
+    let main = s.sources.load(s.fs.clone().as_ref(), files[0].0.as_ref()).unwrap();
+    let out = Preprocessor::new(&mut s).run(main).unwrap();
+    // The tree outlives the helper, so its arena lives as long as the test.
+    let arena = Box::leak(Box::new(TuArena::new()));
+    let tu = Parser::new(&mut s, arena, out.tokens).parse().unwrap();
+    assert_eq!(ast::verify(&tu, &s.sources), [], "{}", files[0].1);
+    (s, tu)
+}
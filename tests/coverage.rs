@@ -0,0 +1,131 @@
+mod common;
+
+use std::sync::Arc;
+
+use assert_cmd::Command;
+
+use ruscom::arena::TuArena;
+use ruscom::ast::{ConstructCategory, Decl, StmtKind, TranslationUnit};
+use ruscom::coverage::{self, Coverage, Report};
+use ruscom::parser::Parser;
+use ruscom::preprocessor::Preprocessor;
+use ruscom::session::{Options, Session};
+use ruscom::vfs::MemoryFileSystem;
+
+fn analyze(source: &str) -> (Session, TranslationUnit<'static>) {
+    let options = Options { skip_unsupported: true, ..Options::default() };
+    common::parse_files(&[("main.cpp", source), ("lib.h", "using enum E;\nint from_header;\n")], options)
+}
+
+fn categories(coverage: &Coverage) -> Vec<ConstructCategory> { coverage.unsupported.iter().map(|u| u.category).collect() }
+
+const SOURCE: &str = r#"#include "lib.h"
+using enum std::byte;
+extern "Java" { int puts(const char*); }
+struct S {
+    int x;
+    decltype(x) y;
+    int get() const { return x; }
+};
+int f(int n) {
+    auto g = [n](int k) { return n + k; };
+    try { throw 1; } catch (...) { }
+    if constexpr (true) { n++; } else { n--; }
+    return n + g(1);
+}
+int after = 1;
+"#;
+
+#[test]
+fn unsupported_constructs_are_skipped_whole_and_parsing_goes_on() {
+    let (s, tu) = analyze(SOURCE);
+    assert!(s.diagnostics.is_empty(), "{:?}", s.diagnostics.iter().map(|d| d.message.clone()).collect::<Vec<_>>());
+    let names: Vec<String> = tu.decls.iter().map(|d| d.name().to_string()).collect();
+    eprintln!("{:?}", names);
+    assert_eq!(names, ["", "from_header", "", "", "S", "f", "after"]);
+    let Decl::Unsupported(u) = &tu.decls[3] else { panic!("{:?}", tu.decls[3]) };
+    assert_eq!(s.sources.snippet(u.span), Some(r#"extern "Java" { int puts(const char*); }"#));
+    let Decl::Function(f) = &tu.decls[5] else { panic!() };
+    let stmts = &f.body.as_ref().unwrap().stmts;
+    assert_eq!(stmts.len(), 4);
+    let StmtKind::Unsupported(u) = &stmts[2].kind else { panic!("{:?}", stmts[2]) };
+    assert_eq!(s.sources.snippet(u.span), Some("if constexpr (true) { n++; } else { n--; }"));
+    assert!(matches!(stmts[3].kind, StmtKind::Return(Some(_))));
+}
+
+#[test]
+fn coverage_counts_the_main_file_and_guesses_categories() {
+    let (_, tu) = analyze(SOURCE);
+    let coverage = Coverage::of(&tu);
+    eprintln!("{}", coverage);
+    assert_eq!(categories(&coverage), [
+        ConstructCategory::UsingEnum,
+        ConstructCategory::LinkageSpecification,
+        ConstructCategory::Decltype,
+        ConstructCategory::Lambda,
+        ConstructCategory::Exceptions,
+        ConstructCategory::ConstexprIf,
+    ]);
+    // S, x, get, its return, f, its return and `after`.
+    assert_eq!(coverage.parsed, 7);
+    assert_eq!(coverage.to_string(), "parsed 7 of 13 constructs (53.8%)");
+
+    let mut total = coverage.clone();
+    total.merge(coverage);
+    assert_eq!(total.by_category()[0], (ConstructCategory::LinkageSpecification, 2));
+    assert_eq!(total.total(), 26);
+}
+
+#[test]
+fn without_the_option_unsupported_constructs_are_errors() {
+    let mut fs = MemoryFileSystem::new();
+    fs.insert("main.cpp", "using enum E;\nint x;\n");
+    let mut s = Session::new(Options::default()).with_file_system(Arc::new(fs));
+    let main = s.sources.load(s.fs.clone().as_ref(), "main.cpp".as_ref()).unwrap();
+    let out = Preprocessor::new(&mut s).run(main).unwrap();
+    let arena = TuArena::new();
+    let tu = Parser::new(&mut s, &arena, out.tokens).parse().unwrap();
+    assert_eq!(s.diagnostics.error_count(), 1);
+    assert!(!tu.decls.iter().any(|d| matches!(d, Decl::Unsupported(_))));
+}
+
+#[test]
+fn reports_add_up_files_and_rank_what_to_support_next() {
+    let mut report = Report::default();
+    for source in ["using enum E;\nint x;\n", "int f() { auto g = [] {}; return 0; }\nusing enum E;\n", "int y;\n"] {
+        let (_, tu) = analyze(source);
+        report.add(Coverage::of(&tu));
+    }
+    eprint!("{}", report);
+    assert_eq!((report.files, report.complete_files), (3, 1));
+    assert_eq!(report.roadmap(), [(ConstructCategory::UsingEnum, 2, 2), (ConstructCategory::Lambda, 1, 1)]);
+    assert_eq!(report.coverage.by_kind(), [("variable", 2), ("function", 1), ("return", 1)]);
+    assert!(report.to_string().contains("      2 using enum (in 2 files)\n"));
+}
+
+#[test]
+fn sources_are_found_recursively_outside_hidden_directories() {
+    let dir = std::env::temp_dir().join(format!("ruscom_coverage_{}", std::process::id()));
+    for (path, text) in [("src/a.cpp", ""), ("src/b.txt", ""), ("include/a.hpp", ""), (".git/c.cpp", ""), ("main.c", "")] {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, text).unwrap();
+    }
+    let found = coverage::sources(&dir).unwrap();
+    let found: Vec<_> = found.iter().map(|p| p.strip_prefix(&dir).unwrap().to_string_lossy().into_owned()).collect();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(found, ["include/a.hpp", "main.c", "src/a.cpp"]);
+}
+
+#[test]
+fn cli_reports_project_coverage() {
+    let dir = std::env::temp_dir().join(format!("ruscom_coverage_cli_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.cpp"), "extern \"Java\" { int puts(const char*); }\nint main() { return 0; }\n").unwrap();
+    let assert = Command::cargo_bin("ruscom").unwrap().arg("coverage-report").arg(&dir).assert().success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    std::fs::remove_dir_all(&dir).unwrap();
+    eprint!("{}", stdout);
+    assert!(stdout.starts_with("1 files, 0 parsed completely\nparsed 2 of 3 constructs (66.7%)\n"));
+    assert!(stdout.contains("      1 linkage specification (in 1 file)\n"));
+}
@@ -0,0 +1,291 @@
+use std::sync::Arc;
+
+use assert_cmd::Command;
+use serde_json::{json, Value};
+
+use ruscom::daemon::{Daemon, INVALID_PARAMS, METHOD_NOT_FOUND, PARSE_ERROR, REQUEST_FAILED};
+use ruscom::session::{Options, Session};
+use ruscom::vfs::MemoryFileSystem;
+
+fn request(daemon: &mut Daemon, id: u64, method: &str, params: Value) -> Value {
+    let req = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+    let resp = daemon.handle(&req).expect("request with id got no response");
+    eprintln!("{} -> {}", method, resp);
+    assert_eq!(resp["id"], id);
+    resp
+}
+
+#[test]
+fn tokenize_returns_tokens_and_diagnostics() {
+    let mut daemon = Daemon::new(Session::new(Options::default()));
+    let resp = request(&mut daemon, 1, "tokenize", json!({ "source": "int x;" }));
+    let tokens = resp["result"]["tokens"].as_array().unwrap();
+    assert_eq!(tokens.len(), 3);
+    assert_eq!(tokens[1], json!({ "kind": "Identifier", "text": "x", "start": 4, "end": 5 }));
+    assert!(resp["result"]["diagnostics"].as_array().unwrap().is_empty());
+
+    let resp = request(&mut daemon, 2, "tokenize", json!({ "source": "char c = 'a", "path": "bad.cpp" }));
+    let diags = resp["result"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diags.len(), 1);
+    assert_eq!(diags[0]["spans"][0]["file"], "bad.cpp");
+}
+
+#[test]
+fn session_is_reused_across_requests() {
+    let mut daemon = Daemon::new(Session::new(Options::default()));
+    request(&mut daemon, 1, "tokenize", json!({ "source": "int shared;" }));
+    request(&mut daemon, 2, "tokenize", json!({ "source": "long shared;" }));
+    let stats = request(&mut daemon, 3, "stats", Value::Null);
+    // The second request's source replaced the first's under `<input>`.
+    assert_eq!(stats["result"]["files"], 1);
+    // `int`, `long` and `shared`: the second `shared` reuses the first.
+    assert_eq!(stats["result"]["symbols"], 3);
+}
+
+#[test]
+fn repeated_requests_reuse_the_file_of_a_path() {
+    let mut fs = MemoryFileSystem::new();
+    fs.insert("v.h", "int v;\n");
+    let mut daemon = Daemon::new(Session::new(Options::default()).with_file_system(Arc::new(fs)));
+    let sources = ["#include \"v.h\"\nint a = v;\n", "#include \"v.h\"\nint a = v + 1;\n", "#include \"v.h\"\nint a;\n"];
+    for (id, source) in (1..).zip(sources.iter().cycle().take(9)) {
+        for method in ["tokenize", "parse", "inlayHints", "codeAction"] {
+            let resp = request(&mut daemon, id, method, json!({ "source": source, "path": "main.cpp" }));
+            assert!(resp.get("error").is_none());
+        }
+    }
+    let stats = request(&mut daemon, 99, "stats", Value::Null);
+    // `<built-in>`, `v.h`, and `main.cpp` twice: the second source outgrew
+    // the first's positions while a header came after them.
+    assert_eq!(stats["result"]["files"], 4, "the source manager grew with every request");
+    // What the last request saw is what is loaded now.
+    let resp = request(&mut daemon, 100, "tokenize", json!({ "source": "int b;", "path": "main.cpp" }));
+    assert_eq!(resp["result"]["tokens"][1]["text"], "b");
+}
+
+#[test]
+fn parse_returns_the_syntax_tree() {
+    let mut daemon = Daemon::new(Session::new(Options::default()));
+    let resp = request(&mut daemon, 1, "parse", json!({ "source": "int f(int a);\n", "path": "p.cpp" }));
+    let decl = &resp["result"]["ast"]["inner"][0];
+    assert_eq!(decl["kind"], "FunctionDecl");
+    assert_eq!(decl["detail"], "f 'int (int)'");
+    assert_eq!(decl["loc"], json!({ "line": 1, "col": 5 }));
+    assert!(resp["result"]["diagnostics"].as_array().unwrap().is_empty());
+
+    let resp = request(&mut daemon, 2, "parse", json!({ "source": "int f(;\n", "path": "p.cpp" }));
+    let diags = resp["result"]["diagnostics"].as_array().unwrap();
+    assert!(!diags.is_empty());
+    assert_eq!(diags[0]["spans"][0]["file"], "p.cpp");
+}
+
+#[test]
+fn errors_follow_json_rpc() {
+    let mut daemon = Daemon::new(Session::new(Options::default()));
+    let resp = request(&mut daemon, 1, "frobnicate", Value::Null);
+    assert_eq!(resp["error"]["code"], METHOD_NOT_FOUND);
+    let resp: Value = serde_json::from_str(&daemon.handle_line("{not json").unwrap()).unwrap();
+    assert_eq!(resp["error"]["code"], PARSE_ERROR);
+    let notification = json!({ "jsonrpc": "2.0", "method": "stats" });
+    assert!(daemon.handle(&notification).is_none());
+}
+
+#[test]
+fn daemon_subcommand_serves_stdio() {
+    let input = concat!(
+        r#"{"jsonrpc":"2.0","id":1,"method":"tokenize","params":{"source":"a + b"}}"#, "\n",
+        r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#, "\n",
+        r#"{"jsonrpc":"2.0","id":3,"method":"stats"}"#, "\n",
+    );
+    let mut cmd = Command::cargo_bin("ruscom").expect("binary not built");
+    let assert = cmd.arg("daemon").write_stdin(input).assert().success();
+    let out = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    let lines: Vec<Value> = out.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(lines.len(), 2, "requests after shutdown must not be answered");
+    assert_eq!(lines[0]["result"]["tokens"].as_array().unwrap().len(), 3);
+    assert_eq!(lines[1]["result"], Value::Null);
+}
+
+#[test]
+fn inlay_hints_follow_lsp() {
+    let mut daemon = Daemon::new(Session::new(Options::default()));
+    let source = "int area(int width, int height);\nint x = area(1, 2);\nauto y = x;\n";
+    let resp = request(&mut daemon, 1, "inlayHints", json!({ "source": source, "path": "hints.cpp" }));
+    let hints = resp["result"]["hints"].as_array().unwrap();
+    assert_eq!(hints.len(), 3);
+    assert_eq!(hints[0], json!({ "position": { "line": 1, "character": 13 }, "label": "width:", "kind": 2, "paddingRight": true }));
+    assert_eq!(hints[2], json!({ "position": { "line": 2, "character": 6 }, "label": ": int", "kind": 1, "paddingRight": false }));
+
+    let resp = request(&mut daemon, 2, "inlayHints", json!({ "source": source, "parameterNames": false }));
+    assert_eq!(resp["result"]["hints"].as_array().unwrap().len(), 1);
+}
+
+const RENAME_SOURCE: &str = "namespace geo {
+struct Box {
+    int width;
+    Box(int w) : width(w) {}
+    ~Box();
+    int area() const;
+};
+Box::~Box() {}
+int Box::area() const { return width * width; }
+}
+int count = 0;
+int main() {
+    geo::Box b(2);
+    int total = b.width + count;
+    { int count = total; total = count; }
+    return b.area() + total;
+}
+";
+
+/// The `[line, character]` each edit of a rename replaces, for `path`.
+fn edit_starts(resp: &Value, path: &str) -> Vec<(u64, u64)> {
+    resp["result"]["changes"][path].as_array().unwrap().iter()
+        .map(|e| (e["range"]["start"]["line"].as_u64().unwrap(), e["range"]["start"]["character"].as_u64().unwrap()))
+        .collect()
+}
+
+fn rename(daemon: &mut Daemon, line: u64, character: u64, new_name: &str) -> Value {
+    let params = json!({ "source": RENAME_SOURCE, "path": "rename.cpp", "position": { "line": line, "character": character }, "newName": new_name });
+    request(daemon, 1, "textDocument/rename", params)
+}
+
+#[test]
+fn rename_edits_every_reference() {
+    let mut daemon = Daemon::new(Session::new(Options::default()));
+    // The class, from its use in `main`: its constructor, destructor and
+    // out-of-line members follow.
+    let resp = rename(&mut daemon, 12, 10, "Rect");
+    assert_eq!(edit_starts(&resp, "rename.cpp"), [(1, 7), (3, 4), (4, 5), (7, 0), (7, 6), (8, 4), (12, 9)]);
+    let edit = &resp["result"]["changes"]["rename.cpp"][0];
+    assert_eq!(edit["range"]["end"], json!({ "line": 1, "character": 10 }));
+    assert_eq!(edit["newText"], "Rect");
+    // A member, through the initializer list, the class's scope and `.`.
+    let resp = rename(&mut daemon, 2, 8, "size");
+    assert_eq!(edit_starts(&resp, "rename.cpp"), [(2, 8), (3, 17), (8, 31), (8, 39), (13, 18)]);
+    // The global `count`, not the block's.
+    let resp = rename(&mut daemon, 10, 5, "counter");
+    assert_eq!(edit_starts(&resp, "rename.cpp"), [(10, 4), (13, 26)]);
+    let resp = rename(&mut daemon, 14, 35, "inner");
+    assert_eq!(edit_starts(&resp, "rename.cpp"), [(14, 10), (14, 33)]);
+}
+
+#[test]
+fn rename_conflicts_are_errors() {
+    let mut daemon = Daemon::new(Session::new(Options::default()));
+    let cases = [
+        // `total` is declared in `main` already.
+        ((12, 13), "total", "rename.cpp:14:9: `total` is already declared in this scope"),
+        // `main`'s `total` would capture the use of the global `count`.
+        ((13, 26), "total", "rename.cpp:14:27: this use would refer to another `total` declared in an inner scope"),
+        // The block's `count` would hide `main`'s `total` from the use
+        // after it.
+        ((14, 10), "total", "rename.cpp:15:19: this use of `total` would refer to the renamed declaration"),
+        // The constructor's parameter would capture `width(w)`.
+        ((2, 8), "w", "rename.cpp:4:18: this use would refer to another `w` declared in an inner scope"),
+        ((10, 4), "main", "rename.cpp:12:5: `main` is already declared in this scope"),
+    ];
+    for ((line, character), new_name, message) in cases {
+        let resp = rename(&mut daemon, line, character, new_name);
+        assert_eq!(resp["error"]["code"], REQUEST_FAILED);
+        assert_eq!(resp["error"]["message"], message);
+    }
+    let resp = rename(&mut daemon, 10, 4, "while");
+    assert_eq!(resp["error"]["code"], INVALID_PARAMS);
+    let resp = rename(&mut daemon, 0, 0, "x");
+    assert_eq!(resp["error"]["code"], REQUEST_FAILED);
+    assert_eq!(resp["error"]["message"], "no symbol to rename at rename.cpp:1:1");
+}
+
+#[test]
+fn rename_spans_included_files() {
+    let mut fs = MemoryFileSystem::new();
+    fs.insert("shapes.h", "int area(int w, int h);\n#define AREA area(1, 1)\n");
+    let mut daemon = Daemon::new(Session::new(Options::default()).with_file_system(Arc::new(fs)));
+    let source = "#include \"shapes.h\"\nint area(int w, int h) { return w * h; }\nint x = area(2, 3) + AREA;\n";
+    let params = json!({ "source": source, "path": "main.cpp", "position": { "line": 2, "character": 8 }, "newName": "surface" });
+    let resp = request(&mut daemon, 1, "rename", params);
+    // Not the `area` in the macro, which is spelled in its definition.
+    assert_eq!(edit_starts(&resp, "shapes.h"), [(0, 4)]);
+    assert_eq!(edit_starts(&resp, "main.cpp"), [(1, 4), (2, 8)]);
+}
+
+#[test]
+fn positions_count_utf16_code_units() {
+    let mut daemon = Daemon::new(Session::new(Options::default()));
+    // `😀` is two UTF-16 code units, `é` one: LSP counts neither as bytes.
+    let source = "const char *s = \"😀é\"; int x = 1; auto y = x;\n";
+    let character = |prefix: &str| prefix.encode_utf16().count() as u64;
+    let resp = request(&mut daemon, 1, "inlayHints", json!({ "source": source }));
+    let hint = &resp["result"]["hints"][0];
+    assert_eq!(hint["position"], json!({ "line": 0, "character": character("const char *s = \"😀é\"; int x = 1; auto y") }));
+    let x = character("const char *s = \"😀é\"; int ");
+    let params = json!({ "source": source, "path": "u.cpp", "position": { "line": 0, "character": x + 16 }, "newName": "w" });
+    let resp = request(&mut daemon, 2, "rename", params);
+    assert_eq!(edit_starts(&resp, "u.cpp"), [(0, x), (0, x + 16)]);
+    assert_eq!(resp["result"]["changes"]["u.cpp"][0]["range"]["end"], json!({ "line": 0, "character": x + 1 }));
+}
+
+#[test]
+fn on_type_formatting_reindents_around_the_cursor() {
+    let mut daemon = Daemon::new(Session::new(Options::default()));
+    // `}` typed at the end of line 3 re-indents its block.
+    let source = "int f() {\nif (x) {\nreturn 1;\n}\n  int y;\n}\n";
+    let resp = request(&mut daemon, 1, "textDocument/onTypeFormatting", json!({
+        "source": source, "position": { "line": 3, "character": 1 }, "ch": "}",
+    }));
+    let edits = resp["result"].as_array().unwrap();
+    let lines: Vec<u64> = edits.iter().map(|e| e["range"]["start"]["line"].as_u64().unwrap()).collect();
+    assert_eq!(lines, [1, 2, 3]);
+    assert_eq!(edits[1], json!({
+        "range": { "start": { "line": 2, "character": 0 }, "end": { "line": 2, "character": 0 } },
+        "newText": "        ",
+    }));
+
+    // A newline indents the new, still empty, line.
+    let resp = request(&mut daemon, 2, "onTypeFormatting", json!({
+        "source": "int f() {\n  int x;\n\n}\n", "position": { "line": 2, "character": 0 }, "ch": "\n",
+        "options": { "tabSize": 2, "insertSpaces": false },
+    }));
+    let edits = resp["result"].as_array().unwrap();
+    assert_eq!(edits.len(), 2);
+    assert_eq!(edits[0]["range"]["end"], json!({ "line": 1, "character": 2 }));
+    assert_eq!(edits[0]["newText"], "\t");
+    assert_eq!(edits[1]["range"]["start"]["line"], 2);
+    assert_eq!(edits[1]["newText"], "\t");
+
+    let resp = request(&mut daemon, 3, "onTypeFormatting", json!({ "source": "int x;", "ch": ";" }));
+    assert_eq!(resp["error"]["code"], INVALID_PARAMS);
+}
+
+#[test]
+fn code_actions_offer_fixits_and_includes() {
+    let mut daemon = Daemon::new(Session::new(Options::default()));
+    let source = "#include <cstddef>\n[[nodiscard]] int h();\nint main() {\n  h();\n  size_t n = strlen(\"x\")\n  std::vector<int> v;\n  return 0;\n}\n";
+    let resp = request(&mut daemon, 1, "textDocument/codeAction", json!({ "source": source, "path": "a.cpp" }));
+    let actions = resp["result"].as_array().unwrap();
+    let titles: Vec<&str> = actions.iter().map(|a| a["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, ["Insert `(void)`", "Insert `;`", "Add `#include <cstring>`", "Add `#include <vector>`"]);
+    assert!(actions.iter().all(|a| a["kind"] == "quickfix"));
+    assert_eq!(actions[1]["diagnostics"][0]["code"], "E0201");
+    assert_eq!(actions[1]["edit"]["changes"]["a.cpp"], json!([{
+        "range": { "start": { "line": 4, "character": 24 }, "end": { "line": 4, "character": 24 } },
+        "newText": ";",
+    }]));
+    assert_eq!(actions[3]["edit"]["changes"]["a.cpp"][0]["range"]["start"], json!({ "line": 1, "character": 0 }));
+    assert_eq!(actions[3]["edit"]["changes"]["a.cpp"][0]["newText"], "#include <vector>\n");
+
+    // Only what the range touches.
+    let resp = request(&mut daemon, 2, "codeAction", json!({
+        "source": source, "path": "a.cpp",
+        "range": { "start": { "line": 3, "character": 0 }, "end": { "line": 3, "character": 99 } },
+    }));
+    let titles: Vec<&str> = resp["result"].as_array().unwrap().iter().map(|a| a["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, ["Insert `(void)`"]);
+
+    let resp = request(&mut daemon, 3, "codeAction", json!({
+        "source": source, "range": { "start": { "line": 30, "character": 0 }, "end": { "line": 31, "character": 0 } },
+    }));
+    assert_eq!(resp["error"]["code"], INVALID_PARAMS);
+}
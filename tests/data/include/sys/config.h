@@ -0,0 +1 @@
+typedef int config_t;
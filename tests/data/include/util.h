@@ -0,0 +1,2 @@
+int helper();
+#include <sys/config.h>
@@ -0,0 +1,4 @@
+#include "common.h"
+#warning a1
+int a;
+#bogus
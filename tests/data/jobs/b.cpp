@@ -0,0 +1,4 @@
+#include "common.h"
+#if 1 +
+#endif
+char c = '\\q';
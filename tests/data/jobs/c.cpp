@@ -0,0 +1,3 @@
+#include "missing.h"
+#warning c1
+#error c2
@@ -0,0 +1,4 @@
+#warning d1
+#include "common.h"
+#define D 1
+#define D 2
@@ -0,0 +1,5 @@
+#define INNER(x) ((x) + 1)
+#define MIDDLE(x) INNER(x) * INNER(x)
+#define OUTER(x) MIDDLE(x)
+int a = OUTER(0);
+int b = INNER(2);
@@ -0,0 +1,88 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use assert_cmd::Command;
+use ruscom::depfile::{self, DepfileOptions};
+use ruscom::driver::Driver;
+use ruscom::session::{Options, Session};
+use ruscom::vfs::MemoryFileSystem;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ruscom_depfile_{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn rules_escape_make_specials() {
+    let rule = depfile::rule("out/a.o".as_ref(), &["my file.cpp".into(), "cost$.h".into(), "#x.h".into()]);
+    eprintln!("{}", rule);
+    assert_eq!(rule, "out/a.o: \\\n  my\\ file.cpp \\\n  cost$$.h \\\n  \\#x.h\n");
+    assert_eq!(depfile::object_path("src/a.cpp".as_ref(), None), PathBuf::from("a.o"));
+    assert_eq!(depfile::default_path("build/a.o".as_ref()), PathBuf::from("build/a.d"));
+}
+
+fn run(depfile: DepfileOptions, output: PathBuf) -> Driver {
+    let mut fs = MemoryFileSystem::new();
+    fs.insert("main.cpp", "#include \"a.h\"\n#include <sys.h>\n#include \"a.h\"\nint x;\n");
+    fs.insert("a.h", "#pragma once\nint a;\n");
+    fs.insert("root/include/sys.h", "int s;\n");
+    let options = Options {
+        inputs: vec!["main.cpp".into()],
+        output: Some(output),
+        include_dirs: vec!["root/include".into()],
+        sysroot: Some("root".into()),
+        depfile: Some(depfile),
+        ..Options::default()
+    };
+    let mut driver = Driver::from_session(Session::new(options).with_file_system(Arc::new(fs)));
+    driver.preprocess().unwrap();
+    driver
+}
+
+#[test]
+fn md_lists_each_included_header_once() {
+    let dir = temp_dir("md");
+    run(DepfileOptions::default(), dir.join("main.o"));
+    let text = std::fs::read_to_string(dir.join("main.d")).unwrap();
+    eprintln!("{}", text);
+    let target = dir.join("main.o").display().to_string();
+    assert_eq!(text, format!("{}: \\\n  main.cpp \\\n  a.h \\\n  root/include/sys.h\n", target));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn mmd_skips_system_headers_and_mf_picks_the_file() {
+    let dir = temp_dir("mmd");
+    let mf = dir.join("deps.mk");
+    run(DepfileOptions { path: Some(mf.clone()), skip_system_headers: true }, "main.o".into());
+    let text = std::fs::read_to_string(&mf).unwrap();
+    eprintln!("{}", text);
+    assert_eq!(text, "main.o: \\\n  main.cpp \\\n  a.h\n");
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn cli_accepts_gcc_spellings() {
+    let dir = temp_dir("cli");
+    let mf = dir.join("pp_main.d");
+    let mut cmd = Command::cargo_bin("ruscom").expect("binary not built");
+    cmd.args(["compile", "tests/data/pp_main.cpp", "-I", "tests/data/include", "-MD", "-MF"]).arg(&mf).assert().success();
+    let text = std::fs::read_to_string(&mf).unwrap();
+    eprintln!("{}", text);
+    assert!(text.starts_with("pp_main.o: \\\n  tests/data/pp_main.cpp \\\n"));
+    assert!(text.contains("tests/data/include/sys/config.h"));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn builtin_headers_are_not_dependencies() {
+    let dir = temp_dir("builtin");
+    let (src, mf) = (dir.join("size.cpp"), dir.join("size.d"));
+    std::fs::write(&src, "#include <cstddef>\nstd::size_t n;\n").unwrap();
+    Command::cargo_bin("ruscom").unwrap().arg("compile").arg(&src).arg("-MD").arg("-MF").arg(&mf).assert().success();
+    let text = std::fs::read_to_string(&mf).unwrap();
+    eprintln!("{}", text);
+    assert_eq!(text, format!("size.o: \\\n  {}\n", src.display()));
+    std::fs::remove_dir_all(&dir).ok();
+}
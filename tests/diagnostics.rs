@@ -0,0 +1,191 @@
+use assert_cmd::Command;
+use ruscom::diagnostics::{json, Diagnostic, Diagnostics, Emitter, Label, Level, Warning, WarningOption, WarningOptions};
+use ruscom::lexer::token::Span;
+use ruscom::session::{Options, Session};
+use ruscom::source::SourceManager;
+
+#[test]
+fn renders_snippet_with_carets() {
+    let mut sm = SourceManager::new();
+    sm.add_file("main.cpp", "int main() {\n    int x = y;\n}\n");
+    let diag = Diagnostic::error("use of undeclared identifier `y`")
+        .with_code("E0100")
+        .with_label(Label::primary(Span::new(25, 26), "not found in this scope"))
+        .with_label(Label::secondary(Span::new(21, 22), "while initializing this"))
+        .with_note("identifiers must be declared before use");
+    let out = Emitter::new(false).render(&diag, &sm);
+    eprintln!("{}", out);
+    let expected = "\
+error[E0100]: use of undeclared identifier `y`
+ --> main.cpp:2:13
+  |
+2 |     int x = y;
+  |         - while initializing this
+  |             ^ not found in this scope
+  |
+  = note: identifiers must be declared before use
+";
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn lex_errors_become_diagnostics() {
+    let mut session = Session::new(Options::default());
+    session.tokenize("char c = '\\q';").unwrap();
+    assert_eq!(session.diagnostics.error_count(), 1);
+    let d = session.diagnostics.iter().next().unwrap();
+    assert_eq!(d.level, Level::Error);
+    assert_eq!(d.code.as_deref(), Some("E0003"));
+    let mut diags = Diagnostics::new();
+    diags.emit(d.clone());
+    let out = Emitter::new(false).render_all(&diags, &session.sources);
+    eprintln!("{}", out);
+    assert!(out.contains("1 | char c = '\\q';"));
+    assert!(out.contains("error: aborting due to 1 error"));
+}
+
+#[test]
+fn lex_command_shows_offending_line() {
+    let dir = std::env::temp_dir().join(format!("ruscom-diag-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("bad.cpp");
+    std::fs::write(&path, "int a;\nconst char* s = \"open;\n").unwrap();
+    let mut cmd = Command::cargo_bin("ruscom").expect("binary not built");
+    let assert = cmd.arg("lex").arg(&path).assert().failure();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+    let _ = std::fs::remove_dir_all(&dir);
+    eprintln!("{}", stderr);
+    assert!(stderr.contains("error[E0001]: unterminated string literal"));
+    assert!(stderr.contains("2 | const char* s = \"open;"));
+    assert!(stderr.contains("^^^^^^ string starts here and never ends"));
+}
+
+fn lex_error_session() -> Session {
+    let mut session = Session::new(Options::default());
+    let file = session.sources.add_file("src/bad.cpp", "int a;\nchar c = '\\q';\n");
+    session.tokenize_file(file).unwrap();
+    session
+}
+
+#[test]
+fn json_lines_carry_spans_and_codes() {
+    let session = lex_error_session();
+    let out = json::render_json_lines(&session.diagnostics, &session.sources);
+    eprintln!("{}", out);
+    assert_eq!(out.lines().count(), 1);
+    let v: serde_json::Value = serde_json::from_str(out.lines().next().unwrap()).unwrap();
+    assert_eq!(v["severity"], "error");
+    assert_eq!(v["code"], "E0003");
+    assert_eq!(v["spans"][0]["file"], "src/bad.cpp");
+    assert_eq!(v["spans"][0]["line_start"], 2);
+    assert_eq!(v["spans"][0]["column_start"], 10);
+    assert_eq!(v["spans"][0]["byte_start"], 16);
+    assert_eq!(v["spans"][0]["primary"], true);
+}
+
+#[test]
+fn sarif_log_has_required_structure() {
+    let session = lex_error_session();
+    let log = json::to_sarif(&session.diagnostics, &session.sources);
+    eprintln!("{:#}", log);
+    assert_eq!(log["version"], "2.1.0");
+    let run = &log["runs"][0];
+    assert_eq!(run["tool"]["driver"]["name"], "ruscom");
+    assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "E0003");
+    let result = &run["results"][0];
+    assert_eq!(result["ruleId"], "E0003");
+    assert_eq!(result["ruleIndex"], 0);
+    assert_eq!(result["level"], "error");
+    assert!(result["message"]["text"].as_str().unwrap().starts_with("invalid escape sequence"));
+    let region = &result["locations"][0]["physicalLocation"]["region"];
+    assert_eq!(region["startLine"], 2);
+    assert_eq!(region["startColumn"], 10);
+    assert_eq!(result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"], "src/bad.cpp");
+}
+
+#[test]
+fn diagnostic_format_flag_emits_sarif() {
+    let mut cmd = Command::cargo_bin("ruscom").expect("binary not built");
+    let assert = cmd
+        .args(["lex", "x.cpp", "--overlay", "x.cpp=tests/data/sample1.cpp", "--diagnostic-format", "sarif"])
+        .assert()
+        .success();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+    let log: serde_json::Value = serde_json::from_str(&stderr).expect("stderr is not a SARIF document");
+    assert_eq!(log["runs"][0]["results"].as_array().unwrap().len(), 0);
+}
+
+#[test]
+fn errors_past_the_limit_are_dropped_with_a_note() {
+    let mut diags = Diagnostics::with_error_limit(2);
+    diags.emit(Diagnostic::warning("first"));
+    diags.emit(Diagnostic::error("one"));
+    assert!(!diags.limit_reached());
+    diags.emit(Diagnostic::error("two"));
+    diags.emit(Diagnostic::error("three"));
+    diags.emit(Diagnostic::warning("late"));
+    let messages: Vec<&str> = diags.iter().map(|d| d.message.as_str()).collect();
+    eprintln!("{:?}", messages);
+    assert_eq!(messages, ["first", "one", "two", "too many errors emitted, stopping now (--error-limit 2)"]);
+    assert!(diags.limit_reached());
+    // Taking what was collected does not lift the limit.
+    diags.take();
+    diags.emit(Diagnostic::error("four"));
+    assert!(diags.is_empty());
+
+    let mut unlimited = Diagnostics::new();
+    for _ in 0..100 { unlimited.emit(Diagnostic::error("again")); }
+    assert_eq!(unlimited.error_count(), 100);
+}
+
+#[test]
+fn warnings_are_turned_on_and_off_and_made_errors_by_category() {
+    let options = |flags: &[&str]| {
+        let mut warnings = WarningOptions::new();
+        for flag in flags { warnings.apply(flag.parse().unwrap()); }
+        warnings
+    };
+    let none = options(&[]);
+    assert!(none.is_enabled(Warning::UnusedResult) && !none.is_enabled(Warning::UnusedVariable) && !none.is_enabled(Warning::Shadow));
+    let all = options(&["all", "no-sign-compare"]);
+    assert!(all.is_enabled(Warning::UnusedVariable) && !all.is_enabled(Warning::SignCompare) && !all.is_enabled(Warning::UnusedParameter));
+    // A category named on its own wins over a group, before or after it.
+    assert!(!options(&["no-unused-variable", "all"]).is_enabled(Warning::UnusedVariable));
+    assert!(options(&["extra"]).is_enabled(Warning::UnusedParameter));
+    assert!(options(&["error=shadow"]).is_enabled(Warning::Shadow));
+    assert_eq!("no-error=shadow".parse(), Ok(WarningOption::ErrorFor(Warning::Shadow, false)));
+    assert_eq!("bogus".parse::<WarningOption>(), Err("unknown warning option `-Wbogus`".to_string()));
+    assert_eq!("no-bogus".parse::<WarningOption>(), Ok(WarningOption::DisableUnknown("bogus".to_string())));
+
+    let mut diags = Diagnostics::new().with_warnings(options(&["shadow", "error", "no-error=unused-result"]));
+    diags.emit(Diagnostic::warning("off").with_warning(Warning::UnusedVariable));
+    diags.emit(Diagnostic::warning("shadows").with_warning(Warning::Shadow));
+    diags.emit(Diagnostic::warning("discarded").with_warning(Warning::UnusedResult));
+    diags.emit(Diagnostic::warning("uncategorized"));
+    let found: Vec<(Level, &str, Option<Warning>)> = diags.iter().map(|d| (d.level, d.message.as_str(), d.category())).collect();
+    eprintln!("{:?}", found);
+    assert_eq!(found, [
+        (Level::Error, "shadows", Some(Warning::Shadow)),
+        (Level::Warning, "discarded", Some(Warning::UnusedResult)),
+        (Level::Error, "uncategorized", None),
+    ]);
+    assert_eq!(diags.iter().next().unwrap().notes, ["treated as an error because of `-Werror`"]);
+
+    let mut inhibited = options(&["all", "error", "no-bogus"]);
+    inhibited.inhibit();
+    assert!(!inhibited.is_enabled(Warning::UnusedResult));
+    let mut diags = Diagnostics::new().with_warnings(inhibited);
+    diags.emit(Diagnostic::warning("uncategorized"));
+    diags.note_unknown_options();
+    assert!(diags.is_empty());
+    diags.emit(Diagnostic::error("error"));
+    diags.note_unknown_options();
+    assert_eq!(diags.iter().map(|d| d.level).collect::<Vec<_>>(), [Level::Error, Level::Note]);
+
+    let mut sm = SourceManager::new();
+    sm.add_file("main.cpp", "int f(int x) { int y; return x; }\n");
+    let diag = Diagnostic::warning("unused variable `y`").with_warning(Warning::UnusedVariable).with_label(Label::primary(Span::new(19, 20), ""));
+    let out = Emitter::new(false).render(&diag, &sm);
+    eprintln!("{}", out);
+    assert!(out.starts_with("warning: unused variable `y` [-Wunused-variable]\n --> main.cpp:1:20\n"));
+}
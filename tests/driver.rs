@@ -0,0 +1,81 @@
+use ruscom::driver::{Driver, DriverError};
+use ruscom::session::Options;
+
+#[test]
+fn driver_lexes_all_inputs() {
+    let options = Options {
+        inputs: vec!["tests/data/sample1.cpp".into(), "tests/data/sample3.cpp".into()],
+        ..Options::default()
+    };
+    let mut driver = Driver::new(options);
+    let files = driver.lex().expect("lex failed");
+    for f in &files { eprintln!("{}: {} tokens", f.path.display(), f.output.tokens.len()); }
+    assert_eq!(files.len(), 2);
+    assert!(files.iter().all(|f| f.output.error.is_none() && !f.output.tokens.is_empty()));
+}
+
+#[test]
+fn missing_input_reports_path() {
+    let options = Options { inputs: vec!["tests/data/does_not_exist.cpp".into()], ..Options::default() };
+    let err = Driver::new(options).lex().unwrap_err();
+    assert!(matches!(err, DriverError::Io { .. }));
+    assert!(err.to_string().starts_with("tests/data/does_not_exist.cpp: "));
+}
+
+#[test]
+fn diagnostics_do_not_depend_on_job_count() {
+    let run = |jobs: &str| {
+        let mut cmd = assert_cmd::Command::cargo_bin("ruscom").expect("binary not built");
+        let assert = cmd
+            .args(["compile", "-j", jobs])
+            .args(["tests/data/jobs/d.cpp", "tests/data/jobs/c.cpp", "tests/data/jobs/b.cpp", "tests/data/jobs/a.cpp"])
+            .assert()
+            .failure();
+        String::from_utf8_lossy(&assert.get_output().stderr).to_string()
+    };
+    let serial = run("1");
+    eprintln!("{}", serial);
+    for _ in 0..4 { assert_eq!(run("8"), serial); }
+    // Source order: a.cpp first, even though it is the last input.
+    let a = serial.find("--> tests/data/jobs/a.cpp").unwrap();
+    let d = serial.find("--> tests/data/jobs/d.cpp").unwrap();
+    assert!(a < d);
+}
+
+#[test]
+fn parallel_preprocess_matches_serial() {
+    let inputs: Vec<std::path::PathBuf> = ["a", "b", "c", "d"].iter().map(|n| format!("tests/data/jobs/{}.cpp", n).into()).collect();
+    let snapshot = |jobs: usize| {
+        let mut driver = Driver::new(Options { inputs: inputs.clone(), jobs, ..Options::default() });
+        let files = driver.preprocess().unwrap();
+        let sources = &driver.session().sources;
+        let texts: Vec<Vec<String>> = files.iter()
+            .map(|f| f.output.tokens.iter().map(|t| sources.snippet(t.span).unwrap().to_string()).collect())
+            .collect();
+        (texts, driver.session().diagnostics.len())
+    };
+    assert_eq!(snapshot(4), snapshot(1));
+}
+
+#[test]
+fn check_reports_diagnostics_and_writes_nothing() {
+    let dir = std::env::temp_dir().join(format!("ruscom-check-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("ok.cpp"), "static_assert(sizeof(int) == 4);\nint main() { return 0; }\n").unwrap();
+    std::fs::write(dir.join("bad.cpp"), "static_assert(1 + 1 == 3, \"arithmetic\");\n").unwrap();
+    let run = |file: &str| {
+        assert_cmd::Command::cargo_bin("ruscom").unwrap().current_dir(&dir).args(["check", file, "-MD", "-MF", "deps.d"]).output().unwrap()
+    };
+    let output = run("ok.cpp");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(output.stdout.is_empty() && output.stderr.is_empty());
+    let output = run("bad.cpp");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    eprintln!("{}", stderr);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr.contains("arithmetic"));
+    let mut files: Vec<String> = std::fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().into_owned()).collect();
+    files.sort();
+    assert_eq!(files, ["bad.cpp", "ok.cpp"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,120 @@
+use assert_cmd::Command;
+use ruscom::format::{self, FormatOptions};
+
+const MESSY: &str = r#"#include <vector>
+#define SWAP(a, b) { \
+  auto t = a; a = b; b = t; }
+namespace geo {
+struct Point {
+public:
+int x,
+y;
+/* the origin
+   is (0, 0) */
+private:
+  static int count;
+};
+}
+int sign(int v) {
+switch (v > 0) {
+case true:
+return 1;
+default:
+  return v < 0
+? -1 : 0;
+}
+}
+"#;
+
+const FORMATTED: &str = r#"#include <vector>
+#define SWAP(a, b) { \
+  auto t = a; a = b; b = t; }
+namespace geo {
+    struct Point {
+    public:
+        int x,
+        y;
+        /* the origin
+   is (0, 0) */
+    private:
+        static int count;
+    };
+}
+int sign(int v) {
+    switch (v > 0) {
+        case true:
+            return 1;
+        default:
+            return v < 0
+                ? -1 : 0;
+    }
+}
+"#;
+
+#[test]
+fn format_reindents_by_nesting() {
+    let out = format::format(MESSY, FormatOptions::default());
+    eprintln!("{}", out);
+    assert_eq!(out, FORMATTED);
+    assert_eq!(format::format(FORMATTED, FormatOptions::default()), FORMATTED);
+    let tabs = format::format("void f() {\n  if (x)\n  g();\n}\n", FormatOptions { indent_width: 4, use_tabs: true });
+    assert_eq!(tabs, "void f() {\n\tif (x)\n\t\tg();\n}\n");
+}
+
+#[test]
+fn ranges_format_like_the_whole_file() {
+    let lines = MESSY.lines().count();
+    for start in 0..lines {
+        for end in start + 1..=lines {
+            let edits = format::format_lines(MESSY, start..end, FormatOptions::default());
+            assert!(edits.iter().all(|e| (start..end).contains(&e.line)));
+            let out = format::apply(MESSY, &edits);
+            for (i, (got, want)) in out.lines().zip(FORMATTED.lines()).enumerate() {
+                let expected = if (start..end).contains(&i) { want } else { MESSY.lines().nth(i).unwrap() };
+                assert_eq!(got, expected, "line {} formatting {}..{}", i, start, end);
+            }
+        }
+    }
+}
+
+#[test]
+fn on_type_formats_the_lines_typed_on() {
+    let source = "void f() {\nif (x) {\ng();\n}\n}\n";
+    let edits = format::on_type(source, 3, '}', FormatOptions::default());
+    eprintln!("{:?}", edits);
+    assert_eq!(edits.iter().map(|e| e.line).collect::<Vec<_>>(), [1, 2, 3]);
+    assert!(format::on_type(source, 2, ';', FormatOptions::default()).iter().all(|e| e.line == 2));
+    let edits = format::on_type("void f() {\n\n}\n", 1, '\n', FormatOptions::default());
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0].indent, "    ");
+    // Without a cursor on it, a blank line stays empty.
+    assert!(format::format_lines("void f() {\n\n}\n", 1..2, FormatOptions::default()).is_empty());
+}
+
+#[test]
+fn lex_errors_leave_the_rest_alone() {
+    let source = "int f() {\nint x;\nchar c = 'a\n  int y;\n}\n";
+    let out = format::format(source, FormatOptions::default());
+    assert_eq!(out, "int f() {\n    int x;\nchar c = 'a\n  int y;\n}\n");
+}
+
+#[test]
+fn cli_formats_files_and_line_ranges() {
+    let dir = std::env::temp_dir().join(format!("ruscom_format_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let src = dir.join("a.cpp");
+    std::fs::write(&src, MESSY).unwrap();
+    Command::cargo_bin("ruscom").unwrap().arg("format").arg(&src).assert().success().stdout(FORMATTED);
+    let assert = Command::cargo_bin("ruscom").unwrap()
+        .args(["format", "--lines", "16:18", "--indent-width", "2"]).arg(&src)
+        .assert().success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    eprintln!("{}", stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[15..18], ["  switch (v > 0) {", "    case true:", "      return 1;"]);
+    assert_eq!(lines[18], "default:");
+    Command::cargo_bin("ruscom").unwrap()
+        .args(["format", "--lines", "3"]).arg(&src)
+        .assert().failure();
+    std::fs::remove_dir_all(&dir).ok();
+}
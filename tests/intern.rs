@@ -0,0 +1,58 @@
+use ruscom::intern::{Interner, Symbol};
+use ruscom::types::{Type, TypeId};
+
+#[test]
+fn repeated_identifiers_share_a_symbol() {
+    let mut interner = Interner::new();
+    let a = interner.intern("std");
+    let b = interner.intern("int");
+    let c = interner.intern("std");
+    eprintln!("symbols: {:?} {:?} {:?}", a, b, c);
+    assert_eq!(a, c);
+    assert_ne!(a, b);
+    assert_eq!(interner.len(), 2);
+}
+
+#[test]
+fn resolve_round_trips() {
+    let mut interner = Interner::new();
+    let x = interner.intern("x");
+    let main = interner.intern("main");
+    assert_eq!(&*interner.resolve(x), "x");
+    assert_eq!(&*interner.resolve(main), "main");
+    assert_eq!(interner.get("main"), Some(main));
+    assert_eq!(interner.get("missing"), None);
+}
+
+#[test]
+fn symbols_debug_print_their_text() {
+    let a = Symbol::intern("vector");
+    assert_eq!(a, Symbol::intern("vector"));
+    assert_eq!(&*a.as_str(), "vector");
+    let dbg = format!("{:?}", a);
+    eprintln!("debug: {}", dbg);
+    assert!(dbg.contains("\"vector\""));
+}
+
+#[test]
+fn sessions_share_symbol_identity() {
+    let mut s1 = Interner::new();
+    let mut s2 = Interner::new();
+    assert_eq!(s1.intern("shared_name"), s2.intern("shared_name"));
+    assert_eq!(s1.get("only_in_s2"), None);
+    s2.intern("only_in_s2");
+    assert_eq!(s1.get("only_in_s2"), None);
+}
+
+#[test]
+fn type_ids_are_structural_and_readable() {
+    let int = TypeId::intern(Type::Int);
+    let p1 = TypeId::intern(Type::Pointer(int));
+    let p2 = TypeId::intern(Type::Pointer(TypeId::intern(Type::Int)));
+    assert_eq!(p1, p2);
+    let named = TypeId::intern(Type::Named(Symbol::intern("Widget")));
+    let r = TypeId::intern(Type::Reference(named));
+    eprintln!("debug: {:?} {:?}", p1, r);
+    assert_eq!(p1.to_string(), "int*");
+    assert_eq!(format!("{:?}", r), format!("TypeId({}, `Widget&`)", r.as_u32()));
+}
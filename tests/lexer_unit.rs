@@ -1,5 +1,6 @@
-use ruscom::lexer::token::Token;
-use ruscom::lexer::Lexer;
+use ruscom::lexer::token::LiteralChar::{Char, Unit};
+use ruscom::lexer::token::{Encoding, LexError, Literal, Token};
+use ruscom::lexer::{Lexer, LexerOptions};
 
 #[test]
 fn simple_ident_and_number() {
@@ -15,7 +16,7 @@ fn simple_ident_and_number() {
     eprintln!("simple_ident_and_number tokens ({}): {:?}", tokens.len(), tokens);
     assert_eq!(tokens[0], Token::Identifier("int".into()));
     assert_eq!(tokens[1], Token::Identifier("x".into()));
-    assert_eq!(tokens[2], Token::Operator("=".into()));
+    assert_eq!(tokens[2], Token::Operator("="));
     assert_eq!(tokens[3], Token::Number("42".into()));
     assert_eq!(tokens[4], Token::Punct(';'));
 }
@@ -33,3 +34,163 @@ fn comments_and_whitespace() {
     eprintln!("comments_and_whitespace tokens ({}): {:?}", tokens.len(), tokens);
     assert_eq!(tokens[0], Token::Identifier("foo".into()));
 }
+
+#[test]
+fn preserved_comments_carry_spans() {
+    let src = "// line\nint /* block */ x;";
+    let opts = LexerOptions { preserve_comments: true };
+    let mut lex = Lexer::with_options(src, opts);
+    let mut tokens = Vec::new();
+    loop {
+        let t = lex.next_token().unwrap();
+        if t.token == Token::Eof { break; }
+        tokens.push(t);
+    }
+    eprintln!("preserved_comments_carry_spans tokens ({}): {:?}", tokens.len(), tokens);
+    assert_eq!(tokens[0].token, Token::Comment { text: " line".into(), block: false });
+    assert_eq!(&src[tokens[0].span.start..tokens[0].span.end], "// line");
+    assert_eq!(tokens[1].token, Token::Identifier("int".into()));
+    assert_eq!(tokens[2].token, Token::Comment { text: " block ".into(), block: true });
+    assert_eq!(&src[tokens[2].span.start..tokens[2].span.end], "/* block */");
+    assert_eq!(tokens[3].token, Token::Identifier("x".into()));
+}
+
+#[test]
+fn backslash_newline_is_spliced() {
+    let src = "in\\\nt x = \"ab\\\ncd\";\\\r\n";
+    let mut lex = Lexer::new(src);
+    let mut tokens = Vec::new();
+    loop {
+        let t = lex.next_token().unwrap();
+        if t.token == Token::Eof { break; }
+        tokens.push(t);
+    }
+    eprintln!("backslash_newline_is_spliced tokens ({}): {:?}", tokens.len(), tokens);
+    assert_eq!(tokens.len(), 5);
+    assert_eq!(tokens[0].token, Token::Identifier("int".into()));
+    assert_eq!(&src[tokens[0].span.start..tokens[0].span.end], "in\\\nt");
+    assert_eq!(tokens[3].token, Token::StringLiteral("abcd".into()));
+    assert_eq!(&src[tokens[4].span.start..tokens[4].span.end], ";");
+}
+
+#[test]
+fn preprocessor_directives() {
+    let src = "#include <iostream>\n  # include \"local.h\"\n#define SQ(x) ((x) * (x))\nint a = 1 # 2;\n#ifdef X\n#endif";
+    let mut lex = Lexer::new(src);
+    let mut tokens = Vec::new();
+    while let Some(r) = lex.next() {
+        let t = r.unwrap();
+        if t == Token::Eof { break; }
+        tokens.push(t);
+    }
+    eprintln!("preprocessor_directives tokens ({}): {:?}", tokens.len(), tokens);
+    assert_eq!(tokens[0], Token::Directive("include".into()));
+    assert_eq!(tokens[1], Token::HeaderName { name: "iostream".into(), angled: true });
+    assert_eq!(tokens[2], Token::DirectiveEnd);
+    assert_eq!(tokens[3], Token::Directive("include".into()));
+    assert_eq!(tokens[4], Token::HeaderName { name: "local.h".into(), angled: false });
+    assert_eq!(tokens[5], Token::DirectiveEnd);
+    assert_eq!(tokens[6], Token::Directive("define".into()));
+    assert_eq!(tokens[7], Token::Identifier("SQ".into()));
+    let end = tokens.iter().skip(6).position(|t| *t == Token::DirectiveEnd).unwrap() + 6;
+    // `#` in the middle of a line is an ordinary operator
+    assert_eq!(tokens[end + 5], Token::Operator("#"));
+    assert_eq!(tokens[tokens.len() - 5], Token::Directive("ifdef".into()));
+    assert_eq!(tokens[tokens.len() - 2], Token::Directive("endif".into()));
+    assert_eq!(tokens[tokens.len() - 1], Token::DirectiveEnd);
+}
+
+#[test]
+fn non_ascii_text_and_spliced_comments() {
+    let src = "'é' \"naïve\\tx\" // line \\\n still comment\nz /* a *\\\n/ w";
+    let lex = Lexer::with_options(src, LexerOptions { preserve_comments: true });
+    let mut tokens = Vec::new();
+    for r in lex {
+        let t = r.unwrap();
+        if t == Token::Eof { break; }
+        tokens.push(t);
+    }
+    eprintln!("non_ascii_text_and_spliced_comments tokens ({}): {:?}", tokens.len(), tokens);
+    assert_eq!(tokens[0], Token::CharLiteral('é'.into()));
+    assert_eq!(tokens[1], Token::StringLiteral("naïve\tx".into()));
+    assert_eq!(tokens[2], Token::Comment { text: " line  still comment".into(), block: false });
+    assert_eq!(tokens[3], Token::Identifier("z".into()));
+    assert_eq!(tokens[4], Token::Comment { text: " a ".into(), block: true });
+    assert_eq!(tokens[5], Token::Identifier("w".into()));
+}
+
+#[test]
+fn encoding_prefixes_and_raw_strings() {
+    let src = "L\"w\" u8\"\\xff\" u\"\\xffff\" U'x' u8'a' R\"x(a)\"\\n)x\" LR\"(\n#)\" u8R U\"a\"";
+    let tokens: Vec<Token> = Lexer::new(src).map(|r| r.unwrap()).take(9).collect();
+    eprintln!("prefix tokens: {:?}", tokens);
+    let string = |encoding, chars: Vec<_>| Token::StringLiteral(Literal { encoding, chars });
+    assert_eq!(tokens[0], string(Encoding::Wide, vec![Char('w')]));
+    assert_eq!(tokens[1], string(Encoding::Utf8, vec![Unit(0xff)]));
+    assert_eq!(tokens[2], string(Encoding::Utf16, vec![Unit(0xffff)]));
+    assert_eq!(tokens[3], Token::CharLiteral(Literal { encoding: Encoding::Utf32, chars: vec![Char('x')] }));
+    assert_eq!(tokens[4], Token::CharLiteral(Literal { encoding: Encoding::Utf8, chars: vec![Char('a')] }));
+    // Raw strings take everything up to `)delimiter"`, a `#` at the start
+    // of a line included.
+    assert_eq!(tokens[5], Token::StringLiteral(Literal::new(Encoding::Ordinary, "a)\"\\n")));
+    assert_eq!(tokens[6], string(Encoding::Wide, vec![Char('\n'), Char('#')]));
+    // Not followed by a quote, a prefix is an identifier.
+    assert_eq!(tokens[7], Token::Identifier("u8R".into()));
+    assert_eq!(tokens[8], string(Encoding::Utf32, vec![Char('a')]));
+    assert_eq!(tokens[1].spelling(), r#"u8"\377""#);
+    // A hex escape too large for the code unit is an error.
+    assert_eq!(Lexer::new("u8\"\\x100\"").next(), Some(Err(LexError::InvalidEscape)));
+    assert_eq!(Lexer::new("R\"(never closed").next(), Some(Err(LexError::UnterminatedString)));
+}
+
+#[test]
+fn pp_numbers() {
+    let tokens: Vec<Token> = Lexer::new("0x1Fu 201703L 1e+5 1'000 3.5f x").map(|r| r.unwrap()).take(6).collect();
+    eprintln!("pp_numbers tokens: {:?}", tokens);
+    let expected = ["0x1Fu", "201703L", "1e+5", "1'000", "3.5f"];
+    for (t, e) in tokens.iter().zip(expected) { assert_eq!(*t, Token::Number(e.into())); }
+    assert_eq!(tokens[5], Token::Identifier("x".into()));
+}
+
+#[test]
+fn pp_numbers_may_start_with_a_dot() {
+    let tokens: Vec<Token> = Lexer::new(".5 .5e-3f a.b . 1.").map(|r| r.unwrap()).take(7).collect();
+    eprintln!("dot tokens: {:?}", tokens);
+    assert_eq!(tokens[0], Token::Number(".5".into()));
+    assert_eq!(tokens[1], Token::Number(".5e-3f".into()));
+    assert_eq!(tokens[3], Token::Operator("."));
+    assert_eq!(tokens[5], Token::Operator("."));
+    assert_eq!(tokens[6], Token::Number("1.".into()));
+}
+
+#[test]
+fn every_escape_sequence_is_read() {
+    let src = r#"'\0' '\x41' '\101' '\7' '\a' '\b' '\f' '\v' '\?' 'é' '\U0001F600' '\o{101}' '\x{41}' '\u{e9}' "\1234\x41g\0""#;
+    let tokens: Vec<Token> = Lexer::new(src).map(|r| r.unwrap()).take(15).collect();
+    eprintln!("escape tokens: {:?}", tokens);
+    // Octal and hex escapes are code units, the others characters.
+    let chars = [Unit(0), Unit(0x41), Unit(0o101), Unit(7), Char('\x07'), Char('\x08'), Char('\x0c'), Char('\x0b'), Char('?'), Char('é'), Char('😀'), Unit(0o101), Unit(0x41), Char('é')];
+    for (t, c) in tokens.iter().zip(chars) { assert_eq!(*t, Token::CharLiteral(Literal { encoding: Encoding::Ordinary, chars: vec![c] })); }
+    // At most three octal digits; hex digits run on.
+    assert_eq!(tokens[14], Token::StringLiteral(Literal { encoding: Encoding::Ordinary, chars: vec![Unit(0o123), Char('4'), Unit(0x41), Char('g'), Unit(0)] }));
+    // Spelled back, code units take three octal digits.
+    assert_eq!(tokens[14].spelling(), r#""\1234\101g\000""#);
+    assert_eq!(Lexer::new(&tokens[14].spelling()).next(), Some(Ok(tokens[14].clone())));
+}
+
+#[test]
+fn malformed_escapes_are_errors() {
+    for src in [r"'\q'", r"'\x'", r"'\u12'", r"'\U0000D800'", r"'\x110000'", r"'\o{}'", r"'\x{41'", r"'\8'"] {
+        let first = Lexer::new(src).next();
+        eprintln!("{} -> {:?}", src, first);
+        assert_eq!(first, Some(Err(LexError::InvalidEscape)), "{}", src);
+    }
+}
+
+#[test]
+fn operators_are_spelled_across_splices_and_stray_characters_stand_alone() {
+    let tokens: Vec<Token> = Lexer::new("a =\\\n= b ¤ c").map(|r| r.unwrap()).take(5).collect();
+    assert_eq!(tokens[1], Token::Operator("=="));
+    assert_eq!(tokens[3], Token::Punct('¤'));
+    assert_eq!(tokens[3].spelling(), "¤");
+}
@@ -0,0 +1,428 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use assert_cmd::Command;
+use ruscom::arena::TuArena;
+
+use ruscom::diagnostics::{Diagnostic, Diagnostics};
+use ruscom::lint::naming::{ConfigError, NameKind, NamingConfig, Rule, Style};
+use ruscom::lint::pairing::{self, ProjectIndex};
+use ruscom::lint::spelling::{self, Dictionary, Misspelling};
+use ruscom::lint::{self, LintOptions};
+use ruscom::rewrite::Rewriter;
+use ruscom::session::{Options, Session};
+use ruscom::source::SourceManager;
+use ruscom::vfs::MemoryFileSystem;
+
+/// The unknown words of `source`, with their suggestions, and the source
+/// manager holding it.
+fn misspellings(source: &str, dictionary: &Dictionary) -> (SourceManager, Vec<Misspelling>) {
+    let mut sources = SourceManager::new();
+    // Not the first file, so spans are session positions, not offsets.
+    sources.add_file("other.cpp", "int x;\n");
+    let file = sources.add_file("main.cpp", source);
+    let file = sources.file(file);
+    let found = spelling::misspellings(file, &lint::tokens(file), dictionary);
+    eprintln!("{:?}", found);
+    (sources, found)
+}
+
+#[test]
+fn identifiers_split_at_case_changes_and_underscores() {
+    let cases: [(&str, &[&str]); 6] = [
+        ("parseHTTPHeader", &["parse", "HTTP", "Header"]),
+        ("content_length_", &["content", "length"]),
+        ("MAX_BUFFER_SIZE", &["MAX", "BUFFER", "SIZE"]),
+        ("utf8Decoder2", &["utf", "Decoder"]),
+        ("XMLParser", &["XML", "Parser"]),
+        ("lowercase", &["lowercase"]),
+    ];
+    for (ident, words) in cases {
+        let split: Vec<&str> = spelling::split_identifier(ident).into_iter().map(|(_, w)| w).collect();
+        assert_eq!(split, words, "{}", ident);
+    }
+    assert_eq!(spelling::split_identifier("getX_value")[2], (5, "value"));
+}
+
+#[test]
+fn dictionary_knows_affixed_and_compound_words() {
+    let dictionary = Dictionary::builtin();
+    for word in ["parsing", "Returns", "copied", "stopped", "unbounded", "reinitialize", "filename", "callbacks", "writers", "Allocations"] {
+        assert!(dictionary.knows(word), "{}", word);
+    }
+    for word in ["recieve", "seperate", "retrun", "paket", "funtion"] {
+        assert!(!dictionary.knows(word), "{}", word);
+    }
+    assert_eq!(dictionary.suggestions("retrun"), ["return"]);
+    assert_eq!(dictionary.suggestions("funtion"), ["function"]);
+}
+
+#[test]
+fn comments_and_identifiers_are_spell_checked() {
+    let source = "\
+        // Recieve the paket and retrun it; see https://example.com/pakets.\n\
+        /// @param buf the bufer, mail to someone@exmaple.com\n\
+        /* Doesn't handle utf8 or x86 targets yet, and the bufer is fixed. */\n\
+        #include <cstring>\n\
+        int recieveCount = 0;\n\
+        void flushBufer(char *buf, int len) { strlen(buf); std::memcpy(buf, buf, len); }\n";
+    let (sources, found) = misspellings(source, &Dictionary::builtin());
+    let words: Vec<(&str, bool)> = found.iter().map(|m| (m.word.as_str(), m.in_comment)).collect();
+    // `bufer` and `recieve` once each, at their first use.
+    assert_eq!(words, [("Recieve", true), ("paket", true), ("retrun", true), ("bufer", true)]);
+    assert_eq!(sources.snippet(found[0].span), Some("Recieve"));
+    assert_eq!(sources.location(found[3].span.start).map(|l| (l.line, l.column)), Some((2, 20)));
+    assert_eq!(found[2].suggestions, ["return"]);
+    assert_eq!(found[1].suggestions, ["packet"]);
+
+    let (_, found) = misspellings("int recieveCount;\nvoid flushBufer();\n", &Dictionary::builtin());
+    let words: Vec<(&str, bool)> = found.iter().map(|m| (m.word.as_str(), m.in_comment)).collect();
+    assert_eq!(words, [("recieve", false), ("Bufer", false)]);
+    assert_eq!(found[1].suggestions, ["Buffer", "Buyer"]);
+}
+
+#[test]
+fn project_words_are_known() {
+    let source = "// Ruscom lowers to its zorblax IR.\nint zorblaxCount;\n";
+    let (_, found) = misspellings(source, &Dictionary::builtin());
+    assert_eq!(found.iter().map(|m| m.word.as_str()).collect::<Vec<_>>(), ["Ruscom", "zorblax"]);
+
+    let mut dictionary = Dictionary::builtin();
+    dictionary.add_words("# Project words\nRusCom\nzorblax  # the IR\n");
+    assert!(misspellings(source, &dictionary).1.is_empty());
+
+    let mut fs = MemoryFileSystem::new();
+    fs.insert("proj/.ruscom-dictionary", "zorblax\n");
+    fs.insert("proj/src/a.cpp", source);
+    assert_eq!(spelling::project_dictionary(&fs, Path::new("proj/src/a.cpp")), Some(Path::new("proj/.ruscom-dictionary").to_path_buf()));
+    assert_eq!(spelling::project_dictionary(&fs, Path::new("other/a.cpp")), None);
+}
+
+#[test]
+fn comment_misspellings_with_one_suggestion_get_fixits() {
+    let mut sources = SourceManager::new();
+    let file = sources.add_file("main.cpp", "// Retrun the paket, or recieve it.\nint retrunValue;\n");
+    let mut diagnostics = Diagnostics::new();
+    lint::check(sources.file(file), None, &LintOptions::default(), &mut diagnostics);
+    for d in diagnostics.iter() { eprintln!("{} {:?} {:?}", d.message, d.help, d.fixits); }
+    let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+    assert_eq!(messages, ["unknown word `Retrun`", "unknown word `paket`", "unknown word `recieve`"]);
+    let mut rewriter = Rewriter::new();
+    for d in diagnostics.iter() { rewriter.fix(d); }
+    let fixed = rewriter.apply(&sources).unwrap();
+    // `recieve` has two suggestions, so it is left alone.
+    assert_eq!(fixed[0].1, "// Return the packet, or recieve it.\nint retrunValue;\n");
+}
+
+#[test]
+fn cli_lints_with_project_dictionaries() {
+    let dir = std::env::temp_dir().join(format!("ruscom_lint_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    let src = dir.join("src/a.cpp");
+    std::fs::write(&src, "// Ruscom reads the paket.\nint zorblax;\n").unwrap();
+    let assert = Command::cargo_bin("ruscom").unwrap().arg("lint").arg(&src).assert().success();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+    eprintln!("{}", stderr);
+    for word in ["Ruscom", "paket", "zorblax"] { assert!(stderr.contains(&format!("unknown word `{}`", word)), "{}", word); }
+    assert!(stderr.contains("did you mean `packet`?"));
+
+    std::fs::write(dir.join(".ruscom-dictionary"), "ruscom\n").unwrap();
+    let words = dir.join("extra.dic");
+    std::fs::write(&words, "zorblax\n").unwrap();
+    let assert = Command::cargo_bin("ruscom").unwrap().arg("lint").arg("--dictionary").arg(&words).arg(&src).assert().success();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+    eprintln!("{}", stderr);
+    assert!(stderr.contains("unknown word `paket`"));
+    assert!(!stderr.contains("`Ruscom`") && !stderr.contains("`zorblax`"));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn names_are_converted_between_styles() {
+    let cases = [
+        ("parseHTTPHeader", Style::LowerSnake, "parse_http_header"),
+        ("parse_http_header", Style::PascalCase, "ParseHttpHeader"),
+        ("MAX_BUFFER_SIZE", Style::CamelCase, "maxBufferSize"),
+        ("utf8Decoder2", Style::UpperSnake, "UTF8_DECODER2"),
+        ("get__value_", Style::PascalCase, "GetValue"),
+    ];
+    for (name, style, converted) in cases {
+        assert!(!style.matches(name), "{}", name);
+        assert_eq!(style.apply(name), converted, "{}", name);
+        assert!(style.matches(converted), "{}", converted);
+    }
+    // Acronyms are left alone.
+    assert!(Style::PascalCase.matches("HTTPServer"));
+
+    let member = Rule { style: Some(Style::LowerSnake), prefix: "m_".into(), suffix: String::new() };
+    assert_eq!(member.expected("m_bufferSize"), "m_buffer_size");
+    assert_eq!(member.expected("count"), "m_count");
+    assert_eq!(member.expected("m_count"), "m_count");
+    let member = Rule { style: Some(Style::LowerSnake), prefix: String::new(), suffix: "_".into() };
+    assert_eq!(member.expected("itemCount"), "item_count_");
+    assert_eq!(member.expected("total_"), "total_");
+}
+
+#[test]
+fn naming_rules_are_read_from_the_naming_section() {
+    let text = "\
+        [other]\n\
+        type = \"ignored\"\n\
+        \n\
+        [naming]\n\
+        # Types and functions.\n\
+        type = \"PascalCase\"\n\
+        function = \"snake_case\"\n\
+        member_suffix = \"_\"\n\
+        variable = \"lower_snake\"\n";
+    let config = NamingConfig::parse(text).unwrap();
+    assert_eq!(config.rule(NameKind::Type), Some(&Rule { style: Some(Style::PascalCase), ..Rule::default() }));
+    assert_eq!(config.rule(NameKind::Function).and_then(|r| r.style), Some(Style::LowerSnake));
+    assert_eq!(config.rule(NameKind::Member), Some(&Rule { style: None, prefix: String::new(), suffix: "_".into() }));
+    assert_eq!(config.rule(NameKind::Macro), None);
+    assert!(NamingConfig::parse("[other]\nx = 1\n").unwrap().is_empty());
+    // Comments may follow a value or a section header, but not start inside a string.
+    let config = NamingConfig::parse("[naming] # names\nmacro_prefix = \"LIB_#\" # for the public macros\nenumerator = \"SCREAMING_SNAKE\"#no space\n").unwrap();
+    assert_eq!(config.rule(NameKind::Macro).map(|r| r.prefix.as_str()), Some("LIB_#"));
+    assert_eq!(config.rule(NameKind::Enumerator).and_then(|r| r.style), Some(Style::UpperSnake));
+
+    let errors = [
+        ("[naming]\ntype = PascalCase\n", ConfigError::Syntax { line: 2 }),
+        ("[naming]\n\nclass = \"PascalCase\"\n", ConfigError::UnknownKey { line: 3, key: "class".into() }),
+        ("[naming]\nmacro = \"Screaming\"\n", ConfigError::UnknownStyle { line: 2, style: "Screaming".into() }),
+    ];
+    for (text, error) in errors {
+        let e = NamingConfig::parse(text).unwrap_err();
+        eprintln!("{}", e);
+        assert_eq!(e, error);
+    }
+}
+
+/// The naming warnings of `source` under `config`, and the source with
+/// their fix-its applied.
+fn naming(source: &str, config: &str) -> (Vec<Diagnostic>, String) {
+    let mut session = Session::new(Options::default());
+    let file = session.sources.add_file("main.cpp", source);
+    let arena = TuArena::new();
+    let index = lint::index(&mut session, &arena, file, None).unwrap();
+    let options = LintOptions { naming: NamingConfig::parse(config).unwrap(), ..LintOptions::default() };
+    let mut diagnostics = Diagnostics::new();
+    lint::check(session.sources.file(file), Some(&index), &options, &mut diagnostics);
+    let found: Vec<Diagnostic> = diagnostics.take().into_iter().filter(|d| !d.message.starts_with("unknown word")).collect();
+    let mut rewriter = Rewriter::new();
+    for d in &found {
+        eprintln!("{} {:?} {:?}", d.message, d.notes, d.fixits);
+        rewriter.fix(d);
+    }
+    let fixed = rewriter.apply(&session.sources).unwrap().pop().map_or(source.to_owned(), |(_, text)| text);
+    (found, fixed)
+}
+
+#[test]
+fn names_breaking_the_rules_are_renamed_everywhere() {
+    let config = "[naming]\ntype = \"PascalCase\"\nfunction = \"lower_snake\"\nmacro = \"SCREAMING_SNAKE\"\nmember_suffix = \"_\"\n";
+    let source = "\
+        #define maxSize 16\n\
+        struct my_widget {\n\
+        \x20   int count;\n\
+        \x20   my_widget() : count(0) {}\n\
+        \x20   int GetCount() const { return count; }\n\
+        };\n\
+        int ComputeTotal(my_widget w) { return w.GetCount() + maxSize; }\n\
+        int main() { my_widget w; return ComputeTotal(w); }\n";
+    let (found, fixed) = naming(source, config);
+    let messages: Vec<&str> = found.iter().map(|d| d.message.as_str()).collect();
+    assert_eq!(messages, [
+        "macro `maxSize` should be named `MAX_SIZE`",
+        "type `my_widget` should be named `MyWidget`",
+        "member `count` should be named `count_`",
+        "function `GetCount` should be named `get_count`",
+        "function `ComputeTotal` should be named `compute_total`",
+    ]);
+    assert_eq!(fixed, "\
+        #define MAX_SIZE 16\n\
+        struct MyWidget {\n\
+        \x20   int count_;\n\
+        \x20   MyWidget() : count_(0) {}\n\
+        \x20   int get_count() const { return count_; }\n\
+        };\n\
+        int compute_total(MyWidget w) { return w.get_count() + MAX_SIZE; }\n\
+        int main() { MyWidget w; return compute_total(w); }\n");
+}
+
+#[test]
+fn renames_that_would_change_meaning_are_not_fixed() {
+    let config = "[naming]\nfunction = \"lower_snake\"\nvariable = \"lower_snake\"\nmacro = \"SCREAMING_SNAKE\"\n";
+    let source = "\
+        #define Limit 3\n\
+        int LIMIT = Limit;\n\
+        int load_all() { return 1; }\n\
+        int LoadAll() { return load_all() + Limit; }\n";
+    let (found, fixed) = naming(source, config);
+    let found: Vec<_> = found.iter().map(|d| (d.message.as_str(), d.notes.clone(), d.fixits.len())).collect();
+    assert_eq!(found, [
+        ("macro `Limit` should be named `LIMIT`", vec!["not renamed automatically: `LIMIT` is already used in this file".to_owned()], 0),
+        ("variable `LIMIT` should be named `limit`", vec![], 1),
+        ("function `LoadAll` should be named `load_all`", vec!["not renamed automatically: `load_all` is already declared in this scope".to_owned()], 0),
+    ]);
+    assert!(fixed.contains("int limit = Limit;"));
+}
+
+#[test]
+fn cli_checks_names_against_ruscom_toml() {
+    let dir = std::env::temp_dir().join(format!("ruscom_naming_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    let src = dir.join("src/a.cpp");
+    std::fs::write(&src, "int ReadValue() { return 1; }\nint main() { return ReadValue(); }\n").unwrap();
+    // Without a config, names are not checked.
+    let assert = Command::cargo_bin("ruscom").unwrap().arg("lint").arg(&src).assert().success();
+    assert!(!String::from_utf8_lossy(&assert.get_output().stderr).contains("should be named"));
+
+    std::fs::write(dir.join("ruscom.toml"), "[naming]\nfunction = \"lower_snake\"\n").unwrap();
+    let assert = Command::cargo_bin("ruscom").unwrap().arg("lint").arg(&src).assert().success();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+    eprintln!("{}", stderr);
+    assert!(stderr.contains("function `ReadValue` should be named `read_value`"));
+    assert!(!stderr.contains("`main`"));
+
+    std::fs::write(dir.join("ruscom.toml"), "[naming]\nfunction = \"lower\"\n").unwrap();
+    let assert = Command::cargo_bin("ruscom").unwrap().arg("lint").arg(&src).assert().failure();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+    eprintln!("{}", stderr);
+    assert!(stderr.contains("ruscom.toml") && stderr.contains("line 2: unknown naming style `lower`"));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+/// The pairing warnings of the source files `inputs`, linted together in a
+/// project holding `files`, as `message (labels)`.
+fn pairing(files: &[(&str, &str)], inputs: &[&str]) -> Vec<String> {
+    let mut fs = MemoryFileSystem::new();
+    for (path, contents) in files { fs.insert(path, *contents); }
+    let mut session = Session::new(Options::default());
+    session.fs = Arc::new(fs);
+    let arena = TuArena::new();
+    let mut project = ProjectIndex::new();
+    for input in inputs {
+        let file = session.sources.load(session.fs.as_ref(), Path::new(input)).unwrap();
+        lint::index(&mut session, &arena, file, Some(&mut project)).unwrap();
+        if let Some(header) = pairing::paired_header(session.fs.as_ref(), Path::new(input)) { project.pair(file, header); }
+    }
+    let mut diagnostics = Diagnostics::new();
+    project.check(&session.sources, &mut diagnostics);
+    diagnostics.take().into_iter().map(|d| {
+        let labels: Vec<String> = d.labels.iter().filter(|l| !l.message.is_empty()).map(|l| l.message.clone()).collect();
+        eprintln!("{} {:?} {:?} {:?}", d.message, labels, d.notes, d.help);
+        if labels.is_empty() { d.message } else { format!("{} ({})", d.message, labels.join(", ")) }
+    }).collect()
+}
+
+#[test]
+fn headers_are_paired_with_their_sources() {
+    let mut fs = MemoryFileSystem::new();
+    fs.insert("proj/shapes.cpp", "");
+    fs.insert("proj/shapes.hpp", "");
+    fs.insert("proj/main.cpp", "");
+    assert_eq!(pairing::paired_header(&fs, Path::new("proj/shapes.cpp")), Some(Path::new("proj/shapes.hpp").to_path_buf()));
+    assert_eq!(pairing::paired_header(&fs, Path::new("proj/main.cpp")), None);
+    assert_eq!(pairing::paired_header(&fs, Path::new("proj/shapes.hpp")), None);
+}
+
+#[test]
+fn definitions_and_declarations_are_matched_across_the_project() {
+    let header = "\
+        namespace geo {\n\
+        struct Point { double x, y; double norm() const; };\n\
+        double area(double r);\n\
+        double perimeter(double r);\n\
+        double scale(double r, double k);\n\
+        inline double twice(double r) { return 2 * r; }\n\
+        }\n\
+        int unused(int);\n";
+    let source = "\
+        #include \"shapes.h\"\n\
+        namespace geo {\n\
+        double Point::norm() const { return x * x + y * y; }\n\
+        double area(double r) { return 3.14 * r * r; }\n\
+        double scale(double r, int k) { return r * k; }\n\
+        double helper(double r) { return r; }\n\
+        static double hidden(double r) { return r; }\n\
+        namespace { double internal(double r) { return r; } }\n\
+        }\n\
+        template <typename T> T twice_of(T t) { return t + t; }\n";
+    let other = "#include \"shapes.h\"\ndouble geo::perimeter(double r) { return 6.28 * r; }\nint main() { return 0; }\n";
+    let files = [("proj/shapes.h", header), ("proj/shapes.cpp", source), ("proj/main.cpp", other)];
+    assert_eq!(pairing(&files, &["proj/shapes.cpp", "proj/main.cpp"]), [
+        "function `geo::scale` is not declared in `shapes.h` (declared here as `double (double, double)`)",
+        "function `geo::helper` is not declared in `shapes.h`",
+        "function `geo::scale` is declared but never defined (defined here as `double (double, int)`)",
+        "function `unused` is declared but never defined",
+    ]);
+    // Without `main.cpp`, nothing defines `geo::perimeter`.
+    let found = pairing(&files, &["proj/shapes.cpp"]);
+    assert!(found.contains(&"function `geo::perimeter` is declared but never defined".to_owned()));
+}
+
+#[test]
+fn sources_that_do_not_include_their_header_are_reported() {
+    let files = [("proj/util.h", "int twice(int);\n"), ("proj/util.cpp", "int twice(int x) { return 2 * x; }\n")];
+    assert_eq!(pairing(&files, &["proj/util.cpp"]), ["`util.cpp` does not include its header `util.h`"]);
+}
+
+#[test]
+fn cli_pairs_headers_and_sources() {
+    let dir = std::env::temp_dir().join(format!("ruscom_pairing_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("counter.h"), "int next_count();\nvoid reset_count();\n").unwrap();
+    std::fs::write(dir.join("counter.cpp"), "#include \"counter.h\"\nstatic int count;\nint next_count() { return ++count; }\nvoid reset_count(int to) { count = to; }\n").unwrap();
+    let assert = Command::cargo_bin("ruscom").unwrap().arg("lint").arg(dir.join("counter.cpp")).assert().success();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+    eprintln!("{}", stderr);
+    assert!(stderr.contains("function `reset_count` is not declared in `counter.h`"));
+    assert!(stderr.contains("function `reset_count` is declared but never defined"));
+    assert!(!stderr.contains("`next_count`"));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[test]
+fn definitions_that_differ_between_units_are_reported() {
+    let header = "\
+        struct Buffer {\n\
+        char data[SIZE];\n\
+        #ifdef CHECKED\n\
+        int checks;\n\
+        #endif\n\
+        };\n\
+        struct Same { int x; };\n\
+        inline int capacity() { return SIZE; }\n\
+        inline int version() { return 2; }\n\
+        template <typename T> T clamp(T v) { return v < 0 ? 0 : v; }\n\
+        namespace { struct Local { int n; }; }\n";
+    let files = [
+        ("proj/config.h", header),
+        ("proj/a.cpp", "#define SIZE 64\n#include \"config.h\"\nnamespace { struct Local { long n; }; }\n"),
+        ("proj/b.cpp", "#define SIZE 64\n#define CHECKED\n#include \"config.h\"\n"),
+        ("proj/c.cpp", "#define SIZE 128\n#include \"config.h\"\ntemplate <typename T> T clamp(T v) { return v; }\n"),
+    ];
+    assert_eq!(pairing(&files, &["proj/a.cpp", "proj/b.cpp", "proj/c.cpp"]), [
+        "class `Buffer` is defined differently in `a.cpp` and `b.cpp` (as `b.cpp` sees it)",
+        "class `Buffer` is defined differently in `a.cpp` and `c.cpp` (as `c.cpp` sees it)",
+        "inline function `capacity` is defined differently in `a.cpp` and `c.cpp` (as `c.cpp` sees it)",
+    ]);
+    // Each unit sees its own definition first.
+    assert!(pairing(&files, &["proj/a.cpp", "proj/a.cpp"]).is_empty());
+}
+
+#[test]
+fn cli_reports_definitions_that_differ_with_a_structural_diff() {
+    let dir = std::env::temp_dir().join(format!("ruscom_odr_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("packet.h"), "struct Packet {\n    int id;\n#ifdef WITH_CRC\n    unsigned crc;\n#endif\n};\n").unwrap();
+    std::fs::write(dir.join("send.cpp"), "#define WITH_CRC\n#include \"packet.h\"\n").unwrap();
+    std::fs::write(dir.join("recv.cpp"), "#include \"packet.h\"\n").unwrap();
+    let assert = Command::cargo_bin("ruscom").unwrap().arg("lint").arg(dir.join("send.cpp")).arg(dir.join("recv.cpp")).assert().success();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).to_string();
+    eprintln!("{}", stderr);
+    assert!(stderr.contains("class `Packet` is defined differently in `send.cpp` and `recv.cpp`"));
+    assert!(stderr.contains("the definitions differ (- in `send.cpp`, + in `recv.cpp`):\n    -   FieldDecl crc 'unsigned int' public\n"));
+    std::fs::remove_dir_all(&dir).ok();
+}
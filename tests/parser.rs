@@ -0,0 +1,1301 @@
+mod common;
+
+use std::sync::Arc;
+
+use assert_cmd::Command;
+
+use ruscom::arena::TuArena;
+use ruscom::ast::{self, Access, Decl, DumpFormat, DumpOptions, Expr, FunctionKind, ExprKind, IntSuffix, Stmt, StmtKind, StorageClass, TemplateArg, TemplateParamKind, TranslationUnit};
+use ruscom::lexer::token::Span;
+use ruscom::module::{ExportKind, ModuleInterface};
+use ruscom::parser::Parser;
+use ruscom::preprocessor::Preprocessor;
+use ruscom::session::{Options, Session};
+use ruscom::vfs::MemoryFileSystem;
+use common::parse;
+
+/// `name: type` for every declaration, in order.
+fn types(tu: &TranslationUnit) -> Vec<String> {
+    tu.decls.iter().map(|d| format!("{}: {}", d.name(), type_of(d))).collect()
+}
+
+fn type_of(d: &Decl) -> String {
+    match d {
+        Decl::Var(v) => v.ty.to_string(),
+        Decl::Decomposition(d) => d.ty.to_string(),
+        Decl::Function(f) => f.ty.to_string(),
+        Decl::Typedef(t) => t.ty.to_string(),
+        Decl::Namespace(_) => "namespace".into(),
+        Decl::Using(_) => "using".into(),
+        Decl::UsingDirective(_) => "using namespace".into(),
+        Decl::Class(c) => c.key.keyword().into(),
+        Decl::Enum(e) => if e.is_scoped { "enum class".into() } else { "enum".into() },
+        Decl::Template(t) => format!("template {}", type_of(&t.decl)),
+        Decl::Concept(_) => "concept".into(),
+        Decl::Module(m) => if m.is_export { "export module".into() } else { "module".into() },
+        Decl::Import(i) => if i.is_export { "export import".into() } else { "import".into() },
+        Decl::Export(e) => format!("export {{{}}}", e.decls.iter().map(type_of).collect::<Vec<_>>().join(", ")),
+        Decl::StaticAssert(_) => "static_assert".into(),
+        Decl::Unsupported(u) => format!("unsupported {}", u.category),
+        Decl::Invalid(_) => "invalid".into(),
+    }
+}
+
+fn codes(s: &Session) -> Vec<String> {
+    s.diagnostics.iter().filter_map(|d| d.code.clone()).collect()
+}
+
+#[test]
+fn declarators_build_the_right_types() {
+    let (s, tu) = parse("\
+        int *p, a[3][4], (*fp)(int, char), *(*pa)[2];\n\
+        const char *const name = \"x\";\n\
+        unsigned long long big;\n\
+        const wchar_t *wide; char8_t u8; char16_t u16[2]; char32_t (*u32)(wchar_t);\n\
+        int &r = a, &&rr = 0;\n\
+        void (*signal(int, void (*)(int)))(int);\n");
+    let types = types(&tu);
+    eprintln!("{:#?}", types);
+    assert!(s.diagnostics.is_empty());
+    assert_eq!(types, [
+        "p: int*",
+        "a: int[3][4]",
+        "fp: int (*)(int, char)",
+        "pa: int* (*)[2]",
+        "name: const char* const",
+        "big: unsigned long long",
+        "wide: const wchar_t*",
+        "u8: char8_t",
+        "u16: char16_t[2]",
+        "u32: char32_t (*)(wchar_t)",
+        "r: int&",
+        "rr: int&&",
+        "signal: void (*(int, void (*)(int)))(int)",
+    ]);
+}
+
+#[test]
+fn functions_definitions_and_parameters() {
+    let (s, tu) = parse("\
+        typedef int T;\n\
+        static inline T twice(T x = 2) { return x + x; }\n\
+        void f(int a[], void g(void), ...);\n");
+    eprintln!("{:#?}", tu);
+    assert!(s.diagnostics.is_empty());
+    let Decl::Function(twice) = &tu.decls[1] else { panic!("not a function") };
+    assert!(twice.body.is_some());
+    assert!(twice.specifiers.is_inline);
+    assert_eq!(twice.params[0].default.as_ref().map(|e| &e.kind), Some(&ExprKind::IntLiteral(2, IntSuffix::default())));
+    let Decl::Function(f) = &tu.decls[2] else { panic!("not a function") };
+    assert!(f.body.is_none());
+    // Array and function parameters decay to pointers.
+    assert_eq!(f.ty.to_string(), "void (int*, void (*)(), ...)");
+}
+
+#[test]
+fn namespaces_and_using_declarations() {
+    let (s, tu) = parse("namespace a { int x; namespace { char c; } }\nusing a::x;\nstd::size_t n;\n");
+    eprintln!("{:#?}", tu);
+    assert!(s.diagnostics.is_empty());
+    let Decl::Namespace(a) = &tu.decls[0] else { panic!("not a namespace") };
+    assert_eq!(a.name.map(|n| n.to_string()).as_deref(), Some("a"));
+    assert_eq!(types(&TranslationUnit { decls: a.decls.clone(), span: a.span }), ["x: int", ": namespace"]);
+    assert_eq!(types(&tu)[1..], ["a::x: using", "n: std::size_t"]);
+}
+
+#[test]
+fn nested_and_inline_namespaces_and_using_directives() {
+    let (s, tu) = parse("\
+        namespace lib::inline v2 { typedef int handle; }\n\
+        inline namespace abi { struct Node {}; }\n\
+        namespace q { namespace r { typedef long T; } }\n\
+        using namespace q;\n\
+        lib::handle h = ::lib::v2::open(1);\n\
+        r::T t;\n\
+        Node n;\n");
+    eprintln!("{:#?}", types(&tu));
+    assert!(s.diagnostics.is_empty());
+    assert_eq!(types(&tu), [
+        "lib: namespace", "abi: namespace", "q: namespace", "q: using namespace", "h: lib::handle", "t: r::T", "n: Node",
+    ]);
+    let Decl::Namespace(lib) = &tu.decls[0] else { panic!("not a namespace") };
+    let Decl::Namespace(v2) = &lib.decls[0] else { panic!("not a namespace") };
+    assert!(!lib.is_inline && v2.is_inline && v2.span == lib.span);
+    let Decl::Var(h) = &tu.decls[4] else { panic!("not a variable") };
+    let ExprKind::Call { callee, .. } = &h.init.as_ref().unwrap().kind else { panic!("not a call") };
+    let ExprKind::Name(name) = callee.kind else { panic!("not a name") };
+    assert!(name.is_global());
+    assert_eq!(name.segments().collect::<Vec<_>>(), ["lib", "v2", "open"]);
+    assert_eq!(name.last().to_string(), "open");
+    assert_eq!(name.qualifier().map(|q| q.to_string()).as_deref(), Some("::lib::v2"));
+    for source in ["namespace a:: { }", "namespace a::inline { }", "using namespace ;"] {
+        let (s, _) = parse(source);
+        eprintln!("{:?} -> {:?}", source, codes(&s));
+        assert_eq!(codes(&s), ["E0201"], "{}", source);
+    }
+}
+
+#[test]
+fn linkage_specifications_declare_with_c_linkage() {
+    let (s, tu) = parse("\
+        extern \"C\" {\n\
+        int open(const char *path);\n\
+        int errors;\n\
+        namespace io { void flush(); }\n\
+        struct File { int fd; int size(); };\n\
+        extern \"C++\" { int close(File *f); }\n\
+        }\n\
+        extern \"C\" int verbose;\n\
+        extern \"C\" void log(const char *, ...);\n\
+        int read(File *f);\n");
+    eprintln!("{:?}", types(&tu));
+    assert!(s.diagnostics.is_empty(), "{:?}", codes(&s));
+    assert_eq!(types(&tu), [
+        "open: int (const char*)", "errors: int", "io: namespace", "File: struct", "close: int (File*)", "verbose: int", "log: void (const char*, ...)", "read: int (File*)",
+    ]);
+    let c_linkage: Vec<String> = tu.decls.iter().filter(|d| matches!(d, Decl::Function(f) if f.specifiers.is_extern_c) || matches!(d, Decl::Var(v) if v.specifiers.is_extern_c)).map(|d| d.name().to_string()).collect();
+    assert_eq!(c_linkage, ["open", "errors", "verbose", "log"]);
+    let Decl::Namespace(io) = &tu.decls[2] else { panic!("not a namespace") };
+    assert!(matches!(&io.decls[0], Decl::Function(f) if f.specifiers.is_extern_c));
+    // Members have no language linkage, and only the form without braces
+    // makes a variable `extern`.
+    let Decl::Class(file) = &tu.decls[3] else { panic!("not a class") };
+    assert!(file.members.iter().flatten().all(|m| match &m.decl { Decl::Var(v) => !v.specifiers.is_extern_c, Decl::Function(f) => !f.specifiers.is_extern_c, _ => false }));
+    let storage = |i: usize| match &tu.decls[i] { Decl::Var(v) => v.specifiers.storage, _ => panic!("not a variable") };
+    assert_eq!((storage(1), storage(5)), (StorageClass::None, StorageClass::Extern));
+    let (s, tu) = parse("extern \"Java\" { int x; }\nint y;\n");
+    assert_eq!(codes(&s), ["E0211"]);
+    assert_eq!(tu.decls.last().map(|d| d.name().to_string()).as_deref(), Some("y"));
+}
+
+#[test]
+fn specifier_errors_have_codes() {
+    let cases = [
+        ("const *p;", "E0202"),
+        ("int char c;", "E0203"),
+        ("unsigned wchar_t c;", "E0203"),
+        ("long char16_t c;", "E0203"),
+        ("const const int c;", "E0204"),
+        ("int a[n];", "E0205"),
+        ("int f()[3];", "E0206"),
+        ("int x = 08;", "E0207"),
+        ("int = 1;", "E0201"),
+    ];
+    for (source, code) in cases {
+        let (s, _) = parse(source);
+        eprintln!("{:?} -> {:?}", source, codes(&s));
+        assert_eq!(codes(&s), [code], "{}", source);
+    }
+}
+
+#[test]
+fn array_bounds_are_constant_expressions() {
+    let (s, tu) = parse("\
+        constexpr int N = 4;\n\
+        const unsigned long M = N * 2 + 1;\n\
+        enum { Small = 3, Large = Small << 4 };\n\
+        int a[N];\n\
+        char b[M - N][Large];\n\
+        int c[(N > 2) ? N : 2];\n\
+        static_assert(sizeof(a) == 16, \"four ints\");\n\
+        struct S { static_assert(N == 4); int d[N + 1]; };\n\
+        char bytes[sizeof(S) / sizeof(int)];\n\
+        char aligned[alignof(S) * sizeof a];\n\
+        constexpr int n() { return 4; }\n\
+        constexpr int twice(int x) { int y = x; y *= 2; return y; }\n\
+        int e[n()], f[twice(N) + n()];\n");
+    eprintln!("{:?}", types(&tu));
+    assert!(s.diagnostics.is_empty(), "{:?}", codes(&s));
+    assert_eq!(types(&tu)[3..7], ["a: int[4]", "b: char[5][48]", "c: int[4]", ": static_assert"]);
+    assert_eq!(types(&tu)[8..], ["bytes: char[5]", "aligned: char[64]", "n: int ()", "twice: int (int)", "e: int[4]", "f: int[12]"]);
+    let Decl::StaticAssert(a) = &tu.decls[6] else { panic!("not a static_assert") };
+    assert_eq!(a.message.as_deref(), Some("four ints"));
+    let cases = [
+        ("int a[-1];", "E0210"),
+        ("int a[1 / 0];", "E0302"),
+        ("int a[1.5];", "E0205"),
+        ("int n = 2; int a[n];", "E0205"),
+        ("int a[1 << 64];", "E0338"),
+        ("void f();\nint a[sizeof(f)];", "E0341"),
+        ("constexpr int per(int n) { return 12 / n; }\nint a[per(0)];", "E0302"),
+        ("template <int N> void f() { int n = N; int a[n]; }", "E0205"),
+    ];
+    for (source, code) in cases {
+        let (s, _) = parse(source);
+        eprintln!("{:?} -> {:?}", source, codes(&s));
+        assert_eq!(codes(&s), [code], "{}", source);
+    }
+    // A bound that depends on a template parameter is kept unknown.
+    let (s, tu) = parse("template <int N> struct A { int a[N]; };\ntemplate <typename T, T N, int... Ns> void f() { T b[N * 2 + sizeof(T)]; }\nint N;\n");
+    eprintln!("{}", ast::dump(&tu, &s.sources));
+    assert!(s.diagnostics.is_empty(), "{:?}", codes(&s));
+    assert_eq!(parse("template <int N> struct A { };\nint a[N];\n").0.diagnostics.iter().filter_map(|d| d.code.clone()).collect::<Vec<_>>(), ["E0205"]);
+}
+
+#[test]
+fn errors_recover_at_the_next_declaration() {
+    let (s, tu) = parse("int a = ;\nint f() { { } oops }\nint b;\nnamespace n { int = 2; int c; }\n}\nint d;\n");
+    eprintln!("{:?} {:?}", codes(&s), types(&tu));
+    // `oops` in the body is missing its `;`.
+    assert_eq!(codes(&s), ["E0201", "E0201", "E0201", "E0201"]);
+    // Each declaration with an error is kept as an invalid one.
+    assert_eq!(types(&tu), [": invalid", "f: int ()", "b: int", "n: namespace", "d: int"]);
+}
+
+#[test]
+fn many_errors_are_reported_in_one_pass_up_to_the_limit() {
+    let source = "int a = ;\nint f() { int x = ; return x; }\nstruct S { int = 3; };\nint b = );\nint c;\n";
+    let (s, tu) = parse(source);
+    assert_eq!(codes(&s), ["E0201", "E0201", "E0201", "E0201"]);
+    assert_eq!(types(&tu), [": invalid", "f: int ()", "S: struct", ": invalid", "c: int"]);
+    assert_eq!(s.sources.snippet(tu.decls[3].span()), Some("int b = );"));
+
+    let mut fs = MemoryFileSystem::new();
+    fs.insert("main.cpp", source);
+    let mut s = Session::new(Options { error_limit: 2, ..Options::default() }).with_file_system(Arc::new(fs));
+    let main = s.sources.load(s.fs.clone().as_ref(), "main.cpp".as_ref()).unwrap();
+    let out = Preprocessor::new(&mut s).run(main).unwrap();
+    let arena = TuArena::new();
+    let tu = Parser::new(&mut s, &arena, out.tokens).parse().unwrap();
+    eprintln!("{:?}", s.diagnostics.iter().map(|d| d.message.clone()).collect::<Vec<_>>());
+    assert_eq!(s.diagnostics.error_count(), 2);
+    assert!(s.diagnostics.limit_reached());
+    // Parsing stops at the second error, leaving `f` unfinished.
+    assert_eq!(types(&tu), [": invalid", ": invalid"]);
+}
+
+/// The initializer of each variable.
+fn inits<'t, 'a>(tu: &'t TranslationUnit<'a>) -> Vec<&'t Expr<'a>> {
+    tu.decls.iter().filter_map(|d| match d { Decl::Var(v) => v.init.as_ref(), _ => None }).collect()
+}
+
+/// Fully parenthesized, to show how operands were grouped.
+fn grouping(e: &Expr) -> String {
+    match &e.kind {
+        ExprKind::Paren(inner) => grouping(inner),
+        ExprKind::Unary { op, operand } if op.is_postfix() => format!("({}{})", grouping(operand), op.spelling()),
+        ExprKind::Unary { op, operand } => format!("({}{})", op.spelling(), grouping(operand)),
+        ExprKind::Binary { op, lhs, rhs } => format!("({} {} {})", grouping(lhs), op.spelling(), grouping(rhs)),
+        ExprKind::Conditional { cond, then, otherwise } => format!("({} ? {} : {})", grouping(cond), grouping(then), grouping(otherwise)),
+        ExprKind::Cast { ty, operand, .. } => format!("(({}){})", ty, grouping(operand)),
+        ExprKind::Member { base, member, arrow } => format!("({}{}{})", grouping(base), if *arrow { "->" } else { "." }, member),
+        ExprKind::Index { base, index } => format!("({}[{}])", grouping(base), grouping(index)),
+        ExprKind::Call { callee, args } => {
+            let args: Vec<String> = args.iter().map(grouping).collect();
+            format!("{}({})", grouping(callee), args.join(", "))
+        }
+        _ => e.to_string(),
+    }
+}
+
+#[test]
+fn operators_group_by_precedence_and_associativity() {
+    let cases = [
+        ("1 + 2 * 3 - 4", "((1 + (2 * 3)) - 4)"),
+        ("a - b - c", "((a - b) - c)"),
+        ("a = b = c", "(a = (b = c))"),
+        ("a << 1 < b == c & d ^ e | f && g || h", "((((((((a << 1) < b) == c) & d) ^ e) | f) && g) || h)"),
+        ("a ? b : c ? d : e", "(a ? b : (c ? d : e))"),
+        ("x ? y : z = 3", "(x ? y : (z = 3))"),
+        ("a += b <<= c >>= d", "(a += (b <<= (c >>= d)))"),
+        // `<=>` binds tighter than the relational operators.
+        ("a >= b <=> c <= d", "((a >= (b <=> c)) <= d)"),
+        ("-*&p++", "(-(*(&(p++))))"),
+        ("a-->b", "((a--) > b)"),
+        ("- -a + !~b", "((-(-a)) + (!(~b)))"),
+        ("p->*m .* n * 2", "(((p ->* m) .* n) * 2)"),
+        ("(int)f(1, 2)[3]->m.n", "((int)(((f(1, 2)[3])->m).n))"),
+        ("(a + b) * c", "((a + b) * c)"),
+        ("a %= b &= c ^= d |= e", "(a %= (b &= (c ^= (d |= e))))"),
+    ];
+    for (source, expected) in cases {
+        let (s, tu) = parse(&format!("int v = {};", source));
+        let got = inits(&tu).first().map(|e| grouping(e)).unwrap_or_default();
+        eprintln!("{:<40} => {}", source, got);
+        assert!(s.diagnostics.is_empty(), "{}", source);
+        assert_eq!(got, expected);
+    }
+}
+
+#[test]
+fn casts_sizeof_and_functional_notation() {
+    let (s, tu) = parse("\
+        typedef unsigned long size;\n\
+        int a = (size)x, b = (y), c = sizeof(int*), d = sizeof b, e = alignof(const char);\n\
+        int f = static_cast<const char*>(p), g = reinterpret_cast<int (*)(int)>(q);\n\
+        int h = size(3), i = unsigned(-1), j = double();\n");
+    let printed: Vec<String> = inits(&tu).iter().map(|e| e.to_string()).collect();
+    eprintln!("{:#?}", printed);
+    assert!(s.diagnostics.is_empty());
+    let kinds: Vec<&str> = inits(&tu).iter().map(|e| match &e.kind {
+        ExprKind::Cast { .. } => "cast",
+        ExprKind::Paren(_) => "paren",
+        ExprKind::SizeofType(_) => "sizeof type",
+        ExprKind::SizeofExpr(_) => "sizeof expr",
+        ExprKind::Alignof(_) => "alignof",
+        ExprKind::Construct { .. } => "construct",
+        _ => "other",
+    }).collect();
+    assert_eq!(kinds, ["cast", "paren", "sizeof type", "sizeof expr", "alignof", "cast", "cast", "construct", "construct", "construct"]);
+}
+
+#[test]
+fn printed_expressions_parse_back_the_same() {
+    let sources = [
+        "a = b ? c : -(d + e) * f[g](h, i)->j.k--",
+        "static_cast<const char* const*>(p) + sizeof(int (*)[3]) - (unsigned int)q",
+        "- -a - --b + +c + ++d + & &e",
+        "'\\n' + '\\'' + \"x\\\"y\" \"z\"",
+        "1.5e3 + 0x1F + 1'000",
+        ".5 + .25e1f + '\\0' + '\\x41' + '\\101' + '\\a' + \"\\1234\\x7f\"",
+        "0u + 017 + 4294967295UL + 0xFFull + 10LL + 2z",
+        "a <<= b >>= c <=> d->*e",
+    ];
+    for source in sources {
+        let (s, tu) = parse(&format!("int v = {};", source));
+        assert!(s.diagnostics.is_empty(), "{}", source);
+        let printed = inits(&tu)[0].to_string();
+        let (s, again) = parse(&format!("int v = {};", printed));
+        eprintln!("{} => {}", source, printed);
+        assert!(s.diagnostics.is_empty(), "{}", printed);
+        assert_eq!(inits(&again)[0].to_string(), printed);
+        assert_eq!(grouping(inits(&again)[0]), grouping(inits(&tu)[0]));
+    }
+}
+
+#[test]
+fn expression_errors() {
+    let cases = [
+        ("int v = a ? b;", "expected `:`, found `;`"),
+        ("int v = f(1, 2;", "expected `)`, found `;`"),
+        ("int v = a[1;", "expected `]`, found `;`"),
+        ("int v = static_cast(x);", "expected `<`, found `(`"),
+        ("int v = 1 + return;", "expected an expression, found `return`"),
+    ];
+    for (source, message) in cases {
+        let (s, _) = parse(source);
+        let d = s.diagnostics.iter().next().expect("a diagnostic");
+        eprintln!("{} -> {}", source, d.message);
+        assert_eq!(d.message, message);
+        assert_eq!(d.code.as_deref(), Some("E0201"));
+    }
+}
+
+/// The statements of the body of the function `tu` declares last.
+fn body<'t, 'a>(tu: &'t TranslationUnit<'a>) -> &'t [Stmt<'a>] {
+    match tu.decls.last() {
+        Some(Decl::Function(f)) => &f.body.as_ref().expect("a definition").stmts,
+        _ => panic!("not a function"),
+    }
+}
+
+/// One word per statement, nested statements in brackets.
+fn outline(stmt: &Stmt) -> String {
+    let nested = |stmts: &[&Stmt]| stmts.iter().map(|s| outline(s)).collect::<Vec<_>>().join(" ");
+    match &stmt.kind {
+        StmtKind::Compound(block) => format!("{{{}}}", nested(&block.stmts.iter().collect::<Vec<_>>())),
+        StmtKind::Null => "null".into(),
+        StmtKind::Expr(_) => "expr".into(),
+        StmtKind::Decl(decls) => format!("decl({})", decls.iter().map(|d| d.name().to_string()).collect::<Vec<_>>().join(",")),
+        StmtKind::If { then, otherwise: Some(otherwise), .. } => format!("if[{}] else[{}]", outline(then), outline(otherwise)),
+        StmtKind::If { then, .. } => format!("if[{}]", outline(then)),
+        StmtKind::While { body, .. } => format!("while[{}]", outline(body)),
+        StmtKind::DoWhile { body, .. } => format!("do[{}]", outline(body)),
+        StmtKind::For { init, cond, step, body } => format!("for({} {} {})[{}]",
+            init.as_deref().map_or("-".into(), outline), if cond.is_some() { "cond" } else { "-" }, if step.is_some() { "step" } else { "-" }, outline(body)),
+        StmtKind::RangeFor { init, decl, body, .. } => format!("for({} {} : range)[{}]", init.as_deref().map_or("-".into(), outline), type_of(decl), outline(body)),
+        StmtKind::Switch { body, .. } => format!("switch[{}]", outline(body)),
+        StmtKind::Case { body, .. } => format!("case[{}]", outline(body)),
+        StmtKind::Default(body) => format!("default[{}]", outline(body)),
+        StmtKind::Break => "break".into(),
+        StmtKind::Continue => "continue".into(),
+        StmtKind::Return(value) => if value.is_some() { "return(value)".into() } else { "return".into() },
+        StmtKind::CoReturn(value) => if value.is_some() { "co_return(value)".into() } else { "co_return".into() },
+        StmtKind::Attributed { stmt, .. } => format!("attributed[{}]", outline(stmt)),
+        StmtKind::Unsupported(u) => format!("unsupported {}", u.category),
+        StmtKind::Invalid => "invalid".into(),
+    }
+}
+
+#[test]
+fn statements_in_function_bodies() {
+    let (s, tu) = parse("\
+        typedef int T;\n\
+        void f(int n) {\n\
+          int a = 0, b; T t; Widget w; std::size_t z;\n\
+          a * b; g(n); ;\n\
+          if (n) a = 1; else if (b) { b = 2; } else return;\n\
+          while (n--) continue;\n\
+          do { break; } while (a < b);\n\
+          for (int i = 0; i < n; ++i) a += i;\n\
+          for (a = 0;;) {}\n\
+          switch (n) { case 1: case 2: a = 0; break; default: return; }\n\
+          return;\n\
+        }\n");
+    let outline: Vec<String> = body(&tu).iter().map(outline).collect();
+    eprintln!("{:#?}", outline);
+    assert!(s.diagnostics.is_empty());
+    assert_eq!(outline, [
+        "decl(a,b)", "decl(t)", "decl(w)", "decl(z)",
+        "expr", "expr", "null",
+        "if[expr] else[if[{expr}] else[return]]",
+        "while[continue]",
+        "do[{break}]",
+        "for(decl(i) cond step)[expr]",
+        "for(expr - -)[{}]",
+        "switch[{case[case[expr]] break default[return]}]",
+        "return",
+    ]);
+}
+
+#[test]
+fn statement_errors_recover_inside_the_block() {
+    let (s, tu) = parse("\
+        int f() {\n\
+          int a = ;\n\
+          if (a { a = 1; }\n\
+          while (1) { a = ); }\n\
+          do a++; until (a);\n\
+          int g() { return 0; }\n\
+          return a;\n\
+        }\n\
+        int after;\n");
+    let messages: Vec<String> = s.diagnostics.iter().map(|d| d.message.clone()).collect();
+    eprintln!("{:#?}", messages);
+    assert_eq!(messages, [
+        "expected an expression, found `;`",
+        "expected `)`, found `{`",
+        "expected an expression, found `)`",
+        "expected `while`, found `until`",
+        "a function cannot be defined inside another function",
+    ]);
+    let Decl::Function(f) = &tu.decls[0] else { panic!("not a function") };
+    let outline: Vec<String> = f.body.as_ref().unwrap().stmts.iter().map(outline).collect();
+    assert_eq!(outline, ["invalid", "invalid", "while[{invalid}]", "invalid", "invalid", "return(value)"]);
+    assert_eq!(tu.decls[1].name().to_string(), "after");
+}
+
+/// `access name: type` for every member of the class `decl`.
+fn members(decl: &Decl) -> Vec<String> {
+    let Decl::Class(c) = decl else { panic!("not a class") };
+    let decls: Vec<Decl> = c.members.iter().flatten().map(|m| m.decl.clone()).collect();
+    let types = types(&TranslationUnit { decls, ..Default::default() });
+    c.members.iter().flatten().zip(types).map(|(m, t)| format!("{} {}", m.access, t)).collect()
+}
+
+#[test]
+fn class_definitions_and_members() {
+    let (s, tu) = parse("\
+        struct Base { virtual ~Base(); };\n\
+        class Widget : public Base, virtual Other {\n\
+            int id;\n\
+        public:\n\
+            static int count;\n\
+            explicit Widget(int i) : Base(), id(i) { ++count; }\n\
+            int get() const { return id; }\n\
+            virtual void draw() = 0;\n\
+            Widget(const Widget&) = delete;\n\
+        protected:\n\
+            struct Inner { int x; } inner;\n\
+        };\n\
+        int Widget::count = 0;\n\
+        Widget::~Widget() {}\n\
+        struct Fwd;\n\
+        Widget w;\n");
+    eprintln!("{:#?}", types(&tu));
+    assert!(s.diagnostics.is_empty());
+    assert_eq!(types(&tu), [
+        "Base: struct", "Widget: class", "Widget::count: int", "Widget::~Widget: void ()", "Fwd: struct", "w: Widget",
+    ]);
+    assert_eq!(members(&tu.decls[1]), [
+        "private id: int",
+        "public count: int",
+        "public Widget: void (int)",
+        "public get: int ()",
+        "public draw: void ()",
+        "public Widget: void (const Widget&)",
+        "protected Inner: struct",
+        "protected inner: Inner",
+    ]);
+    let Decl::Class(widget) = &tu.decls[1] else { panic!("not a class") };
+    let bases: Vec<_> = widget.bases.iter().map(|b| (b.name.to_string(), b.access, b.is_virtual)).collect();
+    assert_eq!(bases, [("Base", Access::Public, false), ("Other", Access::Private, true)].map(|(name, access, is_virtual)| (name.to_string(), access, is_virtual)));
+    let members = widget.members.as_ref().unwrap();
+    let Decl::Function(ctor) = &members[2].decl else { panic!("not a function") };
+    assert_eq!(ctor.kind, FunctionKind::Constructor);
+    assert!(ctor.specifiers.is_explicit);
+    assert_eq!(ctor.initializers.iter().map(|i| i.name.to_string()).collect::<Vec<_>>(), ["Base", "id"]);
+    let Decl::Function(get) = &members[3].decl else { panic!("not a function") };
+    assert!(get.qualifiers.quals.is_const && get.body.is_some());
+    let Decl::Function(draw) = &members[4].decl else { panic!("not a function") };
+    assert!(draw.specifiers.is_virtual && draw.qualifiers.is_pure);
+    let Decl::Function(dtor) = &tu.decls[3] else { panic!("not a function") };
+    assert_eq!(dtor.kind, FunctionKind::Destructor);
+    let Decl::Class(fwd) = &tu.decls[4] else { panic!("not a class") };
+    assert!(fwd.members.is_none());
+}
+
+#[test]
+fn member_errors_recover_inside_the_class() {
+    let (s, tu) = parse("struct S { int = 1; int a; void f() { oops } int b; };\nint c;\n");
+    eprintln!("{:?} {:?}", codes(&s), types(&tu));
+    assert_eq!(codes(&s), ["E0201", "E0201"]);
+    assert_eq!(members(&tu.decls[0]), ["public : invalid", "public a: int", "public f: void ()", "public b: int"]);
+    assert_eq!(types(&tu), ["S: struct", "c: int"]);
+}
+
+#[test]
+fn final_classes_and_noexcept_functions() {
+    let (s, tu) = parse("\
+        struct A final {};\n\
+        class B final : public A { void f() const noexcept override final; B() noexcept(false); };\n\
+        struct C { virtual void f() noexcept(sizeof(int) == 4) = 0; };\n\
+        void g() noexcept;\n\
+        void h() noexcept(1 + 1 == 3) {}\n\
+        struct A final;\n\
+        struct final {};\n");
+    eprintln!("{:?} {:?}", codes(&s), types(&tu));
+    assert!(s.diagnostics.is_empty());
+    assert_eq!(types(&tu), ["A: struct", "B: class", "C: struct", "g: void ()", "h: void ()", "final: A", "final: struct"]);
+    let finals: Vec<bool> = tu.decls.iter().filter_map(|d| if let Decl::Class(c) = d { Some(c.is_final) } else { None }).collect();
+    assert_eq!(finals, [true, true, false, false]);
+    let noexcept = |d: &Decl| if let Decl::Function(f) = d { f.qualifiers.is_noexcept } else { panic!("not a function") };
+    let Decl::Class(b) = &tu.decls[1] else { panic!("not a class") };
+    let members = b.members.as_ref().unwrap();
+    let Decl::Function(f) = &members[0].decl else { panic!("not a function") };
+    assert!(f.qualifiers.quals.is_const && f.qualifiers.is_noexcept && f.qualifiers.is_override && f.qualifiers.is_final);
+    assert!(!noexcept(&members[1].decl));
+    let Decl::Class(c) = &tu.decls[2] else { panic!("not a class") };
+    let Decl::Function(f) = &c.members.as_ref().unwrap()[0].decl else { panic!("not a function") };
+    assert!(f.qualifiers.is_noexcept && f.qualifiers.is_pure);
+    assert!(noexcept(&tu.decls[3]));
+    assert!(!noexcept(&tu.decls[4]));
+    let dump = ast::dump(&tu, &s.sources);
+    eprintln!("{}", dump);
+    assert!(dump.contains("CXXRecordDecl struct A definition final <1:1>"));
+    assert!(dump.contains("CXXMethodDecl f 'void ()' const noexcept override final"));
+    assert!(dump.contains("FunctionDecl g 'void ()' noexcept <4:6>"));
+
+    // Dependent operands are left unevaluated.
+    let (s, tu) = parse("template <bool B> void f() noexcept(B);");
+    assert!(s.diagnostics.is_empty());
+    let Decl::Template(t) = &tu.decls[0] else { panic!("not a template") };
+    assert!(!noexcept(&t.decl));
+
+    for (source, code) in [("void f() noexcept noexcept;", "E0204"), ("int n; void f() noexcept(n);", "E0212"), ("void f() noexcept(1 / 0);", "E0302")] {
+        let (s, _) = parse(source);
+        eprintln!("{} -> {:?}", source, codes(&s));
+        assert_eq!(codes(&s), [code], "{}", source);
+    }
+}
+
+#[test]
+fn bit_fields_have_widths() {
+    let (s, tu) = parse("\
+        enum { Width = 5 };\n\
+        struct Flags { unsigned a : 3, b : Width; int : 0; char c; int d : 2 = 1; };\n\
+        template <int N> struct Bits { int x : N; };\n");
+    eprintln!("{:?} {:?}", codes(&s), types(&tu));
+    assert!(s.diagnostics.is_empty(), "{:?}", codes(&s));
+    let Decl::Class(flags) = &tu.decls[1] else { panic!("not a class") };
+    let widths: Vec<(String, Option<u64>)> = flags.members.iter().flatten().map(|m| match &m.decl {
+        Decl::Var(v) => (v.name.to_string(), v.bit_width.as_ref().and_then(|w| w.bits)),
+        _ => panic!("not a member"),
+    }).collect();
+    assert_eq!(widths, [("a", Some(3)), ("b", Some(5)), ("", Some(0)), ("c", None), ("d", Some(2))].map(|(name, bits)| (name.to_string(), bits)));
+    let Decl::Var(d) = &flags.members.as_ref().unwrap()[4].decl else { panic!("not a member") };
+    assert_eq!(d.init.as_ref().map(|e| e.to_string()).as_deref(), Some("1"));
+    let Decl::Template(bits) = &tu.decls[2] else { panic!("not a template") };
+    let Decl::Class(c) = &*bits.decl else { panic!("not a class") };
+    assert!(matches!(&c.members.as_ref().unwrap()[0].decl, Decl::Var(x) if x.bit_width.as_ref().is_some_and(|w| w.bits.is_none())));
+    let dump = ast::dump(&tu, &s.sources);
+    eprintln!("{}", dump);
+    assert!(dump.contains("|-FieldDecl 'int' public <2:47>\n| | `-IntegerLiteral 0"));
+
+    for (source, code) in [
+        ("struct S { int a : -1; };", "E0217"),
+        ("struct S { int a : 0; };", "E0218"),
+        ("int n; struct S { int a : n; };", "E0216"),
+        ("struct S { double d : 3; };", "E0206"),
+        ("struct S { static int s : 3; };", "E0206"),
+        ("int x : 3;", "E0201"),
+    ] {
+        let (s, _) = parse(source);
+        eprintln!("{} -> {:?}", source, codes(&s));
+        assert_eq!(codes(&s), [code], "{}", source);
+    }
+}
+
+#[test]
+fn enum_declarations() {
+    let (s, tu) = parse("\
+        enum Color { Red, Green = 4, Blue, };\n\
+        enum class Mode : unsigned char { Off, On = Off + 2 } m;\n\
+        enum struct Fwd : long;\n\
+        enum { A } a;\n\
+        enum Color c = Red;\n");
+    eprintln!("{:#?}", tu);
+    assert!(s.diagnostics.is_empty());
+    assert_eq!(types(&tu), ["Color: enum", "Mode: enum class", "m: Mode", "Fwd: enum class", ": enum", "a: (anonymous enum)", "c: Color"]);
+    let Decl::Enum(color) = &tu.decls[0] else { panic!("not an enum") };
+    let names: Vec<_> = color.enumerators.iter().flatten().map(|c| c.name.to_string()).collect();
+    assert_eq!(names, ["Red", "Green", "Blue"]);
+    let Decl::Enum(mode) = &tu.decls[1] else { panic!("not an enum") };
+    assert_eq!(mode.underlying.map(|t| t.to_string()).as_deref(), Some("unsigned char"));
+    assert_eq!(mode.enumerators.as_ref().unwrap()[1].value.as_ref().map(|v| v.to_string()).as_deref(), Some("Off + 2"));
+    let Decl::Enum(fwd) = &tu.decls[3] else { panic!("not an enum") };
+    assert!(fwd.is_scoped && fwd.enumerators.is_none());
+    for source in ["enum class { A };", "enum E { 1 };", "enum E { A B };"] {
+        let (s, _) = parse(source);
+        eprintln!("{:?} -> {:?}", source, codes(&s));
+        assert_eq!(codes(&s), ["E0201"], "{}", source);
+    }
+}
+
+#[test]
+fn verify_finds_misplaced_spans() {
+    let (s, mut tu) = parse("int x = 1 + 2, y;\n");
+    let Decl::Var(x) = &mut tu.decls[0] else { panic!("not a variable") };
+    let ExprKind::Binary { rhs, .. } = &mut x.init.as_mut().unwrap().kind else { panic!("not a binary expression") };
+    rhs.span = Span::new(15, 16);
+    let Decl::Var(y) = &mut tu.decls[1] else { panic!("not a variable") };
+    y.span = Span::new(0, 1);
+    let found: Vec<_> = ast::verify(&tu, &s.sources).into_iter().map(|m| format!("{} {}", m.node, m.problem)).collect();
+    eprintln!("{:#?}", found);
+    assert_eq!(found, [
+        "IntegerLiteral is not inside its parent `BinaryOperator` at 8..13",
+        "VarDecl comes before its previous sibling `VarDecl` at 0..13",
+    ][..]);
+}
+
+#[test]
+fn ast_dump_prints_the_tree() {
+    let dir = std::env::temp_dir().join(format!("ruscom-ast-dump-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("main.cpp");
+    std::fs::write(&file, "#include <cstddef>\nstatic const int limit = 42;\nint main(int argc, char **argv) { return 0; }\n").unwrap();
+    let output = Command::cargo_bin("ruscom").unwrap().args(["--verify-ast", "ast-dump"]).arg(&file).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    eprintln!("{}", stdout);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.starts_with("TranslationUnit\n"));
+    assert!(stdout.contains("|-NamespaceDecl std"));
+    assert!(stdout.contains("|-VarDecl limit 'const int' static <2:18>\n| `-IntegerLiteral 42 <2:26>\n"));
+    assert!(stdout.contains("`-FunctionDecl main 'int (int, char**)' <3:5>\n  |-ParamDecl argc 'int' <3:10>\n"));
+    assert!(stdout.ends_with("  `-CompoundStmt <3:33>\n    `-ReturnStmt <3:35>\n      `-IntegerLiteral 0 <3:42>\n"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn ast_dump_renders_json_and_dot() {
+    let (s, tu) = parse("int x = 1;\nint main() { return x; }\n");
+    let json: serde_json::Value = serde_json::from_str(&ast::dump_as(&tu, &s.sources, DumpOptions { format: DumpFormat::Json, spans: false })).unwrap();
+    eprintln!("{:#}", json);
+    assert_eq!(json["kind"], "TranslationUnit");
+    let var = &json["inner"][0];
+    assert_eq!(var["kind"], "VarDecl");
+    assert_eq!(var["detail"], "x 'int'");
+    assert_eq!(var["loc"], serde_json::json!({ "line": 1, "col": 5 }));
+    assert_eq!(var["range"], serde_json::json!({ "begin": { "line": 1, "col": 1 }, "end": { "line": 1, "col": 10 } }));
+    assert_eq!(var["inner"][0]["kind"], "IntegerLiteral");
+    assert!(var["inner"][0].get("inner").is_none());
+    assert_eq!(json["inner"][1]["inner"][0]["inner"][0]["inner"][0]["detail"], "x");
+
+    let dot = ast::dump_as(&tu, &s.sources, DumpOptions { format: DumpFormat::Dot, spans: false });
+    eprintln!("{}", dot);
+    assert!(dot.starts_with("digraph AST {\n"));
+    assert!(dot.contains("  n0 [label=\"TranslationUnit\"];\n  n0 -> n1;\n  n1 [label=\"VarDecl x 'int' <1:5>\"];\n  n1 -> n2;\n"));
+    assert!(dot.contains("  n0 -> n3;\n  n3 [label=\"FunctionDecl main 'int ()' <2:5>\"];\n"));
+    assert!(dot.ends_with("}\n"));
+    assert_eq!(ast::dump_as(&tu, &s.sources, DumpOptions::default()), ast::dump(&tu, &s.sources));
+    assert_eq!("dot".parse(), Ok(DumpFormat::Dot));
+    assert!("xml".parse::<DumpFormat>().is_err());
+}
+
+#[test]
+fn ast_dump_format_flag_selects_the_output() {
+    let dir = std::env::temp_dir().join(format!("ruscom-ast-dump-format-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("main.cpp");
+    std::fs::write(&file, "char s[] = \"a\\\"b\";\n").unwrap();
+    let output = Command::cargo_bin("ruscom").unwrap().args(["ast-dump", "--format", "dot"]).arg(&file).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    eprintln!("{}", stdout);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains(r#"[label="StringLiteral \"a\\\"b\" <1:12>"];"#));
+    let output = Command::cargo_bin("ruscom").unwrap().args(["ast-dump", "--format", "json"]).arg(&file).output().unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["inner"][0]["inner"][0]["detail"], r#""a\"b""#);
+    let output = Command::cargo_bin("ruscom").unwrap().args(["ast-dump", "--format", "xml"]).arg(&file).output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("expected tree, json or dot"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn every_node_has_a_span() {
+    let source = "template<class T = unsigned long> T f(T t) { return t; }\nlong x = f<long>(1);\n";
+    let (s, tu) = parse(source);
+    assert_eq!(s.sources.snippet(tu.span), Some(source));
+    let Decl::Template(t) = &tu.decls[0] else { panic!("not a template") };
+    let default = t.params[0].default.as_ref().unwrap();
+    assert_eq!(s.sources.snippet(default.span()), Some("unsigned long"));
+    let ExprKind::Call { callee, .. } = &inits(&tu)[0].kind else { panic!("not a call") };
+    let ExprKind::TemplateId { args, .. } = &callee.kind else { panic!("not a template-id") };
+    assert_eq!(s.sources.snippet(args[0].span()), Some("long"));
+
+    let dump = ast::dump_as(&tu, &s.sources, DumpOptions { format: DumpFormat::Tree, spans: true });
+    eprintln!("{}", dump);
+    assert!(dump.starts_with("TranslationUnit [1:1, 3:1)\n|-FunctionTemplateDecl f <1:1> [1:1, 1:57)\n"));
+    assert!(dump.contains("`-VarDecl x 'long' <2:6> [2:1, 2:20)\n"));
+    assert!(dump.lines().all(|l| l.ends_with(')')), "{}", dump);
+    let dot = ast::dump_as(&tu, &s.sources, DumpOptions { format: DumpFormat::Dot, spans: true });
+    assert!(dot.contains("n0 [label=\"TranslationUnit [1:1, 3:1)\"];"));
+    let json = ast::dump_as(&tu, &s.sources, DumpOptions { format: DumpFormat::Json, spans: true });
+    assert_eq!(json, ast::dump_as(&tu, &s.sources, DumpOptions { format: DumpFormat::Json, spans: false }));
+}
+
+#[test]
+fn template_declarations_and_template_ids() {
+    let (s, tu) = parse("\
+        template<typename T, int N = 4, class... Rest> struct Array { T items[4]; Array<T, N>* next; };\n\
+        template<class T> T max(T a, T b) { return a > b ? a : b; }\n\
+        template<template<class> class C = Array, typename U = const int*> struct Holder;\n\
+        Array<Array<int>> nested;\n\
+        Array<char, 2 + 1> sized;\n\
+        Array<int, (3 > 2)> compared;\n\
+        int m = max<int>(1, 2);\n\
+        int a, b, c;\n\
+        bool less = a < b > c;\n\
+        int T;\n");
+    eprintln!("{:#?}", types(&tu));
+    assert!(s.diagnostics.is_empty(), "{:?}", codes(&s));
+    assert_eq!(types(&tu), [
+        "Array: template struct",
+        "max: template T (T, T)",
+        "Holder: template struct",
+        "nested: Array<Array<int>>",
+        "sized: Array<char, 2 + 1>",
+        "compared: Array<int, (3 > 2)>",
+        "m: int",
+        "a: int", "b: int", "c: int",
+        "less: bool",
+        "T: int",
+    ]);
+    let Decl::Template(array) = &tu.decls[0] else { panic!("not a template") };
+    let params: Vec<String> = array.params.iter().map(|p| format!("{:?} {:?} {}", p.kind, p.name.map(|n| n.as_str()), p.is_pack)).collect();
+    eprintln!("{:#?}", params);
+    assert!(matches!(array.params[0].kind, TemplateParamKind::Type));
+    assert!(matches!(&array.params[1].default, Some(TemplateArg::Expr(e)) if e.to_string() == "4"));
+    assert!(array.params[2].is_pack);
+    assert_eq!(members(&array.decl), ["public items: T[4]", "public next: Array<T, N>*"]);
+    let Decl::Template(holder) = &tu.decls[2] else { panic!("not a template") };
+    assert!(matches!(&holder.params[0].kind, TemplateParamKind::Template(inner) if inner.len() == 1));
+    assert_eq!(holder.params[1].default.as_ref().map(|d| d.to_string()).as_deref(), Some("const int*"));
+    let printed: Vec<String> = inits(&tu).iter().map(|e| e.to_string()).collect();
+    assert_eq!(printed, ["max<int>(1, 2)", "a < b > c"]);
+    let ExprKind::Call { callee, .. } = &inits(&tu)[0].kind else { panic!("not a call") };
+    assert!(matches!(&callee.kind, ExprKind::TemplateId { args, .. } if args.len() == 1));
+    for source in ["template<class T> int x, y;", "template<class T struct S;"] {
+        let (s, _) = parse(source);
+        eprintln!("{:?} -> {:?}", source, codes(&s));
+        assert!(!codes(&s).is_empty(), "{}", source);
+    }
+}
+
+#[test]
+fn parameter_packs_out_of_class_members_and_explicit_specializations() {
+    let (s, tu) = parse("\
+        template<class... Ts> void f(Ts... ts);\n\
+        template<class T> struct S { void f(); static int n; };\n\
+        template<class T> void S<T>::f() {}\n\
+        template<class T> int S<T>::n = 1;\n\
+        template<> struct S<int> { int x; };\n\
+        template<class T> struct S<T*> {};\n\
+        S<int> s;\n");
+    eprintln!("{:#?}", types(&tu));
+    assert!(s.diagnostics.is_empty(), "{:?}", codes(&s));
+    let Decl::Template(f) = &tu.decls[0] else { panic!("not a template") };
+    let Decl::Function(f) = &*f.decl else { panic!("not a function") };
+    assert_eq!(f.params[0].name.map(|n| n.to_string()).as_deref(), Some("ts"));
+    assert!(f.params[0].is_pack);
+    let Decl::Template(member) = &tu.decls[2] else { panic!("not a template") };
+    assert!(matches!(&*member.decl, Decl::Function(m) if &*m.name.as_str() == "S::f" && m.body.is_some()));
+    let Decl::Template(n) = &tu.decls[3] else { panic!("not a template") };
+    assert_eq!(n.decl.name().to_string(), "S::n");
+    let args = |d: &Decl| match d {
+        Decl::Template(t) => match &*t.decl {
+            Decl::Class(c) => (t.params.len(), c.args.as_ref().map(|a| a.iter().map(|a| a.to_string()).collect::<Vec<_>>())),
+            _ => panic!("not a class"),
+        },
+        _ => panic!("not a template"),
+    };
+    assert_eq!(args(&tu.decls[1]), (1, None));
+    assert_eq!(args(&tu.decls[4]), (0, Some(vec!["int".to_string()])));
+    assert_eq!(args(&tu.decls[5]), (1, Some(vec!["T*".to_string()])));
+    assert_eq!(types(&tu).last().map(String::as_str), Some("s: S<int>"));
+    let dump = ast::dump(&tu, &s.sources);
+    eprintln!("{}", dump);
+    assert!(dump.contains("ClassTemplateSpecializationDecl"));
+    assert!(dump.contains("ClassTemplatePartialSpecializationDecl"));
+    assert!(dump.contains("'Ts...'"));
+}
+
+#[test]
+fn attributes_attach_to_declarations_and_statements() {
+    let (s, tu) = parse("\
+        [[nodiscard]] int f([[maybe_unused]] int x);\n\
+        int g(const char *fmt, ...) __attribute__((noinline, format(printf, 1, 2)));\n\
+        [[deprecated(\"use k\"), gnu::cold]] void h();\n\
+        struct [[gnu::packed]] P { [[nodiscard]] int get() const; };\n\
+        enum class [[nodiscard]] E { A [[deprecated]], B };\n\
+        [[using gnu: hot, always_inline]] void k();\n\
+        int n [[maybe_unused]] = 0;\n\
+        void body() { [[likely]] return; [[maybe_unused]] int y = 0; [[,]] ; }\n");
+    assert!(s.diagnostics.is_empty(), "{:?}", codes(&s));
+    let list = |attrs: &[ast::Attribute]| attrs.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", ");
+    let attrs: Vec<String> = tu.decls.iter().map(|d| match d {
+        Decl::Function(f) => list(&f.attrs),
+        Decl::Class(c) => list(&c.attrs),
+        Decl::Enum(e) => list(&e.attrs),
+        Decl::Var(v) => list(&v.attrs),
+        _ => panic!("unexpected {:?}", d),
+    }).collect();
+    eprintln!("{:#?}", attrs);
+    assert_eq!(attrs, [
+        "nodiscard",
+        "gnu::noinline, gnu::format(printf, 1, 2)",
+        "deprecated(\"use k\"), gnu::cold",
+        "gnu::packed",
+        "nodiscard",
+        "gnu::hot, gnu::always_inline",
+        "maybe_unused",
+        "",
+    ]);
+    let Decl::Function(f) = &tu.decls[0] else { panic!("not a function") };
+    assert_eq!(list(&f.params[0].attrs), "maybe_unused");
+    let Decl::Class(p) = &tu.decls[3] else { panic!("not a class") };
+    assert!(matches!(&p.members.as_ref().unwrap()[0].decl, Decl::Function(get) if list(&get.attrs) == "nodiscard"));
+    let Decl::Enum(e) = &tu.decls[4] else { panic!("not an enum") };
+    assert_eq!(list(&e.enumerators.as_ref().unwrap()[0].attrs), "deprecated");
+    let Decl::Function(body) = &tu.decls[7] else { panic!("not a function") };
+    let stmts = &body.body.as_ref().unwrap().stmts;
+    assert!(matches!(&stmts[0].kind, StmtKind::Attributed { attrs, stmt } if list(attrs) == "likely" && matches!(stmt.kind, StmtKind::Return(None))));
+    assert!(matches!(&stmts[1].kind, StmtKind::Decl(d) if matches!(&d[0], Decl::Var(y) if list(&y.attrs) == "maybe_unused")));
+    assert!(matches!(&stmts[2].kind, StmtKind::Attributed { attrs, .. } if attrs.is_empty()));
+}
+
+#[test]
+fn typedefs_aliases_and_alias_templates() {
+    let (s, tu) = parse("\
+        typedef unsigned long size;\n\
+        using Byte = unsigned char;\n\
+        using Handler [[deprecated]] = void (*)(int);\n\
+        namespace std { template<class T> struct vector; void swap(int &, int &); using size_t = size; }\n\
+        template<class T> using Vec = std::vector<T>;\n\
+        template<class T> using Ptr = const T*;\n\
+        using std::swap;\n\
+        using std::size_t;\n\
+        Byte b;\n\
+        Handler h;\n\
+        Vec<int> v;\n\
+        Ptr<Byte> p;\n\
+        size_t n = sizeof(Vec<Byte>);\n");
+    eprintln!("{:#?}", types(&tu));
+    assert!(s.diagnostics.is_empty(), "{:?}", codes(&s));
+    assert_eq!(types(&tu), [
+        "size: unsigned long",
+        "Byte: unsigned char",
+        "Handler: void (*)(int)",
+        "std: namespace",
+        "Vec: template std::vector<T>",
+        "Ptr: template const T*",
+        "std::swap: using",
+        "std::size_t: using",
+        "b: Byte",
+        "h: Handler",
+        "v: Vec<int>",
+        "p: Ptr<Byte>",
+        "n: size_t",
+    ]);
+    let aliases: Vec<bool> = tu.decls[..3].iter().map(|d| matches!(d, Decl::Typedef(t) if t.is_alias)).collect();
+    assert_eq!(aliases, [false, true, true]);
+    let dump = ast::dump(&tu, &s.sources);
+    eprintln!("{}", dump);
+    assert!(dump.contains("TypeAliasDecl Byte 'unsigned char'"));
+    assert!(dump.contains("TypeAliasTemplateDecl Vec"));
+    for source in ["using X = ;", "using = int;"] {
+        let (s, _) = parse(source);
+        eprintln!("{:?} -> {:?}", source, codes(&s));
+        assert!(!codes(&s).is_empty(), "{}", source);
+    }
+}
+
+#[test]
+fn braced_and_direct_initializers() {
+    let (s, tu) = parse("\
+        struct Point { int x{0}, y = 1; Point() : x{2}, y(3) {} };\n\
+        int a{5}, b(6), c = {7}, d{}, e = int{8};\n\
+        int arr[3] = {1, 2, 3,};\n\
+        Point p{.x = 1, .y{2}};\n\
+        int f();\n\
+        int g(int);\n\
+        Point make() { Point q(p); q = {3, 4}; Point{5, 6}; use({1, 2}, Point{}); return {7, 8}; }\n");
+    eprintln!("{:#?}", types(&tu));
+    assert!(s.diagnostics.is_empty(), "{:?}", codes(&s));
+    let vars: Vec<String> = tu.decls.iter().filter_map(|d| match d {
+        Decl::Var(v) => Some(format!("{} {:?} {}", v.name, v.init_style, v.init.as_ref().map_or("-".into(), |e| e.to_string()))),
+        _ => None,
+    }).collect();
+    eprintln!("{:#?}", vars);
+    assert_eq!(vars, [
+        "a List {5}", "b Direct (6)", "c Copy {7}", "d List {}", "e Copy int{8}",
+        "arr Copy {1, 2, 3}",
+        "p List {.x = 1, .y{2}}",
+    ]);
+    assert_eq!(types(&tu)[8..10], ["f: int ()", "g: int (int)"]);
+    assert_eq!(members(&tu.decls[0]), ["public x: int", "public y: int", "public Point: void ()"]);
+    let Decl::Class(point) = &tu.decls[0] else { panic!("not a class") };
+    let Decl::Function(ctor) = &point.members.as_ref().unwrap()[2].decl else { panic!("not a constructor") };
+    let inits: Vec<(String, bool)> = ctor.initializers.iter().map(|i| (i.name.to_string(), i.braced)).collect();
+    assert_eq!(inits, [("x".to_string(), true), ("y".to_string(), false)]);
+    let outlines: Vec<String> = body(&tu).iter().map(outline).collect();
+    assert_eq!(outlines, ["decl(q)", "expr", "expr", "expr", "return(value)"]);
+    let printed: Vec<String> = body(&tu).iter().map(|s| match &s.kind {
+        StmtKind::Expr(e) | StmtKind::Return(Some(e)) => e.to_string(),
+        StmtKind::Decl(d) => match &d[0] { Decl::Var(v) => format!("{:?} {}", v.init_style, v.init.as_ref().unwrap()), _ => panic!() },
+        _ => panic!("unexpected statement"),
+    }).collect();
+    assert_eq!(printed, ["Direct (p)", "q = {3, 4}", "Point{5, 6}", "use({1, 2}, Point{})", "{7, 8}"]);
+    let dump = ast::dump(&tu, &s.sources);
+    eprintln!("{}", dump);
+    assert!(dump.contains("VarDecl a 'int' listinit"));
+    assert!(dump.contains("VarDecl b 'int' callinit"));
+    assert!(dump.contains("DesignatedInitExpr .y"));
+    for source in ["int a{1;", "Point p{.x 1};", "int b(1;"] {
+        let (s, _) = parse(&format!("struct Point {{ int x; }}; {}", source));
+        eprintln!("{:?} -> {:?}", source, codes(&s));
+        assert!(!codes(&s).is_empty(), "{}", source);
+    }
+}
+
+#[test]
+fn embedded_files_are_one_expression() {
+    let mut fs = MemoryFileSystem::new();
+    fs.insert("main.cpp", "const unsigned char data[] = {\n#embed \"icon.bin\"\n, 0 };\nint first =\n#embed \"icon.bin\" limit(1)\n;\n");
+    fs.insert("icon.bin", vec![137u8, 80, 78, 71]);
+    let mut s = Session::new(Options::default()).with_file_system(Arc::new(fs));
+    let main = s.sources.load(s.fs.clone().as_ref(), "main.cpp".as_ref()).unwrap();
+    let out = Preprocessor::new(&mut s).run(main).unwrap();
+    let arena = TuArena::new();
+    let tu = Parser::new(&mut s, &arena, out.tokens).parse().unwrap();
+    assert!(s.diagnostics.is_empty(), "{:?}", codes(&s));
+    let printed: Vec<String> = inits(&tu).iter().map(|e| e.to_string()).collect();
+    assert_eq!(printed, ["{137, 80, 78, 71, 0}", "137"]);
+    assert!(matches!(&inits(&tu)[0].kind, ExprKind::InitList(items) if matches!(&items[0].kind, ExprKind::Embed(b) if b.len() == 4)));
+    assert_eq!(ast::verify(&tu, &s.sources), []);
+}
+
+#[test]
+fn structured_bindings() {
+    let (s, tu) = parse("\
+        struct P { int x; double y; };\n\
+        P p;\n\
+        auto [a, b] = p;\n\
+        const auto& [c, d](p);\n\
+        auto&& [e [[maybe_unused]], f]{p};\n\
+        static auto x = 1;\n\
+        void g() { auto [m, n] = p; auto& [k] = q; }\n");
+    assert!(s.diagnostics.is_empty(), "{:?}", codes(&s));
+    let decls: Vec<String> = tu.decls.iter().filter_map(|d| match d {
+        Decl::Decomposition(d) => Some(format!("{} [{}] {:?} {}", d.ty, d.bindings.iter().map(|b| b.name.to_string()).collect::<Vec<_>>().join(", "), d.init_style, d.init.as_ref().unwrap())),
+        Decl::Var(v) => Some(format!("{}: {}", v.name, v.ty)),
+        _ => None,
+    }).collect();
+    eprintln!("{:#?}", decls);
+    assert_eq!(decls, ["p: P", "auto [a, b] Copy p", "const auto& [c, d] Direct (p)", "auto&& [e, f] List {p}", "x: auto"]);
+    let outlines: Vec<String> = body(&tu).iter().map(outline).collect();
+    assert_eq!(outlines, ["decl()", "decl()"]);
+    let dump = ast::dump(&tu, &s.sources);
+    eprintln!("{}", dump);
+    assert!(dump.contains("DecompositionDecl 'auto&&' listinit"));
+    assert!(dump.contains("BindingDecl e [[maybe_unused]]"));
+    assert_eq!(ast::verify(&tu, &s.sources), []);
+    for source in ["int [a, b] = p;", "auto [a, b];", "auto [] = p;", "typedef auto [a] = p;", "auto [a, 1] = p;"] {
+        let (s, _) = parse(source);
+        eprintln!("{:?} -> {:?}", source, codes(&s));
+        assert!(!codes(&s).is_empty(), "{}", source);
+    }
+}
+
+#[test]
+fn range_based_for_loops() {
+    let (s, tu) = parse("\
+        struct Container;\n\
+        void f(Container &v, int arr[3], int n) {\n\
+          for (auto& x : v) use(x);\n\
+          for (const int y : arr) {}\n\
+          for (auto [a, b] : pairs) ;\n\
+          for (int i = 0; auto&& z : {1, 2, 3}) ;\n\
+          for (n = 0; [[maybe_unused]] auto w : v) break;\n\
+          for (int i = 0; i < n; ++i) ;\n\
+          for (Container::iterator it; it != v.end(); ) ;\n\
+        }\n");
+    assert!(s.diagnostics.is_empty(), "{:?}", codes(&s));
+    let outlines: Vec<String> = body(&tu).iter().map(outline).collect();
+    assert_eq!(outlines, [
+        "for(- auto& : range)[expr]",
+        "for(- const int : range)[{}]",
+        "for(- auto : range)[null]",
+        "for(decl(i) auto&& : range)[null]",
+        "for(expr auto : range)[break]",
+        "for(decl(i) cond step)[null]",
+        "for(decl(it) cond -)[null]",
+    ]);
+    let StmtKind::RangeFor { decl, range, .. } = &body(&tu)[3].kind else { panic!("not a range-based for") };
+    assert_eq!(range.to_string(), "{1, 2, 3}");
+    assert!(matches!(&**decl, Decl::Var(z) if &*z.name.as_str() == "z" && z.init.is_none()));
+    let dump = ast::dump(&tu, &s.sources);
+    eprintln!("{}", dump);
+    assert_eq!(dump.matches("CXXForRangeStmt").count(), 5);
+    assert!(dump.contains("DecompositionDecl 'auto'"));
+    assert_eq!(ast::verify(&tu, &s.sources), []);
+    for source in ["for (auto x : ) ;", "for (auto x : v ;", "for (auto [a, b] v) ;"] {
+        let (s, _) = parse(&format!("void f() {{ {} }}", source));
+        eprintln!("{:?} -> {:?}", source, codes(&s));
+        assert!(!codes(&s).is_empty(), "{}", source);
+    }
+}
+
+#[test]
+fn operator_functions_and_user_defined_literals() {
+    let (s, tu) = parse("\
+        struct V {\n\
+          V operator+(const V &o) const;\n\
+          int &operator[](int i);\n\
+          int operator()(int a, int b);\n\
+          explicit operator bool() const;\n\
+          operator const char *() const;\n\
+          V &operator<<=(int n);\n\
+          void *operator new[](unsigned long n);\n\
+          friend bool operator==(V, V) { return true; }\n\
+        };\n\
+        V::operator bool() const { return true; }\n\
+        V operator-(V a, V b);\n\
+        long double operator\"\"_km(long double v);\n\
+        void f(V v) { v.operator bool(); operator-(v, v); x = 1.5_km + 12_n + 10ms; y = \"abc\"_str; z = \"%\" \"d\"s; }\n");
+    assert!(s.diagnostics.is_empty(), "{:?}", codes(&s));
+    assert_eq!(members(&tu.decls[0]), [
+        "public operator+: V (const V&)",
+        "public operator[]: int&(int)",
+        "public operator(): int (int, int)",
+        "public operator bool: bool ()",
+        "public operator const char*: const char*()",
+        "public operator<<=: V&(int)",
+        "public operator new[]: void*(unsigned long)",
+        "public operator==: bool (V, V)",
+    ]);
+    let Decl::Class(c) = &tu.decls[0] else { panic!("not a class") };
+    let kinds: Vec<FunctionKind> = c.members.iter().flatten().filter_map(|m| match &m.decl { Decl::Function(f) => Some(f.kind), _ => None }).collect();
+    assert_eq!(kinds.iter().filter(|k| **k == FunctionKind::Conversion).count(), 2);
+    assert_eq!(types(&tu)[1..4], ["V::operator bool: bool ()", "operator-: V (V, V)", "operator\"\"_km: long double (long double)"]);
+    let exprs: Vec<String> = body(&tu).iter().map(|s| match &s.kind { StmtKind::Expr(e) => e.to_string(), _ => panic!("not an expression") }).collect();
+    assert_eq!(exprs, ["v.operator bool()", "operator-(v, v)", "x = 1.5_km + 12_n + 10ms", "y = \"abc\"_str", "z = \"%d\"s"]);
+    let dump = ast::dump(&tu, &s.sources);
+    eprintln!("{}", dump);
+    assert_eq!(dump.matches("CXXConversionDecl").count(), 3);
+    assert!(dump.contains("FunctionDecl operator== 'bool (V, V)' friend"));
+    assert!(dump.contains("UserDefinedLiteral _km"));
+    for source in ["int operator@(int);", "int operator\"\"(int);", "int x = 1.5.5_km;", "int operator;"] {
+        let (s, _) = parse(source);
+        eprintln!("{:?} -> {:?}", source, codes(&s));
+        assert!(!codes(&s).is_empty(), "{}", source);
+    }
+}
+
+#[test]
+fn concepts_and_requires_clauses() {
+    let (s, tu) = parse("\
+        template<class T> constexpr bool is_integral_v = false;\n\
+        template<typename T> concept Integral = is_integral_v<T>;\n\
+        template<typename T, typename U> concept Same = is_integral_v<T> && true;\n\
+        template<typename T> concept Addable = requires (T a, T b) {\n\
+          a + b;\n\
+          typename T::value_type;\n\
+          { a + b } noexcept -> Same<T>;\n\
+          requires Integral<typename T::value_type>;\n\
+        };\n\
+        template<Integral T> T twice(T x) { return x + x; }\n\
+        template<Same<int>... Ts> void many();\n\
+        template<typename T> requires Integral<T> || (sizeof(T) > 4) struct Box { T value; };\n\
+        template<typename T> void f(T x) requires Addable<T> && std::copyable<T>;\n\
+        void g(Integral auto x, const Same<int> auto &y) { Integral auto z = x; }\n\
+        struct S { template<class T> S(T t) requires Integral<T>; void h() const requires true = delete; };\n\
+        bool b = requires { 1 + 1; };\n");
+    assert!(s.diagnostics.is_empty(), "{:?}", codes(&s));
+    assert_eq!(types(&tu), [
+        "is_integral_v: template bool",
+        "Integral: template concept",
+        "Same: template concept",
+        "Addable: template concept",
+        "twice: template T (T)",
+        "many: template void ()",
+        "Box: template struct",
+        "f: template void (T)",
+        "g: void (auto, const auto&)",
+        "S: struct",
+        "b: bool",
+    ]);
+    let Decl::Template(t) = &tu.decls[3] else { panic!("not a template") };
+    let Decl::Concept(c) = &*t.decl else { panic!("not a concept") };
+    assert_eq!(c.constraint.to_string(), "requires (T a, T b) { a + b; typename T::value_type; { a + b } noexcept -> Same<T>; requires Integral<T::value_type>; }");
+    let constraints: Vec<String> = tu.decls[4..6].iter().map(|d| match d {
+        Decl::Template(t) => t.params[0].constraint.as_ref().map(|c| c.to_string()).unwrap_or_default(),
+        _ => panic!("not a template"),
+    }).collect();
+    assert_eq!(constraints, ["Integral", "Same<int>"]);
+    let Decl::Template(t) = &tu.decls[6] else { panic!("not a template") };
+    assert_eq!(t.requires.as_ref().unwrap().to_string(), "Integral<T> || (sizeof(T) > 4)");
+    let Decl::Template(t) = &tu.decls[7] else { panic!("not a template") };
+    let Decl::Function(f) = &*t.decl else { panic!("not a function") };
+    assert_eq!(f.requires.as_ref().unwrap().to_string(), "Addable<T> && std::copyable<T>");
+    let dump = ast::dump(&tu, &s.sources);
+    eprintln!("{}", dump);
+    for part in ["ConceptDecl Addable", "RequiresExpr", "CompoundRequirement noexcept", "TypeRequirement 'T::value_type'", "NestedRequirement", "SimpleRequirement"] {
+        assert!(dump.contains(part), "{}", part);
+    }
+    for source in [
+        "template<class T> concept C;",
+        "template<class T> concept = true;",
+        "template<class T> concept C = requires (T a) { a + 1 };",
+        "template<class T> requires void f();",
+    ] {
+        let (s, _) = parse(source);
+        eprintln!("{:?} -> {:?}", source, codes(&s));
+        assert!(!codes(&s).is_empty(), "{}", source);
+    }
+}
+
+#[test]
+fn coroutine_operators_and_co_return() {
+    let (s, tu) = parse("\
+        task count(int n) {\n\
+          for (int i = 0; i < n; ++i) co_yield i;\n\
+          co_yield {1, 2};\n\
+          int x = co_await read() + 1;\n\
+          co_await std::suspend_always();\n\
+          if (x) co_return;\n\
+          co_return x;\n\
+        }\n");
+    let outline: Vec<String> = body(&tu).iter().map(outline).collect();
+    eprintln!("{:#?}", outline);
+    assert!(s.diagnostics.is_empty(), "{:?}", codes(&s));
+    assert_eq!(outline, ["for(decl(i) cond step)[expr]", "expr", "decl(x)", "expr", "if[co_return]", "co_return(value)"]);
+    let Decl::Function(f) = &tu.decls[0] else { panic!("not a function") };
+    assert!(f.is_coroutine);
+    let printed: Vec<String> = f.body.as_ref().unwrap().stmts[1..4].iter().map(|stmt| match &stmt.kind {
+        StmtKind::Expr(e) => e.to_string(),
+        StmtKind::Decl(decls) => match &decls[0] {
+            Decl::Var(v) => v.init.as_ref().unwrap().to_string(),
+            _ => panic!("not a variable"),
+        },
+        _ => panic!("not an expression"),
+    }).collect();
+    // `co_await` binds like a unary operator, `co_yield` like an assignment.
+    assert_eq!(printed, ["co_yield {1, 2}", "co_await read() + 1", "co_await std::suspend_always()"]);
+
+    let (s, tu) = parse("int f() { return 1; } struct S { S() {} task run() { co_await 1; } void g(); };");
+    assert!(s.diagnostics.is_empty());
+    let Decl::Function(f) = &tu.decls[0] else { panic!("not a function") };
+    assert!(!f.is_coroutine);
+    let Decl::Class(c) = &tu.decls[1] else { panic!("not a class") };
+    let coroutines: Vec<bool> = c.members.iter().flatten().map(|m| matches!(&m.decl, Decl::Function(f) if f.is_coroutine)).collect();
+    assert_eq!(coroutines, [false, true, false]);
+    let dump = ast::dump(&tu, &s.sources);
+    assert!(dump.contains("CXXMethodDecl run 'task ()' coroutine"), "{}", dump);
+
+    for source in ["void f() { co_return 1 }", "void f() { co_await; }", "int co_yield;"] {
+        let (s, _) = parse(source);
+        eprintln!("{:?} -> {:?}", source, codes(&s));
+        assert!(!codes(&s).is_empty(), "{}", source);
+    }
+}
+
+#[test]
+fn module_declarations_imports_and_exports() {
+    let (s, tu) = parse("\
+        module;\n\
+        export module geo.core:shapes;\n\
+        import std;\n\
+        export import :base;\n\
+        import <sys/types.h>;\n\
+        import \"config.h\";\n\
+        export int area(int w, int h);\n\
+        export { struct Point { int x; }; using Id = long; }\n\
+        export namespace geo { template<class T> struct Box; }\n\
+        export template<class T> concept Small = sizeof(T) <= 4;\n\
+        namespace { export int hidden; }\n\
+        int internal;\n\
+        int module = 1; int import(int);\n\
+        module :private;\n");
+    assert!(s.diagnostics.is_empty(), "{:?}", codes(&s));
+    assert_eq!(types(&tu), [
+        ": module",
+        "geo.core:shapes: export module",
+        "std: import",
+        ":base: export import",
+        "<sys/types.h>: import",
+        "\"config.h\": import",
+        ": export {int (int, int)}",
+        ": export {struct, long}",
+        ": export {namespace}",
+        ": export {template concept}",
+        ": namespace",
+        "internal: int",
+        "module: int",
+        "import: int (int)",
+        ": module",
+    ]);
+    let interface = ModuleInterface::of(&tu).expect("a module interface");
+    assert_eq!(interface.name.to_string(), "geo.core:shapes");
+    assert_eq!(interface.imports.iter().map(|m| m.to_string()).collect::<Vec<_>>(), ["geo.core:base"]);
+    let exported: Vec<String> = interface.decls.iter().map(|d| format!("{} {}", d.kind, d.name)).collect();
+    // `hidden` is in an unnamed namespace, which exports nothing.
+    assert_eq!(exported, ["function area", "class Point", "alias Id", "class template geo::Box", "concept Small"]);
+    let dump = ast::dump(&tu, &s.sources);
+    eprintln!("{}", dump);
+    for part in ["ModuleDecl <1:1>", "ModuleDecl export geo.core:shapes", "ImportDecl export :base", "ExportDecl braced", "ModuleDecl :private"] {
+        assert!(dump.contains(part), "{}", part);
+    }
+
+    for source in ["export module;", "export { export int x; }", "import ;", "export module m", "import <vector;"] {
+        let (s, _) = parse(source);
+        eprintln!("{:?} -> {:?}", source, codes(&s));
+        assert!(!codes(&s).is_empty(), "{}", source);
+    }
+}
+
+#[test]
+fn imports_load_compiled_interfaces_from_the_module_cache() {
+    let (_, tu) = parse("export module geo;\nexport import :shapes;\nexport namespace geo { template<class T> struct Box { T v; }; }\nexport using Id = int;\n");
+    let geo = ModuleInterface::of(&tu).unwrap();
+    let (_, tu) = parse("export module geo:shapes;\nexport template<class T> concept Small = true;\n");
+    let shapes = ModuleInterface::of(&tu).unwrap();
+    assert_eq!(shapes.decls[0].kind, ExportKind::Concept);
+    let mut fs = MemoryFileSystem::new();
+    fs.insert("cache/geo.rcm", ruscom::artifact::encode_module(&geo));
+    fs.insert("cache/geo-shapes.rcm", ruscom::artifact::encode_module(&shapes));
+    let source = "import geo;\nBox<int> a;\ngeo::Box<Id> b;\ntemplate<Small T> void f(T);\nimport missing;\n";
+    fs.insert("main.cpp", source);
+    let options = Options { module_cache: Some("cache".into()), ..Options::default() };
+    let mut s = Session::new(options).with_file_system(Arc::new(fs));
+    let main = s.sources.load(s.fs.clone().as_ref(), "main.cpp".as_ref()).unwrap();
+    let out = Preprocessor::new(&mut s).run(main).unwrap();
+    let arena = TuArena::new();
+    let tu = Parser::new(&mut s, &arena, out.tokens).parse().unwrap();
+    let messages: Vec<String> = s.diagnostics.iter().map(|d| d.message.clone()).collect();
+    eprintln!("{:?}", messages);
+    assert_eq!(messages.len(), 1);
+    assert!(messages[0].starts_with("cannot load module `missing`: cache/missing.rcm"), "{}", messages[0]);
+    assert_eq!(types(&tu), ["geo: import", "a: Box<int>", "b: geo::Box<Id>", "f: template void (T)", "missing: import"]);
+}
@@ -0,0 +1,554 @@
+use std::sync::Arc;
+
+use assert_cmd::Command;
+
+use ruscom::driver::Driver;
+use ruscom::lexer::token::Token;
+use ruscom::preprocessor::{print, Preprocessor};
+use ruscom::session::{Options, Session, Target};
+use ruscom::vfs::MemoryFileSystem;
+
+fn session(files: &[(&str, &str)], include_dirs: &[&str]) -> Session {
+    let mut fs = MemoryFileSystem::new();
+    for &(path, contents) in files { fs.insert(path, contents); }
+    let options = Options { include_dirs: include_dirs.iter().map(Into::into).collect(), ..Options::default() };
+    Session::new(options).with_file_system(Arc::new(fs))
+}
+
+fn texts(session: &Session, tokens: &[ruscom::lexer::token::SpannedToken]) -> Vec<String> {
+    tokens.iter().map(|t| session.sources.snippet(t.span).unwrap().to_string()).collect()
+}
+
+#[test]
+fn quoted_and_angled_includes_are_spliced_in() {
+    let mut s = session(&[
+        ("src/main.cpp", "#include \"local.h\"\n#include <lib.h>\nint x;"),
+        ("src/local.h", "int local;"),
+        ("inc/lib.h", "int lib;"),
+    ], &["inc"]);
+    let main = s.sources.load(s.fs.clone().as_ref(), "src/main.cpp".as_ref()).unwrap();
+    let out = Preprocessor::new(&mut s).run(main).unwrap();
+    let texts = texts(&s, &out.tokens);
+    eprintln!("{:?}", texts);
+    assert_eq!(texts, ["int", "local", ";", "int", "lib", ";", "int", "x", ";"]);
+    assert_eq!(out.files.len(), 3);
+    // Spans point into the header that was included.
+    let lib = s.sources.location(out.tokens[4].span.start).unwrap();
+    assert_eq!(lib.to_string(), "inc/lib.h:1:5");
+    let from = s.sources.file(out.files[2]).included_from.unwrap();
+    assert_eq!(s.sources.snippet(from), Some("#include <lib.h>"));
+    assert!(s.diagnostics.is_empty());
+}
+
+#[test]
+fn includes_climb_above_the_including_directory() {
+    let mut s = session(&[
+        ("n2/n3/main.cpp", "#include \"../../h.h\"\n"),
+        ("n2/n3/h.h", "int wrong;"),
+        ("h.h", "int right;"),
+    ], &[]);
+    let main = s.sources.load(s.fs.clone().as_ref(), "n2/n3/main.cpp".as_ref()).unwrap();
+    let out = Preprocessor::new(&mut s).run(main).unwrap();
+    assert_eq!(texts(&s, &out.tokens), ["int", "right", ";"]);
+    // From a file named without a directory, `..` stays above it.
+    let mut s = session(&[("main.cpp", "#include \"../../h.h\"\n"), ("h.h", "int wrong;")], &[]);
+    let main = s.sources.load(s.fs.clone().as_ref(), "main.cpp".as_ref()).unwrap();
+    let out = Preprocessor::new(&mut s).run(main).unwrap();
+    assert!(out.tokens.is_empty());
+    assert_eq!(s.diagnostics.iter().next().unwrap().message, "`../../h.h` file not found");
+}
+
+#[test]
+fn angled_includes_skip_the_current_directory() {
+    let mut s = session(&[("main.cpp", "#include <near.h>\n"), ("near.h", "int near;")], &[]);
+    let main = s.sources.load(s.fs.clone().as_ref(), "main.cpp".as_ref()).unwrap();
+    let out = Preprocessor::new(&mut s).run(main).unwrap();
+    assert!(out.tokens.is_empty());
+    let diag = s.diagnostics.iter().next().unwrap();
+    assert_eq!(diag.code.as_deref(), Some("E0101"));
+    assert_eq!(diag.message, "`near.h` file not found");
+}
+
+#[test]
+fn include_cycles_are_reported() {
+    let mut s = session(&[
+        ("a.h", "#include \"b.h\"\nint a;"),
+        ("b.h", "#include \"a.h\"\nint b;"),
+    ], &[]);
+    let a = s.sources.load(s.fs.clone().as_ref(), "a.h".as_ref()).unwrap();
+    let out = Preprocessor::new(&mut s).run(a).unwrap();
+    assert_eq!(texts(&s, &out.tokens), ["int", "b", ";", "int", "a", ";"]);
+    let diag = s.diagnostics.iter().next().unwrap();
+    eprintln!("{:?}", diag);
+    assert_eq!(diag.code.as_deref(), Some("E0102"));
+    assert_eq!(diag.notes, ["`a.h` includes `b.h`", "`b.h` includes `a.h`"]);
+}
+
+#[test]
+fn include_without_header_name_is_an_error() {
+    let mut s = session(&[], &[]);
+    let file = s.sources.add_file("x.cpp", "#include HEADER\nint y;");
+    let out = Preprocessor::new(&mut s).run(file).unwrap();
+    assert_eq!(out.tokens.len(), 3);
+    assert_eq!(out.tokens[0].token, Token::Identifier("int".into()));
+    assert_eq!(s.diagnostics.iter().next().unwrap().code.as_deref(), Some("E0103"));
+}
+
+#[test]
+fn driver_preprocesses_with_include_dirs() {
+    let options = Options {
+        inputs: vec!["tests/data/pp_main.cpp".into()],
+        include_dirs: vec!["tests/data/include".into()],
+        ..Options::default()
+    };
+    let mut driver = Driver::new(options);
+    let files = driver.preprocess().unwrap();
+    assert_eq!(files[0].output.files.len(), 3);
+    assert!(!driver.session().diagnostics.has_errors());
+}
+
+#[test]
+fn compile_reports_missing_headers() {
+    let mut cmd = Command::cargo_bin("ruscom").expect("binary not built");
+    cmd.args(["compile", "tests/data/pp_main.cpp"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("error[E0101]: `util.h` file not found"));
+    let mut cmd = Command::cargo_bin("ruscom").expect("binary not built");
+    cmd.args(["compile", "tests/data/pp_main.cpp", "-I", "tests/data/include"]).assert().success();
+}
+
+fn preprocess(src: &str) -> (Session, Vec<String>) {
+    let mut s = session(&[], &[]);
+    let file = s.sources.add_file("t.cpp", src);
+    let out = Preprocessor::new(&mut s).run(file).unwrap();
+    let texts = texts(&s, &out.tokens);
+    eprintln!("{:?}", texts);
+    (s, texts)
+}
+
+#[test]
+fn conditionals_select_groups() {
+    let (s, texts) = preprocess(concat!(
+        "#define LEVEL 2\n",
+        "#if LEVEL > 1 && defined(LEVEL)\n a\n#elif 1\n b\n#else\n c\n#endif\n",
+        "#ifdef MISSING\n d\n#elif LEVEL == 2\n e\n#endif\n",
+        "#ifndef MISSING\n f\n#else\n g\n#endif\n",
+        "#if 0\n #if 1\n h\n #endif\n#else\n i\n#endif\n",
+    ));
+    assert_eq!(texts, ["a", "e", "f", "i"]);
+    assert!(s.diagnostics.is_empty());
+}
+
+#[test]
+fn constant_expressions() {
+    let (s, texts) = preprocess(concat!(
+        "#define CPP 201703L\n#define ALIAS CPP\n",
+        "#if ALIAS >= 201402L\n a\n#endif\n",
+        "#if (1 << 4) == 0x10 && 017 == 15 && 0b101 == 5 && -1 < 0\n b\n#endif\n",
+        "#if UNDEFINED_NAME || !true\n c\n#endif\n",
+        "#if 2 * 3 + 1 == 7 ? 'a' == 97 : 0\n d\n#endif\n",
+        "#if 0 && 1 / 0\n e\n#endif\n",
+        "#if 1'000 % 7 == 6 && ~0 == -1 && 8 >> 1 == 4\n f\n#endif\n",
+        // An unsigned operand makes the other one unsigned too.
+        "#if -1 > 0u && 0xFFFFFFFFFFFFFFFF > 0 && -2 / 2u > 1 && (0 ? 1u : -1) > 0 && -1 % 10u == 5\n g\n#endif\n",
+        "#if -1 >> 1 < 0 && 0xFFFFFFFFFFFFFFFF >> 63 == 1 && (-1 < 0u) == 0 && -1 < 0 && !(1 ? -1 : 0u) == 0\n h\n#endif\n",
+    ));
+    assert_eq!(texts, ["a", "b", "d", "f", "g", "h"]);
+    assert!(s.diagnostics.is_empty(), "{:?}", s.diagnostics.iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn skipped_groups_are_not_lexed() {
+    let (s, texts) = preprocess("#if 0\nit's \"unterminated\n#error not reached\n#endif\nok");
+    assert_eq!(texts, ["ok"]);
+    assert!(s.diagnostics.is_empty());
+}
+
+#[test]
+fn conditional_errors() {
+    let (s, texts) = preprocess("#endif\n#if 1 +\n#endif\n#if 1\n#else\n#else\n#endif\n#if 1 / 0\n#endif\n#ifdef\n#endif\n#if 1\nx");
+    assert_eq!(texts, ["x"]);
+    let messages: Vec<_> = s.diagnostics.iter().map(|d| (d.code.clone().unwrap(), d.message.clone())).collect();
+    eprintln!("{:#?}", messages);
+    assert_eq!(messages.len(), 6);
+    assert_eq!(messages[0].1, "#endif without #if");
+    assert_eq!(messages[1].0, "E0107");
+    assert_eq!(messages[2].1, "#else after #else");
+    assert_eq!(messages[3].1, "division by zero in preprocessor expression");
+    assert_eq!(messages[4].1, "macro name missing");
+    assert_eq!(messages[5].1, "unterminated conditional directive");
+}
+
+#[test]
+fn include_guards_and_pragma_once() {
+    let mut s = session(&[
+        ("main.cpp", "#include \"a.h\"\n#include \"a.h\"\n#include \"once.h\"\n#include \"once.h\"\n"),
+        ("a.h", "#ifndef A_H\n#define A_H\n#include \"b.h\"\nint a;\n#endif\n"),
+        ("b.h", "#ifndef B_H\n#define B_H\n#include \"a.h\"\nint b;\n#endif\n"),
+        ("once.h", "#pragma once\nint once;\n"),
+    ], &[]);
+    let main = s.sources.load(s.fs.clone().as_ref(), "main.cpp".as_ref()).unwrap();
+    let out = Preprocessor::new(&mut s).run(main).unwrap();
+    assert_eq!(texts(&s, &out.tokens), ["int", "b", ";", "int", "a", ";", "int", "once", ";"]);
+    assert!(s.diagnostics.is_empty(), "{:?}", s.diagnostics.iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn define_and_undef() {
+    let (s, texts) = preprocess(concat!(
+        "#define A 1\n#define A 1\n#undef A\n#ifdef A\n x\n#endif\n",
+        "#define B 1\n#define B 2\n#define F(a, b, ...) a\n#if defined F\n y\n#endif\n",
+        "#error stop here\n#bogus\n",
+    ));
+    assert_eq!(texts, ["y"]);
+    let messages: Vec<_> = s.diagnostics.iter().map(|d| d.message.clone()).collect();
+    assert_eq!(messages, ["`B` macro redefined", "#error stop here", "invalid preprocessing directive `#bogus`"]);
+}
+
+fn spellings(tokens: &[ruscom::lexer::token::SpannedToken]) -> Vec<String> {
+    tokens.iter().map(|t| t.token.spelling()).collect()
+}
+
+fn expand(src: &str) -> (Session, Vec<String>) {
+    let mut s = session(&[], &[]);
+    let file = s.sources.add_file("dir/t.cpp", src);
+    let out = Preprocessor::new(&mut s).run(file).unwrap();
+    let spelled = spellings(&out.tokens);
+    eprintln!("{:?}", spelled);
+    (s, spelled)
+}
+
+#[test]
+fn object_and_function_like_macros() {
+    let (s, out) = expand(concat!(
+        "#define N 4\n#define SQ(x) ((x) * (x))\n#define MAX(a, b) ((a) > (b) ? (a) : (b))\n",
+        "int a = SQ(N + 1);\nint b = MAX(SQ(2), N);\nSQ;\n",
+    ));
+    assert_eq!(out.join(" "), concat!(
+        "int a = ( ( 4 + 1 ) * ( 4 + 1 ) ) ; ",
+        "int b = ( ( ( ( 2 ) * ( 2 ) ) ) > ( 4 ) ? ( ( ( 2 ) * ( 2 ) ) ) : ( 4 ) ) ; SQ ;",
+    ));
+    assert!(s.diagnostics.is_empty());
+}
+
+#[test]
+fn stringify_paste_and_varargs() {
+    let (s, out) = expand(concat!(
+        "#define STR(x) #x\n#define CAT(a, b) a ## b\n#define LOG(fmt, ...) printf(fmt, __VA_ARGS__)\n",
+        "STR(a  +  \"q\")\nCAT(foo, bar) CAT(, x) CAT(1, 2)\nLOG(\"%d %d\", 1, 2)\n",
+    ));
+    assert_eq!(out, [
+        "\"a + \\\"q\\\"\"", "foobar", "x", "12",
+        "printf", "(", "\"%d %d\"", ",", "1", ",", "2", ")",
+    ]);
+    assert!(s.diagnostics.is_empty());
+}
+
+#[test]
+fn stringizing_escapes_only_double_quotes_and_backslashes() {
+    let (s, out) = expand("#define STR(x) #x\nSTR('a' \"b\\n\")\n");
+    assert_eq!(out, ["\"'a' \\\"b\\\\n\\\"\""]);
+    assert!(s.diagnostics.is_empty());
+}
+
+#[test]
+fn gnu_comma_paste_drops_the_comma_before_empty_variable_arguments() {
+    let (s, out) = expand(concat!(
+        "#define LOG(fmt, ...) printf(fmt, ## __VA_ARGS__)\n",
+        "LOG(\"a\") LOG(\"b\", 1, 2)\n",
+    ));
+    assert_eq!(out, ["printf", "(", "\"a\"", ")", "printf", "(", "\"b\"", ",", "1", ",", "2", ")"]);
+    assert!(s.diagnostics.is_empty());
+}
+
+#[test]
+fn va_opt_keeps_its_tokens_only_with_variable_arguments() {
+    let (s, out) = expand(concat!(
+        "#define F(a, ...) f(a __VA_OPT__(,) __VA_ARGS__)\n#define G(x, ...) x ## __VA_OPT__(y) z\n",
+        "F(1) F(1, 2) G(p) G(p, q)\n",
+    ));
+    assert_eq!(out.join(" "), "f ( 1 ) f ( 1 , 2 ) p z py z");
+    assert!(s.diagnostics.is_empty());
+}
+
+#[test]
+fn pasted_hashes_are_not_directives() {
+    // The example in [cpp.concat].
+    let (s, out) = expand(concat!(
+        "#define hash_hash # ## #\n#define mkstr(a) # a\n#define in_between(a) mkstr(a)\n#define join(c, d) in_between(c hash_hash d)\n",
+        "char p[] = join(x, y);\n",
+    ));
+    assert_eq!(out, ["char", "p", "[", "]", "=", "\"x ## y\"", ";"]);
+    assert!(s.diagnostics.is_empty());
+}
+
+#[test]
+fn recursion_is_blocked_and_rescans_see_following_text() {
+    let (_, out) = expand(concat!(
+        "#define foo foo + 1\n#define a b\n#define b a\n#define F(x) G\n#define G(y) y * 2\n",
+        "foo; a; F(0)(3);\n",
+        "#define PAIR(x, y) x y\nPAIR(\nmulti,\nline)\n",
+    ));
+    assert_eq!(out.join(" "), "foo + 1 ; a ; 3 * 2 ; multi line");
+}
+
+#[test]
+fn macro_expansions_are_counted() {
+    let mut s = Session::new(Options { macro_stats: true, ..Options::default() });
+    let file = s.sources.add_file("t.cpp", concat!(
+        "#define INNER(x) ((x) + 1)\n#define MIDDLE(x) INNER(x) * INNER(x)\n#define OUTER(x) MIDDLE(x)\n#define ZERO 0\n",
+        "int a = OUTER(ZERO);\nint b = INNER(2) + __LINE__;\n#if INNER(0)\n#endif\n",
+    ));
+    let stats = Preprocessor::new(&mut s).run(file).unwrap().macro_stats.unwrap();
+    let macros: Vec<_> = stats.by_tokens().into_iter().map(|(name, m)| (name, m.expansions, m.tokens)).collect();
+    assert_eq!(macros, [("INNER", 4, 28), ("MIDDLE", 1, 9), ("OUTER", 1, 4), ("ZERO", 1, 1), ("__LINE__", 1, 1)]);
+    assert_eq!((stats.expansions(), stats.tokens()), (8, 43));
+    // `ZERO` is expanded as `OUTER`'s argument, outside its chain.
+    let chains: Vec<_> = stats.deepest_chains().into_iter().map(|(chain, span)| (chain.join(" -> "), s.sources.location(span.start).unwrap().to_string())).collect();
+    assert_eq!(chains, [("OUTER -> MIDDLE -> INNER".to_string(), "t.cpp:5:9".to_string())]);
+
+    let out = Command::cargo_bin("ruscom").unwrap()
+        .args(["stats", "macros", "tests/data/macros.cpp", "tests/data/pp_main.cpp", "-I", "tests/data/include", "--top", "1"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(out.get_output().stdout.clone()).unwrap();
+    eprintln!("{}", stdout);
+    assert_eq!(stdout, concat!(
+        "tests/data/macros.cpp: 5 expansions of 3 macros producing 34 tokens\n",
+        "  expansions   tokens  macro\n",
+        "           3       21  INNER\n",
+        "  deepest expansion chains:\n",
+        "           3  OUTER -> MIDDLE -> INNER at tests/data/macros.cpp:4:9\n",
+        "tests/data/pp_main.cpp: 0 expansions of 0 macros producing 0 tokens\n",
+    ));
+}
+
+#[test]
+fn macro_errors() {
+    let (s, out) = expand("#define F(a, b) a\nF(1)\nF(1, 2, 3)\n#define P(a, b) a ## b\nP(+, /)\nF(1,\n#define X\n");
+    eprintln!("{:?}", s.diagnostics.iter().map(|d| &d.message).collect::<Vec<_>>());
+    let codes: Vec<_> = s.diagnostics.iter().map(|d| d.code.clone().unwrap()).collect();
+    assert_eq!(codes, ["E0112", "E0112", "E0113", "E0111"]);
+    assert_eq!(s.diagnostics.iter().next().unwrap().message, "macro `F` requires 2 arguments, but 1 given");
+    assert_eq!(out, ["+", "/"]);
+}
+
+#[test]
+fn predefined_macros() {
+    std::env::set_var("SOURCE_DATE_EPOCH", "1791364800");
+    let (_, out) = expand(concat!(
+        "__FILE__ __LINE__\n__LINE__ __DATE__ __TIME__ __COUNTER__ __COUNTER__\n",
+        "#if __cplusplus >= 201703L && __STDC_HOSTED__ && defined(__FILE__)\nok\n#endif\n",
+    ));
+    assert_eq!(out, ["\"dir/t.cpp\"", "1", "2", "\"Oct  7 2026\"", "\"09:20:00\"", "0", "1", "ok"]);
+}
+
+#[test]
+fn line_directives_renumber_lines() {
+    let (s, out) = expand(concat!(
+        "__LINE__\n#line 100 \"foo.cpp\"\n__LINE__ __FILE__\n\n__LINE__\n",
+        "#define L 7\n#line L\n__LINE__ __FILE__\n#line 1 \"a\" \"b\"\n#line 0\n#line x\n",
+    ));
+    assert_eq!(out, ["1", "100", "\"foo.cpp\"", "102", "7", "\"foo.cpp\""]);
+    let messages: Vec<_> = s.diagnostics.iter().map(|d| (d.code.clone().unwrap(), d.message.clone())).collect();
+    assert_eq!(messages, [
+        ("E0115".to_string(), "extra tokens after the file name".to_string()),
+        ("E0115".into(), "#line directive requires a positive integer argument".into()),
+        ("E0115".into(), "#line directive requires a positive integer argument".into()),
+    ]);
+    // Diagnostics report the renumbered location too.
+    let first = s.diagnostics.iter().next().unwrap();
+    assert_eq!(s.sources.presumed_location(first.primary_span().unwrap().start).unwrap().to_string(), "foo.cpp:8:13");
+}
+
+#[test]
+fn command_line_defines() {
+    let mut s = session(&[], &[]);
+    s.options.defines = vec!["DEBUG".into(), "LEVEL=3".into(), "GONE".into(), "TWICE(x)=x x".into()];
+    s.options.undefines = vec!["GONE".into()];
+    let file = s.sources.add_file("t.cpp", "DEBUG LEVEL GONE TWICE(y)\n#include MISSING\n");
+    let out = Preprocessor::new(&mut s).run(file).unwrap();
+    assert_eq!(spellings(&out.tokens), ["1", "3", "GONE", "y", "y"]);
+}
+
+#[test]
+fn target_cpu_defines_feature_macros() {
+    let options = Options { march: Some("x86-64-v3".into()), mcpu: Some("x86-64".into()), ..Options::default() };
+    let mut s = Session::with_target(options, Target { arch: "x86_64".into(), os: "linux".into() });
+    let file = s.sources.add_file("t.cpp", "__SSE4_2__ __AVX2__ __AVX512F__\n");
+    let out = Preprocessor::new(&mut s).run(file).unwrap();
+    assert_eq!(spellings(&out.tokens), ["1", "1", "__AVX512F__"]);
+    Command::cargo_bin("ruscom").unwrap()
+        .args(["preprocess", "tests/data/pp_main.cpp", "-I", "tests/data/include", "-march=no-such-cpu"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("unknown target CPU `no-such-cpu`"));
+}
+
+#[test]
+fn freestanding_and_nostdinc() {
+    let options = Options { freestanding: true, nostdinc: true, ..Options::default() };
+    let mut s = Session::new(options);
+    let file = s.sources.add_file("t.cpp", "__STDC_HOSTED__\n#include <cstddef>\n");
+    let out = Preprocessor::new(&mut s).run(file).unwrap();
+    let messages: Vec<_> = s.diagnostics.iter().map(|d| d.message.clone()).collect();
+    eprintln!("{:?}", messages);
+    assert_eq!(spellings(&out.tokens), ["0"]);
+    assert!(messages[0].contains("cstddef"), "{:?}", messages);
+    // Built-in headers are found without -nostdinc, and freestanding code may use them.
+    let mut s = Session::new(Options { freestanding: true, ..Options::default() });
+    let file = s.sources.add_file("t.cpp", "#include <cstddef>\n");
+    Preprocessor::new(&mut s).run(file).unwrap();
+    assert!(s.diagnostics.is_empty());
+    Command::cargo_bin("ruscom").unwrap()
+        .args(["preprocess", "tests/data/pp_main.cpp", "-I", "tests/data/include", "-ffreestanding", "-nostdinc"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn computed_includes() {
+    let mut s = session(&[("main.cpp", "#define H \"h.h\"\n#define SYS <sys.h>\n#include H\n#include SYS\n"), ("h.h", "h"), ("inc/sys.h", "sys")], &["inc"]);
+    let main = s.sources.load(s.fs.clone().as_ref(), "main.cpp".as_ref()).unwrap();
+    let out = Preprocessor::new(&mut s).run(main).unwrap();
+    assert_eq!(spellings(&out.tokens), ["h", "sys"]);
+    assert!(s.diagnostics.is_empty());
+}
+
+#[test]
+fn compile_accepts_defines() {
+    let mut cmd = Command::cargo_bin("ruscom").expect("binary not built");
+    cmd.args(["compile", "tests/data/pp_main.cpp", "-I", "tests/data/include", "-D", "FAIL=1"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn printed_output_keeps_lines_and_marks_includes() {
+    let mut s = session(&[
+        ("main.cpp", "#include \"a.h\"\n#define ADD(x, y) x+y\n  int v = ADD(1, 2);\n"),
+        ("a.h", "int a;\n"),
+    ], &[]);
+    let main = s.sources.load(s.fs.clone().as_ref(), "main.cpp".as_ref()).unwrap();
+    let out = Preprocessor::new(&mut s).run(main).unwrap();
+    let text = print::print(&out, &s.sources, true);
+    eprintln!("{}", text);
+    assert_eq!(text, "# 1 \"main.cpp\"\n# 1 \"a.h\" 1\nint a;\n# 3 \"main.cpp\" 2\n  int v = 1 + 2 ;\n");
+    let plain = print::print(&out, &s.sources, false);
+    assert_eq!(plain, "int a;\n  int v = 1 + 2 ;\n");
+}
+
+#[test]
+fn preprocess_subcommand_prints_source() {
+    let mut cmd = Command::cargo_bin("ruscom").expect("binary not built");
+    cmd.args(["preprocess", "-P", "tests/data/pp_main.cpp", "-I", "tests/data/include"])
+        .assert()
+        .success()
+        .stdout("int helper();\ntypedef int config_t;\nint main() { return helper(); }\n");
+    let out = std::env::temp_dir().join(format!("ruscom_pp_{}.ii", std::process::id()));
+    let mut cmd = Command::cargo_bin("ruscom").expect("binary not built");
+    cmd.args(["preprocess", "tests/data/pp_main.cpp", "-I", "tests/data/include", "-DUNUSED", "-o"]).arg(&out).assert().success();
+    let text = std::fs::read_to_string(&out).unwrap();
+    std::fs::remove_file(&out).ok();
+    assert!(text.starts_with("# 1 \"tests/data/pp_main.cpp\"\n"));
+    assert!(text.contains("# 2 \"tests/data/pp_main.cpp\" 2\n"));
+}
+
+#[test]
+fn preprocessed_output_checks_with_original_locations() {
+    let dir = std::env::temp_dir().join(format!("ruscom_pp_roundtrip_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("bad.h"), "int ok;\n\n\nint bad() { int x = \"s\"; return x; }\n").unwrap();
+    std::fs::write(dir.join("main.cpp"), "#include \"bad.h\"\nint main() { return ok; }\n").unwrap();
+    let mut cmd = Command::cargo_bin("ruscom").expect("binary not built");
+    cmd.current_dir(&dir).args(["preprocess", "main.cpp", "-o", "main.ii"]).assert().success();
+    let mut cmd = Command::cargo_bin("ruscom").expect("binary not built");
+    let assert = cmd.current_dir(&dir).args(["check", "main.ii"]).assert().failure();
+    std::fs::remove_dir_all(&dir).ok();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).into_owned();
+    assert!(stderr.contains("--> bad.h:4:21"), "{}", stderr);
+    assert!(!stderr.contains("E0115") && !stderr.contains("main.ii:"), "{}", stderr);
+}
+
+#[test]
+fn builtin_headers_are_found_last() {
+    let (s, all) = preprocess("#include <cstdint>\n#include <cstddef>\nstd::int64_t m = INT32_MAX; std::size_t n;");
+    assert!(s.diagnostics.is_empty());
+    assert!(all.ends_with(&["std", ":", ":", "int64_t", "m", "=", "INT32_MAX", ";", "std", ":", ":", "size_t", "n", ";"].map(String::from)));
+    let paths: Vec<_> = s.sources.files().iter().map(|f| f.path.display().to_string()).collect();
+    eprintln!("{:?}", paths);
+    assert!(paths.contains(&"<ruscom>/stdint.h".to_string()));
+    // A header of the same name on the search path wins.
+    let mut s = session(&[("main.cpp", "#include <cstdint>\n"), ("inc/cstdint", "int mine;")], &["inc"]);
+    let main = s.sources.load(s.fs.clone().as_ref(), "main.cpp".as_ref()).unwrap();
+    let out = Preprocessor::new(&mut s).run(main).unwrap();
+    assert_eq!(texts(&s, &out.tokens), ["int", "mine", ";"]);
+}
+
+#[test]
+fn system_dirs_come_after_user_dirs() {
+    let mut fs = MemoryFileSystem::new();
+    fs.insert("main.cpp", "#include <h.h>\n#include <only_sys.h>\n");
+    fs.insert("user/h.h", "int user;");
+    fs.insert("sys/h.h", "int sys;");
+    fs.insert("sys/only_sys.h", "int only;");
+    let options = Options { include_dirs: vec!["user".into()], system_include_dirs: vec!["sys".into()], ..Options::default() };
+    let mut s = Session::new(options).with_file_system(Arc::new(fs));
+    let main = s.sources.load(s.fs.clone().as_ref(), "main.cpp".as_ref()).unwrap();
+    let out = Preprocessor::new(&mut s).run(main).unwrap();
+    assert_eq!(texts(&s, &out.tokens), ["int", "user", ";", "int", "only", ";"]);
+    assert!(s.is_system_header("sys/only_sys.h".as_ref()));
+    assert!(s.is_system_header("<ruscom>/cstddef".as_ref()));
+    assert!(!s.is_system_header("user/h.h".as_ref()));
+}
+
+#[test]
+fn isystem_and_include_path_variable() {
+    let mut cmd = Command::cargo_bin("ruscom").expect("binary not built");
+    cmd.args(["preprocess", "-P", "tests/data/pp_main.cpp", "-isystem", "tests/data/include"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("int helper();"));
+    let mut cmd = Command::cargo_bin("ruscom").expect("binary not built");
+    cmd.env("RUSCOM_INCLUDE_PATH", "tests/data/include")
+        .args(["preprocess", "-P", "tests/data/pp_main.cpp"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("typedef int config_t;"));
+}
+
+#[test]
+fn embed_expands_to_one_token_per_file() {
+    let mut fs = MemoryFileSystem::new();
+    fs.insert("main.cpp", "\
+        #define N 2\n\
+        {\n#embed \"blob.bin\"\n}\n\
+        {\n#embed <blob.bin> limit(N + 1) prefix(0,) suffix(, 9)\n}\n\
+        {\n#embed \"empty.bin\" __if_empty__(-1) prefix(nope)\n}\n\
+        {\n#embed <big.bin>\n}\n");
+    fs.insert("blob.bin", vec![1u8, 2, 255, 0]);
+    fs.insert("empty.bin", Vec::new());
+    fs.insert("assets/blob.bin", vec![7u8, 8, 9, 10]);
+    fs.insert("assets/big.bin", vec![42u8; 1 << 20]);
+    let options = Options { embed_dirs: vec!["assets".into()], ..Options::default() };
+    let mut s = Session::new(options).with_file_system(Arc::new(fs));
+    let main = s.sources.load(s.fs.clone().as_ref(), "main.cpp".as_ref()).unwrap();
+    let out = Preprocessor::new(&mut s).run(main).unwrap();
+    assert!(s.diagnostics.is_empty(), "{:?}", s.diagnostics.iter().map(|d| d.message.clone()).collect::<Vec<_>>());
+    let spelled = spellings(&out.tokens[..out.tokens.len() - 3]);
+    eprintln!("{:?}", spelled);
+    assert_eq!(spelled, ["{", "1,2,255,0", "}", "{", "0", ",", "7,8,9", ",", "9", "}", "{", "-", "1", "}"]);
+    // A megabyte is still one token.
+    assert!(matches!(&out.tokens[out.tokens.len() - 2].token, Token::Embed(bytes) if bytes.len() == 1 << 20));
+    assert_eq!(out.embedded, [std::path::PathBuf::from("blob.bin"), "assets/blob.bin".into(), "empty.bin".into(), "assets/big.bin".into()]);
+
+    for (line, code) in [("#embed \"missing.bin\"", "E0101"), ("#embed", "E0103"), ("#embed \"blob.bin\" offset(1)", "E0114"), ("#embed \"blob.bin\" limit", "E0114"), ("#embed \"blob.bin\" limit(-1)", "E0107")] {
+        let mut s = session(&[("blob.bin", "ab")], &[]);
+        let file = s.sources.add_file("t.cpp", format!("{}\n", line));
+        Preprocessor::new(&mut s).run(file).unwrap();
+        let codes: Vec<_> = s.diagnostics.iter().filter_map(|d| d.code.clone()).collect();
+        eprintln!("{} -> {:?}", line, codes);
+        assert_eq!(codes, [code], "{}", line);
+    }
+}
@@ -0,0 +1,88 @@
+use assert_cmd::Command;
+use ruscom::profile::Profiler;
+use std::fs;
+
+#[test]
+fn profiler_emits_chrome_trace_events() {
+    let mut p = Profiler::new();
+    let v = p.time("lex \"main\"", "phase", || 7);
+    assert_eq!(v, 7);
+    let json = p.to_chrome_json();
+    eprintln!("trace: {}", json);
+    assert!(json.starts_with("{\"traceEvents\":["));
+    assert!(json.contains("\"name\":\"lex \\\"main\\\"\""));
+    assert!(json.contains("\"ph\":\"X\""));
+    assert_eq!(p.events().len(), 1);
+    p.count("arena", &[("allocated", 10), ("reserved", 64)]);
+    let json = p.to_chrome_json();
+    assert!(json.contains("},{\"name\":\"arena\",\"ph\":\"C\","));
+    assert!(json.contains("\"args\":{\"allocated\":10,\"reserved\":64}}"));
+}
+
+#[test]
+fn profile_out_flag_writes_trace() {
+    let out = std::env::temp_dir().join(format!("ruscom-profile-{}.json", std::process::id()));
+    let mut cmd = Command::cargo_bin("ruscom").expect("binary not built");
+    cmd.arg("lex").arg("--count").arg("tests/data/sample1.cpp")
+        .arg("--profile-out").arg(&out)
+        .assert().success();
+    let json = fs::read_to_string(&out).expect("profile not written");
+    let _ = fs::remove_file(&out);
+    assert!(json.contains("\"name\":\"lex\""));
+    assert!(json.contains("\"name\":\"read tests/data/sample1.cpp\""));
+}
+
+#[test]
+fn profile_times_every_phase() {
+    let out = std::env::temp_dir().join(format!("ruscom-profile-phases-{}.json", std::process::id()));
+    let mut cmd = Command::cargo_bin("ruscom").expect("binary not built");
+    cmd.arg("check").arg("tests/data/sample1.cpp")
+        .arg("--profile-out").arg(&out)
+        .assert().success();
+    let json = fs::read_to_string(&out).expect("profile not written");
+    let _ = fs::remove_file(&out);
+    eprintln!("trace: {}", json);
+    for phase in ["read", "preprocess", "parse", "sema"] {
+        assert!(json.contains(&format!("\"name\":\"{} tests/data/sample1.cpp\"", phase)), "no `{}` event", phase);
+    }
+    assert!(json.contains("\"name\":\"syntax tree arena\",\"ph\":\"C\""));
+    assert!(json.contains("\"args\":{\"allocated\":"));
+}
+
+#[test]
+fn profile_times_headers_functions_and_workers() {
+    let out = std::env::temp_dir().join(format!("ruscom-profile-spans-{}.json", std::process::id()));
+    Command::cargo_bin("ruscom").expect("binary not built")
+        .args(["check", "tests/data/pp_main.cpp", "-I", "tests/data/include"])
+        .arg("--profile-out").arg(&out)
+        .assert().success();
+    let json = fs::read_to_string(&out).expect("profile not written");
+    eprintln!("trace: {}", json);
+    // Nested headers are regions inside the one of the header including them.
+    for name in ["include tests/data/include/util.h", "include tests/data/include/sys/config.h", "sema main"] {
+        assert!(json.contains(&format!("\"name\":\"{}\"", name)), "no `{}` event", name);
+    }
+    let event = |name: &str| -> (u64, u64) {
+        let at = json.find(&format!("\"name\":\"{}\"", name)).unwrap();
+        let field = |key: &str| json[at..].split(key).nth(1).unwrap().split(|c: char| !c.is_ascii_digit()).next().unwrap().parse().unwrap();
+        (field("\"ts\":"), field("\"dur\":"))
+    };
+    let (util, config) = (event("include tests/data/include/util.h"), event("include tests/data/include/sys/config.h"));
+    assert!(util.0 <= config.0 && config.0 + config.1 <= util.0 + util.1, "{:?} {:?}", util, config);
+
+    Command::cargo_bin("ruscom").expect("binary not built")
+        .args(["check", "-j", "2", "tests/data/jobs/a.cpp", "tests/data/jobs/d.cpp"])
+        .arg("--profile-out").arg(&out)
+        .assert();
+    let json = fs::read_to_string(&out).expect("profile not written");
+    let _ = fs::remove_file(&out);
+    eprintln!("trace: {}", json);
+    // Each worker's events are on its own thread, named.
+    for input in ["a", "d"] {
+        let at = json.find(&format!("\"name\":\"preprocess tests/data/jobs/{}.cpp\"", input)).expect("no worker event");
+        assert!(!json[at..].split('}').next().unwrap().contains("\"tid\":1"));
+    }
+    assert!(json.contains("\"name\":\"include tests/data/jobs/common.h\""));
+    assert!(json.contains("\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":1,\"tid\":2,\"args\":{\"name\":\"worker 1\"}"));
+    assert!(json.contains("\"name\":\"preprocess\",\"cat\":\"phase\",\"ph\":\"X\""));
+}
@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use assert_cmd::Command;
+use predicates::str::contains;
+use ruscom::arena::TuArena;
+use ruscom::preprocessor::Preprocessor;
+use ruscom::parser::Parser;
+use ruscom::query::{self, Matcher, QueryError};
+use ruscom::session::{Options, Session};
+use ruscom::vfs::MemoryFileSystem;
+
+const SOURCE: &str = r#"void free(void* p);
+namespace shapes {
+struct Box {
+    int width;
+    void resize(int w, int h);
+private:
+    static int count;
+};
+}
+int area(int w, int h) { return w * h; }
+int main() {
+    int* p = (int*)0;
+    if (area(1, 2) == 2) free(p);
+    return area(0, 3);
+}
+"#;
+
+/// `kind name` of every node `query` matches in `SOURCE`.
+fn run(query: &str) -> Vec<String> {
+    let mut fs = MemoryFileSystem::new();
+    fs.insert("main.cpp", SOURCE);
+    let mut s = Session::new(Options::default()).with_file_system(Arc::new(fs));
+    let main = s.sources.load(s.fs.clone().as_ref(), "main.cpp".as_ref()).unwrap();
+    let out = Preprocessor::new(&mut s).run(main).unwrap();
+    let arena = TuArena::new();
+    let tu = Parser::new(&mut s, &arena, out.tokens).parse().unwrap();
+    let matcher = Matcher::parse(query).unwrap();
+    let found: Vec<String> = query::find(&tu, &matcher).iter().map(|m| m.to_string()).collect();
+    eprintln!("{}: {:?}", query, found);
+    found
+}
+
+#[test]
+fn node_matchers_match_dump_kinds() {
+    assert_eq!(run("functionDecl()"), ["FunctionDecl free", "CXXMethodDecl resize", "FunctionDecl area", "FunctionDecl main"]);
+    assert_eq!(run("cxxMethodDecl()"), ["CXXMethodDecl resize"]);
+    assert_eq!(run("fieldDecl()"), ["FieldDecl width"]);
+    assert_eq!(run("parmVarDecl(hasName(\"w\"))"), ["ParamDecl w", "ParamDecl w"]);
+    assert_eq!(run("cStyleCastExpr()"), ["CStyleCastExpr"]);
+    assert_eq!(run("decl(hasName(\"Box\"))"), ["CXXRecordDecl Box"]);
+    assert_eq!(run("stmt(hasDescendant(integerLiteral(equals(3))))").len(), 3);
+}
+
+#[test]
+fn narrowing_matchers() {
+    assert_eq!(run("m functionDecl(hasName(\"main\"))"), ["FunctionDecl main"]);
+    assert_eq!(run("functionDecl(isDefinition(), parameterCountIs(2))"), ["FunctionDecl area"]);
+    assert_eq!(run("varDecl(isPrivate())"), ["VarDecl count"]);
+    assert_eq!(run("varDecl(hasType(\"int*\"))"), ["VarDecl p"]);
+    assert_eq!(run("decl(hasName(\"Box::width\"))"), ["FieldDecl width"]);
+    assert_eq!(run("decl(hasName(\"::shapes::Box::count\"))"), ["VarDecl count"]);
+    assert!(run("decl(hasName(\"::Box::count\"))").is_empty());
+    assert_eq!(run("binaryOperator(hasOperatorName(\"*\"), hasLHS(declRefExpr(hasName(\"w\"))))"), ["BinaryOperator"]);
+}
+
+#[test]
+fn traversal_matchers() {
+    assert_eq!(run("callExpr(callee(declRefExpr(hasName(\"area\"))), hasArgument(0, integerLiteral(equals(0))))").len(), 1);
+    assert_eq!(run("callExpr(argumentCountIs(1), hasAncestor(ifStmt()))").len(), 1);
+    assert_eq!(run("callExpr(unless(hasAncestor(ifStmt())))").len(), 1);
+    assert_eq!(run("ifStmt(hasCondition(binaryOperator(hasOperatorName(\"==\"))))"), ["IfStmt"]);
+    assert_eq!(run("declRefExpr(hasParent(callExpr()), anyOf(hasName(\"free\"), hasName(\"p\")))"), ["DeclRefExpr free", "DeclRefExpr p"]);
+    assert_eq!(run("cxxRecordDecl(has(fieldDecl()), hasAncestor(namespaceDecl(hasName(\"shapes\"))))"), ["CXXRecordDecl Box"]);
+}
+
+#[test]
+fn bad_queries_are_rejected() {
+    let cases = [
+        ("fooDecl()", QueryError::UnknownMatcher("fooDecl".into())),
+        ("has(1)", QueryError::Arguments { matcher: "has".into(), expected: "a matcher" }),
+        ("isDefinition(decl())", QueryError::Arguments { matcher: "isDefinition".into(), expected: "no arguments" }),
+        ("callExpr(", QueryError::Syntax { offset: 9, expected: "a matcher" }),
+        ("decl(hasName(\"x))", QueryError::Syntax { offset: 17, expected: "`\"`" }),
+        ("decl() decl()", QueryError::Syntax { offset: 7, expected: "the end of the query" }),
+    ];
+    for (query, expected) in cases {
+        let err = Matcher::parse(query).unwrap_err();
+        eprintln!("{}: {}", query, err);
+        assert_eq!(err, expected);
+    }
+}
+
+#[test]
+fn cli_prints_matches_with_locations() {
+    let dir = std::env::temp_dir().join(format!("ruscom_ast_query_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let src = dir.join("a.cpp");
+    std::fs::write(&src, SOURCE).unwrap();
+    let assert = Command::cargo_bin("ruscom").unwrap()
+        .args(["ast-query", "functionDecl(hasName(\"main\"))"]).arg(&src)
+        .assert().success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    eprintln!("{}", stdout);
+    assert!(stdout.starts_with("Match #1:\n\nnote: FunctionDecl main\n"));
+    assert!(stdout.contains("a.cpp:11:1\n"));
+    assert!(stdout.contains("11 | int main() {\n"));
+    assert!(stdout.ends_with("1 match.\n"));
+    Command::cargo_bin("ruscom").unwrap()
+        .args(["ast-query", "returnStmt()"]).arg(&src)
+        .assert().success().stdout(contains("Match #2:")).stdout(contains("2 matches."));
+    Command::cargo_bin("ruscom").unwrap()
+        .args(["ast-query", "functionDecl(hasName(main))"]).arg(&src)
+        .assert().failure().stderr(contains("expected `(` at column 26"));
+    std::fs::remove_dir_all(&dir).ok();
+}
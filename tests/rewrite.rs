@@ -0,0 +1,68 @@
+use ruscom::diagnostics::{json, Diagnostic, Label};
+use ruscom::lexer::token::Span;
+use ruscom::rewrite::{RewriteError, Rewriter};
+use ruscom::sema::includes;
+use ruscom::source::SourceManager;
+
+#[test]
+fn edits_apply_per_file_in_position_order() {
+    let mut sm = SourceManager::new();
+    let a = sm.add_file("a.cpp", "int x = 1\nint y;\n");
+    let b = sm.add_file("b.h", "void f();\n");
+    let b_start = sm.file(b).start;
+    let mut rw = Rewriter::new();
+    rw.replace(Span::new(b_start + 5, b_start + 6), "g");
+    rw.insert(10, "long ");
+    rw.remove(Span::new(10, 14));
+    rw.insert(9, ";");
+    rw.insert(9, ";");
+    let out = rw.apply(&sm).unwrap();
+    eprintln!("{:?}", out);
+    assert_eq!(out, [(a, "int x = 1;\nlong y;\n".to_string()), (b, "void g();\n".to_string())]);
+}
+
+#[test]
+fn overlapping_edits_are_rejected() {
+    let mut sm = SourceManager::new();
+    sm.add_file("a.cpp", "int x = 1;\n");
+    let mut rw = Rewriter::new();
+    rw.replace(Span::new(4, 9), "y = 2");
+    rw.replace(Span::new(8, 9), "3");
+    assert_eq!(rw.apply(&sm), Err(RewriteError::Overlap { first: Span::new(4, 9), second: Span::new(8, 9) }));
+    let mut rw = Rewriter::new();
+    rw.insert(40, "x");
+    assert_eq!(rw.files(&sm).unwrap_err(), RewriteError::OutsideFile(Span::new(40, 40)));
+}
+
+#[test]
+fn fixits_are_rewrites_and_json() {
+    let mut sm = SourceManager::new();
+    sm.add_file("a.cpp", "int f() { g(); }\n");
+    let diag = Diagnostic::warning("ignoring return value")
+        .with_label(Label::primary(Span::new(10, 13), ""))
+        .with_fixit(Span::new(10, 10), "(void)");
+    let mut rw = Rewriter::new();
+    rw.fix(&diag);
+    rw.fix(&diag);
+    assert_eq!(rw.apply(&sm).unwrap()[0].1, "int f() { (void)g(); }\n");
+    let v = json::to_json(&diag, &sm);
+    eprintln!("{}", v);
+    assert_eq!(v["fixits"][0]["replacement"], "(void)");
+    assert_eq!(v["fixits"][0]["column_start"], 11);
+    assert_eq!(v["fixits"][0]["byte_end"], 10);
+}
+
+#[test]
+fn include_edits_go_after_the_last_include() {
+    assert_eq!(includes::header_for("std::vector"), Some("vector"));
+    assert_eq!(includes::header_for("printf"), Some("cstdio"));
+    assert_eq!(includes::header_for("vector"), None);
+    let mut sm = SourceManager::new();
+    let a = sm.add_file("a.cpp", "// top\n#include <cstddef>\n#include \"a.h\"\nint x;\n");
+    let b = sm.add_file("b.cpp", "int y;\n");
+    let c = sm.add_file("c.cpp", "#include <cstddef>");
+    let (start_a, start_b, start_c) = (sm.file(a).start, sm.file(b).start, sm.file(c).start);
+    assert_eq!(includes::include_edit(sm.file(a), "vector"), (start_a + 41, "#include <vector>\n".to_string()));
+    assert_eq!(includes::include_edit(sm.file(b), "vector"), (start_b, "#include <vector>\n".to_string()));
+    assert_eq!(includes::include_edit(sm.file(c), "vector"), (start_c + 18, "\n#include <vector>".to_string()));
+}
@@ -0,0 +1,1290 @@
+mod common;
+
+use std::collections::HashMap;
+
+use ruscom::ast::{self, Decl};
+use ruscom::diagnostics::{Warning, WarningOption};
+use ruscom::sema;
+use ruscom::sema::layout::{LayoutError, Layouts};
+use ruscom::sema::range_for::{self, Ends};
+use ruscom::intern::Symbol;
+use ruscom::session::Target;
+use ruscom::symbols::{self, Visibility};
+use ruscom::types::{Type, TypeId};
+use common::parse_valid as parse;
+
+/// Parses `source` and runs the semantic checks; returns the messages.
+fn check(source: &str) -> Vec<String> {
+    let (mut s, tu) = parse(source);
+    sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+    s.diagnostics.iter().map(|d| d.message.clone()).collect()
+}
+
+#[test]
+fn unsequenced_modifications_are_reported() {
+    let cases = [
+        ("int v = i++ + i++;", "multiple unsequenced modifications to `i`"),
+        ("int v = i + ++i;", "unsequenced modification and access to `i`"),
+        ("int v = (i = 1) * i;", "unsequenced modification and access to `i`"),
+        ("int v = f(i++) == i;", "unsequenced modification and access to `i`"),
+        ("int v = a[i] < (i += 2);", "unsequenced modification and access to `i`"),
+        ("namespace n { int v = --i - i--; }", "multiple unsequenced modifications to `i`"),
+        ("void f(int x = j++ & j);", "unsequenced modification and access to `j`"),
+    ];
+    for (source, message) in cases {
+        let messages = check(source);
+        eprintln!("{} -> {:?}", source, messages);
+        assert_eq!(messages, [message]);
+    }
+}
+
+#[test]
+fn sequenced_operands_are_fine() {
+    let sources = [
+        "int v = i++ && i++;",
+        "int v = i++ || i;",
+        "int v = (i++, i);",
+        "int v = i ? i++ : i--;",
+        "int v = i = i++ + 1;",
+        "int v = a[i++] = i;",
+        "int v = i++ << i;",
+        "int v = f(i++, i);",
+        "int v = sizeof(i++) + i;",
+        "int v = i++ + j++;",
+    ];
+    for source in sources {
+        let messages = check(source);
+        eprintln!("{} -> {:?}", source, messages);
+        assert!(messages.is_empty(), "{}", source);
+    }
+}
+
+#[test]
+fn each_variable_is_reported_once_per_expression() {
+    let messages = check("int v = i++ + i++ + i + (j = 1) + j;");
+    eprintln!("{:?}", messages);
+    assert_eq!(messages, ["multiple unsequenced modifications to `i`", "unsequenced modification and access to `j`"]);
+}
+
+#[test]
+fn statements_are_checked() {
+    let messages = check("void f() { if (i++ < i) {} for (int k = k++ + k;;) return j + j++; x = y++ * y; }");
+    eprintln!("{:?}", messages);
+    assert_eq!(messages, [
+        "unsequenced modification and access to `i`",
+        "unsequenced modification and access to `k`",
+        "unsequenced modification and access to `j`",
+        "unsequenced modification and access to `y`",
+    ]);
+}
+
+#[test]
+fn enumerators_are_numbered() {
+    let (mut s, tu) = parse("\
+        enum Color { Red, Green = 4, Blue, };\n\
+        enum class Mode : unsigned char { Off, On = Off + 2, Wrapped = (unsigned char)300 };\n\
+        enum { A = Blue * 2 + Mode::On, B = A > 10 ? -1 : 1, C, D = sizeof(int), E };\n");
+    let mut constants = HashMap::new();
+    let values: Vec<_> = tu.decls.iter().map(|d| {
+        let Decl::Enum(e) = d else { panic!("not an enum") };
+        sema::enums::values(e, &Layouts::default(), &mut constants, &mut s.diagnostics)
+    }).collect();
+    eprintln!("{:?}", values);
+    assert!(s.diagnostics.is_empty());
+    assert_eq!(values, [
+        vec![Some(0), Some(4), Some(5)],
+        vec![Some(0), Some(2), Some(44)],
+        // `sizeof` needs type layout, which sema does not have yet.
+        vec![Some(12), Some(-1), Some(0), None, None],
+    ]);
+    // Scoped enumerators are only visible qualified.
+    assert_eq!(constants.get(&Symbol::intern("Off")), None);
+    assert_eq!(constants.get(&Symbol::intern("Mode::Off")), Some(&0));
+}
+
+#[test]
+fn enumerator_errors_have_codes() {
+    let cases = [
+        ("enum E { A = 1.5 };", "E0301"),
+        ("enum E { A = x = 1 };", "E0301"),
+        ("enum E { A = 1 % (2 - 2) };", "E0302"),
+        ("enum class E : unsigned char { A = 255, B };", "E0303"),
+        ("enum E : short { A = -32769 };", "E0303"),
+        ("enum class E { A = 0x7fffffff + 1 };", "E0303"),
+        ("enum E : float { A };", "E0304"),
+        ("typedef unsigned char byte;\nenum E : byte { A = 255, B };", "E0303"),
+        ("typedef float real;\nenum E : real { A };", "E0304"),
+    ];
+    for (source, code) in cases {
+        let (mut s, tu) = parse(source);
+        sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+        let codes: Vec<_> = s.diagnostics.iter().filter_map(|d| d.code.clone()).collect();
+        eprintln!("{} -> {:?}", source, codes);
+        assert_eq!(codes, [code], "{}", source);
+    }
+    // Typedefs and aliases of integral types are integral.
+    assert!(check("typedef unsigned char byte;\nenum E : byte {};\n").is_empty());
+    assert!(check("using U = int;\nenum class F : U {};\n").is_empty());
+    assert!(check("typedef unsigned char byte;\nusing octet = const byte;\nenum G : octet { A = 255 };\nstatic_assert(sizeof(G) == 1);\n").is_empty());
+}
+
+#[test]
+fn static_assertions_are_evaluated() {
+    let source = "\
+        constexpr int square(int x) { return x * x; }\n\
+        constexpr long factorial(int n) { return n <= 1 ? 1 : n * factorial(n - 1); }\n\
+        constexpr int bits(unsigned v) { int n = 0; for (; v; v >>= 1) { if (v & 1) ++n; } return n; }\n\
+        constexpr double half = 1.0 / 2;\n\
+        enum Color { Red, Green = 5, Blue };\n\
+        static_assert(square(3) == 9);\n\
+        static_assert(factorial(10) == 3628800L, \"10!\");\n\
+        static_assert(bits(0xF0u) == 4 && Blue == 6);\n\
+        static_assert(half * 4 == 2.0);\n\
+        static_assert(sizeof(int) == 4);\n\
+        static_assert(square(2) == 5, \"two squared\");\n\
+        static_assert(sizeof(wchar_t) == 4 && sizeof(char8_t) == 1 && sizeof(char16_t) == 2 && alignof(char32_t) == 4);\n\
+        static_assert(char16_t(-1) == 65535 && char8_t(256) == 0 && (char32_t)-1 > 0 && wchar_t(-1) < 0);\n\
+        static_assert(factorial(3) > 6);\n\
+        static_assert(false, \"\");\n";
+    let messages = check(source);
+    eprintln!("{:?}", messages);
+    assert_eq!(messages, ["static assertion failed: two squared", "static assertion failed", "static assertion failed"]);
+    // Array bounds may call `constexpr` functions too.
+    assert!(check("constexpr int n() { return 4; }\nint a[n()];\nstatic_assert(sizeof(a) == 16);\nint sum() { int t = 0; for (int x : a) t += x; return t; }\n").is_empty());
+}
+
+#[test]
+fn constant_evaluation_errors_have_codes() {
+    let cases = [
+        ("constexpr int a = 2147483647 + 1;", "E0303"),
+        ("constexpr int a = 1 << 40;", "E0338"),
+        ("constexpr int a = -1 >> -1;", "E0338"),
+        ("constexpr int a = 10 / (5 - 5);", "E0302"),
+        ("constexpr double d = 1.0 / 0;", "E0302"),
+        ("constexpr int a = 1e10;", "E0303"),
+        ("constexpr int f(int n) { return f(n + 1); }\nconstexpr int a = f(0);", "E0339"),
+        ("constexpr int f() { for (;;) { } return 0; }\nconstexpr int a = f();", "E0339"),
+        ("constexpr int div(int n) { return 100 / n; }\nstatic_assert(div(0) == 0);", "E0302"),
+        ("void f(int n) { switch (n) { case 1 << 33: break; } }", "E0338"),
+        ("void f(int n) { switch (n) { case 1.5: break; } }", "E0301"),
+        ("static_assert(1 - 1, \"zero\");", "E0340"),
+        ("static_assert(a, \"\");", "E0343"),
+        ("int n;\nstatic_assert(n == 0, \"\");", "E0301"),
+        ("int n = 3;\nconst int m = n;\nstatic_assert(m == 3);", "E0301"),
+        ("static_assert(sizeof(Undeclared) == 1);", "E0343"),
+        ("int g();\nstatic_assert(g() == 0);", "E0301"),
+        ("void f(int k, int m) { switch (k) { case m: break; } }", "E0301"),
+        ("void f(int k) { switch (k) { case nope: break; } }", "E0343"),
+    ];
+    for (source, code) in cases {
+        let (mut s, tu) = parse(source);
+        sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+        let codes: Vec<_> = s.diagnostics.iter().filter_map(|d| d.code.clone()).collect();
+        eprintln!("{} -> {:?}", source, codes);
+        assert_eq!(codes, [code], "{}", source);
+    }
+    // What the evaluator does not know is not an error.
+    assert!(check("constexpr int g(int x) { return x; }\nvoid f(int k) { switch (k) { case sizeof(long): break; } }\n").is_empty());
+    assert!(check("template<int N> struct A { static_assert(N > 0); };\nstatic_assert(ns::k == 1);\nstatic_assert(__builtin_constant_p(1));\n").is_empty());
+    // A local hides a constant only in its own scope.
+    assert!(check("constexpr int n = 3;\nvoid f(int n) { int m = n; }\nvoid g() { int n = 0; }\nstatic_assert(n == 3);\n").is_empty());
+}
+
+#[test]
+fn constant_types_come_from_the_operands_not_their_values() {
+    let source = "\
+        static_assert(sizeof(1 ? 1 : 2.0) == 8 && sizeof(1 ? (short)1 : (char)1) == 4);\n\
+        static_assert((true ? -1 : 0u) > 0 && (false ? 0u : -1) == 4294967295);\n\
+        static_assert(sizeof(\"\\xff\") == 2 && sizeof(u\"ab\") == 6 && sizeof(-(char)1) == 4);\n\
+        static_assert(1 << 31 == -2147483647 - 1 && (3 << 31) == -2147483648L && -1 << 1 == -2);\n\
+        constexpr int depth(int n) { return n == 0 ? 0 : 1 + depth(n - 1); }\n\
+        static_assert(depth(500) == 500);\n";
+    let messages = check(source);
+    eprintln!("{:?}", messages);
+    assert!(messages.is_empty());
+}
+
+#[test]
+fn integer_literals_have_the_types_their_suffixes_and_bases_give() {
+    let source = "\
+        static_assert(-1 > 0u);\n\
+        static_assert(0u - 1 > 0 && 0u - 1 == 4294967295);\n\
+        static_assert(-1 < 0 && -1 < 0L && -1L < 0u);\n\
+        static_assert(-1 > 0ul && -1LL > 0ULL && -1 > 0uz);\n\
+        static_assert(0xFFFFFFFF > 0 && -1 < 4294967295 && -1 == 0xFFFFFFFF);\n\
+        static_assert(sizeof(0) == 4 && sizeof(0l) == 8 && sizeof(0x80000000) == 4 && sizeof(2147483648) == 8);\n\
+        static_assert(0x8000000000000000 > 0 && -1 > 0x8000000000000000 && 010u - 9 > 0);\n\
+        constexpr unsigned x = 2147483647 + 1u;\n\
+        static_assert(x == 2147483648u);\n\
+        constexpr unsigned long long y = 18446744073709551615ull + 1;\n\
+        static_assert(y == 0);\n";
+    let messages = check(source);
+    eprintln!("{:?}", messages);
+    assert!(messages.is_empty());
+    // Signed arithmetic still overflows.
+    assert_eq!(check("constexpr long a = 9223372036854775807L + 1;"), ["value 9223372036854775808 is outside the range of `long`"]);
+}
+
+#[test]
+fn records_are_laid_out_as_the_itanium_abi_does() {
+    let (_, tu) = parse("\
+        struct Empty {};\n\
+        struct Packet { char tag; double value; short id; };\n\
+        struct Base { virtual ~Base(); int x; };\n\
+        struct Derived : Base { char c; };\n\
+        struct NonPod { NonPod(); int i; char c; };\n\
+        struct Reuse : NonPod { char d; };\n\
+        struct Pod { int i; char c; };\n\
+        struct NoReuse : Pod { char d; };\n\
+        struct WithEmpty : Empty { int n; };\n\
+        struct Clash : Empty { Empty e; };\n\
+        struct Twice : Empty, WithEmpty { };\n\
+        struct Other {};\n\
+        struct Apart : Empty { Other o; };\n\
+        union Either { char bytes[5]; int word; };\n\
+        enum Big { Small = 1, Huge = 0x100000000 };\n\
+        namespace net { struct Header { unsigned char version; Packet body[2]; long *next; }; }\n\
+        typedef net::Header Frame;\n\
+        struct Opaque;\n\
+        struct Virtual : virtual Base { };\n\
+        struct G { [[no_unique_address]] Empty e; int x; };\n\
+        struct Both { [[no_unique_address]] Empty a; [[no_unique_address]] Empty b; char c; };\n\
+        struct Hollow { [[no_unique_address]] Empty e; };\n\
+        struct Packed { [[no_unique_address]] NonPod inner; char after; };\n\
+        struct Flags { unsigned a : 3; unsigned b : 5; unsigned c : 30; char d; int : 0; char e; };\n\
+        struct Straddle { char c; int x : 20; int y : 20; short z : 3; };\n\
+        struct Unnamed { char a; int : 4; };\n");
+    let linux = Layouts::of_unit(&tu, &Target { arch: "x86_64".into(), os: "linux".into() });
+    let named = |name: &str| TypeId::intern(Type::Named(Symbol::intern(name)));
+    let offsets = |layouts: &Layouts, name: &str| -> Vec<(u64, u64)> { layouts.layout(named(name)).unwrap().placed.iter().map(|p| (p.offset, p.size)).collect() };
+    let sizes = |layouts: &Layouts, name: &str| (layouts.size_of(named(name)).unwrap(), layouts.align_of(named(name)).unwrap());
+    assert_eq!(sizes(&linux, "Empty"), (1, 1));
+    assert_eq!(sizes(&linux, "Packet"), (24, 8));
+    assert_eq!(offsets(&linux, "Packet"), [(0, 1), (8, 8), (16, 2)]);
+    // The table pointer comes first, and `c` goes into `Base`'s tail padding.
+    assert_eq!(offsets(&linux, "Base"), [(0, 8), (8, 4)]);
+    assert_eq!((sizes(&linux, "Derived"), offsets(&linux, "Derived")), ((16, 8), vec![(0, 12), (12, 1)]));
+    // Only a base that is not POD lends its tail padding.
+    assert_eq!((sizes(&linux, "Reuse"), offsets(&linux, "Reuse")), ((8, 4), vec![(0, 5), (5, 1)]));
+    assert_eq!((sizes(&linux, "NoReuse"), offsets(&linux, "NoReuse")), ((12, 4), vec![(0, 8), (8, 1)]));
+    assert_eq!(offsets(&linux, "WithEmpty"), [(0, 0), (0, 4)]);
+    // Two empty subobjects of one type never share an address.
+    assert_eq!((sizes(&linux, "Clash"), offsets(&linux, "Clash")), ((2, 1), vec![(0, 0), (1, 1)]));
+    assert_eq!((sizes(&linux, "Twice"), offsets(&linux, "Twice")), ((8, 4), vec![(0, 0), (4, 4)]));
+    assert_eq!((sizes(&linux, "Apart"), offsets(&linux, "Apart")), ((1, 1), vec![(0, 0), (0, 1)]));
+    // A `[[no_unique_address]]` member is placed like a base.
+    assert_eq!((sizes(&linux, "G"), offsets(&linux, "G")), ((4, 4), vec![(0, 0), (0, 4)]));
+    assert_eq!((sizes(&linux, "Both"), offsets(&linux, "Both")), ((2, 1), vec![(0, 0), (0, 1), (1, 0)]));
+    assert!(linux.layout(named("Hollow")).unwrap().is_empty);
+    assert_eq!((sizes(&linux, "Packed"), offsets(&linux, "Packed")), ((8, 4), vec![(0, 5), (5, 1)]));
+    // Bit-fields share units of their type, and never straddle one.
+    assert_eq!((sizes(&linux, "Flags"), offsets(&linux, "Flags")), ((16, 4), vec![(0, 1), (0, 1), (4, 4), (8, 1), (12, 1)]));
+    let bits: Vec<_> = linux.layout(named("Flags")).unwrap().placed.iter().map(|p| p.bits).collect();
+    assert_eq!(bits, [Some((0, 3)), Some((3, 5)), Some((0, 30)), None, None]);
+    assert_eq!((sizes(&linux, "Straddle"), offsets(&linux, "Straddle")), ((8, 4), vec![(0, 1), (1, 3), (4, 3), (6, 1)]));
+    // An unnamed bit-field's type does not align the class.
+    assert_eq!(sizes(&linux, "Unnamed"), (2, 1));
+    assert_eq!(sizes(&linux, "Either"), (8, 4));
+    assert_eq!(sizes(&linux, "Big"), (8, 8));
+    assert_eq!(offsets(&linux, "Frame"), [(0, 1), (8, 48), (56, 8)]);
+    assert_eq!(linux.size_of(named("Opaque")), Err(LayoutError::Incomplete(named("Opaque"))));
+    assert_eq!(linux.size_of(named("Virtual")), Err(LayoutError::VirtualBase(Symbol::intern("Virtual"))));
+    assert_eq!(linux.size_of(named("Missing")), Err(LayoutError::Unknown(named("Missing"))));
+    eprint!("{}", linux.layout(named("Frame")).unwrap());
+
+    // 32-bit x86 aligns `double` to 4 bytes; 64-bit Windows has a 32-bit `long`.
+    let x86 = Layouts::of_unit(&tu, &Target { arch: "i686".into(), os: "linux".into() });
+    assert_eq!(sizes(&x86, "Packet"), (16, 4));
+    assert_eq!(sizes(&x86, "Base"), (8, 4));
+    let windows = Layouts::of_unit(&tu, &Target { arch: "x86_64".into(), os: "windows".into() });
+    assert_eq!(windows.size_of(TypeId::intern(Type::Long)), Ok(4));
+    let characters = [Type::WChar, Type::Char8, Type::Char16, Type::Char32].map(TypeId::intern);
+    assert_eq!(characters.map(|ty| linux.size_of(ty).unwrap()), [4, 1, 2, 4]);
+    assert_eq!(characters.map(|ty| windows.size_of(ty).unwrap()), [2, 1, 2, 4]);
+    assert_eq!(windows.size_of(TypeId::intern(Type::Pointer(TypeId::intern(Type::Long)))), Ok(8));
+}
+
+#[test]
+fn sizeof_and_alignof_are_constant_expressions() {
+    let source = "\
+        struct Pair { int first; char second; };\n\
+        struct Node { Node *next; Pair items[3]; };\n\
+        typedef Pair Entry;\n\
+        enum class Flag : unsigned char { On };\n\
+        int table[10];\n\
+        short &ref = *(short *)0;\n\
+        constexpr int count(int n) { char buffer[4]; return n * sizeof buffer; }\n\
+        static_assert(sizeof(Pair) == 8 && alignof(Pair) == 4);\n\
+        static_assert(sizeof(Entry[2]) == 16 && sizeof(Flag) == 1);\n\
+        static_assert(sizeof table == 40 && sizeof table[0] == 4 && sizeof(ref) == 2);\n\
+        static_assert(sizeof \"abc\" == 4 && sizeof(1 + 2.0) == sizeof(double));\n\
+        static_assert(count(3) == 12);\n\
+        static_assert(sizeof(Node) == sizeof(void *) + 24);\n\
+        static_assert(sizeof(Pair) == 5, \"packed\");\n";
+    assert_eq!(check(source), ["static assertion failed: packed"]);
+    let cases = [
+        ("static_assert(sizeof(void) == 1);", "E0341"),
+        ("struct S;\nstatic_assert(sizeof(S) == 1);", "E0341"),
+        ("int f();\nconstexpr unsigned long n = sizeof(int ());", "E0341"),
+        ("static_assert(sizeof(char[1ull << 62][8]) > 0);", "E0341"),
+        ("struct S;\nint size() { return sizeof(S); }", "E0341"),
+        ("void f();\nint size() { return sizeof f; }", "E0341"),
+        ("static_assert(sizeof(Unknown) == 4);", "E0343"),
+    ];
+    for (source, code) in cases {
+        let (mut s, tu) = parse(source);
+        sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+        let codes: Vec<_> = s.diagnostics.iter().filter_map(|d| d.code.clone()).collect();
+        eprintln!("{} -> {:?}", source, codes);
+        assert_eq!(codes, [code], "{}", source);
+    }
+    // What sema cannot see into is not known, and not an error.
+    assert!(check("template <typename T> struct Box { T value; };\nstatic_assert(sizeof(Box<int>) == 4);\n").is_empty());
+    // A member function's body sees the classes nested after it.
+    assert!(check("struct List { struct Node; int size() { return sizeof(Node); } struct Node { int value; }; };\n").is_empty());
+}
+
+#[test]
+fn cli_prints_record_layouts() {
+    let dir = std::env::temp_dir().join(format!("ruscom-layout-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("packet.cpp");
+    std::fs::write(&file, "namespace net { struct Packet { char tag; int length; short id; }; }\n").unwrap();
+    let run = |name: &str| assert_cmd::Command::cargo_bin("ruscom").unwrap().arg("layout").arg(name).arg(&file).output().unwrap();
+    let output = run("net::Packet");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    eprint!("{}", stdout);
+    assert_eq!(stdout, "\
+net::Packet
+  offset  size  member
+       0     1  char tag
+       1     3  (padding)
+       4     4  int length
+       8     2  short id
+      10     2  (tail padding)
+  size 12, align 4
+");
+    let output = run("Missing");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("declares no class, enum or typedef named `Missing`"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn discarded_nodiscard_results_are_reported() {
+    let (mut s, tu) = parse("\
+        [[nodiscard(\"it may fail\")]] int open(const char *path);\n\
+        struct [[nodiscard]] Error { int code; };\n\
+        Error check();\n\
+        struct File { [[gnu::warn_unused_result]] int read(); };\n\
+        int plain();\n\
+        void f(File file) { open(\"a\"); (check()); file.read(); (void)open(\"b\"); int fd = open(\"c\"); plain(); }\n");
+    sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+    let messages: Vec<_> = s.diagnostics.iter().map(|d| (d.message.clone(), d.notes.clone())).collect();
+    eprintln!("{:#?}", messages);
+    assert_eq!(messages, [
+        ("ignoring return value of `open`, declared with attribute `nodiscard`".to_string(), vec!["it may fail".to_string()]),
+        ("ignoring return value of `check`, declared with attribute `nodiscard`".to_string(), vec![]),
+        ("ignoring return value of `read`, declared with attribute `nodiscard`".to_string(), vec![]),
+    ]);
+}
+
+#[test]
+fn nodiscard_classes_are_seen_through_aliases() {
+    let messages = check("\
+        struct [[nodiscard]] Status { int code; };\n\
+        typedef Status Result;\n\
+        using Outcome = const Result;\n\
+        Outcome run();\n\
+        void f() { run(); }\n");
+    eprintln!("{:?}", messages);
+    assert_eq!(messages, ["ignoring return value of `run`, declared with attribute `nodiscard`"]);
+}
+
+#[test]
+fn interrupt_handlers_and_naked_functions() {
+    let source = "\
+        struct Frame;\n\
+        __attribute__((interrupt)) void isr(Frame *frame) {}\n\
+        __attribute__((interrupt)) void fault(Frame *frame, unsigned long code) {}\n\
+        __attribute__((interrupt)) int bad_return(Frame *frame);\n\
+        [[gnu::interrupt]] void bad_code(Frame *frame, int code);\n\
+        __attribute__((interrupt)) void no_frame();\n\
+        __attribute__((naked)) void reset() {}\n\
+        [[gnu::naked]] void start() { int x = 0; return; }\n";
+    let mut results = Vec::new();
+    for arch in ["x86_64", "aarch64"] {
+        let (mut s, tu) = parse(source);
+        s.target = Target { arch: arch.into(), os: "none".into() };
+        sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+        let found: Vec<_> = s.diagnostics.iter().map(|d| format!("{} {}", d.code.as_deref().unwrap_or("warning"), d.message)).collect();
+        eprintln!("{}: {:#?}", arch, found);
+        results.push(found);
+    }
+    let signature = "an interrupt handler takes a pointer to the interrupt frame and, for exceptions, an `unsigned long` error code";
+    assert_eq!(results[0], [
+        "E0305 an interrupt handler must return `void`".to_string(),
+        format!("E0305 {}", signature),
+        format!("E0305 {}", signature),
+        "E0306 only inline assembly may appear in a naked function".to_string(),
+        "E0306 only inline assembly may appear in a naked function".to_string(),
+    ]);
+    assert_eq!(results[1].len(), 7);
+    assert_eq!(results[1][0], "-Wattributes `gnu::interrupt` attribute ignored on aarch64");
+    assert!(results[1][5..].iter().all(|m| m.starts_with("E0306")));
+}
+
+#[test]
+fn structured_bindings_deduce_their_types() {
+    let (mut s, tu) = parse("\
+        namespace std { template <class A, class B> struct pair; template <class T, unsigned long N> struct array; }\n\
+        struct P { int x; const char *name; static int count; };\n\
+        typedef P Alias;\n\
+        P p; const P cp; Alias alias; int arr[3]; std::pair<int, double> pr; std::array<long, 2> two;\n\
+        auto [a, b] = p;\n\
+        const auto& [c, d] = p;\n\
+        auto [e, f] = cp;\n\
+        auto& [g, h] = alias;\n\
+        auto [i, j, k] = arr;\n\
+        auto [l, m] = pr;\n\
+        auto [n, o] = two;\n\
+        auto [q, r] = unknown;\n");
+    let mut bindings = sema::bindings::Bindings::default();
+    for decl in &tu.decls {
+        match decl {
+            Decl::Var(v) => bindings.declare_variable(v.name, v.ty),
+            Decl::Class(c) => bindings.declare_class(c),
+            Decl::Typedef(t) => bindings.declare_alias(t),
+            Decl::Decomposition(d) => bindings.bind(d, &mut s.diagnostics),
+            _ => {}
+        }
+    }
+    assert!(s.diagnostics.is_empty());
+    let found: Vec<String> = "abcdefghijklmnoqr".chars().map(|c| {
+        bindings.variable(Symbol::intern(&c.to_string())).map_or("-".into(), |t| t.to_string())
+    }).collect();
+    eprintln!("{:?}", found);
+    assert_eq!(found, [
+        "int", "const char*", "const int", "const char* const", "const int", "const char* const", "int", "const char*",
+        "int", "int", "int", "int", "double", "long", "long", "-", "-",
+    ]);
+    let cases = [
+        ("struct P { int x, y; }; P p; auto [a, b, c] = p;", "E0307 type `P` decomposes into 2 elements, but 3 names were provided"),
+        ("int arr[2]; void f() { auto& [a] = arr; }", "E0307 type `int[2]` decomposes into 2 elements, but 1 names were provided"),
+        ("void f(int n) { auto [a] = n; }", "E0308 cannot decompose non-class, non-array type `int`"),
+        ("struct S { int v; }; using T = S; T s; auto [a, b] = (s);", "E0307 type `T` decomposes into 1 elements, but 2 names were provided"),
+    ];
+    for (source, expected) in cases {
+        let (mut s, tu) = parse(source);
+        sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+        let found: Vec<String> = s.diagnostics.iter().map(|d| format!("{} {}", d.code.as_deref().unwrap_or("warning"), d.message)).collect();
+        eprintln!("{:?} -> {:?}", source, found);
+        assert_eq!(found, [expected], "{}", source);
+    }
+}
+
+#[test]
+fn range_based_for_is_lowered_to_iterators() {
+    let (s, mut tu) = parse("int arr[3];\nvoid f() { for (auto& x : arr) use(x); for (int i = 0; int j : v) ; for (auto [a, b] : bag) ; }\n");
+    let Some(Decl::Function(f)) = tu.decls.last_mut() else { panic!("not a function") };
+    let stmts = &mut f.body.as_mut().unwrap().stmts;
+    for (stmt, ends) in stmts.iter_mut().zip([Ends::Array(3), Ends::Members, Ends::Functions]) { *stmt = range_for::lower(stmt, ends); }
+    let dump = ast::dump(&tu, &s.sources);
+    eprintln!("{}", dump);
+    assert_eq!(dump.matches("VarDecl __range 'auto&&'").count(), 3);
+    assert_eq!(dump.matches("BinaryOperator '!='").count(), 3);
+    assert_eq!(dump.matches("UnaryOperator prefix '++'").count(), 3);
+    assert!(!dump.contains("CXXForRangeStmt"));
+    for part in ["BinaryOperator '+'", "IntegerLiteral 3", "MemberExpr .begin", "MemberExpr .end", "DeclRefExpr begin", "DeclRefExpr end", "VarDecl x 'auto&'", "VarDecl j 'int'", "VarDecl i 'int'"] {
+        assert!(dump.contains(part), "{}", part);
+    }
+    let cases: [(&str, &[&str]); 5] = [
+        ("struct P { int a, b; }; P ps[2]; void f() { for (auto& [a, b] : ps) a = b; for (auto p : ps) { auto [c, d] = p; } }", &[]),
+        ("int n; void f() { for (int x : n) ; }", &["E0309 invalid range expression of type `int`; no viable `begin` function available"]),
+        ("struct P { int a, b; }; P ps[2]; void f() { for (auto [a, b, c] : ps) ; }", &["E0307 type `P` decomposes into 2 elements, but 3 names were provided"]),
+        ("int arr[2]; void f() { for (const auto& x : arr) { auto [a] = x; } }", &["E0308 cannot decompose non-class, non-array type `const int`"]),
+        ("int arr[2]; void f() { for (int x : arr) x = x++ + x++; }", &["-Wsequence-point multiple unsequenced modifications to `x`"]),
+    ];
+    for (source, expected) in cases {
+        let (mut s, tu) = parse(source);
+        sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+        let found: Vec<String> = s.diagnostics.iter().map(|d| format!("{} {}", d.code.as_deref().unwrap_or("warning"), d.message)).collect();
+        eprintln!("{:?} -> {:?}", source, found);
+        assert_eq!(found, expected, "{}", source);
+    }
+}
+
+#[test]
+fn operators_on_class_objects_need_an_operator_function() {
+    let prelude = "\
+        struct P { int a; };\n\
+        struct V { int x; V operator+(int) const; int operator[](int); bool operator==(const V&) const; auto operator<=>(const V&) const; };\n\
+        V operator-(V, V);\n\
+        struct B : P {};\n\
+        struct C { operator int() const; };\n\
+        template<class T> T operator*(T, T);\n\
+        long double operator\"\"_km(long double);\n";
+    let cases: [(&str, &[&str]); 8] = [
+        ("void f(V v, V w, int n) { v + 1; v[n]; v - w; v != w; v < w; w == v; v * w; n + n; &v; v = w; }", &[]),
+        ("void f(V v) { x = 1.5_km + 3ms; }", &[]),
+        ("void f(B b, C c) { b + b; c + 1; -c; }", &[]),
+        ("void f(P p, P q) { p + q; }", &["E0310 no viable `operator+` for an operand of type `P`"]),
+        ("void f(P p, int i) { i == p; p(1); p[0]; !p; }", &[
+            "E0310 no viable `operator==` for an operand of type `P`",
+            "E0310 no viable `operator()` for an operand of type `P`",
+            "E0310 no viable `operator[]` for an operand of type `P`",
+            "E0310 no viable `operator!` for an operand of type `P`",
+        ]),
+        ("void f(V v) { 1 + v; ~v; }", &["E0310 no viable `operator+` for an operand of type `V`", "E0310 no viable `operator~` for an operand of type `V`"]),
+        ("void f(V *v) { v->x; (*v)[1]; } void g(P *p) { (*p)->a; }", &["E0310 no viable `operator->` for an operand of type `P`"]),
+        ("int n = 12_mi;", &["E0311 no matching literal operator for suffix `_mi`"]),
+    ];
+    for (source, expected) in cases {
+        let source = format!("{}{}", prelude, source);
+        let (mut s, tu) = parse(&source);
+        sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+        let found: Vec<String> = s.diagnostics.iter().map(|d| format!("{} {}", d.code.as_deref().unwrap_or("warning"), d.message)).collect();
+        eprintln!("{:?} -> {:?}", source, found);
+        assert_eq!(found, expected, "{}", source);
+    }
+}
+
+#[test]
+fn expressions_are_type_checked() {
+    let prelude = "\
+        struct S { int x; const int c; int get() const; void set(int); };\n\
+        int add(int a, int b = 0);\n\
+        int add(int a, int b, int c);\n\
+        void log(const char *format, ...);\n\
+        int count(const char *s);\n\
+        namespace m { double half(double); }\n";
+    let cases: [(&str, &[&str]); 13] = [
+        (
+            "int g(S s, S *p, int *q, const int *r, int n) { \
+                int k = add(1) + add(1, 2, 3) + s.get() + p->x + q[n] + *r; double d = m::half(n) * 2; void *v = q; \
+                const int &ref = n + 1; int &&rref = n * 2; auto a = &s; a->set(k); auto &b = *q; b = d; \
+                log(\"%d\", n, k); bool z = q == nullptr || q != 0; q = 0; n += *q; q -= 1; long diff = q - q; \
+                int arr[3]; arr[0] = k; 1[arr] = count(\"x\"); return s.x = k; }",
+            &[],
+        ),
+        ("void f(S s, T t, int n) { t(); t.x = 1; u + n; undeclared(1, 2); s.frobnicate(t); }", &["E0332 no member named `frobnicate` in `S`"]),
+        ("int n = 1; void f() { n(); int (*fp)(int) = 0; fp(2); fp(); }", &[
+            "E0319 called object type `int` is not a function or function pointer",
+            "E0327 too few arguments to function call, expected 1, have 0",
+        ]),
+        ("void f(int n, S s) { add(1) = n; n + 1 = 2; 5++; s.get() = 1; }", &[
+            "E0320 expression is not assignable",
+            "E0320 expression is not assignable",
+            "E0320 expression is not assignable",
+            "E0320 expression is not assignable",
+        ]),
+        ("void f(const int n, S s, const S &t) { n = 1; s.c = 2; t.x++; }", &[
+            "E0321 cannot assign to a value of const-qualified type `const int`",
+            "E0321 cannot assign to a value of const-qualified type `const int`",
+            "E0321 cannot assign to a value of const-qualified type `const int`",
+        ]),
+        ("void f(int *p, int *q, double d) { p + q; d % 2; -p; *d; &5; d[1]; p * 2; }", &[
+            "E0322 invalid operands to binary `+` (`int*` and `int*`)",
+            "E0322 invalid operands to binary `%` (`double` and `int`)",
+            "E0323 invalid argument type `int*` to unary `-`",
+            "E0324 indirection requires pointer operand (`double` invalid)",
+            "E0325 cannot take the address of an rvalue of type `int`",
+            "E0326 subscripted value of type `double` is not an array or pointer",
+            "E0322 invalid operands to binary `*` (`int*` and `int`)",
+        ]),
+        ("void f() { add(); add(1, 2, 3, 4); count(); count(\"a\", \"b\"); log(); }", &[
+            "E0328 no overload of `add` takes 0 arguments",
+            "E0328 no overload of `add` takes 4 arguments",
+            "E0327 too few arguments to function call, expected 1, have 0",
+            "E0327 too many arguments to function call, expected 1, have 2",
+            "E0327 too few arguments to function call, expected at least 1, have 0",
+        ]),
+        ("void f(int n, double *d, const char *s) { int *p = n; int *q = d; char *t = s; count(n); p = 1; }", &[
+            "E0329 no viable conversion from `int` to `int*`",
+            "E0329 no viable conversion from `double*` to `int*`",
+            "E0329 no viable conversion from `const char*` to `char*`",
+            "E0329 no viable conversion from `int` to `const char*`",
+            "E0329 no viable conversion from `int` to `int*`",
+        ]),
+        ("void f(int n, const int c) { int &a = n + 1; int &&b = n; int &d = c; }", &[
+            "E0330 non-const lvalue reference to type `int` cannot bind to a temporary of type `int`",
+            "E0331 rvalue reference to type `int` cannot bind to lvalue of type `int`",
+            "E0329 no viable conversion from `const int` to `int&`",
+        ]),
+        ("void f(S s, S *p, int n) { n.x; s->x; p.x; }", &[
+            "E0333 member reference base type `int` is not a structure or union",
+            "E0310 no viable `operator->` for an operand of type `S`",
+            "E0334 member reference type `S*` is a pointer; did you mean to use `->`?",
+        ]),
+        ("int f(int n) { if (n) return; return n; } void g() { return 1; } int *h() { return 1.5; }", &[
+            "E0335 non-void function `f` should return a value",
+            "E0336 void function `g` should not return a value",
+            "E0329 no viable conversion from `double` to `int*`",
+        ]),
+        ("int S::get() const { x = 1; return this->x; } void S::set(int v) { x = v; c = v; }", &[
+            "E0321 cannot assign to a value of const-qualified type `const int`",
+            "E0321 cannot assign to a value of const-qualified type `const int`",
+        ]),
+        // Neither a `mutable` member nor what a reference member refers to
+        // is `const` in a `const` object.
+        ("struct M { mutable int m; int &r; int n; void touch() const; };\n\
+          void M::touch() const { m = 2; r = 1; n = 0; } void g(const M &s) { s.m = 3; s.r = 1; s.n = 4; }", &[
+            "E0321 cannot assign to a value of const-qualified type `const int`",
+            "E0321 cannot assign to a value of const-qualified type `const int`",
+        ]),
+    ];
+    for (source, expected) in cases {
+        let source = format!("{}{}", prelude, source);
+        let (mut s, tu) = parse(&source);
+        sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+        let found: Vec<String> = s.diagnostics.iter().map(|d| format!("{} {}", d.code.as_deref().unwrap_or("warning"), d.message)).collect();
+        eprintln!("{:?} -> {:?}", source, found);
+        assert_eq!(found, expected, "{}", source);
+    }
+}
+
+#[test]
+fn names_in_member_functions_are_looked_up_in_their_class() {
+    let prelude = "\
+        enum Color { Red };\n\
+        typedef int Int;\n\
+        struct S {\n\
+            int x; static int count; enum { Size = 4 };\n\
+            int get() const; void set(int); static int make(); int twice();\n\
+        };\n";
+    let cases: [(&str, &[&str]); 7] = [
+        ("int S::twice() { set(x + Red + Size + count + Int(1) + make()); return get() * 2; }", &[]),
+        ("int S::get() const { x = 1; return this->x; }", &["E0321 cannot assign to a value of const-qualified type `const int`"]),
+        ("int S::get() const { set(make()); return twice(); }", &[
+            "E0345 member function `set` cannot be called on a `const` object",
+            "E0345 member function `twice` cannot be called on a `const` object",
+        ]),
+        ("struct T { int x; int f() { return y; } };", &["E0343 use of undeclared identifier `y`"]),
+        ("int S::twice() { return frobnicate(x) + z; }", &[
+            "E0343 use of undeclared identifier `frobnicate`",
+            "E0343 use of undeclared identifier `z`",
+        ]),
+        ("struct T { int x; void g(); static int f() { g(); return count + x + sizeof(x); } static int count; };", &[
+            "E0344 invalid use of member `g` in static member function",
+            "E0344 invalid use of member `x` in static member function",
+        ]),
+        ("int S::make() { return count + x + this->x; }", &["E0344 invalid use of member `x` in static member function"]),
+    ];
+    for (source, expected) in cases {
+        let source = format!("{}{}", prelude, source);
+        let (mut s, tu) = parse(&source);
+        sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+        let found: Vec<String> = s.diagnostics.iter().map(|d| format!("{} {}", d.code.as_deref().unwrap_or("warning"), d.message)).collect();
+        eprintln!("{:?} -> {:?}", source, found);
+        assert_eq!(found, expected, "{}", source);
+    }
+    // What a name may be is not known with a base, in a template, in a
+    // nested class or after a `using`.
+    assert!(check("struct B { int y; };\nstruct D : B { int f() { return y; } };\n").is_empty());
+    assert!(check("template<int N> struct A { int f() { return N + dependent(); } };\n").is_empty());
+    assert!(check("struct O { static int s; struct I { int m() { return s; } }; };\n").is_empty());
+    assert!(check("namespace n { int k; }\nusing n::k;\nstruct T { int f() { return k; } };\n").is_empty());
+    assert!(check("struct T { int f() { return __builtin_popcount(3); } };\n").is_empty());
+}
+
+#[test]
+fn const_objects_call_only_const_member_functions() {
+    let prelude = "\
+        struct S {\n\
+            void mutate(); int get() const; static int make();\n\
+            int &at(); const int &at() const;\n\
+        };\n";
+    let cases: [(&str, &[&str]); 4] = [
+        ("int f(const S s, const S *p, S t) { t.mutate(); t.at() = 1; return s.get() + s.make() + p->get() + t.get(); }", &[]),
+        ("void f(const S s, const S *p) { s.mutate(); p->mutate(); }", &[
+            "E0345 member function `mutate` cannot be called on a `const` object",
+            "E0345 member function `mutate` cannot be called on a `const` object",
+        ]),
+        // The const object picks the const overload.
+        ("void f(const S &s) { s.at() = 1; }", &["E0321 cannot assign to a value of const-qualified type `const int`"]),
+        ("int f(volatile S v) { return v.get(); }", &["E0345 member function `get` cannot be called on a `volatile` object"]),
+    ];
+    for (source, expected) in cases {
+        let source = format!("{}{}", prelude, source);
+        let (mut s, tu) = parse(&source);
+        sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+        let found: Vec<String> = s.diagnostics.iter().map(|d| format!("{} {}", d.code.as_deref().unwrap_or("warning"), d.message)).collect();
+        eprintln!("{:?} -> {:?}", source, found);
+        assert_eq!(found, expected, "{}", source);
+    }
+}
+
+#[test]
+fn member_access_chains_through_pointers_objects_and_operator_arrow() {
+    let prelude = "\
+        struct D { int d(int); };\n\
+        struct C { D *c; };\n\
+        struct B { C b; };\n\
+        struct A { B *a; };\n\
+        struct P { A *operator->(); const A *operator->() const; };\n\
+        struct Q { P operator->(); };\n\
+        struct R { R operator->(); };\n";
+    let cases: [(&str, &[&str]); 5] = [
+        ("int f(A *a, P p, Q q, int e) { int n = a->a->b.c->d(e); n += p->a->b.c->d(e); return q->a->b.c->d(n); }", &[]),
+        ("void f(A *a, P p) { a->a->b.c->x; p->a->b.c->nope; }", &[
+            "E0332 no member named `x` in `D`",
+            "E0332 no member named `nope` in `D`",
+        ]),
+        ("void f(A *a, Q q, int e) { a->a->b.c.d(e); q->a.b; }", &[
+            "E0334 member reference type `D*` is a pointer; did you mean to use `->`?",
+            "E0334 member reference type `B*` is a pointer; did you mean to use `->`?",
+        ]),
+        ("void f(Q q, int e) { q->a->b.c->d(e, e); q->a->b.c->d(\"s\"); }", &[
+            "E0327 too many arguments to function call, expected 1, have 2",
+            "E0329 no viable conversion from `const char[2]` to `int`",
+        ]),
+        // A const object calls the const `operator->`; drilling round in a
+        // circle gives no type.
+        ("void f(P p, const P &c, R r, B *b) { p->a = b; c->a = b; r->x; }", &["E0321 cannot assign to a value of const-qualified type `B* const`"]),
+    ];
+    for (source, expected) in cases {
+        let source = format!("{}{}", prelude, source);
+        let (mut s, tu) = parse(&source);
+        sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+        let found: Vec<String> = s.diagnostics.iter().map(|d| format!("{} {}", d.code.as_deref().unwrap_or("warning"), d.message)).collect();
+        eprintln!("{:?} -> {:?}", source, found);
+        assert_eq!(found, expected, "{}", source);
+    }
+}
+
+#[test]
+fn overloads_are_ranked_by_their_conversions() {
+    // Each call's result is assigned to a `double*`, so the error names
+    // the overload picked by its return type.
+    let source = "\
+        int pick(int); char *pick(double); void *pick(const char *); int pick(int, int);\n\
+        long ref(int &); char *ref(const double &);\n\
+        int amb(long); int amb(unsigned);\n\
+        void g(long); void g(unsigned); void g(int, int);\n\
+        void f(char c, float x, short s, int i, double d, const int k, long long ll, unsigned long ul, long l, unsigned u) {\n\
+        \x20   double *p;\n\
+        \x20   p = pick(c); p = pick(x); p = pick(s); p = pick(\"s\"); p = pick(d);\n\
+        \x20   p = ref(i); p = ref(k); p = ref(1);\n\
+        \x20   pick(&i); ref(nullptr); p = amb(i); g(1);\n\
+        \x20   p = ll + ul; p = l + u; p = i + u; p = c + s; p = x + i; p = x * 2.0;\n\
+        }\n";
+    let to = |from: &str| format!("no viable conversion from `{}` to `double*`", from);
+    let expected = [
+        to("int"), to("char*"), to("int"), to("void*"), to("char*"),
+        to("long"), to("char*"), to("char*"),
+        "no overload of `pick` accepts arguments of type (`int*`)".to_owned(),
+        "no overload of `ref` accepts arguments of type (`std::nullptr_t`)".to_owned(),
+        "call to `amb` is ambiguous between `int (long)`, `int (unsigned int)`".to_owned(),
+        "call to `g` is ambiguous between `void (long)`, `void (unsigned int)`".to_owned(),
+        to("unsigned long long"), to("long"), to("unsigned int"), to("int"), to("float"), to("double"),
+    ];
+    assert_eq!(check(source), expected);
+}
+
+#[test]
+fn narrowing_conversions_are_warned_about_with_wconversion() {
+    let source = "\
+        void take(short, float);\n\
+        int f(int i, short s, long l, float x, double d, unsigned u) {\n\
+        \x20   int a = d; short b = i; char c = 300; unsigned char e = 255; char g = 'a'; unsigned h = -1;\n\
+        \x20   float j = d; float m = 1.5; float n = 0.1; int o = 2.0; int q = 2.5; double r = x;\n\
+        \x20   long t = i; int v = l; float w = i; double y = i; double z = l; bool flag = d; short sum = s + s;\n\
+        \x20   take(i, d); take(1, 2); a = x; a = (int)d; return l;\n\
+        }\n";
+    let (mut s, tu) = parse(source);
+    sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+    assert!(s.diagnostics.is_empty(), "off without -Wconversion");
+    s.diagnostics.warnings_mut().apply(WarningOption::Enable(Warning::Conversion));
+    sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+    let found: Vec<String> = s.diagnostics.iter().map(|d| {
+        let at = s.sources.location(d.primary_span().unwrap().start).unwrap();
+        format!("{}:{} {}", at.line, at.column, d.message)
+    }).collect();
+    eprintln!("{:#?}", found);
+    assert_eq!(found, [
+        "3:13 implicit conversion from `double` to `int` may change the value",
+        "3:26 implicit conversion from `int` to `short` may change the value",
+        "3:38 implicit conversion from `int` to `char` changes the value from 300 to 44",
+        "4:15 implicit conversion from `double` to `float` may change the value",
+        "4:43 implicit conversion from `double` to `float` may change the value",
+        "4:69 implicit conversion from `double` to `int` changes the value from 2.5 to 2",
+        "5:25 implicit conversion from `long` to `int` may change the value",
+        "5:38 implicit conversion from `int` to `float` may change the value",
+        "5:66 implicit conversion from `long` to `double` may change the value",
+        "5:96 implicit conversion from `int` to `short` may change the value",
+        "6:10 implicit conversion from `int` to `short` may change the value",
+        "6:13 implicit conversion from `double` to `float` may change the value",
+        "6:33 implicit conversion from `float` to `int` may change the value",
+        "6:55 implicit conversion from `long` to `int` may change the value",
+    ]);
+    let first = s.diagnostics.iter().next().unwrap();
+    assert_eq!((first.code.as_deref(), first.help.as_slice()), (Some("-Wconversion"), ["cast to `int` explicitly if this is intended".to_owned()].as_slice()));
+}
+
+#[test]
+fn coroutines_are_checked_where_they_appear() {
+    let cases: [(&str, &[&str]); 6] = [
+        ("task f(int n) { int x = co_await n; co_yield x; if (x) co_return; co_return x; } int g() { return 1; }", &[]),
+        ("int n = co_await 1; void f(int a = co_yield 2);", &[
+            "E0312 `co_await` cannot be used outside a function body",
+            "E0312 `co_yield` cannot be used outside a function body",
+        ]),
+        ("struct S { int m = co_await 1; S() : m(co_await 2) {} };", &[
+            "E0312 `co_await` cannot be used outside a function body",
+            "E0312 `co_await` cannot be used outside a function body",
+        ]),
+        ("int main() { co_return; } constexpr task f() { co_return; } auto g() { co_yield 1; } task h(int, ...) { co_await 1; }", &[
+            "E0313 `main` cannot be a coroutine",
+            "E0313 a `constexpr` function cannot be a coroutine",
+            "E0313 a coroutine cannot have a deduced return type",
+            "E0313 a coroutine cannot take variadic arguments",
+        ]),
+        ("struct S { S() { co_return; } ~S() { co_await 1; } };", &["E0313 a constructor cannot be a coroutine", "E0313 a destructor cannot be a coroutine"]),
+        ("task f(int n) { while (n) { if (n) return 1; } co_return; }", &["E0314 return statement not allowed in coroutine; did you mean `co_return`?"]),
+    ];
+    for (source, expected) in cases {
+        let (mut s, tu) = parse(source);
+        sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+        let found: Vec<String> = s.diagnostics.iter().map(|d| format!("{} {}", d.code.as_deref().unwrap_or("warning"), d.message)).collect();
+        eprintln!("{:?} -> {:?}", source, found);
+        assert_eq!(found, expected, "{}", source);
+    }
+}
+
+#[test]
+fn symbols_get_their_visibility_from_attributes_and_scopes() {
+    let source = r#"
+struct __attribute__((visibility("hidden"))) S { void f(); [[gnu::visibility("default")]] void g(); void h() {} static int n; };
+void S::f() {}
+void S::g() {}
+int S::n = 1;
+[[gnu::visibility("protected")]] extern int x;
+int x = 4;
+const int k = 3;
+static int local() { return 1; }
+inline int used_only() { return 1; }
+namespace { int anon = 2; }
+namespace __attribute__((visibility("hidden"))) detail { void helper() {} }
+namespace api [[gnu::visibility("default")]] { void run(); }
+void api::run() {}
+template<class T> T id(T t) { return t; }
+int main() { return 0; }
+"#;
+    let (_, tu) = parse(source);
+    let listed = |default| symbols::defined(&tu, default).iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    let all = listed(Visibility::Default);
+    eprintln!("{:#?}", all);
+    assert_eq!(all, [
+        "global hidden function S::f 'void ()'",
+        "global default function S::g 'void ()'",
+        "global hidden variable S::n 'int'",
+        "global protected variable x 'int'",
+        "local variable k 'const int'",
+        "local function local 'int ()'",
+        "local variable anon 'int'",
+        "global hidden function detail::helper 'void ()'",
+        "global default function api::run 'void ()'",
+        "global default function main 'int ()'",
+    ]);
+    let exported: Vec<Symbol> = symbols::defined(&tu, Visibility::Hidden).into_iter().filter(|s| s.is_exported()).map(|s| s.name).collect();
+    assert_eq!(exported, [Symbol::intern("S::g"), Symbol::intern("x"), Symbol::intern("api::run")]);
+    assert_eq!(listed(Visibility::Hidden).last().map(String::as_str), Some("global hidden function main 'int ()'"));
+}
+
+#[test]
+fn visibility_attributes_must_name_a_visibility() {
+    let found = check(r#"
+void a() __attribute__((visibility("secret")));
+[[gnu::visibility]] int b;
+struct [[gnu::visibility(1)]] C {};
+namespace __attribute__((visibility("hidden", "default"))) d {}
+__attribute__((visibility("internal"))) void e();
+"#);
+    eprintln!("{:?}", found);
+    assert_eq!(found, ["visibility must be \"default\", \"protected\", \"hidden\" or \"internal\""; 4]);
+}
+
+#[test]
+fn cli_lists_exported_symbols() {
+    let dir = std::env::temp_dir().join(format!("ruscom-symbols-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("lib.cpp");
+    std::fs::write(&file, "__attribute__((visibility(\"default\"))) int api() { return 1; }\nint helper() { return 2; }\nstatic int counter;\n").unwrap();
+    let run = |args: &[&str]| {
+        let output = assert_cmd::Command::cargo_bin("ruscom").unwrap().arg("symbols").args(args).arg(&file).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let all = run(&[]);
+    eprintln!("{}", all);
+    assert_eq!(all, "global default function api 'int ()'\nglobal default function helper 'int ()'\nlocal variable counter 'int'\n");
+    assert_eq!(run(&["--exported"]), "global default function api 'int ()'\nglobal default function helper 'int ()'\n");
+    assert_eq!(run(&["--exported", "-fvisibility=hidden"]), "global default function api 'int ()'\n");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn cli_warns_about_conversions_with_wconversion() {
+    let dir = std::env::temp_dir().join(format!("ruscom-wconversion-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("conv.cpp");
+    std::fs::write(&file, "int half(double d) { return d / 2; }\n").unwrap();
+    let run = |args: &[&str]| {
+        let output = assert_cmd::Command::cargo_bin("ruscom").unwrap().arg("compile").args(args).arg(&file).output().unwrap();
+        String::from_utf8(output.stderr).unwrap()
+    };
+    assert!(!run(&[]).contains("implicit conversion"));
+    let stderr = run(&["-Wconversion"]);
+    eprintln!("{}", stderr);
+    assert!(stderr.contains("warning: implicit conversion from `double` to `int` may change the value"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn weak_definitions_and_aliases_are_listed() {
+    let (_, tu) = parse(r#"
+int impl(int x) { return x; }
+int api(int) __attribute__((alias("impl")));
+__attribute__((weak)) void hook() {}
+void on_exit() __attribute__((weak));
+void on_exit() {}
+int counter = 0;
+extern int count_alias [[gnu::alias("counter")]];
+extern void weak_ref() __attribute__((weak));
+"#);
+    let listed: Vec<String> = symbols::defined(&tu, Visibility::Default).iter().map(|s| s.to_string()).collect();
+    eprintln!("{:#?}", listed);
+    assert_eq!(listed, [
+        "global default function impl 'int (int)'",
+        "global default function api 'int (int)' alias of impl",
+        "global weak default function hook 'void ()'",
+        "global weak default function on_exit 'void ()'",
+        "global default variable counter 'int'",
+        "global default variable count_alias 'int' alias of counter",
+    ]);
+}
+
+#[test]
+fn aliases_must_name_definitions_of_the_same_type() {
+    let (mut s, tu) = parse(r#"
+int impl(int x) { return x; }
+long wrong_type(int) __attribute__((alias("impl")));
+void missing() __attribute__((alias("nowhere")));
+int not_a_string(int) __attribute__((alias(impl)));
+int defined(int x) __attribute__((alias("impl"))) { return x; }
+int plain_var __attribute__((alias("counter")));
+int counter;
+static void quiet() __attribute__((weak));
+static void quiet() {}
+namespace { [[gnu::weak]] int hidden_counter; }
+"#);
+    sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+    let found: Vec<String> = s.diagnostics.iter().map(|d| format!("{} {}", d.code.as_deref().unwrap_or("warning"), d.message)).collect();
+    eprintln!("{:#?}", found);
+    assert_eq!(found, [
+        "E0316 `alias` takes the name of its target as a string",
+        "E0316 a definition cannot also be an alias",
+        "E0316 a definition cannot also be an alias",
+        "-Wattribute-alias function alias `wrong_type` of type `long (int)` does not match its target, function `impl` of type `int (int)`",
+        "E0317 `missing` is an alias for `nowhere`, which is not defined in this translation unit",
+        "E0318 weak definition of `quiet` must have external linkage",
+        "E0318 weak definition of `hidden_counter` must have external linkage",
+    ]);
+}
+
+/// The inlay hints for `source` with `options`, each shown in place.
+fn hinted(source: &str, options: sema::hints::HintOptions) -> String {
+    let (s, tu) = parse(source);
+    let base = s.sources.files()[0].start;
+    let mut text = source.to_string();
+    for hint in sema::hints::inlay_hints(&tu, &s.target, s.exec_charsets(), options).iter().rev() { text.insert_str(hint.position - base, &format!("«{}»", hint)); }
+    text
+}
+
+#[test]
+fn inlay_hints_name_parameters_and_deduced_types() {
+    let source = "\
+int area(int width, int height = 1);
+void log(const char* message, ...);
+int min(int a, int b);
+double min(double a, double b, double c);
+int g(int a);
+int g(long b);
+struct P { int x; int y; void move(int dx, int dy); void move(double angle, double distance); };
+P make();
+void f(P p, int width) {
+    int n = area(3, 4) + area(width);
+    auto copy = p;
+    const auto& ref = n;
+    auto [x, y] = p;
+    log(\"%d\", n);
+    min(1, 2);
+    g(1);
+    g(2L);
+    p.move(1, 2);
+    p.move(1.5, 2.0);
+    auto a = area(3, 4);
+    auto made = make();
+    unknown(1, 2);
+}
+";
+    let all = hinted(source, sema::hints::HintOptions::default());
+    eprintln!("{}", all);
+    assert!(all.contains("int n = area(«width:»3, «height:»4) + area(width);"));
+    assert!(all.contains("auto copy«: P» = p;"));
+    assert!(all.contains("const auto& ref«: const int&» = n;"));
+    assert!(all.contains("auto [x«: int», y«: int»] = p;"));
+    // Arguments past the named parameters get none. Overloads are told
+    // apart by their arguments' types.
+    assert!(all.contains("log(«message:»\"%d\", n);"));
+    assert!(all.contains("min(«a:»1, «b:»2);"));
+    assert!(all.contains("g(«a:»1);") && all.contains("g(«b:»2L);"));
+    assert!(all.contains("p.move(«dx:»1, «dy:»2);") && all.contains("p.move(«angle:»1.5, «distance:»2.0);"));
+    // Types deduced from calls are their results'.
+    assert!(all.contains("auto a«: int» = area(«width:»3, «height:»4);"));
+    assert!(all.contains("auto made«: P» = make();"));
+    assert!(all.contains("    unknown(1, 2);"));
+
+    let types_only = hinted(source, sema::hints::HintOptions { parameter_names: false, deduced_types: true });
+    assert!(types_only.contains("int n = area(3, 4)") && types_only.contains("auto copy«: P»"));
+    let names_only = hinted(source, sema::hints::HintOptions { parameter_names: true, deduced_types: false });
+    assert!(names_only.contains("area(«width:»3") && names_only.contains("auto copy = p;"));
+}
+
+#[test]
+fn inlay_hints_deduce_types_from_literals() {
+    let source = "\
+auto i = 1;
+auto u = 4000000000u;
+auto l = 10L;
+auto d = .5;
+auto c = '\\0';
+auto s = \"a\";
+auto b = true;
+const auto& r = 2;
+auto n = nullptr;
+auto list = {1};
+auto braced{1};
+";
+    let all = hinted(source, sema::hints::HintOptions::default());
+    eprintln!("{}", all);
+    for line in [
+        "auto i«: int» = 1;",
+        "auto u«: unsigned int» = 4000000000u;",
+        "auto l«: long» = 10L;",
+        "auto d«: double» = .5;",
+        "auto c«: char» = '\\0';",
+        "auto s«: const char*» = \"a\";",
+        "auto b«: bool» = true;",
+        "const auto& r«: const int&» = 2;",
+        "auto n«: std::nullptr_t» = nullptr;",
+        // A `std::initializer_list`, which is not known.
+        "auto list = {1};",
+        "auto braced«: int»{1};",
+    ] {
+        assert!(all.contains(line), "{}", line);
+    }
+}
+
+#[test]
+fn unused_and_shadowing_locals_are_warned_about() {
+    let source = "\
+        struct Guard { Guard(); ~Guard(); };\n\
+        int level;\n\
+        struct Point {\n\
+        \x20   int x, y;\n\
+        \x20   int sum(int x) const { int level = 1; return x + y + level; }\n\
+        };\n\
+        int f(int used, int unused, [[maybe_unused]] int quiet, int) {\n\
+        \x20   int a = 1; int b; Guard g; [[maybe_unused]] int c; int d = 2; (void)d;\n\
+        \x20   for (int i = 0; i < used; i++) { int used = i; a += used; }\n\
+        \x20   { int a = 3; return a; }\n\
+        }\n\
+        int g(int n) { int n2 = n; static int calls; return n2; }\n";
+    let (mut s, tu) = parse(source);
+    sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+    assert!(s.diagnostics.is_empty(), "off by default");
+    for flag in ["all", "extra", "shadow"] { s.diagnostics.warnings_mut().apply(flag.parse().unwrap()); }
+    sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+    let found: Vec<String> = s.diagnostics.iter().map(|d| {
+        let at = s.sources.location(d.primary_span().unwrap().start).unwrap();
+        format!("{}:{} {} {}", at.line, at.column, d.code.as_deref().unwrap_or("warning"), d.message)
+    }).collect();
+    eprintln!("{:#?}", found);
+    assert_eq!(found, [
+        "5:13 -Wshadow declaration of `x` shadows a member of `Point`",
+        "5:32 -Wshadow declaration of `level` shadows a global declaration",
+        "9:42 -Wshadow declaration of `used` shadows a parameter",
+        "10:11 -Wshadow declaration of `a` shadows a previous local",
+        "8:20 -Wunused-variable unused variable `b`",
+        "7:17 -Wunused-parameter unused parameter `unused`",
+        "12:39 -Wunused-variable unused variable `calls`",
+    ]);
+}
+
+#[test]
+fn comparisons_of_different_signedness_are_warned_about() {
+    let source = "\
+        bool f(int i, unsigned u, unsigned long n, long l, unsigned short s, char c) {\n\
+        \x20   return i < u || n == i || u > 3 || l < u || s < i || c == 'a' || u != -1;\n\
+        }\n";
+    let (mut s, tu) = parse(source);
+    s.diagnostics.warnings_mut().apply(WarningOption::All);
+    sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+    let found: Vec<String> = s.diagnostics.iter().map(|d| format!("{} {}", d.code.as_deref().unwrap_or("warning"), d.message)).collect();
+    eprintln!("{:#?}", found);
+    assert_eq!(found, [
+        "-Wsign-compare comparison of integer expressions of different signedness: `int` and `unsigned int`",
+        "-Wsign-compare comparison of integer expressions of different signedness: `unsigned long` and `int`",
+        "-Wsign-compare comparison of integer expressions of different signedness: `unsigned int` and `int`",
+    ]);
+}
+
+#[test]
+fn cli_takes_warning_options() {
+    let dir = std::env::temp_dir().join(format!("ruscom-warning-options-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("warn.cpp");
+    std::fs::write(&file, "int f(int x, unsigned n) { int unused; int count = 0; if (x < n) count++; return count; }\n").unwrap();
+    let run = |args: &[&str]| {
+        let output = assert_cmd::Command::cargo_bin("ruscom").unwrap().arg("check").args(args).arg(&file).output().unwrap();
+        (output.status.success(), String::from_utf8(output.stderr).unwrap())
+    };
+    let (ok, stderr) = run(&[]);
+    assert!(ok && !stderr.contains("warning"), "{}", stderr);
+    let (ok, stderr) = run(&["-Wall"]);
+    eprintln!("{}", stderr);
+    assert!(ok);
+    assert!(stderr.contains("warning: unused variable `unused` [-Wunused-variable]"));
+    assert!(stderr.contains("warning: comparison of integer expressions of different signedness: `int` and `unsigned int` [-Wsign-compare]"));
+    let (ok, stderr) = run(&["-Wall", "-Wno-unused-variable", "-Werror=sign-compare"]);
+    eprintln!("{}", stderr);
+    assert!(!ok);
+    assert!(!stderr.contains("unused variable"));
+    assert!(stderr.contains("error: comparison of integer expressions of different signedness: `int` and `unsigned int` [-Wsign-compare]"));
+    assert!(stderr.contains("note: treated as an error because of `-Werror=sign-compare`"));
+    let (ok, stderr) = run(&["-Wbogus"]);
+    assert!(!ok && stderr.contains("unknown warning option `-Wbogus`"), "{}", stderr);
+    // Unknown `-Wno-` options are ignored, and mentioned only next to other diagnostics.
+    let (ok, stderr) = run(&["-Wno-bogus"]);
+    assert!(ok && stderr.is_empty(), "{}", stderr);
+    let (ok, stderr) = run(&["-Wall", "-Wno-bogus"]);
+    assert!(ok && stderr.contains("note: unrecognized command-line option `-Wno-bogus` may have been intended to silence earlier diagnostics"), "{}", stderr);
+    let (ok, stderr) = run(&["-Wall", "-Werror", "-w"]);
+    assert!(ok && stderr.is_empty(), "{}", stderr);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn control_flow_is_checked_for_missing_returns_unreachable_code_and_infinite_loops() {
+    let source = "\
+        [[noreturn]] void fail(const char* why);\n\
+        int sign(int x) { if (x > 0) return 1; else if (x < 0) return -1; }\n\
+        int both(int x) { if (x) return 1; else return 0; }\n\
+        int pick(int x) { switch (x) { case 0: return 1; default: return 2; } }\n\
+        int partial(int x) { switch (x) { case 0: return 1; case 1: return 2; } }\n\
+        int checked(int x) { if (x) return x; fail(\"zero\"); }\n\
+        int aborts(int x) { if (x) return x; std::abort(); }\n\
+        int after(int x) { return x; x++; }\n\
+        int loop(int n) { int total = 0; for (int i = 0; i < n; i++) { total += i; continue; total--; } return total; }\n\
+        int cases(int x) { switch (x) { case 0: return 1; break; } return 0; }\n\
+        void spin() { int i = 0; while (true) { i++; } }\n\
+        void poll(volatile int* ready) { while (1) { if (*ready) break; } }\n\
+        void serve() { for (;;) { fail(\"stop\"); } }\n\
+        void wait(int* flag) { while (true) { *flag = 1; } }\n\
+        int main() { }\n\
+        auto deduced(int x) { if (x) return x; }\n";
+    let check = |options: &[WarningOption]| {
+        let (mut s, tu) = parse(source);
+        for option in options { s.diagnostics.warnings_mut().apply(option.clone()); }
+        sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+        let found: Vec<String> = s.diagnostics.iter().map(|d| {
+            let at = s.sources.location(d.primary_span().unwrap().start).unwrap();
+            format!("{}:{} {} {}", at.line, at.column, d.code.as_deref().unwrap_or("warning"), d.message)
+        }).collect();
+        eprintln!("{:#?}", found);
+        (found, s)
+    };
+    let (found, _) = check(&[]);
+    assert_eq!(found, [
+        "2:67 -Wreturn-type non-void function `sign` does not return a value in all control paths",
+        "5:73 -Wreturn-type non-void function `partial` does not return a value in all control paths",
+    ], "unreachable code and infinite loops are only found with -Wall");
+    let (found, s) = check(&[WarningOption::All]);
+    assert_eq!(found, [
+        "2:67 -Wreturn-type non-void function `sign` does not return a value in all control paths",
+        "5:73 -Wreturn-type non-void function `partial` does not return a value in all control paths",
+        "8:30 -Wunreachable-code code will never be executed",
+        "9:86 -Wunreachable-code code will never be executed",
+        "11:26 -Winfinite-loop infinite loop without side effects",
+    ]);
+    let spin = s.diagnostics.iter().find(|d| d.code.as_deref() == Some("-Winfinite-loop")).unwrap();
+    assert_eq!(spin.notes, ["the behavior is undefined: a C++ implementation may assume that such a loop terminates"]);
+}
+
+#[test]
+fn locals_read_before_they_are_assigned_are_found_on_the_control_flow_graph() {
+    let source = "\
+        void read(int& into);\n\
+        void show(int value);\n\
+        int never() { int x; return x + 1; }\n\
+        int some(int n) { int r; if (n > 0) r = n; return r; }\n\
+        int every(int n) { int r; if (n) r = 1; else r = 2; return r; }\n\
+        int looped(int n) { int last; for (int i = 0; i < n; i++) last = i; return last; }\n\
+        int guarded(int* p) { int v; if (p && (v = *p)) return v; return 0; }\n\
+        int unguarded(int* p) { int v; if (p && (v = *p)) return 1; return v; }\n\
+        int escapes() { int a, b; int* pa = &a; read(b); return *pa + b; }\n\
+        void shown() { int c; show(c); }\n\
+        int sum(int n) { int total; while (n--) total += n; return total; }\n\
+        int after() { int d; return 0; d++; }\n";
+    let check = |options: &[WarningOption]| {
+        let (mut s, tu) = parse(source);
+        for option in options { s.diagnostics.warnings_mut().apply(option.clone()); }
+        sema::check(&tu, &s.target, s.exec_charsets(), &mut s.diagnostics);
+        let found: Vec<String> = s.diagnostics.iter().map(|d| {
+            let labels: Vec<String> = d.labels.iter().map(|l| {
+                let at = s.sources.location(l.span.start).unwrap();
+                format!("{}:{} {}", at.line, at.column, l.message)
+            }).collect();
+            format!("{} {} | {}", d.code.as_deref().unwrap_or("warning"), d.message, labels.join(" | "))
+        }).collect();
+        eprintln!("{:#?}", found);
+        found
+    };
+    assert_eq!(check(&[]), Vec::<String>::new(), "both are only found with -Wall");
+    assert_eq!(check(&[WarningOption::All, WarningOption::Disable(Warning::UnreachableCode)]), [
+        "-Wuninitialized `x` is used uninitialized | 3:29 read here before it is assigned | 3:19 declared here without an initializer",
+        "-Wmaybe-uninitialized `r` may be used uninitialized | 4:51 read here | 4:23 declared here without an initializer | 4:30 `r` is not assigned when this condition is false",
+        "-Wmaybe-uninitialized `last` may be used uninitialized | 6:76 read here | 6:25 declared here without an initializer | 6:47 `last` is not assigned when this condition is false",
+        "-Wmaybe-uninitialized `v` may be used uninitialized | 8:68 read here | 8:29 declared here without an initializer | 8:36 `v` is not assigned when this condition is false",
+        "-Wuninitialized `c` is used uninitialized | 10:28 read here before it is assigned | 10:20 declared here without an initializer",
+        "-Wuninitialized `total` is used uninitialized | 11:41 read here before it is assigned | 11:22 declared here without an initializer",
+    ]);
+    assert_eq!(check(&[WarningOption::All, WarningOption::Disable(Warning::UnreachableCode), WarningOption::Disable(Warning::MaybeUninitialized)]).len(), 3);
+}
+
+#[test]
+fn cli_check_reports_control_flow_warnings() {
+    let dir = std::env::temp_dir().join(format!("ruscom-control-flow-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("flow.cpp");
+    std::fs::write(&file, "int f(int x) {\n    if (x) return 1;\n}\nint g() { return 0; g(); }\n").unwrap();
+    let run = |args: &[&str]| {
+        let output = assert_cmd::Command::cargo_bin("ruscom").unwrap().arg("check").args(args).arg(&file).output().unwrap();
+        (output.status.success(), String::from_utf8(output.stderr).unwrap())
+    };
+    let (ok, stderr) = run(&[]);
+    eprintln!("{}", stderr);
+    assert!(ok);
+    assert!(stderr.contains("warning: non-void function `f` does not return a value in all control paths [-Wreturn-type]"));
+    assert!(stderr.contains("control reaches the end of the function here"));
+    assert!(!stderr.contains("never be executed"));
+    let (ok, stderr) = run(&["-Wall", "-Werror=unreachable-code"]);
+    eprintln!("{}", stderr);
+    assert!(!ok);
+    assert!(stderr.contains("error: code will never be executed [-Wunreachable-code]"));
+    let (ok, stderr) = run(&["-Wno-return-type"]);
+    assert!(ok && !stderr.contains("warning"), "{}", stderr);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,62 @@
+use ruscom::cancel::Cancelled;
+use ruscom::lexer::token::Token;
+use ruscom::session::{Options, Session, Target};
+
+#[test]
+fn sessions_are_independent_across_threads() {
+    let handles: Vec<_> = ["int a;", "float b = 1.5; float c;"]
+        .into_iter()
+        .map(|src| {
+            std::thread::spawn(move || {
+                let mut session = Session::new(Options::default());
+                let out = session.tokenize(src).unwrap();
+                assert!(out.error.is_none());
+                (out.tokens.len(), session.interner.len(), session.profiler.events().len())
+            })
+        })
+        .collect();
+    let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    eprintln!("results: {:?}", results);
+    assert_eq!(results[0], (3, 2, 1));
+    assert_eq!(results[1], (8, 3, 1));
+}
+
+#[test]
+fn tokenize_stops_at_first_error() {
+    let mut session = Session::new(Options::default());
+    let out = session.tokenize("x = \"open").unwrap();
+    assert_eq!(out.tokens.len(), 2);
+    assert_eq!(out.tokens[0].token, Token::Identifier("x".into()));
+    assert!(out.error.is_some());
+}
+
+#[test]
+fn target_triples() {
+    let linux = Target { arch: "x86_64".into(), os: "linux".into() };
+    assert_eq!(linux.triple(), "x86_64-unknown-linux-gnu");
+    let mac = Target { arch: "aarch64".into(), os: "macos".into() };
+    assert_eq!(mac.triple(), "aarch64-apple-darwin");
+}
+
+#[test]
+fn cpu_features_include_earlier_levels() {
+    let x86 = Target { arch: "x86_64".into(), os: "linux".into() };
+    let haswell = x86.cpu_features("haswell").unwrap();
+    eprintln!("{:?}", haswell);
+    assert!(haswell.contains(&"sse4.2") && haswell.contains(&"avx2") && !haswell.contains(&"avx512f"));
+    assert_eq!(x86.feature_macro("sse4.2"), "__SSE4_2__");
+    assert_eq!(x86.cpu_features("apple-m1"), None);
+    let arm = Target { arch: "aarch64".into(), os: "macos".into() };
+    let m1: Vec<String> = arm.cpu_features("apple-m1").unwrap().into_iter().map(|f| arm.feature_macro(f)).collect();
+    assert_eq!(m1, ["__ARM_NEON", "__ARM_FEATURE_CRC32", "__ARM_FEATURE_AES", "__ARM_FEATURE_SHA2", "__ARM_FEATURE_DOTPROD"]);
+}
+
+#[test]
+fn cancelled_session_stops_lexing() {
+    let mut session = Session::new(Options::default());
+    let handle = session.cancel.clone();
+    let src = "int x; ".repeat(10_000);
+    assert!(session.tokenize(&src).is_ok());
+    handle.cancel();
+    assert_eq!(session.tokenize(&src).unwrap_err(), Cancelled);
+}
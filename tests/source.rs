@@ -0,0 +1,64 @@
+use std::path::Path;
+
+use ruscom::lexer::token::Span;
+use ruscom::session::{Options, Session};
+use ruscom::source::SourceManager;
+
+#[test]
+fn spans_resolve_across_files() {
+    let mut sm = SourceManager::new();
+    let a = sm.add_file("a.cpp", "int x;\nint yy;\n");
+    let b = sm.add_included_file("b.h", "// header\nvoid f();", Span::new(0, 6));
+    let b_start = sm.file(b).start;
+    assert!(b_start > sm.file(a).end());
+
+    let loc = sm.location(11).unwrap();
+    assert_eq!((loc.file, loc.line, loc.column), (a, 2, 5));
+    assert_eq!(loc.to_string(), "a.cpp:2:5");
+
+    let loc = sm.location(b_start + 15).unwrap();
+    eprintln!("resolved: {}", loc);
+    assert_eq!((loc.file, loc.line, loc.column), (b, 2, 6));
+    assert_eq!(sm.file(b).line_text(2), "void f();");
+    assert_eq!(sm.file(b).included_from, Some(Span::new(0, 6)));
+    assert_eq!(sm.snippet(Span::new(b_start + 15, b_start + 16)), Some("f"));
+    assert_eq!(sm.lookup_path(Path::new("./b.h")), Some(b));
+}
+
+#[test]
+fn columns_count_characters() {
+    let mut sm = SourceManager::new();
+    sm.add_file("u.cpp", "auto s = \"é\"; x");
+    let loc = sm.location(16).unwrap();
+    assert_eq!(loc.column, 16);
+}
+
+#[test]
+fn session_tokens_carry_global_positions() {
+    let mut session = Session::new(Options::default());
+    session.tokenize("int first;").unwrap();
+    let out = session.tokenize("int second;").unwrap();
+    let span = out.tokens[1].span;
+    assert_eq!(session.sources.snippet(span), Some("second"));
+    assert_eq!(session.sources.location(span.start).unwrap().column, 5);
+}
+
+#[test]
+fn set_file_replaces_the_file_of_a_path() {
+    let mut sm = SourceManager::new();
+    let a = sm.set_file("a.cpp", "int a;");
+    let b = sm.add_file("b.h", "int b;");
+    assert_eq!(sm.set_file("a.cpp", "int a;"), a);
+    // Shorter contents fit where the old ones were.
+    assert_eq!(sm.set_file("a.cpp", "x;\ny;"), a);
+    assert_eq!(sm.location(sm.file(a).start + 3).unwrap().line, 2);
+    // Longer ones would run into `b.h`, so they go after it.
+    let grown = sm.set_file("a.cpp", "int grown;");
+    assert_ne!(grown, a);
+    assert_eq!(sm.lookup_path(Path::new("a.cpp")), Some(grown));
+    // The last file can always grow in place.
+    assert_eq!(sm.set_file("a.cpp", "int grown_again;"), grown);
+    assert_eq!(sm.snippet(Span::new(sm.file(grown).start + 4, sm.file(grown).end() - 1)), Some("grown_again"));
+    assert_eq!(sm.files().len(), 3);
+    assert_eq!(sm.file(b).contents.as_ref(), "int b;");
+}
@@ -0,0 +1,49 @@
+use std::fs;
+use std::path::PathBuf;
+
+use ruscom::session::Target;
+use ruscom::sysroot::{self, Sysroot, SysrootSpec};
+
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ruscom-sysroot-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn create_and_reopen_relocated_sysroot() {
+    let host = scratch("host");
+    let inc = host.join("usr/include");
+    fs::create_dir_all(inc.join("sys")).unwrap();
+    fs::write(inc.join("stdio.h"), "int printf(const char*, ...);").unwrap();
+    fs::write(inc.join("sys/types.h"), "typedef long ssize_t;").unwrap();
+    let lib = host.join("usr/lib");
+    fs::create_dir_all(&lib).unwrap();
+    fs::write(lib.join("libc.so.6"), "elf").unwrap();
+    fs::write(lib.join("crt1.o"), "elf").unwrap();
+    fs::write(lib.join("libunrelated.so"), "elf").unwrap();
+
+    let spec = SysrootSpec {
+        target: Target { arch: "x86_64".into(), os: "linux".into() },
+        include_dirs: vec![inc.clone()],
+        lib_dirs: vec![lib.clone()],
+    };
+    let dest = scratch("dest");
+    let stats = sysroot::create(&spec, &dest).unwrap();
+    assert_eq!(stats.headers, 2);
+    assert_eq!(stats.libraries, 2);
+
+    // Moving the tree must keep it usable.
+    let moved = scratch("moved").join("root");
+    fs::rename(&dest, &moved).unwrap();
+    let root = Sysroot::open(&moved).unwrap();
+    eprintln!("sysroot: {:?}", root);
+    assert_eq!(root.triple, "x86_64-unknown-linux-gnu");
+    assert!(root.include_dirs[0].starts_with(&moved));
+    assert!(root.include_dirs[0].join("sys/types.h").is_file());
+    assert!(root.lib_dirs[0].join("libc.so.6").is_file());
+    assert!(!root.lib_dirs[0].join("libunrelated.so").exists());
+
+    for d in [host, moved.parent().unwrap().to_path_buf()] { let _ = fs::remove_dir_all(d); }
+}
@@ -0,0 +1,47 @@
+use ruscom::lexer::token::{Token, TokenKind};
+use ruscom::lexer::{Lexer, LexerOptions, TokenStream};
+
+#[test]
+fn peek_nth_looks_ahead_without_consuming() {
+    let mut ts = TokenStream::new(Lexer::new("a * b;"));
+    assert_eq!(ts.peek_nth(2).token, Token::Identifier("b".into()));
+    assert_eq!(ts.peek_nth(10).token, Token::Eof);
+    assert_eq!(ts.peek().token, Token::Identifier("a".into()));
+}
+
+#[test]
+fn eat_and_expect() {
+    let mut ts = TokenStream::new(Lexer::new("int x;"));
+    assert!(ts.eat_kind(TokenKind::Identifier).is_some());
+    assert!(!ts.eat(&Token::Punct(';')));
+    let x = ts.expect_kind(TokenKind::Identifier).unwrap();
+    assert_eq!(x.span.start, 4);
+    let err = ts.expect(&Token::Punct('(')).unwrap_err();
+    eprintln!("expect error: {}", err);
+    assert_eq!(err.to_string(), "expected `(`, found `;`");
+    assert!(ts.eat(&Token::Punct(';')));
+    assert!(ts.is_eof());
+    assert_eq!(ts.bump().token, Token::Eof);
+}
+
+#[test]
+fn checkpoint_and_rollback() {
+    let mut ts = TokenStream::new(Lexer::new("T * p ;"));
+    let cp = ts.checkpoint();
+    ts.bump();
+    ts.bump();
+    assert_eq!(ts.peek().token, Token::Identifier("p".into()));
+    ts.rollback(cp);
+    assert_eq!(ts.peek().token, Token::Identifier("T".into()));
+}
+
+#[test]
+fn comments_are_set_aside() {
+    let lexer = Lexer::with_options("/// doc\nint x; // trailing", LexerOptions { preserve_comments: true });
+    let mut ts = TokenStream::new(lexer);
+    let mut tokens = Vec::new();
+    while !ts.is_eof() { tokens.push(ts.bump().token); }
+    eprintln!("tokens: {:?}", tokens);
+    assert_eq!(tokens.len(), 3);
+    assert_eq!(ts.comments().len(), 2);
+}
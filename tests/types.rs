@@ -0,0 +1,55 @@
+use ruscom::types::{Qualifiers, Type, TypeId};
+
+fn int() -> TypeId { TypeId::intern(Type::Int) }
+fn ptr(t: TypeId) -> TypeId { TypeId::intern(Type::Pointer(t)) }
+fn cnst(t: TypeId) -> TypeId { t.qualified(Qualifiers::CONST) }
+
+#[test]
+fn qualified_types_are_canonical() {
+    let ci = cnst(int());
+    assert_eq!(cnst(ci), ci);
+    assert_eq!(ci.qualified(Qualifiers::VOLATILE), int().qualified(Qualifiers::CONST_VOLATILE));
+    assert_eq!(int().qualified(Qualifiers::NONE), int());
+    let r = TypeId::intern(Type::Reference(int()));
+    assert_eq!(cnst(r), r);
+    assert_eq!(ci.unqualified(), int());
+    assert!(ci.is_const() && !int().is_const());
+}
+
+#[test]
+fn qualified_types_print_like_cpp() {
+    let cases = [
+        (cnst(int()), "const int"),
+        (ptr(cnst(int())), "const int*"),
+        (cnst(ptr(int())), "int* const"),
+        (cnst(ptr(cnst(int()))), "const int* const"),
+        (TypeId::intern(Type::Reference(cnst(int()))), "const int&"),
+        (int().qualified(Qualifiers::CONST_VOLATILE), "const volatile int"),
+    ];
+    for (ty, text) in cases {
+        eprintln!("{:?}", ty);
+        assert_eq!(ty.to_string(), text);
+    }
+}
+
+#[test]
+fn qualification_conversions() {
+    let pp = ptr(ptr(int()));
+    assert!(ptr(int()).converts_by_qualification(ptr(cnst(int()))));
+    assert!(!ptr(cnst(int())).converts_by_qualification(ptr(int())));
+    // Top-level qualifiers don't matter for prvalues.
+    assert!(cnst(ptr(int())).converts_by_qualification(ptr(int())));
+    assert!(!pp.converts_by_qualification(ptr(ptr(cnst(int())))));
+    assert!(pp.converts_by_qualification(ptr(cnst(ptr(cnst(int()))))));
+    assert!(pp.converts_by_qualification(ptr(cnst(ptr(int())))));
+    assert!(!ptr(int()).converts_by_qualification(ptr(TypeId::intern(Type::Long))));
+}
+
+#[test]
+fn reference_binding_respects_qualifiers() {
+    assert!(cnst(int()).reference_binds_to(int()));
+    assert!(cnst(int()).reference_binds_to(cnst(int())));
+    assert!(!int().reference_binds_to(cnst(int())));
+    assert!(!int().qualified(Qualifiers::VOLATILE).reference_binds_to(cnst(int())));
+    assert!(Qualifiers::CONST_VOLATILE.contains(Qualifiers::CONST));
+}
@@ -0,0 +1,106 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use ruscom::driver::Driver;
+use ruscom::lexer::token::Token;
+use ruscom::preprocessor::Preprocessor;
+use ruscom::session::{Options, Session};
+use ruscom::vfs::{normalize, ArchiveFileSystem, FileSystem, MemoryFileSystem, OverlayFileSystem};
+
+#[test]
+fn memory_fs_normalizes_paths() {
+    let mut fs = MemoryFileSystem::new();
+    fs.insert("src/./main.cpp", "int main() {}");
+    assert!(fs.is_file(Path::new("src/include/../main.cpp")));
+    assert_eq!(fs.read_to_string(Path::new("src/main.cpp")).unwrap(), "int main() {}");
+    assert!(fs.read_to_string(Path::new("other.cpp")).is_err());
+}
+
+#[test]
+fn parent_dirs_cancel_names_but_not_other_parent_dirs() {
+    let cases = [
+        ("../a/../../c", "../../c"),
+        ("../../x", "../../x"),
+        ("a/b/../../../x", "../x"),
+        ("a/../../b/./c", "../b/c"),
+        ("/../a/../b", "/b"),
+    ];
+    for (path, expected) in cases {
+        assert_eq!(normalize(Path::new(path)), Path::new(expected), "{}", path);
+    }
+}
+
+#[test]
+fn driver_compiles_from_memory() {
+    let mut fs = MemoryFileSystem::new();
+    fs.insert("virtual.cpp", "int x = 1;");
+    let options = Options { inputs: vec!["virtual.cpp".into()], ..Options::default() };
+    let session = Session::new(options).with_file_system(Arc::new(fs));
+    let files = Driver::from_session(session).lex().unwrap();
+    assert_eq!(files[0].output.tokens.len(), 5);
+}
+
+fn tar_entry(name: &str, body: &[u8]) -> Vec<u8> {
+    let mut header = [0u8; 512];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    let size = format!("{:011o}\0", body.len());
+    header[124..136].copy_from_slice(size.as_bytes());
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    let mut out = header.to_vec();
+    out.extend_from_slice(body);
+    out.resize(out.len().div_ceil(512) * 512, 0);
+    out
+}
+
+#[test]
+fn archive_fs_reads_tar_entries() {
+    let mut tar = tar_entry("include/config.h", b"#define ONE 1\n");
+    tar.extend(tar_entry("main.cpp", b"int main() { return ONE; }"));
+    tar.extend([0u8; 1024]);
+    let fs = ArchiveFileSystem::from_tar_bytes(&tar).unwrap();
+    assert_eq!(fs.read_to_string(Path::new("include/config.h")).unwrap(), "#define ONE 1\n");
+    assert!(fs.is_file(Path::new("main.cpp")));
+    assert!(!fs.is_file(Path::new("include")));
+}
+
+#[test]
+fn overlay_shadows_base_files() {
+    let mut base = MemoryFileSystem::new();
+    base.insert("a.cpp", "int on_disk;");
+    base.insert("b.cpp", "int untouched;");
+    let fs = OverlayFileSystem::new(Arc::new(base));
+    let stamp = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+    fs.set_overlay_at("a.cpp", "int unsaved;", stamp);
+    assert_eq!(fs.read_to_string(Path::new("a.cpp")).unwrap(), "int unsaved;");
+    assert_eq!(fs.read_to_string(Path::new("b.cpp")).unwrap(), "int untouched;");
+    assert_eq!(fs.modified(Path::new("a.cpp")).unwrap(), stamp);
+    assert!(fs.modified(Path::new("b.cpp")).is_err());
+    assert!(fs.remove_overlay("a.cpp"));
+    assert_eq!(fs.read_to_string(Path::new("a.cpp")).unwrap(), "int on_disk;");
+}
+
+#[test]
+fn overlays_are_found_by_relative_and_absolute_paths() {
+    let fs = OverlayFileSystem::new(Arc::new(MemoryFileSystem::new())).with_current_dir("/work");
+    fs.set_overlay("/work/include/config.h", "#define ANSWER 42\n");
+    fs.set_overlay("src/../main.cpp", "#include \"include/config.h\"\nint answer = ANSWER;\n");
+    assert!(fs.is_file(Path::new("include/config.h")) && fs.is_file(Path::new("/work/main.cpp")));
+    let mut session = Session::new(Options::default()).with_file_system(Arc::new(fs));
+    let main = session.sources.load(session.fs.clone().as_ref(), Path::new("main.cpp")).unwrap();
+    let out = Preprocessor::new(&mut session).run(main).unwrap();
+    assert!(session.diagnostics.is_empty());
+    assert!(out.tokens.iter().any(|t| t.token == Token::Number("42".into())));
+}
+
+#[test]
+fn overlay_cli_flag_replaces_input() {
+    let mut cmd = assert_cmd::Command::cargo_bin("ruscom").expect("binary not built");
+    let assert = cmd
+        .args(["lex", "--count", "not_on_disk.cpp", "--overlay", "not_on_disk.cpp=tests/data/sample1.cpp"])
+        .assert()
+        .success();
+    let out = String::from_utf8_lossy(&assert.get_output().stdout).to_string();
+    assert_eq!(out.trim(), "14");
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use ruscom::ast::{Block, Expr, ExprKind};
+use ruscom::visit::{MutVisitor, Visitor};
+use common::parse_valid;
+
+/// The names used in expressions, in the order they are visited.
+#[derive(Default)]
+struct Names {
+    names: Vec<String>,
+    skip_bodies: bool,
+}
+
+impl Visitor for Names {
+    fn visit_expr(&mut self, e: &Expr) {
+        if let ExprKind::Name(name) = &e.kind { self.names.push(name.to_string()); }
+        self.walk_expr(e);
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        if !self.skip_bodies { self.walk_block(block); }
+    }
+}
+
+const SOURCE: &str = "\
+enum E { A = a, B = A + 1 };
+[[deprecated(why)]] int v = init;
+struct S { int m = member; S(int p = param) : m(ctor) { body_s; } };
+template<class T, int N = n> requires (r) T f(T t) { if (c) return x; for (int i = i0; i < lim; ++i) {} return y; }
+namespace ns { auto w = sizeof(z); }
+";
+
+#[test]
+fn visitor_reaches_every_expression_in_source_order() {
+    let tu = parse_valid(SOURCE).1;
+    let mut names = Names::default();
+    names.visit_translation_unit(&tu);
+    eprintln!("{:?}", names.names);
+    assert_eq!(names.names, [
+        "a", "A", "why", "init", "member", "param", "ctor", "body_s", "n", "r", "c", "x", "i0", "i", "lim", "i", "y", "z",
+    ]);
+}
+
+#[test]
+fn overriding_a_visit_method_can_skip_the_subtree() {
+    let tu = parse_valid(SOURCE).1;
+    let mut names = Names { skip_bodies: true, ..Names::default() };
+    names.visit_translation_unit(&tu);
+    eprintln!("{:?}", names.names);
+    assert_eq!(names.names, ["a", "A", "why", "init", "member", "param", "ctor", "n", "r", "z"]);
+}
+
+/// Doubles every integer literal.
+struct Double;
+
+impl MutVisitor for Double {
+    fn visit_expr(&mut self, e: &mut Expr) {
+        if let ExprKind::IntLiteral(n, _) = &mut e.kind { *n *= 2; }
+        self.walk_expr(e);
+    }
+}
+
+#[test]
+fn mut_visitor_rewrites_in_place() {
+    let mut tu = parse_valid("int a[3] = {1, 2, 3}; int f(int x = 4) { return x + 5; }").1;
+    Double.visit_translation_unit(&mut tu);
+    let mut literals = Vec::new();
+    struct Literals<'a>(&'a mut Vec<u64>);
+    impl Visitor for Literals<'_> {
+        fn visit_expr(&mut self, e: &Expr) {
+            if let ExprKind::IntLiteral(n, _) = e.kind { self.0.push(n); }
+            self.walk_expr(e);
+        }
+    }
+    Literals(&mut literals).visit_translation_unit(&tu);
+    eprintln!("{:?}", literals);
+    assert_eq!(literals, [2, 4, 6, 8, 10]);
+}